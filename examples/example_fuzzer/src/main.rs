@@ -8,15 +8,15 @@ use lain::rand::Rng;
 // the driver is optional -- you can figure out how to manage
 // your fuzzer's threads
 use lain::driver::*;
+use lain::net::{NetConfig, Sender, TcpSender};
 
-use std::io::prelude::*;
-use std::net::TcpStream;
 use std::sync::{Arc, RwLock};
 
 const THREAD_COUNT: usize = 10;
 
 #[derive(Default)]
 struct FuzzerThreadContext {
+    sender: Option<TcpSender>,
     last_packet: Option<PacketData>,
     scratch_packet: PacketData,
     thread_packet_iterations: usize,
@@ -28,14 +28,14 @@ struct GlobalContext {
     // counter per operation here or whatever you'd like
 }
 
-#[derive(Debug, Default, Clone, PostFuzzerIteration, FixupChildren, NewFuzzed, Mutatable, VariableSizeObject, BinarySerialize)]
+#[derive(Debug, Default, Clone, PostFuzzerIteration, FixupChildren, NewFuzzed, Mutatable, VariableSizeObject, BinarySerialize, FuzzedDebug)]
 struct PacketData {
     typ: UnsafeEnum<PacketType, u32>,
 
     offset: u64,
     length: u64,
 
-    #[fuzzer(min = 0, max = 10)]
+    #[lain(min = 0, max = 10)]
     data: Vec<u8>,
 }
 
@@ -47,7 +47,7 @@ impl Fixup for PacketData {
     }
 }
 
-#[derive(Debug, Copy, Clone, FuzzerObject, ToPrimitiveU32, BinarySerialize)]
+#[derive(Debug, Copy, Clone, FuzzerObject, ToPrimitiveU32, FuzzedDebug)]
 #[repr(u32)]
 enum PacketType {
     Read = 0x0,
@@ -62,10 +62,22 @@ impl Default for PacketType {
 }
 
 fn main() {
-    let mut driver = FuzzerDriver::<GlobalContext>::new(THREAD_COUNT);
+    // run with `--help` to see the flags every lain fuzzer understands
+    let options = DriverOptions::from_args();
+
+    let mut driver = FuzzerDriver::<GlobalContext>::new(options.threads.unwrap_or(THREAD_COUNT));
+    if let Some(seed) = options.seed {
+        driver.set_seed(seed);
+    }
 
     driver.set_global_context(Default::default());
 
+    // `--dry-run N` prints the first N packets instead of sending them to the target
+    if let Some(count) = options.dry_run {
+        print!("{}", dry_run::<PacketData, _>(&driver, count));
+        return;
+    }
+
     let driver = Arc::new(driver);
     let ctrlc_driver = driver.clone();
 
@@ -80,9 +92,8 @@ fn main() {
     println!("Finished in {} iterations", driver.num_iterations());
 }
 
-fn fuzzer_routine<R: Rng>(mutator: &mut Mutator<R>, thread_context: &mut FuzzerThreadContext, _global_context: Option<Arc<RwLock<GlobalContext>>>) -> Result<(), ()> {
-    // TODO: we have overhead here of re-estabilishing the connection every time
-    let mut stream = TcpStream::connect("127.0.0.1:8080").expect("server isn't running. possible crash?");
+fn fuzzer_routine<R: Rng>(mutator: &mut Mutator<R>, context: &mut FuzzerContext<FuzzerThreadContext>, _global_context: Option<Arc<RwLock<GlobalContext>>>) -> Result<(), ()> {
+    let thread_context = &mut context.user_data;
 
     let packet = match thread_context.last_packet {
         Some(ref mut last_packet) => {
@@ -104,17 +115,24 @@ fn fuzzer_routine<R: Rng>(mutator: &mut Mutator<R>, thread_context: &mut FuzzerT
         }
     };
 
-    let mut serialized_data = Vec::with_capacity(packet.serialized_size());
-    packet.binary_serialize::<_, LittleEndian>(&mut serialized_data);
+    // the context's buffer is cleared before every iteration, so we can reuse its allocation
+    let serialized_data = &mut context.buffer;
+    packet.binary_serialize::<_, LittleEndian>(serialized_data).expect("failed to serialize packet");
 
     println!("Sending packet: {:?}", packet);
 
-    stream.write(&serialized_data).expect("failed to write data");
+    thread_context.thread_packet_iterations += 1;
 
-    let mut response_data = Vec::new();
-    stream.read(&mut response_data);
+    // the connection is kept open across iterations and re-established if the server drops it
+    let sender = thread_context.sender.get_or_insert_with(|| {
+        TcpSender::new("127.0.0.1:8080", NetConfig::default()).expect("invalid server address")
+    });
 
-    thread_context.thread_packet_iterations += 1;
+    let status = sender
+        .send(&context.buffer, &mut context.response)
+        .expect("server isn't running. possible crash?");
+
+    println!("Response ({:?}): {:?}", status, context.response);
 
     Ok(())
 }
@@ -0,0 +1,361 @@
+//! Shifting generation toward inputs the target actually processes.
+//!
+//! A [ResponseClassifier] maps the target's response codes to [Outcome]s. Once it's been given to
+//! the driver with [FuzzerDriver::set_adaptive_weighting], the choices made while generating each
+//! input are tallied against the outcome the input produced. Enum variants and field values which
+//! consistently produce the same rejection are then picked less often.
+//!
+//! The choices tracked are the variants of derived enums and the values of `bool`, `u8`, `i8`,
+//! `u16`, and `i16` fields of derived (non-generic) structs. A down-weighted choice keeps
+//! [AdaptiveConfig::min_weight] of its original weight, so it's still generated occasionally and
+//! recovers if the target starts accepting it.
+//!
+//! ```
+//! use lain::adaptive::{AdaptiveConfig, Outcome, ResponseClassifier};
+//! use lain::driver::FuzzerDriver;
+//!
+//! // the first byte of the response is a status code
+//! let classifier = ResponseClassifier::response_prefix(1)
+//!     .class(0x01, Outcome::Rejected("bad command"))
+//!     .class(0x02, Outcome::Rejected("bad length"));
+//!
+//! let mut driver = FuzzerDriver::<()>::new(1);
+//! driver.set_adaptive_weighting(classifier, AdaptiveConfig::default());
+//! ```
+//!
+//! [FuzzerDriver::set_adaptive_weighting]: crate::driver::FuzzerDriver::set_adaptive_weighting
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::feedback::Observation;
+use crate::mutator::Mutator;
+use crate::observer::MAX_REGENERATIONS;
+use crate::rand::Rng;
+
+/// Variant weights are scaled up by this much before being adjusted so that down-weighted variants
+/// keep a nonzero integer weight
+const VARIANT_WEIGHT_SCALE: f64 = 1000.0;
+
+/// What became of an input, as decided by a [ResponseClassifier]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    /// The target processed the input
+    Processed,
+    /// The target rejected the input. The name identifies why (e.g. `"bad checksum"`), which lets
+    /// choices that always fail the same check be told apart from ones which fail for many reasons.
+    Rejected(&'static str),
+    /// The outcome isn't known (e.g. the target didn't respond) and isn't tallied
+    Ignored,
+}
+
+/// Reads the response code of an iteration
+type ResponseCode = dyn Fn(&Observation) -> Option<u64> + Send + Sync;
+
+/// Maps the target's responses to [Outcome]s by their response code
+pub struct ResponseClassifier {
+    code: Box<ResponseCode>,
+    classes: HashMap<u64, Outcome>,
+    default: Outcome,
+}
+
+impl ResponseClassifier {
+    /// Creates a classifier which reads an iteration's response code with `code`. Iterations
+    /// without a code are [ignored](Outcome::Ignored), and codes which weren't given a class are
+    /// considered [processed](Outcome::Processed).
+    pub fn new<F>(code: F) -> ResponseClassifier
+    where
+        F: Fn(&Observation) -> Option<u64> + Send + Sync + 'static,
+    {
+        ResponseClassifier {
+            code: Box::new(code),
+            classes: HashMap::new(),
+            default: Outcome::Processed,
+        }
+    }
+
+    /// Creates a classifier which reads the response code as a big-endian integer from the first
+    /// `len` bytes of the response. Shorter responses have no code.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is 0 or more than 8
+    pub fn response_prefix(len: usize) -> ResponseClassifier {
+        assert!(
+            len > 0 && len <= 8,
+            "response codes must be between 1 and 8 bytes long"
+        );
+
+        ResponseClassifier::new(move |observation| {
+            observation.response.get(..len).map(|prefix| {
+                prefix
+                    .iter()
+                    .fold(0u64, |code, &byte| (code << 8) | u64::from(byte))
+            })
+        })
+    }
+
+    /// Classifies responses with the given code as `outcome`
+    pub fn class(mut self, code: u64, outcome: Outcome) -> ResponseClassifier {
+        self.classes.insert(code, outcome);
+        self
+    }
+
+    /// Sets the outcome of codes which weren't given a class
+    pub fn default_class(mut self, outcome: Outcome) -> ResponseClassifier {
+        self.default = outcome;
+        self
+    }
+
+    /// Returns the outcome of the iteration the target responded to with `observation`
+    pub fn classify(&self, observation: &Observation) -> Outcome {
+        match (self.code)(observation) {
+            Some(code) => *self.classes.get(&code).unwrap_or(&self.default),
+            None => Outcome::Ignored,
+        }
+    }
+}
+
+/// A decision made while generating an input
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Choice {
+    /// A variant of the enum was picked. Variants are identified by their declaration index.
+    Variant {
+        enum_name: &'static str,
+        index: usize,
+    },
+    /// A field was given a value. `path` is the field's path from the outermost struct being
+    /// generated, e.g. `header.command`.
+    FieldValue {
+        container: &'static str,
+        path: String,
+        value: i64,
+    },
+}
+
+/// The outcomes a [Choice] has been seen with
+#[derive(Debug, Clone, Default)]
+pub struct ChoiceStats {
+    /// Number of tallied outcomes
+    pub outcomes: usize,
+    /// Number of outcomes which were each kind of rejection
+    pub rejections: HashMap<&'static str, usize>,
+}
+
+impl ChoiceStats {
+    /// Returns the most common rejection and the share of all outcomes which were that rejection
+    pub fn dominant_rejection(&self) -> Option<(&'static str, f64)> {
+        self.rejections
+            .iter()
+            .max_by_key(|(_, &count)| count)
+            .map(|(&reason, &count)| (reason, count as f64 / self.outcomes as f64))
+    }
+}
+
+/// Tunables for how quickly choices are down-weighted
+#[derive(Debug, Clone)]
+pub struct AdaptiveConfig {
+    /// Number of outcomes a choice must be seen with before its weight is adjusted
+    pub min_samples: usize,
+    /// Share of a choice's outcomes which must be the same rejection for it to be down-weighted
+    pub rejection_threshold: f64,
+    /// Lowest weight a choice is reduced to, relative to its original weight
+    pub min_weight: f64,
+    /// Maximum number of distinct choices tracked. Choices first made after this many are tracked
+    /// are generated at their original weight.
+    pub max_tracked_choices: usize,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        AdaptiveConfig {
+            min_samples: 32,
+            rejection_threshold: 0.9,
+            min_weight: 0.05,
+            max_tracked_choices: 0x10000,
+        }
+    }
+}
+
+/// Outcome tallies for the choices made across a campaign, shared by every fuzzer thread
+#[derive(Debug)]
+pub struct AdaptiveWeights {
+    config: AdaptiveConfig,
+    choices: RwLock<HashMap<Choice, ChoiceStats>>,
+}
+
+impl AdaptiveWeights {
+    pub fn new(config: AdaptiveConfig) -> AdaptiveWeights {
+        AdaptiveWeights {
+            config,
+            choices: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &AdaptiveConfig {
+        &self.config
+    }
+
+    /// Tallies `outcome` against every choice made while generating an input
+    pub fn record(&self, choices: &[Choice], outcome: Outcome) {
+        if outcome == Outcome::Ignored || choices.is_empty() {
+            return;
+        }
+
+        let mut tracked = self.choices.write().unwrap();
+        for choice in choices {
+            if !tracked.contains_key(choice) {
+                if tracked.len() >= self.config.max_tracked_choices {
+                    continue;
+                }
+
+                tracked.insert(choice.clone(), ChoiceStats::default());
+            }
+
+            let stats = tracked.get_mut(choice).unwrap();
+            stats.outcomes += 1;
+            if let Outcome::Rejected(reason) = outcome {
+                *stats.rejections.entry(reason).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Returns the outcomes `choice` has been seen with
+    pub fn stats(&self, choice: &Choice) -> Option<ChoiceStats> {
+        self.choices.read().unwrap().get(choice).cloned()
+    }
+
+    /// Returns the weight of `choice` relative to its original weight
+    pub fn weight(&self, choice: &Choice) -> f64 {
+        self.choices
+            .read()
+            .unwrap()
+            .get(choice)
+            .map_or(1.0, |stats| self.weight_of(stats))
+    }
+
+    /// Returns every down-weighted choice with its weight, lowest first
+    pub fn down_weighted(&self) -> Vec<(Choice, f64)> {
+        let mut down_weighted: Vec<(Choice, f64)> = self
+            .choices
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(choice, stats)| (choice.clone(), self.weight_of(stats)))
+            .filter(|&(_, weight)| weight < 1.0)
+            .collect();
+
+        down_weighted.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        down_weighted
+    }
+
+    fn weight_of(&self, stats: &ChoiceStats) -> f64 {
+        if stats.outcomes < self.config.min_samples {
+            return 1.0;
+        }
+
+        match stats.dominant_rejection() {
+            Some((_, share)) if share >= self.config.rejection_threshold => {
+                (1.0 - share).max(self.config.min_weight)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Scales the weights of `enum_name`'s variants by how often they've been rejected. Returns
+    /// `None` if none of them are down-weighted.
+    pub(crate) fn variant_weights(
+        &self,
+        enum_name: &'static str,
+        weights: &[u64],
+        declaration_indices: &[usize],
+    ) -> Option<Vec<u64>> {
+        let tracked = self.choices.read().unwrap();
+
+        let factors: Vec<f64> = declaration_indices
+            .iter()
+            .map(|&index| {
+                let choice = Choice::Variant { enum_name, index };
+                tracked
+                    .get(&choice)
+                    .map_or(1.0, |stats| self.weight_of(stats))
+            })
+            .collect();
+
+        if factors.iter().all(|&factor| factor >= 1.0) {
+            return None;
+        }
+
+        Some(
+            weights
+                .iter()
+                .zip(factors)
+                .map(|(&weight, factor)| {
+                    if weight == 0 {
+                        0
+                    } else {
+                        (weight as f64 * VARIANT_WEIGHT_SCALE * factor).max(1.0) as u64
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Returns `value` as an integer if it's one of the field types whose values are tracked
+fn tracked_value<T: 'static>(value: &T) -> Option<i64> {
+    let value = value as &dyn Any;
+
+    macro_rules! tracked_types {
+        ($($ty:ty),*) => {
+            $(
+                if TypeId::of::<T>() == TypeId::of::<$ty>() {
+                    return value.downcast_ref::<$ty>().map(|&value| value as i64);
+                }
+            )*
+        };
+    }
+
+    tracked_types!(bool, u8, i8, u16, i16);
+
+    None
+}
+
+/// Decides whether the value generated for the field of `C` being generated by `mutator` (see
+/// [Mutator::enter_field]) is kept. Down-weighted values are rejected in proportion to their
+/// weight, up to [MAX_REGENERATIONS] times, and kept values are recorded as choices of the
+/// iteration.
+#[doc(hidden)]
+pub fn accept_generated<C, T, R>(mutator: &mut Mutator<R>, value: &T, attempt: usize) -> bool
+where
+    C: 'static,
+    T: 'static,
+    R: Rng,
+{
+    let value = match tracked_value(value) {
+        Some(value) => value,
+        None => return true,
+    };
+
+    let weights = match mutator.adaptive_weights() {
+        Some(weights) => weights.clone(),
+        None => return true,
+    };
+
+    let choice = Choice::FieldValue {
+        container: std::any::type_name::<C>(),
+        path: mutator.field_path(),
+        value,
+    };
+
+    let weight = weights.weight(&choice);
+    if attempt < MAX_REGENERATIONS && weight < 1.0 && !mutator.rng.gen_bool(weight) {
+        return false;
+    }
+
+    mutator.record_choice(choice);
+
+    true
+}
@@ -0,0 +1,149 @@
+//! Learning the tokens a target expects while a campaign runs.
+//!
+//! An [AutoDictionary] given to the driver with [FuzzerDriver::set_autodict] is shared by every
+//! fuzzer thread. It scans each iteration's response for printable tokens (keywords, header names,
+//! error messages quoting what was expected), and callbacks with a comparison-tracing hook (e.g.
+//! an instrumented `strcmp`/`memcmp`) can feed it the operands the target compared its input
+//! against with [AutoDictionary::record_comparison]. The mutators insert learned tokens into
+//! strings half of the time they'd otherwise insert a built-in token (see
+//! [StringStrategy::InsertToken]), so string constants the target checks for are found without
+//! writing a dictionary by hand.
+//!
+//! ```
+//! use lain::autodict::{AutoDictConfig, AutoDictionary};
+//! use lain::driver::FuzzerDriver;
+//!
+//! let mut driver = FuzzerDriver::<()>::new(1);
+//! driver.set_autodict(AutoDictionary::new(AutoDictConfig::default()));
+//!
+//! // from inside the fuzzer callback, e.g. when a traced memcmp reports its operands
+//! let dictionary = driver.autodict().unwrap();
+//! dictionary.record_comparison(b"GET ", b"XYZ ");
+//! assert_eq!(dictionary.len(), 2);
+//! ```
+//!
+//! Since the inputs generated depend on the responses seen so far, iterations can't be reproduced
+//! exactly. The dictionary isn't used in [DriverMode::Reproduce].
+//!
+//! [FuzzerDriver::set_autodict]: crate::driver::FuzzerDriver::set_autodict
+//! [StringStrategy::InsertToken]: crate::mutator::StringStrategy::InsertToken
+//! [DriverMode::Reproduce]: crate::driver::DriverMode::Reproduce
+
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use crate::rand::Rng;
+
+/// Tunables for which tokens are learned
+#[derive(Debug, Clone)]
+pub struct AutoDictConfig {
+    /// Shortest token learned. Shorter runs of printable bytes are mostly noise.
+    pub min_token_len: usize,
+    /// Longest token learned. Longer runs of printable bytes (e.g. encoded blobs) are skipped.
+    pub max_token_len: usize,
+    /// Maximum number of tokens kept. Tokens found after this many are learned are dropped.
+    pub max_tokens: usize,
+}
+
+impl Default for AutoDictConfig {
+    fn default() -> Self {
+        AutoDictConfig {
+            min_token_len: 3,
+            max_token_len: 32,
+            max_tokens: 0x1000,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Tokens {
+    /// Tokens in the order they were learned
+    list: Vec<Vec<u8>>,
+    seen: HashSet<Vec<u8>>,
+}
+
+/// Tokens learned from the target across a campaign, shared by every fuzzer thread
+#[derive(Debug)]
+pub struct AutoDictionary {
+    config: AutoDictConfig,
+    tokens: RwLock<Tokens>,
+}
+
+impl AutoDictionary {
+    pub fn new(config: AutoDictConfig) -> AutoDictionary {
+        AutoDictionary {
+            config,
+            tokens: RwLock::new(Tokens::default()),
+        }
+    }
+
+    pub fn config(&self) -> &AutoDictConfig {
+        &self.config
+    }
+
+    /// Adds `token` to the dictionary. Returns `false` if it was already known, its length is
+    /// outside of the configured bounds, or the dictionary is full.
+    pub fn insert(&self, token: &[u8]) -> bool {
+        if token.len() < self.config.min_token_len || token.len() > self.config.max_token_len {
+            return false;
+        }
+
+        // most tokens are seen again and again, so check for them without blocking other threads
+        if self.tokens.read().unwrap().seen.contains(token) {
+            return false;
+        }
+
+        let mut tokens = self.tokens.write().unwrap();
+        if tokens.list.len() >= self.config.max_tokens || !tokens.seen.insert(token.to_vec()) {
+            return false;
+        }
+
+        tokens.list.push(token.to_vec());
+
+        true
+    }
+
+    /// Learns every run of printable, non-whitespace ASCII in `response` whose length is within
+    /// the configured bounds. Returns the number of new tokens.
+    pub fn learn_from_response(&self, response: &[u8]) -> usize {
+        response
+            .split(|b| !b.is_ascii_graphic())
+            .filter(|token| self.insert(token))
+            .count()
+    }
+
+    /// Learns both operands of a comparison the target made, as reported by a comparison-tracing
+    /// hook. Operands may be any bytes, e.g. a binary magic number. Operands which compared equal
+    /// teach nothing new and are skipped. Returns the number of new tokens.
+    pub fn record_comparison(&self, lhs: &[u8], rhs: &[u8]) -> usize {
+        if lhs == rhs {
+            return 0;
+        }
+
+        self.insert(lhs) as usize + self.insert(rhs) as usize
+    }
+
+    /// Number of tokens learned
+    pub fn len(&self) -> usize {
+        self.tokens.read().unwrap().list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The tokens learned so far, in the order they were learned
+    pub fn tokens(&self) -> Vec<Vec<u8>> {
+        self.tokens.read().unwrap().list.clone()
+    }
+
+    /// Picks one of the learned tokens at random
+    pub fn random_token<R: Rng>(&self, rng: &mut R) -> Option<Vec<u8>> {
+        let tokens = self.tokens.read().unwrap();
+        if tokens.list.is_empty() {
+            return None;
+        }
+
+        Some(tokens.list[rng.gen_range(0, tokens.list.len())].clone())
+    }
+}
@@ -1,7 +1,62 @@
+use crate::target::TargetConfig;
 use crate::traits::*;
-use crate::types::UnsafeEnum;
-use byteorder::{ByteOrder, WriteBytesExt};
-use std::io::Write;
+use crate::types::{
+    Conversation, DosDateTime, FieldSwap, FileTime, FixedLengthString, Ipv4Address, Ipv6Address,
+    MacAddress, Offset, OneOf, PointerAligned, ScaleFactor, Scaled, TargetIsize, TargetUsize,
+    TextEncoded, TextEncoding, UnixTimestamp32, UnixTimestamp64, UnsafeEnum, Uuid, U24, U48,
+};
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use num_traits::{Bounded, NumCast, PrimInt};
+use std::borrow::Cow;
+use std::cmp;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+
+/// A [Write] sink which discards what's written to it and only counts the bytes. Serializing a
+/// value into it gives the exact number of bytes the value serializes to without allocating.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CountingWriter {
+    count: usize,
+}
+
+impl CountingWriter {
+    pub fn new() -> CountingWriter {
+        CountingWriter { count: 0 }
+    }
+
+    /// Number of bytes written so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl Write for CountingWriter {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.count += buf.len();
+
+        Ok(buf.len())
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the number of bytes `value` serializes to by serializing it into a [CountingWriter].
+/// This is what types deriving `BinarySerialize` with `#[lain(counted_size)]` use for their
+/// [SerializedSize], so that their size can never disagree with their serialization.
+pub fn counted_serialized_size<T: BinarySerialize + ?Sized>(value: &T) -> usize {
+    let mut writer = CountingWriter::new();
+
+    // the byte order doesn't change the size, and counting never fails
+    value
+        .binary_serialize::<_, BigEndian>(&mut writer)
+        .expect("serialization into a CountingWriter failed");
+
+    writer.count()
+}
 
 /// Default implementation of SerializedSize for slices of items. This runs in O(n) complexity since
 /// not all items in the slice are guaranteed to be the same size (e.g. strings)
@@ -10,7 +65,7 @@ where
     T: SerializedSize,
 {
     default fn serialized_size(&self) -> usize {
-        trace!("using default serialized_size for array");
+        hot_trace!("using default serialized_size for array");
         if self.is_empty() {
             return 0;
         }
@@ -31,7 +86,7 @@ where
 /// Returns the size of an UnsafeEnum's primitive type
 impl<T, P> SerializedSize for UnsafeEnum<T, P> {
     fn serialized_size(&self) -> usize {
-        trace!("using serialized size of unsafe enum");
+        hot_trace!("using serialized size of unsafe enum");
         std::mem::size_of::<P>()
     }
 
@@ -45,15 +100,15 @@ where
     T: SerializedSize,
 {
     fn serialized_size(&self) -> usize {
-        trace!("getting serialized size for Vec");
+        hot_trace!("getting serialized size for Vec");
         if self.is_empty() {
-            trace!("returning 0 since there's no elements");
+            hot_trace!("returning 0 since there's no elements");
             return 0;
         }
 
         let size = self.iter().map(SerializedSize::serialized_size).sum();
 
-        trace!("size is 0x{:02X}", size);
+        hot_trace!("size is 0x{:02X}", size);
 
         size
     }
@@ -63,9 +118,53 @@ where
     }
 }
 
+impl<T> SerializedSize for Option<T>
+where
+    T: SerializedSize,
+{
+    fn serialized_size(&self) -> usize {
+        self.as_ref().map_or(0, SerializedSize::serialized_size)
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        // `None` is empty
+        0
+    }
+}
+
+impl<T, E> SerializedSize for Result<T, E>
+where
+    T: SerializedSize,
+    E: SerializedSize,
+{
+    fn serialized_size(&self) -> usize {
+        match *self {
+            Ok(ref value) => value.serialized_size(),
+            Err(ref value) => value.serialized_size(),
+        }
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        cmp::min(
+            T::min_nonzero_elements_size(),
+            E::min_nonzero_elements_size(),
+        )
+    }
+}
+
+impl<'a> SerializedSize for Cow<'a, [u8]> {
+    fn serialized_size(&self) -> usize {
+        self.len()
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        1
+    }
+}
+
 impl SerializedSize for str {
     fn serialized_size(&self) -> usize {
-        trace!("getting serialized size of str");
+        hot_trace!("getting serialized size of str");
         self.as_bytes().len()
     }
 
@@ -76,7 +175,7 @@ impl SerializedSize for str {
 
 impl SerializedSize for String {
     fn serialized_size(&self) -> usize {
-        trace!("getting serialized size of String");
+        hot_trace!("getting serialized size of String");
         self.as_bytes().len()
     }
 
@@ -89,41 +188,52 @@ impl<T> BinarySerialize for Vec<T>
 where
     T: BinarySerialize,
 {
-    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
         let inner_ref: &[T] = self.as_ref();
-        inner_ref.binary_serialize::<_, E>(buffer);
+        inner_ref.binary_serialize::<_, E>(buffer)
     }
 }
 
 impl BinarySerialize for bool {
     #[inline(always)]
-    default fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
+    default fn binary_serialize<W: Write, E: ByteOrder>(
+        &self,
+        buffer: &mut W,
+    ) -> io::Result<usize> {
         // unsafe code here for non-binary booleans. i.e. when we do unsafe mutations
         // sometimes a bool is represented as 3 or some other non-0/1 number
         let value = unsafe { *((self as *const bool) as *const u8) };
 
-        buffer.write_u8(value).ok();
+        buffer.write_u8(value)?;
+
+        Ok(1)
     }
 }
 
 impl BinarySerialize for i8 {
     #[inline(always)]
-    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
-        buffer.write_i8(*self as i8).ok();
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        buffer.write_i8(*self as i8)?;
+
+        Ok(1)
     }
 }
 
 impl BinarySerialize for u8 {
     #[inline(always)]
-    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
-        buffer.write_u8(*self as u8).ok();
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        buffer.write_u8(*self as u8)?;
+
+        Ok(1)
     }
 }
 
 impl BinarySerialize for [u8] {
     #[inline(always)]
-    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
-        buffer.write(&self).ok();
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        buffer.write_all(&self)?;
+
+        Ok(self.len())
     }
 }
 
@@ -132,10 +242,16 @@ where
     T: BinarySerialize,
 {
     #[inline(always)]
-    default fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
+    default fn binary_serialize<W: Write, E: ByteOrder>(
+        &self,
+        buffer: &mut W,
+    ) -> io::Result<usize> {
+        let mut bytes_written = 0;
         for item in self.iter() {
-            item.binary_serialize::<W, E>(buffer);
+            bytes_written += item.binary_serialize::<W, E>(buffer)?;
         }
+
+        Ok(bytes_written)
     }
 }
 
@@ -144,22 +260,236 @@ where
     T: BinarySerialize,
     I: BinarySerialize + Clone,
 {
-    default fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
+    default fn binary_serialize<W: Write, E: ByteOrder>(
+        &self,
+        buffer: &mut W,
+    ) -> io::Result<usize> {
+        match *self {
+            UnsafeEnum::Invalid(ref value) => value.binary_serialize::<_, E>(buffer),
+            UnsafeEnum::Valid(ref value) => value.binary_serialize::<_, E>(buffer),
+        }
+    }
+}
+
+impl<T> BinarySerialize for Option<T>
+where
+    T: BinarySerialize,
+{
+    /// `None` has no wire representation, so it writes nothing
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
         match *self {
-            UnsafeEnum::Invalid(ref value) => {
-                value.binary_serialize::<_, E>(buffer);
+            Some(ref value) => value.binary_serialize::<_, E>(buffer),
+            None => Ok(0),
+        }
+    }
+}
+
+impl<T, Er> BinarySerialize for Result<T, Er>
+where
+    T: BinarySerialize,
+    Er: BinarySerialize,
+{
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        match *self {
+            Ok(ref value) => value.binary_serialize::<_, E>(buffer),
+            Err(ref value) => value.binary_serialize::<_, E>(buffer),
+        }
+    }
+}
+
+impl<'a> BinarySerialize for Cow<'a, [u8]> {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        (**self).binary_serialize::<_, E>(buffer)
+    }
+}
+
+impl BinarySerialize for U24 {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        // byteorder panics on values wider than 24 bits
+        buffer.write_u24::<E>(self.0 & U24::MAX)?;
+
+        Ok(3)
+    }
+}
+
+impl BinarySerialize for U48 {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        buffer.write_u48::<E>(self.0 & U48::MAX)?;
+
+        Ok(6)
+    }
+}
+
+macro_rules! impl_binary_serialize_octets {
+    ( $($name:ident => $len:expr),* ) => {
+        $(
+            impl BinarySerialize for $name {
+                #[inline(always)]
+                fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+                    // these have a fixed byte order on the wire
+                    buffer.write_all(&self.0)?;
+
+                    Ok($len)
+                }
             }
-            UnsafeEnum::Valid(ref value) => {
-                value.binary_serialize::<_, E>(buffer);
+
+            impl SerializedSize for $name {
+                #[inline(always)]
+                fn serialized_size(&self) -> usize {
+                    $len
+                }
+
+                fn min_nonzero_elements_size() -> usize {
+                    $len
+                }
             }
+        )*
+    }
+}
+
+impl_binary_serialize_octets!(Ipv4Address => 4, Ipv6Address => 16, MacAddress => 6, Uuid => 16);
+
+impl<const N: usize, const PAD: u8> BinarySerialize for FixedLengthString<N, PAD> {
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        let contents = self.serialized_contents();
+        buffer.write_all(contents)?;
+
+        // written from a fixed block so that padding doesn't allocate
+        let padding = [PAD; 64];
+        let mut remaining = N - contents.len();
+        while remaining != 0 {
+            let len = cmp::min(remaining, padding.len());
+            buffer.write_all(&padding[..len])?;
+            remaining -= len;
         }
+
+        Ok(N)
+    }
+}
+
+impl<const N: usize, const PAD: u8> SerializedSize for FixedLengthString<N, PAD> {
+    #[inline(always)]
+    fn serialized_size(&self) -> usize {
+        N
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        N
+    }
+}
+
+impl BinarySerialize for UnixTimestamp32 {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        buffer.write_i32::<E>(self.0)?;
+
+        Ok(4)
+    }
+}
+
+impl BinarySerialize for UnixTimestamp64 {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        buffer.write_i64::<E>(self.0)?;
+
+        Ok(8)
+    }
+}
+
+impl BinarySerialize for DosDateTime {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        buffer.write_u16::<E>(self.time)?;
+        buffer.write_u16::<E>(self.date)?;
+
+        Ok(4)
+    }
+}
+
+impl BinarySerialize for FileTime {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        buffer.write_u32::<E>(self.0 as u32)?;
+        buffer.write_u32::<E>((self.0 >> 32) as u32)?;
+
+        Ok(8)
+    }
+}
+
+macro_rules! impl_serialized_size_fixed {
+    ( $($name:ident => $len:expr),* ) => {
+        $(
+            impl SerializedSize for $name {
+                #[inline(always)]
+                fn serialized_size(&self) -> usize {
+                    $len
+                }
+
+                fn min_nonzero_elements_size() -> usize {
+                    $len
+                }
+            }
+        )*
+    }
+}
+
+impl_serialized_size_fixed!(UnixTimestamp32 => 4, UnixTimestamp64 => 8, DosDateTime => 4, FileTime => 8);
+
+impl SerializedSize for U24 {
+    #[inline(always)]
+    fn serialized_size(&self) -> usize {
+        3
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        3
+    }
+}
+
+impl SerializedSize for U48 {
+    #[inline(always)]
+    fn serialized_size(&self) -> usize {
+        6
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        6
+    }
+}
+
+impl<T, F> BinarySerialize for Scaled<T, F>
+where
+    T: BinarySerialize + NumCast + Bounded + Copy,
+    F: ScaleFactor,
+{
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        self.raw().binary_serialize::<_, E>(buffer)
+    }
+}
+
+impl<T, F> SerializedSize for Scaled<T, F>
+where
+    T: SerializedSize + NumCast + Bounded + Copy,
+    F: ScaleFactor,
+{
+    #[inline(always)]
+    fn serialized_size(&self) -> usize {
+        self.raw().serialized_size()
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        T::min_nonzero_elements_size()
     }
 }
 
 impl BinarySerialize for String {
     #[inline(always)]
-    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
-        self.as_bytes().binary_serialize::<_, E>(buffer);
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        self.as_bytes().binary_serialize::<_, E>(buffer)
     }
 }
 
@@ -168,8 +498,217 @@ impl BinarySerialize for String {
 /// bool (specifically) in the future. See: https://github.com/rust-lang/rust/issues/45542
 impl BinarySerialize for &str {
     #[inline(always)]
-    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
-        self.as_bytes().binary_serialize::<_, E>(buffer);
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        self.as_bytes().binary_serialize::<_, E>(buffer)
+    }
+}
+
+/// Whether the byte order `E` is the platform's native one, in which case the bytes of a
+/// [PlainOldData] value in memory are the bytes it serializes to
+#[inline(always)]
+pub fn is_native_byte_order<E: ByteOrder>() -> bool {
+    let mut probe = [0u8; 2];
+    E::write_u16(&mut probe, 1);
+
+    probe == 1u16.to_ne_bytes()
+}
+
+macro_rules! impl_plain_old_data {
+    ( $($name:ty),* ) => {
+        $(
+            unsafe impl PlainOldData for $name {}
+        )*
+    }
+}
+
+impl_plain_old_data!(bool, i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+unsafe impl<T: PlainOldData, const N: usize> PlainOldData for [T; N] {}
+
+macro_rules! impl_no_alloc_serialize {
+    ( $($name:ty),* ) => {
+        $(
+            impl NoAllocSerialize for $name {}
+        )*
+    }
+}
+
+impl_no_alloc_serialize!(bool, i8, u8, i16, u16, i32, u32, i64, u64, f32, f64, String, &str);
+impl_no_alloc_serialize!(U24, U48, Ipv4Address, Ipv6Address, MacAddress, Uuid);
+impl_no_alloc_serialize!(UnixTimestamp32, UnixTimestamp64, DosDateTime, FileTime);
+
+impl<T: NoAllocSerialize> NoAllocSerialize for [T] {}
+
+impl<T: NoAllocSerialize> NoAllocSerialize for Vec<T> {}
+
+impl<T: NoAllocSerialize> NoAllocSerialize for Option<T> {}
+
+impl<T: NoAllocSerialize, E: NoAllocSerialize> NoAllocSerialize for Result<T, E> {}
+
+impl<'a> NoAllocSerialize for Cow<'a, [u8]> {}
+
+impl<const N: usize, const PAD: u8> NoAllocSerialize for FixedLengthString<N, PAD> {}
+
+impl<T, I> NoAllocSerialize for UnsafeEnum<T, I>
+where
+    T: NoAllocSerialize,
+    I: NoAllocSerialize + Clone,
+{
+}
+
+impl<T, C> BinarySerialize for TextEncoded<T, C>
+where
+    T: BinarySerialize,
+    C: TextEncoding,
+{
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        let mut data = Vec::new();
+        self.value.binary_serialize::<_, E>(&mut data)?;
+
+        let mut text = Vec::with_capacity(self.encoded_len(data.len()));
+        self.encode(&data, &mut text);
+        buffer.write_all(&text)?;
+
+        Ok(text.len())
+    }
+}
+
+impl<T, C> SerializedSize for TextEncoded<T, C>
+where
+    T: SerializedSize,
+    C: TextEncoding,
+{
+    fn serialized_size(&self) -> usize {
+        self.encoded_len(self.value.serialized_size())
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        C::encoded_len(T::min_nonzero_elements_size())
+    }
+}
+
+impl<T, F> NoAllocSerialize for Scaled<T, F>
+where
+    T: NoAllocSerialize + NumCast + Bounded + Copy,
+    F: ScaleFactor,
+{
+}
+
+impl<T: BinarySerialize + PrimInt> BinarySerialize for Offset<T> {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        self.value().binary_serialize::<_, E>(buffer)
+    }
+}
+
+impl<T: SerializedSize + PrimInt> SerializedSize for Offset<T> {
+    #[inline(always)]
+    fn serialized_size(&self) -> usize {
+        self.value().serialized_size()
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        T::min_nonzero_elements_size()
+    }
+}
+
+impl<T: NoAllocSerialize + PrimInt> NoAllocSerialize for Offset<T> {}
+
+impl<M: BinarySerialize> BinarySerialize for Conversation<M> {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        self.messages().binary_serialize::<_, E>(buffer)
+    }
+}
+
+impl<M: SerializedSize> SerializedSize for Conversation<M> {
+    #[inline(always)]
+    fn serialized_size(&self) -> usize {
+        self.messages().serialized_size()
+    }
+
+    #[inline(always)]
+    fn min_nonzero_elements_size() -> usize {
+        M::min_nonzero_elements_size()
+    }
+}
+
+impl<M: NoAllocSerialize> NoAllocSerialize for Conversation<M> {}
+
+impl<T: BinarySerialize> BinarySerialize for OneOf<T> {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        self.value().binary_serialize::<_, E>(buffer)
+    }
+}
+
+impl<T: SerializedSize> SerializedSize for OneOf<T> {
+    #[inline(always)]
+    fn serialized_size(&self) -> usize {
+        self.value().serialized_size()
+    }
+
+    #[inline(always)]
+    fn min_nonzero_elements_size() -> usize {
+        T::min_nonzero_elements_size()
+    }
+}
+
+impl<T: NoAllocSerialize> NoAllocSerialize for OneOf<T> {}
+
+impl BinarySerialize for TargetUsize {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        TargetConfig::current().write_pointer(buffer, self.0)
+    }
+}
+
+impl BinarySerialize for TargetIsize {
+    #[inline(always)]
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        TargetConfig::current().write_pointer(buffer, self.0 as u64)
+    }
+}
+
+macro_rules! impl_serialized_size_pointer {
+    ( $($name:ident),* ) => {
+        $(
+            impl SerializedSize for $name {
+                #[inline(always)]
+                fn serialized_size(&self) -> usize {
+                    TargetConfig::current().pointer_size()
+                }
+
+                fn min_nonzero_elements_size() -> usize {
+                    TargetConfig::current().pointer_size()
+                }
+            }
+        )*
+    }
+}
+
+impl_serialized_size_pointer!(TargetUsize, TargetIsize);
+
+impl<T: BinarySerialize + SerializedSize> BinarySerialize for PointerAligned<T> {
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        let len = self.0.binary_serialize::<_, E>(buffer)?;
+        let padding = TargetConfig::current().padding_after(len);
+        buffer.write_all(&[0u8; 8][..padding])?;
+
+        Ok(len + padding)
+    }
+}
+
+impl<T: SerializedSize> SerializedSize for PointerAligned<T> {
+    #[inline(always)]
+    fn serialized_size(&self) -> usize {
+        let len = self.0.serialized_size();
+
+        len + TargetConfig::current().padding_after(len)
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        T::min_nonzero_elements_size()
     }
 }
 
@@ -178,7 +717,7 @@ macro_rules! impl_buffer_pushable {
         $(
             impl BinarySerialize for $name {
                 #[inline(always)]
-                fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) {
+                fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
                     // need to use mashup here to do write_(u8|u16|...) since you can't concat
                     // idents otherwise
                     mashup! {
@@ -186,8 +725,10 @@ macro_rules! impl_buffer_pushable {
                     }
 
                     m! {
-                        buffer."method_name"::<E>(*self as $name).ok();
+                        buffer."method_name"::<E>(*self as $name)?;
                     }
+
+                    Ok(std::mem::size_of::<$name>())
                 }
             }
         )*
@@ -214,3 +755,73 @@ macro_rules! impl_serialized_size {
 }
 
 impl_serialized_size!(i64, u64, i32, u32, i16, u16, f32, f64, u8, i8, bool);
+
+macro_rules! impl_binary_serialize_tuple {
+    ( $($name:ident $idx:tt),+ ) => {
+        impl<$($name),+> BinarySerialize for ($($name,)+)
+        where
+            $($name: BinarySerialize),+
+        {
+            #[inline(always)]
+            fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+                let mut bytes_written = 0;
+                $(
+                    bytes_written += self.$idx.binary_serialize::<_, E>(buffer)?;
+                )+
+
+                Ok(bytes_written)
+            }
+        }
+
+        impl<$($name),+> SerializedSize for ($($name,)+)
+        where
+            $($name: SerializedSize),+
+        {
+            #[inline(always)]
+            fn serialized_size(&self) -> usize {
+                0 $(+ self.$idx.serialized_size())+
+            }
+
+            fn min_nonzero_elements_size() -> usize {
+                0 $(+ $name::min_nonzero_elements_size())+
+            }
+        }
+
+        impl<$($name),+> NoAllocSerialize for ($($name,)+)
+        where
+            $($name: NoAllocSerialize),+
+        {
+        }
+    }
+}
+
+for_each_tuple!(impl_binary_serialize_tuple);
+
+macro_rules! impl_binary_serialize_zero_sized {
+    ( $($name:ty $(, $generic:ident)?);* ) => {
+        $(
+            impl$(<$generic>)? BinarySerialize for $name {
+                #[inline(always)]
+                fn binary_serialize<W: Write, E: ByteOrder>(&self, _buffer: &mut W) -> io::Result<usize> {
+                    // zero-sized types have no wire representation
+                    Ok(0)
+                }
+            }
+
+            impl$(<$generic>)? SerializedSize for $name {
+                #[inline(always)]
+                fn serialized_size(&self) -> usize {
+                    0
+                }
+
+                fn min_nonzero_elements_size() -> usize {
+                    0
+                }
+            }
+
+            impl$(<$generic>)? NoAllocSerialize for $name {}
+        )*
+    }
+}
+
+impl_binary_serialize_zero_sized!((); PhantomData<T>, T; FieldSwap);
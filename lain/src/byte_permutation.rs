@@ -0,0 +1,103 @@
+//! Custom byte orderings for fields which are neither big nor little endian.
+//!
+//! A field marked `#[lain(byte_permutation = "path::to::fn")]` is serialized with its usual byte
+//! order and the resulting bytes are then rearranged by the function, which is also called to
+//! restore the original order before the field is deserialized. The function has the signature
+//! `fn(&mut [u8], Direction)`. Permutations whose inverse is themselves can ignore the direction.
+//!
+//! ```compile_fail
+//! #[derive(BinarySerialize, BinaryDeserialize)]
+//! struct Header {
+//!     // PDP-11 32-bit integers: 0x0A0B0C0D is stored as 0B 0A 0D 0C
+//!     #[lain(byteorder(big), byte_permutation = "lain::byte_permutation::pdp_endian")]
+//!     timestamp: u32,
+//! }
+//! ```
+//!
+//! Deserializing a permuted field reads `min_nonzero_elements_size()` bytes for it, so the field's
+//! type must have a fixed serialized size, such as an integer.
+
+use crate::deserialize::MissingFieldDefault;
+use crate::traits::{BinaryDeserialize, BinarySerialize, SerializedSize};
+use byteorder::ByteOrder;
+use std::io::{self, Read, Write};
+
+/// Which way a permutation function should rearrange the bytes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    /// From the field's byte order to the wire format
+    Serialize,
+    /// From the wire format back to the field's byte order
+    Deserialize,
+}
+
+/// Swaps each pair of bytes. Applied to big-endian data this produces the PDP-11 "middle endian"
+/// layout, where 32-bit values are stored as two little-endian 16-bit words with the most
+/// significant word first. A trailing odd byte is left in place.
+pub fn pdp_endian(bytes: &mut [u8], _direction: Direction) {
+    for pair in bytes.chunks_exact_mut(2) {
+        pair.swap(0, 1);
+    }
+}
+
+/// Reverses the bytes, flipping the field's byte order
+pub fn reverse(bytes: &mut [u8], _direction: Direction) {
+    bytes.reverse();
+}
+
+/// Serializes `value` with the byte order `E` and writes it rearranged by `permutation`
+#[doc(hidden)]
+pub fn serialize_permuted<T, W, E>(
+    value: &T,
+    buffer: &mut W,
+    permutation: fn(&mut [u8], Direction),
+) -> io::Result<usize>
+where
+    T: BinarySerialize + ?Sized,
+    W: Write,
+    E: ByteOrder,
+{
+    let mut bytes = Vec::new();
+    value.binary_serialize::<_, E>(&mut bytes)?;
+
+    permutation(&mut bytes, Direction::Serialize);
+    buffer.write_all(&bytes)?;
+
+    Ok(bytes.len())
+}
+
+/// Reads a field written by [serialize_permuted], restoring its byte order before deserializing it
+#[doc(hidden)]
+pub fn deserialize_permuted<T, R, E>(
+    buffer: &mut R,
+    permutation: fn(&mut [u8], Direction),
+) -> io::Result<T>
+where
+    T: BinaryDeserialize + SerializedSize,
+    R: Read,
+    E: ByteOrder,
+{
+    let mut bytes = vec![0u8; T::min_nonzero_elements_size()];
+    buffer.read_exact(&mut bytes)?;
+
+    permutation(&mut bytes, Direction::Deserialize);
+
+    T::binary_deserialize::<_, E>(&mut bytes.as_slice())
+}
+
+/// Like [deserialize_permuted], but substitutes the field's default value if the input has ended
+#[doc(hidden)]
+pub fn deserialize_permuted_lenient<T, R, E>(
+    buffer: &mut R,
+    permutation: fn(&mut [u8], Direction),
+) -> io::Result<T>
+where
+    T: BinaryDeserialize + SerializedSize,
+    R: Read,
+    E: ByteOrder,
+{
+    match deserialize_permuted::<T, R, E>(buffer, permutation) {
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => T::missing_field_default(),
+        result => result,
+    }
+}
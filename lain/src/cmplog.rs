@@ -0,0 +1,352 @@
+//! Capturing the values a target compares its input against.
+//!
+//! Targets built with `-fsanitize-coverage=trace-cmp` call a `__sanitizer_cov_trace_cmp*` hook
+//! with both operands of every integer comparison they make, and with `-fsanitize=fuzzer-no-link`
+//! the `memcmp`/`strcmp` family calls a `__sanitizer_weak_hook_*` hook as well. The
+//! `cmp_trace_support` feature defines these hooks for targets linked into the fuzzer, recording
+//! the operands in the process-wide [hook_table]. Like libFuzzer's table of recent compares, only
+//! the last [TABLE_SIZE] comparisons of each width are kept. Recording never blocks or panics,
+//! and comparisons made while a hook is recording (e.g. by lain itself, if it's instrumented as
+//! well) aren't recorded.
+//!
+//! Mutators given a table with [Mutator::set_compare_table] (which the driver does for every
+//! thread once [FuzzerDriver::set_compare_table] is called) substitute the captured operands
+//! into fields: [NumericStrategy::CompareOperand] turns an integer which was compared against a
+//! magic value into that value, and byte buffers have operands of `memcmp`s spliced into them.
+//! Comparisons traced some other way can be added with [CompareTable::record_integers] and
+//! [CompareTable::record_bytes].
+//!
+//! ```
+//! use lain::cmplog::CompareTable;
+//! use lain::driver::FuzzerDriver;
+//! use std::sync::Arc;
+//!
+//! let table = Arc::new(CompareTable::new());
+//! // e.g. `if header.magic == 0x464c457f`
+//! table.record_integers(4, 0x4141_4141, 0x464c_457f);
+//!
+//! let mut driver = FuzzerDriver::<()>::new(1);
+//! driver.set_compare_table(table);
+//! ```
+//!
+//! Since the inputs generated depend on the comparisons the target made so far, iterations can't
+//! be reproduced exactly. The table isn't used in [DriverMode::Reproduce].
+//!
+//! [Mutator::set_compare_table]: crate::mutator::Mutator::set_compare_table
+//! [FuzzerDriver::set_compare_table]: crate::driver::FuzzerDriver::set_compare_table
+//! [NumericStrategy::CompareOperand]: crate::mutator::NumericStrategy::CompareOperand
+//! [DriverMode::Reproduce]: crate::driver::DriverMode::Reproduce
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::lazy_static::lazy_static;
+use crate::rand::Rng;
+
+/// Number of comparisons of each width (and of byte comparisons) kept
+pub const TABLE_SIZE: usize = 32;
+
+/// Longest byte operand kept. Longer operands are truncated.
+pub const MAX_BYTES_OPERAND_LEN: usize = 64;
+
+/// Widths, in bytes, of the integer comparisons which are captured
+const INTEGER_WIDTHS: [usize; 4] = [1, 2, 4, 8];
+
+/// The most recent comparisons of one integer width, overwriting the oldest once full. Pushing
+/// never blocks, since it's done from the comparison hooks of every thread of the target. A
+/// comparison read while another thread overwrites its slot may pair operands of both.
+#[derive(Debug)]
+struct IntegerRing {
+    slots: Vec<(AtomicU64, AtomicU64)>,
+    /// Number of comparisons pushed since the ring was last cleared
+    pushed: AtomicUsize,
+}
+
+impl IntegerRing {
+    fn new() -> IntegerRing {
+        IntegerRing {
+            slots: (0..TABLE_SIZE)
+                .map(|_| (AtomicU64::new(0), AtomicU64::new(0)))
+                .collect(),
+            pushed: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, lhs: u64, rhs: u64) {
+        let (ref slot_lhs, ref slot_rhs) =
+            self.slots[self.pushed.fetch_add(1, Ordering::Relaxed) % TABLE_SIZE];
+        slot_lhs.store(lhs, Ordering::Relaxed);
+        slot_rhs.store(rhs, Ordering::Relaxed);
+    }
+
+    fn entries(&self) -> Vec<(u64, u64)> {
+        let len = std::cmp::min(self.pushed.load(Ordering::Relaxed), TABLE_SIZE);
+        self.slots[..len]
+            .iter()
+            .map(|(lhs, rhs)| (lhs.load(Ordering::Relaxed), rhs.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    fn clear(&self) {
+        self.pushed.store(0, Ordering::Relaxed);
+    }
+}
+
+/// The most recent byte string comparisons. A comparison is dropped rather than waited on if its
+/// slot is being read or written by another thread.
+#[derive(Debug)]
+struct BytesRing {
+    slots: Vec<Mutex<(Vec<u8>, Vec<u8>)>>,
+    pushed: AtomicUsize,
+}
+
+impl BytesRing {
+    fn new() -> BytesRing {
+        BytesRing {
+            slots: (0..TABLE_SIZE)
+                .map(|_| Mutex::new((Vec::new(), Vec::new())))
+                .collect(),
+            pushed: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, lhs: &[u8], rhs: &[u8]) {
+        let slot = &self.slots[self.pushed.fetch_add(1, Ordering::Relaxed) % TABLE_SIZE];
+        if let Ok(mut slot) = slot.try_lock() {
+            slot.0.clear();
+            slot.0.extend_from_slice(lhs);
+            slot.1.clear();
+            slot.1.extend_from_slice(rhs);
+        }
+    }
+
+    fn entries(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let len = std::cmp::min(self.pushed.load(Ordering::Relaxed), TABLE_SIZE);
+        self.slots[..len]
+            .iter()
+            .map(|slot| match slot.lock() {
+                Ok(slot) => slot.clone(),
+                Err(poisoned) => poisoned.into_inner().clone(),
+            })
+            .collect()
+    }
+
+    fn clear(&self) {
+        self.pushed.store(0, Ordering::Relaxed);
+    }
+}
+
+/// The operands of the comparisons a target made most recently, shared by every fuzzer thread
+#[derive(Debug)]
+pub struct CompareTable {
+    /// Integer comparisons, indexed like [INTEGER_WIDTHS]
+    integers: [IntegerRing; 4],
+    bytes: BytesRing,
+}
+
+impl Default for CompareTable {
+    fn default() -> Self {
+        CompareTable::new()
+    }
+}
+
+impl CompareTable {
+    pub fn new() -> CompareTable {
+        CompareTable {
+            integers: [
+                IntegerRing::new(),
+                IntegerRing::new(),
+                IntegerRing::new(),
+                IntegerRing::new(),
+            ],
+            bytes: BytesRing::new(),
+        }
+    }
+
+    /// Records a comparison of two `width`-byte integers. Comparisons of equal operands, and of
+    /// widths other than 1, 2, 4, or 8, are ignored.
+    pub fn record_integers(&self, width: usize, lhs: u64, rhs: u64) {
+        let index = match INTEGER_WIDTHS.iter().position(|&w| w == width) {
+            Some(index) if lhs != rhs => index,
+            _ => return,
+        };
+
+        self.integers[index].push(lhs, rhs);
+    }
+
+    /// Records a comparison of two byte strings, e.g. the arguments of a `memcmp`. Comparisons of
+    /// equal operands are ignored.
+    pub fn record_bytes(&self, lhs: &[u8], rhs: &[u8]) {
+        let lhs = &lhs[..std::cmp::min(lhs.len(), MAX_BYTES_OPERAND_LEN)];
+        let rhs = &rhs[..std::cmp::min(rhs.len(), MAX_BYTES_OPERAND_LEN)];
+        if lhs == rhs {
+            return;
+        }
+
+        self.bytes.push(lhs, rhs);
+    }
+
+    /// The captured comparisons of `width`-byte integers
+    pub fn integer_operands(&self, width: usize) -> Vec<(u64, u64)> {
+        match INTEGER_WIDTHS.iter().position(|&w| w == width) {
+            Some(index) => self.integers[index].entries(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The captured comparisons of byte strings
+    pub fn byte_operands(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.bytes.entries()
+    }
+
+    /// Forgets every captured comparison
+    pub fn clear(&self) {
+        for ring in &self.integers {
+            ring.clear();
+        }
+        self.bytes.clear();
+    }
+
+    /// Picks the value a `width`-byte integer which currently holds `value` should become. If the
+    /// value was one side of a captured comparison it becomes the other side, otherwise it becomes
+    /// either side of a random comparison. Returns `None` if no comparisons of the width were
+    /// captured.
+    pub(crate) fn pick_integer<R: Rng>(
+        &self,
+        rng: &mut R,
+        width: usize,
+        value: u64,
+    ) -> Option<u64> {
+        let index = INTEGER_WIDTHS.iter().position(|&w| w == width)?;
+        let operands = self.integers[index].entries();
+        if operands.is_empty() {
+            return None;
+        }
+
+        let matching = operands.iter().find_map(|&(lhs, rhs)| {
+            if lhs == value {
+                Some(rhs)
+            } else if rhs == value {
+                Some(lhs)
+            } else {
+                None
+            }
+        });
+
+        matching.or_else(|| {
+            let (lhs, rhs) = operands[rng.gen_range(0, operands.len())];
+            Some(if rng.gen() { lhs } else { rhs })
+        })
+    }
+
+    /// Picks a random byte string comparison
+    pub(crate) fn pick_bytes<R: Rng>(&self, rng: &mut R) -> Option<(Vec<u8>, Vec<u8>)> {
+        let operands = self.bytes.entries();
+        if operands.is_empty() {
+            return None;
+        }
+
+        Some(operands[rng.gen_range(0, operands.len())].clone())
+    }
+}
+
+lazy_static! {
+    static ref HOOK_TABLE: Arc<CompareTable> = Arc::new(CompareTable::new());
+}
+
+/// The table the `__sanitizer_cov_trace_cmp*` hooks record into when the `cmp_trace_support`
+/// feature is enabled
+pub fn hook_table() -> Arc<CompareTable> {
+    HOOK_TABLE.clone()
+}
+
+#[cfg(feature = "cmp_trace_support")]
+mod hooks {
+    use super::{CompareTable, HOOK_TABLE};
+    use std::cell::Cell;
+    use std::ffi::CStr;
+    use std::os::raw::{c_char, c_int, c_void};
+    use std::slice;
+
+    thread_local! {
+        static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// Runs `record` unless this thread is already in a hook. lain itself may be built with
+    /// comparison tracing, in which case the comparisons made while recording call the hooks
+    /// again. Nothing is recorded while the thread is being torn down.
+    fn guarded<F: FnOnce(&CompareTable)>(record: F) {
+        let _ = IN_HOOK.try_with(|in_hook| {
+            if !in_hook.replace(true) {
+                record(&HOOK_TABLE);
+                in_hook.set(false);
+            }
+        });
+    }
+
+    macro_rules! trace_cmp_hooks {
+        ( $($width:expr => $ty:ty: $name:ident, $const_name:ident),* ) => {
+            $(
+                #[no_mangle]
+                pub extern "C" fn $name(lhs: $ty, rhs: $ty) {
+                    guarded(|table| table.record_integers($width, lhs as u64, rhs as u64));
+                }
+
+                #[no_mangle]
+                pub extern "C" fn $const_name(lhs: $ty, rhs: $ty) {
+                    guarded(|table| table.record_integers($width, lhs as u64, rhs as u64));
+                }
+            )*
+        }
+    }
+
+    trace_cmp_hooks!(
+        1 => u8: __sanitizer_cov_trace_cmp1, __sanitizer_cov_trace_const_cmp1,
+        2 => u16: __sanitizer_cov_trace_cmp2, __sanitizer_cov_trace_const_cmp2,
+        4 => u32: __sanitizer_cov_trace_cmp4, __sanitizer_cov_trace_const_cmp4,
+        8 => u64: __sanitizer_cov_trace_cmp8, __sanitizer_cov_trace_const_cmp8
+    );
+
+    /// `cases` holds the number of cases, the width of the value in bits, and then the cases
+    #[no_mangle]
+    pub unsafe extern "C" fn __sanitizer_cov_trace_switch(value: u64, cases: *const u64) {
+        let count = *cases as usize;
+        let width = *cases.add(1) as usize / 8;
+        let cases = slice::from_raw_parts(cases.add(2), count);
+
+        guarded(|table| {
+            for &case in cases {
+                table.record_integers(width, value, case);
+            }
+        });
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __sanitizer_weak_hook_memcmp(
+        _caller_pc: *const c_void,
+        s1: *const c_void,
+        s2: *const c_void,
+        n: usize,
+        result: c_int,
+    ) {
+        if result != 0 && n > 0 {
+            let s1 = slice::from_raw_parts(s1 as *const u8, n);
+            let s2 = slice::from_raw_parts(s2 as *const u8, n);
+            guarded(|table| table.record_bytes(s1, s2));
+        }
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn __sanitizer_weak_hook_strcmp(
+        _caller_pc: *const c_void,
+        s1: *const c_char,
+        s2: *const c_char,
+        result: c_int,
+    ) {
+        if result != 0 {
+            let s1 = CStr::from_ptr(s1).to_bytes();
+            let s2 = CStr::from_ptr(s2).to_bytes();
+            guarded(|table| table.record_bytes(s1, s2));
+        }
+    }
+}
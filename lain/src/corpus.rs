@@ -0,0 +1,494 @@
+//! Typed collections of inputs which campaigns can be started from.
+//!
+//! Large corpora can be kept on disk in a [CorpusStore], which addresses entries by the hash of
+//! their serialized bytes so that byte-identical inputs are only stored once.
+
+use crate::error::Error;
+use crate::traits::{BinaryDeserialize, BinarySerialize};
+use byteorder::ByteOrder;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[cfg(feature = "serde_support")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A collection of typed inputs. With the `serde_support` feature, a corpus is serialized as the
+/// list of its entries.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde_support",
+    derive(Serialize, Deserialize),
+    serde(transparent)
+)]
+pub struct Corpus<T> {
+    entries: Vec<T>,
+}
+
+impl<T> Default for Corpus<T> {
+    fn default() -> Self {
+        Corpus {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T> Corpus<T> {
+    pub fn new() -> Corpus<T> {
+        Default::default()
+    }
+
+    /// Adds an entry to the corpus
+    pub fn push(&mut self, entry: T) {
+        self.entries.push(entry);
+    }
+
+    /// The entries in this corpus
+    pub fn entries(&self) -> &[T] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Consumes the corpus, returning its entries
+    pub fn into_entries(self) -> Vec<T> {
+        self.entries
+    }
+}
+
+impl<T> Corpus<T>
+where
+    T: BinarySerialize,
+{
+    /// Removes entries which serialize (with the byte order `E`) to the same bytes as an
+    /// earlier entry, keeping the first occurrence. Returns the number of entries removed.
+    ///
+    /// Panics if an entry can't be serialized. See [Corpus::try_dedup].
+    pub fn dedup<E: ByteOrder>(&mut self) -> usize {
+        self.try_dedup::<E>()
+            .expect("failed to serialize corpus entry")
+    }
+
+    /// Like [Corpus::dedup], but returns an [Error::Serialize] if an entry can't be serialized,
+    /// leaving the corpus untouched
+    pub fn try_dedup<E: ByteOrder>(&mut self) -> crate::Result<usize> {
+        let mut seen = HashSet::with_capacity(self.entries.len());
+        let mut keep = Vec::with_capacity(self.entries.len());
+        let mut buffer = Vec::new();
+
+        for entry in &self.entries {
+            buffer.clear();
+            entry
+                .binary_serialize::<_, E>(&mut buffer)
+                .map_err(Error::Serialize)?;
+
+            keep.push(seen.insert(ContentHash::of(&buffer)));
+        }
+
+        let original_len = self.entries.len();
+        let mut keep = keep.into_iter();
+        self.entries.retain(|_| keep.next().unwrap());
+
+        Ok(original_len - self.entries.len())
+    }
+}
+
+impl<T> Corpus<T>
+where
+    T: BinaryDeserialize,
+{
+    /// Imports every file in `dir` as an entry, such as packets extracted from a PCAP or
+    /// file-format samples. Files are deserialized with [BinaryDeserialize::binary_deserialize_lenient]
+    /// using the byte order `E`: fields missing from the end of a file are filled with their
+    /// defaults and any bytes left over are ignored. Files which still can't be deserialized are
+    /// skipped with a warning. Files are imported in name order so that the corpus is the same
+    /// for every run.
+    ///
+    /// Returns the number of entries imported.
+    pub fn import_raw<E: ByteOrder, P: AsRef<Path>>(&mut self, dir: P) -> io::Result<usize> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+
+        paths.sort();
+
+        let mut imported = 0;
+        for path in paths {
+            let data = fs::read(&path)?;
+            let mut remaining = data.as_slice();
+
+            match T::binary_deserialize_lenient::<_, E>(&mut remaining) {
+                Ok(entry) => {
+                    if !remaining.is_empty() {
+                        log::debug!(
+                            "ignoring {} trailing bytes in {}",
+                            remaining.len(),
+                            path.display()
+                        );
+                    }
+
+                    self.entries.push(entry);
+                    imported += 1;
+                }
+                Err(e) => {
+                    log::warn!("skipping {}: {}", path.display(), e);
+                }
+            }
+        }
+
+        Ok(imported)
+    }
+}
+
+/// Name of the index file inside a [CorpusStore]'s directory
+const INDEX_FILE_NAME: &str = "index";
+
+/// A 128-bit XXH3 hash of an entry's serialized bytes, used as its address in a [CorpusStore]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContentHash(pub u128);
+
+impl ContentHash {
+    pub fn of(data: &[u8]) -> ContentHash {
+        ContentHash(xxhash_rust::xxh3::xxh3_128(data))
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+impl FromStr for ContentHash {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u128::from_str_radix(s, 16).map(ContentHash)
+    }
+}
+
+/// Hashes are serialized as the same hex strings they're stored under
+#[cfg(feature = "serde_support")]
+impl Serialize for ContentHash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde_support")]
+impl<'de> Deserialize<'de> for ContentHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// How a [CorpusStore] writes new entries to disk. Entries written with a different setting
+/// can still be read, since every entry's encoding is recorded in the index.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum StoreCompression {
+    /// Entries are stored as-is
+    #[default]
+    None,
+    /// Entries are compressed with zstd at the given level (1-22)
+    #[cfg(feature = "zstd_support")]
+    Zstd(i32),
+}
+
+impl StoreCompression {
+    fn tag(self) -> &'static str {
+        match self {
+            StoreCompression::None => "raw",
+            #[cfg(feature = "zstd_support")]
+            StoreCompression::Zstd(_) => "zstd",
+        }
+    }
+
+    fn encode(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            StoreCompression::None => Ok(data.to_vec()),
+            #[cfg(feature = "zstd_support")]
+            StoreCompression::Zstd(level) => zstd::encode_all(data, level),
+        }
+    }
+}
+
+/// Where and how an entry of a [CorpusStore] is stored
+#[derive(Debug, Clone, Copy)]
+struct IndexEntry {
+    /// One of [StoreCompression::tag]
+    encoding: &'static str,
+    /// Length of the entry before it was compressed
+    len: usize,
+    /// Length of the entry's file
+    stored_len: usize,
+}
+
+/// A content-addressed, on-disk corpus.
+///
+/// Every entry is written to a file named after its [ContentHash] (in a subdirectory named after
+/// the hash's first byte, to keep directories small) and recorded in an append-only index file.
+/// Inserting bytes which are already in the store is a no-op, so campaigns can insert every
+/// interesting input without filling the disk with duplicates. Entries are optionally
+/// compressed (see [CorpusStore::set_compression]).
+#[derive(Debug)]
+pub struct CorpusStore {
+    dir: PathBuf,
+    compression: StoreCompression,
+    entries: HashMap<ContentHash, IndexEntry>,
+    /// Hashes in the order they were inserted
+    order: Vec<ContentHash>,
+    index: File,
+}
+
+impl CorpusStore {
+    /// Opens the store in `dir`, creating the directory and its index if they don't exist
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<CorpusStore> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let index_path = dir.join(INDEX_FILE_NAME);
+        let mut entries = HashMap::new();
+        let mut order = Vec::new();
+
+        if index_path.exists() {
+            for (line_number, line) in BufReader::new(File::open(&index_path)?).lines().enumerate()
+            {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+
+                let (hash, entry) = parse_index_line(&line).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "malformed line {} in {}",
+                            line_number + 1,
+                            index_path.display()
+                        ),
+                    )
+                })?;
+
+                if entries.insert(hash, entry).is_none() {
+                    order.push(hash);
+                }
+            }
+        }
+
+        let index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&index_path)?;
+
+        Ok(CorpusStore {
+            dir,
+            compression: StoreCompression::None,
+            entries,
+            order,
+            index,
+        })
+    }
+
+    /// Sets how entries inserted from now on are stored
+    pub fn set_compression(&mut self, compression: StoreCompression) {
+        self.compression = compression;
+    }
+
+    /// Stores `data` unless a byte-identical entry is already in the store. Returns the entry's
+    /// hash and whether it was newly inserted.
+    pub fn insert(&mut self, data: &[u8]) -> io::Result<(ContentHash, bool)> {
+        let hash = ContentHash::of(data);
+        if self.entries.contains_key(&hash) {
+            return Ok((hash, false));
+        }
+
+        let encoded = self.compression.encode(data)?;
+        let entry = IndexEntry {
+            encoding: self.compression.tag(),
+            len: data.len(),
+            stored_len: encoded.len(),
+        };
+
+        let path = self.entry_path(hash);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(&path, &encoded)?;
+
+        // the entry's file is written before it's indexed so that an interrupted insert never
+        // leaves the index pointing at a missing file
+        writeln!(
+            self.index,
+            "{} {} {} {}",
+            hash, entry.encoding, entry.len, entry.stored_len
+        )?;
+
+        self.entries.insert(hash, entry);
+        self.order.push(hash);
+
+        Ok((hash, true))
+    }
+
+    /// Serializes `value` with the byte order `E` and [inserts][CorpusStore::insert] it
+    pub fn insert_serialized<T: BinarySerialize, E: ByteOrder>(
+        &mut self,
+        value: &T,
+    ) -> io::Result<(ContentHash, bool)> {
+        let mut data = Vec::new();
+        value.binary_serialize::<_, E>(&mut data)?;
+
+        self.insert(&data)
+    }
+
+    pub fn contains(&self, hash: ContentHash) -> bool {
+        self.entries.contains_key(&hash)
+    }
+
+    /// Reads and decompresses an entry. Returns `None` if the store doesn't contain `hash`.
+    pub fn get(&self, hash: ContentHash) -> io::Result<Option<Vec<u8>>> {
+        let entry = match self.entries.get(&hash) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        let stored = fs::read(self.entry_path(hash))?;
+        let data = match entry.encoding {
+            "raw" => stored,
+            "zstd" => decode_zstd(&stored)?,
+            _ => unreachable!(),
+        };
+
+        if data.len() != entry.len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "entry {} is {} bytes but the index expects {}",
+                    hash,
+                    data.len(),
+                    entry.len
+                ),
+            ));
+        }
+
+        Ok(Some(data))
+    }
+
+    /// Hashes of the stored entries, in the order they were inserted
+    pub fn hashes(&self) -> &[ContentHash] {
+        &self.order
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Total size of the entries before and after compression, in bytes
+    pub fn disk_usage(&self) -> (usize, usize) {
+        self.entries
+            .values()
+            .fold((0, 0), |(len, stored_len), entry| {
+                (len + entry.len, stored_len + entry.stored_len)
+            })
+    }
+
+    /// Deserializes every entry with the byte order `E`, in insertion order. Entries which can't
+    /// be deserialized are skipped with a warning, like [Corpus::import_raw].
+    pub fn load<T: BinaryDeserialize, E: ByteOrder>(&self) -> io::Result<Corpus<T>> {
+        let mut corpus = Corpus::new();
+
+        for &hash in &self.order {
+            let data = self
+                .get(hash)?
+                .expect("indexed entry is missing from the store");
+            let mut remaining = data.as_slice();
+
+            match T::binary_deserialize_lenient::<_, E>(&mut remaining) {
+                Ok(entry) => corpus.push(entry),
+                Err(e) => log::warn!("skipping corpus entry {}: {}", hash, e),
+            }
+        }
+
+        Ok(corpus)
+    }
+
+    /// Like [CorpusStore::load], but fails with an [Error::Deserialize] on the first entry which
+    /// can't be deserialized instead of skipping it, or an [Error::CorpusIo] if an entry can't be
+    /// read
+    pub fn load_strict<T: BinaryDeserialize, E: ByteOrder>(&self) -> crate::Result<Corpus<T>> {
+        let mut corpus = Corpus::new();
+
+        for &hash in &self.order {
+            let data = self
+                .get(hash)
+                .map_err(|e| Error::corpus_io(self.entry_path(hash), e))?
+                .expect("indexed entry is missing from the store");
+
+            let entry = T::binary_deserialize_lenient::<_, E>(&mut data.as_slice())
+                .map_err(Error::deserialize::<T>)?;
+            corpus.push(entry);
+        }
+
+        Ok(corpus)
+    }
+
+    fn entry_path(&self, hash: ContentHash) -> PathBuf {
+        let name = hash.to_string();
+        self.dir.join(&name[..2]).join(name)
+    }
+}
+
+fn parse_index_line(line: &str) -> Option<(ContentHash, IndexEntry)> {
+    let mut parts = line.split(' ');
+
+    let hash = parts.next()?.parse().ok()?;
+    let encoding = match parts.next()? {
+        "raw" => "raw",
+        "zstd" => "zstd",
+        _ => return None,
+    };
+    let len = parts.next()?.parse().ok()?;
+    let stored_len = parts.next()?.parse().ok()?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some((
+        hash,
+        IndexEntry {
+            encoding,
+            len,
+            stored_len,
+        },
+    ))
+}
+
+#[cfg(feature = "zstd_support")]
+fn decode_zstd(stored: &[u8]) -> io::Result<Vec<u8>> {
+    zstd::decode_all(stored)
+}
+
+#[cfg(not(feature = "zstd_support"))]
+fn decode_zstd(_stored: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "corpus entry is zstd-compressed but lain was built without zstd_support",
+    ))
+}
@@ -87,3 +87,72 @@ dangerous_number!(u64, DANGEROUS_NUMBERS_U64);
 dangerous_number!(i64, DANGEROUS_NUMBERS_U64);
 dangerous_number!(f32, DANGEROUS_NUMBERS_F32);
 dangerous_number!(f64, DANGEROUS_NUMBERS_F64);
+
+// Values which tend to sit on either side of a bug: sign boundaries, small counts, buffer and
+// page sizes, and common magic numbers. Each table applies to integers of at least its width, and
+// values are wrapped to the target type (so -1 becomes 0xff for a u8).
+static INTERESTING_8: &[i128] = &[-128, -1, 0, 1, 16, 32, 64, 100, 127];
+
+static INTERESTING_16: &[i128] = &[-32768, -129, 128, 255, 256, 512, 1000, 1024, 4096, 32767];
+
+static INTERESTING_32: &[i128] = &[
+    -2_147_483_648,
+    -100_663_046,
+    -32769,
+    32768,
+    65535,
+    65536,
+    100_663_045,
+    2_147_483_647,
+    0x0020_0000, // 2MiB huge page
+    0xdead_beef,
+    0xcafe_babe,
+    0xfeed_face,
+];
+
+static INTERESTING_64: &[i128] = &[
+    -9_223_372_036_854_775_808,
+    -2_147_483_649,
+    2_147_483_648,
+    4_294_967_295,
+    4_294_967_296,
+    0x4000_0000, // 1GiB huge page
+    0xdead_beef_dead_beef,
+    9_223_372_036_854_775_807,
+];
+
+/// Picks one of the built-in interesting values for an integer which is `bits` wide. Along with
+/// the tables above, this includes the values around the type's bounds and sign boundary (0, 1,
+/// MAX, MAX-1, 0x7f.., 0x80.., etc).
+pub(crate) fn select_interesting_value<R: Rng>(rng: &mut R, bits: usize) -> i128 {
+    let half = 1i128 << (bits - 1);
+    let boundaries = [0, 1, -1, -2, half - 2, half - 1, half, half + 1];
+
+    let tables = [
+        (8, INTERESTING_8),
+        (16, INTERESTING_16),
+        (32, INTERESTING_32),
+        (64, INTERESTING_64),
+    ];
+
+    let total = tables
+        .iter()
+        .filter(|(width, _)| *width <= bits)
+        .fold(boundaries.len(), |sum, (_, table)| sum + table.len());
+
+    let mut idx = rng.gen_range(0, total);
+    if idx < boundaries.len() {
+        return boundaries[idx];
+    }
+
+    idx -= boundaries.len();
+    for (_, table) in tables.iter().filter(|(width, _)| *width <= bits) {
+        if idx < table.len() {
+            return table[idx];
+        }
+
+        idx -= table.len();
+    }
+
+    unreachable!()
+}
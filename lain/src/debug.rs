@@ -0,0 +1,472 @@
+use crate::traits::*;
+use crate::types::*;
+
+use num_traits::{Bounded, NumCast, PrimInt};
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::marker::PhantomData;
+
+/// The max number of elements of a collection that are printed
+const MAX_DEBUG_ELEMENTS: usize = 16;
+
+/// The max number of bytes shown in the hex preview of a byte buffer
+const MAX_HEX_PREVIEW_BYTES: usize = 16;
+
+/// Helper trait for getting the serialized size of a value if its type implements
+/// [SerializedSize]
+pub trait SerializedSizeHint {
+    fn serialized_size_hint(&self) -> Option<usize>;
+}
+
+impl<T: ?Sized> SerializedSizeHint for T {
+    default fn serialized_size_hint(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl<T: ?Sized + SerializedSize> SerializedSizeHint for T {
+    fn serialized_size_hint(&self) -> Option<usize> {
+        Some(self.serialized_size())
+    }
+}
+
+/// Writes a single line of a [FuzzedDebug] tree
+pub fn write_node(
+    output: &mut String,
+    depth: usize,
+    path: &str,
+    type_name: &str,
+    size: Option<usize>,
+    value: Option<&str>,
+) {
+    for _i in 0..depth {
+        output.push_str("  ");
+    }
+
+    if path.is_empty() {
+        output.push_str(type_name);
+    } else {
+        write!(output, "{}: {}", path, type_name).ok();
+    }
+
+    if let Some(size) = size {
+        write!(output, " ({} bytes)", size).ok();
+    }
+
+    if let Some(value) = value {
+        write!(output, " = {}", value).ok();
+    }
+
+    output.push('\n');
+}
+
+/// Joins a parent path and a child field name or index
+pub fn child_path(parent: &str, child: &str) -> String {
+    if parent.is_empty() {
+        child.to_string()
+    } else if child.starts_with('[') {
+        format!("{}{}", parent, child)
+    } else {
+        format!("{}.{}", parent, child)
+    }
+}
+
+/// Returns the name of `T` with all module paths stripped (e.g. `Vec<Header>` instead of
+/// `alloc::vec::Vec<my_crate::Header>`)
+pub fn short_type_name<T: ?Sized>() -> String {
+    let full_name = std::any::type_name::<T>();
+
+    let mut short_name = String::with_capacity(full_name.len());
+    let mut segment_start = 0;
+    let mut chars = full_name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            // drop everything in the path up to this point
+            short_name.truncate(segment_start);
+            continue;
+        }
+
+        short_name.push(c);
+
+        if !(c.is_alphanumeric() || c == '_') {
+            segment_start = short_name.len();
+        }
+    }
+
+    short_name
+}
+
+/// Helper trait so that byte buffers can be printed as a hex preview while other collections
+/// print each element
+pub trait DebugElements {
+    fn debug_elements(&self, type_name: &str, path: &str, depth: usize, output: &mut String);
+}
+
+impl<T: FuzzedDebug> DebugElements for [T] {
+    default fn debug_elements(
+        &self,
+        type_name: &str,
+        path: &str,
+        depth: usize,
+        output: &mut String,
+    ) {
+        let len = format!("{} elements", self.len());
+        write_node(
+            output,
+            depth,
+            path,
+            type_name,
+            self.serialized_size_hint(),
+            Some(&len),
+        );
+
+        for (i, item) in self.iter().take(MAX_DEBUG_ELEMENTS).enumerate() {
+            item.fuzzed_debug(&child_path(path, &format!("[{}]", i)), depth + 1, output);
+        }
+
+        if self.len() > MAX_DEBUG_ELEMENTS {
+            for _i in 0..depth + 1 {
+                output.push_str("  ");
+            }
+
+            writeln!(output, "... {} more", self.len() - MAX_DEBUG_ELEMENTS).ok();
+        }
+    }
+}
+
+impl DebugElements for [u8] {
+    fn debug_elements(&self, type_name: &str, path: &str, depth: usize, output: &mut String) {
+        let mut preview = String::new();
+        for b in self.iter().take(MAX_HEX_PREVIEW_BYTES) {
+            write!(preview, "{:02X} ", b).ok();
+        }
+
+        if self.len() > MAX_HEX_PREVIEW_BYTES {
+            preview.push_str("...");
+        } else {
+            preview.pop();
+        }
+
+        write_node(
+            output,
+            depth,
+            path,
+            type_name,
+            Some(self.len()),
+            Some(&preview),
+        );
+    }
+}
+
+impl<T: FuzzedDebug> FuzzedDebug for [T] {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        self.debug_elements(&short_type_name::<Self>(), path, depth, output);
+    }
+}
+
+impl<T: FuzzedDebug> FuzzedDebug for Vec<T> {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        self.as_slice()
+            .debug_elements(&short_type_name::<Self>(), path, depth, output);
+    }
+}
+
+impl<T, I> FuzzedDebug for UnsafeEnum<T, I>
+where
+    T: FuzzedDebug,
+    I: FuzzedDebug,
+{
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        let type_name = short_type_name::<Self>();
+        let size = self.serialized_size_hint();
+
+        match *self {
+            UnsafeEnum::Valid(ref value) => {
+                write_node(output, depth, path, &type_name, size, Some("Valid"));
+                value.fuzzed_debug(&child_path(path, "0"), depth + 1, output);
+            }
+            UnsafeEnum::Invalid(ref value) => {
+                write_node(output, depth, path, &type_name, size, Some("Invalid"));
+                value.fuzzed_debug(&child_path(path, "0"), depth + 1, output);
+            }
+        }
+    }
+}
+
+impl<T: FuzzedDebug> FuzzedDebug for Option<T> {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        let type_name = short_type_name::<Self>();
+        let size = self.serialized_size_hint();
+
+        match *self {
+            Some(ref value) => {
+                write_node(output, depth, path, &type_name, size, Some("Some"));
+                value.fuzzed_debug(&child_path(path, "0"), depth + 1, output);
+            }
+            None => write_node(output, depth, path, &type_name, size, Some("None")),
+        }
+    }
+}
+
+impl<T, E> FuzzedDebug for Result<T, E>
+where
+    T: FuzzedDebug,
+    E: FuzzedDebug,
+{
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        let type_name = short_type_name::<Self>();
+        let size = self.serialized_size_hint();
+
+        match *self {
+            Ok(ref value) => {
+                write_node(output, depth, path, &type_name, size, Some("Ok"));
+                value.fuzzed_debug(&child_path(path, "0"), depth + 1, output);
+            }
+            Err(ref value) => {
+                write_node(output, depth, path, &type_name, size, Some("Err"));
+                value.fuzzed_debug(&child_path(path, "0"), depth + 1, output);
+            }
+        }
+    }
+}
+
+impl<'a> FuzzedDebug for Cow<'a, [u8]> {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        (**self).debug_elements(&short_type_name::<Self>(), path, depth, output);
+    }
+}
+
+impl<T: FuzzedDebug, C> FuzzedDebug for TextEncoded<T, C> {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        let flaw = self.flaw.map(|flaw| format!("{:?}", flaw));
+        write_node(
+            output,
+            depth,
+            path,
+            &short_type_name::<Self>(),
+            self.serialized_size_hint(),
+            flaw.as_deref(),
+        );
+
+        self.value
+            .fuzzed_debug(&child_path(path, "value"), depth + 1, output);
+    }
+}
+
+impl<M: FuzzedDebug> FuzzedDebug for Conversation<M> {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        self.messages()
+            .debug_elements(&short_type_name::<Self>(), path, depth, output);
+    }
+}
+
+impl<T: FuzzedDebug> FuzzedDebug for OneOf<T> {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        self.value().fuzzed_debug(path, depth, output);
+    }
+}
+
+impl<T: FuzzedDebug> FuzzedDebug for PointerAligned<T> {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        self.0.fuzzed_debug(path, depth, output);
+    }
+}
+
+impl FuzzedDebug for AsciiString {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        let value: String = self.inner.iter().map(|c| c.0).collect();
+        debug_string(&value, "AsciiString", path, depth, output);
+    }
+}
+
+impl FuzzedDebug for Utf8String {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        let value: String = self.inner.iter().map(|c| c.0).collect();
+        debug_string(&value, "Utf8String", path, depth, output);
+    }
+}
+
+impl<const N: usize, const PAD: u8> FuzzedDebug for FixedLengthString<N, PAD> {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        debug_string(
+            &self.to_string_lossy(),
+            &short_type_name::<Self>(),
+            path,
+            depth,
+            output,
+        );
+    }
+}
+
+impl FuzzedDebug for String {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        debug_string(self, "String", path, depth, output);
+    }
+}
+
+fn debug_string(value: &str, type_name: &str, path: &str, depth: usize, output: &mut String) {
+    write_node(
+        output,
+        depth,
+        path,
+        type_name,
+        Some(value.len()),
+        Some(&format!("{:?}", value)),
+    );
+}
+
+macro_rules! impl_fuzzed_debug_int {
+    ( $($name:ident),* ) => {
+        $(
+            impl FuzzedDebug for $name {
+                fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+                    let value = format!("{} ({:#X})", self, self);
+                    write_node(output, depth, path, stringify!($name), self.serialized_size_hint(), Some(&value));
+                }
+            }
+        )*
+    }
+}
+
+impl_fuzzed_debug_int!(i64, u64, i32, u32, i16, u16, i8, u8);
+
+macro_rules! impl_fuzzed_debug_leaf {
+    ( $($name:ident),* ) => {
+        $(
+            impl FuzzedDebug for $name {
+                fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+                    let value = format!("{:?}", self);
+                    write_node(output, depth, path, stringify!($name), self.serialized_size_hint(), Some(&value));
+                }
+            }
+        )*
+    }
+}
+
+impl_fuzzed_debug_leaf!(f32, f64, bool, char);
+
+macro_rules! impl_fuzzed_debug_narrow_int {
+    ( $($name:ident),* ) => {
+        $(
+            impl FuzzedDebug for $name {
+                fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+                    let value = format!("{} ({:#X})", self.0, self.0);
+                    write_node(output, depth, path, stringify!($name), self.serialized_size_hint(), Some(&value));
+                }
+            }
+        )*
+    }
+}
+
+impl_fuzzed_debug_narrow_int!(U24, U48, TargetUsize, TargetIsize);
+
+macro_rules! impl_fuzzed_debug_display {
+    ( $($name:ident),* ) => {
+        $(
+            impl FuzzedDebug for $name {
+                fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+                    let value = self.to_string();
+                    write_node(output, depth, path, stringify!($name), self.serialized_size_hint(), Some(&value));
+                }
+            }
+        )*
+    }
+}
+
+impl_fuzzed_debug_display!(Ipv4Address, Ipv6Address, MacAddress, Uuid);
+impl_fuzzed_debug_display!(UnixTimestamp32, UnixTimestamp64, DosDateTime, FileTime);
+
+impl<T, F> FuzzedDebug for Scaled<T, F>
+where
+    T: NumCast + Bounded + Copy + std::fmt::Debug,
+    F: ScaleFactor,
+{
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        let value = format!("{:?}", self);
+        write_node(
+            output,
+            depth,
+            path,
+            &short_type_name::<Self>(),
+            self.serialized_size_hint(),
+            Some(&value),
+        );
+    }
+}
+
+impl<T: PrimInt + std::fmt::Debug> FuzzedDebug for Offset<T> {
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        let value = format!("{:#x}", self.value().to_u64().unwrap_or(0));
+        write_node(
+            output,
+            depth,
+            path,
+            &short_type_name::<Self>(),
+            self.serialized_size_hint(),
+            Some(&value),
+        );
+    }
+}
+
+macro_rules! impl_fuzzed_debug_array {
+    ( $($size:expr),* ) => {
+        $(
+            impl<T> FuzzedDebug for [T; $size]
+            where T: FuzzedDebug {
+                #[inline(always)]
+                fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+                    // Treat this as a slice
+                    self[0..].debug_elements(&short_type_name::<Self>(), path, depth, output);
+                }
+            }
+        )*
+    }
+}
+
+impl_fuzzed_debug_array!(
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
+    51, 52, 53, 54, 55, 56, 57, 58, 59, 60
+);
+
+macro_rules! impl_fuzzed_debug_tuple {
+    ( $($name:ident $idx:tt),+ ) => {
+        impl<$($name),+> FuzzedDebug for ($($name,)+)
+        where
+            $($name: FuzzedDebug),+
+        {
+            fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+                write_node(
+                    output,
+                    depth,
+                    path,
+                    &short_type_name::<Self>(),
+                    self.serialized_size_hint(),
+                    None,
+                );
+
+                $(
+                    self.$idx.fuzzed_debug(&child_path(path, stringify!($idx)), depth + 1, output);
+                )+
+            }
+        }
+    }
+}
+
+for_each_tuple!(impl_fuzzed_debug_tuple);
+
+macro_rules! impl_fuzzed_debug_zero_sized {
+    ( $($name:ty $(, $generic:ident)?);* ) => {
+        $(
+            impl$(<$generic>)? FuzzedDebug for $name {
+                fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+                    write_node(output, depth, path, &short_type_name::<Self>(), Some(0), None);
+                }
+            }
+        )*
+    }
+}
+
+impl_fuzzed_debug_zero_sized!((); PhantomData<T>, T; FieldSwap);
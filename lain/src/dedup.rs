@@ -0,0 +1,351 @@
+//! Bucketing crashes by the structure of the input which caused them.
+//!
+//! Mutations which only change the raw bytes of an input tend to hit the same bug over and over,
+//! and every hit would otherwise be saved as its own artifact. A [CrashSignature] identifies a
+//! crash by the [shape](InputShape) of its input -- the enum variants it holds and which optional
+//! fields are present -- and optionally a hash of the target's stack at the crash. [CrashBuckets]
+//! keeps one representative input per signature along with the number of crashes seen for it.
+//!
+//! ```
+//! # #![feature(specialization)]
+//! use lain::prelude::*;
+//! use lain::dedup::CrashBuckets;
+//!
+//! #[derive(Debug, Clone, BinarySerialize)]
+//! #[lain(input_shape)]
+//! enum Command {
+//!     Read(u32),
+//!     Write(u32, u8),
+//! }
+//!
+//! let mut crashes = CrashBuckets::new();
+//!
+//! // different addresses, but the same structure
+//! assert!(crashes.insert(Command::Read(0x1000), None));
+//! assert!(!crashes.insert(Command::Read(0x2000), None));
+//! assert!(crashes.insert(Command::Write(0x1000, 0xff), None));
+//!
+//! assert_eq!(crashes.len(), 2);
+//! assert_eq!(crashes.crash_count(), 3);
+//! ```
+
+use crate::traits::{BinarySerialize, InputShape};
+use crate::types::{Conversation, TextEncoded, UnsafeEnum};
+use byteorder::ByteOrder;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+use xxhash_rust::xxh3::Xxh3Default;
+
+/// Returns the hash of `value`'s [shape](InputShape). The hash is stable across runs and
+/// platforms, so it can be used to name artifacts.
+pub fn shape_hash<T: ?Sized + InputShape>(value: &T) -> u64 {
+    let mut hasher = Xxh3Default::new();
+    value.hash_shape(&mut hasher);
+
+    hasher.finish()
+}
+
+/// Hashes the innermost `max_frames` frames of a stack trace (e.g. the function names from a
+/// sanitizer report), innermost first. Frames further out are usually shared by unrelated crashes
+/// and are ignored.
+pub fn stack_hash<S: AsRef<str>>(frames: &[S], max_frames: usize) -> u64 {
+    let mut hasher = Xxh3Default::new();
+    for frame in frames.iter().take(max_frames) {
+        hasher.write(frame.as_ref().as_bytes());
+        // separates the frames so that ["ab", "c"] and ["a", "bc"] differ
+        hasher.write_u8(0);
+    }
+
+    hasher.finish()
+}
+
+/// Identifies the crashes which are likely to be the same bug
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CrashSignature {
+    /// [shape_hash] of the crashing input
+    pub shape: u64,
+    /// Hash of the target's stack at the crash, if it's known (see [stack_hash])
+    pub stack: Option<u64>,
+}
+
+impl CrashSignature {
+    pub fn new<T: ?Sized + InputShape>(input: &T, stack: Option<u64>) -> CrashSignature {
+        CrashSignature {
+            shape: shape_hash(input),
+            stack,
+        }
+    }
+}
+
+impl fmt::Display for CrashSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.shape)?;
+
+        if let Some(stack) = self.stack {
+            write!(f, "-{:016x}", stack)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The crashes which share a [CrashSignature]
+#[derive(Debug, Clone)]
+pub struct CrashBucket<T> {
+    pub signature: CrashSignature,
+    /// The first input seen with this signature
+    pub representative: T,
+    /// Number of crashes seen with this signature
+    pub count: usize,
+}
+
+/// Crashing inputs grouped by their [CrashSignature]. Buckets are kept in the order their first
+/// crash was inserted.
+#[derive(Debug, Clone)]
+pub struct CrashBuckets<T> {
+    buckets: Vec<CrashBucket<T>>,
+    index: HashMap<CrashSignature, usize>,
+}
+
+impl<T> Default for CrashBuckets<T> {
+    fn default() -> Self {
+        CrashBuckets {
+            buckets: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<T> CrashBuckets<T> {
+    pub fn new() -> CrashBuckets<T> {
+        Default::default()
+    }
+
+    /// Adds a crash with the given signature. Returns `true` if it's the first crash with this
+    /// signature, in which case `input` becomes the bucket's representative.
+    pub fn insert_with_signature(&mut self, signature: CrashSignature, input: T) -> bool {
+        if let Some(&index) = self.index.get(&signature) {
+            self.buckets[index].count += 1;
+            return false;
+        }
+
+        self.index.insert(signature, self.buckets.len());
+        self.buckets.push(CrashBucket {
+            signature,
+            representative: input,
+            count: 1,
+        });
+
+        true
+    }
+
+    /// Returns the bucket for `signature`
+    pub fn get(&self, signature: &CrashSignature) -> Option<&CrashBucket<T>> {
+        self.index.get(signature).map(|&index| &self.buckets[index])
+    }
+
+    /// The buckets in the order their first crash was inserted
+    pub fn buckets(&self) -> &[CrashBucket<T>] {
+        &self.buckets
+    }
+
+    /// Number of distinct signatures
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// Number of crashes inserted across all buckets
+    pub fn crash_count(&self) -> usize {
+        self.buckets.iter().map(|bucket| bucket.count).sum()
+    }
+}
+
+impl<T: InputShape> CrashBuckets<T> {
+    /// Adds a crash, bucketing it by the shape of `input` and `stack` (see [CrashSignature]).
+    /// Returns `true` if it's the first crash in its bucket.
+    pub fn insert(&mut self, input: T, stack: Option<u64>) -> bool {
+        let signature = CrashSignature::new(&input, stack);
+
+        self.insert_with_signature(signature, input)
+    }
+}
+
+impl<T: BinarySerialize> CrashBuckets<T> {
+    /// Writes the representative of every bucket to `dir` (which is created if it doesn't
+    /// exist), serialized with the byte order `E`. Files are named `crash-<signature>`, so
+    /// artifacts written by earlier runs are overwritten rather than duplicated. Returns the
+    /// number of files written.
+    pub fn write_artifacts<E: ByteOrder, P: AsRef<Path>>(&self, dir: P) -> io::Result<usize> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut buffer = Vec::new();
+        for bucket in &self.buckets {
+            buffer.clear();
+            bucket
+                .representative
+                .binary_serialize::<_, E>(&mut buffer)?;
+
+            fs::write(dir.join(format!("crash-{}", bucket.signature)), &buffer)?;
+        }
+
+        Ok(self.buckets.len())
+    }
+}
+
+impl<T> InputShape for [T]
+where
+    T: InputShape,
+{
+    fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        // runs of elements with the same shape count once, so that repeating an element doesn't
+        // create a new shape
+        let mut previous = None;
+        for element in self {
+            let shape = shape_hash(element);
+            if previous != Some(shape) {
+                state.write_u64(shape);
+                previous = Some(shape);
+            }
+        }
+    }
+}
+
+impl<T> InputShape for Vec<T>
+where
+    T: InputShape,
+{
+    fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash_shape(state);
+    }
+}
+
+impl<M> InputShape for Conversation<M>
+where
+    M: InputShape,
+{
+    fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        self.messages().hash_shape(state);
+    }
+}
+
+impl<T> InputShape for Box<T>
+where
+    T: ?Sized + InputShape,
+{
+    fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        (**self).hash_shape(state);
+    }
+}
+
+impl<T> InputShape for Option<T>
+where
+    T: InputShape,
+{
+    fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            Some(ref value) => {
+                state.write_u8(1);
+                value.hash_shape(state);
+            }
+            None => state.write_u8(0),
+        }
+    }
+}
+
+impl<T, E> InputShape for Result<T, E>
+where
+    T: InputShape,
+    E: InputShape,
+{
+    fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            Ok(ref value) => {
+                state.write_u8(0);
+                value.hash_shape(state);
+            }
+            Err(ref err) => {
+                state.write_u8(1);
+                err.hash_shape(state);
+            }
+        }
+    }
+}
+
+impl<T, I> InputShape for UnsafeEnum<T, I>
+where
+    T: InputShape,
+{
+    fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            UnsafeEnum::Valid(ref value) => {
+                state.write_u8(0);
+                value.hash_shape(state);
+            }
+            // invalid discriminants are all the same shape
+            UnsafeEnum::Invalid(_) => state.write_u8(1),
+        }
+    }
+}
+
+impl<T, C> InputShape for TextEncoded<T, C>
+where
+    T: InputShape,
+{
+    fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        self.value.hash_shape(state);
+
+        match self.flaw {
+            Some(ref flaw) => {
+                state.write_u8(1);
+                std::mem::discriminant(flaw).hash(state);
+            }
+            None => state.write_u8(0),
+        }
+    }
+}
+
+macro_rules! impl_input_shape_array {
+    ( $($size:expr),* ) => {
+        $(
+            impl<T> InputShape for [T; $size]
+            where T: InputShape {
+                #[inline(always)]
+                fn hash_shape<H: Hasher>(&self, state: &mut H) {
+                    self[0..].hash_shape(state);
+                }
+            }
+        )*
+    }
+}
+
+impl_input_shape_array!(
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
+    51, 52, 53, 54, 55, 56, 57, 58, 59, 60
+);
+
+macro_rules! impl_input_shape_tuple {
+    ( $($name:ident $idx:tt),+ ) => {
+        impl<$($name),+> InputShape for ($($name,)+)
+        where
+            $($name: InputShape),+
+        {
+            fn hash_shape<H: Hasher>(&self, state: &mut H) {
+                $(
+                    self.$idx.hash_shape(state);
+                )+
+            }
+        }
+    }
+}
+
+for_each_tuple!(impl_input_shape_tuple);
@@ -0,0 +1,22 @@
+//! Markers which the derive macros reference when a type uses one of the standalone attributes
+//! that have been folded into `#[lain(...)]`. Rust has no way for a derive to emit a warning on
+//! its own, so the reference is spanned to the attribute to make the compiler warn there.
+
+#[deprecated(
+    note = "`#[fuzzer(...)]` has been replaced by `#[lain(...)]`, e.g. `#[lain(min = 0)]`"
+)]
+pub const FUZZER: () = ();
+
+#[deprecated(note = "`#[bitfield(...)]` has been replaced by `#[lain(bitfield(...))]`")]
+pub const BITFIELD: () = ();
+
+#[deprecated(note = "`#[byteorder(...)]` has been replaced by `#[lain(byteorder(...))]`")]
+pub const BYTEORDER: () = ();
+
+#[deprecated(note = "`#[weight(...)]` has been replaced by `#[lain(weight(...))]`")]
+pub const WEIGHT: () = ();
+
+#[deprecated(
+    note = "`#[serialized_size(16)]` has been replaced by `#[lain(serialized_size = 16)]`"
+)]
+pub const SERIALIZED_SIZE: () = ();
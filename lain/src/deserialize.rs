@@ -0,0 +1,416 @@
+use crate::target::{PointerWidth, TargetConfig};
+use crate::traits::*;
+use crate::types::{
+    Conversation, DosDateTime, FieldSwap, FileTime, FixedLengthString, Ipv4Address, Ipv6Address,
+    MacAddress, Offset, OneOf, PointerAligned, ScaleFactor, Scaled, TargetIsize, TargetUsize,
+    TextEncoded, TextEncoding, UnixTimestamp32, UnixTimestamp64, UnsafeEnum, Uuid, U24, U48,
+};
+use byteorder::{ByteOrder, ReadBytesExt};
+use num_traits::{Bounded, NumCast, PrimInt};
+use std::borrow::Cow;
+use std::io::{self, Read};
+use std::marker::PhantomData;
+
+/// Returns the value used for a field which is missing from the end of the input when
+/// deserializing leniently. Types without a `Default` impl can't be filled in, so the
+/// `UnexpectedEof` error is returned instead.
+pub trait MissingFieldDefault: Sized {
+    fn missing_field_default() -> io::Result<Self>;
+}
+
+impl<T> MissingFieldDefault for T {
+    default fn missing_field_default() -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "input ended before a field with no default value",
+        ))
+    }
+}
+
+impl<T> MissingFieldDefault for T
+where
+    T: Default,
+{
+    fn missing_field_default() -> io::Result<Self> {
+        Ok(T::default())
+    }
+}
+
+/// Leniently deserializes a field, substituting its default value if the input has ended
+#[inline(always)]
+pub fn deserialize_field_lenient<T, R, E>(buffer: &mut R) -> io::Result<T>
+where
+    T: BinaryDeserialize,
+    R: Read,
+    E: ByteOrder,
+{
+    match T::binary_deserialize_lenient::<R, E>(buffer) {
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => T::missing_field_default(),
+        result => result,
+    }
+}
+
+impl BinaryDeserialize for bool {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        // non-binary booleans written by unsafe mutations are normalized to `true`
+        Ok(buffer.read_u8()? != 0)
+    }
+}
+
+impl BinaryDeserialize for u8 {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        buffer.read_u8()
+    }
+}
+
+impl BinaryDeserialize for i8 {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        buffer.read_i8()
+    }
+}
+
+macro_rules! impl_binary_deserialize {
+    ( $($name:ident => $method:ident),* ) => {
+        $(
+            impl BinaryDeserialize for $name {
+                #[inline(always)]
+                fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+                    buffer.$method::<E>()
+                }
+            }
+        )*
+    }
+}
+
+impl_binary_deserialize!(
+    i64 => read_i64,
+    u64 => read_u64,
+    i32 => read_i32,
+    u32 => read_u32,
+    i16 => read_i16,
+    u16 => read_u16,
+    f32 => read_f32,
+    f64 => read_f64
+);
+
+impl BinaryDeserialize for U24 {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Ok(U24(buffer.read_u24::<E>()?))
+    }
+}
+
+impl BinaryDeserialize for U48 {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Ok(U48(buffer.read_u48::<E>()?))
+    }
+}
+
+macro_rules! impl_binary_deserialize_octets {
+    ( $($name:ident => $len:expr),* ) => {
+        $(
+            impl BinaryDeserialize for $name {
+                #[inline(always)]
+                fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+                    let mut octets = [0u8; $len];
+                    buffer.read_exact(&mut octets)?;
+
+                    Ok($name(octets))
+                }
+            }
+        )*
+    }
+}
+
+impl_binary_deserialize_octets!(Ipv4Address => 4, Ipv6Address => 16, MacAddress => 6, Uuid => 16);
+
+impl<const N: usize, const PAD: u8> BinaryDeserialize for FixedLengthString<N, PAD> {
+    /// Reads the whole field and strips the padding from the end of it
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let mut bytes = vec![0u8; N];
+        buffer.read_exact(&mut bytes)?;
+
+        let len = bytes.iter().rposition(|&b| b != PAD).map_or(0, |i| i + 1);
+        bytes.truncate(len);
+
+        Ok(FixedLengthString { bytes })
+    }
+}
+
+impl BinaryDeserialize for UnixTimestamp32 {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Ok(UnixTimestamp32(buffer.read_i32::<E>()?))
+    }
+}
+
+impl BinaryDeserialize for UnixTimestamp64 {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Ok(UnixTimestamp64(buffer.read_i64::<E>()?))
+    }
+}
+
+impl BinaryDeserialize for DosDateTime {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let time = buffer.read_u16::<E>()?;
+        let date = buffer.read_u16::<E>()?;
+
+        Ok(DosDateTime { date, time })
+    }
+}
+
+impl BinaryDeserialize for FileTime {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let low = buffer.read_u32::<E>()?;
+        let high = buffer.read_u32::<E>()?;
+
+        Ok(FileTime((high as u64) << 32 | low as u64))
+    }
+}
+
+impl<T, F> BinaryDeserialize for Scaled<T, F>
+where
+    T: BinaryDeserialize + NumCast + Bounded + Copy,
+    F: ScaleFactor,
+{
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Ok(Scaled::from_raw(T::binary_deserialize::<_, E>(buffer)?))
+    }
+}
+
+impl<T: BinaryDeserialize + PrimInt> BinaryDeserialize for Offset<T> {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Ok(Offset::new(T::binary_deserialize::<_, E>(buffer)?))
+    }
+}
+
+impl BinaryDeserialize for TargetUsize {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Ok(TargetUsize(TargetConfig::current().read_pointer(buffer)?))
+    }
+}
+
+impl BinaryDeserialize for TargetIsize {
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let config = TargetConfig::current();
+        let value = config.read_pointer(buffer)?;
+
+        // 32-bit values are sign-extended
+        Ok(TargetIsize(match config.pointer_width {
+            PointerWidth::Bits32 => value as u32 as i32 as i64,
+            PointerWidth::Bits64 => value as i64,
+        }))
+    }
+}
+
+impl<T: BinaryDeserialize + SerializedSize> BinaryDeserialize for PointerAligned<T> {
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let value = T::binary_deserialize::<_, E>(buffer)?;
+        let padding = TargetConfig::current().padding_after(value.serialized_size());
+        buffer.read_exact(&mut [0u8; 8][..padding])?;
+
+        Ok(PointerAligned(value))
+    }
+}
+
+impl<M: BinaryDeserialize> BinaryDeserialize for Conversation<M> {
+    /// Reads messages until the input is exhausted, without resolving their references
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Ok(Conversation::new(Vec::binary_deserialize::<_, E>(buffer)?))
+    }
+}
+
+impl<T: BinaryDeserialize> BinaryDeserialize for OneOf<T> {
+    /// The value read has no candidates to pick from when mutated
+    #[inline(always)]
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Ok(OneOf::with_value(
+            T::binary_deserialize::<_, E>(buffer)?,
+            Default::default(),
+        ))
+    }
+}
+
+impl<T> BinaryDeserialize for Vec<T>
+where
+    T: BinaryDeserialize,
+{
+    /// Reads items until the input is exhausted. An incomplete trailing item is discarded.
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let mut items = Vec::new();
+
+        loop {
+            match T::binary_deserialize::<R, E>(buffer) {
+                Ok(item) => items.push(item),
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(items),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<'a> BinaryDeserialize for Cow<'a, [u8]> {
+    /// Reads the rest of the input
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        buffer.read_to_end(&mut bytes)?;
+
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+impl<T, C> BinaryDeserialize for TextEncoded<T, C>
+where
+    T: BinaryDeserialize,
+    C: TextEncoding,
+{
+    /// Reads the rest of the input as text and deserializes the value from the bytes it encodes
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let mut text = Vec::new();
+        buffer.read_to_end(&mut text)?;
+
+        let data = C::decode(&text)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid text encoding"))?;
+
+        Ok(TextEncoded::new(T::binary_deserialize::<_, E>(
+            &mut data.as_slice(),
+        )?))
+    }
+}
+
+impl BinaryDeserialize for String {
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        buffer.read_to_end(&mut bytes)?;
+
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl<T, I> BinaryDeserialize for UnsafeEnum<T, I>
+where
+    T: BinaryDeserialize,
+    I: BinaryDeserialize + BinarySerialize,
+{
+    /// Reads the underlying primitive and interprets it as `T` if possible, otherwise it is kept
+    /// as an `UnsafeEnum::Invalid` value
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let value = I::binary_deserialize::<R, E>(buffer)?;
+
+        let mut bytes = Vec::new();
+        value.binary_serialize::<_, E>(&mut bytes)?;
+
+        match T::binary_deserialize::<_, E>(&mut bytes.as_slice()) {
+            Ok(valid) => Ok(UnsafeEnum::Valid(valid)),
+            Err(_) => Ok(UnsafeEnum::Invalid(value)),
+        }
+    }
+}
+
+/// Tracks how many items of an array being deserialized have been written, and drops them if a
+/// later item fails to deserialize (or panics) since `MaybeUninit` won't drop them on its own
+struct PartialArray<T> {
+    items: *mut T,
+    initialized: usize,
+}
+
+impl<T> PartialArray<T> {
+    /// Called once every item has been written, since ownership passes to the array
+    fn finish(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl<T> Drop for PartialArray<T> {
+    fn drop(&mut self) {
+        unsafe {
+            std::ptr::drop_in_place(std::ptr::slice_from_raw_parts_mut(
+                self.items,
+                self.initialized,
+            ));
+        }
+    }
+}
+
+macro_rules! impl_binary_deserialize_array {
+    ( $($size:expr),* ) => {
+        $(
+            impl<T> BinaryDeserialize for [T; $size]
+            where T: BinaryDeserialize {
+                fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+                    let mut array = std::mem::MaybeUninit::<[T; $size]>::uninit();
+                    let mut partial = PartialArray {
+                        items: array.as_mut_ptr() as *mut T,
+                        initialized: 0,
+                    };
+
+                    for i in 0..$size {
+                        let item = T::binary_deserialize::<R, E>(buffer)?;
+
+                        unsafe {
+                            std::ptr::write(partial.items.add(i), item);
+                        }
+                        partial.initialized += 1;
+                    }
+
+                    partial.finish();
+
+                    Ok(unsafe { array.assume_init() })
+                }
+            }
+        )*
+    }
+}
+
+impl_binary_deserialize_array!(
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
+    51, 52, 53, 54, 55, 56, 57, 58, 59, 60
+);
+
+macro_rules! impl_binary_deserialize_tuple {
+    ( $($name:ident $idx:tt),+ ) => {
+        impl<$($name),+> BinaryDeserialize for ($($name,)+)
+        where
+            $($name: BinaryDeserialize),+
+        {
+            fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+                Ok(($($name::binary_deserialize::<R, E>(buffer)?,)+))
+            }
+
+            fn binary_deserialize_lenient<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+                // elements past the end of the input are defaulted, just like trailing fields of
+                // derived structs
+                Ok(($(deserialize_field_lenient::<$name, R, E>(buffer)?,)+))
+            }
+        }
+    }
+}
+
+for_each_tuple!(impl_binary_deserialize_tuple);
+
+macro_rules! impl_binary_deserialize_zero_sized {
+    ( $($name:ty $(, $generic:ident)?);* ) => {
+        $(
+            impl$(<$generic>)? BinaryDeserialize for $name {
+                #[inline(always)]
+                fn binary_deserialize<R: Read, E: ByteOrder>(_buffer: &mut R) -> io::Result<Self> {
+                    Ok(Default::default())
+                }
+            }
+        )*
+    }
+}
+
+impl_binary_deserialize_zero_sized!((); PhantomData<T>, T; FieldSwap);
@@ -0,0 +1,268 @@
+use crate::traits::*;
+use crate::types::*;
+
+use num_traits::{Bounded, NumCast, PrimInt};
+use std::borrow::Cow;
+use std::fmt::Debug;
+
+impl<T> StructuredDiff for [T]
+where
+    T: StructuredDiff,
+{
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        let mut deltas = Vec::new();
+
+        if self.len() != other.len() {
+            deltas.push(FieldDelta::new(DeltaKind::Length {
+                old: self.len(),
+                new: other.len(),
+            }));
+        }
+
+        // only compare the overlapping elements -- anything past that is covered by the
+        // length delta above
+        for (i, (old, new)) in self.iter().zip(other.iter()).enumerate() {
+            let index = format!("[{}]", i);
+            deltas.extend(old.diff(new).into_iter().map(|d| d.prefixed(&index)));
+        }
+
+        deltas
+    }
+}
+
+impl<T> StructuredDiff for Vec<T>
+where
+    T: StructuredDiff,
+{
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        self.as_slice().diff(other.as_slice())
+    }
+}
+
+impl<T, I> StructuredDiff for UnsafeEnum<T, I>
+where
+    T: StructuredDiff,
+    I: StructuredDiff,
+{
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        match (self, other) {
+            (UnsafeEnum::Valid(ref old), UnsafeEnum::Valid(ref new)) => old.diff(new),
+            (UnsafeEnum::Invalid(ref old), UnsafeEnum::Invalid(ref new)) => old.diff(new),
+            (UnsafeEnum::Valid(_), UnsafeEnum::Invalid(_)) => {
+                vec![FieldDelta::new(DeltaKind::Variant {
+                    old: "Valid".to_string(),
+                    new: "Invalid".to_string(),
+                })]
+            }
+            (UnsafeEnum::Invalid(_), UnsafeEnum::Valid(_)) => {
+                vec![FieldDelta::new(DeltaKind::Variant {
+                    old: "Invalid".to_string(),
+                    new: "Valid".to_string(),
+                })]
+            }
+        }
+    }
+}
+
+impl<T> StructuredDiff for Option<T>
+where
+    T: StructuredDiff,
+{
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        match (self, other) {
+            (Some(ref old), Some(ref new)) => old.diff(new),
+            (None, None) => Vec::new(),
+            (Some(_), None) => diff_variant("Some", "None"),
+            (None, Some(_)) => diff_variant("None", "Some"),
+        }
+    }
+}
+
+impl<T, E> StructuredDiff for Result<T, E>
+where
+    T: StructuredDiff,
+    E: StructuredDiff,
+{
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        match (self, other) {
+            (Ok(ref old), Ok(ref new)) => old.diff(new),
+            (Err(ref old), Err(ref new)) => old.diff(new),
+            (Ok(_), Err(_)) => diff_variant("Ok", "Err"),
+            (Err(_), Ok(_)) => diff_variant("Err", "Ok"),
+        }
+    }
+}
+
+/// Returns the [DeltaKind::Variant] delta for a value which changed variants
+fn diff_variant(old: &str, new: &str) -> Vec<FieldDelta> {
+    vec![FieldDelta::new(DeltaKind::Variant {
+        old: old.to_string(),
+        new: new.to_string(),
+    })]
+}
+
+impl<'a> StructuredDiff for Cow<'a, [u8]> {
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        (**self).diff(&**other)
+    }
+}
+
+impl<M: StructuredDiff> StructuredDiff for Conversation<M> {
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        self.messages().diff(other.messages())
+    }
+}
+
+impl<T: StructuredDiff> StructuredDiff for OneOf<T> {
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        self.value().diff(other.value())
+    }
+}
+
+impl<T: StructuredDiff> StructuredDiff for PointerAligned<T> {
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        self.0.diff(&other.0)
+    }
+}
+
+impl<T: StructuredDiff, C> StructuredDiff for TextEncoded<T, C> {
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        let mut deltas = self.value.diff(&other.value);
+        deltas.extend(
+            diff_leaf(&self.flaw, &other.flaw)
+                .into_iter()
+                .map(|d| d.prefixed("flaw")),
+        );
+
+        deltas
+    }
+}
+
+impl StructuredDiff for AsciiString {
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        diff_chars(
+            self.inner.iter().map(|c| c.0),
+            other.inner.iter().map(|c| c.0),
+        )
+    }
+}
+
+impl StructuredDiff for Utf8String {
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        diff_chars(
+            self.inner.iter().map(|c| c.0),
+            other.inner.iter().map(|c| c.0),
+        )
+    }
+}
+
+impl<const N: usize, const PAD: u8> StructuredDiff for FixedLengthString<N, PAD> {
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        diff_chars(
+            self.bytes.iter().map(|&b| b as char),
+            other.bytes.iter().map(|&b| b as char),
+        )
+    }
+}
+
+/// Strings are treated as a single leaf value rather than a collection of chars
+fn diff_chars(old: impl Iterator<Item = char>, new: impl Iterator<Item = char>) -> Vec<FieldDelta> {
+    let old: String = old.collect();
+    let new: String = new.collect();
+
+    diff_leaf(&old, &new)
+}
+
+/// Compares two leaf values and returns a single [DeltaKind::Value] delta if they differ
+fn diff_leaf<T: PartialEq + Debug + ?Sized>(old: &T, new: &T) -> Vec<FieldDelta> {
+    if old == new {
+        return Vec::new();
+    }
+
+    vec![FieldDelta::new(DeltaKind::Value {
+        old: format!("{:?}", old),
+        new: format!("{:?}", new),
+    })]
+}
+
+macro_rules! impl_structured_diff {
+    ( $($name:ident),* ) => {
+        $(
+            impl StructuredDiff for $name {
+                #[inline(always)]
+                fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+                    diff_leaf(self, other)
+                }
+            }
+        )*
+    }
+}
+
+impl_structured_diff!(i64, u64, i32, u32, i16, u16, i8, u8, f32, f64, bool, char, String, U24, U48);
+impl_structured_diff!(TargetUsize, TargetIsize);
+impl_structured_diff!(Ipv4Address, Ipv6Address, MacAddress, Uuid);
+impl_structured_diff!(UnixTimestamp32, UnixTimestamp64, DosDateTime, FileTime);
+
+impl<T, F> StructuredDiff for Scaled<T, F>
+where
+    T: NumCast + Bounded + Copy + PartialEq + Debug,
+    F: ScaleFactor,
+{
+    #[inline(always)]
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        diff_leaf(self, other)
+    }
+}
+
+impl<T: PrimInt + Debug> StructuredDiff for Offset<T> {
+    #[inline(always)]
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        diff_leaf(self, other)
+    }
+}
+
+macro_rules! impl_structured_diff_array {
+    ( $($size:expr),* ) => {
+        $(
+            impl<T> StructuredDiff for [T; $size]
+            where T: StructuredDiff {
+                #[inline(always)]
+                fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+                    // Treat this as a slice
+                    self[0..].diff(&other[0..])
+                }
+            }
+        )*
+    }
+}
+
+impl_structured_diff_array!(
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26,
+    27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
+    51, 52, 53, 54, 55, 56, 57, 58, 59, 60
+);
+
+macro_rules! impl_structured_diff_tuple {
+    ( $($name:ident $idx:tt),+ ) => {
+        impl<$($name),+> StructuredDiff for ($($name,)+)
+        where
+            $($name: StructuredDiff),+
+        {
+            fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+                let mut deltas = Vec::new();
+                $(
+                    deltas.extend(
+                        self.$idx
+                            .diff(&other.$idx)
+                            .into_iter()
+                            .map(|d| d.prefixed(stringify!($idx))),
+                    );
+                )+
+
+                deltas
+            }
+        }
+    }
+}
+
+for_each_tuple!(impl_structured_diff_tuple);
@@ -1,8 +1,35 @@
-use crate::mutator::Mutator;
+use crate::adaptive::{AdaptiveConfig, AdaptiveWeights, ResponseClassifier};
+use crate::autodict::AutoDictionary;
+use crate::cmplog::CompareTable;
+use crate::corpus::{ContentHash, Corpus};
+#[cfg(unix)]
+use crate::exec::{ExecStatus, ProcessExecutor};
+use crate::feedback::{Feedback, Observation};
+use crate::model_coverage::ModelCoverage;
+use crate::mutator::{CandidatePools, DangerScore, Mutator, SpliceCorpora};
+use crate::net::{ResponseStatus, Sender};
+use crate::pool::{BufferPool, PoolStats};
+use crate::postprocess::PostprocessPipeline;
+use crate::reuse::{InputSource, ReusePolicy, ReuseScheduler};
+#[cfg(unix)]
+use crate::shmem::CoverageMap;
+use crate::stats::{CampaignStats, JsonLinesExporter, PrometheusExporter};
+use crate::target::TargetConfig;
+use crate::traits::{
+    BinarySerialize, FeedbackProvider, FuzzedDebug, Mutatable, NewFuzzed, Postprocess, Refresh,
+};
+use byteorder::ByteOrder;
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -16,8 +43,7 @@ pub enum DriverMode {
 pub struct FuzzerDriver<T> {
     thread_count: usize,
     threads: RwLock<Vec<thread::JoinHandle<()>>>,
-    num_iterations: AtomicUsize,
-    num_failed_iterations: AtomicUsize,
+    stats: Arc<CampaignStats>,
     exit: AtomicBool,
     seed: u64,
     global_context: Option<Arc<RwLock<T>>>,
@@ -26,6 +52,25 @@ pub struct FuzzerDriver<T> {
     end_iteration: u64,
     thread_last_execution_time: Vec<AtomicUsize>,
     thread_timeout: Duration,
+    shard_index: usize,
+    shard_count: usize,
+    postprocessors: PostprocessPipeline,
+    exhaustive_sweep_len: usize,
+    exhaustive_cursor: AtomicUsize,
+    feedback: Option<Arc<dyn FeedbackProvider>>,
+    retained_inputs: Mutex<Corpus<Vec<u8>>>,
+    crashing_inputs: Mutex<Corpus<Vec<u8>>>,
+    divergent_inputs: Mutex<Corpus<Vec<u8>>>,
+    divergence_dir: Option<PathBuf>,
+    adaptive: Option<(ResponseClassifier, Arc<AdaptiveWeights>)>,
+    autodict: Option<Arc<AutoDictionary>>,
+    compare_table: Option<Arc<CompareTable>>,
+    model_coverage: Option<Arc<ModelCoverage>>,
+    reuse: Option<Arc<ReuseScheduler>>,
+    target_config: TargetConfig,
+    splice_corpora: SpliceCorpora,
+    candidate_pools: CandidatePools,
+    skip_fixup_percentage: f32,
 }
 
 impl<T: 'static + Send + Sync> Default for FuzzerDriver<T> {
@@ -50,8 +95,7 @@ impl<T: 'static + Send + Sync> FuzzerDriver<T> {
         FuzzerDriver {
             thread_count: num_threads,
             threads: RwLock::new(Vec::with_capacity(num_threads)),
-            num_iterations: Default::default(),
-            num_failed_iterations: Default::default(),
+            stats: Arc::new(CampaignStats::new(num_threads)),
             exit: Default::default(),
             seed: rand::random(),
             global_context: Default::default(),
@@ -60,6 +104,25 @@ impl<T: 'static + Send + Sync> FuzzerDriver<T> {
             end_iteration: 0,
             thread_last_execution_time: last_execution_times,
             thread_timeout: Duration::from_secs(10u64),
+            shard_index: 0,
+            shard_count: 1,
+            postprocessors: PostprocessPipeline::new(),
+            exhaustive_sweep_len: 0,
+            exhaustive_cursor: Default::default(),
+            feedback: None,
+            retained_inputs: Default::default(),
+            crashing_inputs: Default::default(),
+            divergent_inputs: Default::default(),
+            divergence_dir: None,
+            adaptive: None,
+            autodict: None,
+            compare_table: None,
+            model_coverage: None,
+            reuse: None,
+            target_config: TargetConfig::host(),
+            splice_corpora: HashMap::new(),
+            candidate_pools: HashMap::new(),
+            skip_fixup_percentage: 0.0,
         }
     }
 
@@ -70,23 +133,385 @@ impl<T: 'static + Send + Sync> FuzzerDriver<T> {
     /// Sets the driver mode to attempt to reproduce a crash. When [start_fuzzer] is called, the
     /// routine will configure each thread's RNG state to match what it was at start_iteration,
     /// the threads will begin to run, and end at end_iteration.
+    ///
+    /// Iterations are the values reported by [FuzzerContext::iteration]. If the crash came from a
+    /// sharded campaign, the driver must be configured with the same shard (see [FuzzerDriver::set_shard]).
     pub fn set_to_reproduce_mode(&mut self, start_iteration: u64, end_iteration: u64) {
         self.mode = DriverMode::Reproduce;
         // TODO: start_iteration probably isn't necessary
         self.start_iteration = start_iteration;
         self.end_iteration = end_iteration;
-        self.num_iterations
-            .store(start_iteration as usize, Ordering::SeqCst);
+        self.stats.iterations.store(
+            self.local_iteration(start_iteration as usize),
+            Ordering::SeqCst,
+        );
     }
 
-    /// Returns the total number of fuzzing iterations overall.
+    /// Deterministically partitions the iteration space across `shard_count` machines or
+    /// processes. This shard will only execute iterations where `iteration % shard_count == shard_index`,
+    /// so shards configured with the same seed and thread count never duplicate work and any
+    /// shard's iterations can be replayed locally.
+    pub fn set_shard(&mut self, shard_index: usize, shard_count: usize) {
+        if shard_count == 0 || shard_index >= shard_count {
+            panic!(
+                "invalid shard {} of {} -- shard index must be less than the shard count",
+                shard_index, shard_count
+            );
+        }
+
+        self.shard_index = shard_index;
+        self.shard_count = shard_count;
+        self.stats.set_shard(shard_index, shard_count);
+
+        if self.mode == DriverMode::Reproduce {
+            self.stats.iterations.store(
+                self.local_iteration(self.start_iteration as usize),
+                Ordering::SeqCst,
+            );
+        }
+    }
+
+    /// Adds a stage to the postprocessing pipeline. Data serialized with [FuzzerContext::serialize]
+    /// is passed through every stage, in the order they were added, before being returned to the
+    /// fuzzer callback.
+    pub fn add_postprocess_stage<P: Postprocess + 'static>(&mut self, stage: P) {
+        self.postprocessors.add_stage(stage);
+    }
+
+    pub fn postprocessors(&self) -> &PostprocessPipeline {
+        &self.postprocessors
+    }
+
+    /// Sets the provider consulted after every iteration. Inputs it judges
+    /// [Feedback::Interesting] are kept in [FuzzerDriver::retained_inputs] and those it judges
+    /// [Feedback::Crash] in [FuzzerDriver::crashing_inputs]. The input is the contents of
+    /// [FuzzerContext::buffer] when the callback returns.
+    pub fn set_feedback_provider<P: FeedbackProvider + 'static>(&mut self, provider: P) {
+        self.feedback = Some(Arc::new(provider));
+    }
+
+    /// Inputs the feedback provider found interesting, in the order they were found
+    pub fn retained_inputs(&self) -> Corpus<Vec<u8>> {
+        self.retained_inputs.lock().unwrap().clone()
+    }
+
+    /// Inputs the feedback provider reported as crashing the target
+    pub fn crashing_inputs(&self) -> Corpus<Vec<u8>> {
+        self.crashing_inputs.lock().unwrap().clone()
+    }
+
+    /// Inputs which the callback reported with [FuzzerContext::report_divergence], in the order
+    /// they were found
+    pub fn divergent_inputs(&self) -> Corpus<Vec<u8>> {
+        self.divergent_inputs.lock().unwrap().clone()
+    }
+
+    /// Writes each divergent input to `dir` as it's found, creating the directory if needed.
+    /// Files are named `divergence-<hash>` after the input's [ContentHash], so a divergence found
+    /// again is written over its earlier copy.
+    pub fn set_divergence_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.divergence_dir = Some(dir.into());
+    }
+
+    pub fn divergence_dir(&self) -> Option<&Path> {
+        self.divergence_dir.as_deref()
+    }
+
+    /// Enables adaptive weighting: the outcome `classifier` assigns each iteration is tallied
+    /// against the enum variants and field values chosen while generating its input, and choices
+    /// which consistently produce the same rejection are generated less often. See
+    /// [adaptive](crate::adaptive).
+    ///
+    /// Since the inputs generated depend on the outcomes seen so far, iterations can't be
+    /// reproduced exactly. Adaptive weighting is disabled in [DriverMode::Reproduce].
+    pub fn set_adaptive_weighting(
+        &mut self,
+        classifier: ResponseClassifier,
+        config: AdaptiveConfig,
+    ) {
+        self.adaptive = Some((classifier, Arc::new(AdaptiveWeights::new(config))));
+    }
+
+    /// The outcome tallies of adaptive weighting, if it's enabled
+    pub fn adaptive_weights(&self) -> Option<Arc<AdaptiveWeights>> {
+        self.adaptive.as_ref().map(|(_, weights)| weights.clone())
+    }
+
+    /// Enables the automatic dictionary: tokens are learned from every iteration's response (and
+    /// from whatever the callback records) and inserted into strings by every thread's mutator.
+    /// See [autodict](crate::autodict).
+    ///
+    /// Since the inputs generated depend on the responses seen so far, iterations can't be
+    /// reproduced exactly. The dictionary isn't used in [DriverMode::Reproduce].
+    pub fn set_autodict(&mut self, dictionary: AutoDictionary) {
+        self.autodict = Some(Arc::new(dictionary));
+    }
+
+    /// The automatic dictionary, if it's enabled
+    pub fn autodict(&self) -> Option<Arc<AutoDictionary>> {
+        self.autodict.clone()
+    }
+
+    /// Has every thread's mutator substitute the operands of the comparisons captured in `table`
+    /// into the inputs it mutates. `table` is usually [cmplog::hook_table](crate::cmplog::hook_table)
+    /// for targets linked into the fuzzer. See [cmplog](crate::cmplog).
+    ///
+    /// The table isn't used in [DriverMode::Reproduce], since the captured comparisons depend on
+    /// the iterations which ran before.
+    pub fn set_compare_table(&mut self, table: Arc<CompareTable>) {
+        self.compare_table = Some(table);
+    }
+
+    /// The table of captured comparisons, if one was set
+    pub fn compare_table(&self) -> Option<Arc<CompareTable>> {
+        self.compare_table.clone()
+    }
+
+    /// Has every thread's mutator tally the enum variants, `Option` arms, and field values it
+    /// generates into `coverage`. See [model_coverage](crate::model_coverage).
+    pub fn set_model_coverage(&mut self, coverage: Arc<ModelCoverage>) {
+        self.model_coverage = Some(coverage);
+    }
+
+    /// The model coverage tallies, if they're enabled
+    pub fn model_coverage(&self) -> Option<Arc<ModelCoverage>> {
+        self.model_coverage.clone()
+    }
+
+    /// Has the driver pick whether each iteration mutates a corpus entry or generates a new input,
+    /// which the callback reads with [FuzzerContext::input_source]. See [reuse](crate::reuse).
+    pub fn set_reuse_policy(&mut self, policy: ReusePolicy) {
+        self.reuse = Some(Arc::new(ReuseScheduler::new(policy)));
+    }
+
+    /// The scheduler picking each iteration's [InputSource], if a reuse policy was set
+    pub fn reuse_scheduler(&self) -> Option<Arc<ReuseScheduler>> {
+        self.reuse.clone()
+    }
+
+    /// Has every thread serialize pointer-sized values for the build of the target described by
+    /// `config`. See [target](crate::target).
+    pub fn set_target_config(&mut self, config: TargetConfig) {
+        self.target_config = config;
+    }
+
+    /// The build of the target inputs are serialized for. Defaults to the host.
+    pub fn target_config(&self) -> TargetConfig {
+        self.target_config
+    }
+
+    /// Has every thread's mutator splice fields of derived `V`s from entries of `corpus`. See
+    /// [Mutator::set_splice_corpus].
+    ///
+    /// The corpus isn't used in [DriverMode::Reproduce], since entries may have been added to it
+    /// by the iterations which ran before.
+    pub fn set_splice_corpus<V>(&mut self, corpus: Arc<RwLock<Corpus<V>>>)
+    where
+        V: Clone + Send + Sync + 'static,
+    {
+        self.splice_corpora
+            .insert(std::any::TypeId::of::<V>(), corpus);
+    }
+
+    /// Registers the known-valid values of `V` which every thread's mutator picks
+    /// [OneOf](crate::types::OneOf)`<V>` values from. See [Mutator::set_candidates].
+    pub fn set_candidates<V>(&mut self, candidates: Vec<V>)
+    where
+        V: Send + Sync + 'static,
+    {
+        self.candidate_pools
+            .insert(std::any::TypeId::of::<V>(), Arc::new(candidates));
+    }
+
+    /// Has `percentage` percent of iterations run with fixups disabled (see
+    /// [Mutator::set_fixup_enabled]), so that lengths and checksums are deliberately left wrong.
+    /// The other iterations follow the mutator's usual fixup policy. Which iterations skip fixups
+    /// is decided by their seed, so they're skipped again when reproduced.
+    pub fn set_skip_fixup_percentage(&mut self, percentage: f32) {
+        self.skip_fixup_percentage = percentage;
+    }
+
+    pub fn skip_fixup_percentage(&self) -> f32 {
+        self.skip_fixup_percentage
+    }
+
+    /// Consults the feedback provider and response classifier about the iteration which just
+    /// finished in `context`, and learns tokens from its response
+    fn record_feedback<C, R: Rng>(
+        &self,
+        context: &FuzzerContext<C>,
+        mutator: &Mutator<R>,
+        callback_failed: bool,
+    ) {
+        if let Some(ref dictionary) = self.autodict {
+            dictionary.learn_from_response(&context.response);
+        }
+
+        if self.feedback.is_none() && self.adaptive.is_none() {
+            return;
+        }
+
+        let observation = Observation {
+            response: &context.response,
+            response_status: context.response_status,
+            exit_status: context.exit_status,
+            coverage: context.coverage_slice(),
+            callback_failed,
+        };
+
+        if let Some((ref classifier, ref weights)) = self.adaptive {
+            weights.record(mutator.choices(), classifier.classify(&observation));
+        }
+
+        let provider = match self.feedback {
+            Some(ref provider) => provider,
+            None => return,
+        };
+
+        let feedback = provider.evaluate(&context.buffer, &observation);
+        if let Some(ref reuse) = self.reuse {
+            reuse.record(context.input_source, feedback == Feedback::Interesting);
+        }
+
+        match feedback {
+            Feedback::Interesting => {
+                self.stats.interesting_inputs.fetch_add(1, Ordering::SeqCst);
+
+                self.retained_inputs
+                    .lock()
+                    .unwrap()
+                    .push(context.buffer.clone());
+            }
+            Feedback::Crash => {
+                log::warn!("iteration {} crashed the target", context.iteration);
+                self.stats.crashes.fetch_add(1, Ordering::SeqCst);
+
+                self.crashing_inputs
+                    .lock()
+                    .unwrap()
+                    .push(context.buffer.clone());
+            }
+            Feedback::Boring => {}
+        }
+    }
+
+    /// Keeps the input of the iteration which just finished in `context` if the callback reported
+    /// a divergence
+    fn record_divergence<C>(&self, context: &FuzzerContext<C>) {
+        let description = match context.divergence {
+            Some(ref description) => description,
+            None => return,
+        };
+
+        log::warn!("iteration {} diverged: {}", context.iteration, description);
+
+        self.divergent_inputs
+            .lock()
+            .unwrap()
+            .push(context.buffer.clone());
+
+        if let Some(ref dir) = self.divergence_dir {
+            let path = dir.join(format!("divergence-{}", ContentHash::of(&context.buffer)));
+            if let Err(e) = fs::create_dir_all(dir).and_then(|_| fs::write(&path, &context.buffer))
+            {
+                log::error!(
+                    "could not write divergent input to {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Sweeps every combination of the `#[fuzzer(exhaustive)]` fields before the fuzzer falls back
+    /// to generating them randomly. `combinations` is the product of the value counts of the swept
+    /// fields (see [Enumerable::value_count][crate::traits::Enumerable::value_count]). Fields
+    /// which aren't swept are fuzzed as normal.
+    ///
+    /// Combination indices are handed out to threads in order, so every combination is generated
+    /// at least once across all threads. If the campaign is sharded, each shard only sweeps its own
+    /// share of the combinations. The index used for an iteration is available through
+    /// [FuzzerContext::exhaustive_index].
+    pub fn set_exhaustive_sweep(&mut self, combinations: usize) {
+        self.exhaustive_sweep_len = combinations;
+        self.exhaustive_cursor.store(0, Ordering::SeqCst);
+    }
+
+    /// Returns the total number of combinations in the exhaustive sweep and the number which have
+    /// been handed out to fuzzer threads so far
+    pub fn exhaustive_sweep_progress(&self) -> (usize, usize) {
+        let handed_out = self
+            .global_iteration(self.exhaustive_cursor.load(Ordering::SeqCst))
+            .min(self.exhaustive_sweep_len);
+
+        (self.exhaustive_sweep_len, handed_out)
+    }
+
+    /// Claims the next combination index of the exhaustive sweep, if any remain
+    pub(crate) fn next_exhaustive_index(&self) -> Option<usize> {
+        if self.exhaustive_sweep_len == 0 {
+            return None;
+        }
+
+        let index = self.global_iteration(self.exhaustive_cursor.fetch_add(1, Ordering::SeqCst));
+        if index < self.exhaustive_sweep_len {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    /// Returns this driver's shard index and the total number of shards
+    pub fn shard(&self) -> (usize, usize) {
+        (self.shard_index, self.shard_count)
+    }
+
+    /// Maps a count of iterations executed by this shard to the campaign-wide iteration number
+    pub(crate) fn global_iteration(&self, local_iteration: usize) -> usize {
+        local_iteration * self.shard_count + self.shard_index
+    }
+
+    /// Maps a campaign-wide iteration number to the count of iterations this shard executes
+    /// before reaching it
+    fn local_iteration(&self, global_iteration: usize) -> usize {
+        global_iteration
+            .saturating_sub(self.shard_index)
+            .div_ceil(self.shard_count)
+    }
+
+    /// Returns the total number of fuzzing iterations executed by this shard.
     pub fn num_iterations(&self) -> usize {
-        self.num_iterations.load(Ordering::SeqCst)
+        self.stats.iterations.load(Ordering::SeqCst)
     }
 
     /// Returns the number of iterations that returned an error result
     pub fn num_failed_iterations(&self) -> usize {
-        self.num_failed_iterations.load(Ordering::SeqCst)
+        self.stats.failed_iterations.load(Ordering::SeqCst)
+    }
+
+    /// Counters aggregated across all fuzzer threads. The returned handle stays live, so it can
+    /// be polled with [CampaignStats::snapshot] while the campaign runs.
+    pub fn stats(&self) -> Arc<CampaignStats> {
+        self.stats.clone()
+    }
+
+    /// Appends a JSON snapshot of the campaign's stats to `path` every `interval` until the
+    /// returned exporter is dropped (see [JsonLinesExporter])
+    pub fn export_stats_json<P: AsRef<Path>>(
+        &self,
+        path: P,
+        interval: Duration,
+    ) -> io::Result<JsonLinesExporter> {
+        JsonLinesExporter::start(self.stats(), path, interval)
+    }
+
+    /// Serves the campaign's stats for Prometheus on `addr` until the returned exporter is
+    /// dropped (see [PrometheusExporter])
+    pub fn export_stats_prometheus<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+    ) -> io::Result<PrometheusExporter> {
+        PrometheusExporter::start(self.stats(), addr)
     }
 
     pub fn set_global_context(&mut self, context: Arc<RwLock<T>>) {
@@ -140,7 +565,7 @@ impl<T: 'static + Send + Sync> FuzzerDriver<T> {
     /// Returns a boolean indicating whether the calling thread should exit
     pub(crate) fn should_exit(&self) -> bool {
         if self.mode == DriverMode::Reproduce {
-            return self.num_iterations() == self.end_iteration as usize;
+            return self.num_iterations() == self.local_iteration(self.end_iteration as usize);
         }
 
         self.exit.load(Ordering::SeqCst)
@@ -190,18 +615,478 @@ impl<T: 'static + Send + Sync> FuzzerDriver<T> {
     }
 }
 
+/// Per-thread state handed to the fuzzer callback on every iteration.
+///
+/// The context lives for as long as the fuzzer thread does, so expensive resources (sockets,
+/// file handles, scratch structures) should be kept in `user_data` instead of being recreated
+/// every iteration.
+pub struct FuzzerContext<C> {
+    iteration: usize,
+    thread_index: usize,
+    /// Reusable buffer for serialized data. This is cleared before each iteration but retains
+    /// its capacity.
+    pub buffer: Vec<u8>,
+    /// Bytes received from the target by [FuzzerContext::send]. This is cleared before each
+    /// iteration.
+    pub response: Vec<u8>,
+    /// How reading the target's response went. This is set by [FuzzerContext::send] and
+    /// cleared before each iteration.
+    pub response_status: Option<ResponseStatus>,
+    /// The target's exit status, for callbacks which run the target as a process. This is
+    /// passed to the driver's feedback provider and cleared before each iteration.
+    pub exit_status: Option<ExitStatus>,
+    /// Shared memory edge map for callbacks which run an instrumented target. The map is cleared
+    /// before each iteration and passed to the driver's feedback provider afterwards.
+    #[cfg(unix)]
+    pub coverage: Option<CoverageMap>,
+    /// Buffers reused across this thread's iterations, for callbacks which build more than the
+    /// one input held in [FuzzerContext::buffer]. The driver reports the pool's counters in its
+    /// [stats](FuzzerDriver::stats).
+    pub pool: BufferPool,
+    /// User-defined thread-local state
+    pub user_data: C,
+    postprocessors: PostprocessPipeline,
+    scratch: Vec<u8>,
+    exhaustive_index: Option<usize>,
+    divergence: Option<String>,
+    input_source: InputSource,
+    pub(crate) danger_score: DangerScore,
+}
+
+impl<C: Default> FuzzerContext<C> {
+    fn new(thread_index: usize, postprocessors: PostprocessPipeline) -> Self {
+        FuzzerContext {
+            iteration: 0,
+            thread_index,
+            buffer: Vec::new(),
+            response: Vec::new(),
+            response_status: None,
+            exit_status: None,
+            #[cfg(unix)]
+            coverage: None,
+            pool: BufferPool::new(),
+            user_data: C::default(),
+            postprocessors,
+            scratch: Vec::new(),
+            exhaustive_index: None,
+            divergence: None,
+            input_source: InputSource::Fresh,
+            danger_score: DangerScore::default(),
+        }
+    }
+}
+
+impl<C> FuzzerContext<C> {
+    /// The global iteration number this callback is executing. This is the value which seeds
+    /// the mutator, so it can be passed to [FuzzerDriver::set_to_reproduce_mode] to reproduce
+    /// this iteration.
+    pub fn iteration(&self) -> usize {
+        self.iteration
+    }
+
+    /// Index of the fuzzer thread running this callback
+    pub fn thread_index(&self) -> usize {
+        self.thread_index
+    }
+
+    /// The combination of exhaustively swept fields being generated this iteration, or `None` if
+    /// the sweep has finished or isn't configured (see [FuzzerDriver::set_exhaustive_sweep])
+    pub fn exhaustive_index(&self) -> Option<usize> {
+        self.exhaustive_index
+    }
+
+    /// Clears the reusable buffer, serializes `value` into it, runs the driver's postprocessing
+    /// pipeline over the data, and returns the final bytes
+    pub fn serialize<V: BinarySerialize, E: ByteOrder>(&mut self, value: &V) -> &[u8] {
+        self.buffer.clear();
+        value
+            .binary_serialize::<_, E>(&mut self.buffer)
+            .expect("writing to a Vec cannot fail");
+
+        self.postprocessors.run(&mut self.buffer, &mut self.scratch);
+
+        &self.buffer
+    }
+
+    /// Serializes `value` into a buffer reserved from the [pool](FuzzerContext::pool) and runs the
+    /// driver's postprocessing pipeline over it. Unlike [FuzzerContext::serialize], this leaves
+    /// [FuzzerContext::buffer] untouched, which suits callbacks that send several messages per
+    /// iteration. The buffer should be given back with [BufferPool::restore] once it's been sent.
+    pub fn serialize_pooled<V: BinarySerialize, E: ByteOrder>(&mut self, value: &V) -> Vec<u8> {
+        // pooled buffers keep the capacity earlier values grew them to
+        let mut buffer = self.pool.reserve(0);
+        value
+            .binary_serialize::<_, E>(&mut buffer)
+            .expect("writing to a Vec cannot fail");
+
+        self.postprocessors.run(&mut buffer, &mut self.scratch);
+
+        buffer
+    }
+
+    /// Regenerates the volatile fields of `value` (see [Refresh]) and then serializes it like
+    /// [FuzzerContext::serialize]. This is how corpus entries should be replayed, since fields
+    /// such as sequence numbers would otherwise be sent with the values they were saved with.
+    pub fn serialize_refreshed<V, E, R>(&mut self, mutator: &mut Mutator<R>, value: &mut V) -> &[u8]
+    where
+        V: BinarySerialize,
+        E: ByteOrder,
+        R: Rng,
+    {
+        value.refresh(mutator);
+
+        self.serialize::<V, E>(value)
+    }
+
+    /// Reports that the implementations being compared disagreed about the contents of
+    /// [FuzzerContext::buffer]. Once the callback returns, the driver keeps the input in
+    /// [FuzzerDriver::divergent_inputs] and writes it to the
+    /// [divergence directory](FuzzerDriver::set_divergence_dir), if one is set.
+    pub fn report_divergence<D: Into<String>>(&mut self, description: D) {
+        self.divergence = Some(description.into());
+    }
+
+    /// The [DangerScore] of the value the [fuzz!](crate::fuzz) harness generated this iteration.
+    /// Callbacks given to [start_fuzzer] generate their own inputs and should read
+    /// [Mutator::danger_score] instead.
+    pub fn danger_score(&self) -> DangerScore {
+        self.danger_score
+    }
+
+    /// The divergence reported this iteration, if any
+    pub fn divergence(&self) -> Option<&str> {
+        self.divergence.as_deref()
+    }
+
+    /// Where the driver's [reuse policy](FuzzerDriver::set_reuse_policy) wants this iteration's
+    /// input to come from. Without a policy every input is [InputSource::Fresh].
+    pub fn input_source(&self) -> InputSource {
+        self.input_source
+    }
+
+    /// Overrides where this iteration's input came from, e.g. when a callback asked for a corpus
+    /// entry has an empty corpus and generates a new input instead. The driver credits any
+    /// interesting input found to this source.
+    pub fn set_input_source(&mut self, source: InputSource) {
+        self.input_source = source;
+    }
+
+    /// Returns a mutated copy of a random entry of `corpus` if this iteration's
+    /// [input source](FuzzerContext::input_source) is [InputSource::Corpus], or a newly generated
+    /// `V` if it's [InputSource::Fresh] or the corpus is empty
+    pub fn reuse_or_generate<V, R>(&mut self, mutator: &mut Mutator<R>, corpus: &Corpus<V>) -> V
+    where
+        V: NewFuzzed + Mutatable + Clone,
+        R: Rng,
+    {
+        if self.input_source == InputSource::Corpus && !corpus.is_empty() {
+            let entries = corpus.entries();
+            let mut value = entries[mutator.rng.gen_range(0, entries.len())].clone();
+            value.mutate(mutator, None);
+
+            return value;
+        }
+
+        self.input_source = InputSource::Fresh;
+
+        V::new_fuzzed(mutator, None)
+    }
+
+    #[cfg(unix)]
+    fn coverage_slice(&self) -> Option<&[u8]> {
+        self.coverage.as_ref().map(CoverageMap::as_slice)
+    }
+
+    #[cfg(not(unix))]
+    fn coverage_slice(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Runs the target on the contents of the reusable buffer (usually filled by
+    /// [FuzzerContext::serialize]), recording its exit status in [FuzzerContext::exit_status] and
+    /// any output the executor captures in [FuzzerContext::response]. If the thread has a
+    /// [coverage map][FuzzerContext::coverage], the target is pointed at it.
+    #[cfg(unix)]
+    pub fn execute(&mut self, executor: &mut ProcessExecutor) -> io::Result<ExecStatus> {
+        if let Some(ref coverage) = self.coverage {
+            for (key, value) in coverage.env_vars() {
+                executor.env(key, value);
+            }
+        }
+
+        let status = executor.run(&self.buffer, &mut self.response)?;
+        self.exit_status = executor.exit_status();
+
+        Ok(status)
+    }
+
+    /// Sends the contents of the reusable buffer (usually filled by [FuzzerContext::serialize])
+    /// to the target and captures its response in [FuzzerContext::response]
+    pub fn send<S: Sender>(&mut self, sender: &mut S) -> io::Result<ResponseStatus> {
+        let status = sender.send(&self.buffer, &mut self.response)?;
+        self.response_status = Some(status);
+
+        Ok(status)
+    }
+}
+
+/// Generates `count` inputs of type `V` with the same seeds that thread 0 of [start_fuzzer] would
+/// use and returns their [FuzzedDebug] trees instead of running a callback. This is useful for
+/// checking what a harness will generate before pointing it at a target.
+///
+/// Note: the output only matches what the fuzzer sends if the callback creates a single `V` per
+/// iteration with [NewFuzzed::new_fuzzed].
+pub fn dry_run<V, T>(driver: &FuzzerDriver<T>, count: usize) -> String
+where
+    V: NewFuzzed + FuzzedDebug,
+    T: 'static + Send + Sync,
+{
+    let mut output = String::new();
+
+    generate_dry_run::<V, T, _>(driver, count, |iteration, value| {
+        output.push_str(&format!("Iteration {}:\n", iteration));
+        value.fuzzed_debug("", 1, &mut output);
+    });
+
+    output
+}
+
+/// Generates the values [dry_run] prints, handing each one to `f` along with its iteration
+pub(crate) fn generate_dry_run<V, T, F>(driver: &FuzzerDriver<T>, count: usize, mut f: F)
+where
+    V: NewFuzzed,
+    T: 'static + Send + Sync,
+    F: FnMut(usize, V),
+{
+    let mut root_rng = StdRng::seed_from_u64(driver.seed());
+    let thread_seed: u64 = root_rng.gen();
+
+    let mut mutator = Mutator::new(StdRng::seed_from_u64(0u64));
+    mutator.set_seed(Some(driver.seed()));
+    mutator.set_candidate_pools(driver.candidate_pools.clone());
+
+    let previous_target_config = TargetConfig::current();
+    driver.target_config().make_current();
+
+    for local_iteration in 0..count {
+        let iteration = driver.global_iteration(local_iteration);
+        mutator.rng = StdRng::seed_from_u64(thread_seed.wrapping_add(iteration as u64));
+        mutator.begin_new_iteration();
+
+        if iteration < driver.exhaustive_sweep_len {
+            mutator.set_exhaustive_index(Some(iteration));
+        }
+
+        f(iteration, V::new_fuzzed(&mut mutator, None));
+    }
+
+    previous_target_config.make_current();
+}
+
+/// Parses a `--dry-run N` (or `--dry-run=N`) argument from the process arguments. Returns
+/// `None` if the flag wasn't provided.
+pub fn dry_run_count_from_args() -> Option<usize> {
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let count = if arg == "--dry-run" {
+            args.next()
+        } else if let Some(count) = arg.strip_prefix("--dry-run=") {
+            Some(count.to_string())
+        } else {
+            continue;
+        };
+
+        return Some(
+            count
+                .and_then(|count| count.parse().ok())
+                .expect("--dry-run requires a number of inputs to generate"),
+        );
+    }
+
+    None
+}
+
+/// Flags recognized by [DriverOptions::parse_args]
+const DRIVER_FLAGS: &[&str] = &[
+    "--threads",
+    "--seed",
+    "--corpus",
+    "--artifacts",
+    "--iterations",
+    "--dry-run",
+    "--reproduce-file",
+];
+
+/// The command line every lain-based fuzzer understands. Parsing it with [DriverOptions::from_args]
+/// gives fuzzers a consistent interface without each of them wiring up an argument parser:
+///
+/// - `--threads N`
+/// - `--seed N`
+/// - `--corpus DIR` directory of inputs to start the campaign from
+/// - `--artifacts DIR` directory crashing inputs are written to
+/// - `--iterations N` stops after roughly N iterations
+/// - `--dry-run N` prints the first N generated values instead of running the target
+/// - `--reproduce-file FILE` runs the target once on the input in FILE
+///
+/// Values may also be given as `--flag=value`. Options which weren't given are `None`, and the
+/// fuzzer decides what to do with them; [DriverOptions::driver] applies the thread count and seed.
+///
+/// ```
+/// use lain::driver::DriverOptions;
+///
+/// let args = vec!["--threads", "4", "--corpus=seeds", "--verbose"];
+/// let options = DriverOptions::parse_args(args.into_iter().map(String::from)).unwrap();
+///
+/// assert_eq!(options.threads, Some(4));
+/// assert_eq!(options.corpus.as_ref().unwrap().to_str(), Some("seeds"));
+/// assert_eq!(options.remaining, vec!["--verbose"]);
+///
+/// let driver = options.driver::<()>();
+/// assert_eq!(driver.thread_count(), 4);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DriverOptions {
+    pub threads: Option<usize>,
+    pub seed: Option<u64>,
+    pub corpus: Option<PathBuf>,
+    pub artifacts: Option<PathBuf>,
+    pub iterations: Option<usize>,
+    pub dry_run: Option<usize>,
+    pub reproduce_file: Option<PathBuf>,
+    /// Arguments which aren't driver flags, in the order they were given, for the fuzzer to
+    /// parse itself
+    pub remaining: Vec<String>,
+}
+
+impl DriverOptions {
+    /// Parses the process arguments. `--help` prints the usage and exits, and invalid arguments
+    /// print the error and usage and exit with status 2.
+    pub fn from_args() -> DriverOptions {
+        let mut args = std::env::args();
+        let program = args.next().unwrap_or_else(|| "fuzzer".to_string());
+
+        let args: Vec<String> = args.collect();
+        if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+            print!("{}", DriverOptions::usage(&program));
+            std::process::exit(0);
+        }
+
+        DriverOptions::parse_args(args).unwrap_or_else(|e| {
+            eprint!("error: {}\n\n{}", e, DriverOptions::usage(&program));
+            std::process::exit(2);
+        })
+    }
+
+    /// Parses `args`, which shouldn't include the program name. Returns a description of the
+    /// problem if a flag is missing its value or the value is malformed.
+    pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<DriverOptions, String> {
+        let mut options = DriverOptions::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.find('=') {
+                Some(index) => (&arg[..index], Some(arg[index + 1..].to_string())),
+                None => (arg.as_str(), None),
+            };
+
+            if !DRIVER_FLAGS.contains(&name) {
+                options.remaining.push(arg.clone());
+                continue;
+            }
+
+            let value = inline_value
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} requires a value", name))?;
+
+            match name {
+                "--threads" => match parse_flag(name, &value)? {
+                    0 => return Err("--threads must be at least 1".to_string()),
+                    threads => options.threads = Some(threads),
+                },
+                "--seed" => options.seed = Some(parse_flag(name, &value)?),
+                "--corpus" => options.corpus = Some(value.into()),
+                "--artifacts" => options.artifacts = Some(value.into()),
+                "--iterations" => options.iterations = Some(parse_flag(name, &value)?),
+                "--dry-run" => options.dry_run = Some(parse_flag(name, &value)?),
+                _ => options.reproduce_file = Some(value.into()),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Describes the driver flags for `--help` output
+    pub fn usage(program: &str) -> String {
+        let mut usage = format!("Usage: {} [OPTIONS]\n\nOptions:\n", program);
+        let options = [
+            ("--threads N", "number of fuzzer threads"),
+            ("--seed N", "root seed of the campaign"),
+            ("--corpus DIR", "directory of inputs to start from"),
+            (
+                "--artifacts DIR",
+                "directory crashing inputs are written to",
+            ),
+            ("--iterations N", "stop after roughly N iterations"),
+            ("--dry-run N", "print the first N generated values and exit"),
+            (
+                "--reproduce-file FILE",
+                "run the target once on the input in FILE",
+            ),
+            ("-h, --help", "print this message"),
+        ];
+
+        for (flag, description) in options.iter() {
+            usage.push_str(&format!("    {:<23}{}\n", flag, description));
+        }
+
+        usage
+    }
+
+    /// Creates a driver with the given thread count (one per logical CPU by default) and seed
+    pub fn driver<T: 'static + Send + Sync>(&self) -> FuzzerDriver<T> {
+        let threads = self
+            .threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()));
+
+        let mut driver = FuzzerDriver::new(threads);
+        if let Some(seed) = self.seed {
+            driver.set_seed(seed);
+        }
+
+        driver
+    }
+
+    /// Reads the input given with `--reproduce-file`
+    pub fn reproduce_input(&self) -> io::Result<Option<Vec<u8>>> {
+        self.reproduce_file.as_ref().map(fs::read).transpose()
+    }
+
+    /// Whether the campaign has run the number of iterations given with `--iterations`
+    pub fn iterations_reached<T: 'static + Send + Sync>(&self, driver: &FuzzerDriver<T>) -> bool {
+        self.iterations
+            .is_some_and(|limit| driver.num_iterations() >= limit)
+    }
+}
+
+fn parse_flag<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {:?} for {}", value, name))
+}
+
 /// Kicks off a fuzzing job using the driver and callback function.
 ///
 /// The callback should look something like:
 ///
 /// ```compile_fail
-/// fn iteration_routine<R: Rng>(mutator: &mut Mutator<R>, thread_context: &mut FuzzerThreadContext, _global_context: Option<Arc<RwLock<GlobalContext>>>) -> Result<(), ()>
+/// fn iteration_routine<R: Rng>(mutator: &mut Mutator<R>, context: &mut FuzzerContext<FuzzerThreadContext>, _global_context: Option<Arc<RwLock<GlobalContext>>>) -> Result<(), ()>
 /// ```
 pub fn start_fuzzer<F: 'static, C: 'static, T: 'static + Send + Sync>(
     driver: Arc<FuzzerDriver<T>>,
     callback: F,
 ) where
-    F: Fn(&mut Mutator<StdRng>, &mut C, Option<Arc<RwLock<T>>>) -> Result<(), ()>
+    F: Fn(&mut Mutator<StdRng>, &mut FuzzerContext<C>, Option<Arc<RwLock<T>>>) -> Result<(), ()>
         + std::marker::Send
         + std::marker::Sync
         + Copy,
@@ -216,6 +1101,7 @@ pub fn start_fuzzer<F: 'static, C: 'static, T: 'static + Send + Sync>(
         let thread_name = format!("Fuzzer thread {}", i);
 
         let thread_seed: u64 = root_rng.gen();
+        let postprocessors = driver.postprocessors.clone();
 
         let join_handle = thread::Builder::new()
             .name(thread_name)
@@ -224,7 +1110,20 @@ pub fn start_fuzzer<F: 'static, C: 'static, T: 'static + Send + Sync>(
                 // on the first loop iteration
                 let thread_rng = StdRng::seed_from_u64(0u64);
                 let mut mutator = Mutator::new(thread_rng);
-                let mut context = C::default();
+                mutator.set_seed(Some(thread_driver.seed()));
+                let mut context = FuzzerContext::<C>::new(i, postprocessors);
+                mutator.set_candidate_pools(thread_driver.candidate_pools.clone());
+                mutator.set_model_coverage(thread_driver.model_coverage());
+                thread_driver.target_config().make_current();
+
+                if thread_driver.mode() == DriverMode::Run {
+                    mutator.set_adaptive_weights(thread_driver.adaptive_weights());
+                    mutator.set_dictionary(thread_driver.autodict());
+                    mutator.set_compare_table(thread_driver.compare_table());
+                    mutator.set_splice_corpora(thread_driver.splice_corpora.clone());
+                }
+
+                let mut reported_pool_stats = PoolStats::default();
 
                 // loop until we get a signal that we should exit
                 loop {
@@ -232,7 +1131,8 @@ pub fn start_fuzzer<F: 'static, C: 'static, T: 'static + Send + Sync>(
 
                     // TODO: here be dragons? num_iterations is a usize and we're casting it to a u64. on 64-bit systems this
                     // isn't a problem since usize should be a u64, but it's worth noting that this could be a potential issue
-                    let new_seed = thread_seed.wrapping_add(thread_driver.num_iterations() as u64);
+                    let iteration = thread_driver.global_iteration(thread_driver.num_iterations());
+                    let new_seed = thread_seed.wrapping_add(iteration as u64);
                     mutator.rng = StdRng::seed_from_u64(new_seed);
 
                     if thread_driver.should_exit() {
@@ -242,15 +1142,53 @@ pub fn start_fuzzer<F: 'static, C: 'static, T: 'static + Send + Sync>(
 
                     mutator.begin_new_iteration();
 
-                    if let Err(_) =
-                        (callback)(&mut mutator, &mut context, thread_driver.global_context())
+                    if thread_driver.skip_fixup_percentage > 0.0 {
+                        let skip_fixup = mutator.gen_chance(thread_driver.skip_fixup_percentage);
+                        mutator.set_fixup_enabled(!skip_fixup);
+                    }
+
+                    let exhaustive_index = thread_driver.next_exhaustive_index();
+                    mutator.set_exhaustive_index(exhaustive_index);
+
+                    context.iteration = iteration;
+                    context.exhaustive_index = exhaustive_index;
+                    context.buffer.clear();
+                    context.response.clear();
+                    context.response_status = None;
+                    context.exit_status = None;
+                    context.divergence = None;
+                    context.danger_score = DangerScore::default();
+                    context.input_source = match thread_driver.reuse {
+                        Some(ref reuse) if thread_driver.mode() == DriverMode::Run => {
+                            reuse.pick(&mut mutator.rng)
+                        }
+                        Some(ref reuse) => reuse.pick_initial(&mut mutator.rng),
+                        None => InputSource::Fresh,
+                    };
+
+                    #[cfg(unix)]
                     {
-                        thread_driver
-                            .num_failed_iterations
-                            .fetch_add(1, Ordering::SeqCst);
+                        if let Some(ref mut coverage) = context.coverage {
+                            coverage.clear();
+                        }
                     }
 
-                    thread_driver.num_iterations.fetch_add(1, Ordering::SeqCst);
+                    let callback_failed =
+                        (callback)(&mut mutator, &mut context, thread_driver.global_context())
+                            .is_err();
+
+                    thread_driver.record_feedback(&context, &mutator, callback_failed);
+                    thread_driver.record_divergence(&context);
+
+                    thread_driver
+                        .stats
+                        .record_iteration(i, context.buffer.len(), callback_failed);
+
+                    let pool_stats = context.pool.stats();
+                    thread_driver
+                        .stats
+                        .record_buffer_pool(&pool_stats.since(&reported_pool_stats));
+                    reported_pool_stats = pool_stats;
                 }
             })
             .unwrap_or_else(|_| panic!("could not create new thread"));
@@ -258,3 +1196,220 @@ pub fn start_fuzzer<F: 'static, C: 'static, T: 'static + Send + Sync>(
         threads.push(join_handle);
     }
 }
+
+/// Kicks off a differential fuzzing job. Every iteration generates a `V`, serializes it with the
+/// byte order `E`, and hands the same bytes to both `target_a` and `target_b` (e.g. two versions
+/// of a parser, or two libraries implementing the same format). When `agree` returns `false` for
+/// their outputs, the input is reported with [FuzzerContext::report_divergence] and the iteration
+/// counts as failed.
+///
+/// ```no_run
+/// use lain::driver::{start_differential_fuzzer, FuzzerDriver};
+/// use std::sync::Arc;
+///
+/// fn parse_v1(input: &[u8]) -> Option<u16> {
+///     Some(u16::from(*input.first()?))
+/// }
+///
+/// fn parse_v2(input: &[u8]) -> Option<u16> {
+///     Some(u16::from(*input.first()?) & 0x7f)
+/// }
+///
+/// let mut driver = FuzzerDriver::<()>::new(4);
+/// driver.set_divergence_dir("divergences");
+///
+/// let driver = Arc::new(driver);
+/// start_differential_fuzzer::<u8, lain::byteorder::BigEndian, _, _, _, _, _>(
+///     driver.clone(),
+///     parse_v1,
+///     parse_v2,
+///     |a, b| a == b,
+/// );
+/// driver.join_threads();
+/// ```
+pub fn start_differential_fuzzer<V, E, A, B, O, P, T>(
+    driver: Arc<FuzzerDriver<T>>,
+    target_a: A,
+    target_b: B,
+    agree: P,
+) where
+    V: NewFuzzed + BinarySerialize + 'static,
+    E: ByteOrder + 'static,
+    A: Fn(&[u8]) -> O + Send + Sync + Copy + 'static,
+    B: Fn(&[u8]) -> O + Send + Sync + Copy + 'static,
+    O: Debug,
+    P: Fn(&O, &O) -> bool + Send + Sync + Copy + 'static,
+    T: 'static + Send + Sync,
+{
+    start_fuzzer(
+        driver,
+        move |mutator: &mut Mutator<StdRng>, context: &mut FuzzerContext<()>, _global_context| {
+            let value = V::new_fuzzed(mutator, None);
+            let input = context.serialize::<V, E>(&value);
+
+            let output_a = target_a(input);
+            let output_b = target_b(input);
+            if agree(&output_a, &output_b) {
+                return Ok(());
+            }
+
+            context.report_divergence(format!("{:?} != {:?}", output_a, output_b));
+
+            Err(())
+        },
+    );
+}
+
+/// The result of [minimize_corpus]
+#[derive(Debug, Clone, Default)]
+pub struct CorpusMinimization {
+    /// The inputs which together produce every signal the feedback provider observed across the
+    /// corpus, smallest first
+    pub corpus: Corpus<Vec<u8>>,
+    /// Inputs the feedback provider reported as crashing the target. These aren't part of
+    /// [CorpusMinimization::corpus].
+    pub crashing: Corpus<Vec<u8>>,
+}
+
+/// What the target did when running one candidate of [minimize_corpus]
+struct CandidateExecution {
+    response: Vec<u8>,
+    response_status: Option<ResponseStatus>,
+    exit_status: Option<ExitStatus>,
+    coverage: Option<Vec<u8>>,
+    callback_failed: bool,
+}
+
+/// Reduces `corpus` to a subset which preserves every feedback signal it produces, like AFL's
+/// `afl-cmin`. Every distinct input is run once by `callback` across the driver's threads and
+/// judged by `provider`, which should be freshly created so that it hasn't seen any signals yet.
+///
+/// The callback is handed a context whose [buffer][FuzzerContext::buffer] holds the input, and
+/// is responsible for running the target on it as a fuzzer callback would (e.g. with
+/// [FuzzerContext::send] or [FuzzerContext::execute]). The context's
+/// [iteration][FuzzerContext::iteration] is the input's index in `corpus`.
+///
+/// Inputs are judged smallest first, and an input is kept if the provider finds it
+/// [Feedback::Interesting]. Each signal is therefore kept by the smallest input which produces
+/// it. The provider sees the inputs in the same order no matter how the executions are spread
+/// over the threads, so the result only depends on the target's behavior. Minimization stops
+/// early if [FuzzerDriver::signal_exit] is called.
+pub fn minimize_corpus<C, T, P, F>(
+    driver: &FuzzerDriver<T>,
+    corpus: &Corpus<Vec<u8>>,
+    provider: &P,
+    callback: F,
+) -> CorpusMinimization
+where
+    C: Default,
+    T: 'static + Send + Sync,
+    P: FeedbackProvider + ?Sized,
+    F: Fn(&mut FuzzerContext<C>) -> Result<(), ()> + Sync,
+{
+    let entries = corpus.entries();
+
+    // duplicates are only run once
+    let mut candidates: Vec<usize> = (0..entries.len()).collect();
+    candidates.sort_by(|&a, &b| {
+        entries[a]
+            .len()
+            .cmp(&entries[b].len())
+            .then_with(|| entries[a].cmp(&entries[b]))
+            .then(a.cmp(&b))
+    });
+    candidates.dedup_by(|a, b| entries[*a] == entries[*b]);
+
+    let next_candidate = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel::<(usize, CandidateExecution)>();
+    let mut result = CorpusMinimization::default();
+
+    thread::scope(|scope| {
+        for i in 0..driver.thread_count {
+            let sender = sender.clone();
+            let candidates = &candidates;
+            let next_candidate = &next_candidate;
+            let callback = &callback;
+            let postprocessors = driver.postprocessors.clone();
+
+            scope.spawn(move || {
+                let mut context = FuzzerContext::<C>::new(i, postprocessors);
+
+                loop {
+                    let position = next_candidate.fetch_add(1, Ordering::SeqCst);
+                    let index = match candidates.get(position) {
+                        Some(&index) if !driver.exit.load(Ordering::SeqCst) => index,
+                        _ => return,
+                    };
+
+                    context.iteration = index;
+                    context.buffer.clear();
+                    context.buffer.extend_from_slice(&entries[index]);
+                    context.response.clear();
+                    context.response_status = None;
+                    context.exit_status = None;
+
+                    #[cfg(unix)]
+                    {
+                        if let Some(ref mut coverage) = context.coverage {
+                            coverage.clear();
+                        }
+                    }
+
+                    let callback_failed = callback(&mut context).is_err();
+
+                    let execution = CandidateExecution {
+                        response: context.response.clone(),
+                        response_status: context.response_status,
+                        exit_status: context.exit_status,
+                        coverage: context.coverage_slice().map(<[u8]>::to_vec),
+                        callback_failed,
+                    };
+
+                    // the receiver only hangs up if the main thread panicked
+                    if sender.send((position, execution)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        drop(sender);
+
+        // executions finish out of order, so they're held until every smaller candidate has
+        // been judged
+        let mut pending = HashMap::new();
+        let mut next_position = 0;
+
+        for (position, execution) in receiver {
+            pending.insert(position, execution);
+
+            while let Some(execution) = pending.remove(&next_position) {
+                let input = &entries[candidates[next_position]];
+                let observation = Observation {
+                    response: &execution.response,
+                    response_status: execution.response_status,
+                    exit_status: execution.exit_status,
+                    coverage: execution.coverage.as_deref(),
+                    callback_failed: execution.callback_failed,
+                };
+
+                match provider.evaluate(input, &observation) {
+                    Feedback::Interesting => result.corpus.push(input.clone()),
+                    Feedback::Crash => result.crashing.push(input.clone()),
+                    Feedback::Boring => {}
+                }
+
+                next_position += 1;
+            }
+        }
+    });
+
+    log::info!(
+        "minimized {} inputs to {} ({} crashing)",
+        entries.len(),
+        result.corpus.len(),
+        result.crashing.len()
+    );
+
+    result
+}
@@ -0,0 +1,70 @@
+use crate::traits::*;
+use crate::types::*;
+
+impl Enumerable for bool {
+    fn value_count() -> usize {
+        2
+    }
+
+    fn value_at(index: usize) -> Self {
+        match index {
+            0 => false,
+            1 => true,
+            _ => panic!("index {} is out of range for bool", index),
+        }
+    }
+}
+
+macro_rules! impl_enumerable {
+    ( $($name:ident),* ) => {
+        $(
+            impl Enumerable for $name {
+                fn value_count() -> usize {
+                    1 << (std::mem::size_of::<$name>() * 8)
+                }
+
+                fn value_at(index: usize) -> Self {
+                    if index >= Self::value_count() {
+                        panic!("index {} is out of range for {}", index, stringify!($name));
+                    }
+
+                    // values are enumerated starting from the type's minimum
+                    ($name::MIN as i64 + index as i64) as $name
+                }
+            }
+        )*
+    }
+}
+
+impl_enumerable!(u8, i8);
+
+/// Only the valid variants of an [UnsafeEnum] are enumerated
+impl<T, I> Enumerable for UnsafeEnum<T, I>
+where
+    T: Enumerable,
+{
+    fn value_count() -> usize {
+        T::value_count()
+    }
+
+    fn value_at(index: usize) -> Self {
+        UnsafeEnum::Valid(T::value_at(index))
+    }
+}
+
+impl<T> Enumerable for Option<T>
+where
+    T: Enumerable,
+{
+    fn value_count() -> usize {
+        T::value_count() + 1
+    }
+
+    fn value_at(index: usize) -> Self {
+        if index == 0 {
+            None
+        } else {
+            Some(T::value_at(index - 1))
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! The error type returned by lain's fallible APIs.
+//!
+//! Most of lain's older APIs either panic when something goes wrong or return an [io::Error]
+//! which doesn't say what was being done. Newer APIs, such as [Corpus::try_dedup],
+//! [CorpusStore::load_strict], and [Constraints::check], return an [Error] instead so that
+//! callers can tell a value which couldn't be serialized from a corpus which couldn't be read.
+//! An [Error] converts into an [io::Error] for code built around the older APIs.
+//!
+//! [Corpus::try_dedup]: crate::corpus::Corpus::try_dedup
+//! [CorpusStore::load_strict]: crate::corpus::CorpusStore::load_strict
+//! [Constraints::check]: crate::types::Constraints::check
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Result of lain's fallible APIs
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A value couldn't be serialized
+    Serialize(io::Error),
+    /// Bytes couldn't be deserialized into a `type_name`
+    Deserialize {
+        type_name: &'static str,
+        source: io::Error,
+    },
+    /// A value lies outside of the bounds of its constraints. Both are formatted with `Debug`.
+    ConstraintViolation { value: String, constraints: String },
+    /// Reading or writing the corpus file or directory at `path` failed
+    CorpusIo { path: PathBuf, source: io::Error },
+}
+
+impl Error {
+    pub(crate) fn deserialize<T>(source: io::Error) -> Error {
+        Error::Deserialize {
+            type_name: std::any::type_name::<T>(),
+            source,
+        }
+    }
+
+    pub(crate) fn corpus_io<P: Into<PathBuf>>(path: P, source: io::Error) -> Error {
+        Error::CorpusIo {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Serialize(source) => write!(f, "failed to serialize value: {}", source),
+            Error::Deserialize { type_name, source } => {
+                write!(f, "failed to deserialize {}: {}", type_name, source)
+            }
+            Error::ConstraintViolation { value, constraints } => {
+                write!(f, "{} violates {}", value, constraints)
+            }
+            Error::CorpusIo { path, source } => {
+                write!(f, "corpus I/O failed on {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Serialize(source)
+            | Error::Deserialize { source, .. }
+            | Error::CorpusIo { source, .. } => Some(source),
+            Error::ConstraintViolation { .. } => None,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> io::Error {
+        let kind = match &error {
+            Error::Serialize(source)
+            | Error::Deserialize { source, .. }
+            | Error::CorpusIo { source, .. } => source.kind(),
+            Error::ConstraintViolation { .. } => io::ErrorKind::InvalidData,
+        };
+
+        io::Error::new(kind, error)
+    }
+}
@@ -0,0 +1,527 @@
+//! Running target binaries as child processes.
+//!
+//! A [ProcessExecutor] spawns the target for every input (or, with [ExecConfig::forkserver],
+//! asks an AFL-style forkserver in the target to fork a fresh child), delivers the input through
+//! stdin, a file, or the command line, and reports how the target exited. Executors are meant to
+//! be stored in a thread's `user_data` and used with
+//! [FuzzerContext::execute](crate::driver::FuzzerContext::execute).
+//!
+//! Inputs and captured output are passed through files rather than pipes so that a target which
+//! never reads its input (or floods its output) can't stall the fuzzer.
+
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Arguments equal to this are replaced with the input file's path (or, with [InputMode::Argv],
+/// the input itself)
+pub const INPUT_PLACEHOLDER: &str = "@@";
+
+/// File descriptor the forkserver reads commands from
+const FORKSERVER_CONTROL_FD: libc::c_int = 198;
+/// File descriptor the forkserver writes child PIDs and statuses to
+const FORKSERVER_STATUS_FD: libc::c_int = 199;
+
+/// How the input is handed to the target
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum InputMode {
+    /// Through the target's stdin
+    #[default]
+    Stdin,
+    /// Written to the given file, whose path replaces `@@` in the target's arguments
+    File(PathBuf),
+    /// As the argument in place of `@@`. Inputs can't contain NUL bytes, and this can't be used
+    /// with a forkserver since the arguments are fixed when the forkserver starts.
+    Argv,
+}
+
+/// Settings for running the target
+#[derive(Debug, Clone)]
+pub struct ExecConfig {
+    pub input: InputMode,
+    /// How long the target may run before it's killed. `None` waits indefinitely.
+    pub timeout: Option<Duration>,
+    /// Start the target once and have its AFL-style forkserver fork a child for every input,
+    /// which avoids paying for `exec` and the target's initialization on every run. The target
+    /// must be built with AFL's instrumentation.
+    pub forkserver: bool,
+    /// Capture what the target writes to stdout. Otherwise its output is discarded.
+    pub capture_output: bool,
+    /// Maximum number of output bytes captured per run
+    pub max_output_size: usize,
+}
+
+impl Default for ExecConfig {
+    fn default() -> Self {
+        ExecConfig {
+            input: InputMode::default(),
+            timeout: Some(Duration::from_secs(1)),
+            forkserver: false,
+            capture_output: false,
+            max_output_size: 0x10000,
+        }
+    }
+}
+
+/// How a run of the target ended
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExecStatus {
+    /// The target exited normally with the given code
+    Exited(i32),
+    /// The target was killed by the given signal
+    Crashed(i32),
+    /// The target didn't finish within [ExecConfig::timeout] and was killed
+    TimedOut,
+}
+
+/// Runs a target binary on fuzzed inputs
+pub struct ProcessExecutor {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    config: ExecConfig,
+    /// Path of the input file. Temporary files get their path when they're created.
+    input_path: PathBuf,
+    /// Handle to the input file which shares its file offset with the target's stdin
+    input_file: Option<File>,
+    output_path: PathBuf,
+    output_file: Option<File>,
+    forkserver: Option<Forkserver>,
+    exit_status: Option<ExitStatus>,
+}
+
+impl ProcessExecutor {
+    pub fn new<P, I, S>(program: P, args: I, config: ExecConfig) -> io::Result<ProcessExecutor>
+    where
+        P: AsRef<OsStr>,
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        if config.forkserver && config.input == InputMode::Argv {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "inputs can't be passed as arguments to a forkserver",
+            ));
+        }
+
+        let input_path = match config.input {
+            InputMode::File(ref path) => path.clone(),
+            _ => PathBuf::new(),
+        };
+
+        Ok(ProcessExecutor {
+            program: program.as_ref().to_os_string(),
+            args: args
+                .into_iter()
+                .map(|arg| arg.as_ref().to_os_string())
+                .collect(),
+            envs: Vec::new(),
+            config,
+            input_path,
+            input_file: None,
+            output_path: PathBuf::new(),
+            output_file: None,
+            forkserver: None,
+            exit_status: None,
+        })
+    }
+
+    /// Sets an environment variable for the target. Changes don't affect a forkserver which is
+    /// already running.
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(&mut self, key: K, value: V) -> &mut Self {
+        let key = key.as_ref().to_os_string();
+        let value = value.as_ref().to_os_string();
+
+        match self.envs.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.envs.push((key, value)),
+        }
+
+        self
+    }
+
+    /// The exit status of the last run, if it finished
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.exit_status
+    }
+
+    /// Runs the target on `data`, appending anything it writes to stdout to `output` if
+    /// [ExecConfig::capture_output] is set
+    pub fn run(&mut self, data: &[u8], output: &mut Vec<u8>) -> io::Result<ExecStatus> {
+        self.exit_status = None;
+
+        if self.config.input != InputMode::Argv {
+            self.write_input(data)?;
+        }
+
+        if self.config.capture_output {
+            let output_file = self.output_file()?;
+            output_file.set_len(0)?;
+            output_file.seek(SeekFrom::Start(0))?;
+        }
+
+        let (exit_status, timed_out) = if self.config.forkserver {
+            self.run_forkserver()?
+        } else {
+            self.run_process(data)?
+        };
+
+        self.exit_status = Some(exit_status);
+
+        if self.config.capture_output {
+            let max_output_size = self.config.max_output_size as u64;
+            File::open(&self.output_path)?
+                .take(max_output_size)
+                .read_to_end(output)?;
+        }
+
+        if timed_out {
+            return Ok(ExecStatus::TimedOut);
+        }
+
+        Ok(match exit_status.code() {
+            Some(code) => ExecStatus::Exited(code),
+            None => ExecStatus::Crashed(exit_status.signal().unwrap_or(0)),
+        })
+    }
+
+    fn write_input(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.input_file.is_none() {
+            self.input_file = Some(match self.config.input {
+                InputMode::File(ref path) => open_rw(path)?,
+                _ => {
+                    let (path, file) = create_temp("input")?;
+                    self.input_path = path;
+                    file
+                }
+            });
+        }
+
+        let input_file = self.input_file.as_mut().unwrap();
+        input_file.set_len(0)?;
+        input_file.seek(SeekFrom::Start(0))?;
+        input_file.write_all(data)?;
+        // the target's stdin shares this offset
+        input_file.seek(SeekFrom::Start(0))?;
+
+        Ok(())
+    }
+
+    fn output_file(&mut self) -> io::Result<&mut File> {
+        if self.output_file.is_none() {
+            let (path, file) = create_temp("output")?;
+            self.output_path = path;
+            self.output_file = Some(file);
+        }
+
+        Ok(self.output_file.as_mut().unwrap())
+    }
+
+    fn command(&mut self, data: &[u8]) -> io::Result<Command> {
+        let mut command = Command::new(&self.program);
+
+        for arg in &self.args {
+            if arg != INPUT_PLACEHOLDER {
+                command.arg(arg);
+            } else if self.config.input == InputMode::Argv {
+                command.arg(OsStr::from_bytes(data));
+            } else {
+                command.arg(&self.input_path);
+            }
+        }
+
+        command.envs(self.envs.iter().map(|(k, v)| (k, v)));
+
+        match self.config.input {
+            InputMode::Stdin => {
+                command.stdin(self.input_file.as_ref().unwrap().try_clone()?);
+            }
+            _ => {
+                command.stdin(Stdio::null());
+            }
+        }
+
+        if self.config.capture_output {
+            command.stdout(self.output_file()?.try_clone()?);
+        } else {
+            command.stdout(Stdio::null());
+        }
+
+        command.stderr(Stdio::null());
+
+        Ok(command)
+    }
+
+    fn run_process(&mut self, data: &[u8]) -> io::Result<(ExitStatus, bool)> {
+        let mut child = self.command(data)?.spawn()?;
+
+        wait_timeout(&mut child, self.config.timeout)
+    }
+
+    fn run_forkserver(&mut self) -> io::Result<(ExitStatus, bool)> {
+        if self.forkserver.is_none() {
+            let command = self.command(&[])?;
+            self.forkserver = Some(Forkserver::start(command, self.config.timeout)?);
+        }
+
+        let result = self
+            .forkserver
+            .as_mut()
+            .unwrap()
+            .run_child(self.config.timeout);
+
+        if result.is_err() {
+            // the forkserver died or fell out of sync, so start over on the next run
+            self.forkserver = None;
+        }
+
+        result
+    }
+}
+
+impl Drop for ProcessExecutor {
+    fn drop(&mut self) {
+        self.forkserver = None;
+
+        if self.input_file.is_some() && self.config.input == InputMode::Stdin {
+            let _ = fs::remove_file(&self.input_path);
+        }
+
+        if self.output_file.is_some() {
+            let _ = fs::remove_file(&self.output_path);
+        }
+    }
+}
+
+/// A running target which forks a child for every input using AFL's forkserver protocol
+struct Forkserver {
+    process: Child,
+    control: File,
+    status: File,
+    last_timed_out: bool,
+}
+
+impl Forkserver {
+    fn start(mut command: Command, timeout: Option<Duration>) -> io::Result<Forkserver> {
+        let (control_read, control_write) = pipe()?;
+        let (status_read, status_write) = pipe()?;
+
+        let child_control = control_read.as_raw_fd();
+        let child_status = status_write.as_raw_fd();
+
+        unsafe {
+            command.pre_exec(move || {
+                if libc::dup2(child_control, FORKSERVER_CONTROL_FD) < 0
+                    || libc::dup2(child_status, FORKSERVER_STATUS_FD) < 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+
+                Ok(())
+            });
+        }
+
+        let process = command.spawn()?;
+
+        // the child has its own copies of these
+        drop(control_read);
+        drop(status_write);
+
+        let mut forkserver = Forkserver {
+            process,
+            control: control_write,
+            status: status_read,
+            last_timed_out: false,
+        };
+
+        // give the target plenty of time to initialize before it says hello
+        let hello_timeout =
+            timeout.map(|timeout| std::cmp::max(timeout * 10, Duration::from_secs(10)));
+        if forkserver.read_status(hello_timeout)?.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the forkserver didn't start in time",
+            ));
+        }
+
+        Ok(forkserver)
+    }
+
+    fn run_child(&mut self, timeout: Option<Duration>) -> io::Result<(ExitStatus, bool)> {
+        self.control
+            .write_all(&(self.last_timed_out as u32).to_ne_bytes())?;
+
+        let pid = self
+            .read_status(None)?
+            .expect("reads without a timeout don't time out") as libc::pid_t;
+
+        if pid <= 0 {
+            return Err(io::Error::other("the forkserver failed to fork"));
+        }
+
+        let (status, timed_out) = match self.read_status(timeout)? {
+            Some(status) => (status, false),
+            None => {
+                unsafe {
+                    libc::kill(pid, libc::SIGKILL);
+                }
+
+                let status = self
+                    .read_status(None)?
+                    .expect("reads without a timeout don't time out");
+
+                (status, true)
+            }
+        };
+
+        self.last_timed_out = timed_out;
+
+        Ok((ExitStatus::from_raw(status as i32), timed_out))
+    }
+
+    /// Reads a 4 byte value from the status pipe. Returns `None` if nothing arrived within
+    /// `timeout`.
+    fn read_status(&mut self, timeout: Option<Duration>) -> io::Result<Option<u32>> {
+        if let Some(timeout) = timeout {
+            let mut pollfd = libc::pollfd {
+                fd: self.status.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+
+            let timeout_ms = timeout.as_millis().min(libc::c_int::MAX as u128);
+            let ready = unsafe { libc::poll(&mut pollfd, 1, timeout_ms as libc::c_int) };
+            if ready < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            if ready == 0 {
+                return Ok(None);
+            }
+        }
+
+        let mut value = [0u8; 4];
+        self.status.read_exact(&mut value).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "the forkserver exited. Is the target built with AFL's instrumentation?",
+                )
+            } else {
+                e
+            }
+        })?;
+
+        Ok(Some(u32::from_ne_bytes(value)))
+    }
+}
+
+impl Drop for Forkserver {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// Waits for `child` to exit, killing it if it runs longer than `timeout`. Returns its exit
+/// status and whether it timed out.
+fn wait_timeout(child: &mut Child, timeout: Option<Duration>) -> io::Result<(ExitStatus, bool)> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return Ok((child.wait()?, false)),
+    };
+
+    let start = Instant::now();
+    let mut delay = Duration::from_micros(50);
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status, false));
+        }
+
+        if start.elapsed() >= timeout {
+            // the child may have exited in the meantime, in which case the kill fails harmlessly
+            let _ = child.kill();
+            return Ok((child.wait()?, true));
+        }
+
+        thread::sleep(delay);
+        delay = std::cmp::min(delay * 2, Duration::from_millis(5));
+    }
+}
+
+/// Creates a pipe whose ends are closed in child processes unless they're explicitly inherited
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+fn pipe() -> io::Result<(File, File)> {
+    let mut fds = [0 as libc::c_int; 2];
+    // the ends are close-on-exec from the start so a process spawned by another thread can't
+    // inherit them
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) })
+}
+
+/// Creates a pipe whose ends are closed in child processes unless they're explicitly inherited.
+/// There's no `pipe2` here, so a process spawned by another thread before the flags are set may
+/// inherit the ends.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn pipe() -> io::Result<(File, File)> {
+    let mut fds = [0 as libc::c_int; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let (read, write) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+    for fd in &fds {
+        if unsafe { libc::fcntl(*fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok((read, write))
+}
+
+fn open_rw(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+}
+
+/// Creates a new file in the temp directory which only the current user can access. The name is
+/// random and the file must not already exist, so nobody can plant a file or symlink there first.
+fn create_temp(kind: &str) -> io::Result<(PathBuf, File)> {
+    loop {
+        let path = std::env::temp_dir().join(format!(
+            "lain-{}-{}-{:016x}",
+            kind,
+            std::process::id(),
+            rand::random::<u64>()
+        ));
+
+        match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Response-driven feedback for deciding which inputs are worth keeping.
+//!
+//! A [FeedbackProvider] registered with the [FuzzerDriver][crate::driver::FuzzerDriver] is
+//! given every iteration's input along with what the target did in response. Inputs judged
+//! interesting or crashing are retained by the driver.
+
+use crate::net::ResponseStatus;
+use crate::traits::FeedbackProvider;
+
+use std::collections::HashSet;
+use std::process::ExitStatus;
+use std::sync::Mutex;
+
+/// A [FeedbackProvider]'s verdict on an iteration
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Feedback {
+    /// The input made the target do something new and should be kept
+    Interesting,
+    /// Nothing noteworthy happened
+    Boring,
+    /// The input crashed (or hung) the target
+    Crash,
+}
+
+/// What the target did during an iteration. This is gathered from the
+/// [FuzzerContext][crate::driver::FuzzerContext] after the fuzzer callback returns.
+#[derive(Debug, Clone, Copy)]
+pub struct Observation<'a> {
+    /// Bytes received from the target
+    pub response: &'a [u8],
+    /// How reading the response went, if the input was sent with
+    /// [FuzzerContext::send][crate::driver::FuzzerContext::send]
+    pub response_status: Option<ResponseStatus>,
+    /// The target's exit status, if the callback ran it as a process and recorded it
+    pub exit_status: Option<ExitStatus>,
+    /// Edge hit counts recorded by an instrumented target, if the thread has a
+    /// [FuzzerContext::coverage][crate::driver::FuzzerContext::coverage] map
+    pub coverage: Option<&'a [u8]>,
+    /// Whether the fuzzer callback returned an error
+    pub callback_failed: bool,
+}
+
+/// Considers an input interesting when the first `prefix_len` bytes of the response (e.g. a
+/// status code) haven't been seen before, and a crash when the target exited unsuccessfully.
+pub struct UniqueResponseFeedback {
+    prefix_len: usize,
+    seen: Mutex<HashSet<Vec<u8>>>,
+}
+
+impl UniqueResponseFeedback {
+    pub fn new(prefix_len: usize) -> UniqueResponseFeedback {
+        UniqueResponseFeedback {
+            prefix_len,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Number of distinct response prefixes seen so far
+    pub fn unique_responses(&self) -> usize {
+        self.seen.lock().unwrap().len()
+    }
+}
+
+impl FeedbackProvider for UniqueResponseFeedback {
+    fn evaluate(&self, _input: &[u8], observation: &Observation) -> Feedback {
+        if let Some(exit_status) = observation.exit_status {
+            if !exit_status.success() {
+                return Feedback::Crash;
+            }
+        }
+
+        let prefix_len = std::cmp::min(self.prefix_len, observation.response.len());
+        let prefix = &observation.response[..prefix_len];
+
+        let mut seen = self.seen.lock().unwrap();
+        if seen.contains(prefix) {
+            return Feedback::Boring;
+        }
+
+        seen.insert(prefix.to_vec());
+
+        Feedback::Interesting
+    }
+}
+
+/// Considers an input interesting when it hits an edge of the target's
+/// [coverage map][Observation::coverage] which hasn't been hit before, or hits a known edge a
+/// different number of times. Like AFL, hit counts are grouped into buckets (1, 2, 3, 4-7, 8-15,
+/// 16-31, 32-127, 128+) so that loops don't make every input look new. Unsuccessful exits are
+/// reported as crashes.
+pub struct CoverageFeedback {
+    /// For each edge, the buckets which haven't been seen yet
+    virgin: Mutex<Vec<u8>>,
+}
+
+impl CoverageFeedback {
+    pub fn new() -> CoverageFeedback {
+        CoverageFeedback {
+            virgin: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Number of edges hit by any input so far
+    pub fn edges_seen(&self) -> usize {
+        self.virgin
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|&&buckets| buckets != 0xFF)
+            .count()
+    }
+}
+
+impl Default for CoverageFeedback {
+    fn default() -> Self {
+        CoverageFeedback::new()
+    }
+}
+
+impl FeedbackProvider for CoverageFeedback {
+    fn evaluate(&self, _input: &[u8], observation: &Observation) -> Feedback {
+        if let Some(exit_status) = observation.exit_status {
+            if !exit_status.success() {
+                return Feedback::Crash;
+            }
+        }
+
+        let coverage = match observation.coverage {
+            Some(coverage) => coverage,
+            None => return Feedback::Boring,
+        };
+
+        let mut virgin = self.virgin.lock().unwrap();
+        if virgin.len() < coverage.len() {
+            virgin.resize(coverage.len(), 0xFF);
+        }
+
+        let mut interesting = false;
+        for (&hits, buckets) in coverage.iter().zip(virgin.iter_mut()) {
+            let bucket = hit_count_bucket(hits);
+            if bucket & *buckets != 0 {
+                *buckets &= !bucket;
+                interesting = true;
+            }
+        }
+
+        if interesting {
+            Feedback::Interesting
+        } else {
+            Feedback::Boring
+        }
+    }
+}
+
+/// Maps an edge's hit count to a single bit identifying its bucket
+fn hit_count_bucket(hits: u8) -> u8 {
+    match hits {
+        0 => 0,
+        1 => 1,
+        2 => 2,
+        3 => 4,
+        4..=7 => 8,
+        8..=15 => 16,
+        16..=31 => 32,
+        32..=127 => 64,
+        _ => 128,
+    }
+}
@@ -0,0 +1,433 @@
+//! One-liner fuzz targets.
+//!
+//! The [fuzz!][crate::fuzz] macro wires a [NewFuzzed] type and a target closure up to a
+//! [FuzzerDriver] with the plumbing most harnesses otherwise copy from the examples:
+//!
+//! - Ctrl-C stops the fuzzer threads and prints a summary instead of killing the process
+//! - campaign stats are printed to stderr periodically, and can be exported with
+//!   [HarnessOptions::stats_file] or [HarnessOptions::prometheus]
+//! - failing iterations are logged with the command line which reproduces them
+//!
+//! ```no_run
+//! use lain::prelude::*;
+//!
+//! #[derive(Debug, NewFuzzed, BinarySerialize)]
+//! struct Message {
+//!     id: u32,
+//!     length: u8,
+//! }
+//!
+//! fn main() {
+//!     lain::fuzz!(Message, threads = 8, |msg, ctx| {
+//!         let _bytes = ctx.serialize::<_, BigEndian>(msg);
+//!         // send the bytes to the target...
+//!         Ok::<(), std::io::Error>(())
+//!     });
+//! }
+//! ```
+//!
+//! The target closure may return `()` or any `Result<_, E: Debug>`; an `Err` marks the
+//! iteration as failed.
+//!
+//! [FuzzerContext::danger_score] tells the target how many deliberately invalid or extreme
+//! choices went into the value, e.g. for sending risky inputs to a sandboxed instance.
+//!
+//! A harness built with the macro understands the following command line arguments, which take
+//! precedence over the options given to the macro:
+//!
+//! - `--threads N`
+//! - `--seed N`
+//! - `--iterations N` stops after roughly N iterations
+//! - `--reproduce START[..END]` replays iterations `START` up to `END` (exclusive). The seed and
+//!   thread count of the original run must be given as well.
+//! - `--dry-run N` prints the first N generated values instead of running the target
+//! - `--stats FILE` appends a JSON snapshot of the campaign's stats to FILE
+//!
+//! Unrecognized arguments are ignored.
+
+use crate::driver::{self, FuzzerContext, FuzzerDriver};
+use crate::mutator::Mutator;
+use crate::traits::NewFuzzed;
+
+use rand::rngs::StdRng;
+
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the monitor thread checks for Ctrl-C and the iteration limit
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Arguments recognized by [HarnessOptions::apply_args]
+const HARNESS_ARGS: &[&str] = &[
+    "--threads",
+    "--seed",
+    "--iterations",
+    "--reproduce",
+    "--dry-run",
+    "--stats",
+];
+
+/// Set by the SIGINT handler installed by [run]
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Configuration for [run]. The [fuzz!][crate::fuzz] macro calls the builder method named by
+/// each of its `key = value` arguments.
+#[derive(Debug, Clone)]
+pub struct HarnessOptions {
+    threads: usize,
+    seed: Option<u64>,
+    iterations: Option<usize>,
+    reproduce: Option<(u64, u64)>,
+    dry_run: Option<usize>,
+    stats_interval: Duration,
+    stats_file: Option<PathBuf>,
+    prometheus: Option<String>,
+}
+
+impl Default for HarnessOptions {
+    /// One thread per logical CPU, a random seed, and stats printed every 10 seconds
+    fn default() -> Self {
+        HarnessOptions {
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            seed: None,
+            iterations: None,
+            reproduce: None,
+            dry_run: None,
+            stats_interval: Duration::from_secs(10),
+            stats_file: None,
+            prometheus: None,
+        }
+    }
+}
+
+impl HarnessOptions {
+    /// Number of fuzzer threads
+    pub fn threads(mut self, threads: usize) -> Self {
+        if threads == 0 {
+            panic!("a harness needs at least one fuzzer thread");
+        }
+
+        self.threads = threads;
+        self
+    }
+
+    /// Root seed for the driver. A random seed is used if this isn't set.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Stops the campaign after roughly `iterations` iterations. Threads finish the iteration
+    /// they're in, so a few more may be executed.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    /// Replays iterations `start` up to `end` (exclusive) instead of fuzzing
+    pub fn reproduce(mut self, start: u64, end: u64) -> Self {
+        self.reproduce = Some((start, end));
+        self
+    }
+
+    /// Prints the first `count` generated values instead of running the target
+    pub fn dry_run(mut self, count: usize) -> Self {
+        self.dry_run = Some(count);
+        self
+    }
+
+    /// How often stats are printed to stderr and appended to the [stats file][HarnessOptions::stats_file]
+    pub fn stats_interval(mut self, interval: Duration) -> Self {
+        self.stats_interval = interval;
+        self
+    }
+
+    /// Appends a JSON snapshot of the campaign's stats to `path` every stats interval
+    pub fn stats_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.stats_file = Some(path.into());
+        self
+    }
+
+    /// Serves the campaign's stats for Prometheus on `addr`
+    pub fn prometheus<A: Into<String>>(mut self, addr: A) -> Self {
+        self.prometheus = Some(addr.into());
+        self
+    }
+
+    /// Applies the arguments described in the [module documentation][crate::harness] on top
+    /// of these options.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a recognized argument is missing its value or the value is malformed.
+    pub fn apply_args<I: IntoIterator<Item = String>>(mut self, args: I) -> Self {
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.find('=') {
+                Some(index) => (arg[..index].to_string(), Some(arg[index + 1..].to_string())),
+                None => (arg.clone(), None),
+            };
+
+            if !HARNESS_ARGS.contains(&name.as_str()) {
+                continue;
+            }
+
+            let value = inline_value
+                .or_else(|| args.next())
+                .unwrap_or_else(|| panic!("{} requires a value", name));
+
+            self = match name.as_str() {
+                "--threads" => self.threads(parse_arg(&name, &value)),
+                "--seed" => self.seed(parse_arg(&name, &value)),
+                "--iterations" => self.iterations(parse_arg(&name, &value)),
+                "--dry-run" => self.dry_run(parse_arg(&name, &value)),
+                "--stats" => self.stats_file(value),
+                _ => {
+                    let (start, end) = match value.find("..") {
+                        Some(index) => (
+                            parse_arg(&name, &value[..index]),
+                            parse_arg(&name, &value[index + 2..]),
+                        ),
+                        None => {
+                            let start: u64 = parse_arg(&name, &value);
+                            (start, start + 1)
+                        }
+                    };
+
+                    self.reproduce(start, end)
+                }
+            };
+        }
+
+        self
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(name: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| panic!("invalid value {:?} for {}", value, name))
+}
+
+/// The value returned by a harness target, which decides whether the iteration failed
+pub trait IterationOutcome {
+    /// Describes the failure, or returns `None` if the iteration succeeded
+    fn failure(&self) -> Option<String>;
+}
+
+impl IterationOutcome for () {
+    fn failure(&self) -> Option<String> {
+        None
+    }
+}
+
+impl<T, E: Debug> IterationOutcome for Result<T, E> {
+    fn failure(&self) -> Option<String> {
+        self.as_ref().err().map(|err| format!("{:?}", err))
+    }
+}
+
+/// Runs `target` against a freshly generated `V` every iteration until the process is
+/// interrupted or the configured iteration limit is reached. This is what [fuzz!][crate::fuzz]
+/// expands to; the command line arguments are applied on top of `options`.
+pub fn run<V, F, O>(options: HarnessOptions, target: F)
+where
+    V: NewFuzzed + Debug + 'static,
+    F: Fn(&V, &mut FuzzerContext<()>) -> O + Send + Sync + Copy + 'static,
+    O: IterationOutcome,
+{
+    let options = options.apply_args(std::env::args().skip(1));
+
+    let mut driver = FuzzerDriver::<()>::new(options.threads);
+    if let Some(seed) = options.seed {
+        driver.set_seed(seed);
+    }
+
+    if let Some(count) = options.dry_run {
+        driver::generate_dry_run::<V, _, _>(&driver, count, |iteration, value| {
+            println!("Iteration {}:\n{:#?}", iteration, value);
+        });
+        return;
+    }
+
+    if let Some((start, end)) = options.reproduce {
+        driver.set_to_reproduce_mode(start, end);
+    }
+
+    let seed = driver.seed();
+    let threads = options.threads;
+    let driver = Arc::new(driver);
+
+    let _json_exporter = options.stats_file.as_ref().map(|path| {
+        driver
+            .export_stats_json(path, options.stats_interval)
+            .unwrap_or_else(|e| panic!("couldn't open stats file {:?}: {}", path, e))
+    });
+    let _prometheus_exporter = options.prometheus.as_ref().map(|addr| {
+        driver
+            .export_stats_prometheus(addr.as_str())
+            .unwrap_or_else(|e| panic!("couldn't serve stats on {}: {}", addr, e))
+    });
+
+    let previous_handler = install_interrupt_handler();
+
+    log::info!("fuzzing with seed {} on {} threads", seed, threads);
+
+    driver::start_fuzzer(
+        driver.clone(),
+        move |mutator: &mut Mutator<StdRng>,
+              context: &mut FuzzerContext<()>,
+              _global_context: Option<Arc<RwLock<()>>>| {
+            let value = V::new_fuzzed(mutator, None);
+            context.danger_score = mutator.danger_score();
+
+            match target(&value, context).failure() {
+                Some(failure) => {
+                    log::warn!(
+                        "iteration {} failed on thread {}: {} (reproduce with --seed {} --threads {} --reproduce {})",
+                        context.iteration(),
+                        context.thread_index(),
+                        failure,
+                        seed,
+                        threads,
+                        context.iteration()
+                    );
+
+                    Err(())
+                }
+                None => Ok(()),
+            }
+        },
+    );
+
+    let finished = Arc::new(AtomicBool::new(false));
+    let monitor = {
+        let driver = driver.clone();
+        let finished = finished.clone();
+        let iteration_limit = options.iterations;
+        let stats_interval = options.stats_interval;
+
+        thread::spawn(move || monitor(&driver, &finished, iteration_limit, stats_interval))
+    };
+
+    driver.join_threads();
+    finished.store(true, Ordering::SeqCst);
+    monitor.join().expect("harness monitor thread panicked");
+
+    restore_interrupt_handler(previous_handler);
+
+    let snapshot = driver.stats().snapshot();
+    eprintln!(
+        "Finished in {} iterations ({} failed) with seed {}",
+        snapshot.iterations, snapshot.failed_iterations, seed
+    );
+}
+
+/// Stops the driver when the process is interrupted or the iteration limit is reached, and
+/// prints stats every `stats_interval` until the fuzzer threads have joined
+fn monitor(
+    driver: &FuzzerDriver<()>,
+    finished: &AtomicBool,
+    iteration_limit: Option<usize>,
+    stats_interval: Duration,
+) {
+    let mut last_report = Instant::now();
+
+    while !finished.load(Ordering::SeqCst) {
+        let limit_reached = iteration_limit.is_some_and(|limit| driver.num_iterations() >= limit);
+
+        if INTERRUPTED.swap(false, Ordering::SeqCst) || limit_reached {
+            driver.signal_exit();
+        }
+
+        if last_report.elapsed() >= stats_interval {
+            let snapshot = driver.stats().snapshot();
+            eprintln!(
+                "[{}s] {} iterations ({:.1}/s), {} failed",
+                snapshot.elapsed.as_secs(),
+                snapshot.iterations,
+                snapshot.execs_per_sec,
+                snapshot.failed_iterations
+            );
+
+            last_report = Instant::now();
+        }
+
+        thread::sleep(MONITOR_POLL_INTERVAL);
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn handle_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+
+    // a second Ctrl-C kills the process if the threads are stuck
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+}
+
+#[cfg(unix)]
+fn install_interrupt_handler() -> libc::sighandler_t {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            handle_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        )
+    }
+}
+
+#[cfg(unix)]
+fn restore_interrupt_handler(previous: libc::sighandler_t) {
+    unsafe {
+        libc::signal(libc::SIGINT, previous);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_interrupt_handler() {}
+
+#[cfg(not(unix))]
+fn restore_interrupt_handler(_previous: ()) {}
+
+/// Expands to a complete fuzzer for `Type`: driver setup, Ctrl-C handling, stats, and
+/// reproduction of failing iterations (see [harness][crate::harness]).
+///
+/// Any `key = value` pairs before the closure call the [HarnessOptions] builder method of the
+/// same name. The closure is given a reference to each generated value and the iteration's
+/// [FuzzerContext][crate::driver::FuzzerContext], and must not capture anything.
+///
+/// ```no_run
+/// use lain::prelude::*;
+///
+/// #[derive(Debug, NewFuzzed)]
+/// struct Message {
+///     id: u32,
+/// }
+///
+/// fn main() {
+///     lain::fuzz!(Message, threads = 4, seed = 1234, |msg, _ctx| {
+///         assert!(msg.id != 0xdeadbeef);
+///     });
+/// }
+/// ```
+#[macro_export]
+macro_rules! fuzz {
+    ($ty:ty, $($key:ident = $value:expr,)* |$msg:ident, $ctx:ident| $body:expr $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut options = $crate::harness::HarnessOptions::default();
+        $(options = options.$key($value);)*
+
+        $crate::harness::run::<$ty, _, _>(
+            options,
+            |$msg: &$ty, $ctx: &mut $crate::driver::FuzzerContext<()>| $body,
+        )
+    }};
+}
@@ -0,0 +1,501 @@
+//! Support for collections which store their elements inline: `SmallVec<[T; N]>` (with the
+//! `smallvec_support` feature) and `ArrayVec<T, N>` (with the `arrayvec_support` feature).
+//!
+//! These serialize exactly like a `Vec` of the same elements, but can model variable-length
+//! fields without a heap allocation per value. When generating them without a `max`, the inline
+//! capacity is used instead. `SmallVec`s occasionally grow past it (and spill to the heap) the
+//! same way other constraints are occasionally ignored, while `ArrayVec`s never can.
+
+use crate::debug::{short_type_name, DebugElements};
+use crate::mutatable::MaybeDuplicateElement;
+use crate::mutator::{FieldCategory, Mutator};
+use crate::rand::Rng;
+use crate::traits::*;
+use crate::types::*;
+
+use byteorder::ByteOrder;
+use std::cmp;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "arrayvec_support")]
+use arrayvec::ArrayVec;
+#[cfg(feature = "smallvec_support")]
+use smallvec::{Array, SmallVec};
+
+/// Picks how many elements to generate for a collection holding `capacity` elements inline, and
+/// returns it along with the max serialized size of all elements. `hard_capacity` collections
+/// can't hold more than `capacity` elements at all.
+fn gen_len<T, R>(
+    mutator: &mut Mutator<R>,
+    constraints: Option<&Constraints<usize>>,
+    capacity: usize,
+    hard_capacity: bool,
+) -> (usize, Option<usize>)
+where
+    T: SerializedSize,
+    R: Rng,
+{
+    let mut min: usize;
+    let mut max: usize;
+    let weight: Weighted;
+    let max_size: Option<usize>;
+
+    match constraints {
+        Some(constraints) => {
+            min = constraints.inclusive_min().unwrap_or(0);
+            max = constraints.exclusive_max().unwrap_or(capacity);
+
+            if min != max {
+                if min != 0 && mutator.should_ignore_constraint() {
+                    min = 0;
+                }
+
+                if mutator.should_ignore_constraint() {
+                    max *= 2;
+                }
+            }
+
+            weight = constraints.weighted;
+
+            max_size = constraints.max_size;
+            if let Some(max_size) = constraints.max_size {
+                max = cmp::min(max, max_size / T::min_nonzero_elements_size());
+            }
+        }
+        None => {
+            min = 0;
+            max = capacity;
+            max_size = None;
+            weight = Weighted::None;
+
+            // the inline capacity is only a soft limit
+            if !hard_capacity && mutator.should_ignore_constraint() {
+                max *= 2;
+            }
+        }
+    }
+
+    if hard_capacity {
+        max = cmp::min(max, capacity);
+        min = cmp::min(min, max);
+    }
+
+    // If min == max, that means the user probably wants this to be exactly that many elements.
+    let num_elements = if min == max {
+        min
+    } else {
+        mutator.gen_weighted_range(min, max, weight)
+    };
+
+    // only elements past the inline capacity need to be allocated
+    let num_elements = if num_elements > capacity {
+        let spilled = mutator.reserve_allocation(num_elements, std::mem::size_of::<T>());
+        cmp::max(spilled, capacity)
+    } else {
+        num_elements
+    };
+
+    (num_elements, max_size)
+}
+
+/// Generates up to `num_elements` elements, stopping early if they'd exceed `max_size`
+fn gen_elements<T, R, F>(
+    mutator: &mut Mutator<R>,
+    num_elements: usize,
+    max_size: Option<usize>,
+    mut push: F,
+) where
+    T: NewFuzzed + SerializedSize,
+    R: Rng,
+    F: FnMut(T),
+{
+    let mut used_size: usize = 0;
+
+    for _i in 0..num_elements {
+        let element: T = T::new_fuzzed(mutator, None);
+        let element_serialized_size = element.serialized_size();
+
+        if let Some(ref max_size) = max_size {
+            if used_size + element_serialized_size > *max_size {
+                break;
+            } else {
+                used_size += element_serialized_size;
+            }
+        }
+
+        push(element);
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> SerializedSize for SmallVec<A>
+where
+    A: Array,
+    A::Item: SerializedSize,
+{
+    fn serialized_size(&self) -> usize {
+        self.as_slice().serialized_size()
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        A::Item::min_nonzero_elements_size()
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> BinarySerialize for SmallVec<A>
+where
+    A: Array,
+    A::Item: BinarySerialize,
+{
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        self.as_slice().binary_serialize::<_, E>(buffer)
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> NoAllocSerialize for SmallVec<A>
+where
+    A: Array,
+    A::Item: NoAllocSerialize,
+{
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> BinaryDeserialize for SmallVec<A>
+where
+    A: Array,
+    A::Item: BinaryDeserialize,
+{
+    /// Reads items until the input is exhausted. An incomplete trailing item is discarded.
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let mut items = SmallVec::new();
+
+        loop {
+            match A::Item::binary_deserialize::<R, E>(buffer) {
+                Ok(item) => items.push(item),
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(items),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> NewFuzzed for SmallVec<A>
+where
+    A: Array,
+    A::Item: NewFuzzed + SerializedSize,
+{
+    type RangeType = usize;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> SmallVec<A> {
+        hot_trace!(
+            "Generating random SmallVec with constraints: {:#?}",
+            constraints
+        );
+
+        mutator.begin_allocation_scope();
+
+        let (num_elements, max_size) =
+            gen_len::<A::Item, R>(mutator, constraints, A::size(), false);
+
+        let mut output = SmallVec::with_capacity(num_elements);
+        gen_elements(mutator, num_elements, max_size, |element| {
+            output.push(element)
+        });
+
+        mutator.end_allocation_scope();
+
+        output
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> Mutatable for SmallVec<A>
+where
+    A: Array,
+    A::Item: Mutatable,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        let previous_category = mutator.begin_default_category_scope(FieldCategory::Collection);
+
+        self.as_mut_slice().mutate(mutator, None);
+
+        // duplicated after mutating so that the copy is identical to the original
+        self.maybe_duplicate_element(mutator, None);
+
+        mutator.end_category_scope(previous_category);
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> ConstrainedMutatable for SmallVec<A>
+where
+    A: Array,
+    A::Item: Mutatable + NewFuzzed + SerializedSize,
+{
+    fn mutate_constrained<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<usize>>,
+    ) {
+        let previous_category = mutator.begin_default_category_scope(FieldCategory::Collection);
+
+        self.as_mut_slice().mutate(mutator, None);
+
+        self.maybe_duplicate_element(mutator, constraints);
+
+        mutator.end_category_scope(previous_category);
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A: Array> MaybeDuplicateElement for SmallVec<A> {
+    default fn maybe_duplicate_element<R: Rng>(
+        &mut self,
+        _mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<usize>>,
+    ) {
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> MaybeDuplicateElement for SmallVec<A>
+where
+    A: Array,
+    A::Item: Clone,
+{
+    fn maybe_duplicate_element<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<usize>>,
+    ) {
+        if self.is_empty() || !mutator.should_duplicate_element() {
+            return;
+        }
+
+        let fits = match constraints {
+            Some(c) => c.contains(&(self.len() + 1)),
+            None => self.len() < A::size(),
+        };
+
+        if fits || mutator.should_ignore_constraint() {
+            let index = mutator.gen_range(0, self.len());
+            let copy = self[index].clone();
+            self.insert(index + 1, copy);
+        }
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A: Array> VariableSizeObject for SmallVec<A> {
+    fn is_variable_size() -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> FuzzedDebug for SmallVec<A>
+where
+    A: Array,
+    A::Item: FuzzedDebug,
+{
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        self.as_slice()
+            .debug_elements(&short_type_name::<Self>(), path, depth, output);
+    }
+}
+
+#[cfg(feature = "smallvec_support")]
+impl<A> StructuredDiff for SmallVec<A>
+where
+    A: Array,
+    A::Item: StructuredDiff,
+{
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        self.as_slice().diff(other.as_slice())
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> SerializedSize for ArrayVec<T, CAP>
+where
+    T: SerializedSize,
+{
+    fn serialized_size(&self) -> usize {
+        self.as_slice().serialized_size()
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        T::min_nonzero_elements_size()
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> BinarySerialize for ArrayVec<T, CAP>
+where
+    T: BinarySerialize,
+{
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        self.as_slice().binary_serialize::<_, E>(buffer)
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> NoAllocSerialize for ArrayVec<T, CAP> where T: NoAllocSerialize {}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> BinaryDeserialize for ArrayVec<T, CAP>
+where
+    T: BinaryDeserialize,
+{
+    /// Reads items until the input is exhausted or the `ArrayVec` is full, in which case the rest
+    /// of the input is left unread. An incomplete trailing item is discarded.
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let mut items = ArrayVec::new();
+
+        while !items.is_full() {
+            match T::binary_deserialize::<R, E>(buffer) {
+                Ok(item) => items.push(item),
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> NewFuzzed for ArrayVec<T, CAP>
+where
+    T: NewFuzzed + SerializedSize,
+{
+    type RangeType = usize;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> ArrayVec<T, CAP> {
+        hot_trace!(
+            "Generating random ArrayVec with constraints: {:#?}",
+            constraints
+        );
+
+        mutator.begin_allocation_scope();
+
+        let (num_elements, max_size) = gen_len::<T, R>(mutator, constraints, CAP, true);
+
+        let mut output = ArrayVec::new();
+        gen_elements(mutator, num_elements, max_size, |element| {
+            output.push(element)
+        });
+
+        mutator.end_allocation_scope();
+
+        output
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> Mutatable for ArrayVec<T, CAP>
+where
+    T: Mutatable,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        let previous_category = mutator.begin_default_category_scope(FieldCategory::Collection);
+
+        self.as_mut_slice().mutate(mutator, None);
+
+        // duplicated after mutating so that the copy is identical to the original
+        self.maybe_duplicate_element(mutator, None);
+
+        mutator.end_category_scope(previous_category);
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> ConstrainedMutatable for ArrayVec<T, CAP>
+where
+    T: Mutatable + NewFuzzed + SerializedSize,
+{
+    fn mutate_constrained<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<usize>>,
+    ) {
+        let previous_category = mutator.begin_default_category_scope(FieldCategory::Collection);
+
+        self.as_mut_slice().mutate(mutator, None);
+
+        self.maybe_duplicate_element(mutator, constraints);
+
+        mutator.end_category_scope(previous_category);
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> MaybeDuplicateElement for ArrayVec<T, CAP> {
+    default fn maybe_duplicate_element<R: Rng>(
+        &mut self,
+        _mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<usize>>,
+    ) {
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T: Clone, const CAP: usize> MaybeDuplicateElement for ArrayVec<T, CAP> {
+    fn maybe_duplicate_element<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<usize>>,
+    ) {
+        // the capacity can't be exceeded even when ignoring constraints
+        if self.is_empty() || self.is_full() || !mutator.should_duplicate_element() {
+            return;
+        }
+
+        let fits = match constraints {
+            Some(c) => c.contains(&(self.len() + 1)),
+            None => true,
+        };
+        if fits || mutator.should_ignore_constraint() {
+            let index = mutator.gen_range(0, self.len());
+            let copy = self[index].clone();
+            self.insert(index + 1, copy);
+        }
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> VariableSizeObject for ArrayVec<T, CAP> {
+    fn is_variable_size() -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> FuzzedDebug for ArrayVec<T, CAP>
+where
+    T: FuzzedDebug,
+{
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        self.as_slice()
+            .debug_elements(&short_type_name::<Self>(), path, depth, output);
+    }
+}
+
+#[cfg(feature = "arrayvec_support")]
+impl<T, const CAP: usize> StructuredDiff for ArrayVec<T, CAP>
+where
+    T: StructuredDiff,
+{
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        self.as_slice().diff(other.as_slice())
+    }
+}
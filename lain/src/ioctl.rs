@@ -0,0 +1,319 @@
+//! Fuzzing device drivers through their IOCTL interface.
+//!
+//! An IOCTL is a triple of a control code, an input buffer, and the size of the output buffer the
+//! caller provides. Drivers dispatch on the code first and reject inputs which don't match the
+//! code's structure, so generating the three independently wastes most iterations. An [Ioctl]
+//! picks a code first and then asks the code (through [IoctlCode]) for an input and output size
+//! which its handler is likely to accept. Codes are held in an [UnsafeEnum] so that unknown codes
+//! are still tried occasionally.
+//!
+//! ```
+//! # #![feature(specialization)]
+//! use lain::prelude::*;
+//! use lain::ioctl::{Ioctl, IoctlCode};
+//! use lain::rand::Rng;
+//!
+//! #[derive(Debug, Copy, Clone, NewFuzzed, BinarySerialize, ToPrimitiveU32)]
+//! #[repr(u32)]
+//! enum Code {
+//!     Open = 0x22_2000,
+//!     Read = 0x22_2004,
+//! }
+//!
+//! #[derive(Debug, Default, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+//! struct ReadArgs {
+//!     offset: u64,
+//!     length: u32,
+//! }
+//!
+//! #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+//! enum Input {
+//!     Open(u32),
+//!     Read(ReadArgs),
+//! }
+//!
+//! impl IoctlCode<Input> for Code {
+//!     fn new_input<R: Rng>(&self, mutator: &mut Mutator<R>) -> Input {
+//!         match *self {
+//!             Code::Open => Input::Open(u32::new_fuzzed(mutator, None)),
+//!             Code::Read => Input::Read(ReadArgs::new_fuzzed(mutator, None)),
+//!         }
+//!     }
+//!
+//!     fn output_len(&self) -> Option<u32> {
+//!         match *self {
+//!             Code::Open => Some(8),
+//!             Code::Read => None,
+//!         }
+//!     }
+//! }
+//!
+//! let mut mutator = Mutator::new(lain::rand::thread_rng());
+//! let ioctl = Ioctl::<Code, Input>::new_fuzzed(&mut mutator, None);
+//!
+//! let mut input = vec![];
+//! ioctl.input.binary_serialize::<_, LittleEndian>(&mut input).unwrap();
+//! // DeviceIoControl(device, ioctl.raw_code(), input, ioctl.output_len, ...)
+//! ```
+
+use crate::debug::{child_path, short_type_name, write_node, SerializedSizeHint};
+use crate::mutator::Mutator;
+use crate::rand::Rng;
+use crate::traits::*;
+use crate::types::*;
+
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
+
+/// Percent chance that mutating an [Ioctl] switches to a different code (with a new input and
+/// output size to match) instead of mutating the input
+const CODE_CHANGE_CHANCE: f32 = 5.0;
+
+/// Percent chance that mutating an [Ioctl] picks a new output size
+const OUTPUT_LEN_CHANGE_CHANCE: f32 = 10.0;
+
+/// The control codes of a device. Implementations describe what the handler of each code
+/// expects so that [Ioctl]s can be generated with inputs of type `In` which get past it.
+pub trait IoctlCode<In: NewFuzzed> {
+    /// Generates an input which the handler of this code is likely to accept. The default
+    /// generates any `In`.
+    fn new_input<R: Rng>(&self, mutator: &mut Mutator<R>) -> In {
+        In::new_fuzzed(mutator, None)
+    }
+
+    /// Size of the output buffer the handler of this code expects, if it's fixed
+    fn output_len(&self) -> Option<u32> {
+        None
+    }
+}
+
+/// A control code, the input buffer sent with it, and the size of the output buffer provided
+/// for the driver's reply.
+///
+/// Serializes as the code and output size followed by the input, which suits corpus files and
+/// agents which issue the IOCTL on the fuzzer's behalf. Serialize [Ioctl::input] alone to get the
+/// input buffer.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct Ioctl<Codes, In> {
+    pub code: UnsafeEnum<Codes, u32>,
+    pub input: In,
+    pub output_len: u32,
+}
+
+impl<Codes, In> Ioctl<Codes, In> {
+    pub fn new(code: UnsafeEnum<Codes, u32>, input: In, output_len: u32) -> Self {
+        Ioctl {
+            code,
+            input,
+            output_len,
+        }
+    }
+}
+
+impl<Codes, In> Ioctl<Codes, In>
+where
+    Codes: ToPrimitive<u32>,
+{
+    /// The control code as it's passed to the driver
+    pub fn raw_code(&self) -> u32 {
+        self.code.raw_value()
+    }
+}
+
+impl<Codes, In> Ioctl<Codes, In>
+where
+    Codes: IoctlCode<In>,
+    In: NewFuzzed,
+{
+    /// Generates an input and output size for `code`. Invalid codes have no known handler, so
+    /// any input and size is used.
+    fn new_fuzzed_for_code<R: Rng>(code: UnsafeEnum<Codes, u32>, mutator: &mut Mutator<R>) -> Self {
+        let (input, output_len) = match code {
+            UnsafeEnum::Valid(ref code) => (
+                code.new_input(mutator),
+                gen_output_len(mutator, code.output_len()),
+            ),
+            UnsafeEnum::Invalid(_) => {
+                (In::new_fuzzed(mutator, None), gen_output_len(mutator, None))
+            }
+        };
+
+        Ioctl::new(code, input, output_len)
+    }
+
+    /// Output size the handler of the current code expects, if it's known
+    pub fn expected_output_len(&self) -> Option<u32> {
+        self.code
+            .as_valid()
+            .and_then(<Codes as IoctlCode<In>>::output_len)
+    }
+}
+
+/// Picks the size of an output buffer. Handlers with a fixed size mostly get exactly that, and
+/// sizes just around it (or no buffer at all) when constraints are ignored.
+fn gen_output_len<R: Rng>(mutator: &mut Mutator<R>, expected: Option<u32>) -> u32 {
+    match expected {
+        Some(len) if !mutator.should_ignore_constraint() => len,
+        Some(len) => match mutator.gen_range(0, 4) {
+            0 => 0,
+            1 => len.saturating_sub(1),
+            2 => len.saturating_add(1),
+            _ => u32::new_fuzzed(mutator, None),
+        },
+        None => u32::new_fuzzed(mutator, None),
+    }
+}
+
+impl<Codes, In> NewFuzzed for Ioctl<Codes, In>
+where
+    Codes: NewFuzzed + IoctlCode<In>,
+    In: NewFuzzed,
+{
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let code = UnsafeEnum::<Codes, u32>::new_fuzzed(mutator, None);
+
+        Ioctl::new_fuzzed_for_code(code, mutator)
+    }
+}
+
+impl<Codes, In> Mutatable for Ioctl<Codes, In>
+where
+    Codes: NewFuzzed + IoctlCode<In>,
+    In: NewFuzzed + Mutatable,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        // the input is only meaningful for its code, so changing the code starts over
+        if mutator.gen_chance(CODE_CHANGE_CHANCE) {
+            *self = Ioctl::new_fuzzed(mutator, None);
+            return;
+        }
+
+        if mutator.gen_chance(OUTPUT_LEN_CHANGE_CHANCE) {
+            self.output_len = gen_output_len(mutator, self.expected_output_len());
+        }
+
+        self.input.mutate(mutator, None);
+    }
+}
+
+impl<Codes, In> BinarySerialize for Ioctl<Codes, In>
+where
+    Codes: BinarySerialize,
+    In: BinarySerialize,
+{
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        let mut bytes_written = self.code.binary_serialize::<_, E>(buffer)?;
+
+        buffer.write_u32::<E>(self.output_len)?;
+        bytes_written += std::mem::size_of::<u32>();
+
+        bytes_written += self.input.binary_serialize::<_, E>(buffer)?;
+
+        Ok(bytes_written)
+    }
+}
+
+impl<Codes, In> SerializedSize for Ioctl<Codes, In>
+where
+    In: SerializedSize,
+{
+    fn serialized_size(&self) -> usize {
+        self.code.serialized_size() + std::mem::size_of::<u32>() + self.input.serialized_size()
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        UnsafeEnum::<Codes, u32>::min_nonzero_elements_size()
+            + std::mem::size_of::<u32>()
+            + In::min_nonzero_elements_size()
+    }
+}
+
+impl<Codes, In> BinaryDeserialize for Ioctl<Codes, In>
+where
+    Codes: BinaryDeserialize,
+    In: BinaryDeserialize,
+{
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        let code = UnsafeEnum::<Codes, u32>::binary_deserialize::<_, E>(buffer)?;
+        let output_len = buffer.read_u32::<E>()?;
+        let input = In::binary_deserialize::<_, E>(buffer)?;
+
+        Ok(Ioctl::new(code, input, output_len))
+    }
+}
+
+impl<Codes, In> InputShape for Ioctl<Codes, In>
+where
+    Codes: InputShape,
+    In: InputShape,
+{
+    fn hash_shape<H: Hasher>(&self, state: &mut H) {
+        self.code.hash_shape(state);
+        self.input.hash_shape(state);
+    }
+}
+
+impl<Codes, In> FuzzedDebug for Ioctl<Codes, In>
+where
+    Codes: FuzzedDebug + ToPrimitive<u32>,
+    In: FuzzedDebug,
+{
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        let code = format!("{:#x}", self.raw_code());
+        write_node(
+            output,
+            depth,
+            path,
+            &short_type_name::<Self>(),
+            self.serialized_size_hint(),
+            Some(&code),
+        );
+
+        self.code
+            .fuzzed_debug(&child_path(path, "code"), depth + 1, output);
+        self.input
+            .fuzzed_debug(&child_path(path, "input"), depth + 1, output);
+        self.output_len
+            .fuzzed_debug(&child_path(path, "output_len"), depth + 1, output);
+    }
+}
+
+impl<Codes, In> StructuredDiff for Ioctl<Codes, In>
+where
+    Codes: StructuredDiff,
+    In: StructuredDiff,
+{
+    fn diff(&self, other: &Self) -> Vec<FieldDelta> {
+        let mut deltas: Vec<FieldDelta> = self
+            .code
+            .diff(&other.code)
+            .into_iter()
+            .map(|d| d.prefixed("code"))
+            .collect();
+
+        deltas.extend(
+            self.input
+                .diff(&other.input)
+                .into_iter()
+                .map(|d| d.prefixed("input")),
+        );
+        deltas.extend(
+            self.output_len
+                .diff(&other.output_len)
+                .into_iter()
+                .map(|d| d.prefixed("output_len")),
+        );
+
+        deltas
+    }
+}
@@ -0,0 +1,1124 @@
+//! Generating lain models from [Kaitai Struct](https://kaitai.io) format definitions.
+//!
+//! Kaitai describes hundreds of file formats and network protocols in its `.ksy` YAML language.
+//! [generate] converts such a definition into Rust structs and enums which derive
+//! [FuzzerObject](lain_derive::FuzzerObject), giving a starting point for a model which can be
+//! refined by hand. It's meant to be called from a build script:
+//!
+//! ```compile_fail
+//! // build.rs
+//! fn main() {
+//!     let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+//!     lain::kaitai::generate_file("formats/png.ksy", out_dir.join("png.rs")).unwrap();
+//!     println!("cargo:rerun-if-changed=formats/png.ksy");
+//! }
+//!
+//! // src/lib.rs
+//! mod png {
+//!     include!(concat!(env!("OUT_DIR"), "/png.rs"));
+//! }
+//! ```
+//!
+//! Each type becomes a struct named after its `id` in CamelCase, and each enum becomes a
+//! fieldless enum whose `repr` is the integer type of the fields using it. Relationships between
+//! fields are maintained by a generated [Fixup](crate::traits::Fixup) implementation:
+//!
+//! - A field whose `size` or `repeat-expr` names an earlier integer field sets that field to its
+//!   serialized size or element count. `len - 4` and `len + 4` are understood as well.
+//! - Instances with a `pos` which names a field are placed after the type's `seq` fields, and
+//!   the field is set to their offset from the start of the struct.
+//! - `contents` fields are set to their expected bytes.
+//! - Fields whose `type` switches on an earlier field become enums with a variant per case, and
+//!   the field is set to the value of the chosen case.
+//!
+//! Fixups are occasionally skipped by the mutator, so these relationships are sometimes broken in
+//! generated inputs.
+//!
+//! Other expressions, conditions (`if`), and `process` aren't evaluated. The affected fields are
+//! generated without constraints and their docs note what was dropped. Runs of bit-sized integers
+//! (`b1`, `b4`, ...) become bitfields and must add up to 8, 16, 32, or 64 bits. Runs wider than a
+//! byte, like the primitive variants of switched types, use the serializer's byte order.
+
+use yaml_rust::{Yaml, YamlLoader};
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Converts the contents of a `.ksy` file into Rust source code
+pub fn generate(ksy: &str) -> io::Result<String> {
+    let documents =
+        YamlLoader::load_from_str(ksy).map_err(|e| unsupported(format!("invalid YAML: {}", e)))?;
+    let root = documents
+        .into_iter()
+        .next()
+        .ok_or_else(|| unsupported("the file is empty".to_string()))?;
+
+    let id = root["meta"]["id"]
+        .as_str()
+        .ok_or_else(|| unsupported("`meta.id` is missing".to_string()))?;
+
+    let mut generator = Generator::default();
+    generator.collect_types(id, &root)?;
+    generator.collect_enums(&root)?;
+
+    let context = TypeContext {
+        endian: None,
+        bits_little_endian: false,
+    };
+    generator.gen_type(id, &root, &context)?;
+
+    let mut output = String::new();
+    writeln!(
+        output,
+        "// Generated from the `{}` Kaitai Struct definition by lain::kaitai\n",
+        id
+    )
+    .unwrap();
+    output.push_str("#[allow(unused_imports)]\nuse lain::prelude::*;\n");
+
+    for item in generator.items.iter() {
+        output.push('\n');
+        output.push_str(item);
+    }
+
+    for enum_id in generator.enum_order.iter() {
+        if let Some(item) = generator.enums[enum_id].emit()? {
+            output.push('\n');
+            output.push_str(&item);
+        }
+    }
+
+    Ok(output)
+}
+
+/// Converts the `.ksy` file at `input` and writes the Rust source code to `output`
+pub fn generate_file<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q) -> io::Result<()> {
+    let ksy = fs::read_to_string(input)?;
+
+    fs::write(output, generate(&ksy)?)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn parse(value: &Yaml) -> io::Result<Option<Endian>> {
+        match *value {
+            Yaml::BadValue => Ok(None),
+            Yaml::String(ref s) if s == "le" => Ok(Some(Endian::Little)),
+            Yaml::String(ref s) if s == "be" => Ok(Some(Endian::Big)),
+            _ => Err(unsupported(format!("unsupported endianness {:?}", value))),
+        }
+    }
+
+    fn attr(self) -> &'static str {
+        match self {
+            Endian::Little => "byteorder(little)",
+            Endian::Big => "byteorder(big)",
+        }
+    }
+}
+
+/// Settings inherited by nested types
+#[derive(Debug, Clone)]
+struct TypeContext {
+    endian: Option<Endian>,
+    bits_little_endian: bool,
+}
+
+/// A fixed-size integer or float such as `u4le`
+struct Primitive {
+    ty: &'static str,
+    size: usize,
+    endian: Option<Endian>,
+    float: bool,
+}
+
+impl Primitive {
+    fn parse(name: &str) -> Option<Primitive> {
+        let (base, endian) = if let Some(base) = name.strip_suffix("le") {
+            (base, Some(Endian::Little))
+        } else if let Some(base) = name.strip_suffix("be") {
+            (base, Some(Endian::Big))
+        } else {
+            (name, None)
+        };
+
+        let (ty, size) = match base {
+            "u1" => ("u8", 1),
+            "u2" => ("u16", 2),
+            "u4" => ("u32", 4),
+            "u8" => ("u64", 8),
+            "s1" => ("i8", 1),
+            "s2" => ("i16", 2),
+            "s4" => ("i32", 4),
+            "s8" => ("i64", 8),
+            "f4" => ("f32", 4),
+            "f8" => ("f64", 8),
+            _ => return None,
+        };
+
+        Some(Primitive {
+            ty,
+            size,
+            endian,
+            float: base.starts_with('f'),
+        })
+    }
+}
+
+/// Parses the width of bit-sized integers such as `b3`
+fn parse_bits(name: &str) -> Option<u32> {
+    let name = name.trim_end_matches("le").trim_end_matches("be");
+    if !name.starts_with('b') {
+        return None;
+    }
+
+    name[1..]
+        .parse()
+        .ok()
+        .filter(|bits| *bits > 0 && *bits <= 64)
+}
+
+/// The smallest unsigned integer type which holds `bits` bits
+fn unsigned_type(bits: u32) -> &'static str {
+    match bits {
+        0..=8 => "u8",
+        9..=16 => "u16",
+        17..=32 => "u32",
+        _ => "u64",
+    }
+}
+
+fn is_integer(ty: &str) -> bool {
+    matches!(
+        ty,
+        "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64"
+    )
+}
+
+struct Field {
+    name: String,
+    ty: String,
+    docs: Vec<String>,
+    /// Items of the field's `#[lain(...)]` attribute
+    attrs: Vec<String>,
+    bits: Option<u32>,
+}
+
+/// The struct being generated for a Kaitai type
+struct TypeBuilder {
+    name: String,
+    context: TypeContext,
+    fields: Vec<Field>,
+    /// Statements run by the struct's fixup, in order
+    fixups: Vec<String>,
+    /// Offset fields maintained by the fixup, with the index of the field they point at
+    offsets: Vec<(String, i64, usize)>,
+}
+
+impl TypeBuilder {
+    /// The integer type of the field named `name`, if it is one
+    fn integer_field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|f| f.name == name)
+            .map(|f| f.ty.as_str())
+            .filter(|ty| is_integer(ty))
+    }
+
+    fn field_type(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|f| f.name == name)
+            .map(|f| f.ty.as_str())
+    }
+}
+
+struct EnumDef {
+    name: String,
+    docs: Vec<String>,
+    values: Vec<(i64, String)>,
+    /// Integer type of the fields using this enum. Unused enums aren't generated.
+    repr: Option<&'static str>,
+}
+
+impl EnumDef {
+    fn emit(&self) -> io::Result<Option<String>> {
+        let repr = match self.repr {
+            Some(repr) => repr,
+            None => return Ok(None),
+        };
+
+        if self.values.is_empty() {
+            return Ok(None);
+        }
+
+        let mut output = String::new();
+        write_docs(&mut output, "", &self.docs);
+        writeln!(
+            output,
+            "#[derive(Debug, Copy, Clone, PartialEq, FuzzerObject)]\n#[repr({})]\npub enum {} {{",
+            repr, self.name
+        )
+        .unwrap();
+
+        for (value, name) in self.values.iter() {
+            if *value < 0 && repr.starts_with('u') {
+                return Err(unsupported(format!(
+                    "enum `{}` has negative values but is used by {} fields",
+                    self.name, repr
+                )));
+            }
+
+            writeln!(output, "    {} = {},", name, value).unwrap();
+        }
+
+        output.push_str("}\n");
+
+        Ok(Some(output))
+    }
+}
+
+#[derive(Default)]
+struct Generator {
+    /// Kaitai type IDs mapped to their struct names
+    types: HashMap<String, String>,
+    /// Kaitai enum IDs mapped to their definitions
+    enums: HashMap<String, EnumDef>,
+    enum_order: Vec<String>,
+    items: Vec<String>,
+}
+
+impl Generator {
+    fn collect_types(&mut self, id: &str, spec: &Yaml) -> io::Result<()> {
+        let name = type_name(id);
+        if self.types.values().any(|existing| *existing == name) {
+            return Err(unsupported(format!(
+                "more than one type is named `{}`",
+                name
+            )));
+        }
+
+        self.types.insert(id.to_string(), name);
+
+        if let Some(types) = spec["types"].as_hash() {
+            for (id, spec) in types.iter() {
+                self.collect_types(yaml_key(id)?, spec)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn collect_enums(&mut self, spec: &Yaml) -> io::Result<()> {
+        if let Some(enums) = spec["enums"].as_hash() {
+            for (id, values) in enums.iter() {
+                let id = yaml_key(id)?;
+                if self.enums.contains_key(id) {
+                    return Err(unsupported(format!("more than one enum is named `{}`", id)));
+                }
+
+                let values = values
+                    .as_hash()
+                    .ok_or_else(|| unsupported(format!("enum `{}` has no values", id)))?;
+
+                let mut def = EnumDef {
+                    name: type_name(id),
+                    docs: vec![],
+                    values: vec![],
+                    repr: None,
+                };
+
+                for (value, name) in values.iter() {
+                    let value = value.as_i64().ok_or_else(|| {
+                        unsupported(format!("enum `{}` has a non-integer value", id))
+                    })?;
+
+                    let name = match *name {
+                        Yaml::Hash(_) => name["id"].as_str(),
+                        _ => name.as_str(),
+                    }
+                    .ok_or_else(|| {
+                        unsupported(format!("value {} of enum `{}` has no name", value, id))
+                    })?;
+
+                    def.values.push((value, variant_name(name)));
+                }
+
+                self.enums.insert(id.to_string(), def);
+                self.enum_order.push(id.to_string());
+            }
+        }
+
+        if let Some(types) = spec["types"].as_hash() {
+            for spec in types.values() {
+                self.collect_enums(spec)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn gen_type(&mut self, id: &str, spec: &Yaml, parent: &TypeContext) -> io::Result<()> {
+        let mut context = parent.clone();
+        if let Some(endian) = Endian::parse(&spec["meta"]["endian"])? {
+            context.endian = Some(endian);
+        }
+
+        if let Some(bit_endian) = spec["meta"]["bit-endian"].as_str() {
+            context.bits_little_endian = bit_endian == "le";
+        }
+
+        let mut builder = TypeBuilder {
+            name: self.types[id].clone(),
+            context: context.clone(),
+            fields: vec![],
+            fixups: vec![],
+            offsets: vec![],
+        };
+
+        if let Some(seq) = spec["seq"].as_vec() {
+            for attr in seq.iter() {
+                let attr_id = attr["id"]
+                    .as_str()
+                    .ok_or_else(|| unsupported(format!("a field of `{}` has no id", id)))?;
+
+                self.gen_attr(&mut builder, attr_id, attr)?;
+            }
+        }
+
+        if let Some(instances) = spec["instances"].as_hash() {
+            for (instance_id, instance) in instances.iter() {
+                // value instances are computed rather than read, and instances without a position
+                // can't be placed
+                if instance["pos"].is_badvalue() || !instance["value"].is_badvalue() {
+                    continue;
+                }
+
+                let instance_id = yaml_key(instance_id)?;
+                let index = builder.fields.len();
+                self.gen_attr(&mut builder, instance_id, instance)?;
+
+                match instance["pos"] {
+                    Yaml::String(ref pos) => match parse_reference(pos) {
+                        Some((field, adjustment)) if builder.integer_field(&field).is_some() => {
+                            builder.offsets.push((field, adjustment, index));
+                        }
+                        _ => add_note(
+                            &mut builder.fields[index].docs,
+                            format!("Read from `{}` in the original format", pos),
+                        ),
+                    },
+                    ref pos => {
+                        let pos = pos.as_i64().unwrap_or_default();
+                        add_note(
+                            &mut builder.fields[index].docs,
+                            format!("Read from offset {} in the original format", pos),
+                        )
+                    }
+                }
+            }
+        }
+
+        let docs = doc_lines(if spec["doc"].is_badvalue() {
+            &spec["meta"]["title"]
+        } else {
+            &spec["doc"]
+        });
+
+        let item = emit_struct(builder, &docs)?;
+        self.items.push(item);
+
+        if let Some(types) = spec["types"].as_hash() {
+            for (id, spec) in types.iter() {
+                self.gen_type(yaml_key(id)?, spec, &context)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the field described by `spec` to the struct
+    fn gen_attr(&mut self, builder: &mut TypeBuilder, id: &str, spec: &Yaml) -> io::Result<()> {
+        let name = field_name(id);
+        let mut docs = doc_lines(&spec["doc"]);
+        let mut attrs = vec![];
+
+        if let Some(condition) = spec["if"].as_str() {
+            add_note(
+                &mut docs,
+                format!("Only present if `{}` in the original format", condition),
+            );
+        }
+
+        if let Some(process) = spec["process"].as_str() {
+            add_note(
+                &mut docs,
+                format!(
+                    "Stored with `process: {}` in the original format, which isn't applied",
+                    process
+                ),
+            );
+        }
+
+        if !spec["contents"].is_badvalue() {
+            let contents = parse_contents(&spec["contents"])?;
+            let bytes = format!("vec!{:?}", contents);
+
+            attrs.push(format!("initializer = \"{}\"", bytes));
+            builder.fixups.push(format!("self.{} = {};", name, bytes));
+
+            builder.fields.push(Field {
+                name,
+                ty: "Vec<u8>".to_string(),
+                docs,
+                attrs,
+                bits: None,
+            });
+
+            return Ok(());
+        }
+
+        let mut bits = None;
+        let mut is_bytes = false;
+        let mut is_user_type = false;
+        let mut terminator = None;
+
+        let mut ty = match spec["type"] {
+            Yaml::Hash(_) => {
+                is_user_type = true;
+                self.gen_switch(builder, id, &spec["type"])?
+            }
+            Yaml::String(ref type_name) => {
+                if type_name == "str" || type_name == "strz" {
+                    is_bytes = true;
+                    if type_name == "strz" {
+                        terminator = Some(0);
+                    }
+
+                    "Vec<u8>".to_string()
+                } else if let Some(primitive) = Primitive::parse(type_name) {
+                    if primitive.size > 1 {
+                        if let Some(endian) = primitive.endian.or(builder.context.endian) {
+                            attrs.push(endian.attr().to_string());
+                        }
+                    }
+
+                    match spec["enum"].as_str() {
+                        Some(enum_id) if !primitive.float => {
+                            self.use_enum(enum_id, primitive.ty)?
+                        }
+                        _ => primitive.ty.to_string(),
+                    }
+                } else if let Some(width) = parse_bits(type_name) {
+                    if let Some(enum_id) = spec["enum"].as_str() {
+                        add_note(
+                            &mut docs,
+                            format!("Values come from the `{}` enum", enum_id),
+                        );
+                    }
+
+                    bits = Some(width);
+                    unsigned_type(width).to_string()
+                } else {
+                    is_user_type = true;
+                    self.resolve_type(type_name)?
+                }
+            }
+            _ => {
+                is_bytes = true;
+                "Vec<u8>".to_string()
+            }
+        };
+
+        if let Some(value) = spec["terminator"].as_i64() {
+            terminator = Some(value);
+        }
+
+        let repeat = spec["repeat"].as_str();
+        if bits.is_some() && repeat.is_some() {
+            return Err(unsupported(format!(
+                "`{}` is a repeated bit-sized integer",
+                id
+            )));
+        }
+
+        // the size only describes the field itself when it isn't repeated
+        let size = if repeat.is_none() {
+            match spec["size"] {
+                Yaml::Integer(size) => {
+                    if is_bytes {
+                        attrs.push(format!("min = {}, max = {}", size, size));
+                    }
+
+                    None
+                }
+                Yaml::String(ref size) => Some(size.clone()),
+                _ => None,
+            }
+        } else {
+            if let Some(size) = yaml_expression(&spec["size"]) {
+                add_note(
+                    &mut docs,
+                    format!("Elements are `{}` bytes in the original format", size),
+                );
+            }
+
+            None
+        };
+
+        if let Some(repeat) = repeat {
+            ty = format!("Vec<{}>", ty);
+
+            match spec["repeat-expr"] {
+                Yaml::Integer(count) => {
+                    attrs.push(format!("min = {}, max = {}", count, count));
+                }
+                Yaml::String(ref count) => match parse_reference(count) {
+                    Some((field, adjustment)) if builder.integer_field(&field).is_some() => {
+                        let field_ty = builder.integer_field(&field).unwrap();
+                        let value = format!("self.{}.len()", name);
+                        builder
+                            .fixups
+                            .push(assignment(&field, field_ty, &value, adjustment));
+                    }
+                    _ => add_note(
+                        &mut docs,
+                        format!("Repeated `{}` times in the original format", count),
+                    ),
+                },
+                _ => {}
+            }
+
+            if let Some(condition) = yaml_expression(&spec["repeat-until"]) {
+                add_note(
+                    &mut docs,
+                    format!("Repeated until `{}` in the original format", condition),
+                );
+            }
+
+            if repeat != "eos" && repeat != "expr" && repeat != "until" {
+                return Err(unsupported(format!(
+                    "`{}` has an unknown repeat kind `{}`",
+                    id, repeat
+                )));
+            }
+
+            if is_user_type {
+                builder.fixups.push(format!(
+                    "for item in self.{}.iter_mut() {{\n    item.fixup(mutator);\n}}",
+                    name
+                ));
+            }
+        }
+
+        if let Some(size) = size {
+            match parse_reference(&size) {
+                Some((field, adjustment)) if builder.integer_field(&field).is_some() => {
+                    let field_ty = builder.integer_field(&field).unwrap();
+                    let value = format!("self.{}.serialized_size()", name);
+                    builder
+                        .fixups
+                        .push(assignment(&field, field_ty, &value, adjustment));
+                }
+                _ => add_note(
+                    &mut docs,
+                    format!("`{}` bytes in the original format", size),
+                ),
+            }
+        }
+
+        let needs_terminator =
+            terminator.is_some() && spec["size"].is_badvalue() && repeat.is_none();
+
+        builder.fields.push(Field {
+            name: name.clone(),
+            ty,
+            docs,
+            attrs,
+            bits,
+        });
+
+        if needs_terminator {
+            let terminator = terminator.unwrap();
+            let terminator_name = format!("{}_terminator", name);
+
+            // ignored fields are still mutated, so the terminator is restored by the fixup
+            builder
+                .fixups
+                .push(format!("self.{} = {};", terminator_name, terminator));
+
+            builder.fields.push(Field {
+                name: terminator_name,
+                ty: "u8".to_string(),
+                docs: vec![],
+                attrs: vec!["ignore".to_string()],
+                bits: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Generates an enum with a variant for each case of a switched type, returning its name
+    fn gen_switch(
+        &mut self,
+        builder: &mut TypeBuilder,
+        id: &str,
+        spec: &Yaml,
+    ) -> io::Result<String> {
+        let switch_on = spec["switch-on"]
+            .as_str()
+            .ok_or_else(|| unsupported(format!("the type of `{}` has no `switch-on`", id)))?;
+        let cases = spec["cases"]
+            .as_hash()
+            .ok_or_else(|| unsupported(format!("the type of `{}` has no `cases`", id)))?;
+
+        let name = format!("{}{}", builder.name, type_name(id));
+        let tag_field = field_name(switch_on);
+        let tag_ty = builder.field_type(&tag_field).map(|ty| ty.to_string());
+
+        let mut variants: Vec<(String, String)> = vec![];
+        let mut tags = vec![];
+
+        for (case, case_ty) in cases.iter() {
+            let (mut variant, tag) = match *case {
+                Yaml::Integer(value) => {
+                    let variant = if value < 0 {
+                        format!("CaseMinus{}", -value)
+                    } else {
+                        format!("Case{}", value)
+                    };
+
+                    let tag = match tag_ty {
+                        Some(ref ty) if is_integer(ty) => Some(format!("{}", value)),
+                        _ => None,
+                    };
+
+                    (variant, tag)
+                }
+                Yaml::String(ref value) if value == "_" => ("Other".to_string(), None),
+                Yaml::String(ref value) if value.contains("::") => {
+                    let mut path = value.rsplit("::");
+                    let value_name = variant_name(path.next().unwrap());
+                    let enum_name = path
+                        .next()
+                        .and_then(|id| self.enums.get(id))
+                        .map(|e| &e.name);
+
+                    let tag = match (enum_name, tag_ty.as_ref()) {
+                        (Some(enum_name), Some(ty)) if enum_name == ty => {
+                            Some(format!("{}::{}", enum_name, value_name))
+                        }
+                        _ => None,
+                    };
+
+                    (value_name, tag)
+                }
+                ref value => (
+                    variant_name(&yaml_expression(value).unwrap_or_default()),
+                    None,
+                ),
+            };
+
+            if variants.iter().any(|(existing, _)| *existing == variant) {
+                variant = format!("{}{}", variant, variants.len());
+            }
+
+            let case_ty = case_ty
+                .as_str()
+                .ok_or_else(|| unsupported(format!("a case of `{}` has no type", id)))?;
+            let ty = if case_ty == "str" || case_ty == "strz" {
+                "Vec<u8>".to_string()
+            } else if let Some(primitive) = Primitive::parse(case_ty) {
+                primitive.ty.to_string()
+            } else if parse_bits(case_ty).is_some() {
+                return Err(unsupported(format!(
+                    "a case of `{}` is a bit-sized integer",
+                    id
+                )));
+            } else {
+                self.resolve_type(case_ty)?
+            };
+
+            if let Some(tag) = tag {
+                tags.push(format!(
+                    "    {}::{}(_) => self.{} = {},",
+                    name, variant, tag_field, tag
+                ));
+            }
+
+            variants.push((variant, ty));
+        }
+
+        if tags.is_empty() {
+            builder.fixups.push(format!(
+                "// `{}` should be set to the case of `{}`",
+                switch_on,
+                field_name(id)
+            ));
+        } else {
+            if tags.len() < variants.len() {
+                tags.push("    _ => {}".to_string());
+            }
+
+            builder.fixups.push(format!(
+                "match self.{} {{\n{}\n}}",
+                field_name(id),
+                tags.join("\n")
+            ));
+        }
+
+        let mut output = String::new();
+        writeln!(
+            output,
+            "#[derive(Debug, Clone, FuzzerObject)]\npub enum {} {{",
+            name
+        )
+        .unwrap();
+
+        for (variant, ty) in variants.iter() {
+            writeln!(output, "    {}({}),", variant, ty).unwrap();
+        }
+
+        writeln!(
+            output,
+            "}}\n\nimpl Fixup for {} {{\n    fn fixup<R: Rng>(&mut self, mutator: &mut Mutator<R>) {{\n        match *self {{",
+            name
+        )
+        .unwrap();
+
+        for (variant, _) in variants.iter() {
+            writeln!(
+                output,
+                "            {}::{}(ref mut value) => value.fixup(mutator),",
+                name, variant
+            )
+            .unwrap();
+        }
+
+        output.push_str("        }\n    }\n}\n");
+
+        self.items.push(output);
+
+        Ok(name)
+    }
+
+    /// Looks up the struct name of a Kaitai type
+    fn resolve_type(&self, type_name: &str) -> io::Result<String> {
+        if type_name.contains('(') {
+            return Err(unsupported(format!(
+                "parametric type `{}` isn't supported",
+                type_name
+            )));
+        }
+
+        let id = type_name.rsplit("::").next().unwrap();
+
+        self.types
+            .get(id)
+            .cloned()
+            .ok_or_else(|| unsupported(format!("unknown type `{}`", type_name)))
+    }
+
+    /// Records that `enum_id` is stored as a `repr`, returning the enum's name
+    fn use_enum(&mut self, enum_id: &str, repr: &'static str) -> io::Result<String> {
+        let id = enum_id.rsplit("::").next().unwrap();
+        let def = self
+            .enums
+            .get_mut(id)
+            .ok_or_else(|| unsupported(format!("unknown enum `{}`", enum_id)))?;
+
+        match def.repr {
+            Some(existing) if existing != repr => Err(unsupported(format!(
+                "enum `{}` is used by both {} and {} fields",
+                enum_id, existing, repr
+            ))),
+            _ => {
+                def.repr = Some(repr);
+                Ok(def.name.clone())
+            }
+        }
+    }
+}
+
+/// Finishes the struct for `builder`, returning its source code
+fn emit_struct(mut builder: TypeBuilder, docs: &[String]) -> io::Result<String> {
+    assign_bitfields(&mut builder)?;
+
+    let mut output = String::new();
+    write_docs(&mut output, "", docs);
+    writeln!(
+        output,
+        "#[derive(Debug, Clone, FuzzerObject)]\npub struct {} {{",
+        builder.name
+    )
+    .unwrap();
+
+    for field in builder.fields.iter() {
+        write_docs(&mut output, "    ", &field.docs);
+        if !field.attrs.is_empty() {
+            writeln!(output, "    #[lain({})]", field.attrs.join(", ")).unwrap();
+        }
+
+        writeln!(output, "    pub {}: {},", field.name, field.ty).unwrap();
+    }
+
+    output.push_str("}\n");
+
+    // offsets depend on the sizes of everything in front of them, so they're fixed up last
+    for (field, adjustment, index) in builder.offsets.iter() {
+        let trailing: Vec<String> = builder.fields[*index..]
+            .iter()
+            .map(|f| format!("self.{}.serialized_size()", f.name))
+            .collect();
+        let value = format!("(self.serialized_size() - {})", trailing.join(" - "));
+        let field_ty = builder.integer_field(field).unwrap();
+
+        builder
+            .fixups
+            .push(assignment(field, field_ty, &value, *adjustment));
+    }
+
+    if !builder.fixups.is_empty() {
+        writeln!(
+            output,
+            "\nimpl Fixup for {} {{\n    fn fixup<R: Rng>(&mut self, mutator: &mut Mutator<R>) {{\n        self.fixup_children(mutator);\n",
+            builder.name
+        )
+        .unwrap();
+
+        for fixup in builder.fixups.iter() {
+            for line in fixup.lines() {
+                writeln!(output, "        {}", line).unwrap();
+            }
+        }
+
+        output.push_str("    }\n}\n");
+    }
+
+    Ok(output)
+}
+
+/// Groups consecutive bit-sized fields into bitfields
+fn assign_bitfields(builder: &mut TypeBuilder) -> io::Result<()> {
+    let mut start = 0;
+
+    while start < builder.fields.len() {
+        if builder.fields[start].bits.is_none() {
+            start += 1;
+            continue;
+        }
+
+        let mut end = start;
+        let mut total = 0;
+        while end < builder.fields.len() && builder.fields[end].bits.is_some() {
+            total += builder.fields[end].bits.unwrap();
+            end += 1;
+        }
+
+        let backing_type = match total {
+            8 | 16 | 32 | 64 => unsigned_type(total),
+            _ => {
+                return Err(unsupported(format!(
+                    "the bit-sized fields starting at `{}` add up to {} bits rather than 8, 16, 32, or 64",
+                    builder.fields[start].name, total
+                )))
+            }
+        };
+
+        for field in builder.fields[start..end].iter_mut() {
+            field.attrs.push(format!(
+                "bitfield(backing_type = \"{}\", bits = {})",
+                backing_type,
+                field.bits.unwrap()
+            ));
+        }
+
+        // bitfields are packed starting from the least significant bit, while big endian bit
+        // fields start from the most significant bit
+        if !builder.context.bits_little_endian {
+            builder.fields[start..end].reverse();
+        }
+
+        start = end;
+    }
+
+    Ok(())
+}
+
+/// Returns a statement which sets `field` so that `field + adjustment == value`
+fn assignment(field: &str, field_ty: &str, value: &str, adjustment: i64) -> String {
+    if adjustment < 0 {
+        format!(
+            "self.{} = ({} + {}) as {};",
+            field, value, -adjustment, field_ty
+        )
+    } else if adjustment > 0 {
+        format!(
+            "self.{} = {}.wrapping_sub({}) as {};",
+            field, value, adjustment, field_ty
+        )
+    } else {
+        format!("self.{} = {} as {};", field, value, field_ty)
+    }
+}
+
+/// Parses expressions of the form `field`, `field + N`, and `field - N`
+fn parse_reference(expression: &str) -> Option<(String, i64)> {
+    let expression = expression.trim();
+    let (field, adjustment) = match expression.find(&['+', '-'][..]) {
+        Some(index) => {
+            let amount: i64 = expression[index + 1..].trim().parse().ok()?;
+            let amount = if expression[index..].starts_with('-') {
+                -amount
+            } else {
+                amount
+            };
+
+            (expression[..index].trim(), amount)
+        }
+        None => (expression, 0),
+    };
+
+    let is_identifier = field
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && field
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+    if is_identifier {
+        Some((field_name(field), adjustment))
+    } else {
+        None
+    }
+}
+
+fn parse_contents(contents: &Yaml) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+
+    let items = match *contents {
+        Yaml::Array(ref items) => items.iter().collect(),
+        ref item => vec![item],
+    };
+
+    for item in items {
+        match *item {
+            Yaml::String(ref s) => bytes.extend_from_slice(s.as_bytes()),
+            Yaml::Integer(value) if (0..=0xFF).contains(&value) => bytes.push(value as u8),
+            _ => {
+                return Err(unsupported(format!(
+                    "unsupported `contents` item {:?}",
+                    item
+                )))
+            }
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn yaml_key(key: &Yaml) -> io::Result<&str> {
+    key.as_str()
+        .ok_or_else(|| unsupported(format!("expected a name but found {:?}", key)))
+}
+
+/// Returns a scalar as it appeared in the definition
+fn yaml_expression(value: &Yaml) -> Option<String> {
+    match *value {
+        Yaml::String(ref s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        Yaml::Real(ref r) => Some(r.clone()),
+        _ => None,
+    }
+}
+
+fn doc_lines(doc: &Yaml) -> Vec<String> {
+    doc.as_str()
+        .map(|doc| {
+            doc.trim()
+                .lines()
+                .map(|line| line.trim_end().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Adds a note about something which was dropped from the definition to a field's docs
+fn add_note(docs: &mut Vec<String>, note: String) {
+    if docs.last().is_some_and(|line| !line.is_empty()) {
+        docs.push(String::new());
+    }
+
+    docs.push(note);
+}
+
+fn write_docs(output: &mut String, indent: &str, docs: &[String]) {
+    for line in docs.iter() {
+        if line.is_empty() {
+            writeln!(output, "{}///", indent).unwrap();
+        } else {
+            writeln!(output, "{}/// {}", indent, line).unwrap();
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Converts a Kaitai ID to a field name which isn't a keyword
+fn field_name(id: &str) -> String {
+    if KEYWORDS.contains(&id) {
+        format!("{}_", id)
+    } else {
+        id.to_string()
+    }
+}
+
+/// Converts a Kaitai ID to CamelCase, prefixing names which wouldn't be identifiers with `prefix`
+fn camel_case(id: &str, prefix: char) -> String {
+    let mut name = String::new();
+    for part in id.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            name.push(first.to_ascii_uppercase());
+            name.extend(chars);
+        }
+    }
+
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) || name == "Self" {
+        name.insert(0, prefix);
+    }
+
+    name
+}
+
+fn type_name(id: &str) -> String {
+    camel_case(id, 'T')
+}
+
+fn variant_name(id: &str) -> String {
+    camel_case(id, 'V')
+}
+
+fn unsupported(message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unsupported format definition: {}", message),
+    )
+}
@@ -36,16 +36,16 @@
 //! struct MyStruct {
 //!     field_1: u8,
 //!
-//!     #[bitfield(backing_type = "u8", bits = 3)]
+//!     #[lain(bitfield(backing_type = "u8", bits = 3))]
 //!     field_2: u8,
 //!
-//!     #[bitfield(backing_type = "u8", bits = 5)]
+//!     #[lain(bitfield(backing_type = "u8", bits = 5))]
 //!     field_3: u8,
 //!
-//!     #[fuzzer(min = 5, max = 10000)]
+//!     #[lain(min = 5, max = 10000)]
 //!     field_4: u32,
 //!
-//!     #[fuzzer(ignore)]
+//!     #[lain(ignore)]
 //!     ignored_field: u64,
 //! }
 //!
@@ -55,7 +55,7 @@
 //!     let mut instance = MyStruct::new_fuzzed(&mut mutator, None);
 //!
 //!     let mut serialized_data = Vec::with_capacity(instance.serialized_size());
-//!     instance.binary_serialize::<_, BigEndian>(&mut serialized_data);
+//!     instance.binary_serialize::<_, BigEndian>(&mut serialized_data).unwrap();
 //!
 //!     println!("{:?}", instance);
 //!     println!("{}", hexdump());
@@ -113,8 +113,15 @@ pub extern crate byteorder;
 pub extern crate field_offset;
 pub extern crate lain_derive;
 pub extern crate lazy_static;
+#[cfg(unix)]
+pub extern crate libc;
+#[cfg(feature = "smallvec_support")]
+pub extern crate smallvec;
+#[cfg(feature = "arrayvec_support")]
+pub extern crate arrayvec;
 pub extern crate rand;
 
+pub use error::{Error, Result};
 pub use lain_derive::*;
 
 #[macro_use]
@@ -122,19 +129,126 @@ pub extern crate log;
 #[macro_use]
 extern crate mashup;
 
+/// `trace!` for the paths run for every field of every iteration: generation, mutation, and
+/// serialization. With the `no_hot_path_logging` feature these compile to nothing, since even
+/// filtered-out log calls check the log level and show up in profiles of release fuzzing builds.
+#[cfg(not(feature = "no_hot_path_logging"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! hot_trace {
+    ($($arg:tt)+) => {
+        $crate::log::trace!($($arg)+)
+    };
+}
+
+#[cfg(feature = "no_hot_path_logging")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! hot_trace {
+    ($($arg:tt)+) => {
+        // the arguments are still type checked so builds with and without the feature agree
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+/// `debug!` for hot paths, such as the `serialized_size` of derived types. See [hot_trace].
+#[cfg(not(feature = "no_hot_path_logging"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! hot_debug {
+    ($($arg:tt)+) => {
+        $crate::log::debug!($($arg)+)
+    };
+}
+
+#[cfg(feature = "no_hot_path_logging")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! hot_debug {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+
+/// Invokes `$m!` once per supported tuple arity with the element type parameters and their
+/// indices, e.g. `$m!(T0 0, T1 1)` for pairs
+macro_rules! for_each_tuple {
+    ($m:ident) => {
+        $m!(T0 0);
+        $m!(T0 0, T1 1);
+        $m!(T0 0, T1 1, T2 2);
+        $m!(T0 0, T1 1, T2 2, T3 3);
+        $m!(T0 0, T1 1, T2 2, T3 3, T4 4);
+        $m!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5);
+        $m!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6);
+        $m!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7);
+        $m!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8);
+        $m!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9);
+        $m!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10);
+        $m!(T0 0, T1 1, T2 2, T3 3, T4 4, T5 5, T6 6, T7 7, T8 8, T9 9, T10 10, T11 11);
+    };
+}
+
+pub mod adaptive;
+pub mod autodict;
 #[doc(hidden)]
 pub mod buffer;
+pub mod byte_permutation;
+pub mod cmplog;
+pub mod corpus;
 #[doc(hidden)]
 pub mod dangerous_numbers;
+#[doc(hidden)]
+pub mod debug;
+pub mod dedup;
+#[doc(hidden)]
+pub mod deprecated_attributes;
+#[doc(hidden)]
+pub mod deserialize;
+#[doc(hidden)]
+pub mod diff;
 pub mod driver;
 #[doc(hidden)]
+pub mod enumerable;
+pub mod error;
+#[cfg(unix)]
+pub mod exec;
+pub mod feedback;
+pub mod harness;
+#[cfg(any(feature = "smallvec_support", feature = "arrayvec_support"))]
+#[doc(hidden)]
+pub mod inline_vec;
+pub mod ioctl;
+#[cfg(feature = "kaitai_support")]
+pub mod kaitai;
+pub mod model_coverage;
+#[doc(hidden)]
 pub mod mutatable;
 pub mod mutator;
+pub mod net;
+pub mod observer;
 #[doc(hidden)]
 pub mod new_fuzzed;
+pub mod pool;
+pub mod postprocess;
 pub mod prelude;
+#[cfg(feature = "protobuf_support")]
+pub mod protobuf;
+pub mod reuse;
+#[cfg(target_os = "linux")]
+pub mod sandbox;
+#[cfg(unix)]
+pub mod shmem;
+pub mod stats;
+pub mod stream;
+pub mod target;
 pub mod traits;
 pub mod types;
+pub mod undo;
 
 pub fn hexdump(data: &[u8]) -> String {
     let mut ret = "------".to_string();
@@ -0,0 +1,386 @@
+//! Tracking which parts of a model have been generated during a campaign.
+//!
+//! A [ModelCoverage] given to the driver with [FuzzerDriver::set_model_coverage] is shared by every
+//! fuzzer thread. It counts each variant of a derived enum picked, each `Some`/`None` an `Option`
+//! became, and which [ValueBucket] the integer fields of derived structs landed in. Its
+//! [report](ModelCoverage::report) lists what was never produced, so a variant whose weight
+//! rounds to zero or a field whose constraints can't reach its maximum shows up without digging
+//! through generated inputs by hand.
+//!
+//! ```
+//! use lain::model_coverage::ModelCoverage;
+//! use lain::prelude::*;
+//! use lain::rand::SeedableRng;
+//! use std::sync::Arc;
+//!
+//! #[derive(Debug, Copy, Clone, NewFuzzed)]
+//! enum Opcode {
+//!     Read,
+//!     #[lain(weight(0))]
+//!     Write,
+//! }
+//!
+//! let coverage = Arc::new(ModelCoverage::new());
+//! let mut mutator = Mutator::new(lain::rand::rngs::StdRng::seed_from_u64(0));
+//! mutator.set_model_coverage(Some(coverage.clone()));
+//!
+//! for _ in 0..100 {
+//!     Opcode::new_fuzzed(&mut mutator, None);
+//! }
+//!
+//! let report = coverage.report();
+//! assert_eq!(report.gaps().len(), 1);
+//! println!("{}", report);
+//! ```
+//!
+//! Coverage is tallied as values are generated, so enabling it doesn't change which inputs are
+//! generated. Only fields of non-generic structs are bucketed, like [observers](crate::observer).
+//!
+//! [FuzzerDriver::set_model_coverage]: crate::driver::FuzzerDriver::set_model_coverage
+
+use std::any::{Any, TypeId};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Coarse range an integer value falls in. Checked in declaration order, so e.g. `0u8` is
+/// [ValueBucket::Zero] rather than [ValueBucket::Min].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValueBucket {
+    Zero,
+    /// The type's minimum value
+    Min,
+    /// The type's maximum value
+    Max,
+    /// Any other negative value
+    Negative,
+    /// Any other value up to `0xff`
+    Small,
+    /// Any other value above `0xff`
+    Large,
+}
+
+impl ValueBucket {
+    fn classify(value: i128, min: i128, max: i128) -> ValueBucket {
+        if value == 0 {
+            ValueBucket::Zero
+        } else if value == min {
+            ValueBucket::Min
+        } else if value == max {
+            ValueBucket::Max
+        } else if value < 0 {
+            ValueBucket::Negative
+        } else if value <= 0xff {
+            ValueBucket::Small
+        } else {
+            ValueBucket::Large
+        }
+    }
+
+    /// The buckets a type spanning `min..=max` can land in
+    fn reachable(min: i128, max: i128) -> Vec<ValueBucket> {
+        let mut buckets = vec![ValueBucket::Zero];
+        if min < 0 {
+            buckets.push(ValueBucket::Min);
+        }
+        buckets.push(ValueBucket::Max);
+        if min < -1 {
+            buckets.push(ValueBucket::Negative);
+        }
+        if max > 1 {
+            buckets.push(ValueBucket::Small);
+        }
+        if max > 0x100 {
+            buckets.push(ValueBucket::Large);
+        }
+
+        buckets
+    }
+}
+
+/// Returns `value` as an integer along with its type's bounds if it's one of the primitive
+/// integer types
+fn bucketed_value<T: 'static>(value: &T) -> Option<(i128, i128, i128)> {
+    let value = value as &dyn Any;
+
+    macro_rules! bucketed_types {
+        ($($ty:ty),*) => {
+            $(
+                if TypeId::of::<T>() == TypeId::of::<$ty>() {
+                    return value.downcast_ref::<$ty>().map(|&value| {
+                        (value as i128, <$ty>::min_value() as i128, <$ty>::max_value() as i128)
+                    });
+                }
+            )*
+        };
+    }
+
+    bucketed_types!(u8, i8, u16, i16, u32, i32, u64, i64, usize, isize);
+
+    None
+}
+
+#[derive(Debug)]
+struct VariantTally {
+    names: &'static [&'static str],
+    counts: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct ValueTally {
+    reachable: Vec<ValueBucket>,
+    counts: BTreeMap<ValueBucket, usize>,
+}
+
+#[derive(Debug, Default)]
+struct Tallies {
+    variants: BTreeMap<&'static str, VariantTally>,
+    options: BTreeMap<&'static str, [usize; 2]>,
+    values: BTreeMap<(&'static str, String), ValueTally>,
+}
+
+/// Counts of the enum variants, `Option` arms, and field value buckets generated across a
+/// campaign, shared by every fuzzer thread
+#[derive(Debug, Default)]
+pub struct ModelCoverage {
+    tallies: Mutex<Tallies>,
+}
+
+impl ModelCoverage {
+    pub fn new() -> ModelCoverage {
+        ModelCoverage::default()
+    }
+
+    /// Records that variant `index` of the generatable variants `names` of `enum_name` was
+    /// generated
+    pub fn record_variant(
+        &self,
+        enum_name: &'static str,
+        names: &'static [&'static str],
+        index: usize,
+    ) {
+        let mut tallies = self.tallies.lock().unwrap();
+        let tally = tallies
+            .variants
+            .entry(enum_name)
+            .or_insert_with(|| VariantTally {
+                names,
+                counts: vec![0; names.len()],
+            });
+
+        if let Some(count) = tally.counts.get_mut(index) {
+            *count += 1;
+        }
+    }
+
+    /// Records that an `Option` of type `type_name` was generated as `Some` or `None`
+    pub fn record_option(&self, type_name: &'static str, is_some: bool) {
+        self.tallies
+            .lock()
+            .unwrap()
+            .options
+            .entry(type_name)
+            .or_insert([0; 2])[is_some as usize] += 1;
+    }
+
+    /// Records the bucket `value`, generated for the field at `path` of `container`, falls in.
+    /// Values of types other than the primitive integers are ignored.
+    pub fn record_value<T: 'static>(&self, container: &'static str, path: String, value: &T) {
+        let (value, min, max) = match bucketed_value(value) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let mut tallies = self.tallies.lock().unwrap();
+        let tally = tallies
+            .values
+            .entry((container, path))
+            .or_insert_with(|| ValueTally {
+                reachable: ValueBucket::reachable(min, max),
+                counts: BTreeMap::new(),
+            });
+
+        *tally
+            .counts
+            .entry(ValueBucket::classify(value, min, max))
+            .or_insert(0) += 1;
+    }
+
+    /// Forgets everything recorded so far
+    pub fn clear(&self) {
+        *self.tallies.lock().unwrap() = Tallies::default();
+    }
+
+    /// What has been generated so far. Enums, `Option`s, and fields which were never generated
+    /// at all aren't listed.
+    pub fn report(&self) -> CoverageReport {
+        let tallies = self.tallies.lock().unwrap();
+
+        CoverageReport {
+            variants: tallies
+                .variants
+                .iter()
+                .map(|(&enum_name, tally)| VariantCoverage {
+                    enum_name,
+                    counts: tally
+                        .names
+                        .iter()
+                        .cloned()
+                        .zip(tally.counts.iter().cloned())
+                        .collect(),
+                })
+                .collect(),
+            options: tallies
+                .options
+                .iter()
+                .map(|(&type_name, &[none, some])| OptionCoverage {
+                    type_name,
+                    some,
+                    none,
+                })
+                .collect(),
+            values: tallies
+                .values
+                .iter()
+                .map(|((container, path), tally)| ValueCoverage {
+                    container,
+                    path: path.clone(),
+                    counts: tally
+                        .reachable
+                        .iter()
+                        .map(|bucket| (*bucket, tally.counts.get(bucket).cloned().unwrap_or(0)))
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Number of times each generatable variant of an enum was generated
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantCoverage {
+    pub enum_name: &'static str,
+    /// Variant names and counts, in declaration order
+    pub counts: Vec<(&'static str, usize)>,
+}
+
+impl VariantCoverage {
+    /// Variants which were never generated
+    pub fn missing(&self) -> Vec<&'static str> {
+        self.counts
+            .iter()
+            .filter(|(_, count)| *count == 0)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+/// Number of times an `Option` type was generated as each arm
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionCoverage {
+    pub type_name: &'static str,
+    pub some: usize,
+    pub none: usize,
+}
+
+impl OptionCoverage {
+    /// Arms which were never generated
+    pub fn missing(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.some == 0 {
+            missing.push("Some");
+        }
+        if self.none == 0 {
+            missing.push("None");
+        }
+
+        missing
+    }
+}
+
+/// Number of values generated for an integer field in each bucket its type can reach
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueCoverage {
+    pub container: &'static str,
+    /// Path of the field from the outermost struct, e.g. `header.length`
+    pub path: String,
+    pub counts: Vec<(ValueBucket, usize)>,
+}
+
+impl ValueCoverage {
+    /// Buckets no value was generated in
+    pub fn missing(&self) -> Vec<ValueBucket> {
+        self.counts
+            .iter()
+            .filter(|(_, count)| *count == 0)
+            .map(|(bucket, _)| *bucket)
+            .collect()
+    }
+}
+
+/// A snapshot of a [ModelCoverage]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverageReport {
+    pub variants: Vec<VariantCoverage>,
+    pub options: Vec<OptionCoverage>,
+    pub values: Vec<ValueCoverage>,
+}
+
+impl CoverageReport {
+    /// A line for everything which was never generated, e.g. `Opcode::Write`
+    pub fn gaps(&self) -> Vec<String> {
+        let variants = self.variants.iter().flat_map(|coverage| {
+            coverage
+                .missing()
+                .into_iter()
+                .map(move |name| format!("{}::{}", coverage.enum_name, name))
+        });
+        let options = self.options.iter().flat_map(|coverage| {
+            coverage
+                .missing()
+                .into_iter()
+                .map(move |arm| format!("{} ({})", coverage.type_name, arm))
+        });
+        let values = self.values.iter().flat_map(|coverage| {
+            coverage.missing().into_iter().map(move |bucket| {
+                format!("{}.{} ({:?})", coverage.container, coverage.path, bucket)
+            })
+        });
+
+        variants.chain(options).chain(values).collect()
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for coverage in &self.variants {
+            writeln!(f, "{}", coverage.enum_name)?;
+            for (name, count) in &coverage.counts {
+                writeln!(f, "    {:<24} {}", name, count)?;
+            }
+        }
+
+        for coverage in &self.options {
+            writeln!(f, "{}", coverage.type_name)?;
+            writeln!(f, "    {:<24} {}", "Some", coverage.some)?;
+            writeln!(f, "    {:<24} {}", "None", coverage.none)?;
+        }
+
+        for coverage in &self.values {
+            writeln!(f, "{}.{}", coverage.container, coverage.path)?;
+            for (bucket, count) in &coverage.counts {
+                writeln!(f, "    {:<24} {}", format!("{:?}", bucket), count)?;
+            }
+        }
+
+        let gaps = self.gaps();
+        if !gaps.is_empty() {
+            writeln!(f, "never generated:")?;
+            for gap in gaps {
+                writeln!(f, "    {}", gap)?;
+            }
+        }
+
+        Ok(())
+    }
+}
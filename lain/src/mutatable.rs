@@ -1,11 +1,17 @@
-use crate::mutator::Mutator;
+use crate::mutator::{
+    changed_bytes, FieldCategory, GenerationProfile, Mutator, MutatorMode, StringStrategy,
+};
 use crate::rand::seq::index;
 use crate::rand::Rng;
+use crate::target::{PointerWidth, TargetConfig};
 use crate::traits::*;
 use crate::types::*;
 
-use num_traits::{Bounded, NumCast};
+use num_traits::{Bounded, NumCast, PrimInt};
 use num_traits::{WrappingAdd, WrappingSub};
+use std::borrow::Cow;
+use std::cmp;
+use std::marker::PhantomData;
 use std::ops::BitXor;
 
 impl<T> Mutatable for Vec<T>
@@ -17,7 +23,190 @@ where
         mutator: &mut Mutator<R>,
         _constraints: Option<&Constraints<u8>>,
     ) {
+        let previous_category = mutator.begin_default_category_scope(FieldCategory::Collection);
+
         self.as_mut_slice().mutate(mutator, None);
+        self.maybe_substitute_operand(mutator);
+
+        // duplicated after mutating so that the copy is identical to the original
+        self.maybe_duplicate_element(mutator, None);
+
+        mutator.end_category_scope(previous_category);
+    }
+}
+
+impl<T> ConstrainedMutatable for Vec<T>
+where
+    T: Mutatable + NewFuzzed + SerializedSize,
+{
+    fn mutate_constrained<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<usize>>,
+    ) {
+        let previous_category = mutator.begin_default_category_scope(FieldCategory::Collection);
+
+        match constraints.and_then(|c| c.elements::<T::RangeType>()) {
+            Some(element_constraints) => {
+                for element in self.iter_mut() {
+                    element.mutate_constrained(mutator, Some(element_constraints));
+                }
+            }
+            None => self.as_mut_slice().mutate(mutator, None),
+        }
+
+        self.maybe_substitute_operand(mutator);
+        self.maybe_duplicate_element(mutator, constraints);
+
+        mutator.end_category_scope(previous_category);
+    }
+}
+
+/// Chance that an `Option` or `Result` is switched to its other variant, which is generated from
+/// scratch, instead of having its contents mutated
+const SWITCH_VARIANT_CHANCE: f32 = 5.0;
+
+impl<T> Mutatable for Option<T>
+where
+    T: Mutatable + NewFuzzed,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, constraints: Option<&Constraints<u8>>) {
+        if !mutator.has_byte_budget() && mutator.gen_chance(SWITCH_VARIANT_CHANCE) {
+            *self = match *self {
+                Some(_) => None,
+                None => Some(T::new_fuzzed(mutator, None)),
+            };
+
+            if let Some(coverage) = mutator.model_coverage() {
+                coverage.record_option(std::any::type_name::<Self>(), self.is_some());
+            }
+
+            return;
+        }
+
+        if let Some(ref mut value) = *self {
+            value.mutate(mutator, constraints);
+        }
+    }
+}
+
+impl<T, E> Mutatable for Result<T, E>
+where
+    T: Mutatable + NewFuzzed,
+    E: Mutatable + NewFuzzed,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, constraints: Option<&Constraints<u8>>) {
+        if !mutator.has_byte_budget() && mutator.gen_chance(SWITCH_VARIANT_CHANCE) {
+            *self = match *self {
+                Ok(_) => Err(E::new_fuzzed(mutator, None)),
+                Err(_) => Ok(T::new_fuzzed(mutator, None)),
+            };
+
+            return;
+        }
+
+        match *self {
+            Ok(ref mut value) => value.mutate(mutator, constraints),
+            Err(ref mut value) => value.mutate(mutator, constraints),
+        }
+    }
+}
+
+impl<'a> Mutatable for Cow<'a, [u8]> {
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, constraints: Option<&Constraints<u8>>) {
+        // borrowed bytes are copied the first time they're mutated
+        self.to_mut().mutate(mutator, constraints);
+    }
+}
+
+/// Helper trait so that `Vec`s only duplicate elements which can be cloned
+pub(crate) trait MaybeDuplicateElement {
+    /// Occasionally duplicates an element. The new length must lie within the `min`/`max` of
+    /// `constraints` unless the mutator's profile chooses to ignore them.
+    fn maybe_duplicate_element<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<usize>>,
+    );
+}
+
+impl<T> MaybeDuplicateElement for Vec<T> {
+    default fn maybe_duplicate_element<R: Rng>(
+        &mut self,
+        _mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<usize>>,
+    ) {
+    }
+}
+
+impl<T: Clone> MaybeDuplicateElement for Vec<T> {
+    fn maybe_duplicate_element<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<usize>>,
+    ) {
+        if !mutator.should_duplicate_element() {
+            return;
+        }
+
+        let fits = constraints.is_none_or(|c| c.contains(&(self.len() + 1)));
+        if fits || mutator.should_ignore_constraint() {
+            mutator.duplicate_element(self);
+        }
+    }
+}
+
+/// Helper trait so that only byte buffers have compared operands spliced into them
+pub(crate) trait MaybeSubstituteOperand {
+    /// Occasionally replaces one operand of a byte comparison the target made with the other, or
+    /// inserts an operand if neither appears in the buffer. See [cmplog](crate::cmplog).
+    fn maybe_substitute_operand<R: Rng>(&mut self, mutator: &mut Mutator<R>);
+}
+
+impl<T> MaybeSubstituteOperand for Vec<T> {
+    default fn maybe_substitute_operand<R: Rng>(&mut self, _mutator: &mut Mutator<R>) {}
+}
+
+impl MaybeSubstituteOperand for Vec<u8> {
+    fn maybe_substitute_operand<R: Rng>(&mut self, mutator: &mut Mutator<R>) {
+        if mutator.should_substitute_operand() {
+            mutator.substitute_operand(self);
+        }
+    }
+}
+
+/// Helper trait so that derived structs only splice fields from corpus entries (see
+/// [Mutator::set_splice_corpus]) if they can be cloned
+#[doc(hidden)]
+pub trait SpliceDonor: Sized {
+    fn splice_donor<R: Rng>(mutator: &mut Mutator<R>) -> Option<Self>;
+}
+
+impl<T> SpliceDonor for T {
+    default fn splice_donor<R: Rng>(_mutator: &mut Mutator<R>) -> Option<Self> {
+        None
+    }
+}
+
+impl<T: Clone + 'static> SpliceDonor for T {
+    fn splice_donor<R: Rng>(mutator: &mut Mutator<R>) -> Option<Self> {
+        mutator.splice_donor::<T>()
+    }
+}
+
+/// Helper trait so that only fields which can be cloned are spliced from a corpus entry
+#[doc(hidden)]
+pub trait SpliceField {
+    fn splice_from(&mut self, donor: &Self);
+}
+
+impl<T> SpliceField for T {
+    default fn splice_from(&mut self, _donor: &Self) {}
+}
+
+impl<T: Clone> SpliceField for T {
+    fn splice_from(&mut self, donor: &Self) {
+        self.clone_from(donor);
     }
 }
 
@@ -55,39 +244,219 @@ where
             *self = UnsafeEnum::Invalid(value.to_primitive());
         }
 
+        let previous_category = mutator.begin_default_category_scope(FieldCategory::Enum);
+
         match *self {
             UnsafeEnum::Invalid(ref mut value) => {
                 mutator.mutate_from_mutation_mode(value);
             }
             _ => unreachable!(),
         }
+
+        mutator.end_category_scope(previous_category);
+    }
+}
+
+/// Tokens which commonly trigger bugs in string parsers: format strings, path traversal,
+/// and overlong UTF-8 encodings of `/` and `.` (in their percent-encoded form)
+static STRING_TOKENS: &[&str] = &[
+    "%n", "%s", "%x", "%p", "%n%n%n%n", "%s%s%s%s", "%99999999s", "../", "..\\", "../../../../",
+    "%00", "%0d%0a", "%c0%af", "%e0%80%af", "%f0%80%80%af", "%c0%ae", "%c0%ae%c0%ae%c0%af",
+];
+
+/// The max number of characters in a run selected for case flipping, encoding, or repetition
+const MAX_STRING_RUN_LEN: usize = 16;
+
+/// Helper trait so that the string wrapper types can share mutation logic
+trait StringChar: Clone + NewFuzzed {
+    fn from_char(c: char) -> Self;
+    fn to_char(&self) -> char;
+}
+
+impl StringChar for AsciiChar {
+    fn from_char(c: char) -> Self {
+        AsciiChar(c)
+    }
+
+    fn to_char(&self) -> char {
+        self.0
+    }
+}
+
+impl StringChar for Utf8Char {
+    fn from_char(c: char) -> Self {
+        Utf8Char(c)
+    }
+
+    fn to_char(&self) -> char {
+        self.0
+    }
+}
+
+/// Picks a random run of characters in a string of length `len`. `len` must be nonzero.
+fn pick_run<R: Rng>(mutator: &mut Mutator<R>, len: usize) -> (usize, usize) {
+    let start = mutator.gen_range(0, len);
+    let end = mutator.gen_range(start + 1, cmp::min(len, start + MAX_STRING_RUN_LEN) + 1);
+
+    (start, end)
+}
+
+/// Applies a string strategy to `inner`, undoing it if it changes more bytes than are left of the
+/// mutator's budget
+fn mutate_string<C: StringChar, R: Rng>(inner: &mut Vec<C>, mutator: &mut Mutator<R>) {
+    if !mutator.has_byte_budget() {
+        apply_string_strategy(inner, mutator);
+        return;
+    }
+
+    let encode = |s: &[C]| s.iter().map(StringChar::to_char).collect::<String>();
+    let previous = inner.clone();
+    apply_string_strategy(inner, mutator);
+
+    let changed = changed_bytes(encode(&previous).as_bytes(), encode(inner).as_bytes());
+    if !mutator.charge_bytes_changed(changed) {
+        *inner = previous;
+    }
+}
+
+fn apply_string_strategy<C: StringChar, R: Rng>(inner: &mut Vec<C>, mutator: &mut Mutator<R>) {
+    let mut operation = match mutator.pick_string_strategy() {
+        Some(operation) => operation,
+        None => return,
+    };
+
+    // most operations need at least one character to work with
+    if inner.is_empty() {
+        operation = StringStrategy::InsertToken;
+    }
+
+    // characters which may be added before the string reaches its max length
+    let room = mutator.config().max_string_len.saturating_sub(inner.len());
+
+    hot_trace!("String operation selected: {:?}", operation);
+    match operation {
+        StringStrategy::ReplaceChars => {
+            let num_mutations = mutator.gen_range(1, inner.len() + 1);
+            for idx in index::sample(&mut mutator.rng, inner.len(), num_mutations).iter() {
+                inner[idx] = C::new_fuzzed(mutator, None);
+            }
+        }
+        StringStrategy::FlipCase => {
+            let (start, end) = pick_run(mutator, inner.len());
+            for c in inner[start..end].iter_mut() {
+                let chr = c.to_char();
+                let flipped = if chr.is_ascii_lowercase() {
+                    chr.to_ascii_uppercase()
+                } else {
+                    chr.to_ascii_lowercase()
+                };
+
+                *c = C::from_char(flipped);
+            }
+        }
+        StringStrategy::PercentEncode => {
+            let (start, end) = pick_run(mutator, inner.len());
+
+            let mut encoded = Vec::new();
+            let mut utf8_buffer = [0u8; 4];
+            for c in inner[start..end].iter() {
+                for b in c.to_char().encode_utf8(&mut utf8_buffer).bytes() {
+                    encoded.extend(format!("%{:02x}", b).chars().map(C::from_char));
+                }
+            }
+
+            encoded.truncate(end - start + room);
+            inner.splice(start..end, encoded);
+        }
+        StringStrategy::InsertToken => {
+            // tokens learned from the target are picked as often as the built-in ones
+            let learned = match mutator.dictionary().cloned() {
+                Some(dictionary) if mutator.gen_chance(50.0) => dictionary
+                    .random_token(&mut mutator.rng)
+                    .filter(|token| token.is_ascii()),
+                _ => None,
+            };
+
+            let token = match learned {
+                Some(ref token) => std::str::from_utf8(token).unwrap(),
+                None => STRING_TOKENS[mutator.gen_range(0, STRING_TOKENS.len())],
+            };
+            let idx = mutator.gen_range(0, inner.len() + 1);
+
+            inner.splice(idx..idx, token.chars().take(room).map(C::from_char));
+        }
+        StringStrategy::Repeat => {
+            let (start, end) = pick_run(mutator, inner.len());
+            // amplify by a power of two so that we hit common buffer boundaries
+            let repetitions = 1usize << mutator.gen_range(1, 9);
+
+            let run: Vec<C> = inner[start..end].to_vec();
+            let repeated: Vec<C> = run
+                .iter()
+                .cycle()
+                .take(cmp::min(run.len() * repetitions, room))
+                .cloned()
+                .collect();
+
+            inner.splice(end..end, repeated);
+        }
     }
 }
 
 impl Mutatable for AsciiString {
     fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
-        trace!("performing mutation on an AsciiString");
+        hot_trace!("performing mutation on an AsciiString");
 
-        // TODO: Implement logic for resizing?
-        let num_mutations = mutator.gen_range(1, self.inner.len());
-        for idx in index::sample(&mut mutator.rng, self.inner.len(), num_mutations).iter() {
-            self.inner[idx] = AsciiChar::new_fuzzed(mutator, None);
-        }
+        mutate_string(&mut self.inner, mutator);
     }
 }
 
 impl Mutatable for Utf8String {
     fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
-        trace!("performing mutation on a Utf8String");
+        hot_trace!("performing mutation on a Utf8String");
+
+        mutate_string(&mut self.inner, mutator);
+    }
+}
+
+impl<const N: usize, const PAD: u8> Mutatable for FixedLengthString<N, PAD> {
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        hot_trace!("performing mutation on a FixedLengthString");
+
+        let previous = self.bytes.clone();
+        self.mutate_contents(mutator);
 
-        // TODO: Implement logic for resizing?
-        let num_mutations = mutator.gen_range(1, self.inner.len());
-        for idx in index::sample(&mut mutator.rng, self.inner.len(), num_mutations).iter() {
-            self.inner[idx] = Utf8Char::new_fuzzed(mutator, None);
+        if !mutator.charge_bytes_changed(changed_bytes(&previous, &self.bytes)) {
+            self.bytes = previous;
         }
     }
 }
 
+impl<const N: usize, const PAD: u8> FixedLengthString<N, PAD> {
+    fn mutate_contents<R: Rng>(&mut self, mutator: &mut Mutator<R>) {
+        if mutator.gen_chance(crate::mutator::CHANCE_TO_PICK_BOUNDARY_LENGTH) {
+            // one past the field overflows it, which serializes the same as an exact fit
+            let len = crate::new_fuzzed::gen_fixed_string_length(mutator, 0, N + 1);
+            if len < self.bytes.len() {
+                self.bytes.truncate(len);
+            } else {
+                while self.bytes.len() < len {
+                    let c = AsciiChar::new_fuzzed(mutator, None);
+                    self.bytes.push(c.0 as u8);
+                }
+            }
+
+            return;
+        }
+
+        let mut inner: Vec<AsciiChar> = self.bytes.iter().map(|&b| AsciiChar(b as char)).collect();
+        apply_string_strategy(&mut inner, mutator);
+
+        // anything past the field would be cut off when serialized
+        self.bytes = inner.iter().take(N).map(|c| c.0 as u8).collect();
+    }
+}
+
 macro_rules! impl_mutatable {
     ( $($name:ident),* ) => {
         $(
@@ -103,6 +472,447 @@ macro_rules! impl_mutatable {
 
 impl_mutatable!(i64, u64, i32, u32, i16, u16, i8, u8);
 
+macro_rules! impl_mutatable_narrow_int {
+    ( $($name:ident => $inner:ident),* ) => {
+        $(
+            impl Mutatable for $name {
+                #[inline(always)]
+                fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+                    // mutate the value as its backing primitive and discard any bits that were
+                    // pushed outside of the narrow type. wrapping arithmetic stays consistent since
+                    // truncation is equivalent to wrapping at the narrow type's bounds
+                    let mut value: $inner = self.0;
+                    mutator.mutate_from_mutation_mode(&mut value);
+                    *self = $name::new(value);
+                }
+            }
+        )*
+    }
+}
+
+impl_mutatable_narrow_int!(U24 => u32, U48 => u64);
+
+macro_rules! impl_mutatable_pointer {
+    ( $($name:ident => $narrow:ident, $wide:ident),* ) => {
+        $(
+            impl Mutatable for $name {
+                #[inline(always)]
+                fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+                    // mutated at the target's width so that e.g. boundary wraps happen where the
+                    // target's integers wrap
+                    match TargetConfig::current().pointer_width {
+                        PointerWidth::Bits32 => {
+                            let mut value = self.0 as $narrow;
+                            mutator.mutate_from_mutation_mode(&mut value);
+                            self.0 = value as $wide;
+                        }
+                        PointerWidth::Bits64 => mutator.mutate_from_mutation_mode(&mut self.0),
+                    }
+                }
+            }
+        )*
+    }
+}
+
+impl_mutatable_pointer!(TargetUsize => u32, u64, TargetIsize => i32, i64);
+
+impl<T: Mutatable> Mutatable for PointerAligned<T> {
+    #[inline(always)]
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, constraints: Option<&Constraints<u8>>) {
+        self.0.mutate(mutator, constraints);
+    }
+}
+
+/// Chance that an address or UUID is replaced by a freshly generated one, which jumps between
+/// the well-known ranges instead of drifting away from them
+const REGENERATE_ADDRESS_CHANCE: f32 = 10.0;
+
+impl Mutatable for Ipv4Address {
+    #[inline(always)]
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        if mutator.gen_chance(REGENERATE_ADDRESS_CHANCE) {
+            *self = Ipv4Address::new_fuzzed(mutator, None);
+            return;
+        }
+
+        // mutate the address as the integer it is on the wire so that arithmetic mutations walk
+        // through neighbouring hosts
+        let mut value = u32::from_be_bytes(self.0);
+        mutator.mutate_from_mutation_mode(&mut value);
+        self.0 = value.to_be_bytes();
+    }
+}
+
+impl Mutatable for Ipv6Address {
+    #[inline(always)]
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        if mutator.gen_chance(REGENERATE_ADDRESS_CHANCE) {
+            *self = Ipv6Address::new_fuzzed(mutator, None);
+            return;
+        }
+
+        // mutate either the network prefix or the interface identifier
+        let offset = if mutator.gen_chance(50.0) { 0 } else { 8 };
+        let mut half = [0u8; 8];
+        half.copy_from_slice(&self.0[offset..offset + 8]);
+
+        let mut value = u64::from_be_bytes(half);
+        mutator.mutate_from_mutation_mode(&mut value);
+        self.0[offset..offset + 8].copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+impl Mutatable for MacAddress {
+    #[inline(always)]
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        if mutator.gen_chance(REGENERATE_ADDRESS_CHANCE) {
+            *self = MacAddress::new_fuzzed(mutator, None);
+            return;
+        }
+
+        // flipping the multicast or locally administered bits changes how the address is
+        // interpreted without changing the rest of it
+        if mutator.gen_chance(25.0) {
+            self.0[0] ^= if mutator.gen_chance(50.0) { 0x01 } else { 0x02 };
+            return;
+        }
+
+        let mut bytes = [0u8; 8];
+        bytes[2..].copy_from_slice(&self.0);
+
+        let mut value = u64::from_be_bytes(bytes);
+        mutator.mutate_from_mutation_mode(&mut value);
+        self.0.copy_from_slice(&value.to_be_bytes()[2..]);
+    }
+}
+
+impl Mutatable for Uuid {
+    #[inline(always)]
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        if mutator.gen_chance(REGENERATE_ADDRESS_CHANCE) {
+            *self = Uuid::new_fuzzed(mutator, None);
+            return;
+        }
+
+        // parsers commonly dispatch on the version nibble, so try the other (and reserved) ones
+        if mutator.gen_chance(25.0) {
+            let version = mutator.gen_range(0u8, 16);
+            self.0[6] = (self.0[6] & 0x0F) | (version << 4);
+            return;
+        }
+
+        let mut low = [0u8; 8];
+        low.copy_from_slice(&self.0[8..]);
+
+        let mut value = u64::from_be_bytes(low);
+        mutator.mutate_from_mutation_mode(&mut value);
+        self.0[8..].copy_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Chance that a timestamp is replaced by a freshly generated one near another point in time
+const REGENERATE_TIME_CHANCE: f32 = 10.0;
+
+/// Chance that a timestamp is moved by a plausible amount of time rather than mutated as an integer
+const TIME_DELTA_CHANCE: f32 = 60.0;
+
+/// Generates a non-zero offset in seconds for moving a timestamp
+fn gen_nonzero_time_delta<R: Rng>(mutator: &mut Mutator<R>) -> i64 {
+    match crate::new_fuzzed::gen_time_delta(mutator) {
+        0 => 1,
+        delta => delta,
+    }
+}
+
+impl Mutatable for UnixTimestamp32 {
+    #[inline(always)]
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        if mutator.gen_chance(REGENERATE_TIME_CHANCE) {
+            *self = UnixTimestamp32::new_fuzzed(mutator, None);
+        } else if mutator.gen_chance(TIME_DELTA_CHANCE) {
+            self.0 = (self.0 as i64 + gen_nonzero_time_delta(mutator)) as i32;
+        } else {
+            // mutated as the unsigned bit pattern, which is how readers that ignore the sign see it
+            let mut bits = self.0 as u32;
+            mutator.mutate_from_mutation_mode(&mut bits);
+            self.0 = bits as i32;
+        }
+    }
+}
+
+impl Mutatable for UnixTimestamp64 {
+    #[inline(always)]
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        if mutator.gen_chance(REGENERATE_TIME_CHANCE) {
+            *self = UnixTimestamp64::new_fuzzed(mutator, None);
+        } else if mutator.gen_chance(TIME_DELTA_CHANCE) {
+            self.0 = self.0.wrapping_add(gen_nonzero_time_delta(mutator));
+        } else {
+            // mutated as the unsigned bit pattern, which is how readers that ignore the sign see it
+            let mut bits = self.0 as u64;
+            mutator.mutate_from_mutation_mode(&mut bits);
+            self.0 = bits as i64;
+        }
+    }
+}
+
+impl Mutatable for DosDateTime {
+    #[inline(always)]
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        if mutator.gen_chance(REGENERATE_TIME_CHANCE) {
+            *self = DosDateTime::new_fuzzed(mutator, None);
+            return;
+        }
+
+        if !mutator.gen_chance(TIME_DELTA_CHANCE) {
+            if mutator.gen_chance(50.0) {
+                mutator.mutate_from_mutation_mode(&mut self.date);
+            } else {
+                mutator.mutate_from_mutation_mode(&mut self.time);
+            }
+
+            return;
+        }
+
+        // step a single field without carrying into the next one, which walks off the end of
+        // months and days as often as it stays within them
+        let step: i8 = if mutator.gen_chance(50.0) { 1 } else { -1 };
+        let mut year = self.year();
+        let mut fields = [self.month(), self.day(), self.hour(), self.minute(), self.second()];
+
+        match mutator.gen_range(0, 6) {
+            0 => year = year.wrapping_add(step as u16),
+            // seconds are stored halved
+            5 => fields[4] = fields[4].wrapping_add((step * 2) as u8),
+            field => fields[field - 1] = fields[field - 1].wrapping_add(step as u8),
+        }
+
+        let [month, day, hour, minute, second] = fields;
+        *self = DosDateTime::new(year, month, day, hour, minute, second);
+    }
+}
+
+impl Mutatable for FileTime {
+    #[inline(always)]
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        if mutator.gen_chance(REGENERATE_TIME_CHANCE) {
+            *self = FileTime::new_fuzzed(mutator, None);
+        } else if mutator.gen_chance(TIME_DELTA_CHANCE) {
+            let delta = gen_nonzero_time_delta(mutator) * FileTime::INTERVALS_PER_SECOND as i64;
+            self.0 = self.0.wrapping_add(delta as u64);
+        } else {
+            mutator.mutate_from_mutation_mode(&mut self.0);
+        }
+    }
+}
+
+macro_rules! impl_mutatable_float {
+    ( $($name:ident => $bits:ident),* ) => {
+        $(
+            impl Mutatable for $name {
+                #[inline(always)]
+                fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+                    // mutate the raw bits so that the sign, exponent, and mantissa are all exercised
+                    let mut bits: $bits = self.to_bits();
+                    mutator.mutate_from_mutation_mode(&mut bits);
+                    *self = $name::from_bits(bits);
+                }
+            }
+
+            impl ConstrainedMutatable for $name {
+                fn mutate_constrained<R: Rng>(&mut self, mutator: &mut Mutator<R>, constraints: Option<&Constraints<Self::RangeType>>) {
+                    let previous = self.to_bits();
+                    self.mutate(mutator, None);
+
+                    // bit flips rarely land in a narrow range, so generate a new in-range value
+                    // if this mutation pushed us outside of it
+                    if let Some(constraints) = constraints {
+                        if self.to_bits() != previous
+                            && !constraints.contains(self)
+                            && !mutator.should_ignore_constraint()
+                        {
+                            *self = $name::new_fuzzed(mutator, Some(constraints));
+                        }
+                    }
+                }
+            }
+        )*
+    }
+}
+
+impl_mutatable_float!(f32 => u32, f64 => u64);
+
+impl<T, F> Mutatable for Scaled<T, F>
+where
+    T: NumCast + Bounded + Copy,
+    F: ScaleFactor,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        // mutate the real value and round it back to the nearest value representable on the wire
+        let mut real = self.real();
+        real.mutate(mutator, None);
+        *self = Scaled::from_real(real);
+    }
+}
+
+impl<T> Mutatable for OneOf<T>
+where
+    T: Mutatable + Clone,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, constraints: Option<&Constraints<u8>>) {
+        // the picked value is occasionally perturbed, so values near the valid ones are tried too
+        let count = self.candidates().len();
+        if count == 0 || mutator.gen_chance(crate::mutator::CHANCE_TO_PERTURB_CANDIDATE) {
+            self.value_mut().mutate(mutator, constraints);
+            return;
+        }
+
+        let value = self.candidates()[mutator.gen_range(0, count)].clone();
+        *self.value_mut() = value;
+    }
+}
+
+impl<M> Mutatable for Conversation<M>
+where
+    M: Mutatable,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, constraints: Option<&Constraints<u8>>) {
+        self.messages_mut().mutate(mutator, constraints);
+
+        if mutator.should_fixup() {
+            self.fixup(mutator);
+        }
+    }
+}
+
+impl<M> Conversation<M> {
+    /// Fixes up the messages in order, starting from the values already in `bindings` (e.g. ones
+    /// taken from the target's responses) and declaring each message's values into it
+    pub fn fixup_with<R: Rng>(&mut self, mutator: &mut Mutator<R>, bindings: &mut Bindings) {
+        // called through `M` so that the blanket impls for `&mut M` aren't picked instead
+        for message in self.messages_mut().iter_mut() {
+            M::resolve_bindings(message, bindings);
+            M::fixup(message, mutator);
+            M::declare_bindings(message, bindings);
+        }
+    }
+}
+
+impl<M> Fixup for Conversation<M> {
+    fn fixup<R: Rng>(&mut self, mutator: &mut Mutator<R>) {
+        self.fixup_with(mutator, &mut Bindings::new());
+    }
+}
+
+impl<T, C> Mutatable for TextEncoded<T, C>
+where
+    T: Mutatable,
+    C: TextEncoding,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, constraints: Option<&Constraints<u8>>) {
+        self.value.mutate(mutator, constraints);
+
+        // a flaw is picked afresh each time so that mutated values aren't stuck with invalid text
+        self.flaw = if mutator.should_flaw_encoding() {
+            Some(mutator.gen_encoding_flaw::<C>())
+        } else {
+            None
+        };
+    }
+}
+
+impl<T, F> ConstrainedMutatable for Scaled<T, F>
+where
+    T: NewFuzzed + NumCast + Bounded + Copy + PartialEq,
+    F: ScaleFactor,
+{
+    fn mutate_constrained<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) {
+        let previous = self.raw();
+        self.mutate(mutator, None);
+
+        if let Some(constraints) = constraints {
+            if self.raw() != previous
+                && !constraints.contains(&self.real())
+                && !mutator.should_ignore_constraint()
+            {
+                *self = Scaled::new_fuzzed(mutator, Some(constraints));
+            }
+        }
+    }
+}
+
+impl<T> Mutatable for Offset<T>
+where
+    T: Mutatable + PrimInt + WrappingAdd<Output = T> + WrappingSub<Output = T>,
+{
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        self.mutate_within(mutator, None);
+    }
+}
+
+impl<T> Offset<T>
+where
+    T: Mutatable + PrimInt + WrappingAdd<Output = T> + WrappingSub<Output = T>,
+{
+    /// Mutates the offset into a buffer which ends at `end`. Offsets are occasionally mutated
+    /// like any other number, and always are outside of [MutatorMode::Havoc].
+    fn mutate_within<R: Rng>(&mut self, mutator: &mut Mutator<R>, end: Option<usize>) {
+        let mut value = self.value();
+        if mutator.mode() != MutatorMode::Havoc
+            || mutator.gen_chance(crate::mutator::CHANCE_TO_MUTATE_OFFSET_AS_NUMBER)
+        {
+            value.mutate(mutator, None);
+        } else {
+            mutator.mutate_offset(&mut value, end);
+        }
+
+        *self = Offset::new(value);
+    }
+}
+
+impl<T> ConstrainedMutatable for Offset<T>
+where
+    T: NewFuzzed<RangeType = T>
+        + Mutatable
+        + PrimInt
+        + WrappingAdd<Output = T>
+        + WrappingSub<Output = T>
+        + std::fmt::Debug
+        + Default
+        + 'static,
+{
+    fn mutate_constrained<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) {
+        // the max is where the buffer ends, so an inclusive max is the last valid offset
+        let end = constraints.and_then(|constraints| {
+            let max = constraints.max?.to_usize()?;
+            Some(if constraints.bounds.includes_max() {
+                max.saturating_add(1)
+            } else {
+                max
+            })
+        });
+
+        self.mutate_within(mutator, end);
+
+        // landing just outside of the bounds is the point, so out of range offsets are only
+        // regenerated when every value has to be valid
+        if let Some(constraints) = constraints {
+            if !constraints.contains(&self.value()) && mutator.profile() == GenerationProfile::Valid
+            {
+                *self = Offset::new_fuzzed(mutator, Some(constraints));
+            }
+        }
+    }
+}
+
 impl<T> Mutatable for [T; 0]
 where
     T: Mutatable,
@@ -136,3 +946,44 @@ impl_mutatable_array!(
     27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
     51, 52, 53, 54, 55, 56, 57, 58, 59, 60
 );
+
+macro_rules! impl_mutatable_tuple {
+    ( $($name:ident $idx:tt),+ ) => {
+        impl<$($name),+> Mutatable for ($($name,)+)
+        where
+            $($name: Mutatable),+
+        {
+            fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+                // elements are mutated like the fields of a derived struct
+                $(
+                    self.$idx.mutate(mutator, None);
+
+                    if mutator.should_early_bail_mutation() {
+                        if mutator.should_fixup() {
+                            self.$idx.fixup(mutator);
+                        }
+
+                        return;
+                    }
+                )+
+            }
+        }
+    }
+}
+
+for_each_tuple!(impl_mutatable_tuple);
+
+macro_rules! impl_mutatable_zero_sized {
+    ( $($name:ty $(, $generic:ident)?);* ) => {
+        $(
+            impl$(<$generic>)? Mutatable for $name {
+                #[inline(always)]
+                fn mutate<R: Rng>(&mut self, _mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+                    // nop - there's nothing to mutate
+                }
+            }
+        )*
+    }
+}
+
+impl_mutatable_zero_sized!((); PhantomData<T>, T; FieldSwap);
@@ -1,15 +1,25 @@
+use rand::seq::index::sample;
 use rand::seq::SliceRandom;
-use rand::Rng;
-
+use rand::{Rng, SeedableRng};
+
+use crate::adaptive::{AdaptiveWeights, Choice};
+use crate::autodict::AutoDictionary;
+use crate::cmplog::CompareTable;
+use crate::corpus::Corpus;
+use crate::dangerous_numbers::select_interesting_value;
+use crate::model_coverage::ModelCoverage;
 use crate::rand::distributions::uniform::{SampleBorrow, SampleUniform};
 use crate::traits::*;
 use crate::types::*;
-use num::{Bounded, NumCast};
-use num_traits::{WrappingAdd, WrappingSub};
+use num::{Bounded, Float, NumCast};
+use num_traits::{PrimInt, WrappingAdd, WrappingSub};
 
-use crate::lain_derive::NewFuzzed;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::ops::{Add, BitXor, Div, Mul, Range, Sub};
+use std::sync::{Arc, RwLock};
 
-use std::ops::{Add, BitXor, Div, Mul, Sub};
+use crate::lain_derive::NewFuzzed;
 
 #[cfg(feature = "serde_support")]
 use serde::{Deserialize, Serialize};
@@ -19,18 +29,304 @@ pub const CHANCE_TO_REPEAT_ARRAY_VALUE: f32 = 1.0;
 pub const CHANCE_TO_PICK_INVALID_ENUM: f32 = 1.0;
 pub const CHANCE_TO_IGNORE_MIN_MAX: f32 = 1.0;
 pub const CHANCE_TO_IGNORE_POST_MUTATION: f32 = 1.0;
+pub const CHANCE_TO_REPLACE_OPAQUE: f32 = 2.0;
+pub const CHANCE_TO_FLAW_ENCODING: f32 = 2.0;
+pub const CHANCE_TO_SWITCH_VARIANT: f32 = 5.0;
+pub const CHANCE_TO_SUBSTITUTE_OPERAND: f32 = 10.0;
+pub const CHANCE_TO_PICK_BOUNDARY_LENGTH: f32 = 25.0;
+pub const CHANCE_TO_PERTURB_CANDIDATE: f32 = 10.0;
+pub const CHANCE_TO_MUTATE_OFFSET_AS_NUMBER: f32 = 25.0;
+
+/// Largest count an array is grown to when its `#[lain(count_field = "...")]` count is mutated.
+/// Larger counts are reset to the array's length unless they're deliberately left out of sync.
+const MAX_RESIZED_COUNT: usize = 0x1000;
+
+/// Largest number of bytes an [Offset] is moved by when it's nudged
+const MAX_OFFSET_NUDGE: usize = 16;
+
+// chances used instead of the above when the mutator is using the hostile generation profile
+pub const HOSTILE_CHANCE_TO_PICK_INVALID_ENUM: f32 = 50.0;
+pub const HOSTILE_CHANCE_TO_IGNORE_MIN_MAX: f32 = 25.0;
+pub const HOSTILE_CHANCE_TO_IGNORE_POST_MUTATION: f32 = 25.0;
+pub const HOSTILE_CHANCE_TO_PICK_EXTREME_VALUE: f32 = 50.0;
+pub const HOSTILE_CHANCE_TO_FLAW_ENCODING: f32 = 25.0;
+
+/// RNGs which can be reseeded for a seed domain. Only RNGs implementing [SeedableRng] can be.
+trait DomainRng: Sized {
+    fn from_domain_seed(seed: u64) -> Option<Self>;
+}
 
-#[repr(u8)]
-#[derive(Debug, Copy, Clone, NewFuzzed)]
-enum MutatorOperation {
-    #[weight(2)]
-    BitFlip,
+impl<R: Rng> DomainRng for R {
+    default fn from_domain_seed(_seed: u64) -> Option<Self> {
+        None
+    }
+}
 
-    #[weight(2)]
-    Flip,
+impl<R: Rng + SeedableRng> DomainRng for R {
+    fn from_domain_seed(seed: u64) -> Option<Self> {
+        Some(R::seed_from_u64(seed))
+    }
+}
+
+/// FNV-1a hash of a seed domain's name. This needs to be stable across runs and platforms,
+/// which rules out `DefaultHasher`.
+fn seed_domain_hash(domain: &str) -> u64 {
+    domain.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
 
-    #[weight(2)]
+/// Strategies used by [Mutator::mutate] to mutate integers. One of the strategies enabled in the
+/// mutator's [MutatorConfig] is picked at random for each mutation.
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum NumericStrategy {
+    /// Flips a single bit
+    BitFlip,
+    /// Flips a random number of bits
+    Flip,
+    /// Adds or subtracts a small delta, up to [MutatorConfig::max_arithmetic_delta]
     Arithmetic,
+    /// Multiplies or divides by 2
+    ScaleByTwo,
+    /// Moves to the nearest power of two, or one either side of it (e.g. 1000 becomes 1023, 1024,
+    /// or 1025). Negative numbers move to the nearest negative power of two.
+    PowerOfTwoBoundary,
+    /// Moves to within a few steps of the type's min or max, wrapping around on overflow (e.g.
+    /// a `u8` may become 253 or 2)
+    BoundaryWrap,
+    /// Replaces the number with a built-in interesting value for its width (0, 1, MAX, MAX-1,
+    /// sign boundaries, page sizes, magic numbers, etc.) or one of the values registered in
+    /// [MutatorConfig::interesting_values]
+    InterestingValue,
+    /// Replaces the number with an operand of a comparison the target recently made between
+    /// numbers of the same width, preferring the other side of a comparison the number itself was
+    /// part of (see [cmplog](crate::cmplog)). Falls back to [NumericStrategy::InterestingValue]
+    /// when no comparisons were captured.
+    CompareOperand,
+}
+
+/// Strategies used to mutate `AsciiString`s and `Utf8String`s. Unless a [StrategyMix] says
+/// otherwise, one is picked at random with the relative weights given below.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, NewFuzzed)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum StringStrategy {
+    /// Replaces random characters with newly generated ones (weight 4)
+    #[lain(weight(4))]
+    ReplaceChars,
+    /// Flips the case of a run of characters (weight 2)
+    #[lain(weight(2))]
+    FlipCase,
+    /// Percent-encodes a run of characters (weight 1)
+    #[lain(weight(1))]
+    PercentEncode,
+    /// Inserts a token from a built-in dictionary of format strings, path traversals, and overlong
+    /// encodings, or one [learned](crate::autodict) from the target (weight 2)
+    #[lain(weight(2))]
+    InsertToken,
+    /// Repeats a run of characters many times (weight 1)
+    #[lain(weight(1))]
+    Repeat,
+}
+
+/// Kinds of fields which may be mutated with their own [StrategyMix]. The derives determine a
+/// field's category from its type: primitive integers are [FieldCategory::Integer], `String`,
+/// `AsciiString` and `Utf8String` are [FieldCategory::String], `Vec`s and arrays are
+/// [FieldCategory::Collection], and `UnsafeEnum`s are [FieldCategory::Enum]. Other fields can be
+/// given a category with `#[lain(category = "string")]`.
+///
+/// Values mutated outside of a categorized field use the mix for their own type's category.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum FieldCategory {
+    Integer,
+    String,
+    /// Includes the elements of the collection, e.g. the bytes of a `Vec<u8>` payload
+    Collection,
+    /// Includes the invalid discriminants that `UnsafeEnum`s are mutated to
+    Enum,
+}
+
+/// Relative weights of the strategies used to mutate fields of a [FieldCategory]. A strategy's
+/// chance of being picked is its weight divided by the sum of the list's weights, and strategies
+/// which aren't listed are never picked. An empty list keeps the default choice of strategies.
+///
+/// ```
+/// use lain::prelude::*;
+///
+/// let mut config = MutatorConfig::default();
+/// // headers want arithmetic and bit flips, strings want the token dictionary
+/// config.set_strategy_mix(
+///     FieldCategory::Integer,
+///     StrategyMix::default()
+///         .with_numeric(NumericStrategy::Arithmetic, 3)
+///         .with_numeric(NumericStrategy::BitFlip, 1),
+/// );
+/// config.set_strategy_mix(
+///     FieldCategory::String,
+///     StrategyMix::default()
+///         .with_string(StringStrategy::InsertToken, 5)
+///         .with_string(StringStrategy::Repeat, 1),
+/// );
+/// ```
+#[derive(PartialEq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct StrategyMix {
+    /// Weights of the strategies used to mutate integers, replacing
+    /// [MutatorConfig::numeric_strategies] for this category
+    pub numeric: Vec<(NumericStrategy, u32)>,
+    /// Weights of the strategies used to mutate strings
+    pub string: Vec<(StringStrategy, u32)>,
+}
+
+impl StrategyMix {
+    pub fn with_numeric(mut self, strategy: NumericStrategy, weight: u32) -> Self {
+        self.numeric.push((strategy, weight));
+        self
+    }
+
+    pub fn with_string(mut self, strategy: StringStrategy, weight: u32) -> Self {
+        self.string.push((strategy, weight));
+        self
+    }
+}
+
+/// Tunables for how a [Mutator] mutates data
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct MutatorConfig {
+    /// Integer mutation strategies to pick from. An empty list leaves integers untouched by
+    /// havoc mutations.
+    pub numeric_strategies: Vec<NumericStrategy>,
+    /// Largest delta added or subtracted by [NumericStrategy::Arithmetic]
+    pub max_arithmetic_delta: u32,
+    /// How many steps past a type's min or max [NumericStrategy::BoundaryWrap] may land
+    pub max_boundary_distance: u32,
+    /// Target-specific values (e.g. lengths or IDs the target special-cases) picked by
+    /// [NumericStrategy::InterestingValue] half of the time. A value is only used for integers
+    /// wide enough to hold it as either a signed or unsigned number.
+    pub interesting_values: Vec<i128>,
+    /// Percent chance that a `#[lain(count_field = "...")]` count is left out of sync with its
+    /// array after a mutation changes either of them. Counts are never desynchronized with the
+    /// [GenerationProfile::Valid] profile.
+    pub count_desync_chance: f32,
+    /// Percent chance that mutating a `Vec` inserts a copy of one of its elements (an entire
+    /// nested struct for structured elements) right after the original, exercising the target's
+    /// handling of repeated sections. Containers may override this for their own fields with
+    /// `#[lain(duplicate_chance = N)]`. Elements are only duplicated if they implement `Clone`.
+    pub duplicate_chance: f32,
+    /// Percent chance that an `Option` is generated as `None`
+    pub none_chance: f32,
+    /// Percent chance that a `Result` is generated as `Err`. Lowering this biases models of API
+    /// types toward their success paths, and raising it toward their error handling.
+    pub err_chance: f32,
+    /// Strategy weights for fields of a given category. Categories without a mix use
+    /// [MutatorConfig::numeric_strategies] and the default [StringStrategy] weights.
+    pub strategy_mixes: HashMap<FieldCategory, StrategyMix>,
+    /// Percent chance that a havoc mutation of a derived container is followed by another pass
+    /// over its fields, each of which mutates a randomly picked field. The number of passes is
+    /// geometrically distributed (e.g. 50.0 averages 2 passes), similar to AFL's stacked havoc
+    /// mutations. Deterministic [MutatorMode]s always make a single pass.
+    pub stacked_mutation_chance: f32,
+    /// Most passes a single `mutate` call makes. See [MutatorConfig::stacked_mutation_chance].
+    pub max_stacked_mutations: usize,
+    /// Percent chance that a havoc mutation of a derived struct with a
+    /// [splice corpus](Mutator::set_splice_corpus) replaces one of its fields with the same field
+    /// of a random corpus entry instead of mutating its fields
+    pub splice_chance: f32,
+    /// Percent chance that a havoc mutation of a derived struct marked
+    /// `#[lain(field_swap = "...")]` has it serialized with two adjacent fields in swapped order,
+    /// without changing the fields themselves, exercising the target's assumptions about field
+    /// order. The swap is recorded in the struct's [FieldSwap] field and lasts until the struct is
+    /// mutated again. Bitfields are never swapped, and fields are never swapped with the
+    /// [GenerationProfile::Valid] profile.
+    pub field_swap_chance: f32,
+    /// Most bytes a single `mutate` call may change, tallied across every nested field. Numbers,
+    /// strings, and byte buffers are only changed when the change fits in what's left of the
+    /// budget, and fields aren't regenerated, duplicated, spliced, or switched to another variant
+    /// while a budget is set. Small, localized changes get past targets which reject anything
+    /// that differs too much from a valid input. `None` leaves mutations unbounded.
+    pub max_bytes_changed: Option<usize>,
+    /// Most characters an `AsciiString` or `Utf8String` may grow to through mutation. Strategies
+    /// which insert characters ([StringStrategy::PercentEncode], [StringStrategy::InsertToken],
+    /// and [StringStrategy::Repeat]) stop short of this, so strings don't grow without bound over
+    /// repeated mutations.
+    pub max_string_len: usize,
+}
+
+impl Default for MutatorConfig {
+    fn default() -> Self {
+        MutatorConfig {
+            numeric_strategies: vec![
+                NumericStrategy::BitFlip,
+                NumericStrategy::Flip,
+                NumericStrategy::Arithmetic,
+                NumericStrategy::ScaleByTwo,
+                NumericStrategy::PowerOfTwoBoundary,
+                NumericStrategy::BoundaryWrap,
+                NumericStrategy::InterestingValue,
+                NumericStrategy::CompareOperand,
+            ],
+            max_arithmetic_delta: 15,
+            max_boundary_distance: 4,
+            interesting_values: Vec::new(),
+            count_desync_chance: 5.0,
+            duplicate_chance: 1.0,
+            none_chance: 50.0,
+            err_chance: 50.0,
+            strategy_mixes: HashMap::new(),
+            stacked_mutation_chance: 0.0,
+            max_stacked_mutations: 16,
+            splice_chance: 5.0,
+            field_swap_chance: 0.0,
+            max_bytes_changed: None,
+            max_string_len: 4096,
+        }
+    }
+}
+
+impl MutatorConfig {
+    /// Registers a target-specific interesting value. See [MutatorConfig::interesting_values].
+    pub fn add_interesting_value<T: Into<i128>>(&mut self, value: T) {
+        self.interesting_values.push(value.into());
+    }
+
+    /// Sets the strategy weights used for fields of `category`. See [StrategyMix].
+    pub fn set_strategy_mix(&mut self, category: FieldCategory, mix: StrategyMix) {
+        self.strategy_mixes.insert(category, mix);
+    }
+}
+
+/// Converts `value` to `T`, wrapping it around `T`'s range the same way an overflowing integer
+/// operation would
+fn wrapping_from_i128<T: NumCast + Bounded>(value: i128) -> T {
+    let min: i128 = num::cast(T::min_value()).unwrap();
+    let range = 1i128 << (std::mem::size_of::<T>() * 8);
+
+    num::cast((value - min).rem_euclid(range) + min).unwrap()
+}
+
+/// Number of bytes which differ between two integers
+fn changed_int_bytes<T: BitXor<Output = T> + NumCast + Copy>(previous: T, current: T) -> usize {
+    // the difference of signed integers is sign-extended, which only sets bytes past their width
+    let difference: i128 = num::cast(previous ^ current).unwrap_or(-1);
+
+    (0..std::mem::size_of::<T>())
+        .filter(|i| (difference >> (i * 8)) & 0xff != 0)
+        .count()
+}
+
+/// Number of bytes which differ between two byte strings, counting bytes past the end of the
+/// shorter one as changed
+pub(crate) fn changed_bytes(previous: &[u8], current: &[u8]) -> usize {
+    let differing = previous.iter().zip(current).filter(|(a, b)| a != b).count();
+    let (longer, shorter) = if previous.len() > current.len() {
+        (previous, current)
+    } else {
+        (current, previous)
+    };
+
+    differing + longer.len() - shorter.len()
 }
 
 #[derive(PartialEq, Clone, Debug)]
@@ -52,6 +348,150 @@ pub enum MutatorMode {
     Havoc,
 }
 
+/// Named presets which globally adjust how far generated data strays from the constraints
+/// described by a data structure. The profile may be changed between iterations to dial the
+/// hostility of generated data up or down.
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum GenerationProfile {
+    /// Constraints, valid enum variants, and fixups are always respected
+    Valid,
+    /// Occasionally violates a constraint, but no more than once per iteration
+    SemiValid,
+    /// The default mix: constraints and fixups are occasionally ignored
+    #[default]
+    Balanced,
+    /// Frequently ignores constraints and fixups, and favors extreme values and invalid enum variants
+    Hostile,
+}
+
+/// How far the current iteration's input strays from valid, tallied by the mutator while the
+/// input is generated. Harnesses can use it to route risky inputs to a sandboxed instance of the
+/// target and clean ones to a fast path. See [Mutator::danger_score].
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct DangerScore {
+    /// Min/max constraints which were ignored
+    pub constraint_violations: usize,
+    /// Numbers picked from the list of dangerous numbers
+    pub extreme_values: usize,
+    /// Enums generated with an invalid discriminant
+    pub invalid_enums: usize,
+    /// Text values whose encoding was made invalid
+    pub flawed_encodings: usize,
+    /// Fixups (e.g. lengths and checksums) which were skipped while mutating
+    pub skipped_fixups: usize,
+}
+
+impl DangerScore {
+    /// The number of deliberately invalid or extreme choices made
+    pub fn total(&self) -> usize {
+        self.constraint_violations
+            + self.extreme_values
+            + self.invalid_enums
+            + self.flawed_encodings
+            + self.skipped_fixups
+    }
+
+    /// Whether the input was generated without any deliberately invalid or extreme choices
+    pub fn is_clean(&self) -> bool {
+        self.total() == 0
+    }
+}
+
+/// Overrides for the decisions a [Mutatable::mutate] call otherwise leaves to chance and to the
+/// mutator's state, for callers which mutate values themselves rather than through the driver.
+/// Fields left as `None` keep the default behavior. See [Mutator::mutate_with_options].
+///
+/// [Mutatable::mutate]: crate::traits::Mutatable::mutate
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct MutateOptions {
+    /// Whether fixups (e.g. recomputing lengths and checksums) run after fields are mutated
+    pub fixup: Option<bool>,
+    /// Whether mutation stops after the first field which was mutated. When disabled every
+    /// field of the value is visited.
+    pub early_bail: Option<bool>,
+    /// Maximum number of numeric and string fields mutated
+    pub max_fields: Option<usize>,
+}
+
+impl MutateOptions {
+    pub fn with_fixup(mut self, fixup: bool) -> Self {
+        self.fixup = Some(fixup);
+        self
+    }
+
+    pub fn with_early_bail(mut self, early_bail: bool) -> Self {
+        self.early_bail = Some(early_bail);
+        self
+    }
+
+    pub fn with_max_fields(mut self, max_fields: usize) -> Self {
+        self.max_fields = Some(max_fields);
+        self
+    }
+}
+
+/// The version of a protocol being fuzzed. Structs which model several versions of a protocol
+/// mark the field holding their version with `#[lain(protocol_version)]`, and their
+/// `#[lain(version_gate = "...")]` fields are only generated and serialized when the gate holds.
+/// Selecting a version with [Mutator::set_protocol_version] pins that field so every generated
+/// value follows the selected version's layout.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct ProtocolVersion(pub u32);
+
+/// Which variants of an enum each of its variants may be mutated into, e.g. a `Read` request may
+/// become a `Write` but a `Hello` shouldn't become `Data`. Variants are named as they're declared,
+/// and variants without any allowed transitions may be mutated into any variant. Disallowed
+/// transitions are still made when the mutator's [GenerationProfile] ignores a constraint.
+///
+/// Transitions can be declared on a derived enum's variants with
+/// `#[lain(transitions(Write, Close))]`, or set at runtime with
+/// [Mutator::set_variant_transitions], which replaces the declared ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct VariantTransitions {
+    allowed: HashMap<String, Vec<String>>,
+}
+
+impl VariantTransitions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Allows the variant `from` to be mutated into each of `to`
+    pub fn allow(mut self, from: &str, to: &[&str]) -> Self {
+        self.allowed
+            .entry(from.to_string())
+            .or_default()
+            .extend(to.iter().map(|to| to.to_string()));
+
+        self
+    }
+
+    /// Whether the variant `from` may be mutated into `to`. A variant may always stay as it is.
+    pub fn is_allowed(&self, from: &str, to: &str) -> bool {
+        from == to
+            || self
+                .allowed
+                .get(from)
+                .is_none_or(|allowed| allowed.iter().any(|a| a == to))
+    }
+}
+
+/// The transitions declared on a derived enum's variants, as `(from, [to])`
+#[doc(hidden)]
+pub type DeclaredTransitions = &'static [(&'static str, &'static [&'static str])];
+
+/// The variant a derived enum is being mutated away from
+#[derive(Debug, Clone, Copy)]
+struct VariantTransition {
+    enum_name: &'static str,
+    from: &'static str,
+    declared: DeclaredTransitions,
+}
+
 /// Represents the state of the current corpus item being fuzzed.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
@@ -94,6 +534,52 @@ pub struct Mutator<R: Rng> {
     pub rng: R,
     flags: Vec<MutatorFlags>,
     corpus_state: CorpusFuzzingState,
+    max_allocation: Option<usize>,
+    allocation_depth: usize,
+    allocated_bytes: usize,
+    profile: GenerationProfile,
+    fixup_enabled: bool,
+    violated_constraint: bool,
+    danger_score: DangerScore,
+    mutate_options: MutateOptions,
+    fields_mutated: usize,
+    bytes_changed: usize,
+    exhaustive_index: Option<usize>,
+    seed: Option<u64>,
+    config: MutatorConfig,
+    duplicate_chance: Option<f32>,
+    category: Option<FieldCategory>,
+    protocol_version: Option<ProtocolVersion>,
+    field_path: Vec<&'static str>,
+    adaptive_weights: Option<Arc<AdaptiveWeights>>,
+    dictionary: Option<Arc<AutoDictionary>>,
+    compare_table: Option<Arc<CompareTable>>,
+    model_coverage: Option<Arc<ModelCoverage>>,
+    input_len: Option<usize>,
+    choices: Vec<Choice>,
+    mutation_depth: usize,
+    mutation_stack: Option<MutationStack>,
+    static_sections: HashMap<(&'static str, &'static str), Box<dyn Any + Send>>,
+    variant_transitions: HashMap<&'static str, VariantTransitions>,
+    variant_transition: Option<VariantTransition>,
+    splice_corpora: SpliceCorpora,
+    candidate_pools: CandidatePools,
+}
+
+/// Corpora which derived structs splice fields from, keyed by the type of their entries. Each is
+/// an `Arc<RwLock<Corpus<T>>>`.
+pub(crate) type SpliceCorpora = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+
+/// Candidates which [OneOf] values are picked from, keyed by the type of the candidates. Each is
+/// an `Arc<Vec<T>>`.
+pub(crate) type CandidatePools = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+
+/// Field counters of the corpus state saved while a top-level `mutate` call makes stacked passes
+#[derive(Debug, Copy, Clone)]
+struct MutationStack {
+    fields_fuzzed: usize,
+    targeted_field_idx: usize,
+    first_pass_fields: Option<usize>,
 }
 
 impl<R: Rng> Mutator<R> {
@@ -102,6 +588,36 @@ impl<R: Rng> Mutator<R> {
             rng,
             flags: Vec::new(),
             corpus_state: CorpusFuzzingState::default(),
+            max_allocation: None,
+            allocation_depth: 0,
+            allocated_bytes: 0,
+            profile: GenerationProfile::default(),
+            fixup_enabled: true,
+            violated_constraint: false,
+            danger_score: DangerScore::default(),
+            mutate_options: MutateOptions::default(),
+            fields_mutated: 0,
+            bytes_changed: 0,
+            exhaustive_index: None,
+            seed: None,
+            config: MutatorConfig::default(),
+            duplicate_chance: None,
+            category: None,
+            protocol_version: None,
+            field_path: Vec::new(),
+            adaptive_weights: None,
+            dictionary: None,
+            compare_table: None,
+            model_coverage: None,
+            input_len: None,
+            choices: Vec::new(),
+            mutation_depth: 0,
+            mutation_stack: None,
+            static_sections: HashMap::new(),
+            variant_transitions: HashMap::new(),
+            variant_transition: None,
+            splice_corpora: HashMap::new(),
+            candidate_pools: HashMap::new(),
         }
     }
 
@@ -121,6 +637,506 @@ impl<R: Rng> Mutator<R> {
         self.corpus_state = state;
     }
 
+    /// Sets the [GenerationProfile] used for generating and mutating data
+    pub fn set_profile(&mut self, profile: GenerationProfile) {
+        self.profile = profile;
+    }
+
+    pub fn profile(&self) -> GenerationProfile {
+        self.profile
+    }
+
+    /// Enables or disables fixups (e.g. recomputing lengths and checksums after generating or
+    /// mutating). While disabled, [Mutator::should_fixup] always returns `false` so that the
+    /// fields fixups would repair are left corrupt, exercising the target's error paths. The
+    /// setting persists across iterations.
+    pub fn set_fixup_enabled(&mut self, enabled: bool) {
+        self.fixup_enabled = enabled;
+    }
+
+    pub fn fixup_enabled(&self) -> bool {
+        self.fixup_enabled
+    }
+
+    /// The [DangerScore] of the input generated since the last call to
+    /// [Mutator::begin_new_iteration]
+    pub fn danger_score(&self) -> DangerScore {
+        self.danger_score
+    }
+
+    /// Mutates `value` with the decisions `options` sets made the same way every time, e.g.
+    /// touching at most one field and never repairing the fields fixups would recompute. The
+    /// options only apply to this call.
+    pub fn mutate_with_options<T: Mutatable>(&mut self, value: &mut T, options: MutateOptions) {
+        let previous_options = std::mem::replace(&mut self.mutate_options, options);
+        let previous_fields_mutated = std::mem::replace(&mut self.fields_mutated, 0);
+        let previous_bytes_changed = std::mem::replace(&mut self.bytes_changed, 0);
+
+        value.mutate(self, None);
+
+        self.mutate_options = previous_options;
+        self.fields_mutated = previous_fields_mutated;
+        self.bytes_changed = previous_bytes_changed;
+    }
+
+    /// The [MutateOptions] of the [Mutator::mutate_with_options] call in progress
+    pub fn mutate_options(&self) -> MutateOptions {
+        self.mutate_options
+    }
+
+    /// Whether [MutatorConfig::max_bytes_changed] limits the bytes mutations may change
+    pub fn has_byte_budget(&self) -> bool {
+        self.config.max_bytes_changed.is_some()
+    }
+
+    /// Number of bytes changed by mutations since the last call to [Mutator::begin_new_iteration]
+    pub fn bytes_changed(&self) -> usize {
+        self.bytes_changed
+    }
+
+    /// Counts `bytes` changed by a mutation against [MutatorConfig::max_bytes_changed]. Returns
+    /// `false` without counting them if they don't fit in what's left of the budget, in which
+    /// case the mutation should be undone.
+    pub fn charge_bytes_changed(&mut self, bytes: usize) -> bool {
+        if let Some(max_bytes_changed) = self.config.max_bytes_changed {
+            if self.bytes_changed + bytes > max_bytes_changed {
+                return false;
+            }
+        }
+
+        self.bytes_changed += bytes;
+
+        true
+    }
+
+    /// Counts a field about to be mutated against [MutateOptions::max_fields]. Returns `false` if
+    /// no more fields may be mutated.
+    fn take_field(&mut self) -> bool {
+        if let Some(max_fields) = self.mutate_options.max_fields {
+            if self.fields_mutated >= max_fields {
+                return false;
+            }
+        }
+
+        self.fields_mutated += 1;
+
+        true
+    }
+
+    /// Selects the [ProtocolVersion] that `#[lain(protocol_version)]` fields are generated as and
+    /// kept at while mutating. `None` lets the version fields be fuzzed like any other field.
+    pub fn set_protocol_version(&mut self, version: Option<ProtocolVersion>) {
+        self.protocol_version = version;
+    }
+
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        self.protocol_version
+    }
+
+    /// Returns a clone of the section generated for the field `field` of the type `parent`. The
+    /// section is generated with `generate` the first time it's requested and reused after that,
+    /// so that large sections which never change (e.g. a valid certificate chain) are generated
+    /// once per campaign rather than every iteration. Used by `#[lain(generate_once)]` fields.
+    pub fn generate_once<T, F>(
+        &mut self,
+        parent: &'static str,
+        field: &'static str,
+        generate: F,
+    ) -> T
+    where
+        T: Clone + Send + 'static,
+        F: FnOnce(&mut Self) -> T,
+    {
+        let key = (parent, field);
+        if let Some(section) = self
+            .static_sections
+            .get(&key)
+            .and_then(|section| section.downcast_ref::<T>())
+        {
+            return section.clone();
+        }
+
+        let section = generate(self);
+        self.static_sections.insert(key, Box::new(section.clone()));
+
+        section
+    }
+
+    /// Drops the sections cached by [Mutator::generate_once] so that they're generated again, e.g.
+    /// after selecting a different [ProtocolVersion]
+    pub fn clear_static_sections(&mut self) {
+        self.static_sections.clear();
+    }
+
+    /// Registers a corpus of stored inputs of type `T` which derived structs of the same type splice
+    /// whole fields (and so entire nested structures) from. See [MutatorConfig::splice_chance].
+    /// Entries added to the corpus while the campaign runs are spliced from as well.
+    pub fn set_splice_corpus<T>(&mut self, corpus: Arc<RwLock<Corpus<T>>>)
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        self.splice_corpora.insert(TypeId::of::<T>(), corpus);
+    }
+
+    pub(crate) fn set_splice_corpora(&mut self, corpora: SpliceCorpora) {
+        self.splice_corpora = corpora;
+    }
+
+    /// Returns a copy of a random entry of the [splice corpus](Mutator::set_splice_corpus) for `T`
+    /// if a havoc mutation of a `T` should splice fields from one
+    pub fn splice_donor<T: Clone + 'static>(&mut self) -> Option<T> {
+        if self.mode() != MutatorMode::Havoc || self.has_byte_budget() {
+            return None;
+        }
+
+        let corpus = self.splice_corpora.get(&TypeId::of::<T>())?.clone();
+        let corpus = corpus.downcast_ref::<RwLock<Corpus<T>>>()?.read().unwrap();
+        if corpus.is_empty() || !self.gen_chance(self.config.splice_chance) {
+            return None;
+        }
+
+        let index = self.gen_range(0, corpus.len());
+
+        Some(corpus.entries()[index].clone())
+    }
+
+    /// Registers the known-valid values of `T` which [OneOf]`<T>` values are generated from,
+    /// replacing any registered before
+    pub fn set_candidates<T>(&mut self, candidates: Vec<T>)
+    where
+        T: Send + Sync + 'static,
+    {
+        self.candidate_pools
+            .insert(TypeId::of::<T>(), Arc::new(candidates));
+    }
+
+    pub(crate) fn set_candidate_pools(&mut self, pools: CandidatePools) {
+        self.candidate_pools = pools;
+    }
+
+    /// The candidates registered for `T` with [Mutator::set_candidates]
+    pub fn candidates<T: Send + Sync + 'static>(&self) -> Option<Arc<Vec<T>>> {
+        self.candidate_pools
+            .get(&TypeId::of::<T>())?
+            .clone()
+            .downcast::<Vec<T>>()
+            .ok()
+    }
+
+    /// Sets which variants of the enum `T` each variant may be mutated into, replacing any
+    /// transitions declared on `T` itself. See [VariantTransitions].
+    pub fn set_variant_transitions<T: ?Sized>(&mut self, transitions: VariantTransitions) {
+        self.variant_transitions
+            .insert(std::any::type_name::<T>(), transitions);
+    }
+
+    /// Marks that the variant `from` of `enum_name` is about to be replaced by a newly generated
+    /// variant, which should be one it may transition into
+    #[doc(hidden)]
+    pub fn begin_variant_transition(
+        &mut self,
+        enum_name: &'static str,
+        from: &'static str,
+        declared: DeclaredTransitions,
+    ) {
+        self.variant_transition = Some(VariantTransition {
+            enum_name,
+            from,
+            declared,
+        });
+    }
+
+    #[doc(hidden)]
+    pub fn end_variant_transition(&mut self) {
+        self.variant_transition = None;
+    }
+
+    /// Returns whether each of `variants` of `enum_name` may be picked, or `None` if the enum
+    /// isn't being mutated from a variant with restricted transitions. This consumes the
+    /// transition so that nested values of the same enum are generated as usual.
+    pub(crate) fn take_allowed_transitions(
+        &mut self,
+        enum_name: &'static str,
+        variants: &[&'static str],
+    ) -> Option<Vec<bool>> {
+        let transition = match self.variant_transition {
+            Some(transition) if transition.enum_name == enum_name => transition,
+            _ => return None,
+        };
+
+        self.variant_transition = None;
+
+        let allowed = match self.variant_transitions.get(enum_name) {
+            Some(transitions) => variants
+                .iter()
+                .map(|to| transitions.is_allowed(transition.from, to))
+                .collect(),
+            None => {
+                let declared = transition
+                    .declared
+                    .iter()
+                    .find(|(from, _)| *from == transition.from)?;
+
+                variants
+                    .iter()
+                    .map(|to| *to == transition.from || declared.1.contains(to))
+                    .collect()
+            }
+        };
+
+        Some(allowed)
+    }
+
+    /// Marks the start of generating the field `name` of a derived struct. Fields are only tracked
+    /// while [generation observers](crate::observer) are registered or weighting is
+    /// [adaptive](Mutator::set_adaptive_weights).
+    pub fn enter_field(&mut self, name: &'static str) {
+        self.field_path.push(name);
+    }
+
+    /// Marks the end of a field started with [Mutator::enter_field]
+    pub fn exit_field(&mut self) {
+        self.field_path.pop();
+    }
+
+    /// Path of the field being generated from the outermost struct, e.g. `header.length`
+    pub fn field_path(&self) -> String {
+        self.field_path.join(".")
+    }
+
+    /// Sets the outcome tallies which down-weight the enum variants and field values the target
+    /// keeps rejecting. See [adaptive](crate::adaptive).
+    pub fn set_adaptive_weights(&mut self, weights: Option<Arc<AdaptiveWeights>>) {
+        self.adaptive_weights = weights;
+    }
+
+    pub fn adaptive_weights(&self) -> Option<&Arc<AdaptiveWeights>> {
+        self.adaptive_weights.as_ref()
+    }
+
+    /// Sets the dictionary of tokens learned from the target, which are inserted into strings
+    /// alongside the built-in tokens. See [autodict](crate::autodict).
+    pub fn set_dictionary(&mut self, dictionary: Option<Arc<AutoDictionary>>) {
+        self.dictionary = dictionary;
+    }
+
+    pub fn dictionary(&self) -> Option<&Arc<AutoDictionary>> {
+        self.dictionary.as_ref()
+    }
+
+    /// Sets the table of comparisons the target made, whose operands are substituted into
+    /// integers and byte buffers. See [cmplog](crate::cmplog).
+    pub fn set_compare_table(&mut self, table: Option<Arc<CompareTable>>) {
+        self.compare_table = table;
+    }
+
+    pub fn compare_table(&self) -> Option<&Arc<CompareTable>> {
+        self.compare_table.as_ref()
+    }
+
+    /// Sets the tallies of which enum variants, `Option` arms, and field values have been
+    /// generated. See [model_coverage](crate::model_coverage).
+    pub fn set_model_coverage(&mut self, coverage: Option<Arc<ModelCoverage>>) {
+        self.model_coverage = coverage;
+    }
+
+    pub fn model_coverage(&self) -> Option<&Arc<ModelCoverage>> {
+        self.model_coverage.as_ref()
+    }
+
+    /// Whether choices are being weighted and recorded for [adaptive](crate::adaptive) weighting
+    #[inline(always)]
+    pub fn is_adaptive(&self) -> bool {
+        self.adaptive_weights.is_some()
+    }
+
+    /// The choices recorded since the start of the iteration while weighting is
+    /// [adaptive](Mutator::set_adaptive_weights)
+    pub fn choices(&self) -> &[Choice] {
+        &self.choices
+    }
+
+    /// Records that variant `index` (in declaration order) of `enum_name` was generated
+    #[doc(hidden)]
+    pub fn record_variant(&mut self, enum_name: &'static str, index: usize) {
+        if self.is_adaptive() {
+            self.choices.push(Choice::Variant { enum_name, index });
+        }
+    }
+
+    /// Records for [model coverage](Mutator::set_model_coverage) that variant `num` of the
+    /// generatable variants `names` of `enum_name` was generated
+    #[doc(hidden)]
+    pub fn cover_variant(
+        &mut self,
+        enum_name: &'static str,
+        names: &'static [&'static str],
+        num: usize,
+    ) {
+        if let Some(coverage) = self.model_coverage.as_ref() {
+            coverage.record_variant(enum_name, names, num);
+        }
+    }
+
+    pub(crate) fn record_choice(&mut self, choice: Choice) {
+        self.choices.push(choice);
+    }
+
+    /// Sets the [MutatorConfig] used for mutating data
+    pub fn set_config(&mut self, config: MutatorConfig) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &MutatorConfig {
+        &self.config
+    }
+
+    /// Sets the maximum number of bytes that a single top-level [NewFuzzed::new_fuzzed] call may
+    /// allocate for dynamically-sized containers (`Vec`s and strings). Once the cap is hit, containers
+    /// are truncated and the best-effort smaller structure is returned. `None` disables the cap.
+    pub fn set_max_allocation(&mut self, max_allocation: Option<usize>) {
+        self.max_allocation = max_allocation;
+    }
+
+    pub fn max_allocation(&self) -> Option<usize> {
+        self.max_allocation
+    }
+
+    /// Returns the number of bytes that may still be allocated in the current top-level
+    /// [NewFuzzed::new_fuzzed] call, or `None` if there is no cap.
+    pub fn remaining_allocation(&self) -> Option<usize> {
+        self.max_allocation
+            .map(|max| max.saturating_sub(self.allocated_bytes))
+    }
+
+    /// Returns the number of bytes allocated so far in the current top-level [NewFuzzed::new_fuzzed] call.
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated_bytes
+    }
+
+    /// Marks the start of a [NewFuzzed::new_fuzzed] call which may allocate. Entering the outermost
+    /// scope resets the allocation budget.
+    pub fn begin_allocation_scope(&mut self) {
+        if self.allocation_depth == 0 {
+            self.allocated_bytes = 0;
+        }
+
+        self.allocation_depth += 1;
+    }
+
+    /// Marks the end of a scope started with [Mutator::begin_allocation_scope].
+    pub fn end_allocation_scope(&mut self) {
+        self.allocation_depth = self.allocation_depth.saturating_sub(1);
+    }
+
+    /// Marks the start of a derived `mutate` call and returns the number of passes it should make
+    /// over its fields. Only the outermost call in [MutatorMode::Havoc] makes more than one pass
+    /// (see [MutatorConfig::stacked_mutation_chance]).
+    #[doc(hidden)]
+    pub fn begin_mutation_stack(&mut self) -> usize {
+        self.mutation_depth += 1;
+
+        if self.mutation_depth > 1 || self.mode() != MutatorMode::Havoc {
+            return 1;
+        }
+
+        // stacking is disabled by default, in which case no randomness is consumed
+        let mut passes = 1;
+        while passes < self.config.max_stacked_mutations
+            && self.config.stacked_mutation_chance > 0.0
+            && self.gen_chance_ignore_flags(self.config.stacked_mutation_chance)
+        {
+            passes += 1;
+        }
+
+        if passes > 1 {
+            self.mutation_stack = Some(MutationStack {
+                fields_fuzzed: self.corpus_state.fields_fuzzed,
+                targeted_field_idx: self.corpus_state.targeted_field_idx,
+                first_pass_fields: None,
+            });
+        }
+
+        passes
+    }
+
+    /// Marks the start of pass `pass` of a stack started with [Mutator::begin_mutation_stack].
+    /// Every pass after the first targets a random field of those counted by the first pass.
+    #[doc(hidden)]
+    pub fn begin_stacked_pass(&mut self, pass: usize) {
+        if pass == 0 {
+            return;
+        }
+
+        let mut stack = match self.mutation_stack {
+            Some(stack) => stack,
+            None => return,
+        };
+
+        let first_pass_fields = *stack
+            .first_pass_fields
+            .get_or_insert(self.corpus_state.fields_fuzzed - stack.fields_fuzzed);
+        self.mutation_stack = Some(stack);
+
+        self.corpus_state.fields_fuzzed = stack.fields_fuzzed;
+        if first_pass_fields > 0 {
+            self.corpus_state.targeted_field_idx =
+                stack.fields_fuzzed + self.gen_range(0, first_pass_fields);
+        }
+    }
+
+    /// Picks which of the `N` field groups of a derived struct are mutated, where each field
+    /// outside of a `#[lain(group = "...")]` is a group of its own. Havoc mutations mutate a random
+    /// nonempty subset of the groups, and the deterministic modes mutate every group so that they
+    /// still visit every field.
+    #[doc(hidden)]
+    pub fn select_field_groups<const N: usize>(&mut self) -> [bool; N] {
+        if self.mode() != MutatorMode::Havoc || N <= 1 {
+            return [true; N];
+        }
+
+        let mut selected = [false; N];
+        let amount = self.gen_range(1, N + 1);
+        for index in sample(&mut self.rng, N, amount).iter() {
+            selected[index] = true;
+        }
+
+        selected
+    }
+
+    /// Marks the end of a stack started with [Mutator::begin_mutation_stack]. The field counters
+    /// are left as they were after the first pass so that stacking doesn't change the number of
+    /// fields the corpus state sees.
+    #[doc(hidden)]
+    pub fn end_mutation_stack(&mut self) {
+        self.mutation_depth = self.mutation_depth.saturating_sub(1);
+
+        if self.mutation_depth > 0 {
+            return;
+        }
+
+        if let Some(stack) = self.mutation_stack.take() {
+            if let Some(first_pass_fields) = stack.first_pass_fields {
+                self.corpus_state.fields_fuzzed = stack.fields_fuzzed + first_pass_fields;
+            }
+
+            self.corpus_state.targeted_field_idx = stack.targeted_field_idx;
+        }
+    }
+
+    /// Reserves room for up to `count` items of `item_size` bytes each from the allocation budget
+    /// and returns how many items can actually be allocated.
+    pub fn reserve_allocation(&mut self, count: usize, item_size: usize) -> usize {
+        let count = match self.remaining_allocation() {
+            Some(remaining) if item_size > 0 => std::cmp::min(count, remaining / item_size),
+            _ => count,
+        };
+
+        self.allocated_bytes = self.allocated_bytes.saturating_add(count * item_size);
+
+        count
+    }
+
     /// Generates a random choice of the given type
     pub fn gen<T: 'static>(&mut self) -> T
     where
@@ -243,7 +1259,7 @@ impl<R: Rng> Mutator<R> {
         //println!("new: {:?}", self.mode());
     }
 
-    /// Mutates a number after randomly selecting a mutation strategy (see [MutatorOperation] for a list of strategies)
+    /// Mutates a number after randomly selecting one of the [NumericStrategy]s enabled in the mutator's [MutatorConfig]
     /// If a min/max is specified then a new number in this range is chosen instead of performing
     /// a bit/arithmetic mutation
     pub fn mutate<T>(&mut self, num: &mut T)
@@ -275,13 +1291,123 @@ impl<R: Rng> Mutator<R> {
             }
         }
 
-        let operation = MutatorOperation::new_fuzzed(self, None);
+        if !self.take_field() {
+            return;
+        }
+
+        let strategy = match self.pick_numeric_strategy() {
+            Some(strategy) => strategy,
+            None => return,
+        };
+
+        let previous = *num;
+
+        hot_trace!("Strategy selected: {:?}", strategy);
+        match strategy {
+            NumericStrategy::BitFlip => self.bit_flip(num),
+            NumericStrategy::Flip => self.flip(num),
+            NumericStrategy::Arithmetic => self.arithmetic(num),
+            NumericStrategy::ScaleByTwo => self.scale_by_two(num),
+            NumericStrategy::PowerOfTwoBoundary => self.power_of_two_boundary(num),
+            NumericStrategy::BoundaryWrap => self.boundary_wrap(num),
+            NumericStrategy::InterestingValue => self.interesting_value(num),
+            NumericStrategy::CompareOperand => self.compare_operand(num),
+        }
+
+        if !self.charge_bytes_changed(changed_int_bytes(previous, *num)) {
+            *num = previous;
+        }
+    }
+
+    /// Mutates an [Offset] into a buffer which ends at `end`, if it's known, by nudging it a few
+    /// bytes either way, aligning it to a structure boundary, or pointing it just past the end
+    pub(crate) fn mutate_offset<T>(&mut self, num: &mut T, end: Option<usize>)
+    where
+        T: PrimInt + WrappingAdd<Output = T> + WrappingSub<Output = T>,
+    {
+        if !self.take_field() {
+            return;
+        }
+
+        let previous = *num;
+        let end = end.or(self.input_len);
+        let strategies = if end.is_some() { 3 } else { 2 };
+
+        match self.rng.gen_range(0, strategies) {
+            0 => {
+                let delta: T = num::cast(self.rng.gen_range(1, MAX_OFFSET_NUDGE + 1)).unwrap();
+                hot_trace!("nudging offset");
+                *num = if self.rng.gen() {
+                    num.wrapping_add(&delta)
+                } else {
+                    num.wrapping_sub(&delta)
+                };
+            }
+            1 => {
+                // 2, 4, 8, or 16 byte boundaries, rounding either down or up
+                let alignment: T = num::cast(1usize << self.rng.gen_range(1, 5)).unwrap();
+                let mask = alignment - T::one();
+                hot_trace!("aligning offset");
+                *num = if self.rng.gen() || *num & mask == T::zero() {
+                    *num & !mask
+                } else {
+                    (*num & !mask).wrapping_add(&alignment)
+                };
+            }
+            _ => {
+                let past_end = end.unwrap().saturating_add(self.rng.gen_range(0, 4));
+                hot_trace!("pointing offset past the end ({})", past_end);
+                *num = num::cast(past_end).unwrap_or_else(T::max_value);
+            }
+        }
+
+        if !self.charge_bytes_changed(changed_int_bytes(previous, *num)) {
+            *num = previous;
+        }
+    }
+
+    /// Length of the serialized input being mutated, which [Offset] fields without a `max` treat
+    /// as the end of the buffer they point into. Harnesses set this to e.g. the
+    /// [serialized_size](SerializedSize::serialized_size) of the input before mutating it.
+    pub fn set_input_len(&mut self, input_len: Option<usize>) {
+        self.input_len = input_len;
+    }
+
+    pub fn input_len(&self) -> Option<usize> {
+        self.input_len
+    }
 
-        trace!("Operation selected: {:?}", operation);
-        match operation {
-            MutatorOperation::BitFlip => self.bit_flip(num),
-            MutatorOperation::Flip => self.flip(num),
-            MutatorOperation::Arithmetic => self.arithmetic(num),
+    /// Picks a numeric strategy using the current field category's [StrategyMix], if it has one
+    fn pick_numeric_strategy(&mut self) -> Option<NumericStrategy> {
+        let category = self.category.unwrap_or(FieldCategory::Integer);
+        match self.config.strategy_mixes.get(&category) {
+            Some(mix) if !mix.numeric.is_empty() => mix
+                .numeric
+                .choose_weighted(&mut self.rng, |item| item.1)
+                .ok()
+                .map(|item| item.0),
+            _ => self
+                .config
+                .numeric_strategies
+                .choose(&mut self.rng)
+                .cloned(),
+        }
+    }
+
+    /// Picks a string strategy using the current field category's [StrategyMix], if it has one
+    pub fn pick_string_strategy(&mut self) -> Option<StringStrategy> {
+        if !self.take_field() {
+            return None;
+        }
+
+        let category = self.category.unwrap_or(FieldCategory::String);
+        match self.config.strategy_mixes.get(&category) {
+            Some(mix) if !mix.string.is_empty() => mix
+                .string
+                .choose_weighted(&mut self.rng, |item| item.1)
+                .ok()
+                .map(|item| item.0),
+            _ => Some(StringStrategy::new_fuzzed(self, None)),
         }
     }
 
@@ -293,7 +1419,7 @@ impl<R: Rng> Mutator<R> {
         let num_bits = (std::mem::size_of::<T>() * 8) as u8;
         let idx: u8 = self.rng.gen_range(0, num_bits);
 
-        trace!("xoring bit {}", idx);
+        hot_trace!("xoring bit {}", idx);
 
         *num = (*num) ^ num::cast(1u64 << idx).unwrap();
     }
@@ -315,7 +1441,7 @@ impl<R: Rng> Mutator<R> {
             potential_bit_indices[i as usize] = i;
         }
 
-        trace!("flipping {} bits", bits_to_flip);
+        hot_trace!("flipping {} bits", bits_to_flip);
         let (bit_indices, _) = potential_bit_indices[0..num_bits as usize]
             .partial_shuffle(&mut self.rng, num_bits as usize);
 
@@ -334,17 +1460,128 @@ impl<R: Rng> Mutator<R> {
             + WrappingAdd<Output = T>
             + WrappingSub<Output = T>,
     {
-        let added_num: i64 = self.rng.gen_range(1, 0x10);
+        let max_delta = std::cmp::max(self.config.max_arithmetic_delta, 1) as i64;
+        let added_num: i64 = self.rng.gen_range(1, max_delta + 1);
 
         if self.rng.gen_range(0, 2) == 0 {
-            trace!("adding {}", added_num);
+            hot_trace!("adding {}", added_num);
             *num = num.wrapping_add(&num::cast(added_num).unwrap());
         } else {
-            trace!("subtracting {}", added_num);
+            hot_trace!("subtracting {}", added_num);
             *num = num.wrapping_sub(&num::cast(added_num).unwrap());
         }
     }
 
+    /// Multiply (wrapping on overflow) or divide the number by 2
+    fn scale_by_two<T>(&mut self, num: &mut T)
+    where
+        T: NumCast + Bounded + Copy,
+    {
+        let value: i128 = num::cast(*num).unwrap();
+
+        if self.rng.gen_range(0, 2) == 0 {
+            hot_trace!("multiplying by 2");
+            *num = wrapping_from_i128(value * 2);
+        } else {
+            hot_trace!("dividing by 2");
+            *num = wrapping_from_i128(value / 2);
+        }
+    }
+
+    /// Move the number to the power of two nearest to its magnitude, or one either side of it
+    fn power_of_two_boundary<T>(&mut self, num: &mut T)
+    where
+        T: NumCast + Bounded + Copy,
+    {
+        let value: i128 = num::cast(*num).unwrap();
+        let magnitude = value.abs();
+
+        let boundary = if magnitude <= 1 {
+            1
+        } else {
+            let lower = 1i128 << (127 - magnitude.leading_zeros());
+            if magnitude - lower <= lower * 2 - magnitude {
+                lower
+            } else {
+                lower * 2
+            }
+        };
+
+        let offset: i128 = self.rng.gen_range(-1, 2);
+        let boundary = if value < 0 { -boundary } else { boundary };
+
+        hot_trace!("stepping to {} + {}", boundary, offset);
+        *num = wrapping_from_i128(boundary + offset);
+    }
+
+    /// Move the number to within a few steps of the type's min or max, wrapping around on overflow
+    fn boundary_wrap<T>(&mut self, num: &mut T)
+    where
+        T: NumCast + Bounded + Copy,
+    {
+        let bound: i128 = if self.rng.gen_range(0, 2) == 0 {
+            num::cast(T::min_value()).unwrap()
+        } else {
+            num::cast(T::max_value()).unwrap()
+        };
+
+        let distance = self.config.max_boundary_distance as i128;
+        let offset: i128 = self.rng.gen_range(-distance, distance + 1);
+
+        hot_trace!("nudging {} by {}", bound, offset);
+        *num = wrapping_from_i128(bound + offset);
+    }
+
+    /// Replace the number with a built-in or user-registered interesting value
+    fn interesting_value<T>(&mut self, num: &mut T)
+    where
+        T: NumCast + Bounded + Copy,
+    {
+        let bits = std::mem::size_of::<T>() * 8;
+        let fits = |value: &&i128| **value >= -(1i128 << (bits - 1)) && **value < 1i128 << bits;
+
+        let registered = self.config.interesting_values.iter().filter(fits).count();
+
+        let value = if registered > 0 && self.rng.gen() {
+            let idx = self.rng.gen_range(0, registered);
+            *self
+                .config
+                .interesting_values
+                .iter()
+                .filter(fits)
+                .nth(idx)
+                .unwrap()
+        } else {
+            select_interesting_value(&mut self.rng, bits)
+        };
+
+        hot_trace!("selected interesting value {}", value);
+        *num = wrapping_from_i128(value);
+    }
+
+    /// Replace the number with an operand of a comparison the target made
+    fn compare_operand<T>(&mut self, num: &mut T)
+    where
+        T: NumCast + Bounded + Copy,
+    {
+        // the table holds the operands as unsigned numbers of the same width
+        let width = std::mem::size_of::<T>();
+        let value = num::cast::<T, i128>(*num).unwrap() as u64 & (u64::MAX >> (64 - width * 8));
+
+        let operand = match self.compare_table {
+            Some(ref table) => table.pick_integer(&mut self.rng, width, value),
+            None => None,
+        };
+
+        match operand {
+            Some(operand) => {
+                hot_trace!("substituting compared operand {:#x}", operand);
+                *num = wrapping_from_i128(operand as i128);
+            }
+            None => self.interesting_value(num),
+        }
+    }
+
     /// Generates a number in the range from [min, max) (**note**: non-inclusive). Panics if min >= max.
     pub fn gen_range<T, B1>(&mut self, min: B1, max: B1) -> T
     where
@@ -361,9 +1598,9 @@ impl<R: Rng> Mutator<R> {
         if min >= max {
             panic!("cannot gen number where min >= max: {} == {}", min, max);
         }
-        trace!("generating number between {} and {}", &min, &max);
+        hot_trace!("generating number between {} and {}", &min, &max);
         let num = self.rng.gen_range(min, max);
-        trace!("got {}", num);
+        hot_trace!("got {}", num);
 
         num
     }
@@ -396,7 +1633,7 @@ impl<R: Rng> Mutator<R> {
         // 2. The "midrange" with a 20% probability
         // 3. The opposite end with what should be a 10% probability
 
-        trace!(
+        hot_trace!(
             "generating weighted number between {} and {} with weight towards {:?}",
             &min,
             &max,
@@ -441,18 +1678,100 @@ impl<R: Rng> Mutator<R> {
         let dist = WeightedIndex::new(slices.iter().map(|item| item.1)).unwrap();
 
         let subslice_index = dist.sample(&mut self.rng);
-        trace!("got {} subslice index", subslice_index);
+        hot_trace!("got {} subslice index", subslice_index);
 
         let bounds = slices[subslice_index].0;
-        trace!("subslice has bounds {:?}", bounds);
+        hot_trace!("subslice has bounds {:?}", bounds);
 
         let num = self.rng.gen_range(bounds.0, bounds.1);
 
-        trace!("got {}", num);
+        hot_trace!("got {}", num);
 
         num
     }
 
+    /// Generates an integer in the range from [min, max] (**note**: inclusive), weighted to one end
+    /// of the interval. Unlike [Mutator::gen_weighted_range], `max` may be `T::max_value()`.
+    pub fn gen_weighted_range_inclusive<T>(&mut self, min: T, max: T, weighted: Weighted) -> T
+    where
+        T: PrimInt + SampleUniform + std::fmt::Display + std::fmt::Debug,
+    {
+        use crate::rand::distributions::{Distribution, Uniform};
+
+        match max.checked_add(&T::one()) {
+            Some(end) => self.gen_weighted_range(min, end, weighted),
+            // shift the range down by one so that its end fits in T
+            None if min > T::min_value() => {
+                self.gen_weighted_range::<T, T>(min - T::one(), max, weighted) + T::one()
+            }
+            // the whole range of T, which can't be weighted
+            None => Uniform::new_inclusive(min, max).sample(&mut self.rng),
+        }
+    }
+
+    /// Generates a floating point number between `min` and `max`, including or excluding each bound
+    /// according to `bounds`. `weighted` skews the result towards one end of the range. Unlike
+    /// [Mutator::gen_range], this works on ranges whose width doesn't fit in `T` (e.g. `T::MIN` to
+    /// `T::MAX`). Panics if the range is empty.
+    pub fn gen_float_range<T>(&mut self, min: T, max: T, bounds: Bounds, weighted: Weighted) -> T
+    where
+        T: Float + std::fmt::Display,
+    {
+        if min > max || (min == max && bounds != Bounds::Closed) || min.is_nan() || max.is_nan() {
+            panic!(
+                "cannot gen number in empty range: {} to {} ({:?})",
+                min, max, bounds
+            );
+        }
+
+        hot_trace!(
+            "generating float between {} and {} ({:?}, weighted {:?})",
+            &min,
+            &max,
+            bounds,
+            weighted
+        );
+
+        use crate::rand::distributions::{Distribution, Uniform};
+
+        let unit = if bounds.includes_max() {
+            Uniform::new_inclusive(0.0f64, 1.0)
+        } else {
+            Uniform::new(0.0f64, 1.0)
+        };
+
+        // pick a point along the interval. squaring the position biases it towards the start
+        let mut position = || -> T {
+            let p = unit.sample(&mut self.rng);
+
+            let p = match weighted {
+                Weighted::None => p,
+                Weighted::Min => p * p,
+                Weighted::Max => 1.0 - (1.0 - p) * (1.0 - p),
+            };
+
+            T::from(p).unwrap()
+        };
+
+        // floats may round back to a bound which should be excluded, so retry a few times before
+        // giving up and using the midpoint
+        for _i in 0..16 {
+            // interpolate without computing `max - min` since this may overflow to infinity
+            let p = position();
+            let num = min * (T::one() - p) + max * p;
+
+            if (num > min || (bounds.includes_min() && num == min))
+                && (num < max || (bounds.includes_max() && num == max))
+            {
+                hot_trace!("got {}", num);
+                return num;
+            }
+        }
+
+        let two = T::one() + T::one();
+        min / two + max / two
+    }
+
     /// Generates the chance to mutate a field. This will always return `true` if the current mode is
     /// [MutatorMode::Havoc].
     pub fn gen_chance_to_mutate_field(&mut self, chance_percentage: f32) -> bool {
@@ -462,6 +1781,12 @@ impl<R: Rng> Mutator<R> {
     /// Helper function for quitting the recursive mutation early if the target field has already
     /// been mutated.
     pub fn should_early_bail_mutation(&self) -> bool {
+        match self.mutate_options.early_bail {
+            Some(false) => return false,
+            Some(true) => return self.corpus_state.finished_iteration || self.fields_mutated > 0,
+            None => {}
+        }
+
         self.mode() != MutatorMode::Havoc
             && self.corpus_state.finished_iteration
             && self.corpus_state.target_total_passes > 0
@@ -491,7 +1816,7 @@ impl<R: Rng> Mutator<R> {
         let num = self.gen_range(0.0, 100.0);
         let result = num <= chance_percentage;
 
-        trace!(
+        hot_trace!(
             "generating {}% chance. got {}, so returning {}",
             chance_percentage,
             num,
@@ -501,9 +1826,375 @@ impl<R: Rng> Mutator<R> {
         result
     }
 
+    /// Returns a boolean indicating whether or not a min/max constraint should be ignored
+    /// according to the current [GenerationProfile]
+    pub fn should_ignore_constraint(&mut self) -> bool {
+        let ignore = match self.profile {
+            GenerationProfile::Valid => false,
+            GenerationProfile::SemiValid => {
+                if self.violated_constraint || !self.gen_chance(CHANCE_TO_IGNORE_MIN_MAX) {
+                    return false;
+                }
+
+                self.violated_constraint = true;
+                true
+            }
+            GenerationProfile::Balanced => self.gen_chance(CHANCE_TO_IGNORE_MIN_MAX),
+            GenerationProfile::Hostile => self.gen_chance(HOSTILE_CHANCE_TO_IGNORE_MIN_MAX),
+        };
+
+        self.danger_score.constraint_violations += ignore as usize;
+
+        ignore
+    }
+
+    /// Returns a boolean indicating whether or not an [UnsafeEnum] should be generated with an
+    /// invalid value according to the current [GenerationProfile]
+    pub fn should_pick_invalid_enum(&mut self) -> bool {
+        let invalid = match self.profile {
+            GenerationProfile::Valid => false,
+            GenerationProfile::SemiValid => {
+                if self.violated_constraint || !self.gen_chance(CHANCE_TO_PICK_INVALID_ENUM) {
+                    return false;
+                }
+
+                self.violated_constraint = true;
+                true
+            }
+            GenerationProfile::Balanced => self.gen_chance(CHANCE_TO_PICK_INVALID_ENUM),
+            GenerationProfile::Hostile => self.gen_chance(HOSTILE_CHANCE_TO_PICK_INVALID_ENUM),
+        };
+
+        self.danger_score.invalid_enums += invalid as usize;
+
+        invalid
+    }
+
+    /// Returns a boolean indicating whether or not a [TextEncoded] value's text should be made
+    /// invalid according to the current [GenerationProfile]
+    pub fn should_flaw_encoding(&mut self) -> bool {
+        let flaw = match self.profile {
+            GenerationProfile::Valid => false,
+            GenerationProfile::SemiValid => {
+                if self.violated_constraint || !self.gen_chance(CHANCE_TO_FLAW_ENCODING) {
+                    return false;
+                }
+
+                self.violated_constraint = true;
+                true
+            }
+            GenerationProfile::Balanced => self.gen_chance(CHANCE_TO_FLAW_ENCODING),
+            GenerationProfile::Hostile => self.gen_chance(HOSTILE_CHANCE_TO_FLAW_ENCODING),
+        };
+
+        self.danger_score.flawed_encodings += flaw as usize;
+
+        flaw
+    }
+
+    /// Picks a way of making text in the encoding `C` invalid
+    pub fn gen_encoding_flaw<C: TextEncoding>(&mut self) -> EncodingFlaw {
+        match self.gen_range(0u8, 3u8) {
+            0 => {
+                let offset = self.rng.gen::<u32>() as usize;
+                let c = loop {
+                    let c = self.rng.gen::<u8>();
+                    if !C::is_alphabet(c) {
+                        break c;
+                    }
+                };
+
+                EncodingFlaw::InvalidChar { offset, c }
+            }
+            1 => EncodingFlaw::BadPadding,
+            _ => EncodingFlaw::Truncated,
+        }
+    }
+
+    /// Returns a boolean indicating whether or not a field marked `#[lain(opaque)]` should be
+    /// regenerated while mutating its parent. Opaque fields are otherwise left as they are.
+    pub fn should_replace_opaque(&mut self) -> bool {
+        self.mode() == MutatorMode::Havoc
+            && !self.has_byte_budget()
+            && self.gen_chance(CHANCE_TO_REPLACE_OPAQUE)
+    }
+
+    /// Returns a boolean indicating whether or not a `#[lain(count_field = "...")]` count should be
+    /// left out of sync with its array. See [MutatorConfig::count_desync_chance].
+    pub fn should_desync_count(&mut self) -> bool {
+        self.profile != GenerationProfile::Valid && self.gen_chance(self.config.count_desync_chance)
+    }
+
+    /// Clears the swap of the struct holding `swap`, and occasionally picks a new one. See
+    /// [MutatorConfig::field_swap_chance].
+    #[doc(hidden)]
+    pub fn maybe_swap_wire_fields(&mut self, swap: &mut FieldSwap) {
+        swap.clear();
+
+        if self.mode() != MutatorMode::Havoc
+            || self.profile == GenerationProfile::Valid
+            || self.has_byte_budget()
+            || !self.gen_chance(self.config.field_swap_chance)
+        {
+            return;
+        }
+
+        swap.set(self.rng.gen());
+    }
+
+    /// Overrides [MutatorConfig::duplicate_chance] until [Mutator::end_duplicate_chance_scope] is
+    /// called. The previous override is returned and must be passed to
+    /// [Mutator::end_duplicate_chance_scope].
+    pub fn begin_duplicate_chance_scope(&mut self, chance: f32) -> Option<f32> {
+        self.duplicate_chance.replace(chance)
+    }
+
+    /// Restores the override replaced by [Mutator::begin_duplicate_chance_scope]
+    pub fn end_duplicate_chance_scope(&mut self, previous: Option<f32>) {
+        self.duplicate_chance = previous;
+    }
+
+    /// Sets the category of the field being mutated, which selects the [StrategyMix] used for it.
+    /// Returns the previous category, which must be passed to [Mutator::end_category_scope] once
+    /// the field has been mutated.
+    pub fn begin_category_scope(&mut self, category: FieldCategory) -> Option<FieldCategory> {
+        self.category.replace(category)
+    }
+
+    /// Like [Mutator::begin_category_scope], but keeps the current category if there is one. This
+    /// is used by types which aren't necessarily mutated as a field of their own category, e.g.
+    /// `Vec`s.
+    pub fn begin_default_category_scope(
+        &mut self,
+        category: FieldCategory,
+    ) -> Option<FieldCategory> {
+        let previous = self.category;
+        self.category = previous.or(Some(category));
+
+        previous
+    }
+
+    /// Restores the category replaced by [Mutator::begin_category_scope]
+    pub fn end_category_scope(&mut self, previous: Option<FieldCategory>) {
+        self.category = previous;
+    }
+
+    pub fn category(&self) -> Option<FieldCategory> {
+        self.category
+    }
+
+    /// Returns a boolean indicating whether or not a `Vec` being mutated should have one of its
+    /// elements duplicated. See [MutatorConfig::duplicate_chance].
+    pub fn should_duplicate_element(&mut self) -> bool {
+        let chance = self
+            .duplicate_chance
+            .unwrap_or(self.config.duplicate_chance);
+
+        self.mode() == MutatorMode::Havoc && !self.has_byte_budget() && self.gen_chance(chance)
+    }
+
+    /// Inserts a copy of a random element of `array` right after the original. Returns `false`
+    /// without touching the array if it's empty or already too large to grow.
+    pub fn duplicate_element<T: Clone>(&mut self, array: &mut Vec<T>) -> bool {
+        if array.is_empty() || array.len() >= MAX_RESIZED_COUNT {
+            return false;
+        }
+
+        let index = self.gen_range(0, array.len());
+        let copy = array[index].clone();
+        array.insert(index + 1, copy);
+
+        true
+    }
+
+    /// Returns a boolean indicating whether or not a byte buffer being mutated should have an
+    /// operand of a comparison the target made substituted into it. This is never the case
+    /// without a [compare table](Mutator::set_compare_table).
+    pub fn should_substitute_operand(&mut self) -> bool {
+        self.compare_table.is_some()
+            && self.mode() == MutatorMode::Havoc
+            && self.gen_chance(CHANCE_TO_SUBSTITUTE_OPERAND)
+    }
+
+    /// Replaces an occurrence of one operand of a captured byte comparison in `bytes` with the
+    /// other operand, or inserts either operand at a random offset if neither occurs. Returns
+    /// `false` without touching the buffer if no byte comparisons were captured.
+    pub fn substitute_operand(&mut self, bytes: &mut Vec<u8>) -> bool {
+        let operands = match self.compare_table {
+            Some(ref table) => table.pick_bytes(&mut self.rng),
+            None => None,
+        };
+
+        let (lhs, rhs) = match operands {
+            Some(operands) => operands,
+            None => return false,
+        };
+
+        let find = |needle: &[u8]| bytes.windows(needle.len()).position(|w| w == needle);
+        let (start, end, replacement) = if let Some(start) = find(&lhs) {
+            (start, start + lhs.len(), rhs)
+        } else if let Some(start) = find(&rhs) {
+            (start, start + rhs.len(), lhs)
+        } else {
+            let offset = self.gen_range(0, bytes.len() + 1);
+            (offset, offset, if self.rng.gen() { lhs } else { rhs })
+        };
+
+        if !self.charge_bytes_changed(std::cmp::max(end - start, replacement.len())) {
+            return false;
+        }
+
+        hot_trace!("substituting {:?} at {}", replacement, start);
+        bytes.splice(start..end, replacement);
+
+        true
+    }
+
+    /// Truncates `array` to `count` elements or grows it with newly generated ones. Returns `false`
+    /// without touching the array if `count` is too large to be reasonably allocated.
+    pub fn resize_to_count<T: NewFuzzed>(&mut self, array: &mut Vec<T>, count: usize) -> bool {
+        if count > MAX_RESIZED_COUNT {
+            return false;
+        }
+
+        if count < array.len() {
+            array.truncate(count);
+        } else {
+            while array.len() < count {
+                array.push(T::new_fuzzed(self, None));
+            }
+        }
+
+        true
+    }
+
+    /// Returns a boolean indicating whether or not a number should be picked from the list
+    /// of dangerous numbers instead of being randomly generated. This only occurs with the
+    /// [GenerationProfile::Hostile] profile.
+    pub fn should_pick_extreme_value(&mut self) -> bool {
+        let extreme = self.profile == GenerationProfile::Hostile
+            && self.gen_chance(HOSTILE_CHANCE_TO_PICK_EXTREME_VALUE);
+
+        self.danger_score.extreme_values += extreme as usize;
+
+        extreme
+    }
+
+    /// Sets the index of the combination of `#[fuzzer(exhaustive)]` fields which should be
+    /// generated this iteration. This is reset by [Mutator::begin_new_iteration] and is normally
+    /// set by the driver when an exhaustive sweep is configured.
+    pub fn set_exhaustive_index(&mut self, index: Option<usize>) {
+        self.exhaustive_index = index;
+    }
+
+    /// Returns the index of the next exhaustively swept field's value out of `count` possible
+    /// values, or `None` if no sweep is active. Each call consumes one "digit" of the combination
+    /// index using `count` as the radix, so every combination of the swept fields is generated
+    /// once as the index increases.
+    pub fn next_exhaustive_digit(&mut self, count: usize) -> Option<usize> {
+        let remaining = self.exhaustive_index?;
+
+        self.exhaustive_index = Some(remaining / count);
+
+        Some(remaining % count)
+    }
+
+    /// Returns the next value of an exhaustively swept field, or `None` if no sweep is active
+    pub fn next_exhaustive_value<T: Enumerable>(&mut self) -> Option<T> {
+        self.next_exhaustive_digit(T::value_count())
+            .map(T::value_at)
+    }
+
+    /// Mutates the bytes of `bytes` within `range`, leaving the rest of the buffer untouched.
+    /// The range is clamped to the length of the buffer.
+    pub fn mutate_bytes_in_range(&mut self, bytes: &mut [u8], range: Range<usize>) {
+        let end = std::cmp::min(range.end, bytes.len());
+        let start = std::cmp::min(range.start, end);
+
+        bytes[start..end].mutate(self, None);
+    }
+
+    /// Mutates every byte of `bytes` outside of the `pinned` regions. This is used for fields
+    /// marked with `#[lain(pin(...))]` to protect embedded headers and the like from byte-level
+    /// mutation. Regions may overlap or extend past the end of the buffer.
+    pub fn mutate_bytes_excluding(&mut self, bytes: &mut [u8], pinned: &[Range<usize>]) {
+        let mut pinned = pinned.to_vec();
+        pinned.sort_by_key(|region| region.start);
+
+        let mut start = 0;
+        for region in pinned {
+            if region.start > start {
+                self.mutate_bytes_in_range(bytes, start..region.start);
+            }
+
+            start = std::cmp::max(start, region.end);
+        }
+
+        self.mutate_bytes_in_range(bytes, start..bytes.len());
+    }
+
+    /// Sets the campaign's root seed, which `#[lain(seed_domain = "...")]` types derive their
+    /// randomness from. This is normally set by the driver.
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    /// The campaign's root seed, if one has been set
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Replaces the RNG with one seeded from the root seed and the named `domain` so that
+    /// everything generated until [Mutator::end_seed_domain] is the same for every iteration of
+    /// the campaign. The replaced RNG is returned and must be passed to
+    /// [Mutator::end_seed_domain]. This does nothing if no root seed has been set or the RNG
+    /// cannot be seeded.
+    pub fn begin_seed_domain(&mut self, domain: &str) -> Option<R> {
+        let seed = self.seed? ^ seed_domain_hash(domain);
+        let domain_rng = R::from_domain_seed(seed)?;
+
+        Some(std::mem::replace(&mut self.rng, domain_rng))
+    }
+
+    /// Restores the RNG replaced by [Mutator::begin_seed_domain]
+    pub fn end_seed_domain(&mut self, previous_rng: Option<R>) {
+        if let Some(rng) = previous_rng {
+            self.rng = rng;
+        }
+    }
+
     /// Returns a boolean indicating whether or not post mutation steps should be taken
     pub fn should_fixup(&mut self) -> bool {
-        self.mode() == MutatorMode::Havoc && !self.gen_chance(CHANCE_TO_IGNORE_POST_MUTATION)
+        match self.mutate_options.fixup {
+            Some(true) => return true,
+            Some(false) => {
+                self.danger_score.skipped_fixups += 1;
+                return false;
+            }
+            None => {}
+        }
+
+        if self.mode() != MutatorMode::Havoc {
+            return false;
+        }
+
+        if !self.fixup_enabled {
+            self.danger_score.skipped_fixups += 1;
+            return false;
+        }
+
+        let chance_to_ignore = match self.profile {
+            GenerationProfile::Valid => 0.0,
+            GenerationProfile::Hostile => HOSTILE_CHANCE_TO_IGNORE_POST_MUTATION,
+            _ => CHANCE_TO_IGNORE_POST_MUTATION,
+        };
+
+        if self.gen_chance(chance_to_ignore) {
+            self.danger_score.skipped_fixups += 1;
+            return false;
+        }
+
+        true
         // for flag in self.flags.iter() {
         //     if let MutatorFlags::ShouldAlwaysPerformPostMutation(should_perform) = flag {
         //         return *should_perform;
@@ -518,6 +2209,15 @@ impl<R: Rng> Mutator<R> {
     pub fn begin_new_iteration(&mut self) {
         let mut set_flags = [false, false, false];
         self.flags.clear();
+        self.allocation_depth = 0;
+        self.mutation_depth = 0;
+        self.mutation_stack = None;
+        self.violated_constraint = false;
+        self.danger_score = DangerScore::default();
+        self.fields_mutated = 0;
+        self.bytes_changed = 0;
+        self.exhaustive_index = None;
+        self.choices.clear();
         let temp_fields_fuzzed = self.corpus_state.fields_fuzzed;
         self.corpus_state.fields_fuzzed = 0;
 
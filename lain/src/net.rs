@@ -0,0 +1,1105 @@
+//! Senders for delivering fuzzed data to targets over TCP or UDP.
+//!
+//! Senders keep their connection alive across iterations according to their [ReconnectPolicy]
+//! and capture whatever the target sends back so that it can be inspected by the fuzzer loop.
+//! They're meant to be stored in a thread's `user_data` and used with
+//! [FuzzerContext::send](crate::driver::FuzzerContext::send).
+//!
+//! On Unix, local daemons can be reached through their Unix domain sockets with a [UnixSender],
+//! and network stacks can be sent hand-crafted IP packets or link-layer frames with a
+//! [RawSender]. Raw sockets can only be opened with elevated privileges, so a [NetConfig] may
+//! name the [Privileges] to switch to once the socket is open. The fuzz loop then runs as an
+//! ordinary user.
+//!
+//! ```no_run
+//! use lain::net::{NetConfig, Privileges, RawSender, RawTarget, Sender};
+//!
+//! let config = NetConfig {
+//!     drop_privileges: Privileges::sudo_user(),
+//!     ..Default::default()
+//! };
+//!
+//! let target = RawTarget::Ip {
+//!     addr: "127.0.0.1".parse().unwrap(),
+//!     protocol: lain::libc::IPPROTO_ICMP,
+//!     header_included: false,
+//! };
+//!
+//! // opened as root, and running as the user who invoked sudo from here on
+//! let mut sender = RawSender::new(target, config).unwrap();
+//!
+//! let mut response = vec![];
+//! sender.send(&[8, 0, 0xf7, 0xff, 0, 0, 0, 0], &mut response).unwrap();
+//! ```
+//!
+//! Wrapping a sender in a [SessionRecorder] records every message of an iteration along with the
+//! target's responses. When a multi-message session crashes the target, the recorded [Session] can
+//! be saved and later resent exactly with [replay] during triage.
+
+use crate::byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::fs::File;
+#[cfg(unix)]
+use std::net::IpAddr;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, FromRawFd};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::time::Instant;
+
+/// When a sender should re-establish its connection to the target
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ReconnectPolicy {
+    /// Open a new connection for every iteration
+    EveryIteration,
+    /// Keep the connection open, reconnecting when it fails or the target closes it. A send
+    /// which fails on a stale connection is retried once on a new connection.
+    #[default]
+    OnError,
+}
+
+/// Connection and response settings shared by all senders
+#[derive(Debug, Clone)]
+pub struct NetConfig {
+    /// How long to wait for a connection to be established. `None` waits indefinitely.
+    pub connect_timeout: Option<Duration>,
+    /// Number of times to try connecting before giving up. Targets which are restarting
+    /// after a crash may take a moment to accept connections again.
+    pub connect_attempts: usize,
+    /// Delay between connection attempts
+    pub retry_delay: Duration,
+    /// How long to wait for writes to complete. `None` waits indefinitely.
+    pub write_timeout: Option<Duration>,
+    /// How long to wait for the first byte of the target's response. `None` disables reading
+    /// responses.
+    pub read_timeout: Option<Duration>,
+    /// Once a response starts arriving, how long to wait for more data before considering the
+    /// response complete
+    pub response_idle_timeout: Duration,
+    /// Maximum number of response bytes captured per send
+    pub max_response_size: usize,
+    pub reconnect: ReconnectPolicy,
+    /// User and group the process switches to once a [UnixSender] or [RawSender] has opened its
+    /// socket, which it then does when it's created rather than on the first send. This applies
+    /// to every thread of the process and can't be undone, so a [RawSender] keeps its socket open
+    /// for its lifetime, and a [UnixSender] can only reconnect if the user may still connect to
+    /// the socket.
+    #[cfg(unix)]
+    pub drop_privileges: Option<Privileges>,
+}
+
+impl Default for NetConfig {
+    fn default() -> Self {
+        NetConfig {
+            connect_timeout: Some(Duration::from_secs(1)),
+            connect_attempts: 3,
+            retry_delay: Duration::from_millis(100),
+            write_timeout: Some(Duration::from_secs(1)),
+            read_timeout: Some(Duration::from_millis(500)),
+            response_idle_timeout: Duration::from_millis(10),
+            max_response_size: 0x10000,
+            reconnect: ReconnectPolicy::default(),
+            #[cfg(unix)]
+            drop_privileges: None,
+        }
+    }
+}
+
+/// What happened while waiting for the target's response
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResponseStatus {
+    /// Response bytes were captured
+    Received,
+    /// The target didn't respond within the read timeout
+    TimedOut,
+    /// The target closed the connection (or, for UDP, the port was unreachable) without
+    /// responding
+    Closed,
+    /// Responses aren't read since [NetConfig::read_timeout] is `None`
+    NotRead,
+}
+
+/// A transport which delivers data to a target
+pub trait Sender {
+    /// Sends `data` to the target and appends its response to `response`
+    fn send(&mut self, data: &[u8], response: &mut Vec<u8>) -> io::Result<ResponseStatus>;
+}
+
+/// Sends data over a TCP connection
+pub struct TcpSender {
+    addr: SocketAddr,
+    config: NetConfig,
+    stream: Option<TcpStream>,
+}
+
+impl TcpSender {
+    /// Creates a sender for `addr`. The connection is established on the first send.
+    pub fn new<A: ToSocketAddrs>(addr: A, config: NetConfig) -> io::Result<TcpSender> {
+        Ok(TcpSender {
+            addr: resolve(addr)?,
+            config,
+            stream: None,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Closes the current connection. The next send will reconnect.
+    pub fn disconnect(&mut self) {
+        self.stream = None;
+    }
+
+    fn connect(&mut self) -> io::Result<()> {
+        let addr = self.addr;
+        let connect_timeout = self.config.connect_timeout;
+
+        let stream = retry(&self.config, || match connect_timeout {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        })?;
+
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(self.config.write_timeout)?;
+
+        self.stream = Some(stream);
+
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.stream.is_none() {
+            self.connect()?;
+        }
+
+        let result = self.stream.as_mut().unwrap().write_all(data);
+        if result.is_err() {
+            self.disconnect();
+        }
+
+        result
+    }
+}
+
+impl Sender for TcpSender {
+    fn send(&mut self, data: &[u8], response: &mut Vec<u8>) -> io::Result<ResponseStatus> {
+        let was_connected = match self.config.reconnect {
+            ReconnectPolicy::EveryIteration => {
+                self.disconnect();
+                false
+            }
+            ReconnectPolicy::OnError => self.is_connected(),
+        };
+
+        if let Err(e) = self.write(data) {
+            // the target may have closed an idle connection (e.g. after restarting), so give a
+            // fresh connection one chance before reporting the error
+            if !was_connected {
+                return Err(e);
+            }
+
+            log::debug!("write to {} failed ({}), reconnecting", self.addr, e);
+            self.write(data)?;
+        }
+
+        let read_timeout = match self.config.read_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(ResponseStatus::NotRead),
+        };
+
+        let max_response_size = self.config.max_response_size;
+        let idle_timeout = self.config.response_idle_timeout;
+        let stream = self.stream.as_mut().unwrap();
+
+        let status = read_response(
+            stream,
+            read_timeout,
+            idle_timeout,
+            max_response_size,
+            response,
+        );
+        match status {
+            Ok(ResponseStatus::Received) | Ok(ResponseStatus::TimedOut) => {}
+            _ => self.disconnect(),
+        }
+
+        status
+    }
+}
+
+/// Sends data as UDP datagrams
+pub struct UdpSender {
+    addr: SocketAddr,
+    config: NetConfig,
+    socket: Option<UdpSocket>,
+}
+
+impl UdpSender {
+    /// Creates a sender for `addr`. The socket is created on the first send.
+    pub fn new<A: ToSocketAddrs>(addr: A, config: NetConfig) -> io::Result<UdpSender> {
+        Ok(UdpSender {
+            addr: resolve(addr)?,
+            config,
+            socket: None,
+        })
+    }
+
+    /// Closes the current socket. The next send will create a new one.
+    pub fn disconnect(&mut self) {
+        self.socket = None;
+    }
+
+    fn socket(&mut self) -> io::Result<&UdpSocket> {
+        if self.socket.is_none() {
+            let local_addr = if self.addr.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+
+            let socket = UdpSocket::bind(local_addr)?;
+            socket.connect(self.addr)?;
+            socket.set_write_timeout(self.config.write_timeout)?;
+
+            self.socket = Some(socket);
+        }
+
+        Ok(self.socket.as_ref().unwrap())
+    }
+}
+
+impl Sender for UdpSender {
+    /// Sends `data` as a single datagram and captures the first datagram sent in response
+    fn send(&mut self, data: &[u8], response: &mut Vec<u8>) -> io::Result<ResponseStatus> {
+        if self.config.reconnect == ReconnectPolicy::EveryIteration {
+            self.disconnect();
+        }
+
+        let read_timeout = self.config.read_timeout;
+        let max_response_size = self.config.max_response_size;
+
+        let result = (|| {
+            let socket = self.socket()?;
+            socket.send(data)?;
+
+            let read_timeout = match read_timeout {
+                Some(timeout) => timeout,
+                None => return Ok(ResponseStatus::NotRead),
+            };
+
+            socket.set_read_timeout(Some(read_timeout))?;
+
+            let start = response.len();
+            response.resize(start + max_response_size, 0);
+
+            match socket.recv(&mut response[start..]) {
+                Ok(size) => {
+                    response.truncate(start + size);
+                    Ok(ResponseStatus::Received)
+                }
+                Err(e) => {
+                    response.truncate(start);
+
+                    match e.kind() {
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                            Ok(ResponseStatus::TimedOut)
+                        }
+                        io::ErrorKind::ConnectionRefused => Ok(ResponseStatus::Closed),
+                        _ => Err(e),
+                    }
+                }
+            }
+        })();
+
+        if result.is_err() {
+            self.disconnect();
+        }
+
+        result
+    }
+}
+
+/// Sends data over a Unix domain stream socket, e.g. the control socket of a local daemon
+#[cfg(unix)]
+pub struct UnixSender {
+    path: PathBuf,
+    config: NetConfig,
+    stream: Option<UnixStream>,
+}
+
+#[cfg(unix)]
+impl UnixSender {
+    /// Creates a sender for the socket at `path`. The connection is established on the first
+    /// send, unless [NetConfig::drop_privileges] is set.
+    pub fn new<P: Into<PathBuf>>(path: P, config: NetConfig) -> io::Result<UnixSender> {
+        let mut sender = UnixSender {
+            path: path.into(),
+            config,
+            stream: None,
+        };
+
+        if let Some(privileges) = sender.config.drop_privileges {
+            sender.connect()?;
+            drop_privileges(privileges)?;
+        }
+
+        Ok(sender)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Closes the current connection. The next send will reconnect.
+    pub fn disconnect(&mut self) {
+        self.stream = None;
+    }
+
+    fn connect(&mut self) -> io::Result<()> {
+        let path = &self.path;
+        let stream = retry(&self.config, || UnixStream::connect(path))?;
+        stream.set_write_timeout(self.config.write_timeout)?;
+
+        self.stream = Some(stream);
+
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if self.stream.is_none() {
+            self.connect()?;
+        }
+
+        let result = self.stream.as_mut().unwrap().write_all(data);
+        if result.is_err() {
+            self.disconnect();
+        }
+
+        result
+    }
+}
+
+#[cfg(unix)]
+impl Sender for UnixSender {
+    fn send(&mut self, data: &[u8], response: &mut Vec<u8>) -> io::Result<ResponseStatus> {
+        let was_connected = match self.config.reconnect {
+            ReconnectPolicy::EveryIteration => {
+                self.disconnect();
+                false
+            }
+            ReconnectPolicy::OnError => self.is_connected(),
+        };
+
+        if let Err(e) = self.write(data) {
+            // the daemon may have closed an idle connection (e.g. after restarting)
+            if !was_connected {
+                return Err(e);
+            }
+
+            log::debug!(
+                "write to {} failed ({}), reconnecting",
+                self.path.display(),
+                e
+            );
+            self.write(data)?;
+        }
+
+        let read_timeout = match self.config.read_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(ResponseStatus::NotRead),
+        };
+
+        let max_response_size = self.config.max_response_size;
+        let idle_timeout = self.config.response_idle_timeout;
+        let stream = self.stream.as_mut().unwrap();
+
+        let status = read_response(
+            stream,
+            read_timeout,
+            idle_timeout,
+            max_response_size,
+            response,
+        );
+        match status {
+            Ok(ResponseStatus::Received) | Ok(ResponseStatus::TimedOut) => {}
+            _ => self.disconnect(),
+        }
+
+        status
+    }
+}
+
+/// What a [RawSender] sends
+#[cfg(unix)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawTarget {
+    /// IP packets of `protocol` (e.g. `libc::IPPROTO_ICMP`) sent to `addr`. The kernel builds the
+    /// IP header unless `header_included` is set (IPv4 only), in which case the data starts with
+    /// a header of its own so that malformed headers can be sent too. Responses from `addr` are
+    /// captured with their IPv4 header.
+    Ip {
+        addr: IpAddr,
+        protocol: i32,
+        header_included: bool,
+    },
+    /// Link-layer frames, starting with their Ethernet header, sent out of `interface`. The
+    /// first frame received on the interface afterwards is captured as the response.
+    #[cfg(target_os = "linux")]
+    Packet { interface: String },
+}
+
+/// Sends data over a raw IP or packet socket, bypassing the kernel's transport (or, for packet
+/// sockets, network) layer. The socket is opened when the sender is created and kept open, since
+/// opening it requires `CAP_NET_RAW`.
+#[cfg(unix)]
+pub struct RawSender {
+    target: RawTarget,
+    config: NetConfig,
+    socket: File,
+}
+
+#[cfg(unix)]
+impl RawSender {
+    /// Opens a raw socket for `target`, then drops privileges if [NetConfig::drop_privileges] is
+    /// set
+    pub fn new(target: RawTarget, config: NetConfig) -> io::Result<RawSender> {
+        let socket = match target {
+            RawTarget::Ip {
+                addr,
+                protocol,
+                header_included,
+            } => open_raw_ip(addr, protocol, header_included)?,
+            #[cfg(target_os = "linux")]
+            RawTarget::Packet { ref interface } => open_packet(interface)?,
+        };
+
+        set_socket_timeout(&socket, libc::SO_SNDTIMEO, config.write_timeout)?;
+
+        if let Some(privileges) = config.drop_privileges {
+            drop_privileges(privileges)?;
+        }
+
+        Ok(RawSender {
+            target,
+            config,
+            socket,
+        })
+    }
+
+    pub fn target(&self) -> &RawTarget {
+        &self.target
+    }
+
+    /// Receives the first packet which isn't one this sender sent, until `deadline`
+    fn recv(&self, buffer: &mut [u8], deadline: Instant) -> io::Result<Option<usize>> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_secs(0) {
+                return Ok(None);
+            }
+
+            set_socket_timeout(&self.socket, libc::SO_RCVTIMEO, Some(remaining))?;
+
+            let mut from: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+            let mut from_len = std::mem::size_of_val(&from) as libc::socklen_t;
+            let size = unsafe {
+                libc::recvfrom(
+                    self.socket.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    0,
+                    &mut from as *mut _ as *mut libc::sockaddr,
+                    &mut from_len,
+                )
+            };
+
+            if size < 0 {
+                let e = io::Error::last_os_error();
+                match e.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => return Ok(None),
+                    io::ErrorKind::Interrupted => continue,
+                    _ => return Err(e),
+                }
+            }
+
+            // packet sockets see the frames sent out of the interface as well
+            #[cfg(target_os = "linux")]
+            {
+                let from = unsafe { &*(&from as *const _ as *const libc::sockaddr_ll) };
+                if from.sll_family as i32 == libc::AF_PACKET && from.sll_pkttype == PACKET_OUTGOING
+                {
+                    continue;
+                }
+            }
+
+            return Ok(Some(size as usize));
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Sender for RawSender {
+    /// Sends `data` as a single packet and captures the first packet received in response
+    fn send(&mut self, data: &[u8], response: &mut Vec<u8>) -> io::Result<ResponseStatus> {
+        let sent = unsafe {
+            libc::send(
+                self.socket.as_raw_fd(),
+                data.as_ptr() as *const libc::c_void,
+                data.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let read_timeout = match self.config.read_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(ResponseStatus::NotRead),
+        };
+
+        let start = response.len();
+        response.resize(start + self.config.max_response_size, 0);
+
+        let received = self.recv(&mut response[start..], Instant::now() + read_timeout);
+        let size = match received {
+            Ok(Some(size)) => size,
+            _ => 0,
+        };
+        response.truncate(start + size);
+
+        received.map(|received| match received {
+            Some(_) => ResponseStatus::Received,
+            None => ResponseStatus::TimedOut,
+        })
+    }
+}
+
+/// A user and group to run as
+#[cfg(unix)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Privileges {
+    pub uid: libc::uid_t,
+    pub gid: libc::gid_t,
+}
+
+#[cfg(unix)]
+impl Privileges {
+    /// The user who ran the fuzzer through `sudo`, taken from the `SUDO_UID` and `SUDO_GID`
+    /// environment variables
+    pub fn sudo_user() -> Option<Privileges> {
+        let uid = std::env::var("SUDO_UID").ok()?.parse().ok()?;
+        let gid = std::env::var("SUDO_GID").ok()?.parse().ok()?;
+
+        Some(Privileges { uid, gid })
+    }
+}
+
+/// Switches the whole process to `privileges`, leaving only their group as a supplementary group.
+/// Does nothing if the process already runs as them, and fails if root could be regained
+/// afterwards.
+#[cfg(unix)]
+pub fn drop_privileges(privileges: Privileges) -> io::Result<()> {
+    let Privileges { uid, gid } = privileges;
+
+    unsafe {
+        if libc::getuid() == uid
+            && libc::geteuid() == uid
+            && libc::getgid() == gid
+            && libc::getegid() == gid
+        {
+            return Ok(());
+        }
+
+        // the group has to change first, since it can't once the user isn't root anymore
+        if libc::setgroups(1, &gid) < 0 || libc::setgid(gid) < 0 || libc::setuid(uid) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if uid != 0 && libc::setuid(0) == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "root privileges could be regained after dropping them",
+            ));
+        }
+    }
+
+    log::info!("dropped privileges to uid {} gid {}", uid, gid);
+
+    Ok(())
+}
+
+/// Wraps the socket `fd` so that it's closed on drop, or returns the error which prevented it
+/// from being created
+#[cfg(unix)]
+fn socket_file(fd: libc::c_int) -> io::Result<File> {
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+#[cfg(unix)]
+fn open_raw_ip(addr: IpAddr, protocol: i32, header_included: bool) -> io::Result<File> {
+    let (family, header_option) = match addr {
+        IpAddr::V4(_) => (libc::AF_INET, Some((libc::IPPROTO_IP, libc::IP_HDRINCL))),
+        IpAddr::V6(_) => (libc::AF_INET6, None),
+    };
+
+    let socket = socket_file(unsafe { libc::socket(family, libc::SOCK_RAW, protocol) })?;
+
+    if header_included {
+        let (level, option) = header_option.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only IPv4 packets can include their header",
+            )
+        })?;
+        set_socket_option(&socket, level, option, 1 as libc::c_int)?;
+    }
+
+    // connecting a raw socket sets the destination of sends and filters what's received
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let len = match addr {
+        IpAddr::V4(v4) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_addr.s_addr = u32::from_ne_bytes(v4.octets());
+            std::mem::size_of::<libc::sockaddr_in>()
+        }
+        IpAddr::V6(v6) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_addr.s6_addr = v6.octets();
+            std::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    let result = unsafe {
+        libc::connect(
+            socket.as_raw_fd(),
+            &storage as *const _ as *const libc::sockaddr,
+            len as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(socket)
+}
+
+/// `sll_pkttype` of frames sent by this host, from `<linux/if_packet.h>`
+#[cfg(target_os = "linux")]
+const PACKET_OUTGOING: u8 = 4;
+
+#[cfg(target_os = "linux")]
+fn open_packet(interface: &str) -> io::Result<File> {
+    let protocol = (libc::ETH_P_ALL as u16).to_be();
+    let socket = socket_file(unsafe {
+        libc::socket(libc::AF_PACKET, libc::SOCK_RAW, libc::c_int::from(protocol))
+    })?;
+
+    let name = std::ffi::CString::new(interface)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+    addr.sll_family = libc::AF_PACKET as libc::c_ushort;
+    addr.sll_protocol = protocol;
+    addr.sll_ifindex = index as libc::c_int;
+
+    let result = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            &addr as *const _ as *const libc::sockaddr,
+            std::mem::size_of_val(&addr) as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(socket)
+}
+
+#[cfg(unix)]
+fn set_socket_option<T>(socket: &File, level: i32, option: i32, value: T) -> io::Result<()> {
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            option,
+            &value as *const T as *const libc::c_void,
+            std::mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Sets the `SO_RCVTIMEO` or `SO_SNDTIMEO` of `socket`. `None` waits indefinitely.
+#[cfg(unix)]
+fn set_socket_timeout(socket: &File, option: i32, timeout: Option<Duration>) -> io::Result<()> {
+    let timeout = timeout.unwrap_or_default();
+    let mut timeval = libc::timeval {
+        tv_sec: timeout.as_secs() as libc::time_t,
+        tv_usec: timeout.subsec_micros() as libc::suseconds_t,
+    };
+
+    // a zero timeval waits indefinitely, so the shortest timeout is used instead
+    if timeout > Duration::from_secs(0) && timeval.tv_sec == 0 && timeval.tv_usec == 0 {
+        timeval.tv_usec = 1;
+    }
+
+    set_socket_option(socket, libc::SOL_SOCKET, option, timeval)
+}
+
+/// Identifies a saved [Session], followed by the format version
+const SESSION_MAGIC: &[u8; 8] = b"LAINSESS";
+const SESSION_VERSION: u8 = 1;
+
+/// One message sent to the target and what it sent back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exchange {
+    pub sent: Vec<u8>,
+    pub response: Vec<u8>,
+    /// How reading the response went, or `None` if sending the message failed
+    pub status: Option<ResponseStatus>,
+}
+
+/// The messages sent to the target during an iteration, in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    pub exchanges: Vec<Exchange>,
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Default::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.exchanges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exchanges.is_empty()
+    }
+
+    /// Writes the session in lain's session format: the magic `LAINSESS`, a version byte, and the
+    /// number of exchanges, followed by each exchange's status and its length-prefixed message and
+    /// response. Integers are little endian.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(SESSION_MAGIC)?;
+        writer.write_u8(SESSION_VERSION)?;
+        writer.write_u32::<LittleEndian>(self.exchanges.len() as u32)?;
+
+        for exchange in &self.exchanges {
+            writer.write_u8(encode_status(exchange.status))?;
+
+            for data in [&exchange.sent, &exchange.response].iter() {
+                writer.write_u32::<LittleEndian>(data.len() as u32)?;
+                writer.write_all(data)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a session written by [Session::write_to]
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Session> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != SESSION_MAGIC {
+            return Err(invalid_session("missing session magic"));
+        }
+
+        let version = reader.read_u8()?;
+        if version != SESSION_VERSION {
+            return Err(invalid_session(&format!(
+                "unsupported session version {}",
+                version
+            )));
+        }
+
+        let count = reader.read_u32::<LittleEndian>()?;
+        let mut session = Session::new();
+
+        for _ in 0..count {
+            let status = decode_status(reader.read_u8()?)?;
+            let sent = read_length_prefixed(reader)?;
+            let response = read_length_prefixed(reader)?;
+
+            session.exchanges.push(Exchange {
+                sent,
+                response,
+                status,
+            });
+        }
+
+        Ok(session)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut data = Vec::new();
+        self.write_to(&mut data)?;
+
+        fs::write(path, data)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Session> {
+        let data = fs::read(path)?;
+
+        Session::read_from(&mut data.as_slice())
+    }
+
+    /// Resends every message of the session through `sender` in order, returning the session as
+    /// it played out this time. Stops at the first message which fails to send.
+    pub fn replay<S: Sender>(&self, sender: &mut S) -> io::Result<Session> {
+        let mut replayed = Session::new();
+
+        for exchange in &self.exchanges {
+            let mut response = Vec::new();
+            let status = sender.send(&exchange.sent, &mut response)?;
+
+            replayed.exchanges.push(Exchange {
+                sent: exchange.sent.clone(),
+                response,
+                status: Some(status),
+            });
+        }
+
+        Ok(replayed)
+    }
+}
+
+/// Loads the session saved at `path` and resends it through `sender`. See [Session::replay].
+pub fn replay<P: AsRef<Path>, S: Sender>(path: P, sender: &mut S) -> io::Result<Session> {
+    Session::load(path)?.replay(sender)
+}
+
+/// A [Sender] which records everything sent through the sender it wraps, and the responses
+///
+/// ```no_run
+/// use lain::net::{NetConfig, SessionRecorder, Sender, TcpSender};
+///
+/// let sender = TcpSender::new("127.0.0.1:8080", NetConfig::default()).unwrap();
+/// let mut recorder = SessionRecorder::new(sender);
+///
+/// // at the start of every iteration
+/// recorder.clear();
+///
+/// let mut response = vec![];
+/// recorder.send(b"HELLO", &mut response).unwrap();
+/// recorder.send(b"DATA 1234", &mut response).unwrap();
+///
+/// // once the target is found to have crashed
+/// recorder.session().save("crash.session").unwrap();
+/// ```
+pub struct SessionRecorder<S> {
+    inner: S,
+    session: Session,
+}
+
+impl<S: Sender> SessionRecorder<S> {
+    pub fn new(inner: S) -> SessionRecorder<S> {
+        SessionRecorder {
+            inner,
+            session: Session::new(),
+        }
+    }
+
+    /// The exchanges recorded since the recorder was created or last cleared
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns the recorded session and starts a new one
+    pub fn take_session(&mut self) -> Session {
+        std::mem::take(&mut self.session)
+    }
+
+    /// Forgets the recorded exchanges. This should be called at the start of every iteration.
+    pub fn clear(&mut self) {
+        self.session.exchanges.clear();
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sender> Sender for SessionRecorder<S> {
+    fn send(&mut self, data: &[u8], response: &mut Vec<u8>) -> io::Result<ResponseStatus> {
+        // only this message's response is recorded, even if `response` already holds data
+        let start = response.len();
+        let result = self.inner.send(data, response);
+
+        self.session.exchanges.push(Exchange {
+            sent: data.to_vec(),
+            response: response[start..].to_vec(),
+            status: result.as_ref().ok().copied(),
+        });
+
+        result
+    }
+}
+
+fn encode_status(status: Option<ResponseStatus>) -> u8 {
+    match status {
+        Some(ResponseStatus::Received) => 0,
+        Some(ResponseStatus::TimedOut) => 1,
+        Some(ResponseStatus::Closed) => 2,
+        Some(ResponseStatus::NotRead) => 3,
+        None => 0xFF,
+    }
+}
+
+fn decode_status(status: u8) -> io::Result<Option<ResponseStatus>> {
+    Ok(match status {
+        0 => Some(ResponseStatus::Received),
+        1 => Some(ResponseStatus::TimedOut),
+        2 => Some(ResponseStatus::Closed),
+        3 => Some(ResponseStatus::NotRead),
+        0xFF => None,
+        other => return Err(invalid_session(&format!("invalid status {}", other))),
+    })
+}
+
+fn read_length_prefixed<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = reader.read_u32::<LittleEndian>()? as usize;
+
+    // the length isn't trusted to preallocate, so a corrupt file fails with UnexpectedEof
+    let mut data = Vec::new();
+    reader.take(len as u64).read_to_end(&mut data)?;
+    if data.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(data)
+}
+
+fn invalid_session(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid session file: {}", message),
+    )
+}
+
+fn resolve<A: ToSocketAddrs>(addr: A) -> io::Result<SocketAddr> {
+    addr.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "address did not resolve to anything",
+        )
+    })
+}
+
+/// Calls `f` up to `config.connect_attempts` times, waiting `config.retry_delay` between
+/// attempts, and returns the first success or the last error
+fn retry<T, F: FnMut() -> io::Result<T>>(config: &NetConfig, mut f: F) -> io::Result<T> {
+    let mut attempt = 1;
+
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt >= config.connect_attempts => return Err(e),
+            Err(e) => {
+                log::debug!("connection attempt {} failed: {}", attempt, e);
+                attempt += 1;
+                thread::sleep(config.retry_delay);
+            }
+        }
+    }
+}
+
+/// A connected stream whose reads can time out
+trait TimedStream: Read {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl TimedStream for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl TimedStream for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Reads from `stream` into `response` until the target stops sending data, closes the
+/// connection, or `max_size` bytes have been captured
+fn read_response<S: TimedStream>(
+    stream: &mut S,
+    read_timeout: Duration,
+    idle_timeout: Duration,
+    max_size: usize,
+    response: &mut Vec<u8>,
+) -> io::Result<ResponseStatus> {
+    let mut chunk = [0u8; 0x1000];
+    let mut captured = 0;
+
+    stream.set_read_timeout(Some(read_timeout))?;
+
+    while captured < max_size {
+        let to_read = std::cmp::min(chunk.len(), max_size - captured);
+
+        match stream.read(&mut chunk[..to_read]) {
+            Ok(0) if captured == 0 => return Ok(ResponseStatus::Closed),
+            Ok(0) => break,
+            Ok(size) => {
+                response.extend_from_slice(&chunk[..size]);
+
+                if captured == 0 {
+                    stream.set_read_timeout(Some(idle_timeout))?;
+                }
+
+                captured += size;
+            }
+            Err(ref e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                if captured == 0 {
+                    return Ok(ResponseStatus::TimedOut);
+                }
+
+                break;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(ResponseStatus::Received)
+}
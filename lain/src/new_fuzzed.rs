@@ -1,14 +1,25 @@
 use crate::mutator::Mutator;
 
+use crate::rand::distributions::WeightedIndex;
 use crate::rand::seq::SliceRandom;
 use crate::rand::Rng;
+use crate::target::{PointerWidth, TargetConfig};
 use crate::traits::*;
 use crate::types::*;
-use num_traits::Bounded;
+use num_traits::{Bounded, NumCast, PrimInt};
+use std::borrow::Cow;
 use std::fmt::Debug;
+use std::marker::PhantomData;
 use std::mem::MaybeUninit;
 use std::{char, cmp};
 
+impl<T> ConstrainedElements for Vec<T>
+where
+    T: NewFuzzed + SerializedSize,
+{
+    type Element = T;
+}
+
 impl<T> NewFuzzed for Vec<T>
 where
     T: NewFuzzed + SerializedSize,
@@ -28,24 +39,26 @@ where
         let mut used_size: usize = 0;
         let mut output: Vec<T>;
 
-        trace!("Generating random Vec with constraints: {:#?}", constraints);
+        hot_trace!("Generating random Vec with constraints: {:#?}", constraints);
+
+        let element_constraints = constraints.and_then(|c| c.elements::<T::RangeType>());
+
+        mutator.begin_allocation_scope();
 
         // if no min/max were supplied, we'll take a conservative approach of 64 elements
         match constraints {
             Some(ref constraints) => {
-                min = constraints.min.unwrap_or(0);
-                max = constraints.max.unwrap_or(MAX_NUM_ELEMENTS);
+                min = constraints.inclusive_min().unwrap_or(0);
+                max = constraints.exclusive_max().unwrap_or(MAX_NUM_ELEMENTS);
 
                 if min != max {
-                    if min != 0 && mutator.gen_chance(crate::mutator::CHANCE_TO_IGNORE_MIN_MAX) {
+                    if min != 0 && mutator.should_ignore_constraint() {
                         min = 0;
                     }
 
-                    if constraints.max.is_some()
-                        && mutator.gen_chance(crate::mutator::CHANCE_TO_IGNORE_MIN_MAX)
-                    {
+                    if constraints.max.is_some() && mutator.should_ignore_constraint() {
                         // we just hope this doesn't overflow.
-                        max = constraints.max.unwrap() * 2;
+                        max *= 2;
                     }
                 }
 
@@ -70,16 +83,17 @@ where
         } else {
             mutator.gen_weighted_range(min, max, weight)
         };
+        let num_elements = mutator.reserve_allocation(num_elements, std::mem::size_of::<T>());
 
         output = Vec::with_capacity(num_elements);
 
         for _i in 0..num_elements {
-            let element: T = T::new_fuzzed(mutator, None);
+            let element: T = T::new_fuzzed(mutator, element_constraints);
             let element_serialized_size = element.serialized_size();
 
             if let Some(ref max_size) = max_size {
                 if used_size + element_serialized_size > *max_size {
-                    return output;
+                    break;
                 } else {
                     used_size += element_serialized_size;
                 }
@@ -88,6 +102,8 @@ where
             output.push(element);
         }
 
+        mutator.end_allocation_scope();
+
         output
     }
 }
@@ -109,24 +125,26 @@ where
         let mut used_size: usize = 0;
         let mut output: Vec<T>;
 
-        trace!("Generating random Vec with constraints: {:#?}", constraints);
+        hot_trace!("Generating random Vec with constraints: {:#?}", constraints);
+
+        let element_constraints = constraints.and_then(|c| c.elements::<T::RangeType>());
+
+        mutator.begin_allocation_scope();
 
         // if no min/max were supplied, we'll take a conservative approach of 64 elements
         match constraints {
             Some(ref constraints) => {
-                min = constraints.min.unwrap_or(0);
-                max = constraints.max.unwrap_or(MAX_NUM_ELEMENTS);
+                min = constraints.inclusive_min().unwrap_or(0);
+                max = constraints.exclusive_max().unwrap_or(MAX_NUM_ELEMENTS);
 
                 if min != max {
-                    if min != 0 && mutator.gen_chance(crate::mutator::CHANCE_TO_IGNORE_MIN_MAX) {
+                    if min != 0 && mutator.should_ignore_constraint() {
                         min = 0;
                     }
 
-                    if constraints.max.is_some()
-                        && mutator.gen_chance(crate::mutator::CHANCE_TO_IGNORE_MIN_MAX)
-                    {
+                    if constraints.max.is_some() && mutator.should_ignore_constraint() {
                         // we just hope this doesn't overflow.
-                        max = constraints.max.unwrap() * 2;
+                        max *= 2;
                     }
                 }
 
@@ -151,6 +169,7 @@ where
         } else {
             mutator.gen_weighted_range(min, max, weight)
         };
+        let num_elements = mutator.reserve_allocation(num_elements, std::mem::size_of::<T>());
 
         output = Vec::with_capacity(num_elements);
 
@@ -158,28 +177,35 @@ where
             mutator.gen_chance(crate::mutator::CHANCE_TO_REPEAT_ARRAY_VALUE);
 
         if should_reuse_array_item {
-            let element: T = T::new_fuzzed(mutator, None);
+            let allocated_before = mutator.allocated_bytes();
+            let element: T = T::new_fuzzed(mutator, element_constraints);
             let element_serialized_size = element.serialized_size();
+            // every clone of the element allocates as much as generating it did
+            let element_allocation_size = mutator.allocated_bytes() - allocated_before;
 
-            for _i in 0..num_elements {
+            for i in 0..num_elements {
                 if let Some(ref max_size) = max_size {
                     if used_size + element_serialized_size > *max_size {
-                        return output;
+                        break;
                     } else {
                         used_size += element_serialized_size;
                     }
                 }
 
+                if i > 0 && mutator.reserve_allocation(1, element_allocation_size) == 0 {
+                    break;
+                }
+
                 output.push(element.clone());
             }
         } else {
             for _i in 0..num_elements {
-                let element: T = T::new_fuzzed(mutator, None);
+                let element: T = T::new_fuzzed(mutator, element_constraints);
                 let element_serialized_size = element.serialized_size();
 
                 if let Some(ref max_size) = max_size {
                     if used_size + element_serialized_size > *max_size {
-                        return output;
+                        break;
                     } else {
                         used_size += element_serialized_size;
                     }
@@ -189,6 +215,8 @@ where
             }
         }
 
+        mutator.end_allocation_scope();
+
         output
     }
 }
@@ -236,7 +264,7 @@ where
 impl<T, I> NewFuzzed for UnsafeEnum<T, I>
 where
     T: NewFuzzed,
-    I: NewFuzzed<RangeType = I> + Bounded + Debug + Default,
+    I: NewFuzzed<RangeType = I> + Bounded + Debug + Default + 'static,
 {
     type RangeType = I;
 
@@ -244,12 +272,12 @@ where
         mutator: &mut Mutator<R>,
         constraints: Option<&Constraints<Self::RangeType>>,
     ) -> Self {
-        trace!(
+        hot_trace!(
             "Generating random UnsafeEnum with constraints: {:#?}",
             constraints
         );
 
-        if mutator.gen_chance(crate::mutator::CHANCE_TO_PICK_INVALID_ENUM) {
+        if mutator.should_pick_invalid_enum() {
             UnsafeEnum::Invalid(I::new_fuzzed(mutator, constraints))
         } else {
             // TODO/BUG: We should be passing on the constraints, but all
@@ -260,6 +288,66 @@ where
     }
 }
 
+impl<T> NewFuzzed for Option<T>
+where
+    T: NewFuzzed,
+{
+    type RangeType = T::RangeType;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let none_chance = mutator.config().none_chance;
+        let value = if mutator.gen_chance(none_chance) {
+            None
+        } else {
+            Some(T::new_fuzzed(mutator, constraints))
+        };
+
+        if let Some(coverage) = mutator.model_coverage() {
+            coverage.record_option(std::any::type_name::<Self>(), value.is_some());
+        }
+
+        value
+    }
+}
+
+impl<T, E> NewFuzzed for Result<T, E>
+where
+    T: NewFuzzed,
+    E: NewFuzzed,
+{
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        if constraints.is_some() {
+            warn!("Constraints passed to new_fuzzed on Result do nothing");
+        }
+
+        let err_chance = mutator.config().err_chance;
+        if mutator.gen_chance(err_chance) {
+            Err(E::new_fuzzed(mutator, None))
+        } else {
+            Ok(T::new_fuzzed(mutator, None))
+        }
+    }
+}
+
+impl<'a> NewFuzzed for Cow<'a, [u8]> {
+    type RangeType = usize;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        Cow::Owned(Vec::<u8>::new_fuzzed(mutator, constraints))
+    }
+}
+
 impl NewFuzzed for Utf8String {
     type RangeType = usize;
 
@@ -272,7 +360,7 @@ impl NewFuzzed for Utf8String {
         let weight: Weighted;
         let mut output: Utf8String;
 
-        trace!(
+        hot_trace!(
             "Generating random UtfString with constraints: {:#?}",
             constraints
         );
@@ -280,8 +368,8 @@ impl NewFuzzed for Utf8String {
         // if no min/max were supplied, we'll take a conservative approach
         match constraints {
             Some(ref constraints) => {
-                min = constraints.min.unwrap_or(0);
-                max = constraints.max.unwrap_or(256);
+                min = constraints.inclusive_min().unwrap_or(0);
+                max = constraints.exclusive_max().unwrap_or(256);
                 weight = constraints.weighted;
             }
             None => {
@@ -291,7 +379,10 @@ impl NewFuzzed for Utf8String {
             }
         }
 
+        mutator.begin_allocation_scope();
         let string_length = mutator.gen_weighted_range(min, max, weight);
+        let string_length =
+            mutator.reserve_allocation(string_length, std::mem::size_of::<Utf8Char>());
 
         output = Utf8String {
             inner: Vec::with_capacity(string_length),
@@ -320,6 +411,8 @@ impl NewFuzzed for Utf8String {
             }
         }
 
+        mutator.end_allocation_scope();
+
         output
     }
 }
@@ -336,7 +429,7 @@ impl NewFuzzed for AsciiString {
         let weight: Weighted;
         let mut output: AsciiString;
 
-        trace!(
+        hot_trace!(
             "Generating random AsciiString with constraints: {:#?}",
             constraints
         );
@@ -344,8 +437,8 @@ impl NewFuzzed for AsciiString {
         // if no min/max were supplied, we'll take a conservative approach
         match constraints {
             Some(ref constraints) => {
-                min = constraints.min.unwrap_or(0);
-                max = constraints.max.unwrap_or(256);
+                min = constraints.inclusive_min().unwrap_or(0);
+                max = constraints.exclusive_max().unwrap_or(256);
                 weight = constraints.weighted;
             }
             None => {
@@ -355,7 +448,10 @@ impl NewFuzzed for AsciiString {
             }
         }
 
+        mutator.begin_allocation_scope();
         let string_length = mutator.gen_weighted_range(min, max, weight);
+        let string_length =
+            mutator.reserve_allocation(string_length, std::mem::size_of::<AsciiChar>());
 
         output = AsciiString {
             inner: Vec::with_capacity(string_length),
@@ -384,10 +480,56 @@ impl NewFuzzed for AsciiString {
             }
         }
 
+        mutator.end_allocation_scope();
+
         output
     }
 }
 
+/// Picks the length a [FixedLengthString] is generated or mutated to. Lengths which fill the
+/// field, and the lengths just short of that, are favored.
+pub(crate) fn gen_fixed_string_length<R: Rng>(
+    mutator: &mut Mutator<R>,
+    min: usize,
+    max: usize,
+) -> usize {
+    if !mutator.gen_chance(crate::mutator::CHANCE_TO_PICK_BOUNDARY_LENGTH) {
+        return mutator.gen_range(min, max + 1);
+    }
+
+    let below = mutator.gen_range(0, 3);
+    cmp::max(max.saturating_sub(below), min)
+}
+
+impl<const N: usize, const PAD: u8> NewFuzzed for FixedLengthString<N, PAD> {
+    type RangeType = usize;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        hot_trace!(
+            "Generating random FixedLengthString with constraints: {:#?}",
+            constraints
+        );
+
+        // the contents never exceed the field, even when the constraints allow it
+        let max = constraints
+            .and_then(|constraints| constraints.inclusive_max())
+            .map_or(N, |max| cmp::min(max, N));
+        let min = constraints
+            .and_then(|constraints| constraints.inclusive_min())
+            .map_or(0, |min| cmp::min(min, max));
+
+        let len = gen_fixed_string_length(mutator, min, max);
+        let bytes = (0..len)
+            .map(|_| AsciiChar::new_fuzzed(mutator, None).0 as u8)
+            .collect();
+
+        FixedLengthString { bytes }
+    }
+}
+
 impl NewFuzzed for Utf8Char {
     type RangeType = u32;
 
@@ -395,7 +537,7 @@ impl NewFuzzed for Utf8Char {
         mutator: &mut crate::mutator::Mutator<R>,
         _constraints: Option<&Constraints<Self::RangeType>>,
     ) -> Self {
-        trace!("generating random UTF8 char");
+        hot_trace!("generating random UTF8 char");
 
         // This implementation is taken almost verbatim from burntsushi's
         // quickcheck library. See this link for the original implementation:
@@ -509,7 +651,7 @@ impl NewFuzzed for AsciiChar {
         mutator: &mut crate::mutator::Mutator<R>,
         constraints: Option<&Constraints<Self::RangeType>>,
     ) -> Self {
-        trace!("generating random ASCII char");
+        hot_trace!("generating random ASCII char");
         let min: Self::RangeType;
         let max: Self::RangeType;
         let weight: Weighted;
@@ -517,8 +659,8 @@ impl NewFuzzed for AsciiChar {
         // if no min/max were supplied, we'll take a conservative approach of 64 elements
         match constraints {
             Some(ref constraints) => {
-                min = constraints.min.unwrap_or(0);
-                max = constraints.max.unwrap_or(0x80);
+                min = constraints.inclusive_min().unwrap_or(0);
+                max = constraints.exclusive_max().unwrap_or(0x80);
                 weight = constraints.weighted;
             }
             None => {
@@ -577,7 +719,7 @@ impl NewFuzzed for bool {
         mutator: &mut crate::mutator::Mutator<R>,
         _constraints: Option<&Constraints<Self::RangeType>>,
     ) -> Self {
-        trace!("generating random bool");
+        hot_trace!("generating random bool");
 
         mutator.gen_range(0u8, 2u8) != 0
     }
@@ -594,24 +736,28 @@ macro_rules! impl_new_fuzzed {
                     let max: Self::RangeType;
                     let weight: Weighted;
 
+                    if mutator.should_pick_extreme_value() {
+                        return <$name>::select_dangerous_number(&mut mutator.rng);
+                    }
+
                     // if no min/max were supplied, we'll take a conservative approach of 64 elements
                     match constraints {
                         Some(ref constraints) => {
-                            min = if let Some(ref min) = constraints.min {
-                                if mutator.gen_chance(crate::mutator::CHANCE_TO_IGNORE_MIN_MAX) {
+                            min = if let Some(min) = constraints.inclusive_min() {
+                                if mutator.should_ignore_constraint() {
                                     $name::min_value()
                                 } else {
-                                    *min
+                                    min
                                 }
                             } else {
                                 $name::min_value()
                             };
 
-                            max = if let Some(ref max) = constraints.max {
-                                if mutator.gen_chance(crate::mutator::CHANCE_TO_IGNORE_MIN_MAX) {
+                            max = if let Some(max) = constraints.inclusive_max() {
+                                if mutator.should_ignore_constraint() {
                                     $name::max_value()
                                 } else {
-                                    *max
+                                    max
                                 }
                             } else {
                                 $name::max_value()
@@ -619,7 +765,7 @@ macro_rules! impl_new_fuzzed {
 
                             weight = constraints.weighted;
 
-                            return mutator.gen_weighted_range(min, max, weight);
+                            return mutator.gen_weighted_range_inclusive(min, max, weight);
                         }
                         None => {
                             return mutator.rng.gen();
@@ -631,9 +777,525 @@ macro_rules! impl_new_fuzzed {
     }
 }
 
-// BUG: f32/f64 generate a number between 0/1 when no constraints are supplied,
-// otherwise they generate an *integer* between min/max.
-impl_new_fuzzed!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+impl_new_fuzzed!(u8, i8, u16, i16, u32, i32, u64, i64);
+
+macro_rules! impl_new_fuzzed_float {
+    ( $($name:ident),* ) => {
+        $(
+            impl NewFuzzed for $name {
+                type RangeType = $name;
+
+                fn new_fuzzed<R: Rng>(mutator: &mut Mutator<R>, constraints: Option<&Constraints<Self::RangeType>>) -> Self {
+                    if mutator.should_pick_extreme_value() {
+                        return <$name>::select_dangerous_number(&mut mutator.rng);
+                    }
+
+                    hot_trace!("generating random {} with constraints: {:#?}", stringify!($name), constraints);
+
+                    match constraints {
+                        Some(ref constraints) if constraints.min.is_some() || constraints.max.is_some() => {
+                            let min = match constraints.min {
+                                Some(min) if !mutator.should_ignore_constraint() => min,
+                                _ => $name::MIN,
+                            };
+
+                            let max = match constraints.max {
+                                Some(max) if !mutator.should_ignore_constraint() => max,
+                                _ => $name::MAX,
+                            };
+
+                            mutator.gen_float_range(min, max, constraints.bounds, constraints.weighted)
+                        }
+                        // TODO: without a range we only generate numbers in [0, 1)
+                        _ => mutator.rng.gen(),
+                    }
+                }
+            }
+        )*
+    }
+}
+
+impl_new_fuzzed_float!(f32, f64);
+
+macro_rules! impl_new_fuzzed_narrow_int {
+    ( $($name:ident => $inner:ident),* ) => {
+        $(
+            impl NewFuzzed for $name {
+                type RangeType = $inner;
+
+                fn new_fuzzed<R: Rng>(mutator: &mut Mutator<R>, constraints: Option<&Constraints<Self::RangeType>>) -> Self {
+                    // the largest value which fits in the narrow type
+                    let type_max: Self::RangeType = $name::MAX;
+
+                    hot_trace!("generating random {} with constraints: {:#?}", stringify!($name), constraints);
+
+                    match constraints {
+                        Some(ref constraints) => {
+                            let min = match constraints.inclusive_min() {
+                                Some(min) if !mutator.should_ignore_constraint() => min,
+                                _ => 0,
+                            };
+
+                            let max = match constraints.inclusive_max() {
+                                Some(max) if !mutator.should_ignore_constraint() => cmp::min(max, type_max),
+                                _ => type_max,
+                            };
+
+                            $name::new(mutator.gen_weighted_range_inclusive(min, max, constraints.weighted))
+                        }
+                        None => {
+                            $name::new(mutator.gen_range(0, type_max + 1))
+                        }
+                    }
+                }
+            }
+        )*
+    }
+}
+
+impl_new_fuzzed_narrow_int!(U24 => u32, U48 => u64);
+
+/// Well-known IPv4 ranges as (prefix, prefix length). Generated addresses are drawn uniformly
+/// from one of these.
+const IPV4_RANGES: &[([u8; 4], usize)] = &[
+    // unspecified and broadcast
+    ([0, 0, 0, 0], 32),
+    ([255, 255, 255, 255], 32),
+    // loopback
+    ([127, 0, 0, 0], 8),
+    // private networks
+    ([10, 0, 0, 0], 8),
+    ([172, 16, 0, 0], 12),
+    ([192, 168, 0, 0], 16),
+    // carrier-grade NAT
+    ([100, 64, 0, 0], 10),
+    // link-local
+    ([169, 254, 0, 0], 16),
+    // multicast
+    ([224, 0, 0, 0], 4),
+    // anything
+    ([0, 0, 0, 0], 0),
+];
+
+/// Well-known IPv6 ranges as (prefix, prefix length)
+const IPV6_RANGES: &[([u8; 16], usize)] = &[
+    // unspecified and loopback
+    ([0; 16], 128),
+    ([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1], 128),
+    // all-nodes multicast and other multicast
+    ([0xFF, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1], 128),
+    ([0xFF, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 8),
+    // link-local
+    ([0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 64),
+    // unique local
+    ([0xFC, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 7),
+    // IPv4-mapped
+    ([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0, 0, 0, 0], 96),
+    // documentation
+    (
+        [0x20, 0x01, 0x0D, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        32,
+    ),
+    // global unicast
+    ([0x20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], 3),
+    // anything
+    ([0; 16], 0),
+];
+
+/// Fills `octets` with random bytes, then copies the leading `prefix_len` bits of `prefix` over them
+fn gen_octets_in_prefix<R: Rng>(
+    mutator: &mut Mutator<R>,
+    prefix: &[u8],
+    prefix_len: usize,
+    octets: &mut [u8],
+) {
+    mutator.rng.fill(octets);
+
+    for (i, octet) in octets.iter_mut().enumerate() {
+        let bits = cmp::min(prefix_len.saturating_sub(i * 8), 8);
+        if bits == 0 {
+            break;
+        }
+
+        let mask = (0xFF00u16 >> bits) as u8;
+        *octet = (prefix[i] & mask) | (*octet & !mask);
+    }
+}
+
+impl NewFuzzed for Ipv4Address {
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let (prefix, prefix_len) = IPV4_RANGES[mutator.gen_range(0, IPV4_RANGES.len())];
+
+        let mut octets = [0u8; 4];
+        gen_octets_in_prefix(mutator, &prefix, prefix_len, &mut octets);
+
+        Ipv4Address(octets)
+    }
+}
+
+impl NewFuzzed for Ipv6Address {
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let (prefix, prefix_len) = IPV6_RANGES[mutator.gen_range(0, IPV6_RANGES.len())];
+
+        let mut octets = [0u8; 16];
+        gen_octets_in_prefix(mutator, &prefix, prefix_len, &mut octets);
+
+        Ipv6Address(octets)
+    }
+}
+
+impl NewFuzzed for MacAddress {
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let mut octets = [0u8; 6];
+
+        match mutator.gen_range(0, 6) {
+            0 => return MacAddress::BROADCAST,
+            1 => return MacAddress::default(),
+            // multicast addresses mapped from IPv4 and IPv6 multicast groups
+            2 => gen_octets_in_prefix(mutator, &[0x01, 0x00, 0x5E, 0x00], 25, &mut octets),
+            3 => gen_octets_in_prefix(mutator, &[0x33, 0x33], 16, &mut octets),
+            // vendor-assigned and locally administered unicast
+            4 => {
+                mutator.rng.fill(&mut octets[..]);
+                octets[0] &= !0x03;
+            }
+            _ => {
+                mutator.rng.fill(&mut octets[..]);
+                octets[0] = (octets[0] & !0x01) | 0x02;
+            }
+        }
+
+        MacAddress(octets)
+    }
+}
+
+impl NewFuzzed for Uuid {
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let mut bytes = [0u8; 16];
+
+        let version = match mutator.gen_range(0, 6) {
+            0 => return Uuid::default(),
+            1 => return Uuid([0xFF; 16]),
+            2 => 1,
+            // arbitrary bytes, which usually have an invalid version and variant
+            3 => {
+                mutator.rng.fill(&mut bytes[..]);
+                return Uuid(bytes);
+            }
+            _ => 4,
+        };
+
+        mutator.rng.fill(&mut bytes[..]);
+        bytes[6] = (bytes[6] & 0x0F) | (version << 4);
+        // RFC 4122 variant
+        bytes[8] = (bytes[8] & 0x3F) | 0x80;
+
+        Uuid(bytes)
+    }
+}
+
+/// Time units (in seconds) used to place generated timestamps near an interesting point in time:
+/// exactly on it, or a few seconds, minutes, hours, days or years away in either direction
+const TIME_DELTA_UNITS: &[i64] = &[0, 1, 60, 3600, 86_400, 365 * 86_400];
+
+/// Generates an offset in seconds from an interesting point in time
+pub(crate) fn gen_time_delta<R: Rng>(mutator: &mut Mutator<R>) -> i64 {
+    let unit = TIME_DELTA_UNITS[mutator.gen_range(0, TIME_DELTA_UNITS.len())];
+
+    unit * mutator.gen_range(-10i64, 11)
+}
+
+/// Seconds since the Unix epoch of the epoch itself, Y2K, a recent date and either end of the
+/// signed 32-bit range (the Y2K38 rollover)
+const UNIX_TIME_ANCHORS_32: &[i32] = &[0, 946_684_800, 1_700_000_000, i32::MAX, i32::MIN];
+
+/// In addition to the 32-bit anchors, the unsigned 32-bit rollover in 2106, the end of the year
+/// 9999, a recent date in milliseconds and either end of the signed 64-bit range
+const UNIX_TIME_ANCHORS_64: &[i64] = &[
+    1 << 32,
+    253_402_300_799,
+    1_700_000_000_000,
+    i64::MAX,
+    i64::MIN,
+];
+
+impl NewFuzzed for UnixTimestamp32 {
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        if mutator.gen_chance(10.0) {
+            return UnixTimestamp32(mutator.rng.gen());
+        }
+
+        let anchor = UNIX_TIME_ANCHORS_32[mutator.gen_range(0, UNIX_TIME_ANCHORS_32.len())];
+
+        // wrapping past Y2K38 is intentional
+        UnixTimestamp32((anchor as i64 + gen_time_delta(mutator)) as i32)
+    }
+}
+
+impl NewFuzzed for UnixTimestamp64 {
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        if mutator.gen_chance(10.0) {
+            return UnixTimestamp64(mutator.rng.gen());
+        }
+
+        let num_anchors = UNIX_TIME_ANCHORS_32.len() + UNIX_TIME_ANCHORS_64.len();
+        let index = mutator.gen_range(0, num_anchors);
+        let anchor = if index < UNIX_TIME_ANCHORS_32.len() {
+            UNIX_TIME_ANCHORS_32[index] as i64
+        } else {
+            UNIX_TIME_ANCHORS_64[index - UNIX_TIME_ANCHORS_32.len()]
+        };
+
+        UnixTimestamp64(anchor.wrapping_add(gen_time_delta(mutator)))
+    }
+}
+
+impl NewFuzzed for DosDateTime {
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let year = mutator.gen_range(1980u16, 2108);
+        let month = mutator.gen_range(1u8, 13);
+        let day = mutator.gen_range(1, days_in_month(year as i64, month as u32) + 1) as u8;
+        let hour = mutator.gen_range(0u8, 24);
+        let minute = mutator.gen_range(0u8, 60);
+        let second = mutator.gen_range(0u8, 60);
+
+        match mutator.gen_range(0, 8) {
+            // the first and last representable times
+            0 => DosDateTime::new(1980, 1, 1, 0, 0, 0),
+            1 => DosDateTime::new(2107, 12, 31, 23, 59, 58),
+            // all zeroes, which archivers commonly write and which has no valid month or day
+            2 => DosDateTime::default(),
+            3 => DosDateTime::from_raw(mutator.rng.gen(), mutator.rng.gen()),
+            // leap days, valid or not depending on the year
+            4 => DosDateTime::new(year, 2, 29, hour, minute, second),
+            // a single field outside of its calendar range
+            5 => match mutator.gen_range(0, 5) {
+                0 => DosDateTime::new(
+                    year,
+                    *[0, 13, 14, 15].choose(&mut mutator.rng).unwrap(),
+                    day,
+                    hour,
+                    minute,
+                    second,
+                ),
+                1 => DosDateTime::new(
+                    year,
+                    month,
+                    *[0, 30, 31].choose(&mut mutator.rng).unwrap(),
+                    hour,
+                    minute,
+                    second,
+                ),
+                2 => DosDateTime::new(
+                    year,
+                    month,
+                    day,
+                    mutator.gen_range(24u8, 32),
+                    minute,
+                    second,
+                ),
+                3 => DosDateTime::new(year, month, day, hour, mutator.gen_range(60u8, 64), second),
+                _ => DosDateTime::new(year, month, day, hour, minute, mutator.gen_range(60u8, 64)),
+            },
+            _ => DosDateTime::new(year, month, day, hour, minute, second),
+        }
+    }
+}
+
+impl NewFuzzed for FileTime {
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let anchor = match mutator.gen_range(0, 7) {
+            0 => 0,
+            1 => FileTime::UNIX_EPOCH,
+            2 => FileTime::from_unix(1_700_000_000).0,
+            3 => FileTime::from_unix(i32::MAX as i64).0,
+            4 => i64::MAX as u64,
+            5 => u64::MAX,
+            _ => return FileTime(mutator.rng.gen()),
+        };
+
+        let mut intervals =
+            (gen_time_delta(mutator) * FileTime::INTERVALS_PER_SECOND as i64) as u64;
+        if mutator.gen_chance(50.0) {
+            intervals =
+                intervals.wrapping_add(mutator.gen_range(0, FileTime::INTERVALS_PER_SECOND));
+        }
+
+        FileTime(anchor.wrapping_add(intervals))
+    }
+}
+
+impl<T, F> NewFuzzed for Scaled<T, F>
+where
+    T: NewFuzzed + NumCast + Bounded + Copy,
+    F: ScaleFactor,
+{
+    type RangeType = f64;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        match constraints {
+            // constraints are given in the real domain
+            Some(constraints) if constraints.min.is_some() || constraints.max.is_some() => {
+                Scaled::from_real(f64::new_fuzzed(mutator, Some(constraints)))
+            }
+            // otherwise any value that fits on the wire is fair game
+            _ => Scaled::from_raw(T::new_fuzzed(mutator, None)),
+        }
+    }
+}
+
+impl<T> NewFuzzed for Offset<T>
+where
+    T: NewFuzzed<RangeType = T> + PrimInt + Debug + Default + 'static,
+{
+    type RangeType = T;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        Offset::new(T::new_fuzzed(mutator, constraints))
+    }
+}
+
+impl<T, C> NewFuzzed for TextEncoded<T, C>
+where
+    T: NewFuzzed,
+    C: TextEncoding,
+{
+    type RangeType = T::RangeType;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let value = T::new_fuzzed(mutator, constraints);
+
+        if mutator.should_flaw_encoding() {
+            let flaw = mutator.gen_encoding_flaw::<C>();
+            TextEncoded::with_flaw(value, flaw)
+        } else {
+            TextEncoded::new(value)
+        }
+    }
+}
+
+impl<M> NewFuzzed for Conversation<M>
+where
+    M: NewFuzzed + SerializedSize,
+{
+    /// Constrains the number of messages
+    type RangeType = usize;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let mut conversation = Conversation::new(Vec::<M>::new_fuzzed(mutator, constraints));
+
+        if mutator.should_fixup() {
+            conversation.fixup(mutator);
+        }
+
+        conversation
+    }
+}
+
+impl<T> NewFuzzed for OneOf<T>
+where
+    T: NewFuzzed + Clone + Send + Sync + 'static,
+{
+    type RangeType = T::RangeType;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        let candidates = mutator.candidates::<T>().unwrap_or_default();
+        if candidates.is_empty() {
+            return OneOf::with_value(T::new_fuzzed(mutator, constraints), candidates);
+        }
+
+        let value = candidates[mutator.gen_range(0, candidates.len())].clone();
+
+        OneOf::with_value(value, candidates)
+    }
+}
+
+macro_rules! impl_new_fuzzed_pointer {
+    ( $($name:ident => $narrow:ident, $wide:ident),* ) => {
+        $(
+            impl NewFuzzed for $name {
+                type RangeType = $wide;
+
+                fn new_fuzzed<R: Rng>(mutator: &mut Mutator<R>, constraints: Option<&Constraints<Self::RangeType>>) -> Self {
+                    // unconstrained values are generated at the target's width, so that its
+                    // boundary values are picked rather than those of a 64-bit integer
+                    match (TargetConfig::current().pointer_width, constraints) {
+                        (PointerWidth::Bits32, None) => $name($narrow::new_fuzzed(mutator, None) as $wide),
+                        _ => $name($wide::new_fuzzed(mutator, constraints)),
+                    }
+                }
+            }
+        )*
+    }
+}
+
+impl_new_fuzzed_pointer!(TargetUsize => u32, u64, TargetIsize => i32, i64);
+
+impl<T: NewFuzzed> NewFuzzed for PointerAligned<T> {
+    type RangeType = T::RangeType;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        PointerAligned(T::new_fuzzed(mutator, constraints))
+    }
+}
 
 impl<T> NewFuzzed for [T; 0]
 where
@@ -726,3 +1388,215 @@ impl_new_fuzzed_array!(
     27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50,
     51, 52, 53, 54, 55, 56, 57, 58, 59, 60
 );
+
+macro_rules! impl_new_fuzzed_tuple {
+    ( $($name:ident $idx:tt),+ ) => {
+        impl<$($name),+> NewFuzzed for ($($name,)+)
+        where
+            $($name: NewFuzzed),+
+        {
+            type RangeType = u8;
+
+            fn new_fuzzed<R: Rng>(mutator: &mut Mutator<R>, constraints: Option<&Constraints<Self::RangeType>>) -> Self {
+                if constraints.is_some() {
+                    warn!("Constraints passed to new_fuzzed on tuple do nothing");
+                }
+
+                ($($name::new_fuzzed(mutator, None),)+)
+            }
+        }
+    }
+}
+
+for_each_tuple!(impl_new_fuzzed_tuple);
+
+macro_rules! impl_new_fuzzed_zero_sized {
+    ( $($name:ty $(, $generic:ident)?);* ) => {
+        $(
+            impl$(<$generic>)? NewFuzzed for $name {
+                type RangeType = u8;
+
+                #[inline(always)]
+                fn new_fuzzed<R: Rng>(_mutator: &mut Mutator<R>, _constraints: Option<&Constraints<Self::RangeType>>) -> Self {
+                    // zero-sized types have a single value, so no randomness is consumed
+                    Default::default()
+                }
+            }
+        )*
+    }
+}
+
+impl_new_fuzzed_zero_sized!((); PhantomData<T>, T; FieldSwap);
+
+/// Builds the distribution derived enums pick their variants from. The derive rejects enums whose
+/// weights are all 0, but if they do collapse to 0 the variants are picked uniformly rather than
+/// panicking.
+pub fn variant_distribution(enum_name: &str, weights: &[u64]) -> WeightedIndex<u64> {
+    WeightedIndex::new(weights).unwrap_or_else(|_| {
+        if weights.is_empty() {
+            panic!("`{}` has no variants which can be generated", enum_name);
+        }
+
+        warn!(
+            "the variants of `{}` have no usable weights, picking them uniformly instead",
+            enum_name
+        );
+
+        WeightedIndex::new(vec![1u64; weights.len()]).unwrap()
+    })
+}
+
+/// Builds the distribution used by derived enums when `constraints` gives a `min`/`max`, some
+/// variants are down-weighted by [adaptive](crate::adaptive) weighting, or the enum is being
+/// mutated from a variant with restricted [transitions](crate::mutator::VariantTransitions). The
+/// bounds restrict which variants may be picked by their declaration index (e.g.
+/// `min: Some(1), max: Some(3)` picks the second or third variant declared) while keeping their
+/// relative weights.
+///
+/// Returns `None` if the full distribution should be used instead: there are no bounds or
+/// transitions (or the mutator's profile chose to ignore them) and no down-weighted variants, or
+/// no variant with a nonzero weight lies in range.
+pub fn constrained_variant_distribution<R: Rng>(
+    mutator: &mut Mutator<R>,
+    constraints: Option<&Constraints<u8>>,
+    enum_name: &'static str,
+    weights: &[u64],
+    declaration_indices: &[usize],
+    variant_names: &[&'static str],
+) -> Option<WeightedIndex<u64>> {
+    let constraints = match constraints {
+        Some(constraints)
+            if (constraints.min.is_some() || constraints.max.is_some())
+                && !mutator.should_ignore_constraint() =>
+        {
+            Some(constraints)
+        }
+        _ => None,
+    };
+
+    let allowed_transitions = mutator
+        .take_allowed_transitions(enum_name, variant_names)
+        .filter(|_| !mutator.should_ignore_constraint());
+
+    let adaptive_weights = mutator
+        .adaptive_weights()
+        .and_then(|adaptive| adaptive.variant_weights(enum_name, weights, declaration_indices));
+
+    if constraints.is_none() && allowed_transitions.is_none() && adaptive_weights.is_none() {
+        return None;
+    }
+
+    let mut weights = adaptive_weights.as_ref().map_or(weights, Vec::as_slice);
+
+    // variants the enum may not transition into are never picked
+    let transition_weights: Vec<u64>;
+    if let Some(allowed) = allowed_transitions {
+        transition_weights = weights
+            .iter()
+            .zip(allowed)
+            .map(|(&weight, allowed)| if allowed { weight } else { 0 })
+            .collect();
+        weights = &transition_weights;
+    }
+
+    let narrowed = weights
+        .iter()
+        .zip(declaration_indices)
+        .map(|(&weight, &index)| {
+            let constraints = match constraints {
+                Some(constraints) => constraints,
+                None => return weight,
+            };
+
+            let in_range = if index <= u8::MAX as usize {
+                constraints.contains(&(index as u8))
+            } else {
+                // indices past the range type can only be bounded from below
+                constraints.max.is_none()
+            };
+
+            if in_range {
+                weight
+            } else {
+                0
+            }
+        });
+
+    match WeightedIndex::new(narrowed) {
+        Ok(dist) => Some(dist),
+        Err(_) => {
+            if let Some(constraints) = constraints {
+                warn!(
+                    "no enum variants lie within {:?}..{:?}, ignoring the constraint",
+                    constraints.min, constraints.max
+                );
+            }
+
+            None
+        }
+    }
+}
+
+/// Constraints handing what's left of a derived struct's `max_size` to a field which has no
+/// constraints of its own
+#[doc(hidden)]
+pub fn max_size_constraints<T>(max_size: Option<usize>) -> Option<Constraints<T>>
+where
+    T: Bounded + Debug + Default,
+{
+    max_size.map(|max_size| Constraints {
+        max_size: Some(max_size),
+        ..Default::default()
+    })
+}
+
+/// Takes the `size` of a generated field from what's left of a derived struct's `max_size`
+#[doc(hidden)]
+#[inline]
+pub fn take_max_size(max_size: &mut Option<usize>, size: usize) {
+    if let Some(ref mut max_size) = max_size {
+        // fields with a fixed size may not fit in what's left after a variable-size field
+        *max_size = max_size.saturating_sub(size);
+    }
+}
+
+/// Tracks which fields of a derived struct being generated have been written, and drops them if
+/// generating a later one panics since `MaybeUninit` won't drop them on its own. The fields are
+/// dropped by a closure so that they may use the struct's generic parameters.
+#[doc(hidden)]
+pub struct InitGuard<F: FnMut(&[bool; N]), const N: usize> {
+    initialized: [bool; N],
+    drop_initialized: F,
+}
+
+impl<F: FnMut(&[bool; N]), const N: usize> InitGuard<F, N> {
+    pub fn new(drop_initialized: F) -> Self {
+        InitGuard {
+            initialized: [false; N],
+            drop_initialized,
+        }
+    }
+
+    /// Writes `value` to `field`, the `index`th field of the struct.
+    ///
+    /// # Safety
+    ///
+    /// `field` must point to the uninitialized field `index` of the struct being generated.
+    #[inline]
+    pub unsafe fn write_field<T>(&mut self, index: usize, field: *mut T, value: T) {
+        // fields of packed structs may not be aligned
+        field.write_unaligned(value);
+        self.initialized[index] = true;
+    }
+
+    /// Called once every field has been written, since ownership passes to the struct
+    pub fn finish(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl<F: FnMut(&[bool; N]), const N: usize> Drop for InitGuard<F, N> {
+    fn drop(&mut self) {
+        (self.drop_initialized)(&self.initialized);
+    }
+}
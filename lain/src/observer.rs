@@ -0,0 +1,286 @@
+//! Hooks into the generation of derived structs' fields.
+//!
+//! A [GenerationObserver] sees the value generated for every field of a struct deriving
+//! [NewFuzzed](crate::traits::NewFuzzed) before it's stored in the struct, and can adjust it in
+//! place or reject it to have the field generated again. This lets an external component such as
+//! a constraint solver steer particular fields (e.g. a checksum or a magic value a branch depends
+//! on) without changing the derived code.
+//!
+//! Observers are registered globally with [register_observer], or for the fields of a single
+//! struct type with [register_type_observer]. Fields are identified by their path from the
+//! outermost struct being generated, e.g. `header.length`.
+//!
+//! ```
+//! use lain::prelude::*;
+//! use lain::observer::{self, GeneratedField, Verdict};
+//! use lain::rand::rngs::SmallRng;
+//! use lain::rand::SeedableRng;
+//! use std::any::Any;
+//!
+//! #[derive(Debug, NewFuzzed)]
+//! struct Header {
+//!     magic: u32,
+//!     length: u16,
+//! }
+//!
+//! let id = observer::register_type_observer::<Header, _>(
+//!     |field: &GeneratedField, value: &mut dyn Any| {
+//!         if let Some(magic) = value.downcast_mut::<u32>() {
+//!             if field.path == "magic" {
+//!                 *magic = 0x7f454c46;
+//!             }
+//!         }
+//!
+//!         Verdict::Accept
+//!     },
+//! );
+//!
+//! let mut mutator = Mutator::new(SmallRng::from_seed([0u8; 16]));
+//! assert_eq!(Header::new_fuzzed(&mut mutator, None).magic, 0x7f454c46);
+//!
+//! observer::unregister_observer(id);
+//! ```
+//!
+//! Values may still be changed after observers have seen them by count fields, version gates, and
+//! fixups. Fields of generic structs aren't observed.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::lazy_static::lazy_static;
+use crate::mutator::Mutator;
+use crate::rand::Rng;
+
+/// How many times a field rejected by an observer is generated again before the last value is
+/// kept anyway
+pub const MAX_REGENERATIONS: usize = 16;
+
+/// Number of observers currently registered, which lets generation skip the registry entirely
+/// when there are none
+static OBSERVER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    static ref REGISTRY: RwLock<Registry> = RwLock::new(Registry::default());
+}
+
+/// Whether an observer accepts a generated value
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Keep the value, including any changes the observer made to it
+    Accept,
+    /// Generate the field again
+    Reject,
+}
+
+/// The field whose value is being observed
+#[derive(Debug, Clone)]
+pub struct GeneratedField<'a> {
+    /// Full name (as given by [std::any::type_name]) of the struct the field belongs to
+    pub container: &'static str,
+    /// Full name of the field's type
+    pub type_name: &'static str,
+    /// Path of the field from the outermost struct being generated, e.g. `header.length`
+    pub path: &'a str,
+    /// How many times the field has already been rejected
+    pub attempt: usize,
+}
+
+/// Inspects and adjusts values generated for the fields of derived structs. See the
+/// [module-level documentation](self).
+pub trait GenerationObserver: Send + Sync {
+    /// Called with each generated field value. Adjustments are made by downcasting `value` to the
+    /// field's type.
+    fn on_generated(&self, field: &GeneratedField, value: &mut dyn Any) -> Verdict;
+}
+
+impl<F> GenerationObserver for F
+where
+    F: Fn(&GeneratedField, &mut dyn Any) -> Verdict + Send + Sync,
+{
+    fn on_generated(&self, field: &GeneratedField, value: &mut dyn Any) -> Verdict {
+        self(field, value)
+    }
+}
+
+/// Identifies a registered observer
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ObserverId(usize);
+
+type ObserverList = Vec<(ObserverId, Arc<dyn GenerationObserver>)>;
+
+#[derive(Default)]
+struct Registry {
+    next_id: usize,
+    global: ObserverList,
+    by_type: HashMap<TypeId, ObserverList>,
+}
+
+impl Registry {
+    fn add(
+        &mut self,
+        container: Option<TypeId>,
+        observer: Arc<dyn GenerationObserver>,
+    ) -> ObserverId {
+        let id = ObserverId(self.next_id);
+        self.next_id += 1;
+
+        match container {
+            Some(container) => self
+                .by_type
+                .entry(container)
+                .or_default()
+                .push((id, observer)),
+            None => self.global.push((id, observer)),
+        }
+
+        OBSERVER_COUNT.fetch_add(1, Ordering::SeqCst);
+
+        id
+    }
+}
+
+/// Registers an observer for the fields of every derived struct
+pub fn register_observer<O>(observer: O) -> ObserverId
+where
+    O: GenerationObserver + 'static,
+{
+    REGISTRY.write().unwrap().add(None, Arc::new(observer))
+}
+
+/// Registers an observer for the fields of `T` only. Fields of structs nested in `T` are seen by
+/// observers registered for their own type.
+pub fn register_type_observer<T, O>(observer: O) -> ObserverId
+where
+    T: 'static,
+    O: GenerationObserver + 'static,
+{
+    REGISTRY
+        .write()
+        .unwrap()
+        .add(Some(TypeId::of::<T>()), Arc::new(observer))
+}
+
+/// Removes a registered observer. Returns `false` if it was already removed.
+pub fn unregister_observer(id: ObserverId) -> bool {
+    let mut registry = REGISTRY.write().unwrap();
+    let registry = &mut *registry;
+
+    let lists = std::iter::once(&mut registry.global).chain(registry.by_type.values_mut());
+    for list in lists {
+        if let Some(index) = list.iter().position(|(observer_id, _)| *observer_id == id) {
+            list.remove(index);
+            OBSERVER_COUNT.fetch_sub(1, Ordering::SeqCst);
+
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Whether any observers are registered
+#[inline(always)]
+pub fn has_observers() -> bool {
+    OBSERVER_COUNT.load(Ordering::Relaxed) != 0
+}
+
+/// Runs the observers for the field of `C` being generated by `mutator` (see
+/// [Mutator::enter_field]) on `value`. Returns whether the value should be kept, which it always
+/// is once it has been rejected [MAX_REGENERATIONS] times.
+#[doc(hidden)]
+pub fn observe_generated<C, T, R>(mutator: &Mutator<R>, value: &mut T, attempt: usize) -> bool
+where
+    C: 'static,
+    T: 'static,
+    R: Rng,
+{
+    if !has_observers() {
+        return true;
+    }
+
+    // observers are called without holding the lock so that they can (un)register observers
+    let observers: Vec<Arc<dyn GenerationObserver>> = {
+        let registry = REGISTRY.read().unwrap();
+
+        registry
+            .global
+            .iter()
+            .chain(
+                registry
+                    .by_type
+                    .get(&TypeId::of::<C>())
+                    .into_iter()
+                    .flatten(),
+            )
+            .map(|(_, observer)| observer.clone())
+            .collect()
+    };
+
+    if observers.is_empty() {
+        return true;
+    }
+
+    let path = mutator.field_path();
+    let field = GeneratedField {
+        container: std::any::type_name::<C>(),
+        type_name: std::any::type_name::<T>(),
+        path: &path,
+        attempt,
+    };
+
+    for observer in observers {
+        if observer.on_generated(&field, value) == Verdict::Reject {
+            return attempt >= MAX_REGENERATIONS;
+        }
+    }
+
+    true
+}
+
+/// Generates the field `name` of `C` with `generate` until the observers and
+/// [adaptive](crate::adaptive) weighting keep the value, tallying the kept value for
+/// [model coverage](crate::model_coverage) if it's enabled. Called by `#[derive(NewFuzzed)]` for the
+/// fields of structs without generic parameters.
+#[doc(hidden)]
+pub fn generate_observed<C, T, R, F>(
+    mutator: &mut Mutator<R>,
+    name: &'static str,
+    mut generate: F,
+) -> T
+where
+    C: 'static,
+    T: 'static,
+    R: Rng,
+    F: FnMut(&mut Mutator<R>) -> T,
+{
+    let observing = has_observers();
+    let adapting = mutator.is_adaptive();
+    let covering = mutator.model_coverage().is_some();
+    if !observing && !adapting && !covering {
+        return generate(mutator);
+    }
+
+    mutator.enter_field(name);
+
+    let mut attempt = 0;
+    let value = loop {
+        let mut value = generate(mutator);
+        if (!observing || observe_generated::<C, T, R>(mutator, &mut value, attempt))
+            && (!adapting || crate::adaptive::accept_generated::<C, T, R>(mutator, &value, attempt))
+        {
+            break value;
+        }
+
+        attempt += 1;
+    };
+
+    if let Some(coverage) = mutator.model_coverage() {
+        coverage.record_value(std::any::type_name::<C>(), mutator.field_path(), &value);
+    }
+
+    mutator.exit_field();
+
+    value
+}
@@ -0,0 +1,162 @@
+//! Reusable byte buffers for fuzzer threads.
+//!
+//! Allocating fresh buffers every iteration is a large share of the time spent on small messages.
+//! A [BufferPool] hands out cleared buffers which keep the capacity they grew to in earlier
+//! iterations. Every [FuzzerContext](crate::driver::FuzzerContext) has its own pool, so reserving
+//! and restoring buffers never takes a lock.
+//!
+//! ```
+//! use lain::pool::BufferPool;
+//!
+//! let mut pool = BufferPool::new();
+//!
+//! let mut message = pool.reserve(64);
+//! message.extend_from_slice(b"hello");
+//! pool.restore(message);
+//!
+//! // the next buffer reuses the first one's allocation
+//! let message = pool.reserve(64);
+//! assert!(message.is_empty() && message.capacity() >= 64);
+//! assert_eq!(pool.stats().reused, 1);
+//! ```
+
+/// Default for [BufferPool::max_buffers]
+pub const DEFAULT_MAX_BUFFERS: usize = 32;
+
+/// Default for [BufferPool::max_capacity]
+pub const DEFAULT_MAX_CAPACITY: usize = 1 << 20;
+
+/// Counters describing how well a [BufferPool] avoids allocations
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Buffers handed out by [BufferPool::reserve]
+    pub reserved: usize,
+    /// Reserved buffers which were taken from the pool instead of being newly allocated
+    pub reused: usize,
+    /// Buffers given back with [BufferPool::restore]
+    pub restored: usize,
+    /// Restored buffers which were dropped because the pool was full or they were too large
+    pub discarded: usize,
+}
+
+impl PoolStats {
+    /// Reserved buffers which had to be newly allocated
+    pub fn allocated(&self) -> usize {
+        self.reserved - self.reused
+    }
+
+    /// Returns the change in every counter since `earlier` was taken from the same pool
+    pub fn since(&self, earlier: &PoolStats) -> PoolStats {
+        PoolStats {
+            reserved: self.reserved.saturating_sub(earlier.reserved),
+            reused: self.reused.saturating_sub(earlier.reused),
+            restored: self.restored.saturating_sub(earlier.restored),
+            discarded: self.discarded.saturating_sub(earlier.discarded),
+        }
+    }
+}
+
+/// A set of byte buffers whose allocations are reused
+#[derive(Debug)]
+pub struct BufferPool {
+    free: Vec<Vec<u8>>,
+    max_buffers: usize,
+    max_capacity: usize,
+    stats: PoolStats,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        BufferPool::new()
+    }
+}
+
+impl BufferPool {
+    /// Creates a pool which keeps up to [DEFAULT_MAX_BUFFERS] buffers of at most
+    /// [DEFAULT_MAX_CAPACITY] bytes each
+    pub fn new() -> BufferPool {
+        BufferPool::with_limits(DEFAULT_MAX_BUFFERS, DEFAULT_MAX_CAPACITY)
+    }
+
+    /// Creates a pool which keeps up to `max_buffers` buffers. Buffers whose capacity grew past
+    /// `max_capacity` are dropped when they're restored so that one unusually large input doesn't
+    /// pin its memory for the rest of the campaign.
+    pub fn with_limits(max_buffers: usize, max_capacity: usize) -> BufferPool {
+        BufferPool {
+            free: Vec::new(),
+            max_buffers,
+            max_capacity,
+            stats: PoolStats::default(),
+        }
+    }
+
+    pub fn max_buffers(&self) -> usize {
+        self.max_buffers
+    }
+
+    pub fn max_capacity(&self) -> usize {
+        self.max_capacity
+    }
+
+    /// Takes an empty buffer with a capacity of at least `min_capacity` from the pool, or
+    /// allocates one if the pool is empty. Buffers should be given back with
+    /// [BufferPool::restore] once they're no longer needed.
+    pub fn reserve(&mut self, min_capacity: usize) -> Vec<u8> {
+        self.stats.reserved += 1;
+
+        // prefer a buffer which is already large enough, and grow the largest one otherwise
+        let index = self
+            .free
+            .iter()
+            .position(|buffer| buffer.capacity() >= min_capacity)
+            .or_else(|| (0..self.free.len()).max_by_key(|&index| self.free[index].capacity()));
+
+        match index {
+            Some(index) => {
+                self.stats.reused += 1;
+
+                let mut buffer = self.free.swap_remove(index);
+                buffer.reserve(min_capacity);
+
+                buffer
+            }
+            None => Vec::with_capacity(min_capacity),
+        }
+    }
+
+    /// Gives a buffer back to the pool. Its contents are cleared.
+    pub fn restore(&mut self, mut buffer: Vec<u8>) {
+        self.stats.restored += 1;
+
+        if self.free.len() >= self.max_buffers || buffer.capacity() > self.max_capacity {
+            self.stats.discarded += 1;
+            return;
+        }
+
+        buffer.clear();
+        self.free.push(buffer);
+    }
+
+    /// Number of buffers waiting to be reserved
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+
+    /// Total capacity of the buffers waiting to be reserved
+    pub fn pooled_bytes(&self) -> usize {
+        self.free.iter().map(Vec::capacity).sum()
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        self.stats
+    }
+
+    /// Drops every pooled buffer
+    pub fn clear(&mut self) {
+        self.free.clear();
+    }
+}
@@ -0,0 +1,143 @@
+//! Pipelines for transforming serialized data before it's handed to the target.
+//!
+//! Most real targets wrap their payloads in some encoding layer (compression, encryption,
+//! base64, record framing, etc.). Rather than reimplementing this in every harness, stages can
+//! be registered with the [FuzzerDriver][crate::driver::FuzzerDriver] and are applied when data
+//! is serialized through [FuzzerContext::serialize][crate::driver::FuzzerContext::serialize].
+
+use crate::traits::Postprocess;
+#[cfg(feature = "base64_support")]
+use crate::types::{Base64, TextEncoding};
+
+use std::fmt;
+use std::sync::Arc;
+
+/// An ordered list of [Postprocess] stages. Data flows through the stages in the order they
+/// were added.
+#[derive(Clone, Default)]
+pub struct PostprocessPipeline {
+    stages: Vec<Arc<dyn Postprocess>>,
+}
+
+impl PostprocessPipeline {
+    pub fn new() -> PostprocessPipeline {
+        PostprocessPipeline::default()
+    }
+
+    /// Appends a stage to the end of the pipeline
+    pub fn add_stage<P: Postprocess + 'static>(&mut self, stage: P) {
+        self.stages.push(Arc::new(stage));
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Runs `data` through every stage of the pipeline, replacing its contents with the output
+    /// of the final stage. `scratch` is used as an intermediate buffer so that its allocation can
+    /// be reused across calls.
+    pub fn run(&self, data: &mut Vec<u8>, scratch: &mut Vec<u8>) {
+        for stage in self.stages.iter() {
+            scratch.clear();
+            stage.postprocess(data, scratch);
+            std::mem::swap(data, scratch);
+        }
+    }
+}
+
+impl fmt::Debug for PostprocessPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PostprocessPipeline")
+            .field("stages", &self.stages.len())
+            .finish()
+    }
+}
+
+/// Any closure with the right signature can be used as a stage
+impl<F> Postprocess for F
+where
+    F: Fn(&[u8], &mut Vec<u8>) + Send + Sync,
+{
+    fn postprocess(&self, input: &[u8], output: &mut Vec<u8>) {
+        (self)(input, output)
+    }
+}
+
+/// XORs the data with a repeating key
+#[derive(Debug, Clone)]
+pub struct Xor {
+    key: Vec<u8>,
+}
+
+impl Xor {
+    pub fn new(key: &[u8]) -> Xor {
+        if key.is_empty() {
+            panic!("XOR key cannot be empty");
+        }
+
+        Xor { key: key.to_vec() }
+    }
+}
+
+impl Postprocess for Xor {
+    fn postprocess(&self, input: &[u8], output: &mut Vec<u8>) {
+        output.extend(
+            input
+                .iter()
+                .zip(self.key.iter().cycle())
+                .map(|(b, k)| b ^ k),
+        );
+    }
+}
+
+/// Compresses the data as a zlib stream
+#[cfg(feature = "zlib_support")]
+#[derive(Debug, Clone, Copy)]
+pub struct ZlibCompress {
+    level: u32,
+}
+
+#[cfg(feature = "zlib_support")]
+impl ZlibCompress {
+    /// Creates a new stage with the given compression level (0-9)
+    pub fn new(level: u32) -> ZlibCompress {
+        ZlibCompress { level }
+    }
+}
+
+#[cfg(feature = "zlib_support")]
+impl Default for ZlibCompress {
+    fn default() -> Self {
+        ZlibCompress::new(6)
+    }
+}
+
+#[cfg(feature = "zlib_support")]
+impl Postprocess for ZlibCompress {
+    fn postprocess(&self, input: &[u8], output: &mut Vec<u8>) {
+        use flate2::write::ZlibEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let mut encoder = ZlibEncoder::new(output, Compression::new(self.level));
+        // writing to a Vec can't fail
+        encoder.write_all(input).ok();
+        encoder.finish().ok();
+    }
+}
+
+/// Encodes the data as standard base64
+#[cfg(feature = "base64_support")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Base64Encode;
+
+#[cfg(feature = "base64_support")]
+impl Postprocess for Base64Encode {
+    fn postprocess(&self, input: &[u8], output: &mut Vec<u8>) {
+        Base64::encode(input, output);
+    }
+}
@@ -1,19 +1,29 @@
 #[doc(no_inline)]
 pub use lain_derive::{
-    BinarySerialize, FixupChildren, FuzzerObject, Mutatable, NewFuzzed, PostFuzzerIteration,
-    ToPrimitiveU16, ToPrimitiveU32, ToPrimitiveU64, ToPrimitiveU8, VariableSizeObject,
+    BinaryDeserialize, BinarySerialize, Enumerable, FixupChildren, FuzzedDebug, FuzzerObject,
+    Mutatable, NewFuzzed, PostFuzzerIteration, StructuredDiff, ToPrimitiveU16, ToPrimitiveU32,
+    ToPrimitiveU64, ToPrimitiveU8, VariableSizeObject,
 };
 
 #[doc(no_inline)]
 pub use crate::byteorder::{BigEndian, LittleEndian};
 #[doc(no_inline)]
+pub use crate::corpus::Corpus;
+#[doc(no_inline)]
 pub use crate::log::*;
 #[doc(no_inline)]
-pub use crate::mutator::{Mutator, MutatorMode};
+pub use crate::mutator::{
+    DangerScore, FieldCategory, GenerationProfile, MutateOptions, Mutator, MutatorConfig,
+    MutatorMode, NumericStrategy, ProtocolVersion, StrategyMix, StringStrategy,
+};
+#[doc(no_inline)]
+pub use crate::stream::SerializeStream;
 #[doc(no_inline)]
 pub use crate::traits::*;
 #[doc(no_inline)]
 pub use crate::types::*;
+#[doc(no_inline)]
+pub use crate::undo::UndoLog;
 
 #[doc(no_inline)]
 pub use crate::rand::distributions::Distribution;
@@ -0,0 +1,872 @@
+//! Structure-aware fuzzing of protobuf messages described by a compiled schema.
+//!
+//! A [DescriptorPool] is read at runtime from a `FileDescriptorSet`, such as the output of
+//! `protoc --include_imports --descriptor_set_out=api.desc api.proto`. [DynamicMessage] then
+//! generates, mutates, and serializes messages of any type in the pool without each message
+//! having to be modeled by hand. The root message type is picked by implementing
+//! [MessageSchema] on a marker type:
+//!
+//! ```compile_fail
+//! lazy_static! {
+//!     static ref POOL: DescriptorPool =
+//!         DescriptorPool::decode(include_bytes!("api.desc")).unwrap();
+//! }
+//!
+//! struct LoginRequest;
+//!
+//! impl MessageSchema for LoginRequest {
+//!     fn pool() -> &'static DescriptorPool {
+//!         &POOL
+//!     }
+//!
+//!     fn message_name() -> &'static str {
+//!         "api.LoginRequest"
+//!     }
+//! }
+//!
+//! let mut request = DynamicMessage::<LoginRequest>::new_fuzzed(&mut mutator, None);
+//! request.mutate(&mut mutator, None);
+//! ```
+//!
+//! Messages are always written in the protobuf wire format, so the byte order given to
+//! [BinarySerialize::binary_serialize] is ignored.
+
+use crate::mutator::Mutator;
+use crate::rand::seq::SliceRandom;
+use crate::rand::Rng;
+use crate::traits::*;
+use crate::types::*;
+use byteorder::ByteOrder;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+
+/// Nested messages deeper than this are left out when generating, which keeps recursive
+/// messages from growing without bound
+const MAX_DEPTH: usize = 8;
+
+/// The max number of elements generated for a repeated field. This is halved for each level of
+/// nesting for repeated messages, since their size otherwise grows exponentially with depth.
+const MAX_REPEATED: usize = 8;
+
+/// The cardinality of a message field
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Label {
+    Optional,
+    Required,
+    Repeated,
+}
+
+/// The type of a message field
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldKind {
+    Double,
+    Float,
+    Int32,
+    Int64,
+    UInt32,
+    UInt64,
+    SInt32,
+    SInt64,
+    Fixed32,
+    Fixed64,
+    SFixed32,
+    SFixed64,
+    Bool,
+    String,
+    Bytes,
+    /// An enum, identified by its fully-qualified name
+    Enum(String),
+    /// A message, identified by its fully-qualified name
+    Message(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescriptor {
+    pub name: String,
+    pub number: u32,
+    pub label: Label,
+    pub kind: FieldKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageDescriptor {
+    /// Fully-qualified name of the message, e.g. `api.LoginRequest`
+    pub name: String,
+    pub fields: Vec<FieldDescriptor>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDescriptor {
+    /// Fully-qualified name of the enum, e.g. `api.Status`
+    pub name: String,
+    /// The declared values of the enum
+    pub values: Vec<i32>,
+}
+
+/// Every message and enum declared in a `FileDescriptorSet`
+#[derive(Debug, Clone, Default)]
+pub struct DescriptorPool {
+    messages: HashMap<String, MessageDescriptor>,
+    enums: HashMap<String, EnumDescriptor>,
+}
+
+impl DescriptorPool {
+    /// Reads a serialized `FileDescriptorSet`. Fails if the data is malformed, if a field
+    /// references a type which isn't in the set (make sure to pass `--include_imports` to
+    /// `protoc`), or if a message uses groups, which aren't supported.
+    pub fn decode(data: &[u8]) -> io::Result<DescriptorPool> {
+        let mut pool = DescriptorPool::default();
+
+        let mut reader = WireReader::new(data);
+        while let Some((number, wire_type)) = reader.read_key()? {
+            match number {
+                1 => pool.decode_file(reader.read_bytes()?)?,
+                _ => reader.skip(wire_type)?,
+            }
+        }
+
+        pool.validate()?;
+
+        Ok(pool)
+    }
+
+    /// Looks up a message by its fully-qualified name, e.g. `api.LoginRequest`
+    pub fn message(&self, name: &str) -> Option<&MessageDescriptor> {
+        self.messages.get(name)
+    }
+
+    /// Looks up an enum by its fully-qualified name, e.g. `api.Status`
+    pub fn enumeration(&self, name: &str) -> Option<&EnumDescriptor> {
+        self.enums.get(name)
+    }
+
+    /// The fully-qualified names of all messages in the pool
+    pub fn message_names(&self) -> impl Iterator<Item = &str> {
+        self.messages.keys().map(|name| name.as_str())
+    }
+
+    fn decode_file(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut package = String::new();
+        let mut messages = Vec::new();
+        let mut enums = Vec::new();
+
+        let mut reader = WireReader::new(data);
+        while let Some((number, wire_type)) = reader.read_key()? {
+            match number {
+                2 => package = reader.read_string()?,
+                4 => messages.push(reader.read_bytes()?),
+                5 => enums.push(reader.read_bytes()?),
+                _ => reader.skip(wire_type)?,
+            }
+        }
+
+        for message in messages {
+            self.decode_message(&package, message)?;
+        }
+
+        for enumeration in enums {
+            self.decode_enum(&package, enumeration)?;
+        }
+
+        Ok(())
+    }
+
+    fn decode_message(&mut self, scope: &str, data: &[u8]) -> io::Result<()> {
+        let mut name = String::new();
+        let mut fields = Vec::new();
+        let mut nested_messages = Vec::new();
+        let mut nested_enums = Vec::new();
+
+        let mut reader = WireReader::new(data);
+        while let Some((number, wire_type)) = reader.read_key()? {
+            match number {
+                1 => name = reader.read_string()?,
+                2 => fields.push(decode_field(reader.read_bytes()?)?),
+                3 => nested_messages.push(reader.read_bytes()?),
+                4 => nested_enums.push(reader.read_bytes()?),
+                _ => reader.skip(wire_type)?,
+            }
+        }
+
+        let name = qualified_name(scope, &name);
+
+        for message in nested_messages {
+            self.decode_message(&name, message)?;
+        }
+
+        for enumeration in nested_enums {
+            self.decode_enum(&name, enumeration)?;
+        }
+
+        self.messages
+            .insert(name.clone(), MessageDescriptor { name, fields });
+
+        Ok(())
+    }
+
+    fn decode_enum(&mut self, scope: &str, data: &[u8]) -> io::Result<()> {
+        let mut name = String::new();
+        let mut values = Vec::new();
+
+        let mut reader = WireReader::new(data);
+        while let Some((number, wire_type)) = reader.read_key()? {
+            match number {
+                1 => name = reader.read_string()?,
+                2 => {
+                    let mut value_reader = WireReader::new(reader.read_bytes()?);
+                    while let Some((number, wire_type)) = value_reader.read_key()? {
+                        match number {
+                            2 => values.push(value_reader.read_varint()? as i32),
+                            _ => value_reader.skip(wire_type)?,
+                        }
+                    }
+                }
+                _ => reader.skip(wire_type)?,
+            }
+        }
+
+        let name = qualified_name(scope, &name);
+        self.enums
+            .insert(name.clone(), EnumDescriptor { name, values });
+
+        Ok(())
+    }
+
+    /// Makes sure that every type referenced by a field exists in the pool
+    fn validate(&self) -> io::Result<()> {
+        for message in self.messages.values() {
+            for field in &message.fields {
+                let resolved = match field.kind {
+                    FieldKind::Message(ref name) => self.messages.contains_key(name),
+                    FieldKind::Enum(ref name) => self.enums.contains_key(name),
+                    _ => true,
+                };
+
+                if !resolved {
+                    return Err(malformed(format!(
+                        "{}.{} references a type which isn't in the descriptor set",
+                        message.name, field.name
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn decode_field(data: &[u8]) -> io::Result<FieldDescriptor> {
+    let mut name = String::new();
+    let mut number = 0;
+    let mut label = 1;
+    let mut ty = 0;
+    let mut type_name = String::new();
+
+    let mut reader = WireReader::new(data);
+    while let Some((field_number, wire_type)) = reader.read_key()? {
+        match field_number {
+            1 => name = reader.read_string()?,
+            3 => number = reader.read_varint()? as u32,
+            4 => label = reader.read_varint()?,
+            5 => ty = reader.read_varint()?,
+            6 => type_name = reader.read_string()?,
+            _ => reader.skip(wire_type)?,
+        }
+    }
+
+    // type names are fully-qualified by protoc, with a leading dot
+    let type_name = type_name.trim_start_matches('.').to_string();
+
+    let kind = match ty {
+        1 => FieldKind::Double,
+        2 => FieldKind::Float,
+        3 => FieldKind::Int64,
+        4 => FieldKind::UInt64,
+        5 => FieldKind::Int32,
+        6 => FieldKind::Fixed64,
+        7 => FieldKind::Fixed32,
+        8 => FieldKind::Bool,
+        9 => FieldKind::String,
+        11 => FieldKind::Message(type_name),
+        12 => FieldKind::Bytes,
+        13 => FieldKind::UInt32,
+        14 => FieldKind::Enum(type_name),
+        15 => FieldKind::SFixed32,
+        16 => FieldKind::SFixed64,
+        17 => FieldKind::SInt32,
+        18 => FieldKind::SInt64,
+        10 => {
+            return Err(malformed(format!(
+                "field `{}` is a group, which isn't supported",
+                name
+            )))
+        }
+        other => {
+            return Err(malformed(format!(
+                "field `{}` has unknown type {}",
+                name, other
+            )))
+        }
+    };
+
+    let label = match label {
+        2 => Label::Required,
+        3 => Label::Repeated,
+        _ => Label::Optional,
+    };
+
+    Ok(FieldDescriptor {
+        name,
+        number,
+        label,
+        kind,
+    })
+}
+
+fn qualified_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+fn malformed(message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("malformed descriptor set: {}", message),
+    )
+}
+
+/// Reads the protobuf wire format
+struct WireReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> WireReader<'a> {
+    fn new(data: &'a [u8]) -> WireReader<'a> {
+        WireReader { data, position: 0 }
+    }
+
+    /// Reads the next field number and wire type, or `None` at the end of the data
+    fn read_key(&mut self) -> io::Result<Option<(u64, u8)>> {
+        if self.position == self.data.len() {
+            return Ok(None);
+        }
+
+        let key = self.read_varint()?;
+
+        Ok(Some((key >> 3, (key & 7) as u8)))
+    }
+
+    fn read_varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| malformed("truncated varint".to_string()))?;
+            self.position += 1;
+
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(malformed("varint is too long".to_string()))
+    }
+
+    fn read_bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.read_varint()? as usize;
+        self.take(len)
+    }
+
+    fn read_string(&mut self) -> io::Result<String> {
+        let bytes = self.read_bytes()?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| malformed("invalid UTF-8 string".to_string()))
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() - self.position < len {
+            return Err(malformed("truncated field".to_string()));
+        }
+
+        let bytes = &self.data[self.position..self.position + len];
+        self.position += len;
+
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, wire_type: u8) -> io::Result<()> {
+        match wire_type {
+            0 => self.read_varint().map(|_| ()),
+            1 => self.take(8).map(|_| ()),
+            2 => self.read_bytes().map(|_| ()),
+            5 => self.take(4).map(|_| ()),
+            other => Err(malformed(format!("unsupported wire type {}", other))),
+        }
+    }
+}
+
+/// The value of a single message field, or a single element of a repeated field
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    /// Any signed integer field (`int32`, `sint64`, `sfixed32`, etc.)
+    Int(i64),
+    /// Any unsigned integer field (`uint32`, `fixed64`, etc.)
+    UInt(u64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Enum(i32),
+    Message(MessageValue),
+}
+
+/// A message whose fields are described by a [MessageDescriptor]
+#[derive(Clone, PartialEq)]
+pub struct MessageValue {
+    descriptor: &'static MessageDescriptor,
+    /// The values of each field, in the same order as the descriptor's fields. Fields which
+    /// aren't present are empty.
+    fields: Vec<Vec<Value>>,
+}
+
+impl MessageValue {
+    /// Creates a message with none of its fields present
+    pub fn new(descriptor: &'static MessageDescriptor) -> MessageValue {
+        MessageValue {
+            descriptor,
+            fields: vec![Vec::new(); descriptor.fields.len()],
+        }
+    }
+
+    pub fn descriptor(&self) -> &'static MessageDescriptor {
+        self.descriptor
+    }
+
+    /// Returns the values of the field named `name`. Singular fields have at most 1 value.
+    pub fn get(&self, name: &str) -> Option<&[Value]> {
+        self.field_index(name)
+            .map(|idx| self.fields[idx].as_slice())
+    }
+
+    /// Returns the values of the field named `name` so that they can be fixed up
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Vec<Value>> {
+        self.field_index(name).map(move |idx| &mut self.fields[idx])
+    }
+
+    fn field_index(&self, name: &str) -> Option<usize> {
+        self.descriptor.fields.iter().position(|f| f.name == name)
+    }
+
+    fn encode<W: Write>(&self, buffer: &mut W) -> io::Result<usize> {
+        let mut bytes_written = 0;
+
+        for (field, values) in self.descriptor.fields.iter().zip(self.fields.iter()) {
+            for value in values {
+                let key = (u64::from(field.number) << 3) | wire_type(&field.kind, value);
+                bytes_written += write_varint(buffer, key)?;
+                bytes_written += write_value(buffer, &field.kind, value)?;
+            }
+        }
+
+        Ok(bytes_written)
+    }
+
+    fn encoded_len(&self) -> usize {
+        let mut counter = ByteCounter(0);
+        self.encode(&mut counter).unwrap();
+
+        counter.0
+    }
+}
+
+impl fmt::Debug for MessageValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = f.debug_struct(&self.descriptor.name);
+        for (field, values) in self.descriptor.fields.iter().zip(self.fields.iter()) {
+            s.field(&field.name, values);
+        }
+
+        s.finish()
+    }
+}
+
+fn wire_type(kind: &FieldKind, value: &Value) -> u64 {
+    match (kind, value) {
+        (FieldKind::SFixed32, Value::Int(_))
+        | (FieldKind::Fixed32, Value::UInt(_))
+        | (_, Value::Float(_)) => 5,
+        (FieldKind::SFixed64, Value::Int(_))
+        | (FieldKind::Fixed64, Value::UInt(_))
+        | (_, Value::Double(_)) => 1,
+        (_, Value::String(_)) | (_, Value::Bytes(_)) | (_, Value::Message(_)) => 2,
+        _ => 0,
+    }
+}
+
+fn write_value<W: Write>(buffer: &mut W, kind: &FieldKind, value: &Value) -> io::Result<usize> {
+    let fixed = |buffer: &mut W, bytes: &[u8]| buffer.write_all(bytes).map(|_| bytes.len());
+
+    match *value {
+        Value::Bool(b) => write_varint(buffer, b as u64),
+        Value::Int(n) => match *kind {
+            FieldKind::SFixed32 => fixed(buffer, &(n as i32).to_le_bytes()),
+            FieldKind::SFixed64 => fixed(buffer, &n.to_le_bytes()),
+            FieldKind::SInt32 | FieldKind::SInt64 => {
+                write_varint(buffer, ((n << 1) ^ (n >> 63)) as u64)
+            }
+            // negative int32s are sign-extended to 64 bits on the wire
+            _ => write_varint(buffer, n as u64),
+        },
+        Value::UInt(n) => match *kind {
+            FieldKind::Fixed32 => fixed(buffer, &(n as u32).to_le_bytes()),
+            FieldKind::Fixed64 => fixed(buffer, &n.to_le_bytes()),
+            _ => write_varint(buffer, n),
+        },
+        Value::Float(f) => fixed(buffer, &f.to_bits().to_le_bytes()),
+        Value::Double(f) => fixed(buffer, &f.to_bits().to_le_bytes()),
+        Value::String(ref s) => write_length_delimited(buffer, s.as_bytes()),
+        Value::Bytes(ref b) => write_length_delimited(buffer, b),
+        Value::Enum(n) => write_varint(buffer, i64::from(n) as u64),
+        Value::Message(ref m) => {
+            let len = write_varint(buffer, m.encoded_len() as u64)?;
+            Ok(len + m.encode(buffer)?)
+        }
+    }
+}
+
+fn write_varint<W: Write>(buffer: &mut W, mut value: u64) -> io::Result<usize> {
+    let mut bytes = [0u8; 10];
+    let mut len = 0;
+
+    loop {
+        bytes[len] = (value & 0x7f) as u8;
+        value >>= 7;
+        len += 1;
+
+        if value == 0 {
+            break;
+        }
+
+        bytes[len - 1] |= 0x80;
+    }
+
+    buffer.write_all(&bytes[..len])?;
+
+    Ok(len)
+}
+
+fn write_length_delimited<W: Write>(buffer: &mut W, data: &[u8]) -> io::Result<usize> {
+    let len = write_varint(buffer, data.len() as u64)?;
+    buffer.write_all(data)?;
+
+    Ok(len + data.len())
+}
+
+/// Counts the bytes written to it so that the encoded size of a message can be computed
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0 += data.len();
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Picks the message type generated by a [DynamicMessage]
+pub trait MessageSchema {
+    /// The pool holding the message and every type it references
+    fn pool() -> &'static DescriptorPool;
+
+    /// The fully-qualified name of the message, e.g. `api.LoginRequest`
+    fn message_name() -> &'static str;
+}
+
+/// A message of the type picked by `S` which implements [NewFuzzed], [Mutatable], and
+/// [BinarySerialize]. Nested messages are generated and mutated along with their parent.
+pub struct DynamicMessage<S> {
+    message: MessageValue,
+    schema: PhantomData<S>,
+}
+
+impl<S: MessageSchema> DynamicMessage<S> {
+    /// Creates a message with none of its fields present
+    pub fn new() -> DynamicMessage<S> {
+        DynamicMessage {
+            message: MessageValue::new(Self::descriptor()),
+            schema: PhantomData,
+        }
+    }
+
+    pub fn message(&self) -> &MessageValue {
+        &self.message
+    }
+
+    pub fn message_mut(&mut self) -> &mut MessageValue {
+        &mut self.message
+    }
+
+    fn descriptor() -> &'static MessageDescriptor {
+        S::pool().message(S::message_name()).unwrap_or_else(|| {
+            panic!(
+                "message `{}` isn't in the descriptor pool",
+                S::message_name()
+            )
+        })
+    }
+}
+
+impl<S: MessageSchema> Default for DynamicMessage<S> {
+    fn default() -> Self {
+        DynamicMessage::new()
+    }
+}
+
+impl<S> Clone for DynamicMessage<S> {
+    fn clone(&self) -> Self {
+        DynamicMessage {
+            message: self.message.clone(),
+            schema: PhantomData,
+        }
+    }
+}
+
+impl<S> fmt::Debug for DynamicMessage<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+impl<S> PartialEq for DynamicMessage<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.message == other.message
+    }
+}
+
+impl<S: MessageSchema> NewFuzzed for DynamicMessage<S> {
+    type RangeType = u8;
+
+    fn new_fuzzed<R: Rng>(
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) -> Self {
+        DynamicMessage {
+            message: gen_message(S::pool(), Self::descriptor(), mutator, 0),
+            schema: PhantomData,
+        }
+    }
+}
+
+impl<S: MessageSchema> Mutatable for DynamicMessage<S> {
+    fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, _constraints: Option<&Constraints<u8>>) {
+        mutate_message(S::pool(), &mut self.message, mutator, 0);
+    }
+}
+
+impl<S> BinarySerialize for DynamicMessage<S> {
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize> {
+        self.message.encode(buffer)
+    }
+}
+
+impl<S> SerializedSize for DynamicMessage<S> {
+    fn serialized_size(&self) -> usize {
+        self.message.encoded_len()
+    }
+
+    fn min_nonzero_elements_size() -> usize {
+        // an empty message is 0 bytes, but this is used as a divisor
+        1
+    }
+}
+
+impl<S> VariableSizeObject for DynamicMessage<S> {
+    fn is_variable_size() -> bool {
+        true
+    }
+}
+
+fn gen_message<R: Rng>(
+    pool: &'static DescriptorPool,
+    descriptor: &'static MessageDescriptor,
+    mutator: &mut Mutator<R>,
+    depth: usize,
+) -> MessageValue {
+    let fields = descriptor
+        .fields
+        .iter()
+        .map(|field| {
+            if is_message(field) && depth >= MAX_DEPTH {
+                return Vec::new();
+            }
+
+            let count = match field.label {
+                Label::Required => 1,
+                Label::Optional => mutator.gen_range(0, 2),
+                Label::Repeated if is_message(field) => {
+                    mutator.gen_range(0, (MAX_REPEATED >> depth) + 1)
+                }
+                Label::Repeated => mutator.gen_range(0, MAX_REPEATED + 1),
+            };
+
+            (0..count)
+                .map(|_| gen_value(pool, &field.kind, mutator, depth))
+                .collect()
+        })
+        .collect();
+
+    MessageValue { descriptor, fields }
+}
+
+fn gen_value<R: Rng>(
+    pool: &'static DescriptorPool,
+    kind: &FieldKind,
+    mutator: &mut Mutator<R>,
+    depth: usize,
+) -> Value {
+    match *kind {
+        FieldKind::Double => Value::Double(f64::new_fuzzed(mutator, None)),
+        FieldKind::Float => Value::Float(f32::new_fuzzed(mutator, None)),
+        FieldKind::Int32 | FieldKind::SInt32 | FieldKind::SFixed32 => {
+            Value::Int(i64::from(i32::new_fuzzed(mutator, None)))
+        }
+        FieldKind::Int64 | FieldKind::SInt64 | FieldKind::SFixed64 => {
+            Value::Int(i64::new_fuzzed(mutator, None))
+        }
+        FieldKind::UInt32 | FieldKind::Fixed32 => {
+            Value::UInt(u64::from(u32::new_fuzzed(mutator, None)))
+        }
+        FieldKind::UInt64 | FieldKind::Fixed64 => Value::UInt(u64::new_fuzzed(mutator, None)),
+        FieldKind::Bool => Value::Bool(bool::new_fuzzed(mutator, None)),
+        FieldKind::String => {
+            let s = Utf8String::new_fuzzed(mutator, None);
+            Value::String(s.inner.iter().map(|c| c.0).collect())
+        }
+        FieldKind::Bytes => Value::Bytes(Vec::<u8>::new_fuzzed(mutator, None)),
+        FieldKind::Enum(ref name) => Value::Enum(gen_enum_value(pool, name, mutator)),
+        FieldKind::Message(ref name) => {
+            let descriptor = pool.message(name).unwrap();
+            Value::Message(gen_message(pool, descriptor, mutator, depth + 1))
+        }
+    }
+}
+
+/// Picks one of the enum's declared values, or occasionally any `i32`
+fn gen_enum_value<R: Rng>(pool: &DescriptorPool, name: &str, mutator: &mut Mutator<R>) -> i32 {
+    let values = &pool.enumeration(name).unwrap().values;
+
+    if values.is_empty() || mutator.should_pick_invalid_enum() {
+        i32::new_fuzzed(mutator, None)
+    } else {
+        *values.choose(&mut mutator.rng).unwrap()
+    }
+}
+
+/// Mutates a single randomly-picked field of the message: either one of its values is mutated,
+/// or a value is added or removed where the field's label allows it
+fn mutate_message<R: Rng>(
+    pool: &'static DescriptorPool,
+    message: &mut MessageValue,
+    mutator: &mut Mutator<R>,
+    depth: usize,
+) {
+    if message.fields.is_empty() {
+        return;
+    }
+
+    let descriptor = message.descriptor;
+    let idx = mutator.gen_range(0, message.fields.len());
+    let field = &descriptor.fields[idx];
+    let values = &mut message.fields[idx];
+
+    let can_grow = match field.label {
+        Label::Repeated => true,
+        _ => values.is_empty(),
+    } && !(is_message(field) && depth >= MAX_DEPTH);
+    let can_shrink = field.label != Label::Required && !values.is_empty();
+
+    match mutator.gen_range(0, 4) {
+        0 if can_grow => {
+            let value = gen_value(pool, &field.kind, mutator, depth);
+            let position = mutator.gen_range(0, values.len() + 1);
+            values.insert(position, value);
+        }
+        1 if can_shrink => {
+            let position = mutator.gen_range(0, values.len());
+            values.remove(position);
+        }
+        _ if !values.is_empty() => {
+            let position = mutator.gen_range(0, values.len());
+            mutate_value(pool, &field.kind, &mut values[position], mutator, depth);
+        }
+        _ if can_grow => {
+            let value = gen_value(pool, &field.kind, mutator, depth);
+            values.push(value);
+        }
+        _ => {}
+    }
+}
+
+fn mutate_value<R: Rng>(
+    pool: &'static DescriptorPool,
+    kind: &FieldKind,
+    value: &mut Value,
+    mutator: &mut Mutator<R>,
+    depth: usize,
+) {
+    match *value {
+        Value::Bool(ref mut b) => b.mutate(mutator, None),
+        // signed values are mutated through their bit patterns, which are what end up on the wire
+        Value::Int(ref mut n) => match *kind {
+            FieldKind::Int32 | FieldKind::SInt32 | FieldKind::SFixed32 => {
+                let mut bits = *n as u32;
+                bits.mutate(mutator, None);
+                *n = i64::from(bits as i32);
+            }
+            _ => {
+                let mut bits = *n as u64;
+                bits.mutate(mutator, None);
+                *n = bits as i64;
+            }
+        },
+        Value::UInt(ref mut n) => match *kind {
+            FieldKind::UInt32 | FieldKind::Fixed32 => {
+                let mut narrow = *n as u32;
+                narrow.mutate(mutator, None);
+                *n = u64::from(narrow);
+            }
+            _ => n.mutate(mutator, None),
+        },
+        Value::Float(ref mut f) => f.mutate(mutator, None),
+        Value::Double(ref mut f) => f.mutate(mutator, None),
+        Value::String(ref mut s) => {
+            let mut string = Utf8String::new(s);
+            string.mutate(mutator, None);
+            *s = string.inner.iter().map(|c| c.0).collect();
+        }
+        Value::Bytes(ref mut b) => b.mutate(mutator, None),
+        Value::Enum(ref mut n) => match *kind {
+            FieldKind::Enum(ref name) => *n = gen_enum_value(pool, name, mutator),
+            _ => n.mutate(mutator, None),
+        },
+        Value::Message(ref mut m) => mutate_message(pool, m, mutator, depth + 1),
+    }
+}
+
+fn is_message(field: &FieldDescriptor) -> bool {
+    matches!(field.kind, FieldKind::Message(_))
+}
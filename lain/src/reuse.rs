@@ -0,0 +1,182 @@
+//! Choosing between mutating corpus entries and generating inputs from scratch.
+//!
+//! Every harness with a corpus decides, each iteration, whether to mutate one of its entries or
+//! to generate a new input. Mutating entries digs deeper into behavior the corpus already
+//! reaches, while fresh inputs find entirely new behavior. A [ReusePolicy] given to the driver
+//! with [FuzzerDriver::set_reuse_policy] makes that decision for every thread: the callback reads
+//! it with [FuzzerContext::input_source], or lets [FuzzerContext::reuse_or_generate] act on it.
+//!
+//! An adaptive policy starts at its `reuse_percentage` and then moves it towards whichever source
+//! has had the better yield of interesting inputs (as judged by the driver's
+//! [FeedbackProvider](crate::traits::FeedbackProvider)) every `adjust_interval` iterations.
+//!
+//! ```
+//! use lain::driver::FuzzerDriver;
+//! use lain::feedback::UniqueResponseFeedback;
+//! use lain::reuse::ReusePolicy;
+//!
+//! let mut driver = FuzzerDriver::<()>::new(1);
+//! driver.set_feedback_provider(UniqueResponseFeedback::new(4));
+//! driver.set_reuse_policy(ReusePolicy {
+//!     reuse_percentage: 80.0,
+//!     ..Default::default()
+//! });
+//! ```
+//!
+//! Since an adaptive percentage depends on the iterations which ran before, the starting
+//! percentage is used in [DriverMode::Reproduce].
+//!
+//! [FuzzerDriver::set_reuse_policy]: crate::driver::FuzzerDriver::set_reuse_policy
+//! [FuzzerContext::input_source]: crate::driver::FuzzerContext::input_source
+//! [FuzzerContext::reuse_or_generate]: crate::driver::FuzzerContext::reuse_or_generate
+//! [DriverMode::Reproduce]: crate::driver::DriverMode::Reproduce
+
+use std::sync::Mutex;
+
+use crate::rand::Rng;
+
+/// Where an iteration's input comes from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InputSource {
+    /// An existing corpus entry is mutated
+    Corpus,
+    /// A new input is generated from scratch
+    Fresh,
+}
+
+impl InputSource {
+    fn index(self) -> usize {
+        match self {
+            InputSource::Corpus => 0,
+            InputSource::Fresh => 1,
+        }
+    }
+}
+
+/// How often iterations reuse a corpus entry rather than generating a new input
+#[derive(Debug, Clone)]
+pub struct ReusePolicy {
+    /// Percentage of iterations which mutate a corpus entry. For an adaptive policy this is the
+    /// starting percentage.
+    pub reuse_percentage: f32,
+    /// Whether the percentage follows the yield of interesting inputs of each source
+    pub adaptive: bool,
+    /// Bounds an adaptive percentage is kept within, so neither source is ever starved
+    pub min_reuse_percentage: f32,
+    pub max_reuse_percentage: f32,
+    /// Number of iterations between adjustments of an adaptive percentage
+    pub adjust_interval: usize,
+}
+
+impl Default for ReusePolicy {
+    fn default() -> Self {
+        ReusePolicy {
+            reuse_percentage: 50.0,
+            adaptive: true,
+            min_reuse_percentage: 10.0,
+            max_reuse_percentage: 90.0,
+            adjust_interval: 1000,
+        }
+    }
+}
+
+impl ReusePolicy {
+    /// A policy which always reuses corpus entries for `reuse_percentage` of iterations
+    pub fn fixed(reuse_percentage: f32) -> ReusePolicy {
+        ReusePolicy {
+            reuse_percentage,
+            adaptive: false,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Yields {
+    reuse_percentage: f32,
+    /// Iterations and interesting inputs of each source since the last adjustment, indexed by
+    /// [InputSource::index]
+    iterations: [usize; 2],
+    interesting: [usize; 2],
+}
+
+/// Picks the [InputSource] of each iteration according to a [ReusePolicy], shared by every
+/// fuzzer thread
+#[derive(Debug)]
+pub struct ReuseScheduler {
+    policy: ReusePolicy,
+    yields: Mutex<Yields>,
+}
+
+impl ReuseScheduler {
+    pub fn new(policy: ReusePolicy) -> ReuseScheduler {
+        let yields = Yields {
+            reuse_percentage: policy.reuse_percentage,
+            iterations: [0; 2],
+            interesting: [0; 2],
+        };
+
+        ReuseScheduler {
+            policy,
+            yields: Mutex::new(yields),
+        }
+    }
+
+    pub fn policy(&self) -> &ReusePolicy {
+        &self.policy
+    }
+
+    /// The current percentage of iterations which reuse a corpus entry
+    pub fn reuse_percentage(&self) -> f32 {
+        self.yields.lock().unwrap().reuse_percentage
+    }
+
+    /// Picks the source of an iteration's input
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> InputSource {
+        self.pick_with(rng, self.reuse_percentage())
+    }
+
+    /// Picks the source of an iteration's input using the policy's starting percentage
+    pub(crate) fn pick_initial<R: Rng>(&self, rng: &mut R) -> InputSource {
+        self.pick_with(rng, self.policy.reuse_percentage)
+    }
+
+    fn pick_with<R: Rng>(&self, rng: &mut R, reuse_percentage: f32) -> InputSource {
+        if rng.gen_range(0.0f32, 100.0) < reuse_percentage {
+            InputSource::Corpus
+        } else {
+            InputSource::Fresh
+        }
+    }
+
+    /// Records whether an iteration whose input came from `source` was interesting, adjusting an
+    /// adaptive percentage once `adjust_interval` iterations have been recorded
+    pub fn record(&self, source: InputSource, interesting: bool) {
+        let mut yields = self.yields.lock().unwrap();
+        yields.iterations[source.index()] += 1;
+        yields.interesting[source.index()] += interesting as usize;
+
+        if !self.policy.adaptive
+            || yields.iterations.iter().sum::<usize>() < self.policy.adjust_interval
+        {
+            return;
+        }
+
+        // each source's yield is smoothed so that one which was barely tried still gets a
+        // chance, and the percentage only moves halfway to the target so it doesn't swing
+        let yield_of = |source: InputSource| {
+            (yields.interesting[source.index()] as f32 + 1.0)
+                / (yields.iterations[source.index()] as f32 + 2.0)
+        };
+        let reuse_yield = yield_of(InputSource::Corpus);
+        let fresh_yield = yield_of(InputSource::Fresh);
+        let target = 100.0 * reuse_yield / (reuse_yield + fresh_yield);
+
+        let adjusted = (yields.reuse_percentage + target) / 2.0;
+        yields.reuse_percentage = adjusted
+            .max(self.policy.min_reuse_percentage)
+            .min(self.policy.max_reuse_percentage);
+        yields.iterations = [0; 2];
+        yields.interesting = [0; 2];
+    }
+}
@@ -0,0 +1,526 @@
+//! Running risky in-process targets in a forked child.
+//!
+//! A [Sandbox] forks the fuzzer for every call to [Sandbox::run], applies the resource limits and
+//! seccomp filter from its [SandboxConfig] to the child, and runs the given callback there. A
+//! crash, hang, or runaway allocation in the target then only takes down the child, and the
+//! signal which killed it is reported as a [CrashKind].
+//!
+//! ```no_run
+//! use lain::libc;
+//! use lain::sandbox::{Sandbox, SandboxConfig, SeccompFilter};
+//!
+//! fn parse(data: &[u8]) -> Result<(), ()> {
+//!     // the code under test...
+//!     Ok(())
+//! }
+//!
+//! let mut config = SandboxConfig::default();
+//! config.memory_limit = Some(256 << 20);
+//! config.seccomp = Some(SeccompFilter::deny(&[libc::SYS_execve, libc::SYS_socket]));
+//!
+//! let sandbox = Sandbox::new(config);
+//! let status = sandbox.run(|| parse(b"input")).unwrap();
+//! if status.is_crash() {
+//!     println!("crashed: {:?}", status);
+//! }
+//! ```
+//!
+//! The child is a copy of the fuzzer, so the callback can use anything the parent had in memory,
+//! but changes it makes aren't seen by the parent. Shared memory such as a
+//! [CoverageMap](crate::shmem::CoverageMap) is the exception. Only the forking thread exists in
+//! the child, so the callback must not wait on locks other fuzzer threads may have held.
+
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::panic::{self, AssertUnwindSafe};
+use std::process::ExitStatus;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Exit code of a child whose callback returned an error
+const FAILED_EXIT_CODE: libc::c_int = 0x6c;
+
+// classic BPF opcodes and seccomp return values, which the libc crate doesn't provide
+/// `BPF_LD | BPF_W | BPF_ABS`
+const BPF_LD_W_ABS: u16 = 0x20;
+/// `BPF_JMP | BPF_JEQ | BPF_K`
+const BPF_JMP_JEQ_K: u16 = 0x15;
+/// `BPF_JMP | BPF_JGE | BPF_K`
+const BPF_JMP_JGE_K: u16 = 0x35;
+/// `BPF_RET | BPF_K`
+const BPF_RET_K: u16 = 0x06;
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+/// Offsets of `nr` and `arch` in the kernel's `struct seccomp_data`
+const SECCOMP_DATA_NR: u32 = 0;
+const SECCOMP_DATA_ARCH: u32 = 4;
+
+/// `AUDIT_ARCH_*` value of the architecture we're built for. Filters reject syscalls made with
+/// any other calling convention since their numbers mean something else.
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_003e);
+#[cfg(target_arch = "x86")]
+const AUDIT_ARCH: Option<u32> = Some(0x4000_0003);
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: Option<u32> = Some(0xc000_00b7);
+#[cfg(target_arch = "arm")]
+const AUDIT_ARCH: Option<u32> = Some(0x4000_0028);
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "x86",
+    target_arch = "aarch64",
+    target_arch = "arm"
+)))]
+const AUDIT_ARCH: Option<u32> = None;
+
+/// Bit set in the numbers of syscalls made with the x32 ABI, which share `AUDIT_ARCH_X86_64`.
+/// Filters reject them so that they can't be used to get around the rules for their x86_64
+/// counterparts.
+#[cfg(target_arch = "x86_64")]
+const X32_SYSCALL_BIT: Option<u32> = Some(0x4000_0000);
+#[cfg(not(target_arch = "x86_64"))]
+const X32_SYSCALL_BIT: Option<u32> = None;
+
+/// A classic BPF instruction (`struct sock_filter`)
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct SockFilter {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+/// A classic BPF program (`struct sock_fprog`)
+#[repr(C)]
+struct SockFprog {
+    len: libc::c_ushort,
+    filter: *const SockFilter,
+}
+
+/// What happens when the sandboxed callback makes a syscall
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SeccompAction {
+    /// The syscall runs as usual
+    Allow,
+    /// The syscall fails with the given errno without running
+    Errno(u16),
+    /// The child is killed with `SIGSYS`, which is reported as [CrashKind::SeccompViolation]
+    Kill,
+}
+
+impl SeccompAction {
+    fn ret(self) -> u32 {
+        match self {
+            SeccompAction::Allow => SECCOMP_RET_ALLOW,
+            SeccompAction::Errno(errno) => SECCOMP_RET_ERRNO | errno as u32,
+            SeccompAction::Kill => SECCOMP_RET_KILL_PROCESS,
+        }
+    }
+}
+
+/// A seccomp filter deciding which syscalls the sandboxed callback may make, given as the
+/// action for each listed syscall (e.g. `libc::SYS_execve`) and a default for everything else
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeccompFilter {
+    pub default_action: SeccompAction,
+    pub rules: Vec<(libc::c_long, SeccompAction)>,
+}
+
+impl SeccompFilter {
+    /// Allows every syscall except `syscalls`, which kill the child
+    pub fn deny(syscalls: &[libc::c_long]) -> SeccompFilter {
+        SeccompFilter {
+            default_action: SeccompAction::Allow,
+            rules: syscalls
+                .iter()
+                .map(|&syscall| (syscall, SeccompAction::Kill))
+                .collect(),
+        }
+    }
+
+    /// Kills the child on every syscall except `syscalls`. Keep in mind that the callback needs
+    /// at least `exit_group` (and likely memory allocation) to finish cleanly.
+    pub fn allow(syscalls: &[libc::c_long]) -> SeccompFilter {
+        SeccompFilter {
+            default_action: SeccompAction::Kill,
+            rules: syscalls
+                .iter()
+                .map(|&syscall| (syscall, SeccompAction::Allow))
+                .collect(),
+        }
+    }
+
+    /// Sets the action taken for `syscall`, replacing any previous rule for it
+    pub fn rule(mut self, syscall: libc::c_long, action: SeccompAction) -> SeccompFilter {
+        self.rules.retain(|&(s, _)| s != syscall);
+        self.rules.push((syscall, action));
+
+        self
+    }
+
+    /// Compiles the filter to a BPF program. This happens before forking so that the child
+    /// doesn't have to allocate.
+    fn program(&self) -> Vec<SockFilter> {
+        let stmt = |code, k| SockFilter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        };
+        let jeq = |k, jt, jf| SockFilter {
+            code: BPF_JMP_JEQ_K,
+            jt,
+            jf,
+            k,
+        };
+
+        let mut program = Vec::new();
+
+        if let Some(arch) = AUDIT_ARCH {
+            program.push(stmt(BPF_LD_W_ABS, SECCOMP_DATA_ARCH));
+            program.push(jeq(arch, 1, 0));
+            program.push(stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS));
+        }
+
+        program.push(stmt(BPF_LD_W_ABS, SECCOMP_DATA_NR));
+        if let Some(x32) = X32_SYSCALL_BIT {
+            program.push(SockFilter {
+                code: BPF_JMP_JGE_K,
+                jt: 0,
+                jf: 1,
+                k: x32,
+            });
+            program.push(stmt(BPF_RET_K, SECCOMP_RET_KILL_PROCESS));
+        }
+
+        for &(syscall, action) in &self.rules {
+            // skip over the return if this isn't the syscall
+            program.push(jeq(syscall as u32, 0, 1));
+            program.push(stmt(BPF_RET_K, action.ret()));
+        }
+
+        program.push(stmt(BPF_RET_K, self.default_action.ret()));
+
+        program
+    }
+}
+
+/// Settings for the sandboxed child
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    /// How long the callback may run before the child is killed. `None` waits indefinitely.
+    pub timeout: Option<Duration>,
+    /// Maximum size of the child's address space in bytes (`RLIMIT_AS`). Allocations past this
+    /// fail, which aborts Rust targets.
+    pub memory_limit: Option<u64>,
+    /// Maximum CPU time of the child in seconds (`RLIMIT_CPU`)
+    pub cpu_time_limit: Option<u64>,
+    /// Maximum size of files the child writes in bytes (`RLIMIT_FSIZE`)
+    pub file_size_limit: Option<u64>,
+    /// Maximum number of file descriptors the child may have open (`RLIMIT_NOFILE`)
+    pub open_files_limit: Option<u64>,
+    /// Let crashing children write core dumps. Disabled by default since dumping every crash
+    /// is slow and fills the disk.
+    pub core_dumps: bool,
+    /// Filter applied to the child's syscalls before the callback runs
+    pub seccomp: Option<SeccompFilter>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        SandboxConfig {
+            timeout: Some(Duration::from_secs(1)),
+            memory_limit: None,
+            cpu_time_limit: None,
+            file_size_limit: None,
+            open_files_limit: None,
+            core_dumps: false,
+            seccomp: None,
+        }
+    }
+}
+
+/// The kind of crash a signal indicates
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CrashKind {
+    /// `SIGSEGV`
+    SegmentationFault,
+    /// `SIGBUS`
+    BusError,
+    /// `SIGABRT`, which includes panics and failed allocations in Rust targets
+    Abort,
+    /// `SIGILL`, which includes `ud2` traps from e.g. `core::intrinsics::abort`
+    IllegalInstruction,
+    /// `SIGFPE`
+    FloatingPointException,
+    /// `SIGSYS` from a syscall denied by the [SeccompFilter]
+    SeccompViolation,
+    /// `SIGXCPU` from exceeding [SandboxConfig::cpu_time_limit]
+    CpuTimeLimit,
+    /// `SIGXFSZ` from exceeding [SandboxConfig::file_size_limit]
+    FileSizeLimit,
+    /// Any other signal
+    Signal(i32),
+}
+
+impl CrashKind {
+    pub fn from_signal(signal: i32) -> CrashKind {
+        match signal {
+            libc::SIGSEGV => CrashKind::SegmentationFault,
+            libc::SIGBUS => CrashKind::BusError,
+            libc::SIGABRT => CrashKind::Abort,
+            libc::SIGILL => CrashKind::IllegalInstruction,
+            libc::SIGFPE => CrashKind::FloatingPointException,
+            libc::SIGSYS => CrashKind::SeccompViolation,
+            libc::SIGXCPU => CrashKind::CpuTimeLimit,
+            libc::SIGXFSZ => CrashKind::FileSizeLimit,
+            _ => CrashKind::Signal(signal),
+        }
+    }
+}
+
+/// How a sandboxed run ended
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SandboxStatus {
+    /// The callback returned `Ok`
+    Passed,
+    /// The callback returned `Err`
+    Failed,
+    /// The target exited by itself (e.g. by calling `exit`) with the given code
+    Exited(i32),
+    /// The child was killed by a signal
+    Crashed(CrashKind),
+    /// The callback didn't finish within [SandboxConfig::timeout] and the child was killed
+    TimedOut,
+}
+
+impl SandboxStatus {
+    /// Whether the run crashed or hung, as opposed to finishing on its own
+    pub fn is_crash(&self) -> bool {
+        matches!(self, SandboxStatus::Crashed(_) | SandboxStatus::TimedOut)
+    }
+}
+
+/// Runs callbacks in a forked, resource-limited child
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    config: SandboxConfig,
+    program: Vec<SockFilter>,
+}
+
+impl Sandbox {
+    pub fn new(config: SandboxConfig) -> Sandbox {
+        let program = config
+            .seccomp
+            .as_ref()
+            .map(|filter| filter.program())
+            .unwrap_or_default();
+
+        Sandbox { config, program }
+    }
+
+    pub fn config(&self) -> &SandboxConfig {
+        &self.config
+    }
+
+    /// Runs `callback` in a child process and reports how it ended. Panics in the callback
+    /// abort the child and are reported as [CrashKind::Abort].
+    ///
+    /// Returns an error if the child couldn't be forked or sandboxed.
+    pub fn run<F, T, E>(&self, callback: F) -> io::Result<SandboxStatus>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        // the child reports the errno of a failed setup step through this pipe
+        let mut fds = [0 as libc::c_int; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let [error_read, error_write] = fds;
+
+        let pid = unsafe { libc::fork() };
+        if pid < 0 {
+            let error = io::Error::last_os_error();
+            unsafe {
+                libc::close(error_read);
+                libc::close(error_write);
+            }
+
+            return Err(error);
+        }
+
+        if pid == 0 {
+            unsafe {
+                libc::close(error_read);
+            }
+
+            if let Err(error) = self.enter() {
+                let errno = error.raw_os_error().unwrap_or(0).to_ne_bytes();
+                unsafe {
+                    libc::write(error_write, errno.as_ptr() as *const libc::c_void, 4);
+                    libc::_exit(FAILED_EXIT_CODE);
+                }
+            }
+
+            unsafe {
+                libc::close(error_write);
+            }
+
+            let code = match panic::catch_unwind(AssertUnwindSafe(callback)) {
+                Ok(Ok(_)) => 0,
+                Ok(Err(_)) => FAILED_EXIT_CODE,
+                Err(_) => unsafe { libc::abort() },
+            };
+
+            // skip the parent's atexit handlers and buffered output
+            unsafe { libc::_exit(code) }
+        }
+
+        unsafe {
+            libc::close(error_write);
+        }
+
+        let result = wait_timeout(pid, self.config.timeout);
+
+        let mut errno = [0u8; 4];
+        let read = unsafe { libc::read(error_read, errno.as_mut_ptr() as *mut libc::c_void, 4) };
+        unsafe {
+            libc::close(error_read);
+        }
+
+        if read == 4 {
+            return Err(io::Error::from_raw_os_error(i32::from_ne_bytes(errno)));
+        }
+
+        let (status, timed_out) = result?;
+        if timed_out {
+            return Ok(SandboxStatus::TimedOut);
+        }
+
+        let status = ExitStatus::from_raw(status);
+        Ok(match status.code() {
+            Some(0) => SandboxStatus::Passed,
+            Some(FAILED_EXIT_CODE) => SandboxStatus::Failed,
+            Some(code) => SandboxStatus::Exited(code),
+            None => SandboxStatus::Crashed(CrashKind::from_signal(status.signal().unwrap_or(0))),
+        })
+    }
+
+    /// Applies the resource limits and seccomp filter to the current (child) process
+    fn enter(&self) -> io::Result<()> {
+        let config = &self.config;
+
+        if !config.core_dumps {
+            set_rlimit(libc::RLIMIT_CORE as libc::c_int, 0)?;
+        }
+
+        let limits = [
+            (libc::RLIMIT_AS as libc::c_int, config.memory_limit),
+            (libc::RLIMIT_CPU as libc::c_int, config.cpu_time_limit),
+            (libc::RLIMIT_FSIZE as libc::c_int, config.file_size_limit),
+            (libc::RLIMIT_NOFILE as libc::c_int, config.open_files_limit),
+        ];
+
+        for &(resource, limit) in &limits {
+            if let Some(limit) = limit {
+                set_rlimit(resource, limit)?;
+            }
+        }
+
+        if config.seccomp.is_some() {
+            let program = SockFprog {
+                len: self.program.len() as libc::c_ushort,
+                filter: self.program.as_ptr(),
+            };
+
+            // required to install a filter without CAP_SYS_ADMIN
+            if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            if unsafe {
+                libc::prctl(
+                    libc::PR_SET_SECCOMP,
+                    libc::SECCOMP_MODE_FILTER,
+                    &program as *const SockFprog,
+                )
+            } < 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn set_rlimit(resource: libc::c_int, limit: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: limit as libc::rlim_t,
+        rlim_max: limit as libc::rlim_t,
+    };
+
+    if unsafe { libc::setrlimit(resource as _, &limit) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Waits for the child `pid` to exit, killing it if it runs longer than `timeout`. Returns its
+/// wait status and whether it timed out.
+fn wait_timeout(pid: libc::pid_t, timeout: Option<Duration>) -> io::Result<(libc::c_int, bool)> {
+    let mut status = 0;
+
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => {
+            waitpid(pid, &mut status, 0)?;
+            return Ok((status, false));
+        }
+    };
+
+    let start = Instant::now();
+    let mut delay = Duration::from_micros(50);
+
+    loop {
+        if waitpid(pid, &mut status, libc::WNOHANG)? != 0 {
+            return Ok((status, false));
+        }
+
+        if start.elapsed() >= timeout {
+            unsafe {
+                libc::kill(pid, libc::SIGKILL);
+            }
+
+            waitpid(pid, &mut status, 0)?;
+            return Ok((status, true));
+        }
+
+        thread::sleep(delay);
+        delay = std::cmp::min(delay * 2, Duration::from_millis(5));
+    }
+}
+
+/// `waitpid` which retries when interrupted by a signal
+fn waitpid(
+    pid: libc::pid_t,
+    status: &mut libc::c_int,
+    options: libc::c_int,
+) -> io::Result<libc::pid_t> {
+    loop {
+        let result = unsafe { libc::waitpid(pid, status, options) };
+        if result >= 0 {
+            return Ok(result);
+        }
+
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! AFL-style shared memory coverage maps for fuzzing instrumented binaries out of process.
+//!
+//! Targets built with AFL's instrumentation (e.g. `afl-clang-fast`) attach to the shared memory
+//! segment named by the `__AFL_SHM_ID` environment variable at startup and count how often each
+//! edge is hit in it. A [CoverageMap] creates the segment and points a target's [Command] at it.
+//!
+//! When a map is stored in a thread's [FuzzerContext::coverage][crate::driver::FuzzerContext::coverage],
+//! the driver clears it before every iteration and passes it to the feedback provider afterwards,
+//! where [CoverageFeedback][crate::feedback::CoverageFeedback] retains inputs which reach new
+//! edges.
+
+use std::io;
+use std::process::Command;
+use std::ptr;
+use std::slice;
+
+/// Size of the edge map used by AFL's instrumentation unless told otherwise
+pub const DEFAULT_MAP_SIZE: usize = 1 << 16;
+
+/// Environment variable instrumented targets read the shared memory ID from
+pub const SHM_ENV_VAR: &str = "__AFL_SHM_ID";
+
+/// Environment variable telling AFL++ instrumented targets how large the map is
+pub const MAP_SIZE_ENV_VAR: &str = "AFL_MAP_SIZE";
+
+/// A System V shared memory segment holding a target's edge hit counts. The segment is removed
+/// when the map is dropped.
+pub struct CoverageMap {
+    id: libc::c_int,
+    map: *mut u8,
+    size: usize,
+}
+
+// the segment is only ever accessed through the map, which requires &mut for writes
+unsafe impl Send for CoverageMap {}
+
+impl CoverageMap {
+    /// Creates a zeroed map of `size` bytes. Most targets expect [DEFAULT_MAP_SIZE].
+    pub fn new(size: usize) -> io::Result<CoverageMap> {
+        let id = unsafe {
+            libc::shmget(
+                libc::IPC_PRIVATE,
+                size,
+                libc::IPC_CREAT | libc::IPC_EXCL | 0o600,
+            )
+        };
+
+        if id < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let map = unsafe { libc::shmat(id, ptr::null(), 0) };
+        if map as isize == -1 {
+            let error = io::Error::last_os_error();
+            unsafe {
+                libc::shmctl(id, libc::IPC_RMID, ptr::null_mut());
+            }
+
+            return Err(error);
+        }
+
+        Ok(CoverageMap {
+            id,
+            map: map as *mut u8,
+            size,
+        })
+    }
+
+    /// The shared memory ID targets attach to
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// The environment variables which point an instrumented target at this map
+    pub fn env_vars(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (SHM_ENV_VAR, self.id.to_string()),
+            (MAP_SIZE_ENV_VAR, self.size.to_string()),
+        ]
+    }
+
+    /// Sets the environment variables which point an instrumented target at this map
+    pub fn configure<'a>(&self, command: &'a mut Command) -> &'a mut Command {
+        command.envs(self.env_vars())
+    }
+
+    /// Zeroes the hit counts. This should be done before every run of the target.
+    pub fn clear(&mut self) {
+        unsafe {
+            ptr::write_bytes(self.map, 0, self.size);
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.map, self.size) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.map, self.size) }
+    }
+}
+
+impl Drop for CoverageMap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::shmdt(self.map as *const libc::c_void);
+            libc::shmctl(self.id, libc::IPC_RMID, ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! Campaign-wide statistics and exporters for monitoring long-running fuzzing jobs.
+//!
+//! Every [FuzzerDriver][crate::driver::FuzzerDriver] keeps a [CampaignStats] which its fuzzer
+//! threads update with atomics as they run. A [StatsSnapshot] of the counters can be rendered
+//! as a JSON object or in the Prometheus text exposition format, and the exporters in this
+//! module publish snapshots without any glue code in the harness:
+//!
+//! - [JsonLinesExporter] appends a snapshot to a file as one JSON object per line
+//! - [PrometheusExporter] serves the latest snapshot over HTTP for Prometheus to scrape
+
+use crate::pool::PoolStats;
+
+use std::fmt::Write as FmtWrite;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How often exporter threads check whether they've been asked to stop
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Counters shared by all of a driver's fuzzer threads
+#[derive(Debug)]
+pub struct CampaignStats {
+    pub(crate) iterations: AtomicUsize,
+    pub(crate) failed_iterations: AtomicUsize,
+    pub(crate) interesting_inputs: AtomicUsize,
+    pub(crate) crashes: AtomicUsize,
+    pub(crate) bytes_generated: AtomicUsize,
+    buffers_reserved: AtomicUsize,
+    buffers_reused: AtomicUsize,
+    thread_iterations: Vec<AtomicUsize>,
+    shard_index: AtomicUsize,
+    shard_count: AtomicUsize,
+    started: Instant,
+}
+
+impl CampaignStats {
+    pub(crate) fn new(num_threads: usize) -> CampaignStats {
+        CampaignStats {
+            iterations: Default::default(),
+            failed_iterations: Default::default(),
+            interesting_inputs: Default::default(),
+            crashes: Default::default(),
+            bytes_generated: Default::default(),
+            buffers_reserved: Default::default(),
+            buffers_reused: Default::default(),
+            thread_iterations: (0..num_threads).map(|_| AtomicUsize::new(0)).collect(),
+            shard_index: AtomicUsize::new(0),
+            shard_count: AtomicUsize::new(1),
+            started: Instant::now(),
+        }
+    }
+
+    pub(crate) fn set_shard(&self, shard_index: usize, shard_count: usize) {
+        self.shard_index.store(shard_index, Ordering::SeqCst);
+        self.shard_count.store(shard_count, Ordering::SeqCst);
+    }
+
+    /// Records an iteration completed by `thread_index` which produced `input_len` bytes
+    pub(crate) fn record_iteration(&self, thread_index: usize, input_len: usize, failed: bool) {
+        if failed {
+            self.failed_iterations.fetch_add(1, Ordering::SeqCst);
+        }
+
+        self.bytes_generated.fetch_add(input_len, Ordering::Relaxed);
+        self.thread_iterations[thread_index].fetch_add(1, Ordering::Relaxed);
+        self.iterations.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Adds the buffer pool activity of a fuzzer thread's iteration
+    pub(crate) fn record_buffer_pool(&self, stats: &PoolStats) {
+        self.buffers_reserved
+            .fetch_add(stats.reserved, Ordering::Relaxed);
+        self.buffers_reused
+            .fetch_add(stats.reused, Ordering::Relaxed);
+    }
+
+    /// Time since the driver was created
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Reads every counter. The counters are updated independently, so a snapshot taken while
+    /// threads are running may be off by a few iterations between fields.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        let elapsed = self.elapsed();
+        let iterations = self.iterations.load(Ordering::SeqCst);
+        let seconds = duration_secs(elapsed);
+
+        StatsSnapshot {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            elapsed,
+            iterations,
+            failed_iterations: self.failed_iterations.load(Ordering::SeqCst),
+            interesting_inputs: self.interesting_inputs.load(Ordering::SeqCst),
+            crashes: self.crashes.load(Ordering::SeqCst),
+            bytes_generated: self.bytes_generated.load(Ordering::Relaxed),
+            buffers_reserved: self.buffers_reserved.load(Ordering::Relaxed),
+            buffers_reused: self.buffers_reused.load(Ordering::Relaxed),
+            execs_per_sec: if seconds > 0.0 {
+                iterations as f64 / seconds
+            } else {
+                0.0
+            },
+            thread_iterations: self
+                .thread_iterations
+                .iter()
+                .map(|count| count.load(Ordering::Relaxed))
+                .collect(),
+            shard_index: self.shard_index.load(Ordering::SeqCst),
+            shard_count: self.shard_count.load(Ordering::SeqCst),
+        }
+    }
+}
+
+fn duration_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
+
+/// The value of every [CampaignStats] counter at one point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsSnapshot {
+    /// Seconds since the Unix epoch when the snapshot was taken
+    pub timestamp: u64,
+    /// Time since the driver was created
+    pub elapsed: Duration,
+    /// Iterations executed by this shard
+    pub iterations: usize,
+    /// Iterations whose callback returned an error
+    pub failed_iterations: usize,
+    /// Inputs the feedback provider judged interesting
+    pub interesting_inputs: usize,
+    /// Inputs the feedback provider judged to have crashed the target
+    pub crashes: usize,
+    /// Total length of the inputs left in [FuzzerContext::buffer][crate::driver::FuzzerContext::buffer]
+    /// by the callback
+    pub bytes_generated: usize,
+    /// Buffers reserved from the fuzzer threads' [buffer pools][crate::driver::FuzzerContext::pool]
+    pub buffers_reserved: usize,
+    /// Reserved buffers which reused a pooled allocation
+    pub buffers_reused: usize,
+    /// Average iterations per second since the driver was created
+    pub execs_per_sec: f64,
+    /// Iterations executed by each fuzzer thread, indexed by thread
+    pub thread_iterations: Vec<usize>,
+    pub shard_index: usize,
+    pub shard_count: usize,
+}
+
+impl StatsSnapshot {
+    /// Renders the snapshot as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        let thread_iterations = self
+            .thread_iterations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{{\"timestamp\":{},\"elapsed_secs\":{:.3},\"iterations\":{},\"failed_iterations\":{},\
+             \"interesting_inputs\":{},\"crashes\":{},\"bytes_generated\":{},\"buffers_reserved\":{},\
+             \"buffers_reused\":{},\"execs_per_sec\":{:.3},\"thread_iterations\":[{}],\"shard_index\":{},\
+             \"shard_count\":{}}}",
+            self.timestamp,
+            duration_secs(self.elapsed),
+            self.iterations,
+            self.failed_iterations,
+            self.interesting_inputs,
+            self.crashes,
+            self.bytes_generated,
+            self.buffers_reserved,
+            self.buffers_reused,
+            self.execs_per_sec,
+            thread_iterations,
+            self.shard_index,
+            self.shard_count,
+        )
+    }
+
+    /// Renders the snapshot in the Prometheus text exposition format. Every sample is labelled
+    /// with the shard so that shards of the same campaign can be scraped side by side.
+    pub fn to_prometheus(&self) -> String {
+        let shard = format!(
+            "shard=\"{}\",shard_count=\"{}\"",
+            self.shard_index, self.shard_count
+        );
+        let mut output = String::new();
+
+        let counters = [
+            (
+                "lain_iterations_total",
+                "Fuzzing iterations executed",
+                self.iterations,
+            ),
+            (
+                "lain_failed_iterations_total",
+                "Iterations whose callback returned an error",
+                self.failed_iterations,
+            ),
+            (
+                "lain_interesting_inputs_total",
+                "Inputs the feedback provider judged interesting",
+                self.interesting_inputs,
+            ),
+            (
+                "lain_crashes_total",
+                "Inputs the feedback provider judged to have crashed the target",
+                self.crashes,
+            ),
+            (
+                "lain_generated_bytes_total",
+                "Total length of the generated inputs",
+                self.bytes_generated,
+            ),
+            (
+                "lain_buffers_reserved_total",
+                "Buffers reserved from the fuzzer threads' buffer pools",
+                self.buffers_reserved,
+            ),
+            (
+                "lain_buffers_reused_total",
+                "Reserved buffers which reused a pooled allocation",
+                self.buffers_reused,
+            ),
+        ];
+
+        for (name, help, value) in counters.iter() {
+            let _ = writeln!(output, "# HELP {} {}", name, help);
+            let _ = writeln!(output, "# TYPE {} counter", name);
+            let _ = writeln!(output, "{}{{{}}} {}", name, shard, value);
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP lain_thread_iterations_total Fuzzing iterations executed per thread"
+        );
+        let _ = writeln!(output, "# TYPE lain_thread_iterations_total counter");
+        for (thread, count) in self.thread_iterations.iter().enumerate() {
+            let _ = writeln!(
+                output,
+                "lain_thread_iterations_total{{{},thread=\"{}\"}} {}",
+                shard, thread, count
+            );
+        }
+
+        let _ = writeln!(
+            output,
+            "# HELP lain_execs_per_second Average iterations per second"
+        );
+        let _ = writeln!(output, "# TYPE lain_execs_per_second gauge");
+        let _ = writeln!(
+            output,
+            "lain_execs_per_second{{{}}} {}",
+            shard, self.execs_per_sec
+        );
+
+        let _ = writeln!(
+            output,
+            "# HELP lain_uptime_seconds Seconds since the campaign started"
+        );
+        let _ = writeln!(output, "# TYPE lain_uptime_seconds gauge");
+        let _ = writeln!(
+            output,
+            "lain_uptime_seconds{{{}}} {}",
+            shard,
+            duration_secs(self.elapsed)
+        );
+
+        output
+    }
+}
+
+/// Sleeps for `interval`, returning early with `true` if `stop` is set
+fn sleep_until_stopped(stop: &AtomicBool, interval: Duration) -> bool {
+    let deadline = Instant::now() + interval;
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return true;
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+
+        thread::sleep(STOP_POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+/// Periodically appends a [StatsSnapshot::to_json] line to a file from a background thread.
+///
+/// The exporter writes a final snapshot and stops when it's dropped or [JsonLinesExporter::stop]
+/// is called.
+pub struct JsonLinesExporter {
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl JsonLinesExporter {
+    /// Opens `path` for appending and starts writing a snapshot of `stats` every `interval`
+    pub fn start<P: AsRef<Path>>(
+        stats: Arc<CampaignStats>,
+        path: P,
+        interval: Duration,
+    ) -> io::Result<JsonLinesExporter> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let thread = thread::Builder::new()
+            .name(String::from("JSON stats exporter"))
+            .spawn(move || loop {
+                let stopped = sleep_until_stopped(&thread_stop, interval);
+
+                if let Err(e) = write_json_line(&mut file, &stats) {
+                    warn!("could not write campaign stats: {}", e);
+                }
+
+                if stopped {
+                    return;
+                }
+            })?;
+
+        Ok(JsonLinesExporter {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Writes a final snapshot and waits for the exporter thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for JsonLinesExporter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn write_json_line(file: &mut File, stats: &CampaignStats) -> io::Result<()> {
+    let mut line = stats.snapshot().to_json();
+    line.push('\n');
+
+    file.write_all(line.as_bytes())?;
+    file.flush()
+}
+
+/// Serves [StatsSnapshot::to_prometheus] over HTTP from a background thread so that Prometheus
+/// can scrape the campaign directly. Any request path is answered with the metrics, so the
+/// exporter can be pointed at with the default `/metrics` scrape path.
+///
+/// The listener is closed when the exporter is dropped or [PrometheusExporter::stop] is called.
+pub struct PrometheusExporter {
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl PrometheusExporter {
+    /// Binds a listener to `addr` and starts serving snapshots of `stats`
+    pub fn start<A: ToSocketAddrs>(
+        stats: Arc<CampaignStats>,
+        addr: A,
+    ) -> io::Result<PrometheusExporter> {
+        let listener = TcpListener::bind(addr)?;
+        // accept() has to return periodically so the thread notices when it should stop
+        listener.set_nonblocking(true)?;
+
+        let local_addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let thread = thread::Builder::new()
+            .name(String::from("Prometheus stats exporter"))
+            .spawn(move || {
+                while !thread_stop.load(Ordering::SeqCst) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(e) = serve_metrics(stream, &stats) {
+                                debug!("could not serve campaign stats: {}", e);
+                            }
+                        }
+                        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            thread::sleep(STOP_POLL_INTERVAL);
+                        }
+                        Err(e) => {
+                            warn!("stats listener failed: {}", e);
+                            return;
+                        }
+                    }
+                }
+            })?;
+
+        Ok(PrometheusExporter {
+            local_addr,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// The address the exporter is listening on. This is useful when binding to port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Closes the listener and waits for the exporter thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PrometheusExporter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn serve_metrics(mut stream: TcpStream, stats: &CampaignStats) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+
+    // the request itself doesn't matter, but it has to be read before responding or some
+    // clients will see the connection reset
+    let mut request = Vec::new();
+    let mut chunk = [0u8; 512];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+
+        request.extend_from_slice(&chunk[..read]);
+    }
+
+    let body = stats.snapshot().to_prometheus();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
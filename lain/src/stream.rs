@@ -0,0 +1,226 @@
+//! Pull-based serialization of structures too large to hold in memory as a single buffer.
+//!
+//! A [SerializeStream] produces the same bytes as [BinarySerialize::binary_serialize], but
+//! hands them out in chunks sized by the caller. Each chunk is produced by re-running the
+//! serializer and discarding everything before the stream's position, so memory use is bounded
+//! by the chunk size. Skipping is cheap for bulk data such as `Vec<u8>` payloads (which are
+//! written with a single `write_all`), but structures made of many small fields will be walked
+//! once per chunk, so larger chunks should be preferred for those.
+//!
+//! Going the other way, [fuzzed_iter] turns a seed into an endless stream of generated values for
+//! property tests and benchmarks which want ordinary iterator combinators rather than a driver:
+//!
+//! ```
+//! use lain::stream::fuzzed_iter;
+//!
+//! let values: Vec<u32> = fuzzed_iter(0x1234).take(100).collect();
+//! assert_eq!(values, fuzzed_iter::<u32>(0x1234).take(100).collect::<Vec<_>>());
+//! ```
+
+use crate::mutator::Mutator;
+use crate::rand::rngs::StdRng;
+use crate::rand::SeedableRng;
+use crate::traits::{BinarySerialize, NewFuzzed};
+use byteorder::ByteOrder;
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+/// Yields the serialized bytes of `value` in caller-sized chunks. Also implements [Read] so
+/// that it can be used with [io::copy] or anything else that consumes a reader.
+pub struct SerializeStream<'a, T: ?Sized, E> {
+    value: &'a T,
+    position: u64,
+    finished: bool,
+    endian: PhantomData<E>,
+}
+
+impl<'a, T, E> SerializeStream<'a, T, E>
+where
+    T: BinarySerialize + ?Sized,
+    E: ByteOrder,
+{
+    pub fn new(value: &'a T) -> SerializeStream<'a, T, E> {
+        SerializeStream {
+            value,
+            position: 0,
+            finished: false,
+            endian: PhantomData,
+        }
+    }
+
+    /// Number of bytes yielded so far
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Whether all of the value's bytes have been yielded
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Fills `buf` with the next bytes of the serialized value. Returns the number of bytes
+    /// written, which is only less than `buf.len()` for the final chunk and is 0 once the
+    /// stream is finished.
+    pub fn next_chunk(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.finished || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut window = WindowWriter {
+            skip: self.position,
+            buf,
+            filled: 0,
+        };
+
+        match self.value.binary_serialize::<_, E>(&mut window) {
+            Ok(_) => self.finished = true,
+            Err(ref e) if is_window_full(e) => {}
+            Err(e) => return Err(e),
+        }
+
+        self.position += window.filled as u64;
+
+        Ok(window.filled)
+    }
+
+    /// Writes the remainder of the serialized value to `writer`, `chunk_size` bytes at a time.
+    /// Returns the number of bytes written.
+    pub fn write_to<W: Write>(&mut self, writer: &mut W, chunk_size: usize) -> io::Result<u64> {
+        let mut chunk = vec![0u8; std::cmp::max(chunk_size, 1)];
+        let mut written = 0;
+
+        loop {
+            let size = self.next_chunk(&mut chunk)?;
+            if size == 0 {
+                break;
+            }
+
+            writer.write_all(&chunk[..size])?;
+            written += size as u64;
+        }
+
+        Ok(written)
+    }
+}
+
+impl<'a, T, E> Read for SerializeStream<'a, T, E>
+where
+    T: BinarySerialize + ?Sized,
+    E: ByteOrder,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.next_chunk(buf)
+    }
+}
+
+/// Returned by [WindowWriter] to stop the serializer once the chunk has been filled
+#[derive(Debug)]
+struct WindowFull;
+
+impl fmt::Display for WindowFull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "serialization chunk is full")
+    }
+}
+
+impl Error for WindowFull {}
+
+fn is_window_full(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|inner| inner.is::<WindowFull>())
+}
+
+/// Discards the first `skip` bytes written to it and captures as many of the following bytes as
+/// fit in `buf`
+struct WindowWriter<'b> {
+    skip: u64,
+    buf: &'b mut [u8],
+    filled: usize,
+}
+
+impl<'b> Write for WindowWriter<'b> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.skip >= data.len() as u64 {
+            self.skip -= data.len() as u64;
+            return Ok(data.len());
+        }
+
+        let skipped = self.skip as usize;
+        self.skip = 0;
+
+        let remaining = &data[skipped..];
+        let size = std::cmp::min(remaining.len(), self.buf.len() - self.filled);
+        if size == 0 {
+            return Err(io::Error::other(WindowFull));
+        }
+
+        self.buf[self.filled..self.filled + size].copy_from_slice(&remaining[..size]);
+        self.filled += size;
+
+        Ok(skipped + size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An endless stream of fuzzed values, created by [fuzzed_iter]
+pub struct FuzzedIter<T> {
+    mutator: Mutator<StdRng>,
+    seed: u64,
+    index: u64,
+    value: PhantomData<fn() -> T>,
+}
+
+/// Generates values of type `T` forever. Each value gets a fresh iteration of the mutator seeded
+/// from `seed` and its index in the stream, so the same seed always yields the same values and
+/// the value at any index can be regenerated with `fuzzed_iter(seed).nth(index)`.
+pub fn fuzzed_iter<T: NewFuzzed>(seed: u64) -> FuzzedIter<T> {
+    let mut mutator = Mutator::new(StdRng::seed_from_u64(seed));
+    mutator.set_seed(Some(seed));
+
+    FuzzedIter {
+        mutator,
+        seed,
+        index: 0,
+        value: PhantomData,
+    }
+}
+
+impl<T> FuzzedIter<T> {
+    /// Index of the next value the stream will yield
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// The mutator values are generated with, e.g. for setting a
+    /// [generation profile](Mutator::set_profile)
+    pub fn mutator_mut(&mut self) -> &mut Mutator<StdRng> {
+        &mut self.mutator
+    }
+}
+
+impl<T: NewFuzzed> Iterator for FuzzedIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.mutator.rng = StdRng::seed_from_u64(self.seed.wrapping_add(self.index));
+        self.mutator.begin_new_iteration();
+        self.index += 1;
+
+        Some(T::new_fuzzed(&mut self.mutator, None))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<T> {
+        // every value is seeded by its index, so the skipped ones don't need generating
+        self.index = self.index.wrapping_add(n as u64);
+        self.next()
+    }
+}
@@ -0,0 +1,163 @@
+//! Describing the build of the target an input is serialized for.
+//!
+//! Structures which hold pointer-sized integers or are padded to pointer alignment are laid out
+//! differently by 32-bit and 64-bit (and big and little endian) builds of the same target. Rather
+//! than duplicating such a model for every build, its pointer-sized fields are declared as
+//! [TargetUsize]/[TargetIsize] and its padded structures wrapped in [PointerAligned], all of which
+//! are serialized according to the current thread's [TargetConfig].
+//!
+//! ```
+//! # #![feature(specialization)]
+//! use lain::prelude::*;
+//! use lain::target::{Endianness, PointerWidth, TargetConfig};
+//!
+//! #[derive(Debug, Clone, NewFuzzed, BinarySerialize)]
+//! struct IoVec {
+//!     base: TargetUsize,
+//!     len: TargetUsize,
+//! }
+//!
+//! let iov = IoVec {
+//!     base: TargetUsize::new(0x1000),
+//!     len: TargetUsize::new(0x20),
+//! };
+//!
+//! TargetConfig {
+//!     endianness: Endianness::Little,
+//!     pointer_width: PointerWidth::Bits32,
+//! }
+//! .make_current();
+//!
+//! let mut serialized = vec![];
+//! iov.binary_serialize::<_, LittleEndian>(&mut serialized).unwrap();
+//! assert_eq!(serialized.len(), 8);
+//! ```
+//!
+//! Harnesses using the driver set the config of every fuzzer thread with
+//! [FuzzerDriver::set_target_config]. Other threads use the config of the host.
+//!
+//! [TargetUsize]: crate::types::TargetUsize
+//! [TargetIsize]: crate::types::TargetIsize
+//! [PointerAligned]: crate::types::PointerAligned
+//! [FuzzerDriver::set_target_config]: crate::driver::FuzzerDriver::set_target_config
+
+use crate::byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::cell::Cell;
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
+
+/// Byte order of the target's integers
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+/// Width of the target's pointers, and of its `size_t`/`usize`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum PointerWidth {
+    Bits32,
+    Bits64,
+}
+
+impl PointerWidth {
+    /// Size of a pointer in bytes
+    pub fn bytes(self) -> usize {
+        match self {
+            PointerWidth::Bits32 => 4,
+            PointerWidth::Bits64 => 8,
+        }
+    }
+}
+
+/// The byte order and pointer width of the build of the target inputs are serialized for
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct TargetConfig {
+    /// Byte order pointer-sized values are serialized in, regardless of the byte order the rest
+    /// of the input is serialized in
+    pub endianness: Endianness,
+    pub pointer_width: PointerWidth,
+}
+
+thread_local! {
+    static CURRENT: Cell<TargetConfig> = Cell::new(TargetConfig::host());
+}
+
+impl Default for TargetConfig {
+    fn default() -> Self {
+        TargetConfig::host()
+    }
+}
+
+impl TargetConfig {
+    /// The config of the build of lain itself
+    pub fn host() -> TargetConfig {
+        TargetConfig {
+            endianness: if cfg!(target_endian = "big") {
+                Endianness::Big
+            } else {
+                Endianness::Little
+            },
+            pointer_width: if cfg!(target_pointer_width = "64") {
+                PointerWidth::Bits64
+            } else {
+                PointerWidth::Bits32
+            },
+        }
+    }
+
+    /// The config values are serialized with on this thread
+    pub fn current() -> TargetConfig {
+        CURRENT.with(Cell::get)
+    }
+
+    /// Serializes values on this thread with this config
+    pub fn make_current(self) {
+        CURRENT.with(|current| current.set(self));
+    }
+
+    /// Size of a pointer in bytes
+    pub fn pointer_size(&self) -> usize {
+        self.pointer_width.bytes()
+    }
+
+    /// Number of bytes needed after `len` bytes to reach the next pointer boundary
+    pub fn padding_after(&self, len: usize) -> usize {
+        let align = self.pointer_size();
+
+        (align - len % align) % align
+    }
+
+    /// Writes the low [pointer_size](TargetConfig::pointer_size) bytes of `value`
+    pub(crate) fn write_pointer<W: Write>(&self, buffer: &mut W, value: u64) -> io::Result<usize> {
+        match (self.endianness, self.pointer_width) {
+            (Endianness::Big, PointerWidth::Bits32) => buffer.write_u32::<BigEndian>(value as u32),
+            (Endianness::Little, PointerWidth::Bits32) => {
+                buffer.write_u32::<LittleEndian>(value as u32)
+            }
+            (Endianness::Big, PointerWidth::Bits64) => buffer.write_u64::<BigEndian>(value),
+            (Endianness::Little, PointerWidth::Bits64) => buffer.write_u64::<LittleEndian>(value),
+        }?;
+
+        Ok(self.pointer_size())
+    }
+
+    /// Reads a pointer-sized value, zero-extending it to 64 bits
+    pub(crate) fn read_pointer<R: Read>(&self, buffer: &mut R) -> io::Result<u64> {
+        match (self.endianness, self.pointer_width) {
+            (Endianness::Big, PointerWidth::Bits32) => {
+                buffer.read_u32::<BigEndian>().map(u64::from)
+            }
+            (Endianness::Little, PointerWidth::Bits32) => {
+                buffer.read_u32::<LittleEndian>().map(u64::from)
+            }
+            (Endianness::Big, PointerWidth::Bits64) => buffer.read_u64::<BigEndian>(),
+            (Endianness::Little, PointerWidth::Bits64) => buffer.read_u64::<LittleEndian>(),
+        }
+    }
+}
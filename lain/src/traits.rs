@@ -1,20 +1,65 @@
-use crate::mutator::Mutator;
+use crate::mutator::{Mutator, MutatorMode};
 
 use crate::rand::Rng;
 
+use crate::feedback::{Feedback, Observation};
 use crate::types::*;
 use byteorder::ByteOrder;
 use num_traits::Bounded;
 use std::fmt::Debug;
-use std::io::Write;
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
 
 use std::ops::Deref;
 
 /// Represents a data typethat can be pushed to a byte buffer in a constant,
 /// predetermined way.
 pub trait BinarySerialize {
-    /// Pushes all fields in `self` to a buffer
-    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W);
+    /// Pushes all fields in `self` to a buffer. Returns the number of bytes written, or the
+    /// first error returned by the writer so that short writes to sockets/pipes aren't silently
+    /// truncated.
+    fn binary_serialize<W: Write, E: ByteOrder>(&self, buffer: &mut W) -> io::Result<usize>;
+}
+
+/// A [BinarySerialize] type whose serialization never allocates or panics, so that values can be
+/// re-serialized from a crash or signal handler where the heap may be in an inconsistent state.
+///
+/// This is implemented for primitives, strings, and slices and `Vec`s of such types, lain's
+/// wrapper types, and types deriving `BinarySerialize` with `#[lain(no_alloc_serialize)]`. The
+/// derived impl only applies if every field's type implements this trait too.
+pub trait NoAllocSerialize: BinarySerialize {
+    /// Serializes `self` to the start of `buffer`, returning the number of bytes written. Fails
+    /// with [io::ErrorKind::WriteZero] if `buffer` is too small to hold the serialized data.
+    fn binary_serialize_into<E: ByteOrder>(&self, buffer: &mut [u8]) -> io::Result<usize> {
+        let mut writer = buffer;
+        self.binary_serialize::<_, E>(&mut writer)
+    }
+}
+
+/// A type whose in-memory bytes are exactly what [BinarySerialize] writes for it in the platform's
+/// native byte order: there's no padding, no length prefix, and no encoding. This is implemented
+/// for the primitive numbers, `bool`, arrays of such types, and structs deriving `BinarySerialize`
+/// with `#[lain(packed_layout)]`, whose fields must all implement it.
+///
+/// # Safety
+///
+/// Writing the `size_of::<Self>()` bytes at a value's address must produce the same bytes as
+/// serializing it with [byteorder::NativeEndian].
+pub unsafe trait PlainOldData: Copy {}
+
+/// A data structure that can be read back from bytes in the format written by [BinarySerialize].
+/// Since serialized data carries no length information, variable-size members such as `Vec<T>`
+/// consume the remainder of the input.
+pub trait BinaryDeserialize: Sized {
+    /// Reads a value from `buffer`, failing if the input ends early or holds an invalid value
+    fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self>;
+
+    /// Reads a value from `buffer`, filling any fields missing from the end of the input with
+    /// their default values. This is useful for importing real-world samples which may be
+    /// truncated.
+    fn binary_deserialize_lenient<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+        Self::binary_deserialize::<R, E>(buffer)
+    }
 }
 
 /// A trait to represent the output size (in bytes) of an object when serialized to binary.
@@ -30,7 +75,7 @@ pub trait SerializedSize {
 
 /// A data structure that can have a new instance of itself created completely randomly, with optional constraints.
 pub trait NewFuzzed {
-    type RangeType: Debug + Bounded + Default;
+    type RangeType: Debug + Bounded + Default + 'static;
 
     /// Picks a random variant of `Self`
     fn new_fuzzed<R: Rng>(
@@ -45,6 +90,65 @@ pub trait Mutatable {
     fn mutate<R: Rng>(&mut self, mutator: &mut Mutator<R>, constraints: Option<&Constraints<u8>>);
 }
 
+/// A data structure that can be mutated while staying within the same constraints that were used
+/// to generate it with [NewFuzzed]. This is used by the `Mutatable` derive for fields with a
+/// min/max. Types which don't override this ignore the constraints and fall back to [Mutatable].
+pub trait ConstrainedMutatable: Mutatable + NewFuzzed {
+    fn mutate_constrained<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<Self::RangeType>>,
+    );
+}
+
+impl<T: Mutatable + NewFuzzed> ConstrainedMutatable for T {
+    default fn mutate_constrained<R: Rng>(
+        &mut self,
+        mutator: &mut Mutator<R>,
+        _constraints: Option<&Constraints<Self::RangeType>>,
+    ) {
+        self.mutate(mutator, None);
+    }
+}
+
+/// A collection which generates (and, through [ConstrainedMutatable], mutates) its elements within
+/// the [Constraints::element_constraints] it's given. This names the element type so that derived
+/// fields with an `element_min`/`element_max` build constraints of the element's `RangeType`.
+pub trait ConstrainedElements: NewFuzzed {
+    type Element: NewFuzzed;
+}
+
+/// A data structure that can be generated by mutating a known-good template rather than from
+/// scratch. Parsers which reject anything that isn't mostly valid are reached far more often by
+/// inputs derived from a real sample. This is used by `NewFuzzed` derives with
+/// `#[lain(seed_from = "...")]`.
+pub trait FromSeed: Sized {
+    /// Produces a new value starting from `seed`
+    fn from_seed<R: Rng>(
+        seed: &Self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<u8>>,
+    ) -> Self;
+}
+
+impl<T: Clone + Mutatable> FromSeed for T {
+    /// Clones the seed and mutates the copy. Outside of [MutatorMode::Havoc] the seed is returned
+    /// unchanged.
+    default fn from_seed<R: Rng>(
+        seed: &Self,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<u8>>,
+    ) -> Self {
+        let mut value = seed.clone();
+
+        if mutator.mode() == MutatorMode::Havoc {
+            value.mutate(mutator, constraints);
+        }
+
+        value
+    }
+}
+
 /// Helper trait for calling `self.fixup(mutator)` on all child members.
 pub trait FixupChildren {
     fn fixup_children<R: Rng>(&mut self, mutator: &mut Mutator<R>);
@@ -71,6 +175,65 @@ impl<T> Fixup for T {
     }
 }
 
+/// Declares and references the values shared between the messages of a
+/// [Conversation](crate::types::Conversation). Messages which don't implement this neither
+/// declare nor reference anything.
+pub trait Bind {
+    /// Declares the values of `self` which later messages may reference
+    fn declare_bindings(&self, bindings: &mut Bindings);
+
+    /// Overwrites the fields of `self` which reference values declared by earlier messages
+    fn resolve_bindings(&mut self, bindings: &Bindings);
+}
+
+impl<T> Bind for T {
+    default fn declare_bindings(&self, _bindings: &mut Bindings) {
+        // nop - nothing is declared
+    }
+
+    default fn resolve_bindings(&mut self, _bindings: &Bindings) {
+        // nop - nothing is referenced
+    }
+}
+
+/// Regenerates the fields of a value which must be fresh on every iteration, such as sequence
+/// numbers or timestamps, so that a corpus entry can be replayed without the target rejecting it
+/// as stale. `#[derive(NewFuzzed)]` implements this for structs with fields marked
+/// `#[lain(volatile)]` or marked `#[lain(refresh)]` themselves, regenerating the volatile fields
+/// and refreshing nested structs.
+pub trait Refresh {
+    /// Whether this type has any volatile fields, directly or in a nested struct
+    fn has_volatile_fields() -> bool;
+
+    /// Regenerates every volatile field and fixes up the structs holding them
+    fn refresh<R: Rng>(&mut self, mutator: &mut Mutator<R>);
+}
+
+impl<T> Refresh for T {
+    default fn has_volatile_fields() -> bool {
+        false
+    }
+
+    default fn refresh<R: Rng>(&mut self, _mutator: &mut Mutator<R>) {
+        // nop - there's nothing volatile to regenerate
+    }
+}
+
+/// Hashes the structural shape of a value -- the enum variants it holds and which optional fields
+/// are present -- without the values of its leaf fields. Inputs which only differ in their raw
+/// bytes have the same shape, which is what [crate::dedup] buckets crashes by.
+/// `#[derive(BinarySerialize)]` implements this for structs and enums marked
+/// `#[lain(input_shape)]`.
+pub trait InputShape {
+    fn hash_shape<H: Hasher>(&self, state: &mut H);
+}
+
+impl<T: ?Sized> InputShape for T {
+    default fn hash_shape<H: Hasher>(&self, _state: &mut H) {
+        // nop - leaf values don't contribute to the shape
+    }
+}
+
 #[doc(hidden)]
 pub trait DangerousNumber<T> {
     fn select_dangerous_number<R: Rng>(rng: &mut R) -> T;
@@ -170,3 +333,129 @@ impl VariableSizeObject for AsciiString {
         true
     }
 }
+
+/// Describes which fields differ between two instances of the same type and how they differ.
+///
+/// This is useful for logging what a mutation actually changed, or for bucketing crashing inputs
+/// by the region of the structure that was perturbed. Users should `#[derive(StructuredDiff)]`;
+/// types which do not implement this trait are treated as having no observable differences.
+pub trait StructuredDiff {
+    /// Returns a [FieldDelta] for every leaf field of `self` which differs from `other`
+    fn diff(&self, other: &Self) -> Vec<FieldDelta>;
+}
+
+impl<T> StructuredDiff for T {
+    default fn diff(&self, _other: &Self) -> Vec<FieldDelta> {
+        // nop - users should derive StructuredDiff
+        Vec::new()
+    }
+}
+
+/// Describes a type's fields at runtime: their names, types, and attribute settings, and where
+/// each one is in a value's serialized form. This lets generic tooling such as pretty printers,
+/// GUI inspectors, or attributing coverage to the bytes of the fields which produced it work with
+/// any model. `#[derive(BinarySerialize)]` implements this for structs marked
+/// `#[lain(field_metadata)]`; types which don't implement it are treated as having no fields.
+pub trait FieldMetadata {
+    /// Describes the type's fields in declaration order
+    fn field_info() -> Vec<FieldInfo>;
+
+    /// Returns the layout of this value's fields in wire order. Each field of a nested struct
+    /// directly follows the field holding it, with its full path.
+    fn field_layout(&self) -> Vec<FieldLayout>;
+
+    /// Returns the most deeply nested field holding the byte at `offset` of the serialized value
+    fn field_at_offset(&self, offset: usize) -> Option<FieldLayout> {
+        self.field_layout()
+            .into_iter()
+            .rev()
+            .find(|field| field.contains(offset))
+    }
+}
+
+impl<T: ?Sized> FieldMetadata for T {
+    default fn field_info() -> Vec<FieldInfo> {
+        // nop - fields are described by #[lain(field_metadata)]
+        Vec::new()
+    }
+
+    default fn field_layout(&self) -> Vec<FieldLayout> {
+        Vec::new()
+    }
+}
+
+/// A stage in a postprocessing pipeline which transforms serialized bytes before they are handed
+/// to the target (e.g. compression, encryption, or an encoding layer).
+pub trait Postprocess: Send + Sync {
+    /// Transforms `input`, appending the result to `output`. `output` is always empty when
+    /// this is called.
+    fn postprocess(&self, input: &[u8], output: &mut Vec<u8>);
+}
+
+/// Judges an iteration by how the target reacted to it. The driver consults its provider after
+/// every iteration and retains inputs judged [Feedback::Interesting], which lets protocol fuzzers
+/// keep inputs that elicit unusual responses even without coverage instrumentation.
+pub trait FeedbackProvider: Send + Sync {
+    /// Evaluates the serialized `input` that was sent to the target
+    fn evaluate(&self, input: &[u8], observation: &Observation) -> Feedback;
+}
+
+/// Renders a value as an indented tree which shows field paths, serialized sizes, and hex previews
+/// of byte buffers. This is mostly useful for reviewing what a data model actually generates. Users
+/// should `#[derive(FuzzedDebug)]` on their own types.
+pub trait FuzzedDebug {
+    /// Appends the tree for this value to `output`. `path` is the path to this value from the root
+    /// object and `depth` is its depth in the tree.
+    fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String);
+
+    /// Appends the trees of this value's fields, one level below `depth`, without a line for the
+    /// value itself. This is used to show the fields of a `#[lain(flatten)]` field as if they
+    /// belonged to its parent. Types without fields print the whole value instead.
+    fn fuzzed_debug_fields(&self, path: &str, depth: usize, output: &mut String) {
+        self.fuzzed_debug(path, depth + 1, output);
+    }
+
+    /// Returns the full tree for this value
+    fn to_fuzzed_debug_string(&self) -> String {
+        let mut output = String::new();
+        self.fuzzed_debug("", 0, &mut output);
+
+        output
+    }
+}
+
+impl<T: ?Sized> FuzzedDebug for T {
+    default fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+        use crate::debug::*;
+
+        write_node(
+            output,
+            depth,
+            path,
+            &short_type_name::<T>(),
+            self.serialized_size_hint(),
+            Some("<no FuzzedDebug impl>"),
+        );
+    }
+
+    default fn fuzzed_debug_fields(&self, path: &str, depth: usize, output: &mut String) {
+        self.fuzzed_debug(path, depth + 1, output);
+    }
+}
+
+/// A type whose values can all be listed, such as flags, opcodes, or small bounded integers. The
+/// driver can sweep every combination of these values exhaustively (see
+/// [FuzzerDriver::set_exhaustive_sweep][crate::driver::FuzzerDriver::set_exhaustive_sweep]).
+/// Users should `#[derive(Enumerable)]` on their own types.
+pub trait Enumerable: Sized {
+    /// The number of distinct values of this type
+    fn value_count() -> usize;
+
+    /// Returns the value at `index`. Panics if `index >= Self::value_count()`.
+    fn value_at(index: usize) -> Self;
+
+    /// Iterates over every value of this type
+    fn all_values() -> std::iter::Map<std::ops::Range<usize>, fn(usize) -> Self> {
+        (0..Self::value_count()).map(Self::value_at as fn(usize) -> Self)
+    }
+}
@@ -1,4 +1,14 @@
-use num_traits::Bounded;
+use crate::error::Error;
+use num_traits::{Bounded, NumCast, PrimInt};
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::Arc;
 
 #[cfg(feature = "serde_support")]
 use serde::{Deserialize, Serialize};
@@ -39,13 +49,101 @@ use serde::{Deserialize, Serialize};
 ///
 /// **TL;DR** Rust makes too many assumptions about how enums are used to make doing unsafe things
 /// with them worthwhile. This wrapper enum works around that.
-#[derive(Debug, Clone, Copy)]
+///
+/// The `Debug` output of a valid value includes its discriminant when `T` implements
+/// [ToPrimitive](crate::traits::ToPrimitive), e.g. `Valid(Write = 2)`, so that triage logs show
+/// the same numbers for valid and invalid values.
+#[derive(Clone, Copy)]
 #[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
 pub enum UnsafeEnum<T, I> {
     Valid(T),
     Invalid(I),
 }
 
+impl<T, I> UnsafeEnum<T, I> {
+    /// Whether this holds one of `T`'s variants rather than an arbitrary discriminant
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            UnsafeEnum::Valid(_) => true,
+            UnsafeEnum::Invalid(_) => false,
+        }
+    }
+
+    /// Returns the variant held, or `None` if the discriminant is invalid
+    pub fn as_valid(&self) -> Option<&T> {
+        match *self {
+            UnsafeEnum::Valid(ref value) => Some(value),
+            UnsafeEnum::Invalid(_) => None,
+        }
+    }
+}
+
+impl<T, I> UnsafeEnum<T, I>
+where
+    T: crate::traits::ToPrimitive<I>,
+    I: Copy,
+{
+    /// Returns the discriminant as it's serialized, whether or not it's valid
+    pub fn raw_value(&self) -> I {
+        match *self {
+            UnsafeEnum::Valid(ref value) => value.to_primitive(),
+            UnsafeEnum::Invalid(value) => value,
+        }
+    }
+}
+
+/// Reads the discriminant of an [UnsafeEnum]'s valid variant for its `Debug` output. Types which
+/// don't implement [ToPrimitive](crate::traits::ToPrimitive) have no known discriminant.
+trait ValidDiscriminant<I> {
+    fn valid_discriminant(&self) -> Option<I>;
+}
+
+impl<T, I> ValidDiscriminant<I> for T {
+    default fn valid_discriminant(&self) -> Option<I> {
+        None
+    }
+}
+
+impl<T, I> ValidDiscriminant<I> for T
+where
+    T: crate::traits::ToPrimitive<I>,
+{
+    fn valid_discriminant(&self) -> Option<I> {
+        Some(self.to_primitive())
+    }
+}
+
+/// Formats a valid variant as `Variant = discriminant`
+struct DebugVariant<'a, T, I>(&'a T, I);
+
+impl<'a, T: fmt::Debug, I: fmt::Debug> fmt::Debug for DebugVariant<'a, T, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)?;
+        write!(f, " = ")?;
+        self.1.fmt(f)
+    }
+}
+
+impl<T, I> fmt::Debug for UnsafeEnum<T, I>
+where
+    T: fmt::Debug,
+    I: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnsafeEnum::Valid(ref value) => match ValidDiscriminant::<I>::valid_discriminant(value)
+            {
+                Some(discriminant) => f
+                    .debug_tuple("Valid")
+                    .field(&DebugVariant(value, discriminant))
+                    .finish(),
+                None => f.debug_tuple("Valid").field(value).finish(),
+            },
+            UnsafeEnum::Invalid(ref value) => f.debug_tuple("Invalid").field(value).finish(),
+        }
+    }
+}
+
 impl<T, I> Default for UnsafeEnum<T, I>
 where
     T: Default,
@@ -71,6 +169,11 @@ where
 // TODO: Clean up this string interface. This isn't the cleanest
 /// Wrapper around `String` that provides mutation methods appropriate for UTF-8 encoded Strings
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde_support",
+    derive(Serialize, Deserialize),
+    serde(from = "String", into = "String")
+)]
 pub struct Utf8String {
     pub(crate) inner: Vec<Utf8Char>,
 }
@@ -83,8 +186,25 @@ impl Utf8String {
     }
 }
 
+impl From<String> for Utf8String {
+    fn from(s: String) -> Self {
+        Utf8String::new(&s)
+    }
+}
+
+impl From<Utf8String> for String {
+    fn from(s: Utf8String) -> Self {
+        s.inner.iter().map(|c| c.0).collect()
+    }
+}
+
 /// Wrapper around `String` that provides mutation methods appropriate for ASCII encoded Strings
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(
+    feature = "serde_support",
+    derive(Serialize, Deserialize),
+    serde(from = "String", into = "String")
+)]
 pub struct AsciiString {
     pub(crate) inner: Vec<AsciiChar>,
 }
@@ -97,38 +217,1591 @@ impl AsciiString {
     }
 }
 
-/// Represents a UTF-8 character.
-#[derive(Default, Debug, Clone)]
-pub(crate) struct Utf8Char(pub(crate) char);
-
-/// Represents an ASCII character.
-#[derive(Default, Debug, Clone)]
-pub(crate) struct AsciiChar(pub(crate) char);
+impl From<String> for AsciiString {
+    fn from(s: String) -> Self {
+        AsciiString::new(&s)
+    }
+}
 
-/// Data structure holding constraints that the [NewFuzzed::new_fuzzed][lain::traits::NewFuzzed::new_fuzzed] or
-/// [Mutatable::mutate][lain::traits::Mutatable::mutate] methods should try to respect.
-#[derive(Debug, Default, Clone)]
-pub struct Constraints<T: Bounded + std::fmt::Debug> {
-    /// The contextual "min" bound
-    pub min: Option<T>,
-    /// The contextual "max" bound (**not** inclusive)
-    pub max: Option<T>,
-    /// Which direction to weigh the RNG towards
-    pub weighted: Weighted,
-    /// The maximum size that the object has to work with
-    pub max_size: Option<usize>,
+impl From<AsciiString> for String {
+    fn from(s: AsciiString) -> Self {
+        s.inner.iter().map(|c| c.0).collect()
+    }
 }
 
-/// Which direction to weigh ranges towards (min bound, upper bound, or none).
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub enum Weighted {
-    None,
-    Min,
-    Max,
+/// A string stored in a fixed-size field of `N` bytes, like the names and labels in many legacy
+/// binary formats. It always serializes to exactly `N` bytes: contents longer than the field are
+/// truncated and shorter contents are padded with `PAD` (NUL by default).
+///
+/// Generated contents never exceed the field. Generation and mutation favor contents which fill
+/// it exactly, leaving no padding to terminate the string, and the lengths just short of that.
+/// Mutation may also overflow the field by a byte so the truncated encoding is exercised.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct FixedLengthString<const N: usize, const PAD: u8 = 0> {
+    pub(crate) bytes: Vec<u8>,
 }
 
-impl Default for Weighted {
+impl<const N: usize, const PAD: u8> Default for FixedLengthString<N, PAD> {
     fn default() -> Self {
-        Weighted::None
+        FixedLengthString { bytes: Vec::new() }
+    }
+}
+
+impl<const N: usize, const PAD: u8> FixedLengthString<N, PAD> {
+    /// Number of bytes the string serializes to
+    pub const CAPACITY: usize = N;
+
+    /// Byte the serialized string is padded with
+    pub const PADDING: u8 = PAD;
+
+    pub fn new(s: &str) -> Self {
+        FixedLengthString::from_bytes(s.as_bytes())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        FixedLengthString {
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    /// The contents of the string, which may be longer than the field
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The part of the contents which is serialized, i.e. without the bytes past the field
+    pub fn serialized_contents(&self) -> &[u8] {
+        &self.bytes[..cmp::min(self.bytes.len(), N)]
+    }
+
+    /// Whether the contents are longer than the field and will be cut off when serialized
+    pub fn is_truncated(&self) -> bool {
+        self.bytes.len() > N
+    }
+
+    /// Whether the contents fill the field exactly, so there's no padding after them
+    pub fn is_exact_fit(&self) -> bool {
+        self.bytes.len() == N
+    }
+
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.bytes)
+    }
+}
+
+impl<const N: usize, const PAD: u8> From<&str> for FixedLengthString<N, PAD> {
+    fn from(s: &str) -> Self {
+        FixedLengthString::new(s)
+    }
+}
+
+/// Unsigned 24-bit integer which serializes to exactly 3 bytes. Useful for formats which
+/// encode lengths or offsets as 24-bit values.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde_support",
+    derive(Serialize, Deserialize),
+    serde(try_from = "u32", into = "u32")
+)]
+pub struct U24(pub(crate) u32);
+
+impl U24 {
+    /// Largest value representable in 24 bits
+    pub const MAX: u32 = 0x00FF_FFFF;
+
+    /// Creates a new `U24`. Any bits above the low 24 bits are discarded.
+    pub fn new(value: u32) -> Self {
+        U24(value & U24::MAX)
+    }
+
+    pub fn value(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<U24> for u32 {
+    fn from(value: U24) -> u32 {
+        value.0
+    }
+}
+
+/// Fails with an [Error::ConstraintViolation] if `value` doesn't fit in 24 bits
+impl TryFrom<u32> for U24 {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value > U24::MAX {
+            return Err(Error::ConstraintViolation {
+                value: format!("{:?}", value),
+                constraints: format!("{:?}", 0..=U24::MAX),
+            });
+        }
+
+        Ok(U24(value))
+    }
+}
+
+/// Unsigned 48-bit integer which serializes to exactly 6 bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde_support",
+    derive(Serialize, Deserialize),
+    serde(try_from = "u64", into = "u64")
+)]
+pub struct U48(pub(crate) u64);
+
+impl U48 {
+    /// Largest value representable in 48 bits
+    pub const MAX: u64 = 0x0000_FFFF_FFFF_FFFF;
+
+    /// Creates a new `U48`. Any bits above the low 48 bits are discarded.
+    pub fn new(value: u64) -> Self {
+        U48(value & U48::MAX)
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<U48> for u64 {
+    fn from(value: U48) -> u64 {
+        value.0
+    }
+}
+
+/// Fails with an [Error::ConstraintViolation] if `value` doesn't fit in 48 bits
+impl TryFrom<u64> for U48 {
+    type Error = Error;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        if value > U48::MAX {
+            return Err(Error::ConstraintViolation {
+                value: format!("{:?}", value),
+                constraints: format!("{:?}", 0..=U48::MAX),
+            });
+        }
+
+        Ok(U48(value))
+    }
+}
+
+/// An IPv4 address. Serializes to its 4 octets in network order regardless of the byte order it's
+/// serialized with. Generated addresses favor special ranges (loopback, private, link-local,
+/// multicast, broadcast) over arbitrary ones.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct Ipv4Address(pub(crate) [u8; 4]);
+
+impl Ipv4Address {
+    pub fn new(octets: [u8; 4]) -> Self {
+        Ipv4Address(octets)
+    }
+
+    pub fn octets(self) -> [u8; 4] {
+        self.0
+    }
+}
+
+impl From<Ipv4Addr> for Ipv4Address {
+    fn from(address: Ipv4Addr) -> Self {
+        Ipv4Address(address.octets())
+    }
+}
+
+impl From<Ipv4Address> for Ipv4Addr {
+    fn from(address: Ipv4Address) -> Self {
+        Ipv4Addr::from(address.0)
+    }
+}
+
+impl fmt::Display for Ipv4Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Ipv4Addr::from(*self).fmt(f)
+    }
+}
+
+impl fmt::Debug for Ipv4Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ipv4Address({})", self)
+    }
+}
+
+/// An IPv6 address. Serializes to its 16 octets in network order regardless of the byte order it's
+/// serialized with. Generated addresses favor special ranges (unspecified, loopback, link-local,
+/// unique local, multicast, IPv4-mapped) over arbitrary ones.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct Ipv6Address(pub(crate) [u8; 16]);
+
+impl Ipv6Address {
+    pub fn new(octets: [u8; 16]) -> Self {
+        Ipv6Address(octets)
+    }
+
+    pub fn octets(self) -> [u8; 16] {
+        self.0
+    }
+}
+
+impl From<Ipv6Addr> for Ipv6Address {
+    fn from(address: Ipv6Addr) -> Self {
+        Ipv6Address(address.octets())
+    }
+}
+
+impl From<Ipv6Address> for Ipv6Addr {
+    fn from(address: Ipv6Address) -> Self {
+        Ipv6Addr::from(address.0)
+    }
+}
+
+impl fmt::Display for Ipv6Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Ipv6Addr::from(*self).fmt(f)
+    }
+}
+
+impl fmt::Debug for Ipv6Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ipv6Address({})", self)
+    }
+}
+
+/// A 48-bit Ethernet MAC address, serialized as its 6 octets in transmission order. Generated
+/// addresses include broadcast and multicast addresses along with unicast ones.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct MacAddress(pub(crate) [u8; 6]);
+
+impl MacAddress {
+    pub const BROADCAST: MacAddress = MacAddress([0xFF; 6]);
+
+    pub fn new(octets: [u8; 6]) -> Self {
+        MacAddress(octets)
+    }
+
+    pub fn octets(self) -> [u8; 6] {
+        self.0
+    }
+
+    /// Whether the group bit is set, which includes the broadcast address
+    pub fn is_multicast(self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    /// Whether the address was assigned locally rather than from a vendor's OUI
+    pub fn is_locally_administered(self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let o = &self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            o[0], o[1], o[2], o[3], o[4], o[5]
+        )
+    }
+}
+
+impl fmt::Debug for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MacAddress({})", self)
+    }
+}
+
+/// A UUID, serialized as its 16 bytes in RFC 4122 (big endian) order regardless of the byte order
+/// it's serialized with. Generated UUIDs are mostly well-formed version 1 and 4 UUIDs, with the
+/// nil and max UUIDs and arbitrary bytes mixed in.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct Uuid(pub(crate) [u8; 16]);
+
+impl Uuid {
+    pub fn new(bytes: [u8; 16]) -> Self {
+        Uuid(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// The version number from the high nibble of the `time_hi_and_version` field
+    pub fn version(self) -> u8 {
+        self.0[6] >> 4
+    }
+}
+
+impl fmt::Display for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i == 4 || i == 6 || i == 8 || i == 10 {
+                f.write_str("-")?;
+            }
+
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Uuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Uuid({})", self)
+    }
+}
+
+/// Seconds since the Unix epoch as a signed 32-bit integer, which overflows in January 2038.
+/// Generated timestamps are clustered around the epoch, Y2K, Y2K38 and the present day.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct UnixTimestamp32(pub(crate) i32);
+
+impl UnixTimestamp32 {
+    pub fn new(seconds: i32) -> Self {
+        UnixTimestamp32(seconds)
+    }
+
+    pub fn seconds(self) -> i32 {
+        self.0
+    }
+}
+
+impl fmt::Display for UnixTimestamp32 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_unix_time(f, self.0 as i64, None)
+    }
+}
+
+impl fmt::Debug for UnixTimestamp32 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UnixTimestamp32({})", self.0)
+    }
+}
+
+/// Seconds since the Unix epoch as a signed 64-bit integer. Generated timestamps additionally
+/// include dates past the year 9999 and millisecond timestamps mistaken for seconds.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct UnixTimestamp64(pub(crate) i64);
+
+impl UnixTimestamp64 {
+    pub fn new(seconds: i64) -> Self {
+        UnixTimestamp64(seconds)
+    }
+
+    pub fn seconds(self) -> i64 {
+        self.0
+    }
+}
+
+impl From<UnixTimestamp32> for UnixTimestamp64 {
+    fn from(timestamp: UnixTimestamp32) -> Self {
+        UnixTimestamp64(timestamp.0 as i64)
+    }
+}
+
+impl fmt::Display for UnixTimestamp64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_unix_time(f, self.0, None)
+    }
+}
+
+impl fmt::Debug for UnixTimestamp64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UnixTimestamp64({})", self.0)
+    }
+}
+
+/// An MS-DOS date and time as stored in FAT directory entries and ZIP headers. Serialized as the
+/// 16-bit time followed by the 16-bit date, each in the byte order it's serialized with.
+///
+/// The date packs the year since 1980 in bits 9-15, the month in bits 5-8 and the day in bits 0-4.
+/// The time packs the hour in bits 11-15, the minute in bits 5-10 and the second divided by two in
+/// bits 0-4. Generated values are mostly valid, with out-of-range fields (month 13, hour 24,
+/// February 30th) and the boundaries of the representable range mixed in.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct DosDateTime {
+    pub(crate) date: u16,
+    pub(crate) time: u16,
+}
+
+impl DosDateTime {
+    /// Packs the given fields without validating them. Fields wider than their bit field are
+    /// truncated and the second is rounded down to an even number.
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Self {
+        let date =
+            (year.wrapping_sub(1980) & 0x7F) << 9 | (month as u16 & 0x0F) << 5 | day as u16 & 0x1F;
+        let time =
+            (hour as u16 & 0x1F) << 11 | (minute as u16 & 0x3F) << 5 | (second / 2) as u16 & 0x1F;
+
+        DosDateTime { date, time }
+    }
+
+    pub fn from_raw(date: u16, time: u16) -> Self {
+        DosDateTime { date, time }
+    }
+
+    pub fn date(self) -> u16 {
+        self.date
+    }
+
+    pub fn time(self) -> u16 {
+        self.time
+    }
+
+    pub fn year(self) -> u16 {
+        1980 + (self.date >> 9)
+    }
+
+    pub fn month(self) -> u8 {
+        ((self.date >> 5) & 0x0F) as u8
+    }
+
+    pub fn day(self) -> u8 {
+        (self.date & 0x1F) as u8
+    }
+
+    pub fn hour(self) -> u8 {
+        (self.time >> 11) as u8
+    }
+
+    pub fn minute(self) -> u8 {
+        ((self.time >> 5) & 0x3F) as u8
+    }
+
+    pub fn second(self) -> u8 {
+        ((self.time & 0x1F) * 2) as u8
+    }
+
+    /// Whether every field is within its calendar range
+    pub fn is_valid(self) -> bool {
+        let month = self.month();
+        let day = self.day();
+
+        (1..=12).contains(&month)
+            && day >= 1
+            && day <= days_in_month(self.year() as i64, month as u32) as u8
+            && self.hour() < 24
+            && self.minute() < 60
+            && self.second() < 60
+    }
+}
+
+impl fmt::Display for DosDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year(),
+            self.month(),
+            self.day(),
+            self.hour(),
+            self.minute(),
+            self.second()
+        )
+    }
+}
+
+impl fmt::Debug for DosDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DosDateTime({})", self)
+    }
+}
+
+/// A Windows `FILETIME`: the number of 100-nanosecond intervals since January 1st, 1601 (UTC).
+/// Serialized like the Win32 struct, as the low 32 bits followed by the high 32 bits, each in the
+/// byte order it's serialized with.
+///
+/// Generated values are clustered around the FILETIME and Unix epochs, the present day, the
+/// largest value Windows APIs accept (`i64::MAX`) and `u64::MAX`.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct FileTime(pub(crate) u64);
+
+impl FileTime {
+    /// The number of intervals between the FILETIME epoch and the Unix epoch
+    pub const UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+    /// The number of intervals in a second
+    pub const INTERVALS_PER_SECOND: u64 = 10_000_000;
+
+    pub fn new(intervals: u64) -> Self {
+        FileTime(intervals)
+    }
+
+    pub fn intervals(self) -> u64 {
+        self.0
+    }
+
+    /// The time `seconds` after the Unix epoch, saturating at the bounds of the representable range
+    pub fn from_unix(seconds: i64) -> Self {
+        let intervals =
+            seconds as i128 * Self::INTERVALS_PER_SECOND as i128 + Self::UNIX_EPOCH as i128;
+
+        FileTime(cmp::max(0, cmp::min(intervals, u64::MAX as i128)) as u64)
+    }
+
+    /// Whole seconds since the Unix epoch, rounded towards negative infinity
+    pub fn to_unix(self) -> i64 {
+        (self.0 / Self::INTERVALS_PER_SECOND) as i64
+            - (Self::UNIX_EPOCH / Self::INTERVALS_PER_SECOND) as i64
+    }
+}
+
+impl From<UnixTimestamp64> for FileTime {
+    fn from(timestamp: UnixTimestamp64) -> Self {
+        FileTime::from_unix(timestamp.0)
+    }
+}
+
+impl fmt::Display for FileTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_unix_time(
+            f,
+            self.to_unix(),
+            Some((self.0 % Self::INTERVALS_PER_SECOND) as u32),
+        )
+    }
+}
+
+impl fmt::Debug for FileTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FileTime({})", self.0)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub(crate) fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Writes `seconds` since the Unix epoch as an ISO 8601 UTC date and time, with an optional
+/// fraction in 100-nanosecond intervals
+fn write_unix_time(f: &mut fmt::Formatter, seconds: i64, intervals: Option<u32>) -> fmt::Result {
+    // Howard Hinnant's days_from_civil algorithm in reverse
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    write!(
+        f,
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )?;
+
+    if let Some(intervals) = intervals {
+        write!(f, ".{:07}", intervals)?;
+    }
+
+    write!(f, "Z")
+}
+
+/// The factor a [Scaled] value's real value is multiplied by to produce the integer sent on the
+/// wire. Implement this on a marker type to define a new scale.
+pub trait ScaleFactor {
+    const FACTOR: f64;
+}
+
+/// Scale for values sent in tenths of a unit, e.g. 21.5 sent as 215
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Tenths;
+
+impl ScaleFactor for Tenths {
+    const FACTOR: f64 = 10.0;
+}
+
+/// Scale for values sent in hundredths of a unit, e.g. 21.5 sent as 2150
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Hundredths;
+
+impl ScaleFactor for Hundredths {
+    const FACTOR: f64 = 100.0;
+}
+
+/// Scale for values sent in thousandths of a unit, e.g. 21.5 sent as 21500
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Thousandths;
+
+impl ScaleFactor for Thousandths {
+    const FACTOR: f64 = 1000.0;
+}
+
+/// Fixed-point value which is serialized as the integer `T` holding its real value multiplied by
+/// `F::FACTOR`. This is common in sensor and industrial protocols which encode e.g. temperatures
+/// as a number of tenths of a degree.
+///
+/// Values are generated and mutated as real numbers, so `#[fuzzer(min = -40.0, max = 125.0)]`
+/// constrains the real value, and converted to the nearest representable integer. Real values
+/// outside of `T`'s range saturate to `T`'s min or max.
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct Scaled<T, F> {
+    raw: T,
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    factor: PhantomData<F>,
+}
+
+impl<T, F> Scaled<T, F>
+where
+    T: NumCast + Bounded + Copy,
+    F: ScaleFactor,
+{
+    /// Creates a value from the integer sent on the wire
+    pub fn from_raw(raw: T) -> Self {
+        Scaled {
+            raw,
+            factor: PhantomData,
+        }
+    }
+
+    /// Creates a value from its real value, rounding to the nearest representable value
+    pub fn from_real(value: f64) -> Self {
+        let scaled = (value * F::FACTOR).round();
+
+        let raw = if scaled.is_nan() {
+            num_traits::cast(0).unwrap()
+        } else {
+            num_traits::cast(scaled).unwrap_or_else(|| {
+                if scaled < 0.0 {
+                    T::min_value()
+                } else {
+                    T::max_value()
+                }
+            })
+        };
+
+        Scaled::from_raw(raw)
+    }
+
+    /// The integer sent on the wire
+    pub fn raw(&self) -> T {
+        self.raw
+    }
+
+    /// The real value this represents
+    pub fn real(&self) -> f64 {
+        num_traits::cast::<T, f64>(self.raw).unwrap() / F::FACTOR
+    }
+}
+
+impl<T: Clone, F> Clone for Scaled<T, F> {
+    fn clone(&self) -> Self {
+        Scaled {
+            raw: self.raw.clone(),
+            factor: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy, F> Copy for Scaled<T, F> {}
+
+impl<T: PartialEq, F> PartialEq for Scaled<T, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<T: Default, F> Default for Scaled<T, F> {
+    fn default() -> Self {
+        Scaled {
+            raw: T::default(),
+            factor: PhantomData,
+        }
+    }
+}
+
+impl<T, F> fmt::Debug for Scaled<T, F>
+where
+    T: NumCast + Bounded + Copy + fmt::Debug,
+    F: ScaleFactor,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({:?})", self.real(), self.raw)
+    }
+}
+
+/// An integer `T` holding an offset into the serialized buffer, e.g. where a section or string
+/// table starts. It's serialized exactly like `T`.
+///
+/// Rather than being mutated like an arbitrary number, an offset is nudged a few bytes either way,
+/// aligned to a 2, 4, 8, or 16 byte boundary, or pointed just past the end of the buffer. These are
+/// the values which slip past bounds checks and which random values almost never land on. The end
+/// of the buffer is the field's `max` when it has one (e.g. `#[lain(max = 0x200)]`), and otherwise
+/// the length given to [Mutator::set_input_len](crate::mutator::Mutator::set_input_len).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct Offset<T>(T);
+
+impl<T: PrimInt> Offset<T> {
+    pub fn new(value: T) -> Self {
+        Offset(value)
+    }
+
+    pub fn value(&self) -> T {
+        self.0
+    }
+}
+
+/// Unsigned integer the width of the target's pointers, e.g. a `size_t` or an address. It's
+/// serialized with the pointer width and byte order of the current
+/// [TargetConfig](crate::target::TargetConfig), and generated and mutated within that width.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct TargetUsize(pub(crate) u64);
+
+impl TargetUsize {
+    pub fn new(value: u64) -> Self {
+        TargetUsize(value)
+    }
+
+    pub fn value(self) -> u64 {
+        self.0
+    }
+}
+
+/// Signed integer the width of the target's pointers, e.g. a `ssize_t` or `ptrdiff_t`. See
+/// [TargetUsize].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct TargetIsize(pub(crate) i64);
+
+impl TargetIsize {
+    pub fn new(value: i64) -> Self {
+        TargetIsize(value)
+    }
+
+    pub fn value(self) -> i64 {
+        self.0
+    }
+}
+
+/// A value followed by zeroes up to the next multiple of the current
+/// [TargetConfig](crate::target::TargetConfig)'s pointer size, like the tail padding of a C struct
+/// holding pointers. Everything else passes through to the value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct PointerAligned<T>(pub T);
+
+/// A text encoding of binary data, used by [TextEncoded]. Implement this on a marker type to
+/// define a new encoding.
+pub trait TextEncoding {
+    /// Character appended by [EncodingFlaw::BadPadding] when the text has no padding to remove,
+    /// which leaves it with an incomplete group
+    const STRAY_CHAR: u8;
+
+    /// Length of the text encoding `data_len` bytes
+    fn encoded_len(data_len: usize) -> usize;
+
+    /// Number of padding characters at the end of the text encoding `data_len` bytes
+    fn padding_len(data_len: usize) -> usize;
+
+    /// Appends the text encoding `data` to `output`
+    fn encode(data: &[u8], output: &mut Vec<u8>);
+
+    /// Decodes `text`, or returns `None` if it isn't valid
+    fn decode(text: &[u8]) -> Option<Vec<u8>>;
+
+    /// Whether `c` is a character (including padding) that valid text may contain
+    fn is_alphabet(c: u8) -> bool;
+}
+
+/// Lowercase hexadecimal, two characters per byte. Both cases are accepted when decoding.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Hex;
+
+impl TextEncoding for Hex {
+    const STRAY_CHAR: u8 = b'0';
+
+    fn encoded_len(data_len: usize) -> usize {
+        data_len * 2
+    }
+
+    fn padding_len(_data_len: usize) -> usize {
+        0
+    }
+
+    fn encode(data: &[u8], output: &mut Vec<u8>) {
+        const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+        for byte in data {
+            output.push(DIGITS[(byte >> 4) as usize]);
+            output.push(DIGITS[(byte & 0xF) as usize]);
+        }
+    }
+
+    fn decode(text: &[u8]) -> Option<Vec<u8>> {
+        if !text.len().is_multiple_of(2) {
+            return None;
+        }
+
+        text.chunks(2)
+            .map(|pair| {
+                let high = (pair[0] as char).to_digit(16)?;
+                let low = (pair[1] as char).to_digit(16)?;
+
+                Some((high << 4 | low) as u8)
+            })
+            .collect()
+    }
+
+    fn is_alphabet(c: u8) -> bool {
+        c.is_ascii_hexdigit()
+    }
+}
+
+/// Standard base64 (RFC 4648) with `=` padding. Unpadded text is rejected when decoding.
+#[cfg(feature = "base64_support")]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Base64;
+
+#[cfg(feature = "base64_support")]
+impl TextEncoding for Base64 {
+    const STRAY_CHAR: u8 = b'=';
+
+    fn encoded_len(data_len: usize) -> usize {
+        data_len.div_ceil(3) * 4
+    }
+
+    fn padding_len(data_len: usize) -> usize {
+        (3 - data_len % 3) % 3
+    }
+
+    fn encode(data: &[u8], output: &mut Vec<u8>) {
+        let start = output.len();
+        output.resize(start + Self::encoded_len(data.len()), 0);
+
+        base64::encode_config_slice(data, base64::STANDARD, &mut output[start..]);
+    }
+
+    fn decode(text: &[u8]) -> Option<Vec<u8>> {
+        // the base64 crate accepts missing padding, which would hide EncodingFlaw::BadPadding
+        if !text.len().is_multiple_of(4) {
+            return None;
+        }
+
+        base64::decode_config(text, base64::STANDARD).ok()
+    }
+
+    fn is_alphabet(c: u8) -> bool {
+        c.is_ascii_alphanumeric() || c == b'+' || c == b'/' || c == b'='
+    }
+}
+
+/// A way in which a [TextEncoded] value's text is made invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum EncodingFlaw {
+    /// The character at `offset` (wrapped to the text's length) is replaced by `c`, which isn't
+    /// part of the encoding's alphabet. Empty text has `c` appended instead.
+    InvalidChar { offset: usize, c: u8 },
+    /// The padding is removed, or if there is none a stray character is appended
+    BadPadding,
+    /// The last character is removed
+    Truncated,
+}
+
+impl EncodingFlaw {
+    /// Makes `text`, which encodes data with `padding_len` padding characters, invalid
+    pub fn apply<C: TextEncoding>(self, text: &mut Vec<u8>, padding_len: usize) {
+        match self {
+            EncodingFlaw::InvalidChar { c, .. } if text.is_empty() => text.push(c),
+            EncodingFlaw::InvalidChar { offset, c } => {
+                let offset = offset % text.len();
+                text[offset] = c;
+            }
+            EncodingFlaw::BadPadding if padding_len > 0 => {
+                let len = text.len() - padding_len;
+                text.truncate(len);
+            }
+            EncodingFlaw::BadPadding => text.push(C::STRAY_CHAR),
+            EncodingFlaw::Truncated => {
+                text.pop();
+            }
+        }
+    }
+
+    /// Length of text which was `len` characters long (with `padding_len` of them padding) after
+    /// [EncodingFlaw::apply]
+    pub fn applied_len(self, len: usize, padding_len: usize) -> usize {
+        match self {
+            EncodingFlaw::InvalidChar { .. } => cmp::max(len, 1),
+            EncodingFlaw::BadPadding if padding_len > 0 => len - padding_len,
+            EncodingFlaw::BadPadding => len + 1,
+            EncodingFlaw::Truncated => len.saturating_sub(1),
+        }
+    }
+}
+
+/// A value which is serialized as the text encoding (e.g. hex or base64) of its wire bytes, as
+/// found in text formats which carry binary blobs such as JSON APIs. The inner value is generated
+/// and mutated as usual, and the text is occasionally made invalid (see [EncodingFlaw]) so that
+/// the target's decoder is exercised too.
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct TextEncoded<T, C> {
+    pub value: T,
+    /// How the text is made invalid, or `None` if it's a valid encoding of `value`
+    pub flaw: Option<EncodingFlaw>,
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    encoding: PhantomData<C>,
+}
+
+/// A value serialized as hex text. See [TextEncoded].
+pub type HexEncoded<T> = TextEncoded<T, Hex>;
+
+/// A value serialized as base64 text. See [TextEncoded].
+#[cfg(feature = "base64_support")]
+pub type Base64Encoded<T> = TextEncoded<T, Base64>;
+
+impl<T, C: TextEncoding> TextEncoded<T, C> {
+    /// Creates a validly encoded value
+    pub fn new(value: T) -> Self {
+        TextEncoded {
+            value,
+            flaw: None,
+            encoding: PhantomData,
+        }
+    }
+
+    /// Creates a value whose text is made invalid by `flaw`
+    pub fn with_flaw(value: T, flaw: EncodingFlaw) -> Self {
+        TextEncoded {
+            value,
+            flaw: Some(flaw),
+            encoding: PhantomData,
+        }
+    }
+
+    /// Encodes `data`, the inner value's wire bytes, applying the flaw if there is one
+    pub fn encode(&self, data: &[u8], output: &mut Vec<u8>) {
+        let start = output.len();
+        C::encode(data, output);
+
+        if let Some(flaw) = self.flaw {
+            let mut text = output.split_off(start);
+            flaw.apply::<C>(&mut text, C::padding_len(data.len()));
+            output.extend_from_slice(&text);
+        }
+    }
+
+    /// Length of the text encoding `data_len` bytes of the inner value, including the flaw
+    pub fn encoded_len(&self, data_len: usize) -> usize {
+        let len = C::encoded_len(data_len);
+
+        match self.flaw {
+            Some(flaw) => flaw.applied_len(len, C::padding_len(data_len)),
+            None => len,
+        }
+    }
+}
+
+impl<T: Clone, C> Clone for TextEncoded<T, C> {
+    fn clone(&self) -> Self {
+        TextEncoded {
+            value: self.value.clone(),
+            flaw: self.flaw,
+            encoding: PhantomData,
+        }
+    }
+}
+
+impl<T: PartialEq, C> PartialEq for TextEncoded<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.flaw == other.flaw
+    }
+}
+
+impl<T: Default, C> Default for TextEncoded<T, C> {
+    fn default() -> Self {
+        TextEncoded {
+            value: T::default(),
+            flaw: None,
+            encoding: PhantomData,
+        }
+    }
+}
+
+impl<T: fmt::Debug, C> fmt::Debug for TextEncoded<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.flaw {
+            Some(flaw) => write!(f, "{:?} ({:?})", self.value, flaw),
+            None => write!(f, "{:?}", self.value),
+        }
+    }
+}
+
+/// A value picked from a pool of known-valid candidates, e.g. the session IDs a server handed out
+/// or the names of files which exist. Serialization passes through to the picked value.
+///
+/// Values are generated by picking one of the candidates registered for `T` with
+/// [Mutator::set_candidates](crate::mutator::Mutator::set_candidates), falling back to generating
+/// a `T` if there are none. Mutation usually picks another candidate and occasionally perturbs
+/// the picked value, so values near the valid ones are tried as well.
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct OneOf<T> {
+    value: T,
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    candidates: Arc<Vec<T>>,
+}
+
+impl<T> OneOf<T> {
+    /// Creates a value holding the first of `candidates`, which later mutations pick from.
+    /// Panics if there are no candidates.
+    pub fn new(candidates: Vec<T>) -> Self
+    where
+        T: Clone,
+    {
+        let value = candidates
+            .first()
+            .cloned()
+            .expect("OneOf requires at least one candidate");
+
+        OneOf {
+            value,
+            candidates: Arc::new(candidates),
+        }
+    }
+
+    /// Creates a value holding `value` which isn't necessarily one of `candidates`
+    pub fn with_value(value: T, candidates: Arc<Vec<T>>) -> Self {
+        OneOf { value, candidates }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// The candidates mutations pick from
+    pub fn candidates(&self) -> &[T] {
+        &self.candidates
+    }
+
+    /// Whether the value is one of the candidates, rather than a perturbed one
+    pub fn is_candidate(&self) -> bool
+    where
+        T: PartialEq,
+    {
+        self.candidates.contains(&self.value)
+    }
+}
+
+impl<T: Clone> Clone for OneOf<T> {
+    fn clone(&self) -> Self {
+        OneOf {
+            value: self.value.clone(),
+            candidates: self.candidates.clone(),
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for OneOf<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Default> Default for OneOf<T> {
+    fn default() -> Self {
+        OneOf {
+            value: T::default(),
+            candidates: Arc::new(Vec::new()),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OneOf<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.value)
+    }
+}
+
+/// An ordered exchange of messages with a session-oriented target, where later messages carry
+/// values declared by earlier ones (e.g. the session ID chosen by the first message). `M` is
+/// usually an enum with a variant per message type. Serialization writes the messages back to
+/// back.
+///
+/// References between messages are expressed with [Bind](crate::traits::Bind): when the
+/// conversation is fixed up, each message in turn reads the values declared before it, is fixed up
+/// itself, then declares its own values. Mutations of a referenced value therefore carry over to
+/// every message referencing it, except in the iterations which skip fixups.
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct Conversation<M> {
+    messages: Vec<M>,
+}
+
+impl<M> Conversation<M> {
+    /// Creates a conversation of `messages`. Their references aren't resolved until the
+    /// conversation is fixed up.
+    pub fn new(messages: Vec<M>) -> Self {
+        Conversation { messages }
+    }
+
+    pub fn messages(&self) -> &[M] {
+        &self.messages
+    }
+
+    pub fn messages_mut(&mut self) -> &mut Vec<M> {
+        &mut self.messages
+    }
+
+    pub fn into_messages(self) -> Vec<M> {
+        self.messages
+    }
+}
+
+/// Values declared by the messages of a [Conversation], keyed by name
+#[derive(Default)]
+pub struct Bindings {
+    values: HashMap<&'static str, Box<dyn Any>>,
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        Bindings::default()
+    }
+
+    /// Declares `value` as `name`, replacing any value declared earlier
+    pub fn declare<T: Any>(&mut self, name: &'static str, value: T) {
+        self.values.insert(name, Box::new(value));
+    }
+
+    /// Returns the value declared as `name`, or `None` if there's none or it isn't a `T`
+    pub fn get<T: Any>(&self, name: &str) -> Option<&T> {
+        self.values.get(name).and_then(|value| value.downcast_ref())
+    }
+
+    /// Overwrites `target` with the value declared as `name`. Returns whether there was one.
+    pub fn resolve<T: Any + Clone>(&self, name: &str, target: &mut T) -> bool {
+        match self.get(name) {
+            Some(value) => {
+                target.clone_from(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+impl fmt::Debug for Bindings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.values.keys()).finish()
+    }
+}
+
+/// Records which pair of adjacent fields a struct marked `#[lain(field_swap = "...")]` is
+/// serialized with in swapped order (see
+/// [MutatorConfig::field_swap_chance](crate::mutator::MutatorConfig::field_swap_chance)). The swap
+/// is part of the value, so copies and moves keep it, and it's cleared the next time the struct is
+/// mutated. It isn't serialized itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct FieldSwap(Option<u32>);
+
+impl FieldSwap {
+    /// Whether the struct is serialized with a pair of its fields swapped
+    pub fn is_swapped(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Has the struct serialized in declaration order again
+    pub fn clear(&mut self) {
+        self.0 = None;
+    }
+
+    pub(crate) fn set(&mut self, choice: u32) {
+        self.0 = Some(choice);
+    }
+
+    /// Returns the wire position of the first of the two adjacent fields to swap, picked from
+    /// `pairs`. This is used by types deriving `BinarySerialize`, and never allocates or panics so
+    /// that it's usable in `NoAllocSerialize` impls.
+    #[doc(hidden)]
+    #[inline]
+    pub fn swapped_pair(&self, pairs: &[usize]) -> Option<usize> {
+        match self.0 {
+            Some(choice) if !pairs.is_empty() => Some(pairs[choice as usize % pairs.len()]),
+            _ => None,
+        }
+    }
+}
+
+/// Represents a UTF-8 character.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct Utf8Char(pub(crate) char);
+
+/// Represents an ASCII character.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct AsciiChar(pub(crate) char);
+
+/// Data structure holding constraints that the [NewFuzzed::new_fuzzed][lain::traits::NewFuzzed::new_fuzzed] or
+/// [Mutatable::mutate][lain::traits::Mutatable::mutate] methods should try to respect.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct Constraints<T: Bounded + std::fmt::Debug> {
+    /// The contextual "min" bound
+    pub min: Option<T>,
+    /// The contextual "max" bound. This is exclusive unless `bounds` includes it.
+    pub max: Option<T>,
+    /// Which direction to weigh the RNG towards
+    pub weighted: Weighted,
+    /// The maximum size that the object has to work with
+    pub max_size: Option<usize>,
+    /// Whether `min` and `max` are included in the range. Wire specs usually give inclusive maxima,
+    /// which is [Bounds::Closed] (or `#[lain(max_inclusive = N)]` on a derived field).
+    pub bounds: Bounds,
+    /// Constraints for each element of a collection, e.g. the values of a `Vec<u32>` whose
+    /// `min`/`max` above bound its length. Given by `#[lain(element_min = N, element_max = M)]`
+    /// on a derived field. These aren't serialized since their type isn't known.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    pub element_constraints: Option<ElementConstraints>,
+}
+
+impl<T: Bounded + std::fmt::Debug> Constraints<T> {
+    /// Returns the constraints for each element, if they were given for elements with a
+    /// `RangeType` of `E`
+    pub fn elements<E: Bounded + std::fmt::Debug + 'static>(&self) -> Option<&Constraints<E>> {
+        self.element_constraints
+            .as_ref()
+            .and_then(ElementConstraints::get::<E>)
+    }
+}
+
+impl<T: Bounded + std::fmt::Debug + PartialOrd> Constraints<T> {
+    /// Returns whether `value` lies within the `min`/`max` bounds. Values which can't be compared
+    /// (e.g. NaN) are only considered in range if there are no bounds.
+    pub fn contains(&self, value: &T) -> bool {
+        let above_min = match self.min {
+            Some(ref min) => match self.bounds {
+                Bounds::ClosedOpen | Bounds::Closed => value >= min,
+                Bounds::Open | Bounds::OpenClosed => value > min,
+            },
+            None => true,
+        };
+
+        let below_max = match self.max {
+            Some(ref max) => match self.bounds {
+                Bounds::ClosedOpen | Bounds::Open => value < max,
+                Bounds::Closed | Bounds::OpenClosed => value <= max,
+            },
+            None => true,
+        };
+
+        above_min && below_max
+    }
+
+    /// Like [Constraints::contains], but returns an [Error::ConstraintViolation] describing the
+    /// value and bounds when `value` is out of range
+    pub fn check(&self, value: &T) -> crate::Result<()> {
+        if self.contains(value) {
+            return Ok(());
+        }
+
+        Err(Error::ConstraintViolation {
+            value: format!("{:?}", value),
+            constraints: format!("{:?}..{:?} ({:?})", self.min, self.max, self.bounds),
+        })
+    }
+}
+
+impl<T: Bounded + std::fmt::Debug + PrimInt> Constraints<T> {
+    /// The smallest integer in range, or `None` if there's no `min`
+    pub fn inclusive_min(&self) -> Option<T> {
+        self.min.map(|min| {
+            if self.bounds.includes_min() {
+                min
+            } else {
+                min.saturating_add(T::one())
+            }
+        })
+    }
+
+    /// The largest integer in range, or `None` if there's no `max`
+    pub fn inclusive_max(&self) -> Option<T> {
+        self.max.map(|max| {
+            if self.bounds.includes_max() {
+                max
+            } else {
+                max.saturating_sub(T::one())
+            }
+        })
+    }
+
+    /// One past the largest integer in range, or `None` if there's no `max`. This saturates at
+    /// `T::max_value()`, so it's meant for lengths and counts which never get that large; use
+    /// [Constraints::inclusive_max] where the whole range of `T` matters.
+    pub fn exclusive_max(&self) -> Option<T> {
+        self.max.map(|max| {
+            if self.bounds.includes_max() {
+                max.saturating_add(T::one())
+            } else {
+                max
+            }
+        })
+    }
+}
+
+/// The [Constraints] for the elements of a collection. An element's `RangeType` usually isn't the
+/// collection's (a `Vec<u32>` is constrained by `usize` lengths), so the element constraints are
+/// held without their type and are only used by collections of elements with a matching one.
+#[derive(Clone)]
+pub struct ElementConstraints(Arc<dyn AnyConstraints>);
+
+impl ElementConstraints {
+    pub fn new<T>(constraints: Constraints<T>) -> Self
+    where
+        T: Bounded + fmt::Debug + Send + Sync + 'static,
+    {
+        ElementConstraints(Arc::new(constraints))
+    }
+
+    /// Returns the constraints if they're for elements with a `RangeType` of `T`
+    pub fn get<T: Bounded + fmt::Debug + 'static>(&self) -> Option<&Constraints<T>> {
+        self.0.as_any().downcast_ref::<Constraints<T>>()
+    }
+}
+
+impl fmt::Debug for ElementConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Object-safe view of a [Constraints] of any type
+trait AnyConstraints: fmt::Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T> AnyConstraints for Constraints<T>
+where
+    T: Bounded + fmt::Debug + Send + Sync + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Which of the bounds of a [Constraints] range are included in the range.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum Bounds {
+    /// `min <= x < max`
+    #[default]
+    ClosedOpen,
+    /// `min <= x <= max`
+    Closed,
+    /// `min < x < max`
+    Open,
+    /// `min < x <= max`
+    OpenClosed,
+}
+
+impl Bounds {
+    /// Whether `min` is part of the range
+    pub fn includes_min(self) -> bool {
+        self == Bounds::ClosedOpen || self == Bounds::Closed
+    }
+
+    /// Whether `max` is part of the range
+    pub fn includes_max(self) -> bool {
+        self == Bounds::Closed || self == Bounds::OpenClosed
+    }
+}
+
+/// Which direction to weigh ranges towards (min bound, upper bound, or none).
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum Weighted {
+    None,
+    Min,
+    Max,
+}
+
+impl Default for Weighted {
+    fn default() -> Self {
+        Weighted::None
+    }
+}
+
+/// A single difference between two instances as reported by
+/// [StructuredDiff::diff][lain::traits::StructuredDiff::diff].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDelta {
+    /// Path to the field which differs (e.g. `header.length` or `entries[3].id`). This
+    /// is empty if the value being diffed is itself a leaf.
+    pub path: String,
+    /// How the field differs
+    pub kind: DeltaKind,
+}
+
+/// Describes how a field differs between two instances.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeltaKind {
+    /// A leaf value changed. Values are stored in their `Debug` representation.
+    Value { old: String, new: String },
+    /// A collection changed its number of elements
+    Length { old: usize, new: usize },
+    /// An enum changed to a different variant
+    Variant { old: String, new: String },
+}
+
+impl FieldDelta {
+    pub fn new(kind: DeltaKind) -> Self {
+        FieldDelta {
+            path: String::new(),
+            kind,
+        }
+    }
+
+    /// Prepends `parent` to this delta's path. Used by derived impls to build the full
+    /// path as deltas bubble up from nested fields.
+    pub fn prefixed(mut self, parent: &str) -> Self {
+        if self.path.is_empty() {
+            self.path = parent.to_string();
+        } else if self.path.starts_with('[') {
+            self.path = format!("{}{}", parent, self.path);
+        } else {
+            self.path = format!("{}.{}", parent, self.path);
+        }
+
+        self
+    }
+
+    /// Returns the top-level field name of this delta's path. This is a reasonable key for
+    /// bucketing inputs by which region of the structure was perturbed.
+    pub fn root_field(&self) -> &str {
+        let end = self.path.find(['.', '[']).unwrap_or(self.path.len());
+
+        &self.path[..end]
+    }
+}
+
+/// Describes a field of a type as reported by
+/// [FieldMetadata::field_info][lain::traits::FieldMetadata::field_info].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldInfo {
+    /// The field's name
+    pub name: &'static str,
+    /// The field's type with module paths stripped (e.g. `Vec<Header>`)
+    pub type_name: String,
+    /// The field's attribute settings as they were written, e.g. `min = 1` or `byteorder(big)`.
+    /// Settings given with the deprecated standalone attributes are listed by their
+    /// `#[lain(...)]` spelling.
+    pub attributes: Vec<&'static str>,
+}
+
+impl FieldInfo {
+    /// Returns the value of the attribute setting `name`: the right-hand side of `name = value`,
+    /// the arguments of `name(...)`, or an empty string if it's a bare word such as `ignore`.
+    /// Returns `None` if the field doesn't have the setting.
+    pub fn attribute(&self, name: &str) -> Option<&'static str> {
+        self.attributes.iter().find_map(|setting| {
+            let rest = setting.strip_prefix(name)?;
+
+            if rest.is_empty() {
+                Some(rest)
+            } else if let Some(value) = rest.strip_prefix(" = ") {
+                Some(value)
+            } else if rest.starts_with('(') && rest.ends_with(')') {
+                Some(&rest[1..rest.len() - 1])
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Where a field of a value is in the value's serialized form, as reported by
+/// [FieldMetadata::field_layout][lain::traits::FieldMetadata::field_layout].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldLayout {
+    /// Path to the field (e.g. `header.length`)
+    pub path: String,
+    /// The field's type with module paths stripped
+    pub type_name: String,
+    /// Offset of the field's first byte from the start of the serialized value
+    pub offset: usize,
+    /// The field's serialized size in bytes. Fields which aren't part of the serialized value,
+    /// such as those excluded by a `#[lain(version_gate)]`, have a size of 0.
+    pub size: usize,
+    /// For bitfields, the bits of the backing value which hold the field, counted from the least
+    /// significant bit. `offset` and `size` then describe the whole backing value.
+    pub bits: Option<std::ops::Range<usize>>,
+}
+
+impl FieldLayout {
+    /// Prepends `parent` to this field's path and moves it to `offset` within the parent. Used by
+    /// derived impls to place the fields of nested structs. Fields of a flattened struct are
+    /// given an empty `parent` so that their paths are unchanged.
+    pub fn prefixed(mut self, parent: &str, offset: usize) -> Self {
+        if parent.is_empty() || self.path.starts_with('[') {
+            self.path = format!("{}{}", parent, self.path);
+        } else {
+            self.path = format!("{}.{}", parent, self.path);
+        }
+
+        self.offset += offset;
+        self
+    }
+
+    /// Whether the byte at `offset` of the serialized value belongs to this field
+    pub fn contains(&self, offset: usize) -> bool {
+        offset >= self.offset && offset < self.offset + self.size
     }
 }
@@ -0,0 +1,126 @@
+//! Bounded history of mutations which allows a harness to revert the most recent mutations
+//! when the target rejects an input. This makes it possible to build hill-climbing style
+//! search loops on top of lain.
+//!
+//! The history is a ring of snapshots: every entry holds a full copy of the value from before its
+//! mutation along with the fields which the mutation changed, so memory use grows with the size
+//! of `T` times the log's capacity. A log with a capacity of 0 records nothing and doesn't copy
+//! the value.
+//!
+//!
+//! ```compile_fail
+//! let mut history = UndoLog::new(16);
+//!
+//! loop {
+//!     mutator.begin_new_iteration();
+//!     history.mutate(&mut input, &mut mutator, None);
+//!
+//!     if !target_accepts(&input) {
+//!         history.undo(&mut input);
+//!     }
+//! }
+//! ```
+
+use crate::mutator::Mutator;
+use crate::rand::Rng;
+use crate::traits::*;
+use crate::types::*;
+
+use std::collections::VecDeque;
+
+/// A single recorded mutation: a snapshot of the value before it and the fields it changed.
+#[derive(Debug, Clone)]
+pub struct UndoEntry<T> {
+    /// The fields which were changed by this mutation
+    pub deltas: Vec<FieldDelta>,
+    previous: T,
+}
+
+impl<T> UndoEntry<T> {
+    /// The value as it was before this mutation was applied
+    pub fn previous(&self) -> &T {
+        &self.previous
+    }
+}
+
+/// Ring buffer of snapshots taken before the most recent mutations applied to a value. Once
+/// `capacity` entries have been recorded the oldest entry is discarded.
+#[derive(Debug, Clone)]
+pub struct UndoLog<T> {
+    entries: VecDeque<UndoEntry<T>>,
+    capacity: usize,
+}
+
+impl<T> UndoLog<T>
+where
+    T: Mutatable + StructuredDiff + Clone,
+{
+    pub fn new(capacity: usize) -> UndoLog<T> {
+        UndoLog {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Mutates `value` and records the change. Returns the fields which were changed, as reported
+    /// by [StructuredDiff]. Every mutation is recorded, including ones which didn't change
+    /// anything, so that [UndoLog::undo] always reverts the mutation made last. Types which don't
+    /// implement [StructuredDiff] report no changed fields but are still reverted. With a capacity
+    /// of 0 this only mutates `value`.
+    pub fn mutate<R: Rng>(
+        &mut self,
+        value: &mut T,
+        mutator: &mut Mutator<R>,
+        constraints: Option<&Constraints<u8>>,
+    ) -> &[FieldDelta] {
+        if self.capacity == 0 {
+            value.mutate(mutator, constraints);
+            return &[];
+        }
+
+        let previous = value.clone();
+        value.mutate(mutator, constraints);
+
+        let deltas = previous.diff(value);
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(UndoEntry { deltas, previous });
+
+        &self.entries.back().unwrap().deltas
+    }
+
+    /// Reverts the most recently recorded mutation on `value`. Returns the fields it had changed,
+    /// or `None` if there is no history left.
+    pub fn undo(&mut self, value: &mut T) -> Option<Vec<FieldDelta>> {
+        let entry = self.entries.pop_back()?;
+        *value = entry.previous;
+
+        Some(entry.deltas)
+    }
+
+    /// Returns the most recently recorded mutation
+    pub fn last(&self) -> Option<&UndoEntry<T>> {
+        self.entries.back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Discards all recorded mutations. This should be called once a mutated input has been
+    /// accepted and should be kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
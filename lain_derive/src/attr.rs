@@ -1,5 +1,7 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Delimiter, Group, Spacing, Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned};
 use std::str::FromStr;
+use syn::spanned::Spanned;
 use syn::Meta::{List, NameValue};
 use syn::NestedMeta::Meta;
 
@@ -12,16 +14,22 @@ pub struct BitfieldMetadata {
     pub bit_count: usize,
 }
 
-pub(crate) fn get_bitfield_limits(
-    meta: impl Iterator<Item = Vec<syn::NestedMeta>>,
-) -> Option<BitfieldMetadata> {
+/// Parses the `#[bitfield(backing_type = "u8", bits = 1)]` attribute of `field`, if it has one
+pub(crate) fn get_bitfield_limits(field: &syn::Field) -> syn::Result<Option<BitfieldMetadata>> {
     let mut bm = BitfieldMetadata::default();
 
-    for meta_items in meta {
+    for meta_items in get_bitfield_metadata(&field.attrs)? {
         for meta_item in meta_items {
             match meta_item {
                 Meta(NameValue(ref m)) if m.ident == "backing_type" => {
-                    let lit_str = get_lit_str(&m.lit).unwrap().value();
+                    let lit_str = get_lit_str(&m.lit)
+                        .map_err(|_| {
+                            syn::Error::new_spanned(
+                                &m.lit,
+                                "#[bitfield(backing_type)] expects a string, e.g. #[bitfield(backing_type = \"u32\", bits = 10)]",
+                            )
+                        })?
+                        .value();
                     match lit_str.as_ref() {
                         "u8" => {
                             bm.ty_bits = 8;
@@ -36,43 +44,62 @@ pub(crate) fn get_bitfield_limits(
                             bm.ty_bits = 64;
                         }
                         _ => {
-                            panic!("unsupported backing type for bitfield -- must be u8, u16, u32, or u64");
+                            return Err(syn::Error::new_spanned(
+                                &m.lit,
+                                format!("unsupported backing type `{}` for bitfield -- must be u8, u16, u32, or u64", lit_str),
+                            ));
                         }
                     }
 
                     bm.ty = Some(TokenStream::from_str(&lit_str).unwrap());
                 }
                 Meta(NameValue(ref m)) if m.ident == "bits" => {
-                    let bit_count = get_lit_number(&m.lit).unwrap().value();
+                    let bit_count = get_lit_number(&m.lit)
+                        .map_err(|_| {
+                            syn::Error::new_spanned(
+                                &m.lit,
+                                "#[bitfield(bits)] expects an integer, e.g. #[bitfield(backing_type = \"u32\", bits = 10)]",
+                            )
+                        })?
+                        .value();
                     if bit_count > 64 {
-                        panic!("bit count is larger than 64");
+                        return Err(syn::Error::new_spanned(
+                            &m.lit,
+                            "bit count is larger than 64",
+                        ));
                     }
 
                     bm.bit_count = bit_count as usize;
                     bm.max = 2_u64.pow(bit_count as u32);
                 }
-                _ => {
-                    panic!(
-                        "unexpected meta type in get_bitfield_limits -- should be literals only"
-                    );
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "unknown #[bitfield] attribute. Expected `backing_type` or `bits`",
+                    ));
                 }
             }
         }
     }
 
     if (bm.ty.is_some() && bm.bit_count == 0) || (bm.bit_count > 0 && bm.ty.is_none()) {
-        panic!("#[bitfield] requires type and bits to be supplied. e.g. #[bitfield(backing_type=u32, bits=10)]")
+        return Err(syn::Error::new_spanned(
+            field,
+            "#[bitfield] requires type and bits to be supplied. e.g. #[bitfield(backing_type = \"u32\", bits = 10)]",
+        ));
     }
 
     if bm.ty.is_some() {
-        return Some(bm);
+        return Ok(Some(bm));
     }
 
-    None
+    Ok(None)
 }
 
-pub(crate) fn get_bitfield_metadata(attr: &syn::Attribute) -> Option<Vec<syn::NestedMeta>> {
-    get_attribute_metadata("bitfield", &attr)
+pub(crate) fn get_bitfield_metadata(
+    attrs: &[syn::Attribute],
+) -> syn::Result<Vec<Vec<syn::NestedMeta>>> {
+    get_field_metadata("bitfield", attrs)
 }
 
 pub(crate) fn get_attribute_metadata(
@@ -115,6 +142,967 @@ pub(crate) fn get_lit_bool(lit: &syn::Lit) -> Result<&syn::LitBool, ()> {
     Err(())
 }
 
-pub(crate) fn get_fuzzer_metadata(attr: &syn::Attribute) -> Option<Vec<syn::NestedMeta>> {
-    get_attribute_metadata("fuzzer", &attr)
+pub(crate) fn get_fuzzer_metadata(
+    attrs: &[syn::Attribute],
+) -> syn::Result<Vec<Vec<syn::NestedMeta>>> {
+    get_field_metadata("fuzzer", attrs)
+}
+
+/// Field and variant attributes which are nested inside `#[lain(...)]` under their own name,
+/// e.g. `#[lain(bitfield(backing_type = "u8", bits = 1))]`
+const LAIN_NESTED_ATTRIBUTES: &[&str] = &["bitfield", "byteorder", "weight", "pin"];
+
+/// Returns the meta items of every `#[name(...)]` attribute in `attrs`, along with the same items
+/// given in the unified `#[lain(...)]` namespace. The standalone spellings are kept as deprecated
+/// aliases.
+///
+/// `#[fuzzer(...)]` items are written directly in `#[lain(...)]` (`#[lain(min = 0, max = 10)]`),
+/// while the other attributes are nested under their name (`#[lain(byteorder(big))]`).
+pub(crate) fn get_field_metadata(
+    name: &'static str,
+    attrs: &[syn::Attribute],
+) -> syn::Result<Vec<Vec<syn::NestedMeta>>> {
+    let mut metadata = Vec::new();
+
+    for attr in attrs {
+        if let Some(meta_items) = get_attribute_metadata(name, attr) {
+            metadata.push(meta_items);
+            continue;
+        }
+
+        let mut meta_items = Vec::new();
+
+        for item in get_lain_items(attr) {
+            let is_nested = LAIN_NESTED_ATTRIBUTES
+                .iter()
+                .any(|nested| is_lain_item(&item, nested));
+
+            if name == "fuzzer" && !is_nested {
+                let meta_item = syn::parse2::<syn::NestedMeta>(item.clone()).map_err(|_| {
+                    syn::Error::new_spanned(
+                        &item,
+                        "expected an attribute such as `min = 0` or `ignore`. Values which aren't literals (e.g. negative numbers) must be quoted, e.g. `min = \"-1\"`",
+                    )
+                })?;
+
+                meta_items.push(meta_item);
+            } else if is_lain_item(&item, name) {
+                let args = get_nested_args(name, &item)?;
+                let nested = syn::parse::Parser::parse2(
+                    syn::punctuated::Punctuated::<syn::NestedMeta, syn::Token![,]>::parse_terminated,
+                    args,
+                )?;
+
+                meta_items.extend(nested);
+            }
+        }
+
+        if !meta_items.is_empty() {
+            metadata.push(meta_items);
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Splits the arguments of a `#[lain(...)]` attribute into its top-level, comma-separated items.
+/// Returns nothing for other attributes.
+pub(crate) fn get_lain_items(attr: &syn::Attribute) -> Vec<TokenStream> {
+    if attr.path.segments.len() != 1 || attr.path.segments[0].ident != "lain" {
+        return Vec::new();
+    }
+
+    let args = match attr.tts.clone().into_iter().next() {
+        Some(TokenTree::Group(ref g)) if g.delimiter() == Delimiter::Parenthesis => g.stream(),
+        _ => return Vec::new(),
+    };
+
+    let mut items = Vec::new();
+    let mut item = TokenStream::new();
+    for token in args {
+        match token {
+            TokenTree::Punct(ref p) if p.as_char() == ',' => {
+                if !item.is_empty() {
+                    items.push(std::mem::replace(&mut item, TokenStream::new()));
+                }
+            }
+            other => item.extend(std::iter::once(other)),
+        }
+    }
+
+    if !item.is_empty() {
+        items.push(item);
+    }
+
+    items
+}
+
+/// Standalone attributes which are deprecated aliases of their `#[lain(...)]` spelling, along with
+/// the marker in `lain::deprecated_attributes` which makes the compiler warn about them
+const DEPRECATED_ATTRIBUTES: &[(&str, &str)] = &[
+    ("fuzzer", "FUZZER"),
+    ("bitfield", "BITFIELD"),
+    ("byteorder", "BYTEORDER"),
+    ("weight", "WEIGHT"),
+    ("serialized_size", "SERIALIZED_SIZE"),
+];
+
+/// Returns items which reference a `#[deprecated]` marker for every deprecated standalone
+/// attribute on the type, its variants, or its fields. The references are spanned to the
+/// attribute so that the compiler's deprecation warning points at it.
+pub(crate) fn deprecated_attribute_warnings(input: &syn::DeriveInput) -> TokenStream {
+    let mut attrs: Vec<&syn::Attribute> = input.attrs.iter().collect();
+
+    match input.data {
+        syn::Data::Struct(ref data) => {
+            attrs.extend(data.fields.iter().flat_map(|field| &field.attrs));
+        }
+        syn::Data::Enum(ref data) => {
+            for variant in &data.variants {
+                attrs.extend(&variant.attrs);
+                attrs.extend(variant.fields.iter().flat_map(|field| &field.attrs));
+            }
+        }
+        syn::Data::Union(ref data) => {
+            attrs.extend(data.fields.named.iter().flat_map(|field| &field.attrs));
+        }
+    }
+
+    let mut warnings = TokenStream::new();
+
+    for attr in attrs {
+        if attr.path.segments.len() != 1 {
+            continue;
+        }
+
+        let name = attr.path.segments[0].ident.to_string();
+        if let Some((_, marker)) = DEPRECATED_ATTRIBUTES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+        {
+            let marker = syn::Ident::new(marker, attr.path.span());
+            warnings.extend(quote_spanned! {attr.path.span()=>
+                const _: () = ::lain::deprecated_attributes::#marker;
+            });
+        }
+    }
+
+    warnings
+}
+
+/// Attributes whose settings are reported by the derived `FieldMetadata::field_info`
+const SETTINGS_ATTRIBUTES: &[&str] = &["fuzzer", "bitfield", "byteorder", "weight"];
+
+/// Returns a field's attribute settings as they were written, with the items of the deprecated
+/// standalone attributes given by their `#[lain(...)]` spelling (e.g. `byteorder(big)`).
+pub(crate) fn get_attribute_settings(attrs: &[syn::Attribute]) -> Vec<String> {
+    let mut settings = Vec::new();
+
+    for attr in attrs {
+        if attr.path.segments.len() != 1 {
+            continue;
+        }
+
+        let name = attr.path.segments[0].ident.to_string();
+        if name == "lain" {
+            settings.extend(get_lain_items(attr).into_iter().map(compact_tokens));
+        } else if name == "fuzzer" {
+            if let Some(items) = get_attribute_metadata("fuzzer", attr) {
+                settings.extend(items.iter().map(|item| compact_tokens(quote! {#item})));
+            }
+        } else if SETTINGS_ATTRIBUTES.contains(&name.as_str()) {
+            let ident = &attr.path.segments[0].ident;
+            let args = &attr.tts;
+            settings.push(compact_tokens(quote! {#ident #args}));
+        }
+    }
+
+    settings
+}
+
+/// Renders tokens the way they'd usually be written, without the spaces `TokenStream`'s
+/// `Display` puts between every token (`min = 1`, `pin(0..16)`, `weight(30%)`)
+fn compact_tokens(tokens: TokenStream) -> String {
+    let mut output = String::new();
+    let mut previous_word = false;
+    let mut previous_joint = false;
+
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ref ident) => {
+                if previous_word {
+                    output.push(' ');
+                }
+                output.push_str(&ident.to_string());
+                previous_word = true;
+                previous_joint = false;
+            }
+            TokenTree::Literal(ref literal) => {
+                if previous_word {
+                    output.push(' ');
+                }
+                output.push_str(&literal.to_string());
+                previous_word = true;
+                previous_joint = false;
+            }
+            TokenTree::Punct(ref p) => {
+                match p.as_char() {
+                    '=' if !previous_joint && p.spacing() == Spacing::Alone => output.push_str(" = "),
+                    ',' => output.push_str(", "),
+                    c => output.push(c),
+                }
+                previous_word = false;
+                previous_joint = p.spacing() == Spacing::Joint;
+            }
+            TokenTree::Group(ref g) => {
+                let (open, close) = match g.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::None => ("", ""),
+                };
+                output.push_str(open);
+                output.push_str(&compact_tokens(g.stream()));
+                output.push_str(close);
+                previous_word = false;
+                previous_joint = false;
+            }
+        }
+    }
+
+    output
+}
+
+/// Whether a `#[lain(...)]` item starts with `name`, e.g. `pin(0..16)` for `pin`
+fn is_lain_item(item: &TokenStream, name: &str) -> bool {
+    match item.clone().into_iter().next() {
+        Some(TokenTree::Ident(ref ident)) => ident == name,
+        _ => false,
+    }
+}
+
+/// Returns the arguments of a nested `#[lain(name(...))]` item
+fn get_nested_args(name: &str, item: &TokenStream) -> syn::Result<TokenStream> {
+    let mut tokens = item.clone().into_iter().skip(1);
+
+    match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Group(ref g)), None) if g.delimiter() == Delimiter::Parenthesis => {
+            Ok(g.stream())
+        }
+        _ => Err(syn::Error::new_spanned(
+            item,
+            format!("expected a parenthesized list, e.g. #[lain({}(...))]", name),
+        )),
+    }
+}
+
+/// Returns the integer type given in an enum's `#[repr()]` attribute, if any. Non-integer
+/// representations such as `C` or `packed` are ignored.
+pub(crate) fn get_repr_type(attrs: &[syn::Attribute]) -> Option<TokenStream> {
+    for meta_items in attrs.iter().filter_map(|a| get_attribute_metadata("repr", a)) {
+        for meta_item in meta_items {
+            if let Meta(syn::Meta::Word(ref w)) = meta_item {
+                match w.to_string().as_ref() {
+                    "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64" => {
+                        return Some(TokenStream::from_str(&w.to_string()).unwrap());
+                    }
+                    _ => continue,
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Whether the container's `#[repr()]` attributes include `representation`, e.g. `packed`
+pub(crate) fn has_repr(attrs: &[syn::Attribute], representation: &str) -> bool {
+    attrs
+        .iter()
+        .filter_map(|a| get_attribute_metadata("repr", a))
+        .flatten()
+        .any(|meta_item| match meta_item {
+            Meta(syn::Meta::Word(ref w)) => w == representation,
+            _ => false,
+        })
+}
+
+/// Container-level attributes given with `#[lain(...)]`
+#[derive(Default)]
+pub struct ContainerMetadata {
+    /// The type this container mirrors, from `#[lain(remote = "other_crate::Type")]`
+    pub remote: Option<TokenStream>,
+    /// The named seed domain this container's randomness is derived from, from
+    /// `#[lain(seed_domain = "header")]`
+    pub seed_domain: Option<String>,
+    /// A fixed serialized size, from `#[lain(serialized_size = 16)]`
+    pub serialized_size: Option<usize>,
+    /// An expression returning the template new values are mutated from, from
+    /// `#[lain(seed_from = "load_template()")]`
+    pub seed_from: Option<TokenStream>,
+    /// Whether fields are serialized in their `#[lain(order = N)]` order rather than declaration
+    /// order, from `#[lain(sort_by_order)]`
+    pub sort_by_order: bool,
+    /// The percent chance that the container's `Vec` fields have an element duplicated while
+    /// being mutated, from `#[lain(duplicate_chance = 5.0)]`
+    pub duplicate_chance: Option<f32>,
+    /// Invariants checked in debug builds after the container is generated or mutated, from
+    /// `#[lain(assert = "self.len as usize == self.data.len()")]`
+    pub asserts: Vec<syn::LitStr>,
+    /// Whether to implement `NoAllocSerialize`, which requires serialization that never allocates
+    /// or panics, from `#[lain(no_alloc_serialize)]`
+    pub no_alloc_serialize: bool,
+    /// How many times the container is fixed up after it's generated or mutated, from
+    /// `#[lain(fixup_passes = 2)]`
+    pub fixup_passes: Option<usize>,
+    /// Whether `SerializedSize` counts the bytes written by serializing the container rather than
+    /// adding up its fields' sizes, from `#[lain(counted_size)]`
+    pub counted_size: bool,
+    /// Whether the container's serialized layout is checked against its `#[repr(C, packed)]`
+    /// layout at compile time, from `#[lain(packed_layout)]`
+    pub packed_layout: bool,
+    /// Whether a `#[lain(packed_layout)]` container is serialized with a single copy of its bytes
+    /// when the byte order is native, from `#[lain(memcpy_serialize)]`
+    pub memcpy_serialize: bool,
+    /// Whether `Refresh` is implemented even though the container has no volatile fields of its
+    /// own, so that it refreshes the nested structs which do, from `#[lain(refresh)]`
+    pub refresh: bool,
+    /// Whether `FieldMetadata` is implemented for the container, from `#[lain(field_metadata)]`
+    pub field_metadata: bool,
+    /// Whether `InputShape` is implemented for the container, from `#[lain(input_shape)]`
+    pub input_shape: bool,
+    /// The `FieldSwap` field recording which of the container's fields are serialized in swapped
+    /// order, from `#[lain(field_swap = "wire_order")]`
+    pub field_swap: Option<syn::Ident>,
+}
+
+/// An invariant from `#[lain(assert = "...")]` on a struct or one of its fields
+pub(crate) struct Invariant {
+    /// The container, or `Container.field` for invariants given on a field
+    pub path: String,
+    pub expr: syn::LitStr,
+}
+
+pub(crate) fn get_container_metadata(attrs: &[syn::Attribute]) -> syn::Result<ContainerMetadata> {
+    let mut cm = ContainerMetadata::default();
+
+    for meta_items in attrs.iter().filter_map(|a| get_attribute_metadata("lain", a)) {
+        for meta_item in meta_items {
+            match meta_item {
+                Meta(NameValue(ref m)) if m.ident == "remote" => {
+                    let remote = get_lit_str(&m.lit)
+                        .map_err(|_| {
+                            syn::Error::new_spanned(
+                                &m.lit,
+                                "#[lain(remote)] expects a string, e.g. #[lain(remote = \"other_crate::Type\")]",
+                            )
+                        })?
+                        .value();
+
+                    cm.remote = Some(TokenStream::from_str(&remote).map_err(|_| {
+                        syn::Error::new_spanned(&m.lit, format!("`{}` is not a valid type", remote))
+                    })?);
+                }
+                Meta(NameValue(ref m)) if m.ident == "seed_domain" => {
+                    let domain = get_lit_str(&m.lit)
+                        .map_err(|_| {
+                            syn::Error::new_spanned(
+                                &m.lit,
+                                "#[lain(seed_domain)] expects a string, e.g. #[lain(seed_domain = \"header\")]",
+                            )
+                        })?
+                        .value();
+
+                    cm.seed_domain = Some(domain);
+                }
+                Meta(NameValue(ref m)) if m.ident == "serialized_size" => {
+                    let size = get_lit_number(&m.lit).map_err(|_| {
+                        syn::Error::new_spanned(
+                            &m.lit,
+                            "#[lain(serialized_size)] expects an integer, e.g. #[lain(serialized_size = 16)]",
+                        )
+                    })?;
+
+                    cm.serialized_size = Some(size.value() as usize);
+                }
+                Meta(NameValue(ref m)) if m.ident == "seed_from" => {
+                    let expr = get_lit_str(&m.lit)
+                        .map_err(|_| {
+                            syn::Error::new_spanned(
+                                &m.lit,
+                                "#[lain(seed_from)] expects a string, e.g. #[lain(seed_from = \"load_template()\")]",
+                            )
+                        })?
+                        .parse::<TokenStream>()?;
+
+                    cm.seed_from = Some(expr);
+                }
+                Meta(NameValue(ref m)) if m.ident == "duplicate_chance" => {
+                    cm.duplicate_chance = Some(match m.lit {
+                        syn::Lit::Float(ref f) => f.value() as f32,
+                        syn::Lit::Int(ref i) => i.value() as f32,
+                        ref other => {
+                            return Err(syn::Error::new_spanned(
+                                other,
+                                "#[lain(duplicate_chance)] expects a percentage, e.g. #[lain(duplicate_chance = 5.0)]",
+                            ))
+                        }
+                    });
+                }
+                Meta(NameValue(ref m)) if m.ident == "fixup_passes" => {
+                    let passes = get_lit_number(&m.lit)
+                        .ok()
+                        .filter(|passes| passes.value() != 0)
+                        .ok_or_else(|| {
+                            syn::Error::new_spanned(
+                                &m.lit,
+                                "#[lain(fixup_passes)] expects a positive integer, e.g. #[lain(fixup_passes = 2)]",
+                            )
+                        })?;
+
+                    cm.fixup_passes = Some(passes.value() as usize);
+                }
+                Meta(syn::Meta::Word(ref ident)) if ident == "sort_by_order" => {
+                    cm.sort_by_order = true;
+                }
+                Meta(syn::Meta::Word(ref ident)) if ident == "no_alloc_serialize" => {
+                    cm.no_alloc_serialize = true;
+                }
+                Meta(syn::Meta::Word(ref ident)) if ident == "counted_size" => {
+                    cm.counted_size = true;
+                }
+                Meta(syn::Meta::Word(ref ident)) if ident == "packed_layout" => {
+                    cm.packed_layout = true;
+                }
+                Meta(syn::Meta::Word(ref ident)) if ident == "memcpy_serialize" => {
+                    cm.memcpy_serialize = true;
+                }
+                Meta(syn::Meta::Word(ref ident)) if ident == "refresh" => {
+                    cm.refresh = true;
+                }
+                Meta(syn::Meta::Word(ref ident)) if ident == "field_metadata" => {
+                    cm.field_metadata = true;
+                }
+                Meta(syn::Meta::Word(ref ident)) if ident == "input_shape" => {
+                    cm.input_shape = true;
+                }
+                Meta(NameValue(ref m)) if m.ident == "field_swap" => {
+                    let field = get_lit_str(&m.lit).map_err(|_| {
+                        syn::Error::new_spanned(
+                            &m.lit,
+                            "#[lain(field_swap)] should name the container's `lain::types::FieldSwap` field, e.g. #[lain(field_swap = \"wire_order\")]",
+                        )
+                    })?;
+
+                    cm.field_swap = Some(field.parse()?);
+                }
+                Meta(NameValue(ref m)) if m.ident == "assert" => {
+                    let expr = get_lit_str(&m.lit).map_err(|_| {
+                        syn::Error::new_spanned(
+                            &m.lit,
+                            "#[lain(assert)] expects a string, e.g. #[lain(assert = \"self.len as usize == self.data.len()\")]",
+                        )
+                    })?;
+
+                    cm.asserts.push(expr.clone());
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "unknown #[lain] attribute. Expected `remote`, `seed_domain`, `seed_from`, `serialized_size`, `sort_by_order`, `duplicate_chance`, `assert`, `no_alloc_serialize`, `counted_size`, `packed_layout`, `memcpy_serialize`, `refresh`, `field_metadata`, `input_shape`, `field_swap`, or `fixup_passes`",
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(cm)
+}
+
+/// Collects the invariants given with `#[lain(assert = "...")]` on a struct and its fields. The
+/// expressions are evaluated with `self` bound to the struct, so invariants are only supported on
+/// structs with named fields.
+pub(crate) fn get_invariants(
+    name: &syn::Ident,
+    container: &ContainerMetadata,
+    data: &syn::Data,
+) -> syn::Result<Vec<Invariant>> {
+    let mut invariants: Vec<Invariant> = container
+        .asserts
+        .iter()
+        .map(|expr| Invariant {
+            path: name.to_string(),
+            expr: expr.clone(),
+        })
+        .collect();
+
+    let fields = match *data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Named(ref fields),
+            ..
+        }) => fields,
+        _ => {
+            return match container.asserts.first() {
+                Some(expr) => Err(syn::Error::new_spanned(
+                    expr,
+                    "#[lain(assert)] is only supported on structs with named fields",
+                )),
+                None => Ok(invariants),
+            };
+        }
+    };
+
+    for field in fields.named.iter() {
+        for meta_items in get_fuzzer_metadata(&field.attrs)? {
+            for meta_item in meta_items {
+                if let Meta(NameValue(ref m)) = meta_item {
+                    if m.ident != "assert" {
+                        continue;
+                    }
+
+                    let expr = get_lit_str(&m.lit).map_err(|_| {
+                        syn::Error::new_spanned(
+                            &m.lit,
+                            "#[lain(assert)] expects a string, e.g. #[lain(assert = \"self.len as usize == self.data.len()\")]",
+                        )
+                    })?;
+
+                    invariants.push(Invariant {
+                        path: format!("{}.{}", name, field.ident.as_ref().unwrap()),
+                        expr: expr.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(invariants)
+}
+
+/// Returns a struct's fields in the order they appear on the wire. This is declaration order
+/// unless the container is marked `#[lain(sort_by_order)]`, in which case every field must be
+/// given a unique position with `#[lain(order = N)]` and the fields are sorted by it.
+pub(crate) fn get_wire_order<'a>(
+    container: &ContainerMetadata,
+    fields: &'a syn::FieldsNamed,
+) -> syn::Result<Vec<&'a syn::Field>> {
+    let mut ordered = Vec::with_capacity(fields.named.len());
+
+    for field in fields.named.iter() {
+        let mut order = None;
+
+        for meta_items in get_fuzzer_metadata(&field.attrs)? {
+            for meta_item in meta_items {
+                if let Meta(NameValue(ref m)) = meta_item {
+                    if m.ident != "order" {
+                        continue;
+                    }
+
+                    let position = get_lit_number(&m.lit).map_err(|_| {
+                        syn::Error::new_spanned(
+                            &m.lit,
+                            "#[lain(order)] expects an integer, e.g. #[lain(order = 2)]",
+                        )
+                    })?;
+
+                    if !container.sort_by_order {
+                        return Err(syn::Error::new_spanned(
+                            m,
+                            "#[lain(order)] has no effect unless the struct is marked #[lain(sort_by_order)]",
+                        ));
+                    }
+
+                    order = Some((position.value(), m.clone()));
+                }
+            }
+        }
+
+        if !container.sort_by_order {
+            ordered.push((0, field));
+            continue;
+        }
+
+        let (position, meta) = order.ok_or_else(|| {
+            syn::Error::new_spanned(
+                field,
+                "every field of a #[lain(sort_by_order)] struct needs a position, e.g. #[lain(order = 2)]",
+            )
+        })?;
+
+        if ordered.iter().any(|&(other, _)| other == position) {
+            return Err(syn::Error::new_spanned(
+                meta,
+                format!("another field is already at position {}", position),
+            ));
+        }
+
+        ordered.push((position, field));
+    }
+
+    // the sort is stable, so declaration order is kept when the container isn't sorted
+    ordered.sort_by_key(|&(position, _)| position);
+
+    Ok(ordered.into_iter().map(|(_, field)| field).collect())
+}
+
+/// Returns the byte ranges given with `#[lain(pin(0..16, 32..36))]` on a field. Ranges aren't
+/// valid meta syntax, so the attribute's tokens are walked directly instead.
+pub(crate) fn get_pinned_regions(attrs: &[syn::Attribute]) -> syn::Result<Vec<TokenStream>> {
+    let mut regions = Vec::new();
+
+    for item in attrs.iter().flat_map(get_lain_items) {
+        if !is_lain_item(&item, "pin") {
+            continue;
+        }
+
+        let ranges = get_nested_args("pin", &item).map_err(|_| {
+            syn::Error::new_spanned(
+                &item,
+                "#[lain(pin)] expects a list of ranges, e.g. #[lain(pin(0..16))]",
+            )
+        })?;
+
+        let mut region = TokenStream::new();
+        for token in ranges {
+            match token {
+                TokenTree::Punct(ref p) if p.as_char() == ',' => {
+                    if !region.is_empty() {
+                        regions.push(std::mem::replace(&mut region, TokenStream::new()));
+                    }
+                }
+                other => region.extend(std::iter::once(other)),
+            }
+        }
+
+        if !region.is_empty() {
+            regions.push(region);
+        }
+    }
+
+    Ok(regions)
+}
+
+/// Returns the function given by `#[lain(byte_permutation = "path::to::fn")]`, which rearranges the
+/// field's serialized bytes (see `lain::byte_permutation`)
+pub(crate) fn get_byte_permutation(field: &syn::Field) -> syn::Result<Option<syn::Path>> {
+    let mut permutation = None;
+
+    for meta_items in get_fuzzer_metadata(&field.attrs)? {
+        for meta_item in meta_items {
+            if let Meta(NameValue(ref m)) = meta_item {
+                if m.ident != "byte_permutation" {
+                    continue;
+                }
+
+                let path = get_lit_str(&m.lit)
+                    .map_err(|_| {
+                        syn::Error::new_spanned(
+                            &m.lit,
+                            "#[lain(byte_permutation)] expects the path of a function, e.g. #[lain(byte_permutation = \"lain::byte_permutation::pdp_endian\")]",
+                        )
+                    })?
+                    .parse::<syn::Path>()?;
+
+                if !get_bitfield_metadata(&field.attrs)?.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        m,
+                        "#[lain(byte_permutation)] can't be used on a bitfield. Put it on a field holding the whole backing value instead",
+                    ));
+                }
+
+                permutation = Some(path);
+            }
+        }
+    }
+
+    Ok(permutation)
+}
+
+/// Returns the condition given by `#[lain(version_gate = "expr")]`, which decides whether a field is
+/// part of the struct for the protocol version it holds. The condition is written against `self`,
+/// e.g. `#[lain(version_gate = "self.version >= 2")]`.
+pub(crate) fn get_version_gate(field: &syn::Field) -> syn::Result<Option<TokenStream>> {
+    let mut gate = None;
+
+    for meta_items in get_fuzzer_metadata(&field.attrs)? {
+        for meta_item in meta_items {
+            if let Meta(NameValue(ref m)) = meta_item {
+                if m.ident != "version_gate" {
+                    continue;
+                }
+
+                let expr = get_lit_str(&m.lit)
+                    .map_err(|_| {
+                        syn::Error::new_spanned(
+                            &m.lit,
+                            "#[lain(version_gate)] expects a condition, e.g. #[lain(version_gate = \"self.version >= 2\")]",
+                        )
+                    })?
+                    .parse::<TokenStream>()?;
+
+                if !get_bitfield_metadata(&field.attrs)?.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        m,
+                        "#[lain(version_gate)] can't be used on a bitfield since the backing value is shared with other fields",
+                    ));
+                }
+
+                gate = Some(expr);
+            }
+        }
+    }
+
+    Ok(gate)
+}
+
+/// Rewrites a `#[lain(version_gate)]` condition to refer to `receiver` instead of `self`. Without a
+/// receiver, `self.field` becomes `field` so that the condition can be evaluated against the local
+/// bindings of fields which have already been deserialized.
+pub(crate) fn replace_gate_receiver(gate: TokenStream, receiver: Option<&TokenStream>) -> TokenStream {
+    let mut tokens = gate.into_iter().peekable();
+    let mut replaced = TokenStream::new();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ref ident) if ident == "self" => match receiver {
+                Some(receiver) => replaced.extend(receiver.clone()),
+                None => {
+                    if let Some(TokenTree::Punct(ref p)) = tokens.peek() {
+                        if p.as_char() == '.' {
+                            tokens.next();
+                        }
+                    }
+                }
+            },
+            TokenTree::Group(ref g) => {
+                let mut group =
+                    Group::new(g.delimiter(), replace_gate_receiver(g.stream(), receiver));
+                group.set_span(g.span());
+                replaced.extend(std::iter::once(TokenTree::Group(group)));
+            }
+            other => replaced.extend(std::iter::once(other)),
+        }
+    }
+
+    replaced
+}
+
+/// Returns whether a struct field is marked `#[lain(flatten)]`. A flattened field's own fields are
+/// treated as if they were declared in the parent, so attributes which would treat the nested
+/// struct as a single value (a byte order or permutation, constraints, bitfields, or pinned regions)
+/// are rejected.
+pub(crate) fn get_flatten(field: &syn::Field) -> syn::Result<bool> {
+    let mut flatten = None;
+    let mut conflicting = None;
+
+    for meta_items in get_fuzzer_metadata(&field.attrs)? {
+        for meta_item in meta_items {
+            match meta_item {
+                Meta(syn::Meta::Word(ref ident)) if ident == "flatten" => {
+                    flatten = Some(ident.clone());
+                }
+                Meta(NameValue(ref m))
+                    if m.ident == "min"
+                        || m.ident == "max"
+                        || m.ident == "element_min"
+                        || m.ident == "element_max"
+                        || m.ident == "initializer"
+                        || m.ident == "byte_permutation" =>
+                {
+                    conflicting = conflicting.or_else(|| Some(m.ident.to_string()));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let flatten = match flatten {
+        Some(flatten) => flatten,
+        None => return Ok(false),
+    };
+
+    if conflicting.is_none() {
+        if !get_bitfield_metadata(&field.attrs)?.is_empty() {
+            conflicting = Some("bitfield".to_string());
+        } else if !get_field_metadata("byteorder", &field.attrs)?.is_empty() {
+            conflicting = Some("byteorder".to_string());
+        } else if !get_pinned_regions(&field.attrs)?.is_empty() {
+            conflicting = Some("pin".to_string());
+        }
+    }
+
+    if let Some(conflicting) = conflicting {
+        return Err(syn::Error::new_spanned(
+            flatten,
+            format!(
+                "`{}` can't be used on a flattened field. Put it on the nested struct's fields instead",
+                conflicting
+            ),
+        ));
+    }
+
+    match field.ty {
+        syn::Type::Path(ref p)
+            if !p.path.segments.is_empty()
+                && crate::utils::is_primitive(&p.path.segments[0].ident.to_string())
+                    == crate::utils::PrimitiveType::None =>
+        {
+            Ok(true)
+        }
+        _ => Err(syn::Error::new_spanned(
+            &field.ty,
+            "#[lain(flatten)] can only be used on fields whose type is a struct",
+        )),
+    }
+}
+
+/// The weight given to an enum variant with `#[weight(...)]`
+pub(crate) enum VariantWeight {
+    /// A weight relative to the other variants. This may be any constant expression, e.g.
+    /// `#[weight(3)]` or `#[weight(BASE_WEIGHT * 2)]`.
+    Relative(TokenStream),
+    /// A percentage of all generated values, e.g. `#[weight(30%)]`. `literal` holds the value
+    /// when it's an integer literal so that the total can be checked by the derive itself.
+    Percent {
+        expr: TokenStream,
+        literal: Option<u64>,
+        span: Span,
+    },
+}
+
+/// Parses the `#[lain(weight(...))]` (or `#[weight(...)]`) attribute of an enum variant, if it
+/// has one. Percentages aren't valid meta syntax, so the attribute's tokens are walked directly
+/// instead.
+pub(crate) fn get_variant_weight(attrs: &[syn::Attribute]) -> syn::Result<Option<VariantWeight>> {
+    // the tokens to point errors at, along with the parenthesized weight
+    let mut weight_attrs: Vec<(TokenStream, Option<Group>)> = Vec::new();
+
+    for attr in attrs {
+        if attr.path.segments.len() == 1 && attr.path.segments[0].ident == "weight" {
+            let args = match attr.tts.clone().into_iter().next() {
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => Some(g),
+                _ => None,
+            };
+
+            weight_attrs.push((quote! {#attr}, args));
+        }
+
+        for item in get_lain_items(attr) {
+            if !is_lain_item(&item, "weight") {
+                continue;
+            }
+
+            let args = match item.clone().into_iter().nth(1) {
+                Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis => Some(g),
+                _ => None,
+            };
+
+            weight_attrs.push((item, args));
+        }
+    }
+
+    if weight_attrs.len() > 1 {
+        return Err(syn::Error::new_spanned(
+            &weight_attrs[1].0,
+            "a variant may only have one weight",
+        ));
+    }
+
+    let (origin, args) = match weight_attrs.pop() {
+        Some(weight_attr) => weight_attr,
+        None => return Ok(None),
+    };
+
+    let mut tokens: Vec<TokenTree> = args
+        .as_ref()
+        .map_or_else(Vec::new, |g| g.stream().into_iter().collect());
+
+    let is_percent = match tokens.last() {
+        Some(TokenTree::Punct(ref p)) => p.as_char() == '%',
+        _ => false,
+    };
+
+    if is_percent {
+        tokens.pop();
+    }
+
+    if tokens.is_empty() {
+        return Err(syn::Error::new_spanned(
+            origin,
+            "expected a weight or percentage, e.g. #[lain(weight(3))] or #[lain(weight(30%))]",
+        ));
+    }
+
+    let literal = match tokens.as_slice() {
+        [TokenTree::Literal(ref lit)] => match syn::Lit::new(lit.clone()) {
+            syn::Lit::Int(ref i) => Some(i.value()),
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected an integer weight, e.g. #[lain(weight(3))]",
+                ))
+            }
+        },
+        _ => None,
+    };
+
+    let expr: TokenStream = tokens.into_iter().collect();
+
+    if !is_percent {
+        return Ok(Some(VariantWeight::Relative(expr)));
+    }
+
+    if let Some(percent) = literal {
+        if percent > 100 {
+            return Err(syn::Error::new_spanned(
+                &expr,
+                "percentage weights can't exceed 100%",
+            ));
+        }
+    }
+
+    Ok(Some(VariantWeight::Percent {
+        span: args.unwrap().span(),
+        expr,
+        literal,
+    }))
+}
+
+/// Returns the variants given by `#[lain(transitions(Write, Close))]` on an enum variant, which the
+/// variant may be mutated into
+pub(crate) fn get_variant_transitions(
+    variant: &syn::Variant,
+) -> syn::Result<Option<Vec<syn::Ident>>> {
+    let mut transitions: Option<Vec<syn::Ident>> = None;
+
+    for meta_items in get_fuzzer_metadata(&variant.attrs)? {
+        for meta_item in meta_items {
+            let list = match meta_item {
+                Meta(List(ref list)) if list.ident == "transitions" => list,
+                Meta(ref m) if m.name() == "transitions" => {
+                    return Err(syn::Error::new_spanned(
+                        m,
+                        "#[lain(transitions)] expects a list of variants, e.g. #[lain(transitions(Write, Close))]",
+                    ))
+                }
+                _ => continue,
+            };
+
+            for nested in list.nested.iter() {
+                match *nested {
+                    Meta(syn::Meta::Word(ref ident)) => {
+                        transitions.get_or_insert_with(Vec::new).push(ident.clone())
+                    }
+                    ref other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "expected the name of a variant of this enum",
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(transitions)
 }
@@ -0,0 +1,196 @@
+use proc_macro2::TokenStream;
+
+use quote::{quote, quote_spanned};
+
+use std::str::FromStr;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, Ident};
+
+use crate::attr::get_flatten;
+use crate::utils::{named_fields, named_fields_error, union_error};
+
+pub(crate) fn fuzzed_debug_helper(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let debug_body = gen_debug_body(name, &input.data)?;
+    let debug_fields = gen_debug_fields(&input.data)?;
+
+    let expanded = quote! {
+        impl #impl_generics ::lain::traits::FuzzedDebug for #name #ty_generics #where_clause {
+            #[allow(unused)]
+            fn fuzzed_debug(&self, path: &str, depth: usize, output: &mut String) {
+                use ::lain::debug::SerializedSizeHint;
+
+                let type_name = ::lain::debug::short_type_name::<Self>();
+                let size = self.serialized_size_hint();
+
+                #debug_body
+            }
+
+            #debug_fields
+        }
+    };
+
+    // Uncomment to dump the AST
+    // println!("{}", expanded);
+
+    Ok(expanded)
+}
+
+/// Generates the call to print a child value of this object
+fn gen_child(
+    ty: &syn::Type,
+    value: TokenStream,
+    child: &str,
+    span: proc_macro2::Span,
+) -> TokenStream {
+    quote_spanned! { span =>
+        <#ty as ::lain::traits::FuzzedDebug>::fuzzed_debug(
+            #value,
+            &::lain::debug::child_path(path, #child),
+            depth + 1,
+            output,
+        );
+    }
+}
+
+/// Generates `fuzzed_debug_fields` for structs, which prints their fields without a node for the
+/// struct itself. Flattened fields print their own fields the same way so that they appear to
+/// belong to the parent.
+fn gen_debug_fields(data: &Data) -> syn::Result<TokenStream> {
+    let fields = match *data {
+        Data::Struct(ref data) => match named_fields(&data.fields) {
+            Some(fields) => fields,
+            None => return Ok(TokenStream::new()),
+        },
+        _ => return Ok(TokenStream::new()),
+    };
+
+    let mut children = TokenStream::new();
+
+    for field in fields.named.iter() {
+        let field_name = &field.ident;
+        let field_ty = &field.ty;
+
+        if get_flatten(field)? {
+            children.extend(quote_spanned! { field.span() =>
+                <#field_ty as ::lain::traits::FuzzedDebug>::fuzzed_debug_fields(&self.#field_name, path, depth, output);
+            });
+
+            continue;
+        }
+
+        let child = field_name.as_ref().unwrap().to_string();
+
+        children.extend(gen_child(
+            field_ty,
+            quote! {&self.#field_name},
+            &child,
+            field.span(),
+        ));
+    }
+
+    Ok(quote! {
+        #[allow(unused)]
+        fn fuzzed_debug_fields(&self, path: &str, depth: usize, output: &mut String) {
+            #children
+        }
+    })
+}
+
+fn gen_debug_body(name: &Ident, data: &Data) -> syn::Result<TokenStream> {
+    match *data {
+        Data::Struct(ref data) => {
+            if named_fields(&data.fields).is_some() {
+                Ok(quote! {
+                    ::lain::debug::write_node(output, depth, path, &type_name, size, None);
+                    self.fuzzed_debug_fields(path, depth, output);
+                })
+            } else {
+                Err(named_fields_error("FuzzedDebug", name, &data.fields))
+            }
+        }
+        Data::Enum(ref data) => {
+            let mut variant_arms = Vec::<TokenStream>::new();
+
+            for variant in data.variants.iter() {
+                let variant_name = variant.ident.to_string();
+                let full_ident =
+                    TokenStream::from_str(&format!("{}::{}", name, variant_name)).unwrap();
+
+                let write_variant = quote! {
+                    ::lain::debug::write_node(output, depth, path, &type_name, size, Some(#variant_name));
+                };
+
+                match variant.fields {
+                    Fields::Unnamed(ref fields) => {
+                        let mut parameters = TokenStream::new();
+                        let mut children = TokenStream::new();
+
+                        // tuple members are named after their index, e.g. `0.length`
+                        for (i, ref unnamed) in fields.unnamed.iter().enumerate() {
+                            let ident = TokenStream::from_str(&format!("field_{}", i)).unwrap();
+
+                            children.extend(gen_child(
+                                &unnamed.ty,
+                                ident.clone(),
+                                &i.to_string(),
+                                unnamed.span(),
+                            ));
+
+                            parameters.extend(quote! {ref #ident,});
+                        }
+
+                        variant_arms.push(quote! {
+                            #full_ident(#parameters) => {
+                                #write_variant
+                                #children
+                            },
+                        });
+                    }
+                    Fields::Named(ref fields) => {
+                        let mut parameters = TokenStream::new();
+                        let mut children = TokenStream::new();
+
+                        for named in fields.named.iter() {
+                            let field_name = &named.ident;
+                            let child = field_name.as_ref().unwrap().to_string();
+
+                            children.extend(gen_child(
+                                &named.ty,
+                                quote! {#field_name},
+                                &child,
+                                named.span(),
+                            ));
+
+                            parameters.extend(quote! {ref #field_name,});
+                        }
+
+                        variant_arms.push(quote! {
+                            #full_ident{#parameters} => {
+                                #write_variant
+                                #children
+                            },
+                        });
+                    }
+                    Fields::Unit => {
+                        variant_arms.push(quote! {
+                            #full_ident => {
+                                #write_variant
+                            },
+                        });
+                    }
+                }
+            }
+
+            Ok(quote! {
+                match *self {
+                    #(#variant_arms)*
+                }
+            })
+        }
+        Data::Union(ref data) => Err(union_error("FuzzedDebug", data)),
+    }
+}
@@ -0,0 +1,240 @@
+use proc_macro2::TokenStream;
+
+use quote::quote;
+
+use std::str::FromStr;
+use syn::{Data, DeriveInput, Fields, Ident, Type};
+
+use crate::attr::*;
+use crate::serialize::{get_byteorder, get_byteorder_metadata};
+use crate::utils::*;
+
+pub(crate) fn binary_deserialize_helper(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let repr = get_repr_type(&input.attrs);
+
+    let name = &input.ident;
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let container = get_container_metadata(&input.attrs)?;
+
+    let body = match input.data {
+        Data::Struct(ref data) => {
+            let strict = gen_struct_body(name, &container, &data.fields, false)?;
+            let lenient = gen_struct_body(name, &container, &data.fields, true)?;
+
+            quote! {
+                fn binary_deserialize<R: std::io::Read, E: ::lain::byteorder::ByteOrder>(buffer: &mut R) -> std::io::Result<Self> {
+                    #strict
+                }
+
+                fn binary_deserialize_lenient<R: std::io::Read, E: ::lain::byteorder::ByteOrder>(buffer: &mut R) -> std::io::Result<Self> {
+                    #lenient
+                }
+            }
+        }
+        Data::Enum(ref data) => {
+            let repr = repr.ok_or_else(|| {
+                syn::Error::new_spanned(
+                    name,
+                    "BinaryDeserialize requires enums to have an integer #[repr] (e.g. #[repr(u8)])",
+                )
+            })?;
+
+            let name_as_string = name.to_string();
+            let mut variants = Vec::<TokenStream>::new();
+
+            for variant in data.variants.iter() {
+                match variant.fields {
+                    Fields::Unit => {}
+                    ref fields => {
+                        return Err(syn::Error::new_spanned(
+                            fields,
+                            "BinaryDeserialize only supports fieldless enums since the serialized data doesn't record which variant was written",
+                        ));
+                    }
+                }
+
+                let full_ident =
+                    TokenStream::from_str(&format!("{}::{}", name_as_string, variant.ident))
+                        .unwrap();
+
+                variants.push(quote! {
+                    if discriminant == #full_ident as #repr {
+                        return Ok(#full_ident);
+                    }
+                });
+            }
+
+            quote! {
+                fn binary_deserialize<R: std::io::Read, E: ::lain::byteorder::ByteOrder>(buffer: &mut R) -> std::io::Result<Self> {
+                    let discriminant = <#repr as ::lain::traits::BinaryDeserialize>::binary_deserialize::<_, E>(buffer)?;
+
+                    #(#variants)*
+
+                    Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("{} is not a valid discriminant for {}", discriminant, #name_as_string),
+                    ))
+                }
+            }
+        }
+        Data::Union(ref data) => {
+            return Err(union_error("BinaryDeserialize", data));
+        }
+    };
+
+    let expanded = quote! {
+        impl #impl_generics ::lain::traits::BinaryDeserialize for #name #ty_generics #where_clause {
+            #body
+        }
+    };
+
+    // Uncomment to dump the AST
+    // println!("{}", expanded);
+
+    Ok(expanded)
+}
+
+/// Generates the body of `binary_deserialize` (or `binary_deserialize_lenient` if `lenient` is
+/// set) for a struct. Fields are read in wire order, mirroring `BinarySerialize`.
+fn gen_struct_body(
+    name: &Ident,
+    container: &ContainerMetadata,
+    fields: &Fields,
+    lenient: bool,
+) -> syn::Result<TokenStream> {
+    let fields = match named_fields(fields) {
+        Some(named) => named,
+        None => return Err(named_fields_error("BinaryDeserialize", name, fields)),
+    };
+
+    let read = |ty: &TokenStream, byteorder: &TokenStream| {
+        if lenient {
+            quote! {
+                ::lain::deserialize::deserialize_field_lenient::<#ty, _, #byteorder>(buffer)?
+            }
+        } else {
+            quote! {
+                <#ty as ::lain::traits::BinaryDeserialize>::binary_deserialize::<_, #byteorder>(buffer)?
+            }
+        }
+    };
+
+    let mut reads = TokenStream::new();
+    let mut bitfield_shift = 0;
+
+    for f in get_wire_order(container, &fields)? {
+        let ident = &f.ident;
+        let ty = &f.ty;
+
+        get_flatten(f)?;
+
+        let meta = get_byteorder_metadata(&f.attrs)?.into_iter();
+        let byteorder = get_byteorder(meta)?.unwrap_or_else(|| quote! {E});
+
+        if let Some(bitfield_meta) = get_bitfield_limits(f)? {
+            let primitive_type = match ty {
+                Type::Path(ref p) if !p.path.segments.is_empty() => {
+                    is_primitive(&p.path.segments[0].ident.to_string())
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "bitfields are only supported for paths -- arrays should not be used",
+                    ));
+                }
+            };
+
+            // the backing value is read when the first field of the bitfield is reached
+            if bitfield_shift == 0 {
+                let bitfield_ty = bitfield_meta.ty.as_ref().unwrap();
+                let read_bitfield = read(bitfield_ty, &quote! {E});
+
+                reads.extend(quote! {
+                    bitfield = #read_bitfield as u64;
+                });
+            }
+
+            let shift = bitfield_shift;
+            let bit_mask = 2_u64.pow(bitfield_meta.bit_count as u32) - 1;
+
+            reads.extend(match primitive_type {
+                PrimitiveType::Number => quote! {
+                    let #ident = ((bitfield >> #shift) & #bit_mask) as #ty;
+                },
+                PrimitiveType::Bool => quote! {
+                    let #ident = ((bitfield >> #shift) & #bit_mask) != 0;
+                },
+                PrimitiveType::None => {
+                    return Err(syn::Error::new_spanned(
+                        ty,
+                        "BinaryDeserialize only supports bitfields of primitive types",
+                    ));
+                }
+            });
+
+            bitfield_shift += bitfield_meta.bit_count;
+            if bitfield_shift == bitfield_meta.ty_bits {
+                bitfield_shift = 0;
+            }
+
+            continue;
+        }
+
+        if let Type::Reference(_) = ty {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "BinaryDeserialize does not support reference fields. Use an owned type instead",
+            ));
+        }
+
+        let read_field = match get_byte_permutation(f)? {
+            Some(permutation) => {
+                let deserialize_permuted = if lenient {
+                    quote! {deserialize_permuted_lenient}
+                } else {
+                    quote! {deserialize_permuted}
+                };
+
+                quote! {
+                    ::lain::byte_permutation::#deserialize_permuted::<#ty, _, #byteorder>(buffer, #permutation)?
+                }
+            }
+            None => read(&quote! {#ty}, &byteorder),
+        };
+
+        // gated fields are only present in the versions they're part of. their condition is
+        // evaluated against the fields read so far
+        let read_field = match get_version_gate(f)? {
+            Some(gate) => {
+                let gate = replace_gate_receiver(gate, None);
+                quote! {
+                    if #gate {
+                        #read_field
+                    } else {
+                        Default::default()
+                    }
+                }
+            }
+            None => read_field,
+        };
+
+        reads.extend(quote! {
+            let #ident: #ty = #read_field;
+        });
+    }
+
+    let idents = fields.named.iter().map(|f| &f.ident);
+
+    Ok(quote! {
+        // may not be used in all scenarios
+        let mut bitfield: u64 = 0;
+
+        #reads
+
+        Ok(#name {
+            #(#idents,)*
+        })
+    })
+}
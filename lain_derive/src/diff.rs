@@ -0,0 +1,185 @@
+use proc_macro2::TokenStream;
+
+use quote::{quote, quote_spanned};
+
+use std::str::FromStr;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, Ident};
+
+use crate::attr::get_flatten;
+use crate::utils::{named_fields, named_fields_error, union_error};
+
+pub(crate) fn structured_diff_helper(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let diff_body = gen_diff_body(name, &input.data)?;
+
+    let expanded = quote! {
+        impl #impl_generics ::lain::traits::StructuredDiff for #name #ty_generics #where_clause {
+            #[allow(unused)]
+            fn diff(&self, other: &Self) -> Vec<::lain::types::FieldDelta> {
+                let mut deltas: Vec<::lain::types::FieldDelta> = Vec::new();
+
+                #diff_body
+
+                deltas
+            }
+        }
+    };
+
+    // Uncomment to dump the AST
+    // println!("{}", expanded);
+
+    Ok(expanded)
+}
+
+fn gen_diff_body(name: &Ident, data: &Data) -> syn::Result<TokenStream> {
+    match *data {
+        Data::Struct(ref data) => {
+            if let Some(fields) = named_fields(&data.fields) {
+                let mut tokens = TokenStream::new();
+
+                for field in fields.named.iter() {
+                    let field_name = &field.ident;
+                    let field_ty = &field.ty;
+                    let path = field_name.as_ref().unwrap().to_string();
+
+                    // the fields of a flattened struct are reported as if they were our own
+                    if get_flatten(field)? {
+                        tokens.extend(quote_spanned! { field.span() =>
+                            deltas.extend(
+                                <#field_ty as ::lain::traits::StructuredDiff>::diff(&self.#field_name, &other.#field_name),
+                            );
+                        });
+
+                        continue;
+                    }
+
+                    tokens.extend(quote_spanned! { field.span() =>
+                        deltas.extend(
+                            <#field_ty as ::lain::traits::StructuredDiff>::diff(&self.#field_name, &other.#field_name)
+                                .into_iter()
+                                .map(|d| d.prefixed(#path)),
+                        );
+                    });
+                }
+
+                Ok(tokens)
+            } else {
+                Err(named_fields_error("StructuredDiff", name, &data.fields))
+            }
+        }
+        Data::Enum(ref data) => {
+            let mut same_variant_arms = Vec::<TokenStream>::new();
+            let mut variant_name_arms = Vec::<TokenStream>::new();
+
+            for variant in data.variants.iter() {
+                let variant_name = variant.ident.to_string();
+                let full_ident =
+                    TokenStream::from_str(&format!("{}::{}", name, variant_name)).unwrap();
+
+                match variant.fields {
+                    Fields::Unnamed(ref fields) => {
+                        let mut old_parameters = TokenStream::new();
+                        let mut new_parameters = TokenStream::new();
+                        let mut field_diffs = TokenStream::new();
+
+                        // Each tuple member is compared against its counterpart and named
+                        // after its index, e.g. `0.length`
+                        for (i, ref unnamed) in fields.unnamed.iter().enumerate() {
+                            let field_ty = &unnamed.ty;
+                            let old_ident = TokenStream::from_str(&format!("old_{}", i)).unwrap();
+                            let new_ident = TokenStream::from_str(&format!("new_{}", i)).unwrap();
+                            let path = i.to_string();
+
+                            field_diffs.extend(quote_spanned! { unnamed.span() =>
+                                deltas.extend(
+                                    <#field_ty as ::lain::traits::StructuredDiff>::diff(#old_ident, #new_ident)
+                                        .into_iter()
+                                        .map(|d| d.prefixed(#path)),
+                                );
+                            });
+
+                            old_parameters.extend(quote! {#old_ident,});
+                            new_parameters.extend(quote! {#new_ident,});
+                        }
+
+                        same_variant_arms.push(quote! {
+                            (#full_ident(#old_parameters), #full_ident(#new_parameters)) => {
+                                #field_diffs
+                            },
+                        });
+
+                        variant_name_arms.push(quote! {
+                            #full_ident(..) => #variant_name,
+                        });
+                    }
+                    Fields::Named(ref fields) => {
+                        let mut old_parameters = TokenStream::new();
+                        let mut new_parameters = TokenStream::new();
+                        let mut field_diffs = TokenStream::new();
+
+                        for (i, ref named) in fields.named.iter().enumerate() {
+                            let field_ty = &named.ty;
+                            let field_name = &named.ident;
+                            let old_ident = TokenStream::from_str(&format!("old_{}", i)).unwrap();
+                            let new_ident = TokenStream::from_str(&format!("new_{}", i)).unwrap();
+                            let path = field_name.as_ref().unwrap().to_string();
+
+                            field_diffs.extend(quote_spanned! { named.span() =>
+                                deltas.extend(
+                                    <#field_ty as ::lain::traits::StructuredDiff>::diff(#old_ident, #new_ident)
+                                        .into_iter()
+                                        .map(|d| d.prefixed(#path)),
+                                );
+                            });
+
+                            old_parameters.extend(quote! {#field_name: #old_ident,});
+                            new_parameters.extend(quote! {#field_name: #new_ident,});
+                        }
+
+                        same_variant_arms.push(quote! {
+                            (#full_ident{#old_parameters}, #full_ident{#new_parameters}) => {
+                                #field_diffs
+                            },
+                        });
+
+                        variant_name_arms.push(quote! {
+                            #full_ident{..} => #variant_name,
+                        });
+                    }
+                    Fields::Unit => {
+                        same_variant_arms.push(quote! {
+                            (#full_ident, #full_ident) => {},
+                        });
+
+                        variant_name_arms.push(quote! {
+                            #full_ident => #variant_name,
+                        });
+                    }
+                }
+            }
+
+            Ok(quote! {
+                let variant_name = |value: &Self| -> &'static str {
+                    match *value {
+                        #(#variant_name_arms)*
+                    }
+                };
+
+                match (self, other) {
+                    #(#same_variant_arms)*
+                    _ => {
+                        deltas.push(::lain::types::FieldDelta::new(::lain::types::DeltaKind::Variant {
+                            old: variant_name(self).to_string(),
+                            new: variant_name(other).to_string(),
+                        }));
+                    }
+                }
+            })
+        }
+        Data::Union(ref data) => Err(union_error("StructuredDiff", data)),
+    }
+}
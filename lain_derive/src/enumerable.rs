@@ -0,0 +1,170 @@
+use proc_macro2::TokenStream;
+
+use quote::{quote, quote_spanned};
+
+use std::str::FromStr;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, Ident};
+
+use crate::utils::*;
+
+pub(crate) fn enumerable_helper(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (value_count, value_at) = gen_enumerable_body(name, &input.data)?;
+
+    let expanded = quote! {
+        impl #impl_generics ::lain::traits::Enumerable for #name #ty_generics #where_clause {
+            fn value_count() -> usize {
+                #value_count
+            }
+
+            #[allow(unused)]
+            fn value_at(index: usize) -> Self {
+                if index >= Self::value_count() {
+                    panic!("index {} is out of range for {}", index, stringify!(#name));
+                }
+
+                #value_at
+            }
+        }
+    };
+
+    // Uncomment to dump the AST
+    // println!("{}", expanded);
+
+    Ok(expanded)
+}
+
+/// Returns the tokens for the number of combinations of the given fields and the statements
+/// which pick each field's value from `remaining`. Fields are treated as digits of a mixed-radix
+/// number, with the first field being the least significant digit.
+fn gen_fields(fields: &[(TokenStream, TokenStream, TokenStream)]) -> (TokenStream, TokenStream) {
+    let mut count = quote! {1};
+    let mut values = TokenStream::new();
+
+    for (binding, field_count, value) in fields.iter() {
+        count.extend(quote! {* (#field_count)});
+        values.extend(quote! {
+            let #binding = {
+                let count = #field_count;
+                let i = remaining % count;
+                remaining /= count;
+
+                #value
+            };
+        });
+    }
+
+    (count, values)
+}
+
+/// Generates the fields of an enum variant. These don't support any attributes, so their types
+/// must implement `Enumerable`.
+fn gen_variant_fields(fields: &Fields) -> Vec<(TokenStream, TokenStream, TokenStream)> {
+    let mut enumerated = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let ty = &field.ty;
+        let binding = match field.ident {
+            Some(ref ident) => quote! {#ident},
+            None => TokenStream::from_str(&format!("field_{}", i)).unwrap(),
+        };
+
+        enumerated.push((
+            binding,
+            quote_spanned! { field.span() => <#ty as ::lain::traits::Enumerable>::value_count() },
+            quote_spanned! { field.span() => <#ty as ::lain::traits::Enumerable>::value_at(i) },
+        ));
+    }
+
+    enumerated
+}
+
+fn gen_enumerable_body(name: &Ident, data: &Data) -> syn::Result<(TokenStream, TokenStream)> {
+    match *data {
+        Data::Struct(ref data) => {
+            if let Some(fields) = named_fields(&data.fields) {
+                let fields = parse_fields(&fields)?;
+
+                let enumerated = fields
+                    .iter()
+                    .map(|f| {
+                        let ident = &f.field.ident;
+                        let (count, value) = f.enumeration()?;
+
+                        Ok((quote! {#ident}, count, value))
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+
+                let idents = fields.iter().map(|f| &f.field.ident);
+                let (count, values) = gen_fields(&enumerated);
+
+                let value_at = quote! {
+                    let mut remaining = index;
+
+                    #values
+
+                    #name {
+                        #(#idents,)*
+                    }
+                };
+
+                Ok((count, value_at))
+            } else {
+                Err(named_fields_error("Enumerable", name, &data.fields))
+            }
+        }
+        Data::Enum(ref data) => {
+            let mut counts = Vec::<TokenStream>::new();
+            let mut variant_values = Vec::<TokenStream>::new();
+
+            for variant in data.variants.iter() {
+                let full_ident =
+                    TokenStream::from_str(&format!("{}::{}", name, variant.ident)).unwrap();
+
+                let enumerated = gen_variant_fields(&variant.fields);
+                let bindings = enumerated.iter().map(|(binding, _, _)| binding);
+                let (count, values) = gen_fields(&enumerated);
+
+                let constructor = match variant.fields {
+                    Fields::Named(_) => quote! {#full_ident { #(#bindings,)* }},
+                    Fields::Unnamed(_) => quote! {#full_ident(#(#bindings,)*)},
+                    Fields::Unit => quote! {#full_ident},
+                };
+
+                // variants are laid out one after another, so each variant's combinations
+                // start where the previous variant's end
+                variant_values.push(quote! {
+                    let count = #count;
+                    if remaining < count {
+                        #values
+
+                        return #constructor;
+                    }
+
+                    remaining -= count;
+                });
+
+                counts.push(count);
+            }
+
+            let value_count = quote! {0 #(+ (#counts))*};
+
+            let value_at = quote! {
+                let mut remaining = index;
+
+                #({
+                    #variant_values
+                })*
+
+                unreachable!()
+            };
+
+            Ok((value_count, value_at))
+        }
+        Data::Union(ref data) => Err(union_error("Enumerable", data)),
+    }
+}
@@ -2,22 +2,27 @@ use proc_macro2::TokenStream;
 
 use quote::{quote, quote_spanned};
 
+use crate::attr::{get_invariants, get_variant_transitions, ContainerMetadata};
 use crate::utils::*;
 use syn::spanned::Spanned;
 use syn::{Data, Ident};
 
 use std::str::FromStr;
 
-pub(crate) fn get_post_mutation_impl(ident: &Ident, data: &Data) -> TokenStream {
+pub(crate) fn get_post_mutation_impl(ident: &Ident, data: &Data) -> syn::Result<TokenStream> {
     match *data {
         Data::Struct(ref data) => {
-            if let syn::Fields::Named(ref fields) = data.fields {
-                let fields = parse_fields(&fields);
+            if let Some(fields) = named_fields(&data.fields) {
+                let mut fields = parse_fields(&fields)?;
 
                 if fields.is_empty() {
-                    return TokenStream::new();
+                    return Ok(TokenStream::new());
                 }
 
+                // fields are fixed up in ascending #[lain(fixup_order)], with unordered fields at 0.
+                // the sort is stable, so ties keep their declaration order
+                fields.sort_by_key(|f| f.fixup_order.unwrap_or(0));
+
                 let mut base_tokens = quote_spanned! { ident.span() => };
 
                 for field in fields {
@@ -28,23 +33,26 @@ pub(crate) fn get_post_mutation_impl(ident: &Ident, data: &Data) -> TokenStream
                     });
                 }
 
-                return base_tokens;
+                Ok(base_tokens)
             } else {
-                panic!("struct contains unnamed fields");
+                Err(named_fields_error("FixupChildren", ident, &data.fields))
             }
         }
-        _ => TokenStream::new(),
+        _ => Ok(TokenStream::new()),
     }
 }
 
-pub(crate) fn get_post_fuzzer_iteration_impls(ident: &Ident, data: &Data) -> TokenStream {
+pub(crate) fn get_post_fuzzer_iteration_impls(
+    ident: &Ident,
+    data: &Data,
+) -> syn::Result<TokenStream> {
     match *data {
         Data::Struct(ref data) => {
-            if let syn::Fields::Named(ref fields) = data.fields {
-                let fields = parse_fields(&fields);
+            if let Some(fields) = named_fields(&data.fields) {
+                let fields = parse_fields(&fields)?;
 
                 if fields.is_empty() {
-                    return TokenStream::new();
+                    return Ok(TokenStream::new());
                 }
 
                 let mut base_tokens = quote_spanned!(ident.span() => );
@@ -57,23 +65,74 @@ pub(crate) fn get_post_fuzzer_iteration_impls(ident: &Ident, data: &Data) -> Tok
                     });
                 }
 
-                return base_tokens;
+                Ok(base_tokens)
             } else {
-                panic!("struct contains unnamed fields");
+                Err(named_fields_error(
+                    "PostFuzzerIteration",
+                    ident,
+                    &data.fields,
+                ))
             }
         }
-        _ => TokenStream::new(),
+        _ => Ok(TokenStream::new()),
     }
 }
 
-pub(crate) fn gen_mutate_impl(ident: &Ident, data: &Data) -> TokenStream {
+pub(crate) fn gen_mutate_impl(
+    ident: &Ident,
+    data: &Data,
+    container: &ContainerMetadata,
+) -> syn::Result<TokenStream> {
     let mutate_body: TokenStream;
+    let mut swap_fields = TokenStream::new();
 
     match *data {
         Data::Enum(ref data) => {
             let enum_ident = ident.to_string();
 
-            let mut enum_has_simple_variants = false;
+            // the variant names the current variant may switch to, given by
+            // `#[lain(transitions(...))]`. runtime transitions given to the mutator override these
+            let mut declared_transitions: Vec<TokenStream> = Vec::new();
+            let mut current_variant_arms: Vec<TokenStream> = Vec::new();
+            for variant in &data.variants {
+                let name = variant.ident.to_string();
+                let variant_ident =
+                    TokenStream::from_str(&format!("{}::{}", enum_ident, name)).unwrap();
+
+                current_variant_arms.push(match variant.fields {
+                    syn::Fields::Unit => quote! { #variant_ident => #name, },
+                    _ => quote! { #variant_ident(..) => #name, },
+                });
+
+                if let Some(targets) = get_variant_transitions(variant)? {
+                    for target in targets.iter() {
+                        if !data.variants.iter().any(|v| v.ident == *target) {
+                            return Err(syn::Error::new_spanned(
+                                target,
+                                format!("`{}` is not a variant of `{}`", target, enum_ident),
+                            ));
+                        }
+                    }
+
+                    let targets = targets.iter().map(|target| target.to_string());
+                    declared_transitions.push(quote! { (#name, &[#(#targets),*]), });
+                }
+            }
+
+            let has_declared_transitions = !declared_transitions.is_empty();
+            let declared_count = declared_transitions.len();
+            let begin_transition = quote! {
+                static declared_transitions: [(&str, &[&str]); #declared_count] = [#(#declared_transitions)*];
+                let current_variant = match *self {
+                    #(#current_variant_arms)*
+                };
+                mutator.begin_variant_transition(::std::any::type_name::<Self>(), current_variant, &declared_transitions);
+            };
+
+            let enum_has_data_variants = data
+                .variants
+                .iter()
+                .any(|variant| variant.fields != syn::Fields::Unit);
             let mut mutate_match_arms: Vec<TokenStream> = Vec::new();
             for variant in &data.variants {
                 let variant_ident = TokenStream::from_str(&format!(
@@ -93,9 +152,12 @@ pub(crate) fn gen_mutate_impl(ident: &Ident, data: &Data) -> TokenStream {
                             let identifier =
                                 TokenStream::from_str(&format!("field_{}", i)).unwrap();
 
-                            mutate_call.extend(quote_spanned! { unnamed.span() =>
-                                <#field_ty>::mutate(#identifier, mutator, None);
-                            });
+                            mutate_call.extend(gen_category_scope(
+                                get_field_category(field_ty).as_ref(),
+                                quote_spanned! { unnamed.span() =>
+                                    <#field_ty>::mutate(#identifier, mutator, None);
+                                },
+                            ));
 
                             parameters
                                 .extend(quote_spanned! {unnamed.span() => ref mut #identifier,});
@@ -108,18 +170,48 @@ pub(crate) fn gen_mutate_impl(ident: &Ident, data: &Data) -> TokenStream {
                         });
                     }
                     syn::Fields::Unit => {
-                        enum_has_simple_variants = true;
-                        break;
+                        // a unit variant has nothing to mutate, so it's replaced by a new variant
+                        // unless that would blow the byte budget
+                        mutate_match_arms.push(quote! {
+                            #variant_ident => {
+                                if !mutator.has_byte_budget() {
+                                    #begin_transition
+                                    *self = <#ident>::new_fuzzed(mutator, None);
+                                    mutator.end_variant_transition();
+                                }
+                            },
+                        });
+                    }
+                    syn::Fields::Named(ref fields) => {
+                        return Err(syn::Error::new_spanned(
+                            fields,
+                            "Mutatable doesn't support enum variants with named fields. Use a tuple variant instead, e.g. `Variant(u8, u32)`",
+                        ));
                     }
-                    _ => panic!("unsupported enum variant type"),
                 }
             }
 
-            mutate_body = if enum_has_simple_variants {
+            mutate_body = if !enum_has_data_variants {
                 // TODO: This will keep any #[fuzzer(ignore)] or #[weight(N)] attributes...
                 // which we probably don't want.
                 quote_spanned! { ident.span() =>
+                    #begin_transition
                     *self = <#ident>::new_fuzzed(mutator, None);
+                    mutator.end_variant_transition();
+                }
+            } else if has_declared_transitions {
+                // variants with data normally keep their variant and only mutate their fields.
+                // once transitions have been declared they may occasionally switch to another
+                quote_spanned! { ident.span() =>
+                    if !mutator.has_byte_budget() && mutator.gen_chance(::lain::mutator::CHANCE_TO_SWITCH_VARIANT) {
+                        #begin_transition
+                        *self = <#ident>::new_fuzzed(mutator, None);
+                        mutator.end_variant_transition();
+                    } else {
+                        match *self {
+                            #(#mutate_match_arms)*
+                        }
+                    }
                 }
             } else {
                 quote_spanned! { ident.span() =>
@@ -130,31 +222,135 @@ pub(crate) fn gen_mutate_impl(ident: &Ident, data: &Data) -> TokenStream {
             };
         }
         Data::Struct(ref data) => {
-            if let syn::Fields::Named(ref fields) = data.fields {
-                let fields = parse_fields(&fields);
+            if let Some(fields) = named_fields(&data.fields) {
+                let fields = parse_fields(&fields)?;
                 mutate_body = gen_struct_mutate_impl(&fields);
+
+                if let Some(ref field) = container.field_swap {
+                    swap_fields = quote! {
+                        mutator.maybe_swap_wire_fields(&mut self.#field);
+                    };
+                }
             } else {
-                panic!("struct contains unnamed fields");
+                return Err(named_fields_error("Mutatable", ident, &data.fields));
             }
         }
-        Data::Union(ref _data) => {
-            panic!("unions are unsupported. Please use an enum with typed variants instead");
+        Data::Union(ref data) => {
+            return Err(union_error("Mutatable", data));
+        }
+    }
+
+    // in havoc mode the top-level container may run several stacked passes over its fields (see
+    // `MutatorConfig::stacked_mutation_chance`). the body may return early, so each pass is run
+    // in a closure to make sure the stack is always ended
+    let mutate_body = quote! {
+        let passes = mutator.begin_mutation_stack();
+        {
+            let mut mutate_pass = |mutator: &mut ::lain::mutator::Mutator<R>| {
+                #mutate_body
+            };
+
+            for pass in 0..passes {
+                mutator.begin_stacked_pass(pass);
+                mutate_pass(mutator);
+            }
         }
+        mutator.end_mutation_stack();
+
+        #swap_fields
+    };
+
+    // invariants are only expected to hold once the container has been fixed up
+    let invariant_checks = gen_invariant_checks(&get_invariants(ident, container, data)?)?;
+    let fixup = gen_fixup_passes(quote! {self}, container, data)?;
+
+    if container.seed_domain.is_some() || container.duplicate_chance.is_some() {
+        let (begin_seed_domain, end_seed_domain) =
+            gen_seed_domain_scope(container.seed_domain.as_ref());
+
+        let (begin_duplicate_chance, end_duplicate_chance) = match container.duplicate_chance {
+            Some(chance) => (
+                quote! {
+                    let previous_duplicate_chance = mutator.begin_duplicate_chance_scope(#chance);
+                },
+                quote! {
+                    mutator.end_duplicate_chance_scope(previous_duplicate_chance);
+                },
+            ),
+            None => (TokenStream::new(), TokenStream::new()),
+        };
+
+        // the mutation body never returns early, so the domain and duplicate chance are always
+        // restored
+        return Ok(quote_spanned! { ident.span() =>
+            #[allow(unused)]
+            fn mutate<R: ::lain::rand::Rng>(&mut self, mutator: &mut ::lain::mutator::Mutator<R>, constraints: Option<&Constraints<u8>>) {
+                #begin_seed_domain
+                #begin_duplicate_chance
+
+                #mutate_body
+
+                #end_duplicate_chance
+
+                if mutator.should_fixup() {
+                    #fixup
+
+                    #invariant_checks
+                }
+
+                #end_seed_domain
+            }
+        });
     }
 
-    quote_spanned! { ident.span() =>
+    Ok(quote_spanned! { ident.span() =>
         #[allow(unused)]
         fn mutate<R: ::lain::rand::Rng>(&mut self, mutator: &mut ::lain::mutator::Mutator<R>, constraints: Option<&Constraints<u8>>) {
             #mutate_body
 
             if mutator.should_fixup() {
-                self.fixup(mutator);
+                #fixup
+
+                #invariant_checks
             }
         }
-    }
+    })
 }
 
 fn gen_struct_mutate_impl(fields: &[FuzzerObjectStructField]) -> TokenStream {
+    // arrays with a count field are kept in sync with it after the other fields are mutated. if
+    // the count was mutated the array is resized to match, otherwise the count follows the array.
+    // either may be deliberately left out of sync
+    let mut count_snapshots = vec![];
+    let mut count_syncs = vec![];
+
+    for f in fields.iter() {
+        let count_field = match f.count_field {
+            Some(ref count_field) => count_field,
+            None => continue,
+        };
+
+        let ident = &f.field.ident;
+        let count_before = syn::Ident::new(
+            &format!("{}_count_before", ident.as_ref().unwrap()),
+            count_field.span(),
+        );
+
+        count_snapshots.push(quote_spanned! { count_field.span() =>
+            let #count_before = self.#count_field;
+        });
+
+        count_syncs.push(quote_spanned! { count_field.span() =>
+            if self.#count_field as usize != self.#ident.len() && !mutator.should_desync_count() {
+                let count = self.#count_field as usize;
+                if self.#count_field == #count_before || !mutator.resize_to_count(&mut self.#ident, count) {
+                    self.#count_field = self.#ident.len() as _;
+                }
+            }
+        });
+    }
+
+    let early_count_syncs = &count_syncs;
     let mutation_parts: Vec<TokenStream> = fields
         .iter()
         .map(|f| {
@@ -162,9 +358,61 @@ fn gen_struct_mutate_impl(fields: &[FuzzerObjectStructField]) -> TokenStream {
             let ty = &f.field.ty;
             let ident = &f.field.ident;
 
+            // sections generated once are left as they are, since they're shared by every value
+            if f.generate_once {
+                return field_mutation_tokens;
+            }
+
+            // opaque fields are never mutated in place and are only occasionally regenerated.
+            // pinned regions of byte buffers are left untouched. fields with a min/max are kept
+            // within that range. other fields receive the parent's constraints
+            if f.opaque {
+                let field_constraints = f.constraints(quote! {None}).unwrap_or_else(|| quote! {None});
+                field_mutation_tokens.extend(quote_spanned! { f.field.span() =>
+                    if mutator.should_replace_opaque() {
+                        let field_constraints: Option<::lain::types::Constraints<<#ty as ::lain::traits::NewFuzzed>::RangeType>> = #field_constraints;
+                        self.#ident = <#ty as ::lain::traits::NewFuzzed>::new_fuzzed(mutator, field_constraints.as_ref());
+                    }
+                });
+            } else if !f.pinned.is_empty() {
+                let pinned = &f.pinned;
+                field_mutation_tokens.extend(quote_spanned! { f.field.span() =>
+                    mutator.mutate_bytes_excluding(&mut self.#ident[..], &[#(#pinned),*]);
+                });
+            } else if let Some(field_constraints) = f.constraints(quote! {None}) {
+                field_mutation_tokens.extend(quote_spanned! { f.field.span() =>
+                    let field_constraints: Option<::lain::types::Constraints<<#ty as ::lain::traits::NewFuzzed>::RangeType>> = #field_constraints;
+                    <#ty as ::lain::traits::ConstrainedMutatable>::mutate_constrained(&mut self.#ident, mutator, field_constraints.as_ref());
+                });
+            } else {
+                field_mutation_tokens.extend(quote! {
+                    // constraints should be relatively cheap to clone
+                    <#ty>::mutate(&mut self.#ident, mutator, constraints);
+                });
+            }
+
+            let mut field_mutation_tokens = gen_category_scope(f.category.as_ref(), field_mutation_tokens);
+
+            // the version field is held at the mutator's selected version, and fields which aren't
+            // part of the struct's version aren't worth mutating since they won't be serialized
+            if f.protocol_version {
+                field_mutation_tokens = quote_spanned! { f.field.span() =>
+                    match mutator.protocol_version() {
+                        Some(version) => self.#ident = version.0 as _,
+                        None => {
+                            #field_mutation_tokens
+                        }
+                    }
+                };
+            } else if let Some(ref gate) = f.version_gate {
+                field_mutation_tokens = quote_spanned! { f.field.span() =>
+                    if #gate {
+                        #field_mutation_tokens
+                    }
+                };
+            }
+
             field_mutation_tokens.extend(quote! {
-                // constraints should be relatively cheap to clone
-                <#ty>::mutate(&mut self.#ident, mutator, constraints);
                 // TODO: For later
                 // if let Some(ref mut constraints) = constraints {
                 //     constraints.max_size -= self.ident.serialized_size();
@@ -175,6 +423,8 @@ fn gen_struct_mutate_impl(fields: &[FuzzerObjectStructField]) -> TokenStream {
                         <#ty>::fixup(&mut self.#ident, mutator);
                     }
 
+                    #(#early_count_syncs)*
+
                     return;
                 }
             });
@@ -183,7 +433,95 @@ fn gen_struct_mutate_impl(fields: &[FuzzerObjectStructField]) -> TokenStream {
         })
         .collect();
 
+    // fields in the same group are mutated together or not at all, and every other field is a
+    // group of its own. havoc mutations pick which of the groups to mutate
+    let mut groups: Vec<Option<&str>> = vec![];
+    let mut grouped_parts = vec![];
+    if fields.iter().any(|f| f.group.is_some()) {
+        for (f, part) in fields.iter().zip(mutation_parts.iter()) {
+            if part.is_empty() {
+                continue;
+            }
+
+            let group = f.group.as_deref();
+            let index = match groups.iter().position(|g| g.is_some() && *g == group) {
+                Some(index) => index,
+                None => {
+                    groups.push(group);
+                    groups.len() - 1
+                }
+            };
+
+            grouped_parts.push(quote! {
+                if selected_groups[#index] {
+                    #part
+                }
+            });
+        }
+    }
+
+    let mutation_parts = if groups.is_empty() {
+        quote! {
+            #(#mutation_parts)*
+        }
+    } else {
+        let group_count = groups.len();
+        quote! {
+            let selected_groups = mutator.select_field_groups::<#group_count>();
+
+            #(#grouped_parts)*
+        }
+    };
+
+    // a field may be replaced by the same field of an entry from the mutator's splice corpus
+    // instead. sections shared by every value, the version field, and pinned buffers are kept
+    let splice_arms: Vec<TokenStream> = fields
+        .iter()
+        .filter(|f| !f.generate_once && !f.protocol_version && f.pinned.is_empty())
+        .enumerate()
+        .map(|(i, f)| {
+            let ident = &f.field.ident;
+            quote_spanned! { f.field.span() =>
+                #i => ::lain::mutatable::SpliceField::splice_from(&mut self.#ident, &donor.#ident),
+            }
+        })
+        .collect();
+
+    if splice_arms.is_empty() {
+        return quote! {
+            #(#count_snapshots)*
+
+            #mutation_parts
+
+            #(#count_syncs)*
+        };
+    }
+
+    let splice_count = splice_arms.len();
     quote! {
-        #(#mutation_parts)*
+        #(#count_snapshots)*
+
+        if let Some(donor) = <Self as ::lain::mutatable::SpliceDonor>::splice_donor(mutator) {
+            match mutator.gen_range(0, #splice_count) {
+                #(#splice_arms)*
+                _ => unreachable!(),
+            }
+        } else {
+            #mutation_parts
+        }
+
+        #(#count_syncs)*
+    }
+}
+
+/// Wraps a field's mutation so that the mutator knows which `FieldCategory` it's mutating
+fn gen_category_scope(category: Option<&TokenStream>, mutation: TokenStream) -> TokenStream {
+    match category {
+        Some(category) => quote! {
+            let previous_category = mutator.begin_category_scope(#category);
+            #mutation
+            mutator.end_category_scope(previous_category);
+        },
+        None => mutation,
     }
 }
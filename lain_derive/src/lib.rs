@@ -13,18 +13,48 @@ use proc_macro2::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
 
 mod attr;
+mod debug;
+mod deserialize;
+mod diff;
+mod enumerable;
 mod fuzzerobject;
 mod new_fuzzed;
+mod remote;
 mod serialize;
 mod utils;
 
+use crate::attr::{deprecated_attribute_warnings, get_container_metadata};
+use crate::debug::fuzzed_debug_helper;
+use crate::deserialize::binary_deserialize_helper;
+use crate::diff::structured_diff_helper;
+use crate::enumerable::enumerable_helper;
 use crate::fuzzerobject::*;
 use crate::new_fuzzed::*;
+use crate::remote::gen_conversion;
 use crate::serialize::binary_serialize_helper;
+use crate::utils::{named_fields, named_fields_error, union_error};
 use quote::quote_spanned;
 use syn::spanned::Spanned;
 use syn::{Data, Fields};
 
+/// Converts the output of a derive into the tokens handed back to the compiler. Errors are
+/// reported as `compile_error!` invocations pointing at the offending tokens.
+fn expand(result: syn::Result<TokenStream>) -> proc_macro::TokenStream {
+    proc_macro::TokenStream::from(result.unwrap_or_else(|e| e.to_compile_error()))
+}
+
+/// Like [expand], but also warns about any deprecated standalone attributes (e.g. `#[fuzzer]`)
+/// used by the type. Only derives which accept those attributes should use this.
+fn expand_with_deprecations(
+    input: &DeriveInput,
+    result: syn::Result<TokenStream>,
+) -> proc_macro::TokenStream {
+    expand(result.map(|mut tokens| {
+        tokens.extend(deprecated_attribute_warnings(input));
+        tokens
+    }))
+}
+
 /// Implements [rand::distributions::Standard] for enums that derive this trait.
 /// This will allow you to use `rand::gen()` to randomly select an enum value.
 /// # Example
@@ -40,18 +70,301 @@ use syn::{Data, Fields};
 ///
 /// let choice: Foo = rand::gen();
 /// ```
-#[proc_macro_derive(NewFuzzed, attributes(weight, fuzzer, bitfield))]
+///
+/// # Variant weights
+///
+/// Enum variants are picked according to their `#[lain(weight(...))]`, which is either a weight
+/// relative to the other variants or a percentage of all generated values. Weights may be any
+/// constant expression. Variants without a weight get a relative weight of 1, or evenly split
+/// whatever is left of 100% when percentages are used. Percentages can't be mixed with relative
+/// weights, and their total can't exceed 100%.
+///
+/// ```compile_fail
+/// const COMMON: u64 = 8;
+///
+/// #[derive(NewFuzzed)]
+/// enum Register {
+///     #[lain(weight(COMMON))]
+///     Accumulator,
+///     #[lain(weight(COMMON / 2))]
+///     Index,
+///     Flags,
+/// }
+///
+/// #[derive(NewFuzzed)]
+/// enum Opcode {
+///     #[lain(weight(60%))]
+///     Load,
+///     #[lain(weight(30%))]
+///     Store,
+///     // generated the remaining 10% of the time
+///     Halt,
+/// }
+/// ```
+///
+/// # Variant transitions
+///
+/// When a derived `Mutatable` enum is mutated it normally becomes any variant (unit-only enums)
+/// or keeps its variant and mutates its fields. `#[lain(transitions(...))]` on a variant lists the
+/// variants it may be mutated into, which keeps stateful protocol messages plausible (e.g. a
+/// `Close` is never followed by a `Write`). Variants without the attribute may become any variant,
+/// and a variant may always stay as it is. Once any transitions are declared, enums with data
+/// occasionally switch to an allowed variant as well. The transitions are still broken now and
+/// then unless the mutator only generates valid values, and `Mutator::set_variant_transitions`
+/// replaces them at runtime.
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed, Mutatable)]
+/// enum Command {
+///     #[lain(transitions(Write, Close))]
+///     Open,
+///     #[lain(transitions(Write, Close))]
+///     Write(u32),
+///     #[lain(transitions(Open))]
+///     Close,
+/// }
+/// ```
+///
+/// # Constraints
+///
+/// The `min` and `max` of constraints passed to an enum's `new_fuzzed` (or given on a field of
+/// the enum's type with `#[lain(min = 1, max = 3)]`) restrict generation to the variants whose
+/// declaration index lies in that range. Indices count every variant, including ignored ones, and
+/// the picked variants keep their relative weights. The bounds are occasionally ignored just like
+/// numeric ones, depending on the mutator's generation profile.
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed)]
+/// enum Command {
+///     Nop,
+///     Read,
+///     Write,
+///     Reset,
+/// }
+///
+/// let constraints = Constraints { min: Some(1), max: Some(3), ..Default::default() };
+/// // either Read or Write
+/// let command = Command::new_fuzzed(&mut mutator, Some(&constraints));
+/// ```
+///
+/// # Remote types
+///
+/// Types from other crates can't derive lain's traits directly. Instead, declare a local mirror
+/// with the same fields (or variants) and point it at the remote type with
+/// `#[lain(remote = "other_crate::Type")]`. Along with the usual trait impls on the mirror,
+/// `NewFuzzed` generates `Mirror::new_fuzzed_remote()`, `Mutatable` generates
+/// `Mirror::mutate_remote()`, and `BinarySerialize` generates `Mirror::binary_serialize_remote()`
+/// and `Mirror::serialized_size_remote()`, all of which operate on the remote type. The remote
+/// type's fields must be visible to the mirror, and mutating it requires `Clone`. Serializing
+/// reads the remote value's fields in place.
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed, Mutatable, BinarySerialize)]
+/// #[lain(remote = "vendored_protocol::Header")]
+/// struct HeaderDef {
+///     magic: u32,
+///     length: u16,
+/// }
+///
+/// let header: vendored_protocol::Header = HeaderDef::new_fuzzed_remote(&mut mutator, None);
+/// ```
+///
+/// # Seed domains
+///
+/// `#[lain(seed_domain = "name")]` derives all randomness used to generate (or mutate) the type
+/// from the campaign's root seed and the domain's name, rather than the per-iteration seed. The
+/// type is then generated identically every iteration while everything around it still varies,
+/// which is useful for values such as session IDs. The mutator's RNG must implement
+/// `SeedableRng` and the root seed must be set with `Mutator::set_seed` (the driver does this).
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed)]
+/// #[lain(seed_domain = "session")]
+/// struct Session {
+///     id: u64,
+/// }
+/// ```
+///
+/// # Templates
+///
+/// `#[lain(seed_from = "expr")]` generates new values by mutating a known-good template instead
+/// of building them from scratch (see `lain::traits::FromSeed`), which keeps inputs valid enough
+/// to get past a parser's sanity checks. The expression is evaluated every time a value is
+/// generated and must return `Option<Self>`. `None` falls back to generating from scratch, so a
+/// missing sample file doesn't stop the campaign. Templates are usually parsed once, e.g. in a
+/// `lazy_static`, and cloned. The type must implement `Clone` and `Mutatable`.
+///
+/// ```compile_fail
+/// lazy_static! {
+///     static ref TEMPLATE: Option<Header> = std::fs::read("samples/header.bin")
+///         .ok()
+///         .and_then(|bytes| Header::binary_deserialize::<_, BigEndian>(&mut &bytes[..]).ok());
+/// }
+///
+/// #[derive(Clone, NewFuzzed, Mutatable, BinaryDeserialize)]
+/// #[lain(seed_from = "TEMPLATE.clone()")]
+/// struct Header {
+///     magic: u32,
+///     length: u16,
+/// }
+/// ```
+///
+/// # Invariants
+///
+/// `#[lain(assert = "expr")]` on a struct or one of its fields declares an invariant which the
+/// struct's fixups are expected to establish, such as a length field matching its payload. The
+/// expression is evaluated with `self` bound to the struct after it's generated or mutated and
+/// fixed up, and a violation panics in debug builds with the struct (or `Struct.field`) and the
+/// expression. Release builds don't check invariants. Iterations which skip the fixup on
+/// purpose aren't checked either.
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed, Mutatable)]
+/// #[lain(assert = "self.data.len() <= 64")]
+/// struct Chunk {
+///     #[lain(assert = "self.len as usize == self.data.len()")]
+///     len: u32,
+///     #[lain(max = 64)]
+///     data: Vec<u8>,
+/// }
+/// ```
+///
+/// # Protocol versions
+///
+/// A struct can model several versions of a protocol by gating the fields which only some versions
+/// have with `#[lain(version_gate = "expr")]`. The condition is evaluated with `self` bound to the
+/// struct: gated fields are reset to their default after generation when it doesn't hold, and
+/// they're skipped by mutation, serialization, and deserialization (which evaluates it against the
+/// fields read so far). Gated fields must implement `Default`.
+///
+/// The field holding the version can be marked `#[lain(protocol_version)]`. When a version has
+/// been selected with `Mutator::set_protocol_version`, that field is generated as the selected
+/// version and isn't mutated, so every value has that version's layout.
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize)]
+/// struct Hello {
+///     #[lain(protocol_version)]
+///     version: u8,
+///     session_id: u32,
+///     #[lain(version_gate = "self.version >= 2")]
+///     extensions: u16,
+/// }
+/// ```
+///
+/// # Generation observers
+///
+/// Each field's value is passed to the observers registered with `lain::observer` (if any) before
+/// it's stored in the struct. Observers may adjust the value, or reject it to have the field
+/// generated again. Fields of generic structs aren't observed.
+///
+/// # Volatile fields
+///
+/// Fields such as sequence numbers and timestamps can be marked `#[lain(volatile)]`. Structs with
+/// volatile fields also implement `lain::traits::Refresh`, whose `refresh(mutator)` generates
+/// volatile fields again (with their min/max and initializer) and refreshes nested structs, then
+/// fixes up the struct. `FuzzerContext::serialize_refreshed` refreshes a value before serializing
+/// it, so a replayed corpus entry isn't rejected for reusing a stale sequence number. Fields of
+/// enum variants aren't refreshed.
+///
+/// `Refresh` specializes a blanket impl, so deriving it requires `#![feature(specialization)]`.
+/// A struct without volatile fields of its own which holds structs that have some is marked
+/// `#[lain(refresh)]` to implement it too.
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed, BinarySerialize)]
+/// struct Request {
+///     #[lain(volatile)]
+///     sequence: u32,
+///     command: u8,
+/// }
+///
+/// #[derive(NewFuzzed, BinarySerialize)]
+/// #[lain(refresh)]
+/// struct Session {
+///     request: Request,
+///     payload: Vec<u8>,
+/// }
+/// ```
+///
+/// # Generate-once sections
+///
+/// Large sections which are the same for every input, such as a valid certificate chain, can be
+/// marked `#[lain(generate_once)]`. The field is generated (with its min/max and initializer) the
+/// first time a value is generated and cached by the mutator, keyed by the struct's type and the
+/// field's name, so later values get a clone of it instead of generating it again. The section is
+/// never mutated. `Mutator::clear_static_sections` has the sections generated again. The field's
+/// type must implement `Clone` and `Send`.
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed, Mutatable, BinarySerialize)]
+/// struct ClientHello {
+///     random: [u8; 32],
+///     #[lain(generate_once, initializer = "build_certificate_chain()")]
+///     certificates: Vec<u8>,
+/// }
+/// ```
+///
+/// # Corpus splicing
+///
+/// Once the mutator has a corpus of stored inputs of a derived struct's type
+/// (`Mutator::set_splice_corpus`), a havoc mutation of the struct occasionally replaces one of its
+/// fields with the same field of a random corpus entry instead of mutating its fields. Nested
+/// structs are spliced whole, recombining sections of inputs which were found to be interesting.
+/// Only structs and fields which implement `Clone` are spliced, and generate-once sections,
+/// protocol version fields, and pinned buffers are never replaced.
+///
+/// # Field groups
+///
+/// Fields which only make sense together, such as a width and height or an offset and length,
+/// can be put in the same `#[lain(group = "...")]`. A havoc mutation of the struct picks a random
+/// set of groups to mutate, where every field without a group is a group of its own, and mutates
+/// either all of a group's fields or none of them.
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed, Mutatable, BinarySerialize)]
+/// struct Rect {
+///     #[lain(group = "geometry")]
+///     width: u32,
+///     #[lain(group = "geometry")]
+///     height: u32,
+///     color: u32,
+/// }
+/// ```
+///
+/// # Zero-sized types
+///
+/// Unit structs, `()`, and `PhantomData` have a single value, so they're generated, mutated, and
+/// serialized as no-ops. This lets generic models carry type-state markers:
+///
+/// ```compile_fail
+/// #[derive(NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize)]
+/// struct Authenticated;
+///
+/// #[derive(NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize)]
+/// struct Session<S: 'static> {
+///     id: u32,
+///     state: PhantomData<S>,
+/// }
+/// ```
+#[proc_macro_derive(NewFuzzed, attributes(weight, fuzzer, bitfield, lain))]
 pub fn new_fuzzed(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let tokens = new_fuzzed_helper(input);
-    // println!("{}", tokens);
+    let input = parse_macro_input!(input as DeriveInput);
 
-    tokens
+    expand_with_deprecations(&input, new_fuzzed_helper(&input))
 }
 
 /// Implements [lain::traits::BinarySerialize] on the given struct/enum.
 /// The byteorder of fields can be overridden with `#[byteorder(big)]` or
 /// `#[byteorder(little)]`
 ///
+/// Formats which are neither big nor little endian, such as PDP-11 "middle endian" integers, can
+/// rearrange a field's serialized bytes with `#[lain(byte_permutation = "path::to::fn")]` (see
+/// `lain::byte_permutation`). `BinaryDeserialize` undoes the permutation before reading the field.
+///
+/// Fieldless enums with an integer representation (e.g. `#[repr(u32)]`) are serialized as
+/// their declared discriminant using the width of the `repr` type.
+///
 /// # Example
 ///
 /// ```compile_fail
@@ -85,7 +398,7 @@ pub fn new_fuzzed(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 ///    {
 ///        let buffer_ref: &mut [u8] = &mut serialized_buffer;
 ///        let mut writer = BufWriter::new(buffer_ref);
-///        s.binary_serialize::<_, lain::byteorder::BigEndian>(&mut writer);
+///        s.binary_serialize::<_, lain::byteorder::BigEndian>(&mut writer).unwrap();
 ///    }
 ///    println!("{}", &hexdump(serialized_buffer.iter()));
 ///    // Output:
@@ -93,12 +406,169 @@ pub fn new_fuzzed(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 ///    // 0000: AA BB CC DD 33 22 11 00
 ///}
 /// ```
+///
+/// # Field order
+///
+/// Fields are written in declaration order by default. Marking the struct
+/// `#[lain(sort_by_order)]` writes them by their `#[lain(order = N)]` position instead, so that
+/// related fields can be grouped together in code while still matching the wire layout. Every
+/// field then needs a unique position. Bitfields are packed in wire order. `BinaryDeserialize`
+/// reads fields in the same order.
+///
+/// ```compile_fail
+/// #[derive(BinarySerialize, BinaryDeserialize)]
+/// #[lain(sort_by_order)]
+/// struct Record {
+///     // written first
+///     #[lain(order = 0)]
+///     kind: u8,
+///     #[lain(order = 2)]
+///     payload_length: u16,
+///     #[lain(order = 3)]
+///     payload: [u8; 16],
+///     // written between kind and payload_length
+///     #[lain(order = 1)]
+///     flags: u8,
+/// }
+/// ```
+///
+/// Structs marked `#[lain(field_swap = "...")]` are occasionally serialized with two adjacent
+/// fields swapped after they're mutated, while the fields themselves keep their values. The
+/// attribute names a [lain::types::FieldSwap] field of the struct, which records the swap and
+/// isn't serialized. How often fields are swapped is set by
+/// [lain::mutator::MutatorConfig::field_swap_chance]. Bitfields are never swapped.
+///
+/// ```compile_fail
+/// #[derive(BinarySerialize, Mutatable)]
+/// #[lain(field_swap = "wire_order")]
+/// struct Record {
+///     kind: u8,
+///     length: u16,
+///     flags: u32,
+///     wire_order: FieldSwap,
+/// }
+/// ```
+///
+/// # Serializing without allocating
+///
+/// `#[lain(no_alloc_serialize)]` additionally implements [trait@lain::traits::NoAllocSerialize],
+/// whose `binary_serialize_into` writes to a fixed buffer without allocating or panicking. This
+/// lets a crash handler re-serialize the input that was being processed. Every field's type must
+/// implement `NoAllocSerialize` as well, byte permutations aren't allowed, and an enum variant
+/// which doesn't fit in its `serialized_size` is reported as an `InvalidData` error instead of a
+/// panic.
+///
+/// ```compile_fail
+/// #[derive(BinarySerialize)]
+/// #[lain(no_alloc_serialize)]
+/// struct Packet {
+///     length: u16,
+///     payload: [u8; 32],
+/// }
+///
+/// static mut LAST_INPUT: [u8; 34] = [0; 34];
+///
+/// // inside the crash handler
+/// let len = packet.binary_serialize_into::<BigEndian>(unsafe { &mut LAST_INPUT })?;
+/// ```
+///
+/// # Counted sizes
+///
+/// `SerializedSize` normally adds up the sizes of the fields, which can drift from what
+/// serialization actually writes when a field's type has a hand-written size. Marking the type
+/// `#[lain(counted_size)]` instead computes the size by serializing the value into a
+/// [lain::buffer::CountingWriter], which only counts the bytes written, so the size always matches
+/// the serialized data. This can't be combined with a fixed `serialized_size`.
+///
+/// ```compile_fail
+/// #[derive(BinarySerialize)]
+/// #[lain(counted_size)]
+/// struct Record {
+///     tag: u8,
+///     name: EscapedString,
+///     entries: Vec<u16>,
+/// }
+/// ```
+///
+/// # Packed layouts
+///
+/// Marking a `#[repr(C, packed)]` struct `#[lain(packed_layout)]` checks at compile time that its
+/// serialized layout is its in-memory layout: every field must implement
+/// [trait@lain::traits::PlainOldData] (the primitive numbers, `bool`, arrays of them, and other
+/// packed layout structs) and sit at the offset it's serialized at. The struct must be `Copy`,
+/// since fields of packed structs are copied out rather than borrowed. Adding
+/// `#[lain(memcpy_serialize)]` then serializes the whole struct with a single copy of its bytes
+/// whenever the byte order is the platform's native one, and field by field otherwise.
+///
+/// ```compile_fail
+/// #[derive(Clone, Copy, NewFuzzed, BinarySerialize)]
+/// #[repr(C, packed)]
+/// #[lain(packed_layout, memcpy_serialize)]
+/// struct ElfIdent {
+///     magic: [u8; 4],
+///     class: u8,
+///     data: u8,
+///     version: u8,
+///     padding: [u8; 9],
+/// }
+/// ```
+///
+/// # Field metadata
+///
+/// Structs marked `#[lain(field_metadata)]` also implement [trait@lain::traits::FieldMetadata],
+/// which describes their fields at runtime for generic tooling. `field_info()` lists each field's
+/// name, type, and attribute settings, and `field_layout()` gives the serialized offset and size
+/// of every field of a value, including the fields of nested structs which are marked as well
+/// (e.g. `header.length`). `field_at_offset()` maps a byte of the serialized value back to the
+/// field which produced it. Since every type has a default `FieldMetadata` impl, this requires
+/// `#![feature(specialization)]` in the crate deriving it.
+///
+/// ```compile_fail
+/// let field = packet.field_at_offset(crash_offset).unwrap();
+/// println!("the target choked on {} ({})", field.path, field.type_name);
+/// ```
+///
+/// # Input shape
+///
+/// Structs and enums marked `#[lain(input_shape)]` also implement
+/// [trait@lain::traits::InputShape], which hashes the variants a value holds and which of its
+/// optional fields are present. [lain::dedup] uses this to bucket crashing inputs which only
+/// differ in their raw bytes. Like `#[lain(field_metadata)]`, this requires
+/// `#![feature(specialization)]`.
 #[proc_macro_derive(
     BinarySerialize,
-    attributes(bitfield, byteorder, inner_member_serialized_size, serialized_size)
+    attributes(bitfield, byteorder, inner_member_serialized_size, serialized_size, lain)
 )]
 pub fn binary_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    binary_serialize_helper(input)
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_with_deprecations(&input, binary_serialize_helper(&input))
+}
+
+/// Implements [trait@lain::traits::BinaryDeserialize] on the given struct/enum, reading data in
+/// the format written by [lain::traits::BinarySerialize]. `#[byteorder()]` and `#[bitfield()]`
+/// attributes are honored.
+///
+/// Only fieldless enums with an integer representation (e.g. `#[repr(u8)]`) are supported,
+/// since the serialized data doesn't record which variant of an enum with fields was written.
+///
+/// # Example
+///
+/// ```compile_fail
+/// #[derive(BinarySerialize, BinaryDeserialize)]
+/// struct Header {
+///     magic: u32,
+///     #[byteorder(little)]
+///     length: u16,
+/// }
+///
+/// let header = Header::binary_deserialize::<_, BigEndian>(&mut bytes.as_slice())?;
+/// ```
+#[proc_macro_derive(BinaryDeserialize, attributes(bitfield, byteorder, lain))]
+pub fn binary_deserialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_with_deprecations(&input, binary_deserialize_helper(&input))
 }
 
 /// Automatically implements [trait@lain::traits::Mutatable] with basic
@@ -108,8 +578,43 @@ pub fn binary_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStre
 ///
 /// - Any bitfields will automatically be set within the appropriate ranges.
 /// - Min/max values for primitives can be specified using `#[fuzzer(min = 10, max = 20)]`.
+///   Mutations keep these fields within the range for types which implement
+///   [trait@lain::traits::ConstrainedMutatable] (e.g. floats).
+/// - `max` is exclusive. Specs which give an inclusive maximum can use
+///   `#[lain(min = 1, max_inclusive = 20)]` instead.
+/// - On a collection such as a `Vec`, `min` and `max` bound its length. Each element can be bounded
+///   with `#[lain(min = 1, max = 8, element_min = 1, element_max = 100)]` (or
+///   `element_max_inclusive`), which is passed on as the
+///   [lain::types::Constraints::element_constraints] of the collection.
+/// - Ranges may include or exclude their bounds using
+///   `#[fuzzer(min = 0.0, max = 1.0, bounds = "closed")]`. Valid values are `closed_open` (the default),
+///   `closed`, `open`, and `open_closed`.
 /// - Fields can be ignored using #[fuzzer(ignore = true)].
-/// - Custom initializers can be specified using #[fuzzer(initializer = "my_initializer_func()")]
+/// - Fields are mutated with the `MutatorConfig` strategy mix for their type's
+///   [lain::mutator::FieldCategory]. Fields whose type doesn't reveal their category, such as a
+///   `Vec<u8>` holding text, can be given one with `#[lain(category = "string")]`. Valid values are
+///   `integer`, `string`, `collection`, and `enum`.
+/// - Fields marked `#[fuzzer(exhaustive)]` are swept through every combination of their values
+///   when the driver is configured with `FuzzerDriver::set_exhaustive_sweep`. Their types must
+///   implement [trait@lain::traits::Enumerable], or be integers with both a `min` and `max`.
+/// - Custom initializers can be specified using #[fuzzer(initializer = "my_initializer_func()")].
+///   The initializer expression may reference `mutator` (the `&mut Mutator<R>` passed to `new_fuzzed`)
+///   so that random values are reproducible with the campaign seed. If the field has a `min` or `max`,
+///   the expression may also reference `constraints`, an `Option<&Constraints<_>>` built from them.
+/// - Regions of byte buffers (e.g. an embedded header in a `Vec<u8>` payload) can be protected from
+///   mutation using `#[lain(pin(0..16, 32..36))]`. The rest of the buffer is mutated as usual.
+/// - Fields marked `#[lain(opaque)]` are generated once (by `NewFuzzed` or the field's
+///   `initializer`) and never mutated in place. Instead they're occasionally replaced with a newly
+///   generated value. This suits signed or encrypted blobs, which a target rejects after any
+///   partial change.
+/// - `#[lain(count_field = "num_entries")]` on a `Vec` binds its length to an integer field of the
+///   same struct. Generating the struct sets the count to the array's length. After a mutation,
+///   a mutated count resizes the array (generating new elements as needed), and otherwise the
+///   count is updated to the array's new length. Either is occasionally left out of sync on
+///   purpose, as configured by `MutatorConfig::count_desync_chance`.
+/// - In havoc mode the outermost container may make several stacked passes over its fields, as
+///   configured by `MutatorConfig::stacked_mutation_chance`. Nested containers and the
+///   deterministic modes always make a single pass.
 ///
 /// # Example
 ///
@@ -140,17 +645,45 @@ pub fn binary_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStre
 /// let my_struct: Foo = Default::default();
 /// my_struct.mutate()
 /// ```
-#[proc_macro_derive(Mutatable, attributes(fuzzer, bitfield))]
+#[proc_macro_derive(Mutatable, attributes(fuzzer, bitfield, lain))]
 pub fn mutatable_helper(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
-    let name = input.ident;
+    expand_with_deprecations(&input, mutatable_impl(&input))
+}
+
+fn mutatable_impl(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let imp = gen_mutate_impl(&name, &input.data);
+    let container = get_container_metadata(&input.attrs)?;
+
+    let imp = gen_mutate_impl(name, &input.data, &container)?;
+
+    let remote_impl = if let Some(ref remote) = container.remote {
+        let from_remote =
+            gen_conversion(remote, &quote! {#name}, &input.data, quote! {value.clone()})?;
+        let into_remote = gen_conversion(&quote! {#name}, remote, &input.data, quote! {mirror})?;
+
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Mutates a value of the remote type this type mirrors. The remote type must implement `Clone`
+                pub fn mutate_remote<R: ::lain::rand::Rng>(value: &mut #remote, mutator: &mut ::lain::mutator::Mutator<R>, constraints: Option<&::lain::types::Constraints<u8>>) {
+                    let mut mirror: Self = #from_remote;
+                    <Self as ::lain::traits::Mutatable>::mutate(&mut mirror, mutator, constraints);
+
+                    *value = #into_remote;
+                }
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
 
     let expanded = quote! {
+        #remote_impl
+
         impl #impl_generics ::lain::traits::Mutatable for #name #ty_generics #where_clause {
             #imp
         }
@@ -159,7 +692,7 @@ pub fn mutatable_helper(input: proc_macro::TokenStream) -> proc_macro::TokenStre
     // Uncomment to dump the AST
     // println!("{}", expanded);
 
-    proc_macro::TokenStream::from(expanded)
+    Ok(expanded)
 }
 
 /// Automatically implements [trait@lain::traits::VariableSizeObject]
@@ -181,10 +714,16 @@ pub fn mutatable_helper(input: proc_macro::TokenStream) -> proc_macro::TokenStre
 /// let my_struct: Foo = Default::default();
 /// my_struct.mutate()
 /// ```
-#[proc_macro_derive(VariableSizeObject)]
+#[proc_macro_derive(VariableSizeObject, attributes(lain))]
 pub fn variable_size_object_helper(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
+    expand(variable_size_object_impl(&input))
+}
+
+fn variable_size_object_impl(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
+
     let imp: TokenStream;
 
     match input.data {
@@ -208,7 +747,7 @@ pub fn variable_size_object_helper(input: proc_macro::TokenStream) -> proc_macro
             };
         }
         Data::Struct(ref data) => {
-            if let Fields::Named(ref fields) = data.fields {
+            if let Some(fields) = named_fields(&data.fields) {
                 if fields.named.len() == 0 {
                     imp = quote! {false};
                 } else {
@@ -224,14 +763,16 @@ pub fn variable_size_object_helper(input: proc_macro::TokenStream) -> proc_macro
                     imp = tokens;
                 }
             } else {
-                panic!("Need to add support for unnamed struct fields");
+                return Err(named_fields_error(
+                    "VariableSizeObject",
+                    name,
+                    &data.fields,
+                ));
             }
         }
-        _ => panic!("Non-enum/struct data types are not supported"),
+        Data::Union(ref data) => return Err(union_error("VariableSizeObject", data)),
     }
 
-    let name = input.ident;
-
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
     let expanded = quote! {
@@ -245,18 +786,22 @@ pub fn variable_size_object_helper(input: proc_macro::TokenStream) -> proc_macro
     // Uncomment to dump the AST
     // println!("{}", expanded);
 
-    proc_macro::TokenStream::from(expanded)
+    Ok(expanded)
 }
 
-#[proc_macro_derive(PostFuzzerIteration)]
+#[proc_macro_derive(PostFuzzerIteration, attributes(lain))]
 pub fn post_fuzzer_iteration(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
-    let name = input.ident;
+    expand(post_fuzzer_iteration_impl(&input))
+}
+
+fn post_fuzzer_iteration_impl(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let on_success = get_post_fuzzer_iteration_impls(&name, &input.data);
+    let on_success = get_post_fuzzer_iteration_impls(name, &input.data)?;
 
     let expanded = quote! {
         impl #impl_generics ::lain::traits::PostFuzzerIteration for #name #ty_generics #where_clause {
@@ -269,21 +814,50 @@ pub fn post_fuzzer_iteration(input: proc_macro::TokenStream) -> proc_macro::Toke
     // Uncomment to dump the AST
     debug!("{}", expanded);
 
-    proc_macro::TokenStream::from(expanded)
+    Ok(expanded)
 }
 
 /// Automatically implements [trait@lain::traits::FixupChildren] for the given type. Custom implementations
 /// of [trait@lain::traits::Fixup] should call this function at the end of the fixup operations to ensure that
 /// all child fields are properly handled.
-#[proc_macro_derive(FixupChildren)]
+///
+/// # Fixup order
+///
+/// Fields are fixed up in declaration order by default. A field's place can be changed with
+/// `#[lain(fixup_order = N)]`: fields are fixed up in ascending order, fields without an order are
+/// treated as order 0, and ties keep their declaration order.
+///
+/// Fixups which depend on each other, such as a checksum covering a length which is computed from a
+/// payload, may not settle in a single pass. Structs with a `fixup_order` on any field are
+/// therefore fixed up twice after being generated or mutated. The number of passes can be set with
+/// `#[lain(fixup_passes = N)]` on the struct.
+///
+/// ```compile_fail
+/// extern crate lain;
+/// use lain::prelude::*;
+///
+/// #[derive(Default, NewFuzzed, Mutatable, BinarySerialize, FixupChildren)]
+/// #[lain(fixup_passes = 3)]
+/// struct Packet {
+///     // fixed up after the payload
+///     #[lain(fixup_order = 1)]
+///     header: Header,
+///     payload: Payload,
+/// }
+/// ```
+#[proc_macro_derive(FixupChildren, attributes(lain))]
 pub fn post_mutation(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
-    let name = input.ident;
+    expand(post_mutation_impl(&input))
+}
+
+fn post_mutation_impl(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let post_mutation = get_post_mutation_impl(&name, &input.data);
+    let post_mutation = get_post_mutation_impl(name, &input.data)?;
 
     let expanded = quote! {
         impl #impl_generics ::lain::traits::FixupChildren for #name #ty_generics #where_clause {
@@ -296,25 +870,189 @@ pub fn post_mutation(input: proc_macro::TokenStream) -> proc_macro::TokenStream
     // Uncomment to dump the AST
     debug!("{}", expanded);
 
-    proc_macro::TokenStream::from(expanded)
+    Ok(expanded)
+}
+
+/// Implements [trait@lain::traits::StructuredDiff] on the given struct/enum. The generated `diff`
+/// method compares each field recursively and reports the path of every leaf that differs.
+///
+/// # Example
+///
+/// ```compile_fail
+/// extern crate lain;
+/// use lain::prelude::*;
+///
+/// #[derive(Default, Clone, NewFuzzed, Mutatable, StructuredDiff)]
+/// struct Foo {
+///     field1: u8,
+///     field2: Vec<u32>,
+/// }
+///
+/// let mut mutator = Mutator::new(lain::rand::thread_rng());
+/// let original = Foo::new_fuzzed(&mut mutator, None);
+/// let mut mutated = original.clone();
+/// mutated.mutate(&mut mutator, None);
+///
+/// for delta in original.diff(&mutated) {
+///     println!("{} changed: {:?}", delta.path, delta.kind);
+/// }
+/// ```
+#[proc_macro_derive(StructuredDiff, attributes(lain))]
+pub fn structured_diff(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(structured_diff_helper(&input))
 }
 
-/// A "catch-all" derive for NewFuzzed, Mutatable, PostFuzzerIteration, FixupChildren, and VariableObjectSize
-#[proc_macro_derive(FuzzerObject, attributes(fuzzer, bitfield, weight))]
+/// Implements [trait@lain::traits::FuzzedDebug] on the given struct/enum. The generated
+/// tree shows the path, type, and serialized size (if the type implements `SerializedSize`) of
+/// every field.
+///
+/// # Example
+///
+/// ```compile_fail
+/// extern crate lain;
+/// use lain::prelude::*;
+///
+/// #[derive(NewFuzzed, BinarySerialize, FuzzedDebug)]
+/// struct Foo {
+///     field1: u8,
+///     field2: Vec<u8>,
+/// }
+///
+/// let mut mutator = Mutator::new(lain::rand::thread_rng());
+/// let value = Foo::new_fuzzed(&mut mutator, None);
+///
+/// print!("{}", value.to_fuzzed_debug_string());
+/// // Output:
+/// // Foo (6 bytes)
+/// //   field1: u8 (1 bytes) = 18 (0x12)
+/// //   field2: Vec<u8> (5 bytes) = AA BB CC DD EE
+/// ```
+#[proc_macro_derive(FuzzedDebug, attributes(lain))]
+pub fn fuzzed_debug(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(fuzzed_debug_helper(&input))
+}
+
+/// Implements [trait@lain::traits::Enumerable] on the given struct/enum. Structs enumerate every
+/// combination of their fields, and enums enumerate every combination of each variant's fields.
+/// Struct fields with `#[fuzzer(ignore)]` always use their default value, and integer fields with
+/// both a `min` and `max` are enumerated over that range.
+///
+/// # Example
+///
+/// ```compile_fail
+/// extern crate lain;
+/// use lain::prelude::*;
+///
+/// #[derive(Enumerable)]
+/// enum Opcode {
+///     Read,
+///     Write,
+///     Seek(bool),
+/// }
+///
+/// #[derive(Enumerable)]
+/// struct Flags {
+///     opcode: Opcode,
+///     #[fuzzer(min = 1, max = 4)]
+///     priority: u8,
+/// }
+///
+/// assert_eq!(Opcode::value_count(), 4);
+/// assert_eq!(Flags::value_count(), 12);
+///
+/// for flags in Flags::all_values() {
+///     // ...
+/// }
+/// ```
+#[proc_macro_derive(Enumerable, attributes(fuzzer, lain))]
+pub fn enumerable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_with_deprecations(&input, enumerable_helper(&input))
+}
+
+/// A "catch-all" derive for NewFuzzed, Mutatable, BinarySerialize (which includes SerializedSize),
+/// PostFuzzerIteration, FixupChildren, and VariableSizeObject
+///
+/// # Attributes
+///
+/// All derives accept their attributes in the `#[lain(...)]` namespace:
+///
+/// - Generation and mutation options such as `min`, `max`, `ignore`, and `initializer` are given
+///   directly, e.g. `#[lain(min = 1, max = 10)]`.
+/// - Field and variant attributes are nested under their name: `#[lain(bitfield(...))]`,
+///   `#[lain(byteorder(...))]`, `#[lain(weight(...))]`, and `#[lain(pin(...))]`.
+/// - Container options are given as `#[lain(remote = "...")]`, `#[lain(seed_domain = "...")]`,
+///   `#[lain(seed_from = "...")]`, `#[lain(serialized_size = N)]`, `#[lain(sort_by_order)]`,
+///   `#[lain(no_alloc_serialize)]` (see the `BinarySerialize` derive), and
+///   `#[lain(duplicate_chance = N)]`. The last sets the percent chance that mutating one of the
+///   container's `Vec` fields (or anything nested in it) inserts a copy of one of its elements,
+///   overriding `MutatorConfig::duplicate_chance`.
+/// - `#[lain(assert = "expr")]` on a struct or field declares an invariant checked in debug
+///   builds after generation and mutation (see the `NewFuzzed` derive).
+/// - `#[lain(version_gate = "expr")]` on a field only includes it in the protocol versions for
+///   which the condition holds, and `#[lain(protocol_version)]` marks the field holding the
+///   version (see the `NewFuzzed` derive).
+/// - `#[lain(flatten)]` on a field whose type is a struct treats that struct's fields as the
+///   parent's own, which is handy for sharing a common header across message types. Nested
+///   structs are always serialized back to back with their siblings and share the parent's byte
+///   order and size budget. Flattening also drops the field's name from `StructuredDiff` paths
+///   and `FuzzedDebug` trees, and rejects attributes which would treat the nested struct as a
+///   single value, such as `byteorder` or `min`.
+///
+/// The standalone `#[fuzzer(...)]`, `#[bitfield(...)]`, `#[byteorder(...)]`, `#[weight(...)]`, and
+/// `#[serialized_size(...)]` attributes are deprecated aliases. They behave identically, but the
+/// derives emit a deprecation warning pointing at each of them.
+///
+/// # Example
+///
+/// ```compile_fail
+/// #[derive(Debug, Clone, FuzzerObject)]
+/// struct Packet {
+///     #[lain(bitfield(backing_type = "u8", bits = 4))]
+///     version: u8,
+///     #[lain(bitfield(backing_type = "u8", bits = 4))]
+///     flags: u8,
+///     #[lain(byteorder(little), min = 1, max = 1500)]
+///     length: u16,
+///     opcode: Opcode,
+/// }
+///
+/// #[derive(Debug, Copy, Clone, FuzzerObject)]
+/// #[repr(u8)]
+/// enum Opcode {
+///     #[lain(weight(80%))]
+///     Data,
+///     Ack,
+/// }
+/// ```
+#[proc_macro_derive(
+    FuzzerObject,
+    attributes(fuzzer, bitfield, byteorder, weight, serialized_size, lain)
+)]
 pub fn fuzzer_object(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_with_deprecations(&input, fuzzer_object_impl(&input))
+}
+
+fn fuzzer_object_impl(input: &DeriveInput) -> syn::Result<TokenStream> {
     let mut base_token_stream = TokenStream::new();
-    base_token_stream.extend(TokenStream::from(new_fuzzed_helper(input.clone())));
-    base_token_stream.extend(TokenStream::from(mutatable_helper(input.clone())));
-    base_token_stream.extend(TokenStream::from(post_fuzzer_iteration(input.clone())));
-    base_token_stream.extend(TokenStream::from(post_mutation(input.clone())));
-    base_token_stream.extend(TokenStream::from(variable_size_object_helper(
-        input.clone(),
-    )));
+    base_token_stream.extend(new_fuzzed_helper(input)?);
+    base_token_stream.extend(mutatable_impl(input)?);
+    base_token_stream.extend(binary_serialize_helper(input)?);
+    base_token_stream.extend(post_fuzzer_iteration_impl(input)?);
+    base_token_stream.extend(post_mutation_impl(input)?);
+    base_token_stream.extend(variable_size_object_impl(input)?);
 
     // Uncomment to dump the AST
     debug!("{}", base_token_stream);
 
-    proc_macro::TokenStream::from(base_token_stream)
+    Ok(base_token_stream)
 }
 
 /// Implements `ToPrimitive<u8>` for the given enum.
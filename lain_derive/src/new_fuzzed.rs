@@ -1,36 +1,44 @@
-extern crate proc_macro;
-
 use crate::utils::*;
 use proc_macro2::TokenStream;
 use quote::{quote, quote_spanned};
 use std::str::FromStr;
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, Data, DeriveInput, Lit, NestedMeta};
-
-use crate::attr::{get_attribute_metadata, get_fuzzer_metadata, get_lit_bool};
+use syn::{Data, DeriveInput};
 
-pub(crate) fn new_fuzzed_helper(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
+use crate::attr::{
+    get_container_metadata, get_fuzzer_metadata, get_invariants, get_lit_bool, get_variant_weight,
+    has_repr, replace_gate_receiver, VariantWeight,
+};
+use crate::remote::gen_conversion;
 
-    let name = input.ident;
+pub(crate) fn new_fuzzed_helper(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let name = &input.ident;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let container = get_container_metadata(&input.attrs)?;
 
     let method_body: TokenStream;
+    // enums don't have volatile fields of their own, so they use the default (nop) Refresh
+    let mut refresh_impl = TokenStream::new();
 
     match input.data {
         Data::Enum(ref data) => {
+            // rejects invariants, which need a struct to refer to
+            get_invariants(name, &container, &input.data)?;
+
             /// This struct represents an enum variant with parsed attributes
             struct Variant {
+                name: String,
                 full_ident: TokenStream,
                 initializer: TokenStream,
-                weight: u64,
+                weight: Option<VariantWeight>,
                 ignore: bool,
+                declaration_index: usize,
             }
 
             let mut variants = Vec::new();
             let mut enum_contains_items = false;
 
-            for variant in &data.variants {
+            for (declaration_index, variant) in data.variants.iter().enumerate() {
                 let ident = &variant.ident;
                 // This will look like EnumName::VariantName
                 let full_ident = TokenStream::from_str(&format!(
@@ -41,27 +49,17 @@ pub(crate) fn new_fuzzed_helper(input: proc_macro::TokenStream) -> proc_macro::T
                 .unwrap();
 
                 let mut variant_meta = Variant {
+                    name: ident.to_string(),
                     full_ident: full_ident.clone(),
                     initializer: TokenStream::new(),
-                    weight: 1,
+                    weight: None,
                     ignore: false,
+                    declaration_index,
                 };
 
-                // Parse the attributes
-                let meta = variant.attrs.iter().filter_map(get_weighted_metadata);
-                for meta_items in meta {
-                    for meta_item in meta_items {
-                        match meta_item {
-                            NestedMeta::Literal(Lit::Int(ref i)) => {
-                                variant_meta.weight = i.value();
-                            }
-                            _ => panic!("expected a literal int for #[weighted] attribute"),
-                        }
-                    }
-                }
+                variant_meta.weight = get_variant_weight(&variant.attrs)?;
 
-                let meta = variant.attrs.iter().filter_map(get_fuzzer_metadata);
-                for meta_items in meta {
+                for meta_items in get_fuzzer_metadata(&variant.attrs)? {
                     for meta_item in meta_items {
                         match meta_item {
                             syn::NestedMeta::Meta(syn::Meta::NameValue(ref m))
@@ -112,22 +110,31 @@ pub(crate) fn new_fuzzed_helper(input: proc_macro::TokenStream) -> proc_macro::T
                         // Finally, we can build the branch to generate this item. This will look like:
                         // 0 => {
                         //     let mut field_0: X = NewFuzzed::new_fuzzed(mutator, None);
-                        //     return EnumName::VariantName(field_0);
+                        //     EnumName::VariantName(field_0)
                         // }
                         variant_meta.initializer = quote! {
                             #index => {
                                 #initializer
-                                return #full_ident(#parameters);
+                                #full_ident(#parameters)
                             },
                         };
                     }
                     syn::Fields::Unit => {
-                        // do nothing -- this is a simple enum type like
+                        // enums made up of only unit variants, like
                         // enum { Foo, Bar, Baz, }
                         // or enum { Foo = 1, Bar, Baz,}
+                        // are picked from a static array instead. this branch is only used when
+                        // unit variants are mixed with variants carrying data
+                        let index = variants.len();
+                        variant_meta.initializer = quote! {
+                            #index => #full_ident,
+                        };
                     }
-                    _ => {
-                        panic!("Named fields aren't supported :( This should be easy to add though")
+                    syn::Fields::Named(ref fields) => {
+                        return Err(syn::Error::new_spanned(
+                            fields,
+                            "NewFuzzed doesn't support enum variants with named fields. Use a tuple variant instead, e.g. `Variant(u8, u32)`",
+                        ));
                     }
                 }
 
@@ -136,8 +143,27 @@ pub(crate) fn new_fuzzed_helper(input: proc_macro::TokenStream) -> proc_macro::T
 
             // Double-check to ensure we have no variants that want to be ignored
             let variants: Vec<&Variant> = variants.iter().filter(|v| !v.ignore).collect();
+            if variants.is_empty() {
+                let message = if data.variants.is_empty() {
+                    format!(
+                        "NewFuzzed can't be derived for `{}` since it has no variants",
+                        name
+                    )
+                } else {
+                    format!(
+                        "every variant of `{}` is ignored, so there's no variant to generate. Remove `ignore` from at least one variant",
+                        name
+                    )
+                };
+
+                return Err(syn::Error::new_spanned(name, message));
+            }
+
             let variant_count = variants.len();
-            let weights = variants.iter().map(|v| v.weight);
+            let variant_weights: Vec<_> = variants.iter().map(|v| v.weight.as_ref()).collect();
+            let (weights, weight_checks) = gen_variant_weights(name, &variant_weights)?;
+            let declaration_indices = variants.iter().map(|v| v.declaration_index);
+            let variant_names = variants.iter().map(|v| v.name.clone());
 
             // This is the new_fuzzed function's inner body if we have non-basic enum variants
             let inner_body = if enum_contains_items {
@@ -146,7 +172,6 @@ pub(crate) fn new_fuzzed_helper(input: proc_macro::TokenStream) -> proc_macro::T
 
                 //
                 quote! {
-                    let num: usize = dist.sample(&mut mutator.rng);
                     match num {
                         #(#variant_initializers)*
                         i => {
@@ -158,43 +183,157 @@ pub(crate) fn new_fuzzed_helper(input: proc_macro::TokenStream) -> proc_macro::T
                 // We have basic enum variants that are just numbers
                 let variant_tokens = variants.iter().map(|v| v.full_ident.clone());
                 quote! {
-                    use ::lain::rand::seq::SliceRandom;
-
                     static options: [#name; #variant_count] = [#(#variant_tokens,)*];
 
-                    *options.choose(&mut mutator.rng).unwrap()
+                    options[num]
                 }
             };
 
             method_body = quote! {
+                #weight_checks
+
                 static weights: [u64; #variant_count] = [#(#weights,)*];
+                static declaration_indices: [usize; #variant_count] = [#(#declaration_indices,)*];
+                static variant_names: [&str; #variant_count] = [#(#variant_names,)*];
 
                 ::lain::lazy_static::lazy_static! {
                     static ref dist: ::lain::rand::distributions::WeightedIndex<u64> =
-                        ::lain::rand::distributions::WeightedIndex::new(weights.iter()).unwrap();
+                        ::lain::new_fuzzed::variant_distribution(stringify!(#name), &weights);
                 }
 
+                // a min/max restricts which variants may be picked by their declaration index, as do
+                // the transitions of a variant being mutated, and adaptive weighting makes the
+                // ones the target keeps rejecting less likely
+                let enum_name = ::std::any::type_name::<Self>();
+                let constrained_dist = ::lain::new_fuzzed::constrained_variant_distribution(
+                    mutator,
+                    constraints,
+                    enum_name,
+                    &weights,
+                    &declaration_indices,
+                    &variant_names,
+                );
+                let variant_dist: &::lain::rand::distributions::WeightedIndex<u64> =
+                    constrained_dist.as_ref().unwrap_or(&*dist);
+
+                let num: usize = ::lain::rand::distributions::Distribution::sample(variant_dist, &mut mutator.rng);
+                mutator.record_variant(enum_name, declaration_indices[num]);
+                mutator.cover_variant(enum_name, &variant_names, num);
+
                 #inner_body
             };
         }
         Data::Struct(ref data) => {
-            if let syn::Fields::Named(ref fields) = data.fields {
-                let fields = parse_fields(&fields);
-                method_body = gen_struct_new_fuzzed_impl(&name, &fields);
+            if let Some(fields) = named_fields(&data.fields) {
+                let fields = parse_fields(&fields)?;
+                let invariant_checks =
+                    gen_invariant_checks(&get_invariants(name, &container, &input.data)?)?;
+
+                // the invariants are written against `self`, so they're checked through a
+                // local trait implemented for the struct
+                let check_invariants = if invariant_checks.is_empty() {
+                    TokenStream::new()
+                } else {
+                    quote! {
+                        trait CheckInvariants {
+                            fn check_invariants(&self);
+                        }
+
+                        impl #impl_generics CheckInvariants for #name #ty_generics #where_clause {
+                            fn check_invariants(&self) {
+                                #invariant_checks
+                            }
+                        }
+
+                        CheckInvariants::check_invariants(&initialized_struct);
+                    }
+                };
+
+                let fixup = gen_fixup_passes(quote! {initialized_struct}, &container, &input.data)?;
+                // the blanket Refresh impl can only be specialized by crates which enable
+                // specialization, so structs only implement it when they need to
+                if container.refresh || fields.iter().any(|f| f.volatile) {
+                    refresh_impl = gen_refresh_impl(
+                        input,
+                        &fields,
+                        gen_fixup_passes(quote! {self}, &container, &input.data)?,
+                    );
+                }
+
+                // observers are handed field values as `dyn Any`, which requires them to be 'static
+                let observable = input.generics.params.is_empty();
+                let packed = has_repr(&input.attrs, "packed");
+                method_body = gen_struct_new_fuzzed_impl(
+                    name,
+                    &fields,
+                    fixup,
+                    check_invariants,
+                    observable,
+                    packed,
+                )?;
             } else {
-                panic!("currently no support for unnamed fields for NewFuzzed");
+                return Err(named_fields_error("NewFuzzed", name, &data.fields));
             }
         }
-        _ => panic!("NewFuzzed only supports enums and structs"),
+        Data::Union(ref data) => return Err(union_error("NewFuzzed", data)),
     }
 
+    let remote_impl = if let Some(ref remote) = container.remote {
+        let into_remote = gen_conversion(&quote! {#name}, remote, &input.data, quote! {value})?;
+
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Generates a new fuzzed value of the remote type this type mirrors
+                pub fn new_fuzzed_remote<R: ::lain::rand::Rng>(mutator: &mut ::lain::mutator::Mutator<R>, constraints: Option<&::lain::types::Constraints<u8>>) -> #remote {
+                    let value = <Self as ::lain::traits::NewFuzzed>::new_fuzzed(mutator, constraints);
+
+                    #into_remote
+                }
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
+
+    let (begin_seed_domain, end_seed_domain) =
+        gen_seed_domain_scope(container.seed_domain.as_ref());
+
+    // types with a template are mutated from it whenever one is available
+    let method_body = match container.seed_from {
+        Some(ref seed_from) => quote! {
+            let seed: Option<#name #ty_generics> = #seed_from;
+            match seed {
+                Some(ref seed) => <#name #ty_generics as ::lain::traits::FromSeed>::from_seed(seed, mutator, constraints),
+                None => {
+                    #method_body
+                }
+            }
+        },
+        None => method_body,
+    };
+
     let expanded = quote! {
+        #remote_impl
+
+        #refresh_impl
+
         impl #impl_generics ::lain::traits::NewFuzzed for #name #ty_generics #where_clause {
             type RangeType = u8;
 
-            fn new_fuzzed<R: ::lain::rand::Rng>(mutator: &mut ::lain::mutator::Mutator<R>, mut constraints: Option<&::lain::types::Constraints<Self::RangeType>>) -> #name
+            fn new_fuzzed<R: ::lain::rand::Rng>(mutator: &mut ::lain::mutator::Mutator<R>, mut constraints: Option<&::lain::types::Constraints<Self::RangeType>>) -> Self
             {
-                #method_body
+                #begin_seed_domain
+
+                // the outermost scope resets the mutator's allocation budget
+                mutator.begin_allocation_scope();
+                let value = {
+                    #method_body
+                };
+                mutator.end_allocation_scope();
+
+                #end_seed_domain
+
+                value
             }
         }
     };
@@ -202,20 +341,276 @@ pub(crate) fn new_fuzzed_helper(input: proc_macro::TokenStream) -> proc_macro::T
     // Uncomment to dump the AST
     // println!("{}", expanded);
 
-    proc_macro::TokenStream::from(expanded)
+    Ok(expanded)
 }
 
-/// Gets the meta items for #[weight()] attributes
-fn get_weighted_metadata(attr: &syn::Attribute) -> Option<Vec<syn::NestedMeta>> {
-    get_attribute_metadata("weight", &attr)
+/// Implements `Refresh` for a struct. Fields marked `#[lain(volatile)]` are generated again, as
+/// if the struct were being generated, and other fields are refreshed in case they hold volatile
+/// fields of their own. The struct is then fixed up so that e.g. a checksum covers the new values.
+fn gen_refresh_impl(
+    input: &DeriveInput,
+    fields: &[FuzzerObjectStructField],
+    fixup: TokenStream,
+) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let packed = has_repr(&input.attrs, "packed");
+
+    let mut volatile_checks = vec![];
+    let mut refresh_fields = vec![];
+
+    for f in fields.iter() {
+        let span = f.field.span();
+        let ty = &f.field.ty;
+        let ident = &f.field.ident;
+
+        if !f.volatile {
+            volatile_checks.push(quote_spanned! { span =>
+                <#ty as ::lain::traits::Refresh>::has_volatile_fields()
+            });
+            // fields of packed structs can't be borrowed in place
+            let refresh = if packed {
+                quote_spanned! { span =>
+                    let mut field = self.#ident;
+                    <#ty as ::lain::traits::Refresh>::refresh(&mut field, mutator);
+                    self.#ident = field;
+                }
+            } else {
+                quote_spanned! { span =>
+                    <#ty as ::lain::traits::Refresh>::refresh(&mut self.#ident, mutator);
+                }
+            };
+
+            refresh_fields.push(quote_spanned! { span =>
+                if <#ty as ::lain::traits::Refresh>::has_volatile_fields() {
+                    #refresh
+                }
+            });
+
+            continue;
+        }
+
+        volatile_checks.push(quote! {true});
+
+        let constraints = f
+            .constraints(quote! {None})
+            .unwrap_or_else(|| quote! {None});
+
+        let value = match f.user_initializer {
+            Some(ref initializer) => quote_spanned! { span =>
+                {
+                    let field_constraints = #constraints;
+                    let constraints = field_constraints.as_ref();
+                    #initializer
+                }
+            },
+            None => quote_spanned! { span =>
+                {
+                    let constraints: Option<::lain::types::Constraints<<#ty as ::lain::traits::NewFuzzed>::RangeType>> = #constraints;
+                    <#ty>::new_fuzzed(mutator, constraints.as_ref())
+                }
+            },
+        };
+
+        let count_binding = f.count_field.as_ref().map(|count_field| {
+            quote_spanned! { count_field.span() =>
+                self.#count_field = self.#ident.len() as _;
+            }
+        });
+
+        let mut refresh_field = quote_spanned! { span =>
+            self.#ident = #value;
+            #count_binding
+        };
+
+        // fields which aren't part of the struct's version keep their defaults
+        if let Some(ref gate) = f.version_gate {
+            refresh_field = quote_spanned! { span =>
+                if #gate {
+                    #refresh_field
+                }
+            };
+        }
+
+        refresh_fields.push(refresh_field);
+    }
+
+    quote! {
+        impl #impl_generics ::lain::traits::Refresh for #name #ty_generics #where_clause {
+            fn has_volatile_fields() -> bool {
+                false #(|| #volatile_checks)*
+            }
+
+            fn refresh<R: ::lain::rand::Rng>(&mut self, mutator: &mut ::lain::mutator::Mutator<R>) {
+                if !<Self as ::lain::traits::Refresh>::has_volatile_fields() {
+                    return;
+                }
+
+                #(#refresh_fields)*
+
+                if mutator.should_fixup() {
+                    #fixup
+                }
+            }
+        }
+    }
+}
+
+/// Builds the weight of each variant for the generated `WeightedIndex`, along with any checks
+/// which have to be deferred to the compiler.
+///
+/// Relative weights are used as-is, and variants without a weight get a weight of 1. When
+/// percentages are used, variants without one evenly split whatever's left of 100%. The derive
+/// rejects totals over 100% when every percentage is a literal, and otherwise emits a const
+/// assertion for the compiler to evaluate. Weights which are all 0 are rejected the same way.
+fn gen_variant_weights(
+    name: &syn::Ident,
+    variant_weights: &[Option<&VariantWeight>],
+) -> syn::Result<(Vec<TokenStream>, TokenStream)> {
+    let mut percents = Vec::new();
+    let mut literal_total = 0;
+    let mut all_literal = true;
+    let mut relative_weight = None;
+
+    for weight in variant_weights {
+        match *weight {
+            Some(&VariantWeight::Percent {
+                ref expr,
+                literal,
+                span,
+            }) => {
+                match literal {
+                    Some(percent) => {
+                        literal_total += percent;
+                        if literal_total > 100 {
+                            return Err(syn::Error::new(
+                                span,
+                                format!(
+                                    "percentage weights add up to {}%, which is more than 100%",
+                                    literal_total
+                                ),
+                            ));
+                        }
+                    }
+                    None => all_literal = false,
+                }
+
+                percents.push(expr.clone());
+            }
+            Some(VariantWeight::Relative(expr)) => {
+                relative_weight = relative_weight.or_else(|| Some(expr.clone()))
+            }
+            None => {}
+        }
+    }
+
+    if percents.is_empty() {
+        let mut weights = Vec::new();
+        let mut literal_total = Some(0);
+
+        for weight in variant_weights {
+            match *weight {
+                Some(VariantWeight::Relative(expr)) => {
+                    let literal = syn::parse2::<syn::LitInt>(expr.clone()).ok();
+                    literal_total = literal_total.and_then(|total: u64| {
+                        literal.map(|lit| total.saturating_add(lit.value()))
+                    });
+                    weights.push(quote! {(#expr) as u64});
+                }
+                _ => {
+                    literal_total = literal_total.map(|total| total.saturating_add(1));
+                    weights.push(quote! {1});
+                }
+            }
+        }
+
+        let checks = gen_zero_weight_check(name, &weights, literal_total)?;
+
+        return Ok((weights, checks));
+    }
+
+    if let Some(expr) = relative_weight {
+        return Err(syn::Error::new_spanned(
+            expr,
+            "percentage weights can't be mixed with relative weights. Either give every weighted variant a percentage or none of them",
+        ));
+    }
+
+    let percent_total = quote! {(0u64 #(+ (#percents) as u64)*)};
+    let unweighted = variant_weights.iter().filter(|w| w.is_none()).count() as u64;
+
+    let weights: Vec<TokenStream> = variant_weights
+        .iter()
+        .map(|weight| match *weight {
+            // scaled up by the number of unweighted variants so that they can split the
+            // remainder without rounding
+            Some(VariantWeight::Percent { expr, .. }) if unweighted > 0 => {
+                quote! {(#expr) as u64 * #unweighted}
+            }
+            Some(VariantWeight::Percent { expr, .. }) => quote! {(#expr) as u64},
+            _ => quote! {100 - #percent_total},
+        })
+        .collect();
+
+    let mut checks = if all_literal {
+        TokenStream::new()
+    } else {
+        quote! {
+            const _: () = assert!(#percent_total <= 100, "percentage weights add up to more than 100%");
+        }
+    };
+
+    // unweighted variants split whatever the percentages leave, so the weights only add up to 0
+    // when every variant has a percentage of 0
+    let literal_total = if !all_literal {
+        None
+    } else if unweighted > 0 {
+        Some(100 * unweighted)
+    } else {
+        Some(literal_total)
+    };
+    checks.extend(gen_zero_weight_check(name, &weights, literal_total)?);
+
+    Ok((weights, checks))
+}
+
+/// Rejects enums whose variants all have a weight of 0, since there would be no variant to pick.
+/// `literal_total` is the total weight if every weight is a literal, otherwise the check is
+/// emitted as a const assertion.
+fn gen_zero_weight_check(
+    name: &syn::Ident,
+    weights: &[TokenStream],
+    literal_total: Option<u64>,
+) -> syn::Result<TokenStream> {
+    match literal_total {
+        Some(0) => Err(syn::Error::new_spanned(
+            name,
+            format!(
+                "every variant of `{}` has a weight of 0, so there's no variant to generate. Give at least one variant a nonzero weight",
+                name
+            ),
+        )),
+        Some(_) => Ok(TokenStream::new()),
+        None => {
+            let message = format!("every variant of `{}` has a weight of 0", name);
+            Ok(quote! {
+                const _: () = assert!(0u64 #(+ #weights)* > 0, #message);
+            })
+        }
+    }
 }
 
 fn gen_struct_new_fuzzed_impl(
     name: &syn::Ident,
     fields: &[FuzzerObjectStructField],
-) -> TokenStream {
+    fixup: TokenStream,
+    check_invariants: TokenStream,
+    observable: bool,
+    packed: bool,
+) -> syn::Result<TokenStream> {
     let mut generate_arms = vec![];
-    let mut generate_linear = vec![];
+    let mut exhaustive_values = vec![];
+    let mut drop_initialized = vec![];
 
     for (i, f) in fields.iter().enumerate() {
         let span = f.field.span();
@@ -224,77 +619,122 @@ fn gen_struct_new_fuzzed_impl(
         let mut field_mutation_tokens = TokenStream::new();
         let ident = &f.field.ident;
 
+        let default_constraints = f.constraints(quote! {max_size.clone()});
+
         // If the field is ignored, return the default value
         if f.ignore {
             field_mutation_tokens.extend(quote_spanned! { span =>
                 let value = <#ty>::default();
             });
         }
-        // If the user supplied an initializer, use that
+        // If the user supplied an initializer, use that. The initializer may reference `mutator`,
+        // and `constraints` which holds the field's min/max constraints if any were given
         else if let Some(ref initializer) = f.user_initializer {
-            field_mutation_tokens.extend(quote_spanned! { span =>
-                let value = #initializer;
-            });
+            if let Some(ref default_constraints) = default_constraints {
+                field_mutation_tokens.extend(quote_spanned! { span =>
+                    let field_constraints = #default_constraints;
+                    let value = {
+                        let constraints = field_constraints.as_ref();
+                        #initializer
+                    };
+                });
+            } else {
+                field_mutation_tokens.extend(quote_spanned! { span =>
+                    let value = #initializer;
+                });
+            }
         } else {
             // Otherwise, we assume that the field implements NewFuzzed and
             // we generate that value here
 
-            let weighted = &f.weighted;
-
-            let default_constraints = if f.min.is_some() || f.max.is_some() {
-                let min = f
-                    .min
-                    .as_ref()
-                    .map(|v| quote! {Some(#v)})
-                    .unwrap_or_else(|| quote! {None});
-                let max = f
-                    .max
-                    .as_ref()
-                    .map(|v| quote! {Some(#v)})
-                    .unwrap_or_else(|| quote! {None});
-
+            let default_constraints = if let Some(default_constraints) = default_constraints {
                 quote_spanned! { span =>
-                    let constraints: Option<::lain::types::Constraints<<#ty as ::lain::traits::NewFuzzed>::RangeType>> = Some(Constraints {
-                        min: #min,
-                        max: #max,
-                        weighted: #weighted,
-                        max_size: max_size.clone(),
-                    });
+                    let constraints: Option<::lain::types::Constraints<<#ty as ::lain::traits::NewFuzzed>::RangeType>> = #default_constraints;
                 }
             } else {
                 quote_spanned! { span =>
-                    let constraints = if let Some(ref max_size) = max_size {
-                        let mut constraints = ::lain::types::Constraints::default();
-                        constraints.max_size = Some(max_size.clone());
+                    let constraints = ::lain::new_fuzzed::max_size_constraints(max_size);
+                }
+            };
 
-                        Some(constraints)
-                    } else {
-                        None
+            if f.exhaustive {
+                // swept values are taken up front (see below) and generated normally once the
+                // sweep has finished
+                let exhaustive_ident =
+                    syn::Ident::new(&format!("exhaustive_{}", ident.as_ref().unwrap()), span);
+                let exhaustive_value = f.exhaustive_value()?;
+
+                exhaustive_values.push(quote_spanned! { span =>
+                    let mut #exhaustive_ident: Option<#ty> = #exhaustive_value;
+                });
+
+                field_mutation_tokens.extend(quote_spanned! { span =>
+                    #default_constraints
+                    let value = match #exhaustive_ident.take() {
+                        Some(value) => value,
+                        None => <#ty>::new_fuzzed(mutator, constraints.as_ref()),
                     };
-                }
+                });
+            } else {
+                field_mutation_tokens.extend(quote_spanned! { span =>
+                    #default_constraints
+                    let value = <#ty>::new_fuzzed(mutator, constraints.as_ref());
+                });
+            }
+        }
+
+        // generation observers may adjust the value or have it generated again, as does adaptive
+        // weighting if the value is one the target keeps rejecting
+        if observable {
+            let field_name = ident.as_ref().unwrap().to_string();
+            field_mutation_tokens = quote_spanned! { span =>
+                // ignored fields and initializers may not use the mutator
+                let value = ::lain::observer::generate_observed::<#name, #ty, _, _>(mutator, #field_name, |#[allow(unused_variables)] mutator| {
+                    #field_mutation_tokens
+
+                    value
+                });
             };
+        }
 
-            field_mutation_tokens.extend(quote_spanned! { span =>
-                #default_constraints
-                let value = <#ty>::new_fuzzed(mutator, constraints.as_ref());
-            });
+        // sections generated once are cached by the mutator for the rest of the campaign
+        if f.generate_once {
+            let field_name = ident.as_ref().unwrap().to_string();
+            field_mutation_tokens = quote_spanned! { span =>
+                let value = mutator.generate_once::<#ty, _>(::std::any::type_name::<Self>(), #field_name, |mutator| {
+                    #field_mutation_tokens
+
+                    value
+                });
+            };
         }
 
         field_mutation_tokens.extend(quote! {
-            if let Some(ref mut max_size) = max_size {
-                *max_size -= value.serialized_size();
-            }
-
-            let field_offset = ::lain::field_offset::offset_of!(#name => #ident).get_byte_offset() as isize;
+            ::lain::new_fuzzed::take_max_size(&mut max_size, value.serialized_size());
 
             unsafe {
-                let field_ptr = (uninit_struct_ptr as *mut u8).offset(field_offset) as *mut #ty;
-
-                std::ptr::write(field_ptr, value);
+                init_guard.write_field(#i, ::std::ptr::addr_of_mut!((*uninit_struct_ptr).#ident), value);
             }
         });
 
-        generate_linear.push(field_mutation_tokens.clone());
+        // fields of packed structs may not be aligned, so they're moved out to be dropped
+        let drop_field = if packed {
+            quote! {
+                ::std::mem::drop(::std::ptr::read_unaligned(::std::ptr::addr_of_mut!((*uninit_struct_ptr).#ident)));
+            }
+        } else {
+            quote! {
+                ::std::ptr::drop_in_place(::std::ptr::addr_of_mut!((*uninit_struct_ptr).#ident));
+            }
+        };
+
+        drop_initialized.push(quote! {
+            if initialized[#i] {
+                unsafe {
+                    #drop_field
+                }
+            }
+        });
 
         generate_arms.push(quote! {
             #i => {
@@ -305,37 +745,86 @@ fn gen_struct_new_fuzzed_impl(
 
     let generate_fields_count = generate_arms.len();
 
-    quote! {
+    let count_bindings = fields.iter().filter_map(|f| {
+        let ident = &f.field.ident;
+        f.count_field.as_ref().map(|count_field| {
+            quote_spanned! { count_field.span() =>
+                initialized_struct.#count_field = initialized_struct.#ident.len() as _;
+            }
+        })
+    });
+
+    let version_bindings = fields.iter().filter(|f| f.protocol_version).map(|f| {
+        let ident = &f.field.ident;
+        quote_spanned! { f.field.span() =>
+            if let Some(version) = mutator.protocol_version() {
+                initialized_struct.#ident = version.0 as _;
+            }
+        }
+    });
+
+    let gate_resets = fields.iter().filter_map(|f| {
+        let ident = &f.field.ident;
+        f.version_gate.as_ref().map(|gate| {
+            let gate = replace_gate_receiver(gate.clone(), Some(&quote! {initialized_struct}));
+            quote_spanned! { f.field.span() =>
+                if !(#gate) {
+                    initialized_struct.#ident = Default::default();
+                }
+            }
+        })
+    });
+
+    Ok(quote! {
         use std::any::Any;
         use ::lain::rand::seq::index::sample;
 
-        let mut max_size = if let Some(ref mut constraints) = constraints {
-            constraints.max_size.clone()
-        } else {
-            None
-        };
+        let mut max_size = constraints.and_then(|constraints| constraints.max_size);
+
+        // exhaustively swept fields consume the combination index in declaration order, regardless
+        // of the order fields are generated in
+        #(#exhaustive_values)*
 
-        let mut uninit_struct = std::mem::MaybeUninit::<#name>::uninit();
+        let mut uninit_struct = std::mem::MaybeUninit::<Self>::uninit();
         let uninit_struct_ptr = uninit_struct.as_mut_ptr();
 
-        let range = if Self::is_variable_size() {
-            // this makes for ugly code generation, but better perf
-            for i in sample(&mut mutator.rng, #generate_fields_count, #generate_fields_count).iter() {
-                match i {
-                    #(#generate_arms)*
-                    _ => unreachable!(),
-                }
-            }
+        let mut init_guard = ::lain::new_fuzzed::InitGuard::new(move |initialized: &[bool; #generate_fields_count]| {
+            #(#drop_initialized)*
+        });
+
+        // variable-size fields are generated in a random order so that the first one declared
+        // doesn't always get the most of `max_size`
+        let order = if Self::is_variable_size() {
+            Some(sample(&mut mutator.rng, #generate_fields_count, #generate_fields_count))
         } else {
-            #(#generate_linear)*
+            None
         };
 
+        for position in 0..#generate_fields_count {
+            match order.as_ref().map_or(position, |order| order.index(position)) {
+                #(#generate_arms)*
+                _ => unreachable!(),
+            }
+        }
+
+        init_guard.finish();
+
         let mut initialized_struct = unsafe { uninit_struct.assume_init() };
 
+        // the version field follows the mutator's selected version, and fields which aren't part
+        // of the struct's version are left at their defaults
+        #(#version_bindings)*
+        #(#gate_resets)*
+
+        // arrays with a count field start out in sync with it
+        #(#count_bindings)*
+
         if mutator.should_fixup() {
-            initialized_struct.fixup(mutator);
+            #fixup
+
+            #check_invariants
         }
 
         initialized_struct
-    }
+    })
 }
@@ -0,0 +1,130 @@
+use proc_macro2::{Group, TokenStream, TokenTree};
+
+use quote::quote;
+
+use std::str::FromStr;
+use syn::{Data, Fields, Ident};
+
+use crate::utils::union_error;
+
+/// Generates an expression which moves `value` from the type named `from` into the type named
+/// `to`. Both types must have identically named fields and variants, which is the case for a
+/// remote type and its local mirror.
+pub(crate) fn gen_conversion(
+    from: &TokenStream,
+    to: &TokenStream,
+    data: &Data,
+    value: TokenStream,
+) -> syn::Result<TokenStream> {
+    match *data {
+        Data::Struct(ref data) => {
+            let (pattern, constructor) = gen_fields_conversion(from, to, &data.fields);
+
+            Ok(quote! {
+                {
+                    let #pattern = #value;
+                    #constructor
+                }
+            })
+        }
+        Data::Enum(ref data) => {
+            let mut arms = Vec::<TokenStream>::new();
+
+            for variant in data.variants.iter() {
+                let from_variant =
+                    TokenStream::from_str(&format!("{}::{}", from, variant.ident)).unwrap();
+                let to_variant =
+                    TokenStream::from_str(&format!("{}::{}", to, variant.ident)).unwrap();
+
+                let (pattern, constructor) =
+                    gen_fields_conversion(&from_variant, &to_variant, &variant.fields);
+
+                arms.push(quote! {
+                    #pattern => #constructor,
+                });
+            }
+
+            Ok(quote! {
+                match #value {
+                    #(#arms)*
+                }
+            })
+        }
+        Data::Union(ref data) => Err(union_error("remote", data)),
+    }
+}
+
+/// Rewrites code generated for the mirror named `mirror` so that it works on a `&remote` named
+/// `receiver` instead of `self`: `self` becomes `receiver` and paths to the mirror's variants
+/// become paths to the remote's. Fields are then accessed in place, so the remote value never has
+/// to be converted.
+pub(crate) fn replace_receiver(
+    tokens: TokenStream,
+    mirror: &Ident,
+    remote: &TokenStream,
+    receiver: &Ident,
+) -> TokenStream {
+    let mut replaced = TokenStream::new();
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ref ident) if ident == "self" => {
+                replaced.extend(quote! {#receiver});
+            }
+            TokenTree::Ident(ref ident)
+                if ident == mirror
+                    && matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == ':') =>
+            {
+                replaced.extend(remote.clone());
+            }
+            TokenTree::Group(ref group) => {
+                let mut new_group = Group::new(
+                    group.delimiter(),
+                    replace_receiver(group.stream(), mirror, remote, receiver),
+                );
+                new_group.set_span(group.span());
+                replaced.extend(Some(TokenTree::Group(new_group)));
+            }
+            token => replaced.extend(Some(token)),
+        }
+    }
+
+    replaced
+}
+
+/// Returns a pattern which binds each of the fields of `from` and the expression which builds
+/// `to` from those bindings
+fn gen_fields_conversion(
+    from: &TokenStream,
+    to: &TokenStream,
+    fields: &Fields,
+) -> (TokenStream, TokenStream) {
+    match *fields {
+        Fields::Named(ref fields) => {
+            let idents: Vec<&Option<Ident>> = fields.named.iter().map(|f| &f.ident).collect();
+
+            let from_idents = idents.iter();
+            let to_idents = idents.iter();
+
+            (
+                quote! {#from { #(#from_idents,)* }},
+                quote! {#to { #(#to_idents,)* }},
+            )
+        }
+        Fields::Unnamed(ref fields) => {
+            let idents: Vec<TokenStream> = (0..fields.unnamed.len())
+                .map(|i| TokenStream::from_str(&format!("field_{}", i)).unwrap())
+                .collect();
+
+            let from_idents = idents.iter();
+            let to_idents = idents.iter();
+
+            (
+                quote! {#from(#(#from_idents,)*)},
+                quote! {#to(#(#to_idents,)*)},
+            )
+        }
+        Fields::Unit => (quote! {#from}, quote! {#to}),
+    }
+}
@@ -1,15 +1,14 @@
-extern crate proc_macro;
-
-use proc_macro2::TokenStream;
+use proc_macro2::{Span, TokenStream};
 
 use quote::quote;
 
 use std::str::FromStr;
 use syn::Meta::Word;
 use syn::NestedMeta::Meta;
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Type};
+use syn::{Data, DeriveInput, Ident, Type};
 
 use crate::attr::*;
+use crate::remote::replace_receiver;
 use crate::utils::*;
 
 #[derive(Default)]
@@ -17,6 +16,10 @@ struct BinarySerializeTokens {
     pub serialize: TokenStream,
     pub serialized_size: Option<TokenStream>,
     pub min_nonzero_elements_size: Option<TokenStream>,
+    /// Pushes the `FieldLayout`s of a struct's fields, for the derived `FieldMetadata`
+    pub layout: TokenStream,
+    /// Whether this is a bitfield, which is packed with its neighbours and can't be swapped
+    pub bitfield: bool,
 }
 
 impl BinarySerializeTokens {
@@ -29,23 +32,28 @@ impl BinarySerializeTokens {
             serialize,
             serialized_size,
             min_nonzero_elements_size,
+            layout: TokenStream::new(),
+            bitfield: false,
         }
     }
 }
 
-pub(crate) fn binary_serialize_helper(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
+pub(crate) fn binary_serialize_helper(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let container = get_container_metadata(&input.attrs)?;
 
-    let mut use_inner_member_serialized_size = true;
-    let mut static_serialized_size: Option<usize> = None;
+    let mut use_inner_member_serialized_size = container.serialized_size.is_none();
+    let mut static_serialized_size: Option<usize> = container.serialized_size;
 
     if !input.attrs.is_empty() {
         for attr in &input.attrs {
             if attr.path.segments[0].ident == "serialized_size" {
-                let meta = attr.parse_meta().expect("couldn't parse meta");
+                let meta = attr.parse_meta()?;
                 if let syn::Meta::List(l) = meta {
                     if l.nested.len() > 1 {
-                        panic!("only expected 1 item in serialized_size");
+                        return Err(syn::Error::new_spanned(
+                            &l.nested,
+                            "#[serialized_size] expects a single size, e.g. #[serialized_size(16)]",
+                        ));
                     }
 
                     let nested = &l.nested[0];
@@ -60,17 +68,71 @@ pub(crate) fn binary_serialize_helper(input: proc_macro::TokenStream) -> proc_ma
         }
     }
 
-    let name = input.ident;
+    let repr = get_repr_type(&input.attrs);
+
+    let name = &input.ident;
     let name_as_string = name.to_string();
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let tokens = serialize_fields(&name, &input.data, use_inner_member_serialized_size);
+    if container.memcpy_serialize && !container.packed_layout {
+        return Err(syn::Error::new_spanned(
+            name,
+            "#[lain(memcpy_serialize)] requires #[lain(packed_layout)]",
+        ));
+    }
+
+    let tokens = if container.packed_layout {
+        if static_serialized_size.is_some() || container.counted_size {
+            return Err(syn::Error::new_spanned(
+                name,
+                "#[lain(packed_layout)] types are serialized at their in-memory size, so they can't be given a serialized size",
+            ));
+        }
+
+        serialize_packed_fields(input, &container)?
+    } else {
+        serialize_fields(
+            name,
+            &input.data,
+            &container,
+            use_inner_member_serialized_size,
+            static_serialized_size,
+            repr.as_ref(),
+        )?
+    };
 
     let serialize = tokens.serialize;
+    // FieldMetadata and InputShape have blanket impls, so implementing them needs specialization
+    // in the user's crate
+    let field_metadata_impl = if container.field_metadata {
+        gen_field_metadata_impl(input, &tokens.layout)
+    } else {
+        TokenStream::new()
+    };
+    let input_shape_impl = if container.input_shape {
+        gen_input_shape_impl(input, container.packed_layout)
+    } else {
+        TokenStream::new()
+    };
+    let packed_layout_impl = if container.packed_layout {
+        gen_packed_layout_impl(input)
+    } else {
+        TokenStream::new()
+    };
+
+    if container.counted_size && static_serialized_size.is_some() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "#[lain(counted_size)] can't be used on a type with a fixed serialized size",
+        ));
+    }
 
     let serialized_size = if let Some(size) = static_serialized_size {
         quote! {#size}
+    } else if container.counted_size {
+        // the size is whatever serialization writes, so the two can never disagree
+        quote! {::lain::buffer::counted_serialized_size(self)}
     } else if let Some(ref serialized_size) = tokens.serialized_size {
         serialized_size.clone()
     } else {
@@ -79,14 +141,68 @@ pub(crate) fn binary_serialize_helper(input: proc_macro::TokenStream) -> proc_ma
 
     let min_nonzero_elements_size = tokens.min_nonzero_elements_size.unwrap();
 
+    let remote_impl = if let Some(ref remote) = container.remote {
+        if container.memcpy_serialize {
+            return Err(syn::Error::new_spanned(
+                name,
+                "#[lain(memcpy_serialize)] copies the mirror's bytes, so it can't be used with #[lain(remote)]",
+            ));
+        }
+
+        // the fields of the remote value are serialized in place by the mirror's code
+        let receiver = Ident::new("value", Span::call_site());
+        let remote_serialize = replace_receiver(serialize.clone(), name, remote, &receiver);
+        let remote_serialized_size = if container.counted_size {
+            quote! {
+                {
+                    let mut writer = ::lain::buffer::CountingWriter::new();
+                    // the byte order doesn't change the size, and counting never fails
+                    Self::binary_serialize_remote::<_, ::lain::byteorder::BigEndian>(value, &mut writer)
+                        .expect("serialization into a CountingWriter failed");
+
+                    writer.count()
+                }
+            }
+        } else {
+            replace_receiver(serialized_size.clone(), name, remote, &receiver)
+        };
+
+        quote! {
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Serializes a value of the remote type this type mirrors
+                pub fn binary_serialize_remote<W: std::io::Write, E: ::lain::byteorder::ByteOrder>(value: &#remote, buffer: &mut W) -> std::io::Result<usize> {
+                    use ::lain::traits::SerializedSize;
+                    use ::lain::byteorder::{LittleEndian, BigEndian, WriteBytesExt};
+
+                    let mut bytes_written = 0;
+
+                    #remote_serialize
+
+                    Ok(bytes_written)
+                }
+
+                /// Returns the serialized size of a value of the remote type this type mirrors
+                pub fn serialized_size_remote(value: &#remote) -> usize {
+                    use ::lain::traits::SerializedSize;
+
+                    let size = #remote_serialized_size;
+
+                    return size;
+                }
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
+
     let serialized_size = quote! {
         impl #impl_generics ::lain::traits::SerializedSize for #name #ty_generics #where_clause {
             #[inline(always)]
             fn serialized_size(&self) -> usize {
                 use ::lain::traits::SerializedSize;
-                ::lain::log::debug!("getting serialized size of {}", #name_as_string);
+                ::lain::hot_debug!("getting serialized size of {}", #name_as_string);
                 let size = #serialized_size;
-                ::lain::log::debug!("size of {} is 0x{:02X}", #name_as_string, size);
+                ::lain::hot_debug!("size of {} is 0x{:02X}", #name_as_string, size);
 
                 return size;
             }
@@ -100,13 +216,33 @@ pub(crate) fn binary_serialize_helper(input: proc_macro::TokenStream) -> proc_ma
 
     // println!("{}", serialized_size);
 
+    let no_alloc_impl = if container.no_alloc_serialize {
+        gen_no_alloc_serialize_impl(input)?
+    } else {
+        TokenStream::new()
+    };
+
     let expanded = quote! {
+        #remote_impl
+
+        #no_alloc_impl
+
+        #packed_layout_impl
+
+        #field_metadata_impl
+
+        #input_shape_impl
+
         impl #impl_generics ::lain::traits::BinarySerialize for #name #ty_generics #where_clause {
-            fn binary_serialize<W: std::io::Write, E: ::lain::byteorder::ByteOrder>(&self, buffer: &mut W) {
+            fn binary_serialize<W: std::io::Write, E: ::lain::byteorder::ByteOrder>(&self, buffer: &mut W) -> std::io::Result<usize> {
                 use ::lain::traits::SerializedSize;
                 use ::lain::byteorder::{LittleEndian, BigEndian, WriteBytesExt};
 
+                let mut bytes_written = 0;
+
                 #serialize
+
+                Ok(bytes_written)
             }
         }
 
@@ -116,19 +252,33 @@ pub(crate) fn binary_serialize_helper(input: proc_macro::TokenStream) -> proc_ma
     // Uncomment to dump the AST
     // println!("{}", expanded);
 
-    proc_macro::TokenStream::from(expanded)
+    Ok(expanded)
 }
 
 fn serialize_fields(
     name: &Ident,
     data: &Data,
+    container: &ContainerMetadata,
     use_inner_member_serialized_size: bool,
-) -> BinarySerializeTokens {
+    static_serialized_size: Option<usize>,
+    repr: Option<&TokenStream>,
+) -> syn::Result<BinarySerializeTokens> {
     match *data {
         Data::Enum(ref data) => {
+            if let Some(ref field) = container.field_swap {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "#[lain(field_swap)] is only supported on structs",
+                ));
+            }
+
             let mut variant_branches = Vec::<TokenStream>::new();
             let mut serialized_size_variant_branches = Vec::<TokenStream>::new();
             let mut min_sizes = Vec::<TokenStream>::new();
+            let has_data_variants = data
+                .variants
+                .iter()
+                .any(|variant| variant.fields != syn::Fields::Unit);
 
             for variant in data.variants.iter() {
                 let ident = &variant.ident;
@@ -137,78 +287,39 @@ fn serialize_fields(
                 let full_ident = TokenStream::from_str(&full_ident_string).unwrap();
                 let mut parameters = TokenStream::new();
 
-                match variant.fields {
-                    syn::Fields::Unnamed(ref fields) => {
-                        let mut serialized_fields = TokenStream::new();
-                        let mut total_size = TokenStream::new();
-                        let mut variant_sizes = Vec::<TokenStream>::new();
-
-                        // iterate over every item in this tuple
-                        for (i, ref unnamed) in fields.unnamed.iter().enumerate() {
-                            let field_ty = &unnamed.ty;
-                            let ident = TokenStream::from_str(&format!("field_{}", i)).unwrap();
-
-                            total_size.extend(quote! {total_size += #ident.serialized_size();});
-                            variant_sizes.push(quote! {std::mem::size_of::<#field_ty>()});
-
-                            serialized_fields.extend(quote! {
-                                #ident.binary_serialize::<_, E>(buffer);
+                let fields: Vec<&syn::Field> = match variant.fields {
+                    syn::Fields::Unnamed(ref fields) => fields.unnamed.iter().collect(),
+                    // a unit variant among variants with data has nothing to write, just like a
+                    // tuple variant without fields
+                    syn::Fields::Unit if has_data_variants => Vec::new(),
+                    syn::Fields::Unit => {
+                        // enums with an explicit integer repr are serialized as their declared
+                        // discriminant at the repr's width. otherwise we rely on the user
+                        // implementing ToPrimitive
+                        let serialize = if let Some(repr) = repr {
+                            let discriminants = data.variants.iter().map(|v| {
+                                let full_ident =
+                                    TokenStream::from_str(&format!("{}::{}", name, v.ident))
+                                        .unwrap();
+
+                                quote! {
+                                    #full_ident => #full_ident as #repr,
+                                }
                             });
 
-                            parameters.extend(quote! {ref #ident,});
-                        }
-                        min_sizes.push(quote! {0#(+#variant_sizes)*});
-
-                        let serialized_size = if use_inner_member_serialized_size {
                             quote! {
-                                let serialized_size = total_size;
+                                let discriminant: #repr = match *self {
+                                    #(#discriminants)*
+                                };
+
+                                bytes_written += discriminant.binary_serialize::<_, E>(buffer)?;
                             }
                         } else {
                             quote! {
-                                let serialized_size = self.serialized_size();
-                            }
-                        };
-
-                        let trailer = quote! {
-                            let padding = serialized_size - total_size;
-                            if padding != 0 {
-                                let padding_data: Vec<u8> = (0..padding).map(|_| 0).collect();
-                                buffer.write(&padding_data).ok();
+                                bytes_written += self.to_primitive().binary_serialize::<_, E>(buffer)?;
                             }
                         };
 
-                        variant_branches.push(quote!{
-                            #full_ident(#parameters) => {
-                                let mut total_size = 0;
-                                #total_size
-                                #serialized_size
-                                // TODO: we technically handle multiple fields, but this is hardcoded
-                                if total_size > serialized_size {
-                                    panic!("size of serialized data for {} will be greater than enum's marked size ({} > {})", #full_ident_string, total_size, serialized_size);
-                                }
-
-                                #serialized_fields
-
-                                #trailer
-                            },
-                        });
-
-                        serialized_size_variant_branches.push(quote! {
-                            #full_ident(#parameters) => {
-                                let mut total_size = 0;
-                                #total_size
-                                #serialized_size
-
-                                serialized_size
-                            },
-                        });
-                        //println!("{}", variant_branches[variant_branches.len() - 1]);
-                    }
-                    syn::Fields::Unit => {
-                        let serialize = quote! {
-                            self.to_primitive().binary_serialize::<_, E>(buffer);
-                        };
-
                         let size = quote! {
                             std::mem::size_of::<#name>();
                         };
@@ -217,10 +328,119 @@ fn serialize_fields(
                             std::mem::size_of::<#name>()
                         };
 
-                        return BinarySerializeTokens::new(serialize, Some(size), Some(min_size));
+                        return Ok(BinarySerializeTokens::new(
+                            serialize,
+                            Some(size),
+                            Some(min_size),
+                        ));
                     }
-                    _ => panic!("unsupported enum type (probably contains named members)"),
+                    syn::Fields::Named(ref fields) => {
+                        return Err(syn::Error::new_spanned(
+                            fields,
+                            "BinarySerialize doesn't support enum variants with named fields. Use a tuple variant instead, e.g. `Variant(u8, u32)`",
+                        ));
+                    }
+                };
+
+                let mut serialized_fields = TokenStream::new();
+                let mut total_size = TokenStream::new();
+                let mut variant_sizes = Vec::<TokenStream>::new();
+
+                // iterate over every item in this tuple
+                for (i, ref unnamed) in fields.iter().enumerate() {
+                    let field_ty = &unnamed.ty;
+                    let ident = TokenStream::from_str(&format!("field_{}", i)).unwrap();
+
+                    total_size.extend(quote! {total_size += #ident.serialized_size();});
+                    variant_sizes.push(quote! {std::mem::size_of::<#field_ty>()});
+
+                    serialized_fields.extend(quote! {
+                        bytes_written += #ident.binary_serialize::<_, E>(buffer)?;
+                    });
+
+                    parameters.extend(quote! {ref #ident,});
                 }
+                min_sizes.push(quote! {0#(+#variant_sizes)*});
+
+                let pattern = if variant.fields == syn::Fields::Unit {
+                    quote! {#full_ident}
+                } else {
+                    quote! {#full_ident(#parameters)}
+                };
+                let declare_total_size = if fields.is_empty() {
+                    quote! {let total_size = 0;}
+                } else {
+                    quote! {let mut total_size = 0;}
+                };
+
+                let serialized_size = if use_inner_member_serialized_size {
+                    quote! {
+                        let serialized_size = total_size;
+                    }
+                } else if let Some(size) = static_serialized_size {
+                    // avoids the logging in the derived serialized_size(), and works for remote
+                    // values, which don't implement SerializedSize
+                    quote! {
+                        let serialized_size: usize = #size;
+                    }
+                } else {
+                    quote! {
+                        let serialized_size = self.serialized_size();
+                    }
+                };
+
+                let trailer = quote! {
+                    let padding = serialized_size - total_size;
+                    if padding != 0 {
+                        // written from a fixed block of zeroes so that padding doesn't allocate
+                        let zeroes = [0u8; 64];
+                        let mut remaining = padding;
+                        while remaining != 0 {
+                            let len = std::cmp::min(remaining, zeroes.len());
+                            buffer.write_all(&zeroes[..len])?;
+                            remaining -= len;
+                        }
+
+                        bytes_written += padding;
+                    }
+                };
+
+                let oversized = if container.no_alloc_serialize {
+                    quote! {
+                        return Err(std::io::ErrorKind::InvalidData.into());
+                    }
+                } else {
+                    quote! {
+                        panic!("size of serialized data for {} will be greater than enum's marked size ({} > {})", #full_ident_string, total_size, serialized_size);
+                    }
+                };
+
+                variant_branches.push(quote! {
+                    #pattern => {
+                        #declare_total_size
+                        #total_size
+                        #serialized_size
+                        // TODO: we technically handle multiple fields, but this is hardcoded
+                        if total_size > serialized_size {
+                            #oversized
+                        }
+
+                        #serialized_fields
+
+                        #trailer
+                    },
+                });
+
+                serialized_size_variant_branches.push(quote! {
+                    #pattern => {
+                        #declare_total_size
+                        #total_size
+                        #serialized_size
+
+                        serialized_size
+                    },
+                });
+                //println!("{}", variant_branches[variant_branches.len() - 1]);
             }
 
             let serialize_body = quote! {
@@ -237,28 +457,51 @@ fn serialize_fields(
 
             let sizes = quote! {*[#(#min_sizes,)*].iter().min_by(|a, b| a.cmp(b)).unwrap()};
 
-            BinarySerializeTokens::new(serialize_body, Some(serialized_size_body), Some(sizes))
+            Ok(BinarySerializeTokens::new(
+                serialize_body,
+                Some(serialized_size_body),
+                Some(sizes),
+            ))
         }
         Data::Struct(ref data) => {
-            match data.fields {
-                Fields::Named(ref fields) => {
+            match named_fields(&data.fields) {
+                Some(fields) => {
                     let mut bitfield_shift = 0;
                     let mut bitfield_type: Option<TokenStream> = None;
 
-                    let fields = fields.named.iter().map(|f| {
+                    let fields = get_wire_order(container, &fields)?;
+
+                    // the field recording the swap isn't serialized, so it's never swapped
+                    let swap_fields: Vec<bool> = fields
+                        .iter()
+                        .map(|f| f.ident == container.field_swap)
+                        .collect();
+                    if let Some(ref field) = container.field_swap {
+                        if !swap_fields.contains(&true) {
+                            return Err(syn::Error::new_spanned(
+                                field,
+                                format!("#[lain(field_swap)] names `{}`, which isn't a field of `{}`. The named field should be a `lain::types::FieldSwap`", field, name),
+                            ));
+                        }
+                    }
+
+                    let fields = fields.into_iter().map(|f| {
                         let name = &f.ident;
                         let ty = &f.ty;
 
-                        // parse out the byteorder
-                        let meta = f.attrs.iter().filter_map(get_byteorder_metadata);
-                        let field_byteorder = get_byteorder(meta);
-
-                        let meta = f.attrs.iter().filter_map(get_bitfield_metadata);
+                        // flattened fields are written with the parent's byte order like any
+                        // other nested struct, so they only need their attributes checked
+                        let flatten = get_flatten(f)?;
 
+                        // parse out the byteorder
+                        let meta = get_byteorder_metadata(&f.attrs)?.into_iter();
+                        let field_byteorder = get_byteorder(meta)?;
+                        let permutation = get_byte_permutation(f)?;
+                        let version_gate = get_version_gate(f)?;
 
                         // this is a bitfield. we need to use our "bitfield" local variable
                         // to temporarily hold all these bits
-                        let bitfield_meta = get_bitfield_limits(meta);
+                        let bitfield_meta = get_bitfield_limits(f)?;
                         let is_bitfield = bitfield_meta.is_some();
 
                         if is_bitfield {
@@ -268,7 +511,10 @@ fn serialize_fields(
                                     is_primitive(&base_type)
                                 }
                                 _ => {
-                                    panic!("bitfields are only supported for paths -- arrays should not be used");
+                                    return Err(syn::Error::new_spanned(
+                                        ty,
+                                        "bitfields are only supported for paths -- arrays should not be used",
+                                    ));
                                 }
                             };
 
@@ -297,7 +543,7 @@ fn serialize_fields(
 
                             if bitfield_meta.ty_bits == bitfield_shift {
                                 text.extend(quote!{
-                                    (bitfield as #bitfield_type).binary_serialize::<_, E>(buffer);
+                                    bytes_written += (bitfield as #bitfield_type).binary_serialize::<_, E>(buffer)?;
                                     bitfield = 0;
                                 });
 
@@ -312,10 +558,35 @@ fn serialize_fields(
                                 None
                             };
 
-                            return BinarySerializeTokens::new(text, size.clone(), size);
+                            // every bitfield sharing a backing value is placed at its offset
+                            let mut layout = if old_shift == 0 {
+                                quote! {
+                                    bitfield_offset = offset;
+                                    offset += std::mem::size_of::<#bitfield_type>();
+                                }
+                            } else {
+                                TokenStream::new()
+                            };
+
+                            let path = name.as_ref().unwrap().to_string();
+                            layout.extend(quote! {
+                                layout.push(::lain::types::FieldLayout {
+                                    path: #path.to_string(),
+                                    type_name: ::lain::debug::short_type_name::<#ty>(),
+                                    offset: bitfield_offset,
+                                    size: std::mem::size_of::<#bitfield_type>(),
+                                    bits: Some(#old_shift..#old_shift + #num_bits),
+                                });
+                            });
+
+                            let mut tokens = BinarySerializeTokens::new(text, size.clone(), size);
+                            tokens.layout = layout;
+                            tokens.bitfield = true;
+
+                            return Ok(tokens);
                         }
 
-                        fn handle_type(name: &syn::Ident, ty: &syn::Type, field_byteorder: Option<&TokenStream>) -> BinarySerializeTokens {
+                        fn handle_type(name: &syn::Ident, ty: &syn::Type, field_byteorder: Option<&TokenStream>) -> syn::Result<BinarySerializeTokens> {
                             let handle_ident =  |ty: &syn::Path| {
                                 let root = &ty.segments[0].ident;
                                 let primitive_type = is_primitive(&root.to_string());
@@ -351,14 +622,14 @@ fn serialize_fields(
                                 // is serialized, we handle that here
                                 if field_byteorder.is_some() {
                                     let binary_serialize_text = quote!{
-                                        self.#name.binary_serialize::<_, #field_byteorder>(buffer);
+                                        bytes_written += self.#name.binary_serialize::<_, #field_byteorder>(buffer)?;
                                     };
 
                                     return BinarySerializeTokens::new(binary_serialize_text, Some(size), None);
                                 }
 
                                 let binary_serialize_text = quote!{
-                                    self.#name.binary_serialize::<_, E>(buffer);
+                                    bytes_written += self.#name.binary_serialize::<_, E>(buffer)?;
                                 };
 
                                 BinarySerializeTokens::new(binary_serialize_text, Some(size), Some(min_size))
@@ -366,11 +637,11 @@ fn serialize_fields(
 
                             match ty {
                                 Type::Path(ref p) if !p.path.segments.is_empty() => {
-                                    handle_ident(&p.path)
+                                    Ok(handle_ident(&p.path))
                                 }
                                 Type::Array(a) => {
                                     let array_len = &a.len;
-                                    let mut tokens = handle_type(&name, &a.elem, field_byteorder);
+                                    let mut tokens = handle_type(&name, &a.elem, field_byteorder)?;
                                     let per_item_serialized_size = tokens.serialized_size;
                                     let per_item_min = tokens.min_nonzero_elements_size;
 
@@ -383,18 +654,105 @@ fn serialize_fields(
                                         #per_item_min * #array_len
                                     });
 
-                                    tokens
+                                    Ok(tokens)
                                 },
                                 Type::Reference(ref reference) => {
                                     handle_type(&name, &reference.elem, field_byteorder)
                                 }
+                                Type::Tuple(_) => {
+                                    let size = quote! { self.#name.serialized_size() };
+                                    let min_size = quote! { <#ty>::min_nonzero_elements_size() };
+                                    let byteorder = field_byteorder.cloned().unwrap_or_else(|| quote! {E});
+                                    let binary_serialize_text = quote! {
+                                        bytes_written += self.#name.binary_serialize::<_, #byteorder>(buffer)?;
+                                    };
+
+                                    Ok(BinarySerializeTokens::new(binary_serialize_text, Some(size), Some(min_size)))
+                                }
                                 _ => {
-                                    panic!("Unsupported type");
+                                    Err(syn::Error::new_spanned(
+                                        ty,
+                                        "BinarySerialize doesn't support this type. Fields must be paths (e.g. `u32` or `Vec<u8>`), arrays, tuples, or references",
+                                    ))
                                 }
                             }
                         }
 
-                        handle_type(&name.as_ref().unwrap(), &ty, field_byteorder.as_ref())
+                        let mut tokens = handle_type(&name.as_ref().unwrap(), &ty, field_byteorder.as_ref())?;
+
+                        // permuted fields are serialized as usual and then rearranged, which
+                        // doesn't change their size
+                        if let Some(permutation) = permutation {
+                            if container.no_alloc_serialize {
+                                return Err(syn::Error::new_spanned(
+                                    &permutation,
+                                    "#[lain(byte_permutation)] allocates a scratch buffer, so it can't be used with #[lain(no_alloc_serialize)]",
+                                ));
+                            }
+
+                            let byteorder = field_byteorder.unwrap_or_else(|| quote! {E});
+                            tokens.serialize = quote! {
+                                bytes_written += ::lain::byte_permutation::serialize_permuted::<_, _, #byteorder>(&self.#name, buffer, #permutation)?;
+                            };
+                        }
+
+                        // fields of nested structs follow their parent, or replace it if the
+                        // parent is flattened
+                        let path = name.as_ref().unwrap().to_string();
+                        let field_size = tokens.serialized_size.clone().unwrap_or_else(|| quote! {0});
+                        let entry = if flatten {
+                            TokenStream::new()
+                        } else {
+                            quote! {
+                                layout.push(::lain::types::FieldLayout {
+                                    path: #path.to_string(),
+                                    type_name: ::lain::debug::short_type_name::<#ty>(),
+                                    offset,
+                                    size,
+                                    bits: None,
+                                });
+                            }
+                        };
+                        let prefix = if flatten { String::new() } else { path };
+                        let nested = quote! {
+                            layout.extend(
+                                ::lain::traits::FieldMetadata::field_layout(&self.#name)
+                                    .into_iter()
+                                    .map(|field| field.prefixed(#prefix, offset)),
+                            );
+                        };
+                        let nested = match version_gate {
+                            Some(ref gate) => quote! {
+                                if #gate {
+                                    #nested
+                                }
+                            },
+                            None => nested,
+                        };
+
+                        // gated fields are only written for the versions they're part of, so
+                        // they don't count towards the struct's minimum size
+                        if let Some(gate) = version_gate {
+                            let serialize = &tokens.serialize;
+                            tokens.serialize = quote! {
+                                if #gate {
+                                    #serialize
+                                }
+                            };
+                            tokens.serialized_size = tokens.serialized_size.map(|size| quote! {
+                                (if #gate { #size } else { 0 })
+                            });
+                            tokens.min_nonzero_elements_size = None;
+                        }
+
+                        tokens.layout = quote! {
+                            let size = #field_size;
+                            #entry
+                            #nested
+                            offset += size;
+                        };
+
+                        Ok(tokens)
                     });
 
                     let mut serialize_text = quote! {
@@ -404,9 +762,15 @@ fn serialize_fields(
 
                     let mut object_size = quote! {0};
                     let mut min_object_size = quote! {0};
+                    let mut layout = TokenStream::new();
+                    let mut field_serializers = Vec::<TokenStream>::new();
+                    let mut swappable = Vec::<bool>::new();
 
-                    for item in fields {
-                        serialize_text.extend(item.serialize);
+                    for (item, &swap_field) in fields.zip(&swap_fields) {
+                        let item = item?;
+                        field_serializers.push(item.serialize);
+                        swappable.push(!item.bitfield && !swap_field);
+                        layout.extend(item.layout);
 
                         let item_size = item.serialized_size;
                         if item_size.is_some() {
@@ -422,61 +786,486 @@ fn serialize_fields(
                         }
                     }
 
+                    serialize_text.extend(gen_field_serializers(
+                        container,
+                        &field_serializers,
+                        &swappable,
+                    ));
+
                     // the above case would not push the byte if there is
                     // a bitfield in the final position with padding
                     if bitfield_shift != 0 {
                         serialize_text.extend(quote! {
-                            (bitfield as #bitfield_type).binary_serialize::<_, E>(buffer);
+                            bytes_written += (bitfield as #bitfield_type).binary_serialize::<_, E>(buffer)?;
                             bitfield = 0;
                         });
                     }
 
-                    BinarySerializeTokens::new(
+                    let mut tokens = BinarySerializeTokens::new(
                         serialize_text,
                         Some(object_size),
                         Some(min_object_size),
-                    )
-                }
-                _ => {
-                    panic!("BinarySerializer only supports named fields");
+                    );
+                    tokens.layout = layout;
+
+                    Ok(tokens)
                 }
+                None => Err(named_fields_error("BinarySerialize", name, &data.fields)),
             }
         }
+        Data::Union(ref data) => Err(union_error("BinarySerialize", data)),
+    }
+}
+
+/// Writes a struct's fields in wire order. If the container is marked
+/// `#[lain(field_swap = "...")]`, the pair of adjacent fields recorded in the named field by the
+/// mutator (see `MutatorConfig::field_swap_chance`) is written in swapped order. Only pairs of
+/// `swappable` fields are swapped: bitfields are packed with their neighbours.
+fn gen_field_serializers(
+    container: &ContainerMetadata,
+    field_serializers: &[TokenStream],
+    swappable: &[bool],
+) -> TokenStream {
+    let pairs: Vec<usize> = (1..swappable.len())
+        .filter(|&i| swappable[i - 1] && swappable[i])
+        .map(|i| i - 1)
+        .collect();
+
+    let swap_field = match container.field_swap {
+        Some(ref field) if !pairs.is_empty() => field,
         _ => {
-            panic!("BinarySerializer is only supported for structs");
+            return quote! {
+                #(#field_serializers)*
+            }
+        }
+    };
+
+    let count = field_serializers.len();
+    let positions = 0..count;
+
+    quote! {
+        let swapped = self.#swap_field.swapped_pair(&[#(#pairs),*]);
+        for position in 0..#count {
+            let field = match swapped {
+                Some(first) if position == first => first + 1,
+                Some(first) if position == first + 1 => first,
+                _ => position,
+            };
+
+            match field {
+                #(#positions => {
+                    #field_serializers
+                })*
+                _ => {}
+            }
         }
     }
 }
 
+/// Serializes the fields of a `#[lain(packed_layout)]` struct. Fields of a packed struct can't be
+/// borrowed in place, so each one is copied out before it's serialized.
+fn serialize_packed_fields(
+    input: &DeriveInput,
+    container: &ContainerMetadata,
+) -> syn::Result<BinarySerializeTokens> {
+    let name = &input.ident;
+
+    let fields = match input.data {
+        Data::Struct(ref data) => match named_fields(&data.fields) {
+            Some(fields) => fields,
+            None => return Err(named_fields_error("BinarySerialize", name, &data.fields)),
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "#[lain(packed_layout)] is only supported on structs",
+            ))
+        }
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "#[lain(packed_layout)] isn't supported on generic structs",
+        ));
+    }
+
+    if let Some(ref field) = container.field_swap {
+        return Err(syn::Error::new_spanned(
+            field,
+            "#[lain(packed_layout)] types are serialized in their in-memory order, so their fields can't be swapped",
+        ));
+    }
+
+    if !has_repr(&input.attrs, "C") || !has_repr(&input.attrs, "packed") {
+        return Err(syn::Error::new_spanned(
+            name,
+            "#[lain(packed_layout)] requires the struct to be #[repr(C, packed)]",
+        ));
+    }
+
+    if container.sort_by_order {
+        return Err(syn::Error::new_spanned(
+            name,
+            "#[lain(packed_layout)] fields are serialized in declaration order, so they can't be sorted with #[lain(sort_by_order)]",
+        ));
+    }
+
+    let mut serialize = TokenStream::new();
+    let mut layout = TokenStream::new();
+
+    for f in fields.named.iter() {
+        if get_bitfield_limits(f)?.is_some()
+            || get_flatten(f)?
+            || get_byteorder(get_byteorder_metadata(&f.attrs)?.into_iter())?.is_some()
+            || get_byte_permutation(f)?.is_some()
+            || get_version_gate(f)?.is_some()
+        {
+            return Err(syn::Error::new_spanned(
+                f,
+                "#[lain(packed_layout)] fields are serialized as their in-memory bytes, so they can't be bitfields, flattened, permuted, version gated, or given a #[byteorder]",
+            ));
+        }
+
+        let ident = &f.ident;
+        let ty = &f.ty;
+        let path = ident.as_ref().unwrap().to_string();
+
+        serialize.extend(quote! {
+            bytes_written += { self.#ident }.binary_serialize::<_, E>(buffer)?;
+        });
+
+        layout.extend(quote! {
+            let size = std::mem::size_of::<#ty>();
+            layout.push(::lain::types::FieldLayout {
+                path: #path.to_string(),
+                type_name: ::lain::debug::short_type_name::<#ty>(),
+                offset,
+                size,
+                bits: None,
+            });
+            layout.extend(
+                ::lain::traits::FieldMetadata::field_layout(&{ self.#ident })
+                    .into_iter()
+                    .map(|field| field.prefixed(#path, offset)),
+            );
+            offset += size;
+        });
+    }
+
+    if container.memcpy_serialize {
+        // the layout checks guarantee that the struct's bytes are its serialized fields
+        serialize = quote! {
+            if ::lain::buffer::is_native_byte_order::<E>() {
+                let bytes = unsafe {
+                    std::slice::from_raw_parts(self as *const Self as *const u8, std::mem::size_of::<Self>())
+                };
+                buffer.write_all(bytes)?;
+
+                return Ok(bytes.len());
+            }
+
+            #serialize
+        };
+    }
+
+    let size = quote! {std::mem::size_of::<Self>()};
+    let mut tokens = BinarySerializeTokens::new(serialize, Some(size.clone()), Some(size));
+    tokens.layout = layout;
+
+    Ok(tokens)
+}
+
+/// Implements `PlainOldData` for a `#[lain(packed_layout)]` struct, after checking at compile time
+/// that every field is plain old data and sits at the offset it's serialized at
+fn gen_packed_layout_impl(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let fields = match input.data {
+        Data::Struct(ref data) => data.fields.iter().collect::<Vec<_>>(),
+        _ => return TokenStream::new(),
+    };
+
+    let field_types = fields.iter().map(|f| &f.ty);
+    let offset_checks = fields.iter().map(|f| {
+        let ident = &f.ident;
+        let ty = &f.ty;
+        let message = format!(
+            "{}.{} isn't at the offset it's serialized at",
+            name,
+            ident.as_ref().unwrap()
+        );
+
+        quote! {
+            if std::mem::offset_of!(#name, #ident) != offset {
+                panic!(#message);
+            }
+            offset += std::mem::size_of::<#ty>();
+        }
+    });
+    let size_message = format!("{} has bytes which aren't serialized", name);
+
+    quote! {
+        const _: () = {
+            #[allow(dead_code)]
+            fn fields_are_plain_old_data() {
+                fn is_plain_old_data<T: ::lain::traits::PlainOldData>() {}
+                #(is_plain_old_data::<#field_types>();)*
+            }
+
+            let mut offset = 0usize;
+            #(#offset_checks)*
+
+            if std::mem::size_of::<#name>() != offset {
+                panic!(#size_message);
+            }
+        };
+
+        unsafe impl ::lain::traits::PlainOldData for #name {}
+    }
+}
+
 /// Returns the user-specified byteorder of a child field based off of the #[byteorder()] attribute.
 /// This will return an Option<TokenStream> consisting of the full path to the byteorder::BigEndian or
 /// byteorder::LittleEndian enum.
-fn get_byteorder(meta: impl Iterator<Item = Vec<syn::NestedMeta>>) -> Option<TokenStream> {
+pub(crate) fn get_byteorder(
+    meta: impl Iterator<Item = Vec<syn::NestedMeta>>,
+) -> syn::Result<Option<TokenStream>> {
     for meta_items in meta {
         for meta_item in meta_items {
             match meta_item {
-                Meta(ref m) => match m {
-                    Word(ref w) => {
-                        match w.to_string().as_ref() {
-                            "big" => return Some(quote! {::lain::byteorder::BigEndian}),
-                            "little" => return Some(quote! {::lain::byteorder::LittleEndian}),
-                            _ => panic!(
-                                "{} is not a supported byteorder. must be big or little",
-                                w.to_string()
-                            ),
-                        };
+                Meta(Word(ref w)) => {
+                    match w.to_string().as_ref() {
+                        "big" => return Ok(Some(quote! {::lain::byteorder::BigEndian})),
+                        "little" => return Ok(Some(quote! {::lain::byteorder::LittleEndian})),
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                w,
+                                format!(
+                                    "`{}` is not a supported byteorder. must be big or little",
+                                    w
+                                ),
+                            ))
+                        }
+                    };
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "#[byteorder] attribute expects big or little (e.g. #[byteorder(big)])",
+                    ))
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) fn get_byteorder_metadata(
+    attrs: &[syn::Attribute],
+) -> syn::Result<Vec<Vec<syn::NestedMeta>>> {
+    get_field_metadata("byteorder", attrs)
+}
+
+/// Implements `FieldMetadata` for structs with named fields. `layout` pushes the layout of each
+/// field in wire order, as generated alongside the struct's serialization.
+fn gen_field_metadata_impl(input: &DeriveInput, layout: &TokenStream) -> TokenStream {
+    let fields = match input.data {
+        Data::Struct(ref data) => match named_fields(&data.fields) {
+            Some(fields) => fields,
+            None => return TokenStream::new(),
+        },
+        _ => return TokenStream::new(),
+    };
+
+    let field_info = fields.named.iter().map(|f| {
+        let name = f.ident.as_ref().unwrap().to_string();
+        let ty = &f.ty;
+        let attributes = get_attribute_settings(&f.attrs);
+
+        quote! {
+            ::lain::types::FieldInfo {
+                name: #name,
+                type_name: ::lain::debug::short_type_name::<#ty>(),
+                attributes: vec![#(#attributes),*],
+            }
+        }
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::lain::traits::FieldMetadata for #name #ty_generics #where_clause {
+            fn field_info() -> Vec<::lain::types::FieldInfo> {
+                vec![#(#field_info),*]
+            }
+
+            #[allow(unused_assignments, unused_mut, unused_variables)]
+            fn field_layout(&self) -> Vec<::lain::types::FieldLayout> {
+                use ::lain::traits::SerializedSize;
+
+                let mut layout = Vec::new();
+                let mut offset = 0usize;
+                let mut bitfield_offset = 0usize;
+
+                #layout
+
+                layout
+            }
+        }
+    }
+}
+
+/// Implements `InputShape`. Structs hash the shape of each of their fields, and enums hash the index
+/// of their variant followed by the shapes of its fields. The fields of packed structs are copied
+/// out rather than borrowed.
+fn gen_input_shape_impl(input: &DeriveInput, packed: bool) -> TokenStream {
+    let name = &input.ident;
+
+    let body = match input.data {
+        Data::Struct(ref data) => match named_fields(&data.fields) {
+            Some(fields) => {
+                let fields = fields.named.iter().map(|f| {
+                    let ident = &f.ident;
+                    let ty = &f.ty;
+
+                    let field = if packed {
+                        quote! {&{ self.#ident }}
+                    } else {
+                        quote! {&self.#ident}
+                    };
+
+                    quote! {
+                        <#ty as ::lain::traits::InputShape>::hash_shape(#field, state);
+                    }
+                });
+
+                quote! {
+                    #(#fields)*
+                }
+            }
+            None => return TokenStream::new(),
+        },
+        Data::Enum(ref data) => {
+            let arms = data.variants.iter().enumerate().map(|(index, variant)| {
+                let ident = &variant.ident;
+
+                let (pattern, bindings): (TokenStream, Vec<(TokenStream, &Type)>) =
+                    match variant.fields {
+                        syn::Fields::Unnamed(ref fields) => {
+                            let bindings: Vec<_> = fields
+                                .unnamed
+                                .iter()
+                                .enumerate()
+                                .map(|(i, f)| {
+                                    (
+                                        TokenStream::from_str(&format!("field_{}", i)).unwrap(),
+                                        &f.ty,
+                                    )
+                                })
+                                .collect();
+                            let idents = bindings.iter().map(|(ident, _)| ident);
+
+                            (quote! {(#(ref #idents),*)}, bindings)
+                        }
+                        syn::Fields::Named(ref fields) => {
+                            let bindings: Vec<_> = fields
+                                .named
+                                .iter()
+                                .map(|f| {
+                                    let ident = &f.ident;
+                                    (quote! {#ident}, &f.ty)
+                                })
+                                .collect();
+                            let idents = fields.named.iter().map(|f| &f.ident);
+
+                            (quote! {{#(ref #idents),*}}, bindings)
+                        }
+                        syn::Fields::Unit => (TokenStream::new(), Vec::new()),
+                    };
+
+                let fields = bindings.iter().map(|(ident, ty)| {
+                    quote! {
+                        <#ty as ::lain::traits::InputShape>::hash_shape(#ident, state);
                     }
-                    _ => panic!("non-string literal for byteorder attribute"),
-                },
-                _ => panic!(
-                    "#[byteorder] attribute expects a string literal (e.g. #[byteorder(big)]"
-                ),
+                });
+
+                quote! {
+                    #name::#ident #pattern => {
+                        state.write_usize(#index);
+                        #(#fields)*
+                    }
+                }
+            });
+
+            quote! {
+                match *self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => return TokenStream::new(),
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::lain::traits::InputShape for #name #ty_generics #where_clause {
+            #[allow(unused)]
+            fn hash_shape<H: std::hash::Hasher>(&self, state: &mut H) {
+                #body
             }
         }
     }
-    None
 }
 
-fn get_byteorder_metadata(attr: &syn::Attribute) -> Option<Vec<syn::NestedMeta>> {
-    get_attribute_metadata("byteorder", &attr)
+/// Implements `NoAllocSerialize` for containers marked `#[lain(no_alloc_serialize)]`. The impl is
+/// bounded on every serialized field's type implementing it as well, so a field which may allocate
+/// while being serialized is a compile error.
+fn gen_no_alloc_serialize_impl(input: &DeriveInput) -> syn::Result<TokenStream> {
+    fn element_type(ty: &Type) -> &Type {
+        match ty {
+            Type::Array(ref a) => element_type(&a.elem),
+            Type::Reference(ref r) => element_type(&r.elem),
+            _ => ty,
+        }
+    }
+
+    let mut field_types = Vec::<&Type>::new();
+    match input.data {
+        Data::Struct(ref data) => {
+            for field in data.fields.iter() {
+                // bitfields are packed by the derive itself
+                if get_bitfield_limits(field)?.is_none() {
+                    field_types.push(element_type(&field.ty));
+                }
+            }
+        }
+        Data::Enum(ref data) => {
+            for variant in data.variants.iter() {
+                field_types.extend(variant.fields.iter().map(|f| element_type(&f.ty)));
+            }
+        }
+        Data::Union(_) => return Ok(TokenStream::new()),
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let predicates = where_clause.map(|w| {
+        let predicates = &w.predicates;
+        if predicates.empty_or_trailing() {
+            quote! {#predicates}
+        } else {
+            quote! {#predicates,}
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::lain::traits::NoAllocSerialize for #name #ty_generics
+        where
+            #predicates
+            #(#field_types: ::lain::traits::NoAllocSerialize,)*
+        {
+        }
+    })
 }
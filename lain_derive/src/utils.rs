@@ -2,10 +2,12 @@ use proc_macro2::TokenStream;
 
 use quote::{quote, quote_spanned, ToTokens};
 
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
-use syn::{IntSuffix, LitInt};
+use syn::{DataUnion, Fields, FieldsNamed, Ident, IntSuffix, LitInt};
 use syn::{Meta, NestedMeta};
 
+use std::borrow::Cow;
 use std::str::FromStr;
 
 use crate::attr::*;
@@ -34,15 +36,212 @@ impl ToTokens for Weighted {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum Bounds {
+    ClosedOpen,
+    Closed,
+    Open,
+    OpenClosed,
+}
+
+impl Bounds {
+    fn includes_min(&self) -> bool {
+        match *self {
+            Bounds::ClosedOpen | Bounds::Closed => true,
+            Bounds::Open | Bounds::OpenClosed => false,
+        }
+    }
+
+    fn includes_max(&self) -> bool {
+        match *self {
+            Bounds::Closed | Bounds::OpenClosed => true,
+            Bounds::ClosedOpen | Bounds::Open => false,
+        }
+    }
+}
+
+impl ToTokens for Bounds {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match *self {
+            Bounds::ClosedOpen => tokens.extend(quote! {::lain::types::Bounds::ClosedOpen}),
+            Bounds::Closed => tokens.extend(quote! {::lain::types::Bounds::Closed}),
+            Bounds::Open => tokens.extend(quote! {::lain::types::Bounds::Open}),
+            Bounds::OpenClosed => tokens.extend(quote! {::lain::types::Bounds::OpenClosed}),
+        }
+    }
+}
+
 pub(crate) struct FuzzerObjectStructField<'a> {
     pub field: &'a syn::Field,
     pub min: Option<TokenStream>,
     pub max: Option<TokenStream>,
+    /// The min/max of each element of a collection, from `#[lain(element_min = N, element_max = M)]`
+    pub element_min: Option<TokenStream>,
+    pub element_max: Option<TokenStream>,
+    /// Whether `element_min` and `element_max` are included in the range
+    pub element_bounds: Bounds,
     pub ignore: bool,
     pub user_initializer: Option<TokenStream>,
     pub ignore_chance: f32,
     pub is_bitfield: bool,
     pub weighted: Weighted,
+    pub bounds: Bounds,
+    pub exhaustive: bool,
+    pub pinned: Vec<TokenStream>,
+    pub opaque: bool,
+    /// The field holding this array's element count, given by `#[lain(count_field = "...")]`
+    pub count_field: Option<Ident>,
+    /// The `FieldCategory` this field is mutated as, from `#[lain(category = "...")]` or its type
+    pub category: Option<TokenStream>,
+    /// Whether this field holds the struct's protocol version, marked `#[lain(protocol_version)]`
+    pub protocol_version: bool,
+    /// The condition from `#[lain(version_gate = "...")]` under which this field is generated
+    pub version_gate: Option<TokenStream>,
+    /// When this field is fixed up relative to its siblings, from `#[lain(fixup_order = N)]`
+    pub fixup_order: Option<u64>,
+    /// Whether this field is regenerated by `Refresh::refresh`, marked `#[lain(volatile)]`
+    pub volatile: bool,
+    /// Whether this field is generated once and reused by every value, marked
+    /// `#[lain(generate_once)]`
+    pub generate_once: bool,
+    /// The group of correlated fields this field is mutated with, from `#[lain(group = "...")]`
+    pub group: Option<String>,
+}
+
+impl<'a> FuzzerObjectStructField<'a> {
+    /// Returns an expression building the `Option<Constraints<_>>` for this field's min/max and
+    /// element min/max, or `None` if the user didn't supply any
+    pub fn constraints(&self, max_size: TokenStream) -> Option<TokenStream> {
+        if self.min.is_none()
+            && self.max.is_none()
+            && self.element_min.is_none()
+            && self.element_max.is_none()
+        {
+            return None;
+        }
+
+        let span = self.field.span();
+        let min = self
+            .min
+            .as_ref()
+            .map(|v| quote! {Some(#v)})
+            .unwrap_or_else(|| quote! {None});
+        let max = self
+            .max
+            .as_ref()
+            .map(|v| quote! {Some(#v)})
+            .unwrap_or_else(|| quote! {None});
+        let weighted = &self.weighted;
+        let bounds = &self.bounds;
+        let element_constraints = self.element_constraints();
+
+        Some(quote_spanned! { span =>
+            Some(::lain::types::Constraints {
+                min: #min,
+                max: #max,
+                weighted: #weighted,
+                max_size: #max_size,
+                bounds: #bounds,
+                element_constraints: #element_constraints,
+            })
+        })
+    }
+
+    /// Returns an expression building the `Option<ElementConstraints>` for this field's element
+    /// min/max. The field's type names its element type through `ConstrainedElements` so that
+    /// the constraints have the element's `RangeType`.
+    fn element_constraints(&self) -> TokenStream {
+        if self.element_min.is_none() && self.element_max.is_none() {
+            return quote! {None};
+        }
+
+        let span = self.field.span();
+        let ty = &self.field.ty;
+        let min = self
+            .element_min
+            .as_ref()
+            .map(|v| quote! {Some(#v)})
+            .unwrap_or_else(|| quote! {None});
+        let max = self
+            .element_max
+            .as_ref()
+            .map(|v| quote! {Some(#v)})
+            .unwrap_or_else(|| quote! {None});
+        let bounds = &self.element_bounds;
+
+        quote_spanned! { span =>
+            Some(::lain::types::ElementConstraints::new(::lain::types::Constraints::<
+                <<#ty as ::lain::traits::ConstrainedElements>::Element as ::lain::traits::NewFuzzed>::RangeType,
+            > {
+                min: #min,
+                max: #max,
+                weighted: ::lain::types::Weighted::None,
+                max_size: None,
+                bounds: #bounds,
+                element_constraints: None,
+            }))
+        }
+    }
+
+    /// Returns an expression which evaluates to `Some(value)` if this field is part of an active
+    /// exhaustive sweep
+    pub fn exhaustive_value(&self) -> syn::Result<TokenStream> {
+        let (count, value) = self.enumeration()?;
+
+        Ok(quote_spanned! { self.field.span() =>
+            mutator.next_exhaustive_digit(#count).map(|i| #value)
+        })
+    }
+
+    /// Returns the expressions for the number of values this field can be enumerated over and the
+    /// value at index `i`. Integers with both a min and max are enumerated over that range,
+    /// otherwise the field's type must implement `Enumerable`.
+    pub fn enumeration(&self) -> syn::Result<(TokenStream, TokenStream)> {
+        let span = self.field.span();
+        let ty = &self.field.ty;
+
+        if self.ignore {
+            return Ok((
+                quote_spanned! { span => 1 },
+                quote_spanned! { span => <#ty>::default() },
+            ));
+        }
+
+        let primitive_type = match ty {
+            syn::Type::Path(ref p) if !p.path.segments.is_empty() => {
+                is_primitive(&p.path.segments[0].ident.to_string())
+            }
+            _ => PrimitiveType::None,
+        };
+
+        match (primitive_type, &self.min, &self.max) {
+            (PrimitiveType::Number, Some(ref min), Some(ref max)) => {
+                let type_name = ty.into_token_stream().to_string();
+                if type_name == "f32" || type_name == "f64" {
+                    return Err(syn::Error::new_spanned(
+                        self.field,
+                        "floating point fields cannot be enumerated. Remove #[fuzzer(exhaustive)] or use an integer type",
+                    ));
+                }
+
+                // enumerate over the half-open range [first, end)
+                let first_offset = if self.bounds.includes_min() { 0u8 } else { 1 };
+                let end_offset = if self.bounds.includes_max() { 1u8 } else { 0 };
+
+                // i128 holds the width of any range of 64-bit or smaller integers
+                Ok((
+                    quote_spanned! { span =>
+                        (((#max) as i128 + #end_offset as i128) - ((#min) as i128 + #first_offset as i128)) as usize
+                    },
+                    quote_spanned! { span => ((#min) as i128 + #first_offset as i128 + i as i128) as #ty },
+                ))
+            }
+            _ => Ok((
+                quote_spanned! { span => <#ty as ::lain::traits::Enumerable>::value_count() },
+                quote_spanned! { span => <#ty as ::lain::traits::Enumerable>::value_at(i) },
+            )),
+        }
+    }
 }
 
 pub(crate) fn is_primitive(ty: &str) -> PrimitiveType {
@@ -55,8 +254,32 @@ pub(crate) fn is_primitive(ty: &str) -> PrimitiveType {
     }
 }
 
-pub(crate) fn parse_fields(fields: &syn::FieldsNamed) -> Vec<FuzzerObjectStructField> {
-    fields
+/// Returns the `FieldCategory` a field of type `ty` is mutated as, or `None` if its type doesn't
+/// have one. Nested structs and enums have their own fields categorized instead.
+pub(crate) fn get_field_category(ty: &syn::Type) -> Option<TokenStream> {
+    let category = match ty {
+        syn::Type::Array(_) | syn::Type::Slice(_) => quote! {Collection},
+        syn::Type::Reference(ref r) => return get_field_category(&r.elem),
+        syn::Type::Path(ref p) => {
+            let ident = &p.path.segments.last()?.into_value().ident;
+
+            match ident.to_string().as_ref() {
+                "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64" | "U24" | "U48"
+                | "TargetUsize" | "TargetIsize" => quote! {Integer},
+                "String" | "AsciiString" | "Utf8String" => quote! {String},
+                "Vec" | "VecDeque" => quote! {Collection},
+                "UnsafeEnum" => quote! {Enum},
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    Some(quote! {::lain::mutator::FieldCategory::#category})
+}
+
+pub(crate) fn parse_fields(fields: &syn::FieldsNamed) -> syn::Result<Vec<FuzzerObjectStructField>> {
+    let parsed = fields
         .named
         .iter()
         .map(|f| {
@@ -64,17 +287,39 @@ pub(crate) fn parse_fields(fields: &syn::FieldsNamed) -> Vec<FuzzerObjectStructF
                 field: f,
                 min: None,
                 max: None,
+                element_min: None,
+                element_max: None,
+                element_bounds: Bounds::ClosedOpen,
                 ignore_chance: 0.0,
                 ignore: false,
                 user_initializer: None,
                 is_bitfield: false,
                 weighted: Weighted::None,
+                bounds: Bounds::ClosedOpen,
+                exhaustive: false,
+                pinned: get_pinned_regions(&f.attrs)?,
+                opaque: false,
+                count_field: None,
+                category: get_field_category(&f.ty),
+                protocol_version: false,
+                version_gate: get_version_gate(f)?,
+                fixup_order: None,
+                volatile: false,
+                generate_once: false,
+                group: None,
             };
 
             let _ty = &f.ty;
 
-            let meta = f.attrs.iter().filter_map(get_fuzzer_metadata);
-            for meta_items in meta {
+            // `max_inclusive` includes the max in the range on top of whatever `bounds` says
+            // about the min
+            let mut max_inclusive = false;
+
+            // flattened fields are generated and mutated like any other nested struct, which
+            // already shares the parent's size budget. this only rejects conflicting attributes
+            let flatten = get_flatten(f)?;
+
+            for meta_items in get_fuzzer_metadata(&f.attrs)? {
                 for meta_item in meta_items {
                     match meta_item {
                         NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "weighted" => {
@@ -82,36 +327,64 @@ pub(crate) fn parse_fields(fields: &syn::FieldsNamed) -> Vec<FuzzerObjectStructF
                                 field.weighted = match s.value().as_ref() {
                                     "min" => Weighted::Min,
                                     "max" => Weighted::Max,
-                                    other => panic!("Unknown weighted value: {}", other),
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            s,
+                                            format!("unknown weighted value `{}`. Expected \"min\" or \"max\"", other),
+                                        ))
+                                    }
+                                };
+                            }
+                        }
+                        NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "bounds" => {
+                            if let syn::Lit::Str(ref s) = m.lit {
+                                field.bounds = match s.value().as_ref() {
+                                    "closed_open" => Bounds::ClosedOpen,
+                                    "closed" => Bounds::Closed,
+                                    "open" => Bounds::Open,
+                                    "open_closed" => Bounds::OpenClosed,
+                                    other => {
+                                        return Err(syn::Error::new_spanned(
+                                            s,
+                                            format!("unknown bounds value `{}`. Expected \"closed_open\", \"closed\", \"open\", or \"open_closed\"", other),
+                                        ))
+                                    }
                                 };
                             }
                         }
                         NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "min" => {
-                            // we basically only parse out strings since we want to treat those as non-string TokenStreams
-                            if let Ok(s) = get_lit_str(&m.lit) {
-                                let value = TokenStream::from_str(&s.value())
-                                    .expect("invalid tokens for min");
-                                field.min = Some(quote_spanned! {m.lit.span() => #value});
-                            } else if let Ok(i) = get_lit_number(&m.lit) {
-                                let int = LitInt::new(i.value(), IntSuffix::None, m.lit.span());
-                                field.min = Some(quote_spanned! {m.lit.span() => #int});
-                            } else {
-                                let lit = &m.lit;
-                                field.min = Some(quote_spanned! {m.lit.span() => #lit});
+                            field.min = Some(parse_bound(m, "min")?);
+                        }
+                        NestedMeta::Meta(Meta::NameValue(ref m))
+                            if m.ident == "max" || m.ident == "max_inclusive" =>
+                        {
+                            if field.max.is_some() {
+                                return Err(syn::Error::new_spanned(
+                                    &m.ident,
+                                    "only one of `max` and `max_inclusive` may be given",
+                                ));
                             }
+
+                            max_inclusive = m.ident == "max_inclusive";
+                            field.max = Some(parse_bound(m, "max")?);
                         }
-                        NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "max" => {
-                            if let Ok(s) = get_lit_str(&m.lit) {
-                                let value = TokenStream::from_str(&s.value())
-                                    .expect("invalid tokens for max");
-                                field.max = Some(quote_spanned! {m.lit.span() => #value});
-                            } else if let Ok(i) = get_lit_number(&m.lit) {
-                                let int = LitInt::new(i.value(), IntSuffix::None, m.lit.span());
-                                field.max = Some(quote_spanned! {m.lit.span() => #int});
-                            } else {
-                                let lit = &m.lit;
-                                field.max = Some(quote_spanned! {m.lit.span() => #lit});
+                        NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "element_min" => {
+                            field.element_min = Some(parse_bound(m, "element_min")?);
+                        }
+                        NestedMeta::Meta(Meta::NameValue(ref m))
+                            if m.ident == "element_max" || m.ident == "element_max_inclusive" =>
+                        {
+                            if field.element_max.is_some() {
+                                return Err(syn::Error::new_spanned(
+                                    &m.ident,
+                                    "only one of `element_max` and `element_max_inclusive` may be given",
+                                ));
+                            }
+
+                            if m.ident == "element_max_inclusive" {
+                                field.element_bounds = Bounds::Closed;
                             }
+                            field.element_max = Some(parse_bound(m, "element_max")?);
                         }
                         NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "ignore" => {
                             if let Ok(s) = get_lit_bool(&m.lit) {
@@ -121,21 +394,116 @@ pub(crate) fn parse_fields(fields: &syn::FieldsNamed) -> Vec<FuzzerObjectStructF
                         NestedMeta::Meta(Meta::Word(ref ident)) if ident == "ignore" => {
                             field.ignore = true;
                         }
+                        NestedMeta::Meta(Meta::Word(ref ident)) if ident == "protocol_version" => {
+                            if field.version_gate.is_some() {
+                                return Err(syn::Error::new_spanned(
+                                    ident,
+                                    "the protocol version field can't itself be gated with #[lain(version_gate)]",
+                                ));
+                            }
+
+                            field.protocol_version = true;
+                        }
+                        NestedMeta::Meta(Meta::Word(ref ident)) if ident == "exhaustive" => {
+                            field.exhaustive = true;
+                        }
+                        NestedMeta::Meta(Meta::Word(ref ident)) if ident == "volatile" => {
+                            field.volatile = true;
+                        }
+                        NestedMeta::Meta(Meta::Word(ref ident)) if ident == "generate_once" => {
+                            field.generate_once = true;
+                        }
+                        NestedMeta::Meta(Meta::Word(ref ident)) if ident == "opaque" => {
+                            // opaque fields are only ever replaced as a whole, so attributes
+                            // which reach into the value don't make sense
+                            let conflicting = if flatten {
+                                Some("flatten")
+                            } else if !field.pinned.is_empty() {
+                                Some("pin")
+                            } else {
+                                None
+                            };
+
+                            if let Some(conflicting) = conflicting {
+                                return Err(syn::Error::new_spanned(
+                                    ident,
+                                    format!("`{}` can't be used on an opaque field", conflicting),
+                                ));
+                            }
+
+                            field.opaque = true;
+                        }
                         NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "ignore_chance" => {
                             if let syn::Lit::Float(ref f) = m.lit {
                                 field.ignore_chance = f.value() as f32;
                             } else if let syn::Lit::Int(ref i) = m.lit {
                                 field.ignore_chance = i.value() as f32;
                             } else {
-                                panic!("ignore_chance field should be a f32");
+                                return Err(syn::Error::new_spanned(
+                                    &m.lit,
+                                    "ignore_chance should be a f32, e.g. #[fuzzer(ignore_chance = 0.5)]",
+                                ));
                             }
                         }
+                        NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "count_field" => {
+                            let s = get_lit_str(&m.lit).map_err(|_| {
+                                syn::Error::new_spanned(
+                                    &m.lit,
+                                    "count_field should name a field, e.g. #[lain(count_field = \"num_entries\")]",
+                                )
+                            })?;
+
+                            field.count_field = Some(s.parse()?);
+                        }
+                        NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "fixup_order" => {
+                            let order = get_lit_number(&m.lit).map_err(|_| {
+                                syn::Error::new_spanned(
+                                    &m.lit,
+                                    "fixup_order should be an integer, e.g. #[lain(fixup_order = 1)]",
+                                )
+                            })?;
+
+                            field.fixup_order = Some(order.value());
+                        }
+                        NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "group" => {
+                            let s = get_lit_str(&m.lit).map_err(|_| {
+                                syn::Error::new_spanned(
+                                    &m.lit,
+                                    "group should be a string, e.g. #[lain(group = \"geometry\")]",
+                                )
+                            })?;
+
+                            field.group = Some(s.value());
+                        }
+                        NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "category" => {
+                            let s = get_lit_str(&m.lit).map_err(|_| {
+                                syn::Error::new_spanned(
+                                    &m.lit,
+                                    "category should be a string, e.g. #[lain(category = \"string\")]",
+                                )
+                            })?;
+
+                            let category = match s.value().as_ref() {
+                                "integer" => quote! {Integer},
+                                "string" => quote! {String},
+                                "collection" => quote! {Collection},
+                                "enum" => quote! {Enum},
+                                other => {
+                                    return Err(syn::Error::new_spanned(
+                                        s,
+                                        format!("unknown category `{}`. Expected \"integer\", \"string\", \"collection\", or \"enum\"", other),
+                                    ))
+                                }
+                            };
+
+                            field.category = Some(quote! {::lain::mutator::FieldCategory::#category});
+                        }
                         NestedMeta::Meta(Meta::NameValue(ref m)) if m.ident == "initializer" => {
                             if let syn::Lit::Str(ref s) = m.lit {
-                                field.user_initializer = Some(
-                                    TokenStream::from_str(&s.value())
-                                        .expect("invalid tokens for initializer"),
-                                );
+                                field.user_initializer =
+                                    Some(TokenStream::from_str(&s.value()).map_err(|_| {
+                                        syn::Error::new_spanned(s, "invalid tokens for initializer")
+                                    })?);
                             }
                         }
                         _ => continue,
@@ -143,12 +511,20 @@ pub(crate) fn parse_fields(fields: &syn::FieldsNamed) -> Vec<FuzzerObjectStructF
                 }
             }
 
+            if max_inclusive {
+                field.bounds = match field.bounds {
+                    Bounds::ClosedOpen | Bounds::Closed => Bounds::Closed,
+                    Bounds::Open | Bounds::OpenClosed => Bounds::OpenClosed,
+                };
+            }
+
             // parse out all of the details about min/max, etc. before we return
             // anything. we do this so that we can ensure we have all required
             // operations to do things in the mutator as well
-            let meta = f.attrs.iter().filter_map(get_bitfield_metadata);
-            if let Some(bitfield_meta) = get_bitfield_limits(meta) {
+            if let Some(bitfield_meta) = get_bitfield_limits(f)? {
                 field.is_bitfield = true;
+                // the limits are the half-open range of values which fit in the bits
+                field.bounds = Bounds::ClosedOpen;
                 // TODO: we can provide better diagnostics if we instead use the span
                 // of the nested meta items
                 let min = LitInt::new(bitfield_meta.min, IntSuffix::None, f.span());
@@ -158,7 +534,184 @@ pub(crate) fn parse_fields(fields: &syn::FieldsNamed) -> Vec<FuzzerObjectStructF
                 field.max = Some(quote! {#max});
             }
 
-            field
+            Ok(field)
         })
-        .collect()
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    // volatile fields are regenerated on their own, which the protocol version and ignored
+    // fields never are
+    if let Some(field) = parsed
+        .iter()
+        .find(|f| f.volatile && (f.ignore || f.protocol_version))
+    {
+        return Err(syn::Error::new_spanned(
+            field.field,
+            "#[lain(volatile)] can't be used on an ignored field or the protocol version field",
+        ));
+    }
+
+    // a section which is generated once can't also be regenerated, swept, or follow the version
+    if let Some(field) = parsed.iter().find(|f| {
+        f.generate_once
+            && (f.ignore || f.volatile || f.exhaustive || f.opaque || f.protocol_version)
+    }) {
+        return Err(syn::Error::new_spanned(
+            field.field,
+            "#[lain(generate_once)] can't be used on an ignored, volatile, exhaustive, or opaque field, or the protocol version field",
+        ));
+    }
+
+    if let Some(second) = parsed.iter().filter(|f| f.protocol_version).nth(1) {
+        return Err(syn::Error::new_spanned(
+            second.field,
+            "only one field can be marked #[lain(protocol_version)]",
+        ));
+    }
+
+    for field in parsed.iter() {
+        if let Some(ref count_field) = field.count_field {
+            let is_other_field = parsed.iter().any(|other| {
+                other.field.ident.as_ref() == Some(count_field)
+                    && other.field.ident != field.field.ident
+            });
+
+            if !is_other_field {
+                return Err(syn::Error::new_spanned(
+                    count_field,
+                    format!("`{}` isn't another field of this struct", count_field),
+                ));
+            }
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Parses the value of a `min`/`max`-like attribute. Strings are parsed as tokens so that values
+/// which aren't literals (e.g. negative numbers or constants) can be given.
+fn parse_bound(m: &syn::MetaNameValue, name: &str) -> syn::Result<TokenStream> {
+    if let Ok(s) = get_lit_str(&m.lit) {
+        let value = TokenStream::from_str(&s.value())
+            .map_err(|_| syn::Error::new_spanned(s, format!("invalid tokens for {}", name)))?;
+        Ok(quote_spanned! {m.lit.span() => #value})
+    } else if let Ok(i) = get_lit_number(&m.lit) {
+        let int = LitInt::new(i.value(), IntSuffix::None, m.lit.span());
+        Ok(quote_spanned! {m.lit.span() => #int})
+    } else {
+        let lit = &m.lit;
+        Ok(quote_spanned! {m.lit.span() => #lit})
+    }
+}
+
+/// Returns the error for a derive which only supports structs with named fields. This points at
+/// the tuple fields if there are any, otherwise at the struct's name.
+pub(crate) fn named_fields_error(derive: &str, name: &Ident, fields: &Fields) -> syn::Error {
+    let message = format!(
+        "{} only supports structs with named fields, e.g. `struct {} {{ field: u8 }}`",
+        derive, name
+    );
+
+    match *fields {
+        Fields::Unnamed(ref fields) => syn::Error::new_spanned(fields, message),
+        _ => syn::Error::new_spanned(name, message),
+    }
+}
+
+/// Returns the fields of a struct with named fields. Unit structs are treated as having no fields so
+/// that marker types (e.g. type-state markers in generic models) can derive every trait.
+pub(crate) fn named_fields(fields: &Fields) -> Option<Cow<'_, FieldsNamed>> {
+    match *fields {
+        Fields::Named(ref fields) => Some(Cow::Borrowed(fields)),
+        Fields::Unit => Some(Cow::Owned(FieldsNamed {
+            brace_token: Default::default(),
+            named: Punctuated::new(),
+        })),
+        Fields::Unnamed(_) => None,
+    }
+}
+
+/// Returns the error for a derive used on a union
+pub(crate) fn union_error(derive: &str, data: &DataUnion) -> syn::Error {
+    syn::Error::new(
+        data.union_token.span,
+        format!(
+            "{} can't be derived for unions. Please use an enum with typed variants instead",
+            derive
+        ),
+    )
+}
+
+/// Returns the statements which enter and leave a container's `#[lain(seed_domain = "...")]`, if
+/// one was given. Everything generated between them uses the domain's RNG.
+pub(crate) fn gen_seed_domain_scope(seed_domain: Option<&String>) -> (TokenStream, TokenStream) {
+    match seed_domain {
+        Some(domain) => (
+            quote! {
+                let previous_rng = mutator.begin_seed_domain(#domain);
+            },
+            quote! {
+                mutator.end_seed_domain(previous_rng);
+            },
+        ),
+        None => (TokenStream::new(), TokenStream::new()),
+    }
+}
+
+/// Returns the statements fixing up `receiver` once it's been generated or mutated. Containers
+/// with fields given a `#[lain(fixup_order = N)]` have dependent fixups, so they're fixed up twice
+/// to let values computed from other fields (e.g. a checksum covering a length) settle, unless the
+/// number of passes is given with `#[lain(fixup_passes = N)]`.
+pub(crate) fn gen_fixup_passes(
+    receiver: TokenStream,
+    container: &ContainerMetadata,
+    data: &syn::Data,
+) -> syn::Result<TokenStream> {
+    let has_fixup_order = match *data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: Fields::Named(ref fields),
+            ..
+        }) => parse_fields(fields)?
+            .iter()
+            .any(|f| f.fixup_order.is_some()),
+        _ => false,
+    };
+
+    let passes = match container.fixup_passes {
+        Some(passes) => passes,
+        None if has_fixup_order => 2,
+        None => 1,
+    };
+
+    if passes == 1 {
+        Ok(quote! {
+            #receiver.fixup(mutator);
+        })
+    } else {
+        Ok(quote! {
+            for _ in 0..#passes {
+                #receiver.fixup(mutator);
+            }
+        })
+    }
+}
+
+/// Returns the `debug_assert!`s checking a container's `#[lain(assert = "...")]` invariants. They
+/// must be placed where `self` is the container.
+pub(crate) fn gen_invariant_checks(invariants: &[Invariant]) -> syn::Result<TokenStream> {
+    let mut checks = TokenStream::new();
+
+    for invariant in invariants {
+        let expr = invariant.expr.parse::<TokenStream>()?;
+        let message = format!(
+            "invariant violated at {}: `{}`",
+            invariant.path,
+            invariant.expr.value()
+        );
+
+        checks.extend(quote_spanned! { invariant.expr.span() =>
+            debug_assert!(#expr, "{}", #message);
+        });
+    }
+
+    Ok(checks)
 }
@@ -30,15 +30,15 @@ pub struct NestedStruct {
 pub struct TestStruct {
     single_byte: u8,
 
-    #[bitfield(backing_type = "u8", bits = 1)]
+    #[lain(bitfield(backing_type = "u8", bits = 1))]
     bitfield_1: u8,
-    #[bitfield(backing_type = "u8", bits = 2)]
+    #[lain(bitfield(backing_type = "u8", bits = 2))]
     bitfield_2: u8,
-    #[bitfield(backing_type = "u8", bits = 1)]
+    #[lain(bitfield(backing_type = "u8", bits = 1))]
     bitfield_3: u8,
-    #[bitfield(backing_type = "u8", bits = 1)]
+    #[lain(bitfield(backing_type = "u8", bits = 1))]
     bitfield_4: u8,
-    #[bitfield(backing_type = "u8", bits = 3)]
+    #[lain(bitfield(backing_type = "u8", bits = 3))]
     bitfield_5: u8,
 
     uint32: u32,
@@ -78,7 +78,9 @@ fn bench_serialize_1000(c: &mut Criterion) {
         Benchmark::new("serialize", move |b| {
             let mut buffer = Vec::with_capacity(struct_size);
             b.iter(|| {
-                parent.binary_serialize::<_, BigEndian>(&mut buffer);
+                parent
+                    .binary_serialize::<_, BigEndian>(&mut buffer)
+                    .unwrap();
                 black_box(&buffer);
                 buffer.clear();
             });
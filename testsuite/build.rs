@@ -0,0 +1,9 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+
+    lain::kaitai::generate_file("formats/container.ksy", out_dir.join("container.rs")).unwrap();
+    println!("cargo:rerun-if-changed=formats/container.ksy");
+}
@@ -0,0 +1,12 @@
+//! Derive misuse which must be rejected at compile time with an error pointing at the offending
+//! tokens rather than a panic inside the derive. Each case in `tests/ui` is compiled and its
+//! errors are compared against the `.stderr` snapshot next to it, so both the message and its
+//! span are checked. Run the tests with `TRYBUILD=overwrite` to update the snapshots after
+//! changing a message. Cases which `#![deny(deprecated)]` check the warnings emitted for the
+//! deprecated standalone attributes the same way.
+
+#[test]
+fn derive_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
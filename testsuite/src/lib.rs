@@ -2,6 +2,9 @@
 
 extern crate lain;
 
+#[cfg(test)]
+mod compile_fail;
+
 #[cfg(test)]
 mod test {
     use lain::byteorder::{BigEndian, LittleEndian};
@@ -23,15 +26,15 @@ mod test {
     pub struct TestStruct {
         single_byte: u8,
 
-        #[bitfield(backing_type = "u8", bits = 1)]
+        #[lain(bitfield(backing_type = "u8", bits = 1))]
         bitfield_1: u8,
-        #[bitfield(backing_type = "u8", bits = 2)]
+        #[lain(bitfield(backing_type = "u8", bits = 2))]
         bitfield_2: u8,
-        #[bitfield(backing_type = "u8", bits = 1)]
+        #[lain(bitfield(backing_type = "u8", bits = 1))]
         bitfield_3: u8,
-        #[bitfield(backing_type = "u8", bits = 1)]
+        #[lain(bitfield(backing_type = "u8", bits = 1))]
         bitfield_4: u8,
-        #[bitfield(backing_type = "u8", bits = 3)]
+        #[lain(bitfield(backing_type = "u8", bits = 3))]
         bitfield_5: u8,
 
         uint32: u32,
@@ -60,7 +63,8 @@ mod test {
         };
 
         let mut buffer = Vec::with_capacity(expected_data.len());
-        test.binary_serialize::<_, LittleEndian>(&mut buffer);
+        test.binary_serialize::<_, LittleEndian>(&mut buffer)
+            .unwrap();
 
         compare_slices(&expected_data, &buffer);
     }
@@ -84,7 +88,7 @@ mod test {
         };
 
         let mut buffer = Vec::with_capacity(expected_data.len());
-        test.binary_serialize::<_, BigEndian>(&mut buffer);
+        test.binary_serialize::<_, BigEndian>(&mut buffer).unwrap();
 
         compare_slices(&expected_data, &buffer);
     }
@@ -115,7 +119,9 @@ mod test {
         };
 
         let mut buffer = Vec::with_capacity(expected_data.len());
-        parent.binary_serialize::<_, BigEndian>(&mut buffer);
+        parent
+            .binary_serialize::<_, BigEndian>(&mut buffer)
+            .unwrap();
 
         compare_slices(&expected_data, &buffer);
     }
@@ -146,7 +152,9 @@ mod test {
         };
 
         let mut buffer = Vec::with_capacity(expected_data.len());
-        parent.binary_serialize::<_, LittleEndian>(&mut buffer);
+        parent
+            .binary_serialize::<_, LittleEndian>(&mut buffer)
+            .unwrap();
 
         compare_slices(&expected_data, &buffer);
     }
@@ -172,7 +180,7 @@ mod test {
     fn test_ignored_fields() {
         #[derive(NewFuzzed, BinarySerialize, Clone)]
         struct IgnoredFieldsStruct {
-            #[fuzzer(ignore = true)]
+            #[lain(ignore = true)]
             ignored: u8,
         }
 
@@ -187,7 +195,7 @@ mod test {
     fn test_initializer() {
         #[derive(Default, NewFuzzed, BinarySerialize, Clone)]
         struct InitializedFieldsStruct {
-            #[fuzzer(initializer = "0x41")]
+            #[lain(initializer = "0x41")]
             initialized: u8,
         }
 
@@ -198,11 +206,106 @@ mod test {
         assert_eq!(initialized_struct.initialized, 0x41);
     }
 
+    #[test]
+    fn test_initializer_uses_mutator() {
+        fn random_timestamp<R: Rng>(
+            mutator: &mut Mutator<R>,
+            constraints: Option<&Constraints<u64>>,
+        ) -> u64 {
+            let constraints = constraints.unwrap();
+            mutator.gen_range(constraints.min.unwrap(), constraints.max.unwrap())
+        }
+
+        #[derive(Debug, PartialEq, NewFuzzed, Clone)]
+        struct Record {
+            #[lain(initializer = "mutator.gen_range(1, 10)")]
+            id: u8,
+            #[lain(
+                initializer = "random_timestamp(mutator, constraints)",
+                min = 1_500_000_000,
+                max = 1_600_000_000
+            )]
+            timestamp: u64,
+        }
+
+        let first = Record::new_fuzzed(&mut get_mutator(), None);
+        let second = Record::new_fuzzed(&mut get_mutator(), None);
+
+        // the same seed produces the same values
+        assert_eq!(first, second);
+        assert!(first.id >= 1 && first.id < 10);
+        assert!(first.timestamp >= 1_500_000_000 && first.timestamp < 1_600_000_000);
+    }
+
+    #[test]
+    fn test_new_fuzzed_panic_drops_initialized_fields() {
+        use std::cell::Cell;
+        use std::panic::{self, AssertUnwindSafe};
+
+        thread_local! {
+            static DROPS: Cell<usize> = Cell::new(0);
+        }
+
+        #[derive(Clone)]
+        struct DropCounter;
+
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                DROPS.with(|drops| drops.set(drops.get() + 1));
+            }
+        }
+
+        impl SerializedSize for DropCounter {
+            fn serialized_size(&self) -> usize {
+                0
+            }
+
+            fn min_nonzero_elements_size() -> usize {
+                0
+            }
+        }
+
+        fn failing_initializer() -> u32 {
+            panic!("initializer failed");
+        }
+
+        #[derive(NewFuzzed, Clone)]
+        struct Record {
+            #[lain(initializer = "DropCounter")]
+            counter: DropCounter,
+            #[lain(initializer = "failing_initializer()")]
+            id: u32,
+        }
+
+        #[derive(NewFuzzed, Clone)]
+        struct CompleteRecord {
+            #[lain(initializer = "DropCounter")]
+            counter: DropCounter,
+            id: u32,
+        }
+
+        let mut mutator = get_mutator();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            Record::new_fuzzed(&mut mutator, None);
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(DROPS.with(Cell::get), 1);
+
+        // fields of a successfully generated struct are only dropped with the struct
+        let record = CompleteRecord::new_fuzzed(&mut mutator, None);
+        assert_eq!(DROPS.with(Cell::get), 1);
+
+        drop(record);
+        assert_eq!(DROPS.with(Cell::get), 2);
+    }
+
     #[test]
     fn test_dynamic_array_limits() {
         #[derive(Default, NewFuzzed, Clone)]
         struct Foo {
-            #[fuzzer(min = 1, max = 10)]
+            #[lain(min = 1, max = 10)]
             bar: Vec<u32>,
         }
 
@@ -219,11 +322,65 @@ mod test {
         assert!(initialized_struct.bar.len() <= 10);
     }
 
+    #[test]
+    fn test_vec_element_constraints() {
+        use lain::types::ElementConstraints;
+
+        let constraints = Constraints {
+            min: Some(1),
+            max: Some(10),
+            element_constraints: Some(ElementConstraints::new(Constraints {
+                min: Some(5u32),
+                max: Some(50),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let element_constraints = constraints.elements::<u32>().unwrap();
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        for _ in 0..1000 {
+            let values = Vec::<u32>::new_fuzzed(&mut mutator, Some(&constraints));
+            assert!(!values.is_empty() && values.len() < 10);
+            assert!(
+                values.iter().all(|v| element_constraints.contains(v)),
+                "{:?}",
+                values
+            );
+        }
+
+        // constraints for another element type aren't applied
+        assert!(constraints.elements::<u8>().is_none());
+    }
+
+    #[test]
+    fn test_vec_element_attributes() {
+        #[derive(Default, Debug, NewFuzzed, Clone)]
+        struct Foo {
+            #[lain(min = 1, max = 10, element_min = 1, element_max_inclusive = 100)]
+            bar: Vec<u32>,
+            #[lain(element_min = "-4", element_max = 4)]
+            baz: Vec<i8>,
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        for _ in 0..1000 {
+            let foo = Foo::new_fuzzed(&mut mutator, None);
+            assert!(!foo.bar.is_empty() && foo.bar.len() < 10);
+            assert!(foo.bar.iter().all(|v| (1..=100).contains(v)), "{:?}", foo);
+            assert!(foo.baz.iter().all(|v| (-4..4).contains(v)), "{:?}", foo);
+        }
+    }
+
     #[test]
     fn generic_mutation_test() {
         #[derive(Default, Debug, NewFuzzed, BinarySerialize, Clone)]
         struct Foo {
-            #[fuzzer(min = 1, max = 10)]
+            #[lain(min = 1, max = 10)]
             bar: Vec<u32>,
             baz: u64,
             x: u32,
@@ -307,7 +464,7 @@ mod test {
         #[derive(BinarySerialize)]
         struct MyStruct {
             field1: u32,
-            #[byteorder(big)]
+            #[lain(byteorder(big))]
             field2: BigEndianStruct,
         }
 
@@ -322,7 +479,7 @@ mod test {
         {
             let buffer_ref: &mut [u8] = &mut serialized_buffer;
             let mut writer = BufWriter::new(buffer_ref);
-            s.binary_serialize::<_, LittleEndian>(&mut writer);
+            s.binary_serialize::<_, LittleEndian>(&mut writer).unwrap();
         }
 
         compare_slices(&expected, &serialized_buffer);
@@ -340,7 +497,7 @@ mod test {
         #[derive(BinarySerialize)]
         struct MyStruct {
             field1: u32,
-            #[byteorder(little)]
+            #[lain(byteorder(little))]
             field2: LittleEndianStruct,
         }
 
@@ -355,7 +512,7 @@ mod test {
         {
             let buffer_ref: &mut [u8] = &mut serialized_buffer;
             let mut writer = BufWriter::new(buffer_ref);
-            s.binary_serialize::<_, BigEndian>(&mut writer);
+            s.binary_serialize::<_, BigEndian>(&mut writer).unwrap();
         }
 
         compare_slices(&expected, &serialized_buffer);
@@ -366,7 +523,7 @@ mod test {
         let expected: [u8; 8] = [0xFF, 0x00, 0x00, 0x00, 0xAA, 0xBB, 0xCC, 0xDD];
 
         #[derive(BinarySerialize)]
-        #[serialized_size(0x4)]
+        #[lain(serialized_size = 0x4)]
         enum MyEnum {
             MyOtherStruct(MyOtherStruct),
         }
@@ -391,7 +548,7 @@ mod test {
         {
             let buffer_ref: &mut [u8] = &mut serialized_buffer;
             let mut writer = BufWriter::new(buffer_ref);
-            s.binary_serialize::<_, BigEndian>(&mut writer);
+            s.binary_serialize::<_, BigEndian>(&mut writer).unwrap();
         }
 
         compare_slices(&expected, &serialized_buffer);
@@ -414,7 +571,9 @@ mod test {
         {
             let buffer_ref: &mut [u8] = &mut serialized_buffer;
             let mut writer = BufWriter::new(buffer_ref);
-            instance.binary_serialize::<_, BigEndian>(&mut writer);
+            instance
+                .binary_serialize::<_, BigEndian>(&mut writer)
+                .unwrap();
         }
 
         compare_slices(&expected, &serialized_buffer);
@@ -447,7 +606,9 @@ mod test {
         {
             let buffer_ref: &mut [u8] = &mut serialized_buffer;
             let mut writer = BufWriter::new(buffer_ref);
-            instance.binary_serialize::<_, LittleEndian>(&mut writer);
+            instance
+                .binary_serialize::<_, LittleEndian>(&mut writer)
+                .unwrap();
         }
 
         compare_slices(&expected, &serialized_buffer);
@@ -502,7 +663,7 @@ mod test {
 
         fn fuzzer_routine<R: lain::rand::Rng>(
             mutator: &mut Mutator<R>,
-            _ctx: &mut LocalContext,
+            _ctx: &mut lain::driver::FuzzerContext<LocalContext>,
             global_ctx: Option<Arc<RwLock<GlobalContext>>>,
         ) -> Result<(), ()> {
             let global_ctx = global_ctx.unwrap();
@@ -569,97 +730,6136 @@ mod test {
     }
 
     #[test]
-    fn test_post_mutation_called() {
-        #[derive(NewFuzzed, Clone, FixupChildren, BinarySerialize)]
-        struct S {
-            #[fuzzer(ignore = true)]
-            pub post_mutation_called: bool,
+    fn driver_shards_iterations() {
+        use std::sync::{Arc, RwLock};
+
+        #[derive(Default)]
+        struct LocalContext {}
+
+        #[derive(Default, Debug)]
+        struct GlobalContext {
+            // (iteration, generated value)
+            executed: Vec<(usize, u32)>,
         }
 
-        impl Fixup for S {
-            fn fixup<R: lain::rand::Rng>(&mut self, _mutator: &mut Mutator<R>) {
-                println!("post mutation called!");
-                self.post_mutation_called = true;
-            }
+        fn fuzzer_routine<R: lain::rand::Rng>(
+            mutator: &mut Mutator<R>,
+            ctx: &mut lain::driver::FuzzerContext<LocalContext>,
+            global_ctx: Option<Arc<RwLock<GlobalContext>>>,
+        ) -> Result<(), ()> {
+            let value = u32::new_fuzzed(mutator, None);
+            global_ctx
+                .unwrap()
+                .write()
+                .unwrap()
+                .executed
+                .push((ctx.iteration(), value));
+
+            Ok(())
         }
 
-        let mut mutator = get_mutator();
+        fn run_shard(shard_index: usize, start: u64, end: u64) -> Vec<(usize, u32)> {
+            let mut driver = lain::driver::FuzzerDriver::<GlobalContext>::new(1);
+            let global_context: Arc<RwLock<GlobalContext>> = Default::default();
+            driver.set_global_context(global_context.clone());
+            driver.set_seed(0x1234);
+            driver.set_shard(shard_index, 2);
+            driver.set_to_reproduce_mode(start, end);
 
-        let instance = S::new_fuzzed(&mut mutator, None);
+            let driver = Arc::new(driver);
+            lain::driver::start_fuzzer(driver.clone(), fuzzer_routine);
+            driver.join_threads();
 
-        assert!(instance.post_mutation_called);
+            let executed = global_context.read().unwrap().executed.clone();
+            executed
+        }
+
+        let first_shard = run_shard(0, 0, 10);
+        let second_shard = run_shard(1, 0, 10);
+
+        let first_iterations: Vec<usize> = first_shard.iter().map(|e| e.0).collect();
+        let second_iterations: Vec<usize> = second_shard.iter().map(|e| e.0).collect();
+        assert_eq!(first_iterations, vec![0, 2, 4, 6, 8]);
+        assert_eq!(second_iterations, vec![1, 3, 5, 7, 9]);
+
+        // a single iteration from the second shard can be replayed on its own
+        assert_eq!(run_shard(1, 5, 6), vec![second_shard[2]]);
     }
 
     #[test]
-    fn test_mutator_switches_modes_properly() {
-        let mut mutator = get_mutator();
-        #[derive(Default, NewFuzzed, Mutatable, BinarySerialize, Clone)]
-        struct S {
-            first: u64,
-            second: u8,
+    fn test_enumerable() {
+        #[derive(Debug, Clone, PartialEq, Enumerable)]
+        enum Opcode {
+            Read,
+            Write,
+            Seek(bool),
+            Flags { sync: bool, append: bool },
         }
 
-        let mut instance = S::new_fuzzed(&mut mutator, None);
-        mutator.begin_new_corpus();
+        #[derive(Debug, Clone, PartialEq, Enumerable)]
+        struct Header {
+            opcode: Opcode,
+            #[lain(min = "-1", max = 2)]
+            level: i8,
+            #[lain(ignore)]
+            reserved: u32,
+        }
 
-        let mut counter = 0usize;
-        loop {
-            println!("{:?}", mutator.mode());
-            instance.mutate(&mut mutator, None);
-            mutator.begin_new_iteration();
-            counter += 1;
+        assert_eq!(Opcode::value_count(), 8);
+        assert_eq!(Header::value_count(), 24);
+        assert_eq!(Opcode::value_at(2), Opcode::Seek(false));
 
-            match counter {
-                2078 => assert_eq!(
-                    mutator.mode(),
-                    MutatorMode::WalkingBitFlip {
-                        bits: 63,
-                        current_idx: 1
-                    }
-                ),
-                2079 => assert_eq!(
-                    mutator.mode(),
-                    MutatorMode::WalkingBitFlip {
-                        bits: 64,
-                        current_idx: 0
-                    }
-                ),
-                2085 => assert_eq!(
-                    mutator.mode(),
-                    MutatorMode::InterestingValues { current_idx: 5 }
-                ),
-                2100 => assert_eq!(
-                    mutator.mode(),
-                    MutatorMode::WalkingBitFlip {
-                        bits: 2,
-                        current_idx: 6
-                    }
-                ),
-                2134 => {
-                    assert_eq!(mutator.mode(), MutatorMode::Havoc);
-                    break;
+        let headers: Vec<Header> = Header::all_values().collect();
+        for (i, header) in headers.iter().enumerate() {
+            assert!(header.level >= -1 && header.level < 2);
+            assert_eq!(header.reserved, 0);
+            assert!(!headers[..i].contains(header));
+        }
+
+        assert_eq!(bool::value_count(), 2);
+        assert_eq!(i8::all_values().next(), Some(-128));
+        assert_eq!(Option::<bool>::value_count(), 3);
+    }
+
+    #[test]
+    fn driver_sweeps_exhaustive_fields() {
+        use std::sync::{Arc, RwLock};
+
+        #[repr(u8)]
+        #[derive(Debug, Clone, Copy, PartialEq, NewFuzzed, BinarySerialize, Enumerable)]
+        enum Opcode {
+            Read,
+            Write,
+            Reset,
+        }
+
+        #[derive(Debug, Clone, NewFuzzed)]
+        struct Packet {
+            #[lain(exhaustive)]
+            opcode: Opcode,
+            payload: Vec<u8>,
+            #[lain(exhaustive)]
+            urgent: bool,
+            #[lain(exhaustive, min = 1, max = 5)]
+            priority: u32,
+        }
+
+        #[derive(Default)]
+        struct LocalContext {}
+
+        #[derive(Default, Debug)]
+        struct GlobalContext {
+            swept: Vec<(usize, Opcode, bool, u32)>,
+        }
+
+        fn fuzzer_routine<R: lain::rand::Rng>(
+            mutator: &mut Mutator<R>,
+            ctx: &mut lain::driver::FuzzerContext<LocalContext>,
+            global_ctx: Option<Arc<RwLock<GlobalContext>>>,
+        ) -> Result<(), ()> {
+            let packet = Packet::new_fuzzed(mutator, None);
+
+            if let Some(index) = ctx.exhaustive_index() {
+                global_ctx.unwrap().write().unwrap().swept.push((
+                    index,
+                    packet.opcode,
+                    packet.urgent,
+                    packet.priority,
+                ));
+            }
+
+            Ok(())
+        }
+
+        let combinations = Opcode::value_count() * bool::value_count() * 4;
+
+        let mut driver = lain::driver::FuzzerDriver::<GlobalContext>::new(4);
+        let global_context: Arc<RwLock<GlobalContext>> = Default::default();
+        driver.set_global_context(global_context.clone());
+        driver.set_exhaustive_sweep(combinations);
+
+        let driver = Arc::new(driver);
+        lain::driver::start_fuzzer(driver.clone(), fuzzer_routine);
+
+        let one_milli = std::time::Duration::from_millis(1);
+        while driver.exhaustive_sweep_progress() != (combinations, combinations)
+            || driver.num_iterations() < combinations * 2
+        {
+            std::thread::sleep(one_milli);
+        }
+
+        driver.signal_exit();
+        driver.join_threads();
+
+        let mut swept = global_context.read().unwrap().swept.clone();
+        swept.sort_by_key(|entry| entry.0);
+
+        // every combination is generated exactly once
+        assert_eq!(swept.len(), combinations);
+        for (i, entry) in swept.iter().enumerate() {
+            assert_eq!(entry.0, i);
+            assert!(!swept[..i]
+                .iter()
+                .any(|e| (e.1, e.2, e.3) == (entry.1, entry.2, entry.3)));
+        }
+    }
+
+    #[test]
+    fn driver_retains_inputs_from_feedback() {
+        use lain::feedback::{Feedback, Observation, UniqueResponseFeedback};
+        use std::sync::Arc;
+
+        #[derive(Default)]
+        struct LocalContext {}
+
+        // pretends the target answers with one of four status codes and crashes on status 3
+        fn fuzzer_routine<R: lain::rand::Rng>(
+            _mutator: &mut Mutator<R>,
+            ctx: &mut lain::driver::FuzzerContext<LocalContext>,
+            _global_ctx: Option<Arc<std::sync::RwLock<()>>>,
+        ) -> Result<(), ()> {
+            let status = (ctx.iteration() % 4) as u8;
+
+            ctx.buffer.push(status);
+            ctx.response.extend_from_slice(&[status, 0xFF]);
+
+            if status == 3 {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+
+        struct StatusFeedback(UniqueResponseFeedback);
+
+        impl FeedbackProvider for StatusFeedback {
+            fn evaluate(&self, input: &[u8], observation: &Observation) -> Feedback {
+                if observation.callback_failed {
+                    return Feedback::Crash;
                 }
-                _ => {}
+
+                self.0.evaluate(input, observation)
             }
         }
+
+        let mut driver = lain::driver::FuzzerDriver::<()>::new(2);
+        driver.set_feedback_provider(StatusFeedback(UniqueResponseFeedback::new(1)));
+
+        let driver = Arc::new(driver);
+        lain::driver::start_fuzzer(driver.clone(), fuzzer_routine);
+
+        while driver.num_iterations() < 100 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        driver.signal_exit();
+        driver.join_threads();
+
+        let mut retained = driver.retained_inputs().into_entries();
+        retained.sort();
+        assert_eq!(retained, vec![vec![0], vec![1], vec![2]]);
+
+        let crashes = driver.crashing_inputs();
+        assert!(crashes.len() >= 20);
+        assert!(crashes.entries().iter().all(|input| input == &[3]));
     }
 
     #[test]
-    fn test_string_mutation() {
-        // this test mostly ensures that the string generation does not panic
+    fn driver_adapts_to_rejected_choices() {
+        use lain::adaptive::{AdaptiveConfig, Choice, Outcome, ResponseClassifier};
+        use lain::feedback::Observation;
+        use std::sync::Arc;
+
+        #[derive(Debug, Copy, Clone, PartialEq, NewFuzzed, BinarySerialize)]
+        #[repr(u8)]
+        enum Command {
+            Read = 0,
+            Write = 1,
+            Reset = 2,
+        }
+
+        #[derive(Debug, NewFuzzed, BinarySerialize)]
+        struct Request {
+            command: Command,
+            verbose: bool,
+        }
+
+        // the target refuses resets and, for anything else, verbose requests
+        fn fuzzer_routine<R: lain::rand::Rng>(
+            mutator: &mut Mutator<R>,
+            ctx: &mut lain::driver::FuzzerContext<()>,
+            _global_ctx: Option<Arc<std::sync::RwLock<()>>>,
+        ) -> Result<(), ()> {
+            let request = Request::new_fuzzed(mutator, None);
+            ctx.serialize::<_, BigEndian>(&request);
+
+            let status = if request.command == Command::Reset {
+                1
+            } else if request.verbose {
+                2
+            } else {
+                0
+            };
+            ctx.response.push(status);
+
+            Ok(())
+        }
+
+        let classifier = ResponseClassifier::response_prefix(1)
+            .class(1, Outcome::Rejected("bad command"))
+            .class(2, Outcome::Rejected("bad flag"));
+
+        let observation = |response: &'static [u8]| Observation {
+            response,
+            response_status: None,
+            exit_status: None,
+            coverage: None,
+            callback_failed: false,
+        };
+        assert_eq!(
+            classifier.classify(&observation(&[0, 1])),
+            Outcome::Processed
+        );
+        assert_eq!(
+            classifier.classify(&observation(&[1])),
+            Outcome::Rejected("bad command")
+        );
+        assert_eq!(classifier.classify(&observation(&[])), Outcome::Ignored);
+
+        let mut driver = lain::driver::FuzzerDriver::<()>::new(2);
+        driver.set_adaptive_weighting(
+            classifier,
+            AdaptiveConfig {
+                rejection_threshold: 0.6,
+                ..Default::default()
+            },
+        );
+
+        let driver = Arc::new(driver);
+        lain::driver::start_fuzzer(driver.clone(), fuzzer_routine);
+
+        while driver.num_iterations() < 2000 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        driver.signal_exit();
+        driver.join_threads();
+
+        let weights = driver.adaptive_weights().unwrap();
+        let variant = |index| Choice::Variant {
+            enum_name: std::any::type_name::<Command>(),
+            index,
+        };
+        let verbose = |value| Choice::FieldValue {
+            container: std::any::type_name::<Request>(),
+            path: "verbose".to_string(),
+            value,
+        };
+
+        assert_eq!(weights.weight(&variant(2)), weights.config().min_weight);
+        assert_eq!(
+            weights.stats(&variant(2)).unwrap().dominant_rejection(),
+            Some(("bad command", 1.0))
+        );
+        assert!(weights.weight(&verbose(1)) < 1.0);
+        assert_eq!(weights.weight(&variant(0)), 1.0);
+        assert_eq!(weights.weight(&verbose(0)), 1.0);
+
+        // generation now mostly steers clear of what the target rejects
         let mut mutator = get_mutator();
+        mutator.set_adaptive_weights(Some(weights));
 
-        let mut utf8_str = Utf8String::new_fuzzed(&mut mutator, None);
-        println!("{:?}", utf8_str);
+        let mut resets = 0;
+        for _i in 0..1000 {
+            mutator.begin_new_iteration();
+            if Request::new_fuzzed(&mut mutator, None).command == Command::Reset {
+                resets += 1;
+            }
 
-        utf8_str.mutate(&mut mutator, None);
-        println!("{:?}", utf8_str);
+            assert_eq!(mutator.choices().len(), 2);
+        }
 
-        let mut ascii_str = AsciiString::new_fuzzed(&mut mutator, None);
-        println!("{:?}", ascii_str);
+        assert!(resets < 100, "{} resets generated", resets);
+    }
 
-        ascii_str.mutate(&mut mutator, None);
-        println!("{:?}", ascii_str);
+    #[test]
+    fn driver_pools_buffers() {
+        use lain::pool::{BufferPool, PoolStats};
+        use std::sync::Arc;
+
+        let mut pool = BufferPool::with_limits(2, 16);
+
+        let small = pool.reserve(4);
+        let large = pool.reserve(64);
+        assert!(small.capacity() >= 4 && large.capacity() >= 64);
+        pool.restore(small);
+        // too large to keep
+        pool.restore(large);
+        assert_eq!(pool.len(), 1);
+
+        // the pooled buffer is grown rather than allocating a new one
+        let mut grown = pool.reserve(12);
+        assert!(grown.capacity() >= 12);
+        grown.extend_from_slice(&[1, 2, 3]);
+        pool.restore(grown);
+        assert!(pool.reserve(0).is_empty());
+
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                reserved: 4,
+                reused: 2,
+                restored: 3,
+                discarded: 1,
+            }
+        );
+        assert_eq!(pool.stats().allocated(), 2);
+
+        // sends a header and a body per iteration
+        fn fuzzer_routine<R: lain::rand::Rng>(
+            _mutator: &mut Mutator<R>,
+            ctx: &mut lain::driver::FuzzerContext<()>,
+            _global_ctx: Option<Arc<std::sync::RwLock<()>>>,
+        ) -> Result<(), ()> {
+            let header = ctx.serialize_pooled::<_, BigEndian>(&0x1234u16);
+            let body = ctx.serialize_pooled::<_, BigEndian>(&vec![0xAAu8; 32]);
+            assert_eq!(header, [0x12, 0x34]);
+            assert_eq!(body.len(), 32);
+
+            ctx.buffer.extend_from_slice(&header);
+            ctx.pool.restore(header);
+            ctx.pool.restore(body);
+
+            Ok(())
+        }
+
+        let driver = Arc::new(lain::driver::FuzzerDriver::<()>::new(2));
+        lain::driver::start_fuzzer(driver.clone(), fuzzer_routine);
+
+        while driver.num_iterations() < 100 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        driver.signal_exit();
+        driver.join_threads();
+
+        // each thread only allocates during its first iteration
+        let snapshot = driver.stats().snapshot();
+        assert_eq!(snapshot.buffers_reserved, snapshot.iterations * 2);
+        assert!(snapshot.buffers_reserved - snapshot.buffers_reused <= 4);
+        assert!(snapshot
+            .to_json()
+            .contains(&format!("\"buffers_reused\":{},", snapshot.buffers_reused)));
+    }
+
+    #[test]
+    fn driver_minimizes_corpus() {
+        use lain::feedback::{Feedback, Observation, UniqueResponseFeedback};
+
+        // the target answers with the input's first byte and crashes on 0xFF
+        fn run_target(ctx: &mut lain::driver::FuzzerContext<()>) -> Result<(), ()> {
+            let status = ctx.buffer[0];
+            ctx.response.push(status);
+
+            if status == 0xFF {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+
+        struct StatusFeedback(UniqueResponseFeedback);
+
+        impl FeedbackProvider for StatusFeedback {
+            fn evaluate(&self, input: &[u8], observation: &Observation) -> Feedback {
+                if observation.callback_failed {
+                    return Feedback::Crash;
+                }
+
+                self.0.evaluate(input, observation)
+            }
+        }
+
+        let mut corpus = Corpus::new();
+        for input in &[
+            vec![1, 2, 3],
+            vec![2, 0],
+            vec![1],
+            vec![3, 3, 3, 3],
+            vec![2, 1],
+            vec![0xFF, 0],
+            vec![1],
+            vec![3, 3, 3],
+        ] {
+            corpus.push(input.clone());
+        }
+
+        let driver = lain::driver::FuzzerDriver::<()>::new(4);
+        let minimized = lain::driver::minimize_corpus(
+            &driver,
+            &corpus,
+            &StatusFeedback(UniqueResponseFeedback::new(1)),
+            run_target,
+        );
+
+        // the smallest input for each status is kept, and ties go to the smaller bytes
+        assert_eq!(
+            minimized.corpus.into_entries(),
+            vec![vec![1], vec![2, 0], vec![3, 3, 3]]
+        );
+        assert_eq!(minimized.crashing.into_entries(), vec![vec![0xFF, 0]]);
+    }
+
+    #[test]
+    fn driver_exports_campaign_stats() {
+        use std::io::{Read, Write};
+        use std::sync::Arc;
+
+        #[derive(Default)]
+        struct LocalContext {}
+
+        fn fuzzer_routine<R: lain::rand::Rng>(
+            _mutator: &mut Mutator<R>,
+            ctx: &mut lain::driver::FuzzerContext<LocalContext>,
+            _global_ctx: Option<Arc<std::sync::RwLock<()>>>,
+        ) -> Result<(), ()> {
+            ctx.buffer.extend_from_slice(&[0u8; 4]);
+
+            if ctx.iteration() % 2 == 0 {
+                Err(())
+            } else {
+                Ok(())
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("lain_stats_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut driver = lain::driver::FuzzerDriver::<()>::new(2);
+        driver.set_shard(1, 3);
+
+        let driver = Arc::new(driver);
+        let json_exporter = driver
+            .export_stats_json(&path, std::time::Duration::from_millis(5))
+            .unwrap();
+        let prometheus_exporter = driver.export_stats_prometheus("127.0.0.1:0").unwrap();
+
+        lain::driver::start_fuzzer(driver.clone(), fuzzer_routine);
+
+        while driver.num_iterations() < 100 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        driver.signal_exit();
+        driver.join_threads();
+
+        let snapshot = driver.stats().snapshot();
+        assert_eq!(snapshot.iterations, driver.num_iterations());
+        assert_eq!(snapshot.failed_iterations, driver.num_failed_iterations());
+        assert_eq!(snapshot.thread_iterations.len(), 2);
+        assert_eq!(
+            snapshot.thread_iterations.iter().sum::<usize>(),
+            snapshot.iterations
+        );
+        assert_eq!(snapshot.bytes_generated, snapshot.iterations * 4);
+        assert_eq!((snapshot.shard_index, snapshot.shard_count), (1, 3));
+
+        let mut stream = std::net::TcpStream::connect(prometheus_exporter.local_addr()).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        prometheus_exporter.stop();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("# TYPE lain_iterations_total counter\n"));
+        assert!(response.contains(&format!(
+            "lain_iterations_total{{shard=\"1\",shard_count=\"3\"}} {}\n",
+            snapshot.iterations
+        )));
+        assert!(response
+            .contains("lain_thread_iterations_total{shard=\"1\",shard_count=\"3\",thread=\"1\"}"));
+
+        // stopping the exporter writes a final line with the finished campaign's counters
+        json_exporter.stop();
+        let lines = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(lines
+            .lines()
+            .all(|line| line.starts_with("{\"timestamp\":") && line.ends_with('}')));
+        let last = lines.lines().last().unwrap();
+        assert!(last.contains(&format!("\"iterations\":{},", snapshot.iterations)));
+        assert!(last.contains(&format!(
+            "\"bytes_generated\":{},",
+            snapshot.bytes_generated
+        )));
+    }
+
+    #[test]
+    fn fuzz_macro_runs_target() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug, NewFuzzed, BinarySerialize)]
+        struct Message {
+            id: u32,
+            #[lain(min = 0, max = 8)]
+            payload: Vec<u8>,
+        }
+
+        lain::fuzz!(
+            Message,
+            threads = 2,
+            seed = 7,
+            iterations = 100,
+            |msg, ctx| {
+                CALLS.fetch_add(1, Ordering::SeqCst);
+                assert!(msg.payload.len() <= 8);
+                assert_eq!(
+                    ctx.serialize::<_, BigEndian>(msg).len(),
+                    4 + msg.payload.len()
+                );
+
+                if msg.id % 2 == 0 {
+                    FAILURES.fetch_add(1, Ordering::SeqCst);
+                    Err("even id")
+                } else {
+                    Ok(())
+                }
+            }
+        );
+
+        assert!(CALLS.load(Ordering::SeqCst) >= 100);
+        assert!(FAILURES.load(Ordering::SeqCst) > 0);
+
+        let options = lain::harness::HarnessOptions::default()
+            .threads(4)
+            .apply_args(
+                vec![
+                    "--test-threads=1",
+                    "--threads",
+                    "2",
+                    "--reproduce=10..12",
+                    "--seed",
+                    "9",
+                ]
+                .into_iter()
+                .map(String::from),
+            );
+        let debug = format!("{:?}", options);
+        assert!(debug.contains("threads: 2"));
+        assert!(debug.contains("seed: Some(9)"));
+        assert!(debug.contains("reproduce: Some((10, 12))"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn driver_retains_inputs_reaching_new_coverage() {
+        use lain::feedback::CoverageFeedback;
+        use lain::shmem::{CoverageMap, SHM_ENV_VAR};
+        use std::process::Command;
+        use std::sync::Arc;
+
+        let map = CoverageMap::new(lain::shmem::DEFAULT_MAP_SIZE).unwrap();
+        assert!(map.as_slice().iter().all(|&hits| hits == 0));
+
+        // instrumented targets find the map through the environment
+        let output = map
+            .configure(&mut Command::new("sh"))
+            .args(&["-c", &format!("printf %s \"${}\"", SHM_ENV_VAR)])
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            map.id().to_string()
+        );
+
+        #[derive(Default)]
+        struct LocalContext {}
+
+        // pretends to be a target which hits one of four edges depending on the input. hit counts
+        // are incremented, so anything left over from a previous iteration would look like new
+        // coverage
+        fn fuzzer_routine<R: lain::rand::Rng>(
+            _mutator: &mut Mutator<R>,
+            ctx: &mut lain::driver::FuzzerContext<LocalContext>,
+            _global_ctx: Option<Arc<std::sync::RwLock<()>>>,
+        ) -> Result<(), ()> {
+            if ctx.coverage.is_none() {
+                ctx.coverage = Some(CoverageMap::new(0x100).unwrap());
+            }
+
+            let edge = (ctx.iteration() % 4) as u8;
+            ctx.buffer.push(edge);
+            ctx.coverage.as_mut().unwrap().as_mut_slice()[edge as usize] += 1;
+
+            Ok(())
+        }
+
+        let mut driver = lain::driver::FuzzerDriver::<()>::new(2);
+        driver.set_feedback_provider(CoverageFeedback::new());
+
+        let driver = Arc::new(driver);
+        lain::driver::start_fuzzer(driver.clone(), fuzzer_routine);
+
+        while driver.num_iterations() < 100 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        driver.signal_exit();
+        driver.join_threads();
+
+        let mut retained = driver.retained_inputs().into_entries();
+        retained.sort();
+        assert_eq!(retained, vec![vec![0], vec![1], vec![2], vec![3]]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_executor() {
+        use lain::exec::{ExecConfig, ExecStatus, InputMode, ProcessExecutor};
+        use std::time::{Duration, Instant};
+
+        let capture = ExecConfig {
+            capture_output: true,
+            ..ExecConfig::default()
+        };
+
+        let mut output = vec![];
+        let mut executor = ProcessExecutor::new("cat", &[] as &[&str], capture.clone()).unwrap();
+        assert_eq!(
+            executor.run(b"stdin input", &mut output).unwrap(),
+            ExecStatus::Exited(0)
+        );
+        assert_eq!(output, b"stdin input");
+
+        // the input is rewritten for every run
+        output.clear();
+        executor.run(b"short", &mut output).unwrap();
+        assert_eq!(output, b"short");
+
+        let input_path = std::env::temp_dir().join(format!("lain-test-{}", std::process::id()));
+        let config = ExecConfig {
+            input: InputMode::File(input_path.clone()),
+            ..capture.clone()
+        };
+
+        output.clear();
+        let mut executor = ProcessExecutor::new("cat", &["@@"], config).unwrap();
+        executor.run(b"file input", &mut output).unwrap();
+        assert_eq!(output, b"file input");
+        std::fs::remove_file(input_path).unwrap();
+
+        let config = ExecConfig {
+            input: InputMode::Argv,
+            ..capture.clone()
+        };
+
+        output.clear();
+        let mut executor = ProcessExecutor::new("printf", &["%s", "@@"], config).unwrap();
+        executor.run(b"argv input", &mut output).unwrap();
+        assert_eq!(output, b"argv input");
+
+        let mut executor =
+            ProcessExecutor::new("sh", &["-c", "exit 3"], ExecConfig::default()).unwrap();
+        assert_eq!(
+            executor.run(&[], &mut output).unwrap(),
+            ExecStatus::Exited(3)
+        );
+        assert!(!executor.exit_status().unwrap().success());
+
+        let mut executor =
+            ProcessExecutor::new("sh", &["-c", "kill -SEGV $$"], ExecConfig::default()).unwrap();
+        assert_eq!(
+            executor.run(&[], &mut output).unwrap(),
+            ExecStatus::Crashed(11)
+        );
+
+        let config = ExecConfig {
+            timeout: Some(Duration::from_millis(50)),
+            ..ExecConfig::default()
+        };
+
+        let start = Instant::now();
+        let mut executor = ProcessExecutor::new("sleep", &["5"], config).unwrap();
+        assert_eq!(
+            executor.run(&[], &mut output).unwrap(),
+            ExecStatus::TimedOut
+        );
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    /// Stands in for an instrumented target when re-executed by `test_forkserver_executor`. It
+    /// speaks the forkserver protocol, but answers from its own process rather than forking:
+    /// inputs of "crash" die with SIGSEGV and everything else exits with the input's length.
+    #[cfg(unix)]
+    #[test]
+    #[ignore]
+    fn fake_forkserver() {
+        use std::fs::File;
+        use std::io::{Read, Write};
+        use std::os::unix::io::FromRawFd;
+
+        if std::env::var_os("LAIN_FAKE_FORKSERVER").is_none() {
+            return;
+        }
+
+        let mut control = unsafe { File::from_raw_fd(198) };
+        let mut status = unsafe { File::from_raw_fd(199) };
+        let mut stdin = unsafe { File::from_raw_fd(0) };
+
+        status.write_all(&0u32.to_ne_bytes()).unwrap();
+
+        let mut command = [0u8; 4];
+        while control.read_exact(&mut command).is_ok() {
+            status.write_all(&std::process::id().to_ne_bytes()).unwrap();
+
+            let mut input = vec![];
+            stdin.read_to_end(&mut input).unwrap();
+
+            let wait_status: u32 = if input == b"crash" {
+                11
+            } else {
+                (input.len() as u32) << 8
+            };
+
+            status.write_all(&wait_status.to_ne_bytes()).unwrap();
+        }
+
+        std::process::exit(0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_forkserver_executor() {
+        use lain::exec::{ExecConfig, ExecStatus, ProcessExecutor};
+
+        let config = ExecConfig {
+            forkserver: true,
+            ..ExecConfig::default()
+        };
+
+        let mut executor = ProcessExecutor::new(
+            std::env::current_exe().unwrap(),
+            &["--ignored", "--exact", "test::fake_forkserver"],
+            config,
+        )
+        .unwrap();
+        executor.env("LAIN_FAKE_FORKSERVER", "1");
+
+        let mut output = vec![];
+        assert_eq!(
+            executor.run(b"abc", &mut output).unwrap(),
+            ExecStatus::Exited(3)
+        );
+        assert_eq!(
+            executor.run(b"crash", &mut output).unwrap(),
+            ExecStatus::Crashed(11)
+        );
+        assert_eq!(
+            executor.run(b"hello", &mut output).unwrap(),
+            ExecStatus::Exited(5)
+        );
+
+        // targets without a forkserver are reported rather than hanging the fuzzer
+        let config = ExecConfig {
+            forkserver: true,
+            ..ExecConfig::default()
+        };
+
+        let mut executor = ProcessExecutor::new("true", &[] as &[&str], config).unwrap();
+        assert!(executor.run(b"abc", &mut output).is_err());
+    }
+
+    #[test]
+    fn test_coverage_feedback_buckets() {
+        use lain::feedback::{CoverageFeedback, Feedback, Observation};
+
+        let feedback = CoverageFeedback::new();
+        let observe = |coverage: &[u8]| {
+            let observation = Observation {
+                response: &[],
+                response_status: None,
+                exit_status: None,
+                coverage: Some(coverage),
+                callback_failed: false,
+            };
+
+            feedback.evaluate(&[], &observation)
+        };
+
+        assert_eq!(observe(&[1, 0, 0]), Feedback::Interesting);
+        assert_eq!(observe(&[1, 0, 0]), Feedback::Boring);
+        // a new edge
+        assert_eq!(observe(&[1, 0, 1]), Feedback::Interesting);
+        // a new hit count bucket for a known edge
+        assert_eq!(observe(&[4, 0, 1]), Feedback::Interesting);
+        // same bucket as 4
+        assert_eq!(observe(&[7, 0, 1]), Feedback::Boring);
+        assert_eq!(observe(&[0, 0, 0]), Feedback::Boring);
+    }
+
+    #[test]
+    fn test_postprocess_pipeline() {
+        use lain::postprocess::{PostprocessPipeline, Xor};
+
+        let mut pipeline = PostprocessPipeline::new();
+        pipeline.add_stage(Xor::new(&[0xFF, 0x00]));
+        // length-prefixed record framing
+        pipeline.add_stage(|input: &[u8], output: &mut Vec<u8>| {
+            output.push(input.len() as u8);
+            output.extend_from_slice(input);
+        });
+
+        let mut data = vec![0x11, 0x22, 0x33];
+        let mut scratch = vec![];
+        pipeline.run(&mut data, &mut scratch);
+
+        compare_slices(&[0x03, 0xEE, 0x22, 0xCC], &data);
+    }
+
+    #[test]
+    fn test_postprocess_zlib_and_base64() {
+        use lain::postprocess::{Base64Encode, PostprocessPipeline, ZlibCompress};
+        use std::io::Read;
+
+        let input = b"lain lain lain lain lain lain lain lain".to_vec();
+
+        let mut pipeline = PostprocessPipeline::new();
+        pipeline.add_stage(ZlibCompress::default());
+
+        let mut data = input.clone();
+        let mut scratch = vec![];
+        pipeline.run(&mut data, &mut scratch);
+
+        // a zlib header for the default compression level
+        compare_slices(&[0x78, 0x9C], &data[..2]);
+        assert!(data.len() < input.len());
+
+        let mut decompressed = vec![];
+        flate2::read::ZlibDecoder::new(&data[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, input);
+
+        let mut pipeline = PostprocessPipeline::new();
+        pipeline.add_stage(Base64Encode);
+
+        // every padding length
+        for (input, expected) in &[
+            (&b"lain"[..], &b"bGFpbg=="[..]),
+            (&b"lain!"[..], &b"bGFpbiE="[..]),
+            (&b"lain!?"[..], &b"bGFpbiE/"[..]),
+            (&b""[..], &b""[..]),
+        ] {
+            let mut data = input.to_vec();
+            pipeline.run(&mut data, &mut scratch);
+
+            compare_slices(expected, &data);
+        }
+
+        // both stages together, as a target which takes base64 encoded zlib streams would
+        let mut pipeline = PostprocessPipeline::new();
+        pipeline.add_stage(ZlibCompress::new(9));
+        pipeline.add_stage(Base64Encode);
+
+        let mut data = input.clone();
+        pipeline.run(&mut data, &mut scratch);
+
+        let compressed = base64::decode(&data).unwrap();
+        let mut decompressed = vec![];
+        flate2::read::ZlibDecoder::new(&compressed[..])
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, input);
+    }
+
+    #[test]
+    fn test_post_mutation_called() {
+        #[derive(NewFuzzed, Clone, FixupChildren, BinarySerialize)]
+        struct S {
+            #[lain(ignore = true)]
+            pub post_mutation_called: bool,
+        }
+
+        impl Fixup for S {
+            fn fixup<R: lain::rand::Rng>(&mut self, _mutator: &mut Mutator<R>) {
+                println!("post mutation called!");
+                self.post_mutation_called = true;
+            }
+        }
+
+        let mut mutator = get_mutator();
+
+        let instance = S::new_fuzzed(&mut mutator, None);
+
+        assert!(instance.post_mutation_called);
+    }
+
+    #[test]
+    fn test_mutator_switches_modes_properly() {
+        let mut mutator = get_mutator();
+        #[derive(Default, NewFuzzed, Mutatable, BinarySerialize, Clone)]
+        struct S {
+            first: u64,
+            second: u8,
+        }
+
+        let mut instance = S::new_fuzzed(&mut mutator, None);
+        mutator.begin_new_corpus();
+
+        let mut counter = 0usize;
+        loop {
+            println!("{:?}", mutator.mode());
+            instance.mutate(&mut mutator, None);
+            mutator.begin_new_iteration();
+            counter += 1;
+
+            match counter {
+                2078 => assert_eq!(
+                    mutator.mode(),
+                    MutatorMode::WalkingBitFlip {
+                        bits: 63,
+                        current_idx: 1
+                    }
+                ),
+                2079 => assert_eq!(
+                    mutator.mode(),
+                    MutatorMode::WalkingBitFlip {
+                        bits: 64,
+                        current_idx: 0
+                    }
+                ),
+                2085 => assert_eq!(
+                    mutator.mode(),
+                    MutatorMode::InterestingValues { current_idx: 5 }
+                ),
+                2100 => assert_eq!(
+                    mutator.mode(),
+                    MutatorMode::WalkingBitFlip {
+                        bits: 2,
+                        current_idx: 6
+                    }
+                ),
+                2134 => {
+                    assert_eq!(mutator.mode(), MutatorMode::Havoc);
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_numeric_strategies() {
+        let mut mutator = get_mutator();
+
+        let mut with_strategy = |strategy: NumericStrategy, value: i64| -> Vec<i64> {
+            mutator.set_config(MutatorConfig {
+                numeric_strategies: vec![strategy],
+                ..MutatorConfig::default()
+            });
+
+            (0..100)
+                .map(|_| {
+                    let mut num = value;
+                    mutator.mutate(&mut num);
+                    num
+                })
+                .collect()
+        };
+
+        for num in with_strategy(NumericStrategy::Arithmetic, 100) {
+            assert!(num != 100 && (85..=115).contains(&num));
+        }
+
+        for num in with_strategy(NumericStrategy::ScaleByTwo, -100) {
+            assert!(num == -200 || num == -50);
+        }
+
+        for num in with_strategy(NumericStrategy::PowerOfTwoBoundary, 1000) {
+            assert!((1023..=1025).contains(&num));
+        }
+
+        for num in with_strategy(NumericStrategy::PowerOfTwoBoundary, -100) {
+            assert!((-129..=-127).contains(&num));
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_config(MutatorConfig {
+            numeric_strategies: vec![NumericStrategy::BoundaryWrap],
+            ..MutatorConfig::default()
+        });
+
+        let mut near_min = false;
+        let mut near_max = false;
+        for _ in 0..100 {
+            let mut num = 100u8;
+            mutator.mutate(&mut num);
+            assert!(num >= 251 || num <= 4);
+
+            let mut signed = 0i16;
+            mutator.mutate(&mut signed);
+            assert!(signed >= i16::max_value() - 4 || signed <= i16::min_value() + 4);
+
+            near_min |= num <= 4;
+            near_max |= num >= 251;
+        }
+        assert!(near_min && near_max);
+
+        let mut config = MutatorConfig {
+            numeric_strategies: vec![NumericStrategy::InterestingValue],
+            ..MutatorConfig::default()
+        };
+        config.add_interesting_value(0x1337u16);
+        mutator.set_config(config);
+
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..1000 {
+            let mut num = 100u32;
+            mutator.mutate(&mut num);
+            seen.insert(num);
+
+            // 0x1337 doesn't fit in a byte, so it's never used for one
+            let mut byte = 100u8;
+            mutator.mutate(&mut byte);
+            assert_ne!(byte, 0x37);
+        }
+
+        for expected in &[
+            0,
+            1,
+            0xffff_ffff,
+            0xffff_fffe,
+            0x7fff_ffff,
+            0x8000_0000,
+            4096,
+            0x1337,
+        ] {
+            assert!(seen.contains(expected), "never saw {:#x}", expected);
+        }
+
+        // no strategies leaves numbers alone
+        mutator.set_config(MutatorConfig {
+            numeric_strategies: vec![],
+            ..MutatorConfig::default()
+        });
+
+        let mut num = 100u32;
+        mutator.mutate(&mut num);
+        assert_eq!(num, 100);
+    }
+
+    #[test]
+    fn test_stacked_mutations() {
+        #[derive(Default, NewFuzzed, Mutatable, BinarySerialize, Clone, PartialEq)]
+        struct Inner {
+            x: u32,
+            y: u32,
+        }
+
+        #[derive(Default, NewFuzzed, Mutatable, BinarySerialize, Clone, PartialEq)]
+        struct S {
+            a: u32,
+            b: u32,
+            c: u32,
+            d: u32,
+            inner: Inner,
+        }
+
+        fn changed_fields(s: &S) -> usize {
+            [s.a, s.b, s.c, s.d, s.inner.x, s.inner.y]
+                .iter()
+                .filter(|&&field| field != 0)
+                .count()
+        }
+
+        // a single pass only mutates the targeted field
+        let mut mutator = get_mutator();
+        for _ in 0..100 {
+            let mut s = S::default();
+            s.mutate(&mut mutator, None);
+            assert!(changed_fields(&s) <= 1);
+            mutator.begin_new_iteration();
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_config(MutatorConfig {
+            stacked_mutation_chance: 100.0,
+            max_stacked_mutations: 8,
+            ..MutatorConfig::default()
+        });
+
+        let mut most_changed = 0;
+        for _ in 0..100 {
+            let mut s = S::default();
+            s.mutate(&mut mutator, None);
+            most_changed = std::cmp::max(most_changed, changed_fields(&s));
+            mutator.begin_new_iteration();
+
+            // stacked passes don't change the number of fields the corpus state counts
+            assert_eq!(mutator.get_corpus_state().target_total_fields, 6);
+        }
+
+        assert!(most_changed > 1);
+
+        // deterministic modes always make a single pass
+        let mut mutator = get_mutator();
+        mutator.set_config(MutatorConfig {
+            stacked_mutation_chance: 100.0,
+            ..MutatorConfig::default()
+        });
+        mutator.set_mode(MutatorMode::WalkingBitFlip {
+            bits: 1,
+            current_idx: 0,
+        });
+
+        let mut s = S::default();
+        s.mutate(&mut mutator, None);
+        assert_eq!(s.a, 1);
+        assert_eq!(changed_fields(&s), 1);
+    }
+
+    #[test]
+    fn test_string_mutation() {
+        // this test mostly ensures that the string generation does not panic
+        let mut mutator = get_mutator();
+
+        let mut utf8_str = Utf8String::new_fuzzed(&mut mutator, None);
+        println!("{:?}", utf8_str);
+
+        utf8_str.mutate(&mut mutator, None);
+        println!("{:?}", utf8_str);
+
+        let mut ascii_str = AsciiString::new_fuzzed(&mut mutator, None);
+        println!("{:?}", ascii_str);
+
+        ascii_str.mutate(&mut mutator, None);
+        println!("{:?}", ascii_str);
+
+        // empty strings and many rounds of growth should be handled as well
+        let mut utf8_str = Utf8String::new("");
+        let mut ascii_str = AsciiString::new("");
+        for _i in 0..100 {
+            utf8_str.mutate(&mut mutator, None);
+            ascii_str.mutate(&mut mutator, None);
+        }
+
+        assert!(!utf8_str.diff(&Utf8String::new("")).is_empty());
+        assert!(!ascii_str.diff(&AsciiString::new("")).is_empty());
+    }
+
+    #[test]
+    fn test_structured_diff() {
+        #[derive(Debug, Clone, StructuredDiff)]
+        enum Command {
+            Reset,
+            Write(u32),
+        }
+
+        #[derive(Debug, Clone, StructuredDiff)]
+        struct Header {
+            length: u16,
+            command: Command,
+        }
+
+        #[derive(Debug, Clone, StructuredDiff)]
+        struct Message {
+            header: Header,
+            data: Vec<u8>,
+        }
+
+        let original = Message {
+            header: Header {
+                length: 2,
+                command: Command::Write(0x10),
+            },
+            data: vec![0xAA, 0xBB],
+        };
+
+        assert!(original.diff(&original.clone()).is_empty());
+
+        let mut changed = original.clone();
+        changed.header.command = Command::Write(0x20);
+        changed.data[1] = 0xCC;
+        changed.data.push(0xDD);
+
+        let deltas = original.diff(&changed);
+        let paths: Vec<&str> = deltas.iter().map(|d| d.path.as_ref()).collect();
+        assert_eq!(paths, vec!["header.command.0", "data", "data[1]"]);
+        assert_eq!(deltas[1].kind, DeltaKind::Length { old: 2, new: 3 });
+        assert_eq!(deltas[0].root_field(), "header");
+
+        changed.header.command = Command::Reset;
+        let deltas = original.diff(&changed);
+        assert_eq!(
+            deltas[0].kind,
+            DeltaKind::Variant {
+                old: "Write".to_string(),
+                new: "Reset".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_narrow_integer_serialization() {
+        #[derive(Debug, NewFuzzed, Mutatable, BinarySerialize)]
+        struct MediaChunk {
+            #[lain(min = 1, max = 0x100)]
+            length: U24,
+            timestamp: U48,
+        }
+
+        let chunk = MediaChunk {
+            length: U24::new(0xAABBCC),
+            timestamp: U48::new(0x1122_3344_5566),
+        };
+
+        let mut buffer = vec![];
+        chunk.binary_serialize::<_, BigEndian>(&mut buffer).unwrap();
+        compare_slices(
+            &[0xAA, 0xBB, 0xCC, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66],
+            &buffer,
+        );
+        assert_eq!(chunk.serialized_size(), 9);
+
+        let mut mutator = get_mutator();
+        for _i in 0..1000 {
+            let mut chunk = MediaChunk::new_fuzzed(&mut mutator, None);
+            assert!(chunk.length.value() <= U24::MAX);
+
+            chunk.mutate(&mut mutator, None);
+            assert!(chunk.length.value() <= U24::MAX);
+            assert!(chunk.timestamp.value() <= U48::MAX);
+        }
+    }
+
+    #[test]
+    fn test_serialization_reports_short_writes() {
+        #[derive(Debug, Clone, NewFuzzed, BinarySerialize)]
+        struct Message {
+            id: u32,
+            #[lain(min = 1, max = 32)]
+            payload: Vec<u8>,
+        }
+
+        let mut mutator = get_mutator();
+        for _i in 0..100 {
+            let message = Message::new_fuzzed(&mut mutator, None);
+
+            let mut buffer = vec![];
+            let bytes_written = message
+                .binary_serialize::<_, BigEndian>(&mut buffer)
+                .unwrap();
+            assert_eq!(bytes_written, buffer.len());
+            assert_eq!(bytes_written, message.serialized_size());
+
+            // a fixed-size slice behaves like a socket that can't accept all of the data
+            let mut short_buffer = vec![0u8; message.serialized_size() - 1];
+            let mut writer: &mut [u8] = &mut short_buffer;
+            let err = message
+                .binary_serialize::<_, BigEndian>(&mut writer)
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+        }
+    }
+
+    #[test]
+    fn test_enum_discriminant_serialization() {
+        #[repr(u32)]
+        #[derive(Debug, Copy, Clone, PartialEq, NewFuzzed, BinarySerialize)]
+        enum Cmd {
+            Read = 0x10,
+            Write = 0x20,
+        }
+
+        #[derive(Debug, Clone, NewFuzzed, BinarySerialize)]
+        struct Packet {
+            cmd: Cmd,
+            #[lain(byteorder(little))]
+            little_cmd: Cmd,
+        }
+
+        let packet = Packet {
+            cmd: Cmd::Read,
+            little_cmd: Cmd::Write,
+        };
+
+        let mut buffer = vec![];
+        packet
+            .binary_serialize::<_, BigEndian>(&mut buffer)
+            .unwrap();
+        compare_slices(&[0x00, 0x00, 0x00, 0x10, 0x20, 0x00, 0x00, 0x00], &buffer);
+        assert_eq!(packet.serialized_size(), 8);
+
+        let mut mutator = get_mutator();
+        for _i in 0..100 {
+            let cmd = Cmd::new_fuzzed(&mut mutator, None);
+            assert!(cmd == Cmd::Read || cmd == Cmd::Write);
+        }
+    }
+
+    #[test]
+    fn test_undo_log() {
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable, StructuredDiff)]
+        struct Header {
+            magic: u32,
+            length: u16,
+        }
+
+        let mut mutator = get_mutator();
+        let original = Header::new_fuzzed(&mut mutator, None);
+        let mut header = original.clone();
+        let mut history = UndoLog::new(2);
+
+        // mutate until we have more changes recorded than the log can hold
+        let mut changes = 0;
+        let mut snapshots = vec![header.clone()];
+        while changes < 3 {
+            mutator.begin_new_iteration();
+            if !history.mutate(&mut header, &mut mutator, None).is_empty() {
+                changes += 1;
+            }
+            snapshots.push(header.clone());
+        }
+
+        assert_eq!(history.len(), 2);
+
+        let last = snapshots.len() - 1;
+        let deltas = history.undo(&mut header).unwrap();
+        assert!(!deltas.is_empty());
+        assert_eq!(header, snapshots[last - 1]);
+        assert!(history.undo(&mut header).is_some());
+        assert_eq!(header, snapshots[last - 2]);
+
+        // the oldest mutation was evicted
+        assert!(history.undo(&mut header).is_none());
+        assert_eq!(header, snapshots[last - 2]);
+
+        // mutations which don't change anything are recorded too, so undo always reverts the
+        // last one
+        let mut history = UndoLog::new(4);
+        let mut constant = [0u8; 0];
+        history.mutate(&mut constant, &mut mutator, None);
+        assert_eq!(history.len(), 1);
+
+        // types without a StructuredDiff impl report no changes but are still reverted
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable)]
+        struct Plain {
+            value: u64,
+        }
+
+        let mut history = UndoLog::new(4);
+        let mut plain = Plain::new_fuzzed(&mut mutator, None);
+        let before = plain.clone();
+        while plain == before {
+            mutator.begin_new_iteration();
+            history.clear();
+            history.mutate(&mut plain, &mut mutator, None);
+        }
+
+        assert_eq!(history.undo(&mut plain), Some(vec![]));
+        assert_eq!(plain, before);
+
+        // a log without capacity doesn't take snapshots
+        #[derive(Debug, NewFuzzed, Mutatable)]
+        struct Uncloned {
+            value: u64,
+        }
+
+        impl Clone for Uncloned {
+            fn clone(&self) -> Self {
+                panic!("a log without capacity cloned the value");
+            }
+        }
+
+        let mut history = UndoLog::new(0);
+        let mut uncloned = Uncloned::new_fuzzed(&mut mutator, None);
+        for _i in 0..10 {
+            mutator.begin_new_iteration();
+            assert!(history.mutate(&mut uncloned, &mut mutator, None).is_empty());
+        }
+
+        assert!(history.is_empty());
+        assert!(history.undo(&mut uncloned).is_none());
+    }
+
+    #[test]
+    fn test_inclusive_max_constraints() {
+        #[derive(Debug, Clone, NewFuzzed, Mutatable)]
+        struct Frame {
+            #[lain(min = 1, max_inclusive = 3)]
+            channel: u8,
+            #[lain(min = 250, max_inclusive = 255)]
+            priority: u8,
+            #[lain(min = "-2", max_inclusive = 2, bounds = "open")]
+            offset: i8,
+            #[lain(min = 0xfffff0, max_inclusive = 0xffffff)]
+            address: U24,
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let mut seen_max = [false; 4];
+        for _i in 0..500 {
+            mutator.begin_new_iteration();
+            let frame = Frame::new_fuzzed(&mut mutator, None);
+
+            assert!(frame.channel >= 1 && frame.channel <= 3);
+            assert!(frame.priority >= 250);
+            assert!(frame.offset > -2 && frame.offset <= 2);
+            assert!(frame.address.value() >= 0xfffff0 && frame.address.value() <= 0xffffff);
+
+            seen_max[0] |= frame.channel == 3;
+            seen_max[1] |= frame.priority == 255;
+            seen_max[2] |= frame.offset == 2;
+            seen_max[3] |= frame.address.value() == 0xffffff;
+        }
+
+        assert_eq!(seen_max, [true; 4]);
+
+        let lengths = Constraints {
+            min: Some(2),
+            max: Some(4),
+            bounds: Bounds::Closed,
+            ..Default::default()
+        };
+        assert_eq!(lengths.inclusive_max(), Some(4));
+        assert_eq!(lengths.exclusive_max(), Some(5));
+
+        let mut longest = 0;
+        for _i in 0..200 {
+            let entries = Vec::<u16>::new_fuzzed(&mut mutator, Some(&lengths));
+            assert!(entries.len() >= 2 && entries.len() <= 4);
+            longest = std::cmp::max(longest, entries.len());
+        }
+
+        assert_eq!(longest, 4);
+
+        #[derive(Debug, Clone, Enumerable)]
+        struct Selector {
+            #[lain(min = 1, max_inclusive = 3)]
+            channel: u8,
+            #[lain(min = "-2", max_inclusive = 2, bounds = "open")]
+            offset: i8,
+        }
+
+        // enumeration covers the inclusive max but not the excluded min
+        assert_eq!(Selector::value_count(), 3 * 4);
+        let offsets: std::collections::BTreeSet<i8> = Selector::all_values()
+            .map(|selector| selector.offset)
+            .collect();
+        assert_eq!(offsets.into_iter().collect::<Vec<_>>(), vec![-1, 0, 1, 2]);
+        assert!(Selector::all_values().any(|selector| selector.channel == 3));
+    }
+
+    #[test]
+    fn test_float_constraints() {
+        #[derive(Debug, Clone, NewFuzzed, Mutatable)]
+        struct Reading {
+            #[lain(min = 0.0, max = 100.0, bounds = "closed")]
+            percentage: f32,
+            #[lain(min = "-90.0", max = 90.0, bounds = "open")]
+            latitude: f64,
+            #[lain(min = 1.5, max = 2.5, weighted = "max")]
+            scale: f64,
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let check = |reading: &Reading| {
+            assert!(reading.percentage >= 0.0 && reading.percentage <= 100.0);
+            assert!(reading.latitude > -90.0 && reading.latitude < 90.0);
+            assert!(reading.scale >= 1.5 && reading.scale < 2.5);
+        };
+
+        let mut fractional = false;
+        for _i in 0..1000 {
+            mutator.begin_new_iteration();
+            let mut reading = Reading::new_fuzzed(&mut mutator, None);
+            check(&reading);
+
+            fractional |= reading.latitude.fract() != 0.0;
+
+            mutator.begin_new_iteration();
+            reading.mutate(&mut mutator, None);
+            check(&reading);
+        }
+
+        // floats should not be truncated to integers
+        assert!(fractional);
+
+        let closed = Constraints {
+            min: Some(1.0),
+            max: Some(1.0),
+            bounds: Bounds::Closed,
+            ..Default::default()
+        };
+        assert_eq!(f64::new_fuzzed(&mut mutator, Some(&closed)), 1.0);
+        assert!(closed.contains(&1.0));
+        assert!(!closed.contains(&std::f64::NAN));
+    }
+
+    #[test]
+    fn test_generation_profiles() {
+        #[derive(Debug, NewFuzzed, Clone)]
+        struct Packet {
+            #[lain(min = 10, max = 20)]
+            length: u32,
+            #[lain(min = 1, max = 4)]
+            count: u8,
+        }
+
+        let count_violations = |packet: &Packet| {
+            let mut violations = 0;
+            if packet.length < 10 || packet.length >= 20 {
+                violations += 1;
+            }
+
+            if packet.count < 1 || packet.count >= 4 {
+                violations += 1;
+            }
+
+            violations
+        };
+
+        let mut mutator = get_mutator();
+
+        mutator.set_profile(GenerationProfile::Valid);
+        for _i in 0..1000 {
+            mutator.begin_new_iteration();
+            let packet = Packet::new_fuzzed(&mut mutator, None);
+            assert_eq!(count_violations(&packet), 0);
+        }
+
+        mutator.set_profile(GenerationProfile::SemiValid);
+        for _i in 0..1000 {
+            mutator.begin_new_iteration();
+            let packet = Packet::new_fuzzed(&mut mutator, None);
+            assert!(count_violations(&packet) <= 1);
+        }
+
+        mutator.set_profile(GenerationProfile::Hostile);
+        let mut hostile_violations = 0;
+        for _i in 0..1000 {
+            mutator.begin_new_iteration();
+            let packet = Packet::new_fuzzed(&mut mutator, None);
+            hostile_violations += count_violations(&packet);
+        }
+
+        assert!(hostile_violations > 0);
+    }
+
+    #[test]
+    fn test_fuzzed_debug() {
+        #[derive(Debug, Clone, Copy, FuzzedDebug)]
+        enum Kind {
+            Read,
+            Write,
+        }
+
+        #[derive(Debug, Clone, FuzzedDebug)]
+        enum Body {
+            Raw(Vec<u8>),
+            Flags { urgent: bool },
+        }
+
+        #[derive(Debug, Clone, FuzzedDebug)]
+        struct Packet {
+            length: u16,
+            name: AsciiString,
+            body: Body,
+        }
+
+        let packet = Packet {
+            length: 0x10,
+            name: AsciiString::new("lain"),
+            body: Body::Raw(vec![0xAA, 0xBB, 0xCC]),
+        };
+
+        let expected = "\
+Packet
+  length: u16 (2 bytes) = 16 (0x10)
+  name: AsciiString (4 bytes) = \"lain\"
+  body: Body = Raw
+    body.0: Vec<u8> (3 bytes) = AA BB CC
+";
+        assert_eq!(packet.to_fuzzed_debug_string(), expected);
+
+        let flags = Body::Flags { urgent: true };
+        assert_eq!(
+            flags.to_fuzzed_debug_string(),
+            "Body = Flags\n  urgent: bool (1 bytes) = true\n"
+        );
+
+        assert_eq!(Kind::Read.to_fuzzed_debug_string(), "Kind = Read\n");
+        assert_eq!(Kind::Write.to_fuzzed_debug_string(), "Kind = Write\n");
+    }
+
+    mod container {
+        include!(concat!(env!("OUT_DIR"), "/container.rs"));
+    }
+
+    #[test]
+    fn test_kaitai_generated_model() {
+        use container::*;
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let mut constraints = Constraints::default();
+        constraints.max_size = Some(0x400);
+
+        let mut records = 0;
+        for _ in 0..50 {
+            let mut instance = Container::new_fuzzed(&mut mutator, Some(&constraints));
+
+            for _ in 0..10 {
+                let mut bytes = vec![];
+                instance
+                    .binary_serialize::<_, LittleEndian>(&mut bytes)
+                    .unwrap();
+
+                assert_eq!(&bytes[..4], b"\x7fCNT");
+                // the first bit field is in the most significant bits
+                assert_eq!(bytes[4], instance.version << 5 | instance.flags);
+                assert_eq!(
+                    u16::from_be_bytes([bytes[5], bytes[6]]) as usize,
+                    instance.records.len()
+                );
+
+                assert_eq!(instance.name_terminator, 0);
+
+                let ofs_trailer = u32::from_be_bytes([bytes[7], bytes[8], bytes[9], bytes[10]]);
+                assert_eq!(ofs_trailer as usize, bytes.len() - 4);
+                assert_eq!(&bytes[ofs_trailer as usize..], instance.trailer.as_slice());
+
+                records += instance.records.len();
+                for record in instance.records.iter() {
+                    assert_eq!(record.len_body as usize, record.body.serialized_size() + 2);
+                    match record.body {
+                        RecordBody::Text(_) => assert_eq!(record.type_, RecordType::Text),
+                        RecordBody::Number(_) => assert_eq!(record.type_, RecordType::Number),
+                    }
+                }
+
+                mutator.begin_new_iteration();
+                instance.mutate(&mut mutator, None);
+            }
+        }
+
+        assert!(records > 0);
+
+        let parametric = "meta:\n  id: sample\nseq:\n  - id: body\n    type: chunk(4)\n";
+        assert!(lain::kaitai::generate(parametric).is_err());
+
+        let misaligned =
+            "meta:\n  id: sample\nseq:\n  - id: flag\n    type: b1\n  - id: len\n    type: u1\n";
+        assert!(lain::kaitai::generate(misaligned).is_err());
+    }
+
+    #[test]
+    fn test_protobuf_dynamic_message() {
+        use lain::lazy_static::lazy_static;
+        use lain::protobuf::*;
+
+        fn varint(mut value: u64, out: &mut Vec<u8>) {
+            while value >= 0x80 {
+                out.push((value as u8) | 0x80);
+                value >>= 7;
+            }
+            out.push(value as u8);
+        }
+
+        fn read_varint(data: &mut &[u8]) -> u64 {
+            let mut value = 0;
+            for shift in (0..64).step_by(7) {
+                let byte = data[0];
+                *data = &data[1..];
+                value |= u64::from(byte & 0x7f) << shift;
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            value
+        }
+
+        fn len_field(number: u64, data: &[u8]) -> Vec<u8> {
+            let mut out = vec![];
+            varint(number << 3 | 2, &mut out);
+            varint(data.len() as u64, &mut out);
+            out.extend_from_slice(data);
+            out
+        }
+
+        fn varint_field(number: u64, value: u64) -> Vec<u8> {
+            let mut out = vec![];
+            varint(number << 3, &mut out);
+            varint(value, &mut out);
+            out
+        }
+
+        fn field(name: &str, number: u64, label: u64, ty: u64, type_name: &str) -> Vec<u8> {
+            let mut out = len_field(1, name.as_bytes());
+            out.extend(varint_field(3, number));
+            out.extend(varint_field(4, label));
+            out.extend(varint_field(5, ty));
+            if !type_name.is_empty() {
+                out.extend(len_field(6, type_name.as_bytes()));
+            }
+            out
+        }
+
+        fn message(name: &str, fields: &[Vec<u8>]) -> Vec<u8> {
+            let mut out = len_field(1, name.as_bytes());
+            for f in fields {
+                out.extend(len_field(2, f));
+            }
+            out
+        }
+
+        // package test;
+        // enum Status { OK = 0; ERROR = 1; }
+        // message Inner { optional sint32 delta = 1; }
+        // message Request {
+        //     required uint32 id = 1;
+        //     repeated Inner items = 2;
+        //     optional Status status = 3;
+        //     optional bytes payload = 4;
+        //     repeated Request children = 5;
+        // }
+        fn descriptor_set() -> Vec<u8> {
+            let mut status = len_field(1, b"Status");
+            for &(name, number) in &[("OK", 0), ("ERROR", 1)] {
+                let mut value = len_field(1, name.as_bytes());
+                value.extend(varint_field(2, number));
+                status.extend(len_field(2, &value));
+            }
+
+            let inner = message("Inner", &[field("delta", 1, 1, 17, "")]);
+            let request = message(
+                "Request",
+                &[
+                    field("id", 1, 2, 13, ""),
+                    field("items", 2, 3, 11, ".test.Inner"),
+                    field("status", 3, 1, 14, ".test.Status"),
+                    field("payload", 4, 1, 12, ""),
+                    field("children", 5, 3, 11, ".test.Request"),
+                ],
+            );
+
+            let mut file = len_field(2, b"test");
+            file.extend(len_field(4, &inner));
+            file.extend(len_field(4, &request));
+            file.extend(len_field(5, &status));
+
+            len_field(1, &file)
+        }
+
+        lazy_static! {
+            static ref POOL: DescriptorPool = DescriptorPool::decode(&descriptor_set()).unwrap();
+        }
+
+        struct Request;
+
+        impl MessageSchema for Request {
+            fn pool() -> &'static DescriptorPool {
+                &POOL
+            }
+
+            fn message_name() -> &'static str {
+                "test.Request"
+            }
+        }
+
+        /// Walks the encoded message, checking that each field has the wire type its
+        /// descriptor calls for. Returns the number of times `id` appears.
+        fn check_request(mut data: &[u8]) -> usize {
+            let mut ids = 0;
+            while !data.is_empty() {
+                let key = read_varint(&mut data);
+                match (key >> 3, key & 7) {
+                    (1, 0) => {
+                        ids += 1;
+                        assert!(read_varint(&mut data) <= u64::from(std::u32::MAX));
+                    }
+                    (3, 0) => assert!(read_varint(&mut data) <= 1),
+                    (number, 2) => {
+                        let len = read_varint(&mut data) as usize;
+                        let (contents, rest) = data.split_at(len);
+                        match number {
+                            2 => {
+                                let mut inner = contents;
+                                while !inner.is_empty() {
+                                    assert_eq!(read_varint(&mut inner), 1 << 3);
+                                    read_varint(&mut inner);
+                                }
+                            }
+                            4 => {}
+                            5 => assert_eq!(check_request(contents), 1),
+                            other => panic!("unexpected field {}", other),
+                        }
+                        data = rest;
+                    }
+                    other => panic!("unexpected key {:?}", other),
+                }
+            }
+            ids
+        }
+
+        let inner = POOL.message("test.Inner").unwrap();
+        assert_eq!(inner.fields[0].kind, FieldKind::SInt32);
+        assert_eq!(POOL.enumeration("test.Status").unwrap().values, vec![0, 1]);
+
+        let mut missing_type = len_field(2, b"test");
+        missing_type.extend(len_field(
+            4,
+            &message("Broken", &[field("inner", 1, 1, 11, ".test.Missing")]),
+        ));
+        assert!(DescriptorPool::decode(&len_field(1, &missing_type)).is_err());
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        for _ in 0..50 {
+            let mut request = DynamicMessage::<Request>::new_fuzzed(&mut mutator, None);
+            assert_eq!(request.message().get("id").unwrap().len(), 1);
+
+            for _ in 0..20 {
+                let mut bytes = vec![];
+                request
+                    .binary_serialize::<_, BigEndian>(&mut bytes)
+                    .unwrap();
+                assert_eq!(bytes.len(), request.serialized_size());
+                assert_eq!(check_request(&bytes), 1);
+
+                mutator.begin_new_iteration();
+                request.mutate(&mut mutator, None);
+            }
+        }
+
+        // a known value encodes to the expected bytes
+        let mut request = DynamicMessage::<Request>::new();
+        *request.message_mut().get_mut("id").unwrap() = vec![Value::UInt(300)];
+        *request.message_mut().get_mut("status").unwrap() = vec![Value::Enum(1)];
+
+        let mut bytes = vec![];
+        request
+            .binary_serialize::<_, BigEndian>(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, [0x08, 0xAC, 0x02, 0x18, 0x01]);
+    }
+
+    #[test]
+    fn test_flatten() {
+        #[derive(
+            Debug, Clone, PartialEq, FuzzerObject, BinaryDeserialize, StructuredDiff, FuzzedDebug,
+        )]
+        struct CommonHeader {
+            version: u8,
+            length: u16,
+        }
+
+        #[derive(
+            Debug, Clone, PartialEq, FuzzerObject, BinaryDeserialize, StructuredDiff, FuzzedDebug,
+        )]
+        struct Request {
+            #[lain(flatten)]
+            header: CommonHeader,
+            opcode: u8,
+        }
+
+        let request = Request {
+            header: CommonHeader {
+                version: 1,
+                length: 0x10,
+            },
+            opcode: 2,
+        };
+
+        let mut bytes = vec![];
+        request
+            .binary_serialize::<_, BigEndian>(&mut bytes)
+            .unwrap();
+        assert_eq!(bytes, [0x01, 0x00, 0x10, 0x02]);
+        assert_eq!(request.serialized_size(), 4);
+        assert_eq!(Request::min_nonzero_elements_size(), 4);
+        assert_eq!(
+            Request::binary_deserialize::<_, BigEndian>(&mut bytes.as_slice()).unwrap(),
+            request
+        );
+
+        let mut changed = request.clone();
+        changed.header.length = 0x20;
+        changed.opcode = 3;
+
+        let paths: Vec<String> = request.diff(&changed).into_iter().map(|d| d.path).collect();
+        assert_eq!(paths, vec!["length", "opcode"]);
+
+        let expected = "\
+Request (4 bytes)
+  version: u8 (1 bytes) = 1 (0x1)
+  length: u16 (2 bytes) = 16 (0x10)
+  opcode: u8 (1 bytes) = 2 (0x2)
+";
+        assert_eq!(request.to_fuzzed_debug_string(), expected);
+
+        let mut mutator = get_mutator();
+        for _ in 0..100 {
+            let request = Request::new_fuzzed(&mut mutator, None);
+            assert_eq!(request.serialized_size(), 4);
+        }
+    }
+
+    #[test]
+    fn driver_dry_run_is_deterministic() {
+        #[derive(Debug, Clone, NewFuzzed, FuzzedDebug)]
+        struct Header {
+            magic: u32,
+            #[lain(min = 0, max = 4)]
+            payload: Vec<u8>,
+        }
+
+        let mut driver = lain::driver::FuzzerDriver::<()>::new(1);
+        driver.set_seed(1234);
+
+        let output = lain::driver::dry_run::<Header, _>(&driver, 3);
+        assert_eq!(output, lain::driver::dry_run::<Header, _>(&driver, 3));
+
+        assert_eq!(output.matches("Iteration ").count(), 3);
+        assert_eq!(output.matches("magic: u32").count(), 3);
+    }
+
+    #[test]
+    fn driver_buckets_crashes_by_input_shape() {
+        use lain::dedup::{shape_hash, stack_hash, CrashBuckets, CrashSignature};
+
+        #[derive(Debug, Clone, BinarySerialize)]
+        #[lain(input_shape)]
+        enum Command {
+            Read(u32),
+            Write(u32, Vec<u8>),
+            Reset(Option<u8>),
+        }
+
+        #[derive(Debug, Clone, BinarySerialize)]
+        #[lain(input_shape)]
+        struct Request {
+            id: u16,
+            session: Option<u32>,
+            commands: Vec<Command>,
+        }
+
+        let request = |id, session, commands| Request {
+            id,
+            session,
+            commands,
+        };
+
+        let base = shape_hash(&request(1, Some(5), vec![Command::Read(0)]));
+
+        // raw values don't change the shape
+        assert_eq!(
+            base,
+            shape_hash(&request(2, Some(9), vec![Command::Read(0x1000)]))
+        );
+        // neither does repeating an element
+        assert_eq!(
+            base,
+            shape_hash(&request(
+                3,
+                Some(1),
+                vec![Command::Read(1), Command::Read(2)]
+            ))
+        );
+
+        // but field presence, variants, and the order of elements do
+        assert_ne!(base, shape_hash(&request(1, None, vec![Command::Read(0)])));
+        assert_ne!(base, shape_hash(&request(1, Some(5), vec![])));
+        assert_ne!(
+            base,
+            shape_hash(&request(1, Some(5), vec![Command::Write(0, vec![1])]))
+        );
+        assert_ne!(
+            shape_hash(&request(1, None, vec![Command::Reset(Some(1))])),
+            shape_hash(&request(1, None, vec![Command::Reset(None)]))
+        );
+        assert_ne!(
+            shape_hash(&request(
+                1,
+                None,
+                vec![Command::Read(0), Command::Reset(None)]
+            )),
+            shape_hash(&request(
+                1,
+                None,
+                vec![Command::Reset(None), Command::Read(0)]
+            ))
+        );
+
+        let mut crashes = CrashBuckets::new();
+        let stack = stack_hash(&["parse_command", "handle_request", "main"], 2);
+        assert_eq!(
+            stack,
+            stack_hash(&["parse_command", "handle_request", "serve"], 2)
+        );
+
+        assert!(crashes.insert(request(1, None, vec![Command::Read(0)]), Some(stack)));
+        assert!(!crashes.insert(request(2, None, vec![Command::Read(7)]), Some(stack)));
+        assert!(crashes.insert(request(3, None, vec![Command::Read(7)]), None));
+        assert!(crashes.insert(request(4, Some(1), vec![Command::Read(7)]), None));
+
+        assert_eq!(crashes.len(), 3);
+        assert_eq!(crashes.crash_count(), 4);
+
+        let signature = CrashSignature::new(&request(0, None, vec![Command::Read(0)]), Some(stack));
+        let bucket = crashes.get(&signature).unwrap();
+        assert_eq!(bucket.count, 2);
+        assert_eq!(bucket.representative.id, 1);
+        assert_eq!(signature.to_string().len(), 33);
+
+        let dir = std::env::temp_dir().join("lain_crash_buckets");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(crashes.write_artifacts::<BigEndian, _>(&dir).unwrap(), 3);
+        let artifact = std::fs::read(dir.join(format!("crash-{}", signature))).unwrap();
+        assert_eq!(&artifact[..2], &[0, 1]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn driver_options_parse_args() {
+        use lain::driver::DriverOptions;
+
+        let parse =
+            |args: &[&str]| DriverOptions::parse_args(args.iter().map(|arg| arg.to_string()));
+
+        let reproduce_file = std::env::temp_dir().join("lain_driver_options_input");
+        std::fs::write(&reproduce_file, b"crash").unwrap();
+
+        let options = parse(&[
+            "--threads=2",
+            "--seed",
+            "1234",
+            "--corpus",
+            "seeds",
+            "--artifacts=crashes",
+            "--iterations",
+            "100",
+            "--dry-run=3",
+            "--reproduce-file",
+            reproduce_file.to_str().unwrap(),
+            "--target",
+            "127.0.0.1:8080",
+        ])
+        .unwrap();
+
+        assert_eq!(options.threads, Some(2));
+        assert_eq!(options.seed, Some(1234));
+        assert_eq!(options.corpus, Some("seeds".into()));
+        assert_eq!(options.artifacts, Some("crashes".into()));
+        assert_eq!(options.iterations, Some(100));
+        assert_eq!(options.dry_run, Some(3));
+        assert_eq!(options.remaining, vec!["--target", "127.0.0.1:8080"]);
+        assert_eq!(options.reproduce_input().unwrap(), Some(b"crash".to_vec()));
+
+        let driver = options.driver::<()>();
+        assert_eq!(driver.thread_count(), 2);
+        assert_eq!(driver.seed(), 1234);
+        assert!(!options.iterations_reached(&driver));
+
+        std::fs::remove_file(&reproduce_file).unwrap();
+
+        assert_eq!(parse(&[]).unwrap(), DriverOptions::default());
+        assert_eq!(parse(&[]).unwrap().reproduce_input().unwrap(), None);
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--threads", "0"]).is_err());
+        assert!(parse(&["--iterations", "many"]).is_err());
+
+        let usage = DriverOptions::usage("fuzzer");
+        assert!(usage.starts_with("Usage: fuzzer [OPTIONS]"));
+        assert!(usage.contains("--reproduce-file FILE"));
+    }
+
+    #[test]
+    fn test_max_allocation() {
+        #[derive(Debug, NewFuzzed, Clone)]
+        struct Rows {
+            #[lain(min = 0x100, max = 0x200)]
+            rows: Vec<Vec<u64>>,
+        }
+
+        const MAX_ALLOCATION: usize = 0x1000;
+
+        let mut mutator = get_mutator();
+        mutator.set_max_allocation(Some(MAX_ALLOCATION));
+
+        for _i in 0..100 {
+            let rows = Rows::new_fuzzed(&mut mutator, None);
+
+            let allocated = rows.rows.len() * std::mem::size_of::<Vec<u64>>()
+                + rows
+                    .rows
+                    .iter()
+                    .map(|r| r.len() * std::mem::size_of::<u64>())
+                    .sum::<usize>();
+
+            assert!(allocated <= MAX_ALLOCATION);
+        }
+    }
+
+    mod vendored_protocol {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Header {
+            pub magic: u32,
+            pub length: u16,
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum Message {
+            Ping(u8),
+            Data(u32, u8),
+        }
+
+        /// Serializing a remote value doesn't need it to be `Clone`
+        pub struct Record {
+            pub kind: u8,
+            pub payload: Vec<u8>,
+        }
+    }
+
+    #[test]
+    fn test_remote_derive() {
+        #[derive(NewFuzzed, Mutatable, BinarySerialize)]
+        #[lain(remote = "vendored_protocol::Header")]
+        struct HeaderDef {
+            #[lain(min = 1, max = 5)]
+            magic: u32,
+            length: u16,
+        }
+
+        #[derive(NewFuzzed, Mutatable, BinarySerialize)]
+        #[lain(remote = "vendored_protocol::Message")]
+        enum MessageDef {
+            Ping(u8),
+            Data(u32, u8),
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+        let mut mutated = false;
+
+        for _i in 0..100 {
+            let mut header: vendored_protocol::Header =
+                HeaderDef::new_fuzzed_remote(&mut mutator, None);
+            assert!(header.magic >= 1 && header.magic < 5);
+
+            let original = header.clone();
+            mutator.begin_new_iteration();
+            HeaderDef::mutate_remote(&mut header, &mut mutator, None);
+            mutated |= header != original;
+
+            let _message: vendored_protocol::Message =
+                MessageDef::new_fuzzed_remote(&mut mutator, None);
+        }
+
+        assert!(mutated);
+
+        let header = vendored_protocol::Header {
+            magic: 0xAABBCCDD,
+            length: 0x1122,
+        };
+
+        let mut buffer = vec![];
+        let written = HeaderDef::binary_serialize_remote::<_, BigEndian>(&header, &mut buffer)
+            .expect("failed to serialize");
+
+        assert_eq!(written, HeaderDef::serialized_size_remote(&header));
+        compare_slices(&[0xAA, 0xBB, 0xCC, 0xDD, 0x11, 0x22], &buffer);
+
+        let message = vendored_protocol::Message::Data(0x01020304, 0x05);
+        let mut buffer = vec![];
+        let written = MessageDef::binary_serialize_remote::<_, BigEndian>(&message, &mut buffer)
+            .expect("failed to serialize");
+
+        assert_eq!(written, MessageDef::serialized_size_remote(&message));
+        compare_slices(&[0x01, 0x02, 0x03, 0x04, 0x05], &buffer);
+
+        #[derive(BinarySerialize)]
+        #[lain(remote = "vendored_protocol::Record")]
+        struct RecordDef {
+            kind: u8,
+            payload: Vec<u8>,
+        }
+
+        let record = vendored_protocol::Record {
+            kind: 0x7F,
+            payload: vec![0xDE, 0xAD],
+        };
+        let mut buffer = vec![];
+        let written = RecordDef::binary_serialize_remote::<_, BigEndian>(&record, &mut buffer)
+            .expect("failed to serialize");
+
+        assert_eq!(written, RecordDef::serialized_size_remote(&record));
+        compare_slices(&[0x7F, 0xDE, 0xAD], &buffer);
+    }
+
+    #[test]
+    fn test_seed_domains() {
+        #[derive(Debug, NewFuzzed, Clone, PartialEq, BinarySerialize)]
+        #[lain(seed_domain = "session")]
+        struct Session {
+            id: u64,
+            key: u32,
+        }
+
+        #[derive(Debug, NewFuzzed, Clone)]
+        struct Packet {
+            session: Session,
+            payload: u64,
+        }
+
+        let generate = |seed: u64, iteration: u64| {
+            let mut mutator = Mutator::new(SmallRng::seed_from_u64(seed.wrapping_add(iteration)));
+            mutator.set_seed(Some(seed));
+
+            Packet::new_fuzzed(&mut mutator, None)
+        };
+
+        let first = generate(1234, 0);
+        let mut payload_changed = false;
+
+        for iteration in 1..10 {
+            let packet = generate(1234, iteration);
+
+            assert_eq!(packet.session, first.session);
+            payload_changed |= packet.payload != first.payload;
+        }
+
+        assert!(payload_changed);
+        assert_ne!(generate(4321, 0).session, first.session);
+    }
+
+    #[repr(u8)]
+    #[derive(Debug, Copy, Clone, PartialEq, NewFuzzed, BinarySerialize, BinaryDeserialize)]
+    enum RecordKind {
+        Data = 1,
+        Ack = 7,
+    }
+
+    impl Default for RecordKind {
+        fn default() -> Self {
+            RecordKind::Data
+        }
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, NewFuzzed, BinarySerialize, BinaryDeserialize)]
+    struct Record {
+        kind: RecordKind,
+        #[lain(bitfield(backing_type = "u8", bits = 3))]
+        version: u8,
+        #[lain(bitfield(backing_type = "u8", bits = 5))]
+        flags: u8,
+        #[lain(byteorder(little))]
+        length: u16,
+        id: u32,
+        payload: Vec<u8>,
+    }
+
+    #[test]
+    fn test_binary_deserialize() {
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        for _i in 0..100 {
+            let record = Record::new_fuzzed(&mut mutator, None);
+
+            let mut buffer = vec![];
+            record
+                .binary_serialize::<_, BigEndian>(&mut buffer)
+                .expect("failed to serialize");
+
+            let deserialized = Record::binary_deserialize::<_, BigEndian>(&mut buffer.as_slice())
+                .expect("failed to deserialize");
+            assert_eq!(deserialized, record);
+        }
+
+        // truncated in the middle of `id`
+        let truncated = [7u8, 0b1010_1011, 0x34, 0x12, 0xAA];
+        assert!(Record::binary_deserialize::<_, BigEndian>(&mut &truncated[..]).is_err());
+
+        let lenient = Record::binary_deserialize_lenient::<_, BigEndian>(&mut &truncated[..])
+            .expect("lenient deserialization failed");
+        assert_eq!(lenient.kind, RecordKind::Ack);
+        assert_eq!(lenient.version, 0b011);
+        assert_eq!(lenient.flags, 0b10101);
+        assert_eq!(lenient.length, 0x1234);
+        assert_eq!(lenient.id, 0);
+        assert!(lenient.payload.is_empty());
+
+        let invalid_kind = [2u8, 0, 0, 0];
+        let err =
+            Record::binary_deserialize_lenient::<_, BigEndian>(&mut &invalid_kind[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_array_deserialize_error_drops_read_items() {
+        use lain::byteorder::ByteOrder;
+        use std::cell::Cell;
+        use std::io::{self, Read};
+
+        thread_local! {
+            static DROPS: Cell<usize> = Cell::new(0);
+        }
+
+        struct Item;
+
+        impl Drop for Item {
+            fn drop(&mut self) {
+                DROPS.with(|drops| drops.set(drops.get() + 1));
+            }
+        }
+
+        impl BinaryDeserialize for Item {
+            fn binary_deserialize<R: Read, E: ByteOrder>(buffer: &mut R) -> io::Result<Self> {
+                match u8::binary_deserialize::<R, E>(buffer)? {
+                    0xFF => Err(io::Error::new(io::ErrorKind::InvalidData, "bad item")),
+                    _ => Ok(Item),
+                }
+            }
+        }
+
+        let input = [0u8, 1, 0xFF, 3];
+        assert!(<[Item; 4]>::binary_deserialize::<_, BigEndian>(&mut &input[..]).is_err());
+        assert_eq!(DROPS.with(Cell::get), 2);
+
+        // a fully deserialized array only drops its items along with the array
+        let input = [0u8, 1, 2, 3];
+        let items = <[Item; 4]>::binary_deserialize::<_, BigEndian>(&mut &input[..])
+            .expect("failed to deserialize");
+        assert_eq!(DROPS.with(Cell::get), 2);
+
+        drop(items);
+        assert_eq!(DROPS.with(Cell::get), 6);
+    }
+
+    #[test]
+    fn test_corpus_import_raw() {
+        #[derive(Debug, Default, PartialEq, BinaryDeserialize)]
+        struct Header {
+            magic: u16,
+            length: u16,
+        }
+
+        let dir = std::env::temp_dir().join(format!("lain_corpus_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a_complete"), [0xAA, 0xBB, 0x00, 0x10]).unwrap();
+        std::fs::write(dir.join("b_truncated"), [0xAA, 0xBB, 0x00]).unwrap();
+        std::fs::write(dir.join("c_excess"), [0xAA, 0xBB, 0x00, 0x20, 0xFF, 0xFF]).unwrap();
+
+        let mut corpus = Corpus::<Header>::new();
+        let imported = corpus.import_raw::<BigEndian, _>(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(imported.unwrap(), 3);
+        assert_eq!(
+            corpus.entries(),
+            &[
+                Header {
+                    magic: 0xAABB,
+                    length: 0x10
+                },
+                Header {
+                    magic: 0xAABB,
+                    length: 0
+                },
+                Header {
+                    magic: 0xAABB,
+                    length: 0x20
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_corpus_store_dedup() {
+        use lain::corpus::{ContentHash, CorpusStore, StoreCompression};
+
+        #[derive(Debug, Default, Clone, PartialEq, BinarySerialize, BinaryDeserialize)]
+        struct Header {
+            magic: u16,
+            length: u16,
+        }
+
+        let first = Header {
+            magic: 0xAABB,
+            length: 0x10,
+        };
+        let second = Header {
+            magic: 0xAABB,
+            length: 0x20,
+        };
+
+        let mut corpus = Corpus::new();
+        for entry in &[&first, &first, &second, &first] {
+            corpus.push((*entry).clone());
+        }
+        assert_eq!(corpus.dedup::<BigEndian>(), 2);
+        assert_eq!(corpus.entries(), &[first.clone(), second.clone()]);
+
+        let dir = std::env::temp_dir().join(format!("lain_corpus_store_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut store = CorpusStore::open(&dir).unwrap();
+        let (first_hash, inserted) = store.insert_serialized::<_, BigEndian>(&first).unwrap();
+        assert!(inserted);
+        assert_eq!(first_hash, ContentHash::of(&[0xAA, 0xBB, 0x00, 0x10]));
+        assert!(!store.insert(&[0xAA, 0xBB, 0x00, 0x10]).unwrap().1);
+
+        let repetitive = vec![0x41u8; 4096];
+        store.set_compression(StoreCompression::Zstd(3));
+        let (second_hash, _) = store.insert_serialized::<_, BigEndian>(&second).unwrap();
+        let (repetitive_hash, _) = store.insert(&repetitive).unwrap();
+        assert_eq!(store.len(), 3);
+
+        let (len, stored_len) = store.disk_usage();
+        assert_eq!(len, 4 + 4 + 4096);
+        assert!(stored_len < len);
+
+        // reopening the store reads the index back, including entries written with a different
+        // compression setting than the current one
+        drop(store);
+        let mut store = CorpusStore::open(&dir).unwrap();
+        assert_eq!(store.hashes(), &[first_hash, second_hash, repetitive_hash]);
+        assert_eq!(store.get(repetitive_hash).unwrap().unwrap(), repetitive);
+        assert!(store.get(ContentHash(0)).unwrap().is_none());
+        assert!(!store.insert_serialized::<_, BigEndian>(&second).unwrap().1);
+
+        let loaded = store.load::<Header, BigEndian>().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(&loaded.entries()[..2], &[first, second]);
+    }
+
+    #[test]
+    fn test_serde_round_trips() {
+        use lain::corpus::{ContentHash, StoreCompression};
+        use lain::types::{
+            AsciiString, Bounds, ElementConstraints, Utf8String, Weighted, U24, U48,
+        };
+
+        // string wrappers are serialized as plain strings
+        let utf8 = Utf8String::new("h\u{e9}llo \u{2713}");
+        let json = serde_json::to_string(&utf8).unwrap();
+        assert_eq!(json, "\"h\u{e9}llo \u{2713}\"");
+        let utf8: Utf8String = serde_json::from_str(&json).unwrap();
+        assert_eq!(String::from(utf8), "h\u{e9}llo \u{2713}");
+
+        let ascii = AsciiString::new("GET / HTTP/1.1");
+        let json = serde_json::to_string(&ascii).unwrap();
+        assert_eq!(json, "\"GET / HTTP/1.1\"");
+        let ascii: AsciiString = serde_json::from_str(&json).unwrap();
+        assert_eq!(String::from(ascii), "GET / HTTP/1.1");
+
+        // element constraints aren't serialized since their type isn't known
+        let constraints = Constraints {
+            min: Some(2u32),
+            max: Some(10),
+            weighted: Weighted::Max,
+            max_size: Some(64),
+            bounds: Bounds::Closed,
+            element_constraints: Some(ElementConstraints::new(Constraints {
+                min: Some(5u8),
+                ..Default::default()
+            })),
+        };
+
+        let json = serde_json::to_string(&constraints).unwrap();
+        let round_tripped: Constraints<u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_tripped.min, Some(2));
+        assert_eq!(round_tripped.max, Some(10));
+        assert_eq!(round_tripped.weighted, Weighted::Max);
+        assert_eq!(round_tripped.max_size, Some(64));
+        assert_eq!(round_tripped.bounds, Bounds::Closed);
+        assert!(round_tripped.element_constraints.is_none());
+
+        // a corpus is serialized as the list of its entries
+        let mut corpus = Corpus::new();
+        corpus.push(vec![0xAAu8, 0xBB]);
+        corpus.push(vec![]);
+        let json = serde_json::to_string(&corpus).unwrap();
+        assert_eq!(json, "[[170,187],[]]");
+        let corpus: Corpus<Vec<u8>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(corpus.entries(), &[vec![0xAA, 0xBB], vec![]]);
+
+        // hashes are serialized as the hex strings they're stored under
+        let hash = ContentHash::of(&[0xAA, 0xBB]);
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, format!("\"{}\"", hash));
+        assert_eq!(serde_json::from_str::<ContentHash>(&json).unwrap(), hash);
+        assert!(serde_json::from_str::<ContentHash>("\"not hex\"").is_err());
+
+        // integers narrower than their storage go through the checked constructors, so values
+        // which don't fit are rejected rather than panicking when they're serialized
+        let length = U24::new(0xAABBCC);
+        let json = serde_json::to_string(&length).unwrap();
+        assert_eq!(json, "11189196");
+        assert_eq!(serde_json::from_str::<U24>(&json).unwrap(), length);
+        assert!(serde_json::from_str::<U24>("16777216").is_err());
+
+        let offset = U48::new(0xAABB_CCDD_EEFF);
+        let json = serde_json::to_string(&offset).unwrap();
+        assert_eq!(serde_json::from_str::<U48>(&json).unwrap(), offset);
+        assert!(serde_json::from_str::<U48>("281474976710656").is_err());
+
+        let compression = StoreCompression::Zstd(3);
+        let json = serde_json::to_string(&compression).unwrap();
+        assert_eq!(
+            serde_json::from_str::<StoreCompression>(&json).unwrap(),
+            compression
+        );
+    }
+
+    #[test]
+    fn test_tcp_sender_reconnects() {
+        use lain::net::{NetConfig, ResponseStatus, Sender, TcpSender};
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // echoes a single message per connection, then closes it
+        let server = std::thread::spawn(move || {
+            for stream in listener.incoming().take(3) {
+                let mut stream = stream.unwrap();
+                let mut buffer = [0u8; 16];
+                let size = stream.read(&mut buffer).unwrap();
+                stream.write_all(&buffer[..size]).unwrap();
+            }
+        });
+
+        let mut sender = TcpSender::new(addr, NetConfig::default()).unwrap();
+
+        for message in [&b"first"[..], &b"second"[..], &b"third"[..]].iter() {
+            let mut response = vec![];
+            let status = sender.send(message, &mut response).unwrap();
+
+            assert_eq!(status, ResponseStatus::Received);
+            assert_eq!(&response[..], *message);
+
+            // wait for the server to close the connection so the next send hits a stale socket
+            let mut closed = vec![];
+            while sender.is_connected() {
+                let _ = sender.send(b"", &mut closed);
+            }
+        }
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_udp_sender() {
+        use lain::net::{NetConfig, ResponseStatus, Sender, UdpSender};
+        use std::net::UdpSocket;
+
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+
+        let mut sender = UdpSender::new(addr, NetConfig::default()).unwrap();
+
+        let mut response = vec![];
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 16];
+            let (size, peer) = server.recv_from(&mut buffer).unwrap();
+            server.send_to(&buffer[..size], peer).unwrap();
+        });
+
+        let status = sender.send(b"ping", &mut response).unwrap();
+        assert_eq!(status, ResponseStatus::Received);
+        assert_eq!(&response[..], b"ping");
+
+        // nothing is listening anymore, so the next send either times out or is refused
+        response.clear();
+        let status = sender.send(b"ping", &mut response).unwrap();
+        assert_ne!(status, ResponseStatus::Received);
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn test_unix_sender() {
+        use lain::net::{
+            drop_privileges, NetConfig, Privileges, ResponseStatus, Sender, UnixSender,
+        };
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("lain_unix_{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0u8; 16];
+            let size = stream.read(&mut buffer).unwrap();
+            stream.write_all(&buffer[..size]).unwrap();
+        });
+
+        // the process already runs as these, so nothing changes
+        let privileges = unsafe {
+            Privileges {
+                uid: lain::libc::getuid(),
+                gid: lain::libc::getgid(),
+            }
+        };
+        drop_privileges(privileges).unwrap();
+
+        let config = NetConfig {
+            drop_privileges: Some(privileges),
+            ..Default::default()
+        };
+        let mut sender = UnixSender::new(&path, config).unwrap();
+        assert!(sender.is_connected());
+
+        let mut response = vec![];
+        let status = sender.send(b"ping", &mut response).unwrap();
+        assert_eq!(status, ResponseStatus::Received);
+        assert_eq!(&response[..], b"ping");
+
+        server.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_conversation_bindings() {
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Open {
+            session_id: u32,
+        }
+
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Data {
+            session_id: u32,
+            payload: u8,
+        }
+
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize)]
+        enum Message {
+            Open(Open),
+            Data(Data),
+        }
+
+        impl Bind for Message {
+            fn declare_bindings(&self, bindings: &mut Bindings) {
+                if let Message::Open(ref open) = *self {
+                    bindings.declare("session_id", open.session_id);
+                }
+            }
+
+            fn resolve_bindings(&mut self, bindings: &Bindings) {
+                if let Message::Data(ref mut data) = *self {
+                    bindings.resolve("session_id", &mut data.session_id);
+                }
+            }
+        }
+
+        fn session_ids(conversation: &Conversation<Message>) -> Vec<u32> {
+            conversation
+                .messages()
+                .iter()
+                .map(|message| match *message {
+                    Message::Open(ref open) => open.session_id,
+                    Message::Data(ref data) => data.session_id,
+                })
+                .collect()
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let data = |payload| {
+            Message::Data(Data {
+                session_id: 0,
+                payload,
+            })
+        };
+        let mut conversation = Conversation::new(vec![
+            data(1),
+            Message::Open(Open { session_id: 7 }),
+            data(2),
+            Message::Open(Open { session_id: 9 }),
+            data(3),
+        ]);
+
+        // messages reference the latest value declared before them
+        conversation.fixup(&mut mutator);
+        assert_eq!(session_ids(&conversation), vec![0, 7, 7, 9, 9]);
+
+        let mut serialized = vec![];
+        conversation
+            .binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+        assert_eq!(serialized.len(), conversation.serialized_size());
+        assert_eq!(&serialized[..5], &[0, 0, 0, 0, 1]);
+        assert_eq!(&serialized[9..14], &[0, 0, 0, 7, 2]);
+
+        // values known beforehand, e.g. from a response, are referenced until a message declares one
+        let mut bindings = Bindings::new();
+        bindings.declare("session_id", 42u32);
+        conversation.fixup_with(&mut mutator, &mut bindings);
+        assert_eq!(session_ids(&conversation), vec![42, 7, 7, 9, 9]);
+        assert_eq!(bindings.get::<u32>("session_id"), Some(&9));
+        assert_eq!(bindings.get::<u8>("session_id"), None);
+
+        let constraints = Constraints {
+            min: Some(2),
+            max: Some(8),
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let mut conversation =
+                Conversation::<Message>::new_fuzzed(&mut mutator, Some(&constraints));
+            conversation.mutate(&mut mutator, None);
+
+            let mut declared = None;
+            for message in conversation.messages() {
+                match *message {
+                    Message::Open(ref open) => declared = Some(open.session_id),
+                    Message::Data(ref data) => {
+                        if let Some(session_id) = declared {
+                            assert_eq!(data.session_id, session_id);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_field_swaps() {
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        #[lain(field_swap = "wire_order")]
+        struct Record {
+            kind: u8,
+            length: u16,
+            flags: u32,
+            wire_order: FieldSwap,
+        }
+
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Pinned {
+            kind: u8,
+            length: u16,
+            flags: u32,
+        }
+
+        // 0 for declaration order, or 1 + the position of the first swapped field
+        fn swapped_pair(serialized: &[u8], kind: u8, length: u16, flags: u32) -> usize {
+            let (kind, length, flags) = (vec![kind], length.to_be_bytes(), flags.to_be_bytes());
+            let orders = [
+                [&kind[..], &length[..], &flags[..]].concat(),
+                [&length[..], &kind[..], &flags[..]].concat(),
+                [&kind[..], &flags[..], &length[..]].concat(),
+            ];
+
+            orders
+                .iter()
+                .position(|order| &order[..] == serialized)
+                .expect("fields were reordered")
+        }
+
+        fn serialize<T: BinarySerialize>(value: &T) -> Vec<u8> {
+            let mut serialized = vec![];
+            value
+                .binary_serialize::<_, BigEndian>(&mut serialized)
+                .unwrap();
+            serialized
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_config(MutatorConfig {
+            field_swap_chance: 50.0,
+            ..MutatorConfig::default()
+        });
+
+        let mut record = Record::new_fuzzed(&mut mutator, None);
+        let mut pinned = Pinned::new_fuzzed(&mut mutator, None);
+        let mut seen = [0; 3];
+        for _ in 0..200 {
+            record.mutate(&mut mutator, None);
+
+            let serialized = serialize(&record);
+            assert_eq!(serialized.len(), record.serialized_size());
+            let pair = swapped_pair(&serialized, record.kind, record.length, record.flags);
+            assert_eq!(pair != 0, record.wire_order.is_swapped());
+            seen[pair] += 1;
+
+            // the swap is part of the value, so copies and moves keep it
+            let moved = Box::new(record.clone());
+            assert_eq!(serialize(&*moved), serialized);
+
+            // fresh values are serialized in order
+            let fresh = Record::new_fuzzed(&mut mutator, None);
+            assert!(!fresh.wire_order.is_swapped());
+            assert_eq!(
+                swapped_pair(&serialize(&fresh), fresh.kind, fresh.length, fresh.flags),
+                0
+            );
+
+            // structs which don't opt in are never swapped
+            pinned.mutate(&mut mutator, None);
+            let serialized = serialize(&pinned);
+            assert_eq!(
+                swapped_pair(&serialized, pinned.kind, pinned.length, pinned.flags),
+                0
+            );
+        }
+
+        assert!(seen.iter().all(|&count| count > 20), "{:?}", seen);
+
+        // valid inputs are always serialized in order
+        mutator.set_profile(GenerationProfile::Valid);
+        for _ in 0..100 {
+            record.mutate(&mut mutator, None);
+            let serialized = serialize(&record);
+            assert_eq!(
+                swapped_pair(&serialized, record.kind, record.length, record.flags),
+                0
+            );
+        }
+    }
+
+    #[test]
+    fn test_pinned_byte_regions() {
+        #[derive(Debug, Clone, Mutatable)]
+        struct Frame {
+            #[lain(pin(0..4, 8..12))]
+            payload: Vec<u8>,
+        }
+
+        let mut mutator = get_mutator();
+        let original: Vec<u8> = (0..64).collect();
+        let mut frame = Frame {
+            payload: original.clone(),
+        };
+
+        let mut unpinned_changed = false;
+        for _ in 0..100 {
+            frame.mutate(&mut mutator, None);
+            mutator.begin_new_iteration();
+
+            assert_eq!(frame.payload[0..4], original[0..4]);
+            assert_eq!(frame.payload[8..12], original[8..12]);
+            unpinned_changed |= frame.payload[4..8] != original[4..8];
+        }
+
+        assert!(unpinned_changed);
+
+        let mut mutator = get_mutator();
+        let mut bytes = original.clone();
+        for _ in 0..100 {
+            mutator.mutate_bytes_in_range(&mut bytes, 16..32);
+            mutator.begin_new_iteration();
+        }
+
+        assert_eq!(bytes[..16], original[..16]);
+        assert_eq!(bytes[32..], original[32..]);
+        assert_ne!(bytes[16..32], original[16..32]);
+    }
+
+    #[test]
+    fn test_opaque_fields() {
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, BinarySerialize)]
+        struct Signature {
+            bytes: [u8; 16],
+        }
+
+        impl Mutatable for Signature {
+            fn mutate<R: lain::rand::Rng>(
+                &mut self,
+                _mutator: &mut Mutator<R>,
+                _constraints: Option<&Constraints<u8>>,
+            ) {
+                panic!("opaque fields must not be mutated in place");
+            }
+        }
+
+        #[derive(Debug, Clone, NewFuzzed, Mutatable)]
+        struct SignedMessage {
+            sequence: u32,
+            #[lain(opaque)]
+            signature: Signature,
+            #[lain(opaque, initializer = "vec![0xAA; 32]")]
+            certificate: Vec<u8>,
+        }
+
+        let mut mutator = get_mutator();
+        let mut message = SignedMessage::new_fuzzed(&mut mutator, None);
+        assert_eq!(message.certificate, vec![0xAA; 32]);
+
+        let mut replacements = 0;
+        let iterations = 1000;
+        for _ in 0..iterations {
+            let previous = message.clone();
+            message.mutate(&mut mutator, None);
+            mutator.begin_new_iteration();
+
+            if message.signature != previous.signature {
+                replacements += 1;
+            }
+
+            assert!(
+                message.certificate == previous.certificate
+                    || message.certificate != vec![0xAA; 32]
+            );
+        }
+
+        // opaque fields are regenerated rarely rather than on every mutation
+        assert!(replacements > 0);
+        assert!(replacements < iterations / 10);
+    }
+
+    #[test]
+    fn test_count_field() {
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Table {
+            num_entries: u16,
+            #[lain(count_field = "num_entries", max = 64)]
+            entries: Vec<u32>,
+        }
+
+        let mut mutator = get_mutator();
+        // counts are never desynchronized with the valid profile
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let mut resized = false;
+        for _ in 0..100 {
+            let mut table = Table::new_fuzzed(&mut mutator, None);
+            assert_eq!(table.num_entries as usize, table.entries.len());
+
+            for _ in 0..10 {
+                let previous_len = table.entries.len();
+                table.mutate(&mut mutator, None);
+                mutator.begin_new_iteration();
+
+                assert_eq!(table.num_entries as usize, table.entries.len());
+                resized |= table.entries.len() != previous_len;
+            }
+        }
+
+        assert!(resized);
+
+        let mut config = MutatorConfig::default();
+        config.count_desync_chance = 100.0;
+        mutator.set_config(config);
+        mutator.set_profile(GenerationProfile::Balanced);
+
+        let mut table = Table::new_fuzzed(&mut mutator, None);
+        let mut desynced = false;
+        for _ in 0..100 {
+            table.mutate(&mut mutator, None);
+            mutator.begin_new_iteration();
+
+            desynced |= table.num_entries as usize != table.entries.len();
+        }
+
+        assert!(desynced);
+
+        let mut entries = vec![1u8, 2, 3];
+        assert!(mutator.resize_to_count(&mut entries, 1));
+        assert_eq!(entries, [1]);
+        assert!(mutator.resize_to_count(&mut entries, 8));
+        assert_eq!(entries.len(), 8);
+        assert!(!mutator.resize_to_count(&mut entries, usize::max_value()));
+        assert_eq!(entries.len(), 8);
+    }
+
+    #[test]
+    fn test_invariant_assertions() {
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        #[lain(assert = "self.data.len() <= 16")]
+        struct Chunk {
+            #[lain(assert = "self.len as usize == self.data.len()")]
+            len: u32,
+            #[lain(min = 0, max = 16)]
+            data: Vec<u8>,
+        }
+
+        impl Fixup for Chunk {
+            fn fixup<R: Rng>(&mut self, _mutator: &mut Mutator<R>) {
+                self.len = self.data.len() as u32;
+            }
+        }
+
+        // forgets to update the length
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        struct BrokenChunk {
+            #[lain(ignore, assert = "self.len as usize == self.data.len()")]
+            len: u32,
+            #[lain(min = 1, max = 16)]
+            data: Vec<u8>,
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        for _ in 0..100 {
+            let mut chunk = Chunk::new_fuzzed(&mut mutator, None);
+            chunk.mutate(&mut mutator, None);
+            mutator.begin_new_iteration();
+        }
+
+        let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            BrokenChunk::new_fuzzed(&mut mutator, None)
+        }))
+        .unwrap_err();
+
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert_eq!(
+            message,
+            "invariant violated at BrokenChunk.len: `self.len as usize == self.data.len()`"
+        );
+    }
+
+    #[test]
+    fn test_element_duplication() {
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Extension {
+            kind: u8,
+            data: u32,
+        }
+
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        #[lain(duplicate_chance = 100.0)]
+        struct Hello {
+            #[lain(min = 1, max = 8)]
+            extensions: Vec<Extension>,
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let mut config = MutatorConfig::default();
+        config.duplicate_chance = 0.0;
+        mutator.set_config(config);
+
+        let mut grew = 0;
+        for _ in 0..20 {
+            let mut hello = Hello::new_fuzzed(&mut mutator, None);
+
+            for _ in 0..10 {
+                let previous_len = hello.extensions.len();
+                hello.mutate(&mut mutator, None);
+                mutator.begin_new_iteration();
+
+                // the max length is respected with the valid profile
+                assert!(hello.extensions.len() < 8);
+
+                if previous_len < 7 {
+                    assert_eq!(hello.extensions.len(), previous_len + 1);
+                    assert!(hello.extensions.windows(2).any(|pair| pair[0] == pair[1]));
+                    grew += 1;
+                }
+            }
+
+            // the container's chance doesn't leak into the rest of the mutation
+            let mut extensions = hello.extensions.clone();
+            extensions.mutate(&mut mutator, None);
+            assert_eq!(extensions.len(), hello.extensions.len());
+        }
+
+        assert!(grew > 0);
+
+        let mut values = vec![1u8, 2, 3];
+        assert!(mutator.duplicate_element(&mut values));
+        assert_eq!(values.len(), 4);
+        assert!(values.windows(2).any(|pair| pair[0] == pair[1]));
+        assert!(!mutator.duplicate_element(&mut Vec::<u8>::new()));
+    }
+
+    #[test]
+    fn test_seed_templates() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        static TEMPLATE_AVAILABLE: AtomicBool = AtomicBool::new(true);
+
+        // stands in for a sample file read from disk
+        fn record_template() -> Option<Record> {
+            if !TEMPLATE_AVAILABLE.load(Ordering::SeqCst) {
+                return None;
+            }
+
+            let sample = [0x7F, b'E', b'L', b'F', 0x01, 0x00, 0x02];
+            Record::binary_deserialize::<_, BigEndian>(&mut &sample[..]).ok()
+        }
+
+        #[derive(
+            Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize,
+        )]
+        #[lain(seed_from = "record_template()")]
+        struct Record {
+            #[lain(opaque)]
+            magic: u32,
+            version: u8,
+            flags: u16,
+        }
+
+        let template = record_template().unwrap();
+        assert_eq!(template.magic, 0x7F45_4C46);
+
+        // the template is only mutated in havoc mode
+        let mut mutator = get_mutator();
+        mutator.set_mode(MutatorMode::WalkingBitFlip {
+            bits: 1,
+            current_idx: 0,
+        });
+        assert_eq!(Record::new_fuzzed(&mut mutator, None), template);
+
+        let mut mutator = get_mutator();
+        let mut kept_magic = 0;
+        let mut mutated = 0;
+        for _ in 0..200 {
+            let record = Record::new_fuzzed(&mut mutator, None);
+            mutator.begin_new_iteration();
+
+            kept_magic += (record.magic == template.magic) as usize;
+            mutated += (record != template) as usize;
+        }
+
+        assert!(kept_magic > 150);
+        assert!(mutated > 0);
+
+        // without a template, values are generated from scratch
+        TEMPLATE_AVAILABLE.store(false, Ordering::SeqCst);
+        let mut kept_magic = 0;
+        for _ in 0..200 {
+            let record = Record::new_fuzzed(&mut mutator, None);
+            mutator.begin_new_iteration();
+
+            kept_magic += (record.magic == template.magic) as usize;
+        }
+
+        assert!(kept_magic < 10);
+    }
+
+    #[test]
+    fn test_scaled_values() {
+        #[derive(
+            Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize,
+        )]
+        struct SensorReading {
+            #[lain(min = "-40.0", max = "125.0")]
+            temperature: Scaled<i16, Tenths>,
+            humidity: Scaled<u16, Hundredths>,
+        }
+
+        let reading = SensorReading {
+            temperature: Scaled::from_real(21.5),
+            humidity: Scaled::from_real(45.25),
+        };
+
+        assert_eq!(reading.temperature.raw(), 215);
+        assert_eq!(reading.temperature.real(), 21.5);
+        assert_eq!(reading.humidity.raw(), 4525);
+
+        let mut serialized = vec![];
+        reading
+            .binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+        compare_slices(&[0x00, 0xD7, 0x11, 0xAD], &serialized);
+
+        let deserialized =
+            SensorReading::binary_deserialize::<_, BigEndian>(&mut serialized.as_slice()).unwrap();
+        assert_eq!(deserialized, reading);
+
+        // real values which don't fit on the wire saturate
+        assert_eq!(
+            Scaled::<i16, Tenths>::from_real(1e9).raw(),
+            i16::max_value()
+        );
+        assert_eq!(
+            Scaled::<i16, Tenths>::from_real(-1e9).raw(),
+            i16::min_value()
+        );
+        assert_eq!(Scaled::<u16, Tenths>::from_real(-1.0).raw(), 0);
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        for _ in 0..100 {
+            let mut reading = SensorReading::new_fuzzed(&mut mutator, None);
+            let temperature = reading.temperature.real();
+            assert!(
+                temperature >= -40.0 && temperature <= 125.0,
+                "{}",
+                temperature
+            );
+
+            reading.mutate(&mut mutator, None);
+            mutator.begin_new_iteration();
+        }
+    }
+
+    #[test]
+    fn test_serialize_stream() {
+        #[derive(Debug, Clone, BinarySerialize)]
+        struct Image {
+            magic: u32,
+            sector_size: u16,
+            sectors: Vec<u8>,
+            checksum: u32,
+        }
+
+        let image = Image {
+            magic: 0xDEAD_BEEF,
+            sector_size: 0x200,
+            sectors: (0..0x1000).map(|i| i as u8).collect(),
+            checksum: 0x1234_5678,
+        };
+
+        let mut expected = vec![];
+        image
+            .binary_serialize::<_, BigEndian>(&mut expected)
+            .unwrap();
+
+        for &chunk_size in &[1, 3, 7, 0x200, expected.len(), expected.len() + 1] {
+            let mut stream = SerializeStream::<_, BigEndian>::new(&image);
+            let mut chunk = vec![0u8; chunk_size];
+            let mut streamed = vec![];
+
+            loop {
+                let size = stream.next_chunk(&mut chunk).unwrap();
+                if size == 0 {
+                    break;
+                }
+
+                assert!(size == chunk_size || stream.is_finished());
+                streamed.extend_from_slice(&chunk[..size]);
+            }
+
+            assert!(stream.is_finished());
+            assert_eq!(stream.position(), expected.len() as u64);
+            compare_slices(&expected, &streamed);
+        }
+
+        let mut copied = vec![];
+        let mut stream = SerializeStream::<_, BigEndian>::new(&image);
+        std::io::copy(&mut stream, &mut copied).unwrap();
+        compare_slices(&expected, &copied);
+
+        let mut written = vec![];
+        let size = SerializeStream::<_, BigEndian>::new(&image)
+            .write_to(&mut written, 100)
+            .unwrap();
+        assert_eq!(size, expected.len() as u64);
+        compare_slices(&expected, &written);
+    }
+
+    #[test]
+    fn test_variant_weights() {
+        const RARE: u64 = 1;
+
+        #[derive(Debug, Copy, Clone, PartialEq, NewFuzzed)]
+        enum Opcode {
+            #[lain(weight(70%))]
+            Load,
+            #[lain(weight(20%))]
+            Store,
+            Halt,
+        }
+
+        #[derive(Debug, Clone, PartialEq, NewFuzzed)]
+        enum Instruction {
+            #[lain(weight(RARE * 9))]
+            Push(u8),
+            #[lain(weight(RARE))]
+            Pop(u8),
+        }
+
+        let mut mutator = get_mutator();
+        let mut counts = [0; 3];
+        let mut pushes = 0;
+
+        for _ in 0..10000 {
+            counts[Opcode::new_fuzzed(&mut mutator, None) as usize] += 1;
+
+            if let Instruction::Push(_) = Instruction::new_fuzzed(&mut mutator, None) {
+                pushes += 1;
+            }
+        }
+
+        assert!(counts[Opcode::Load as usize] > 6500, "{:?}", counts);
+        assert!(counts[Opcode::Store as usize] > 1500, "{:?}", counts);
+        assert!(counts[Opcode::Store as usize] < 2500, "{:?}", counts);
+        assert!(counts[Opcode::Halt as usize] > 700, "{:?}", counts);
+        assert!(counts[Opcode::Halt as usize] < 1300, "{:?}", counts);
+        assert!(pushes > 8500, "{}", pushes);
+
+        // weights which collapse to 0 fall back to picking every variant uniformly
+        let dist = lain::new_fuzzed::variant_distribution("Opcode", &[0, 0, 0]);
+        let mut seen = [false; 3];
+        for _ in 0..100 {
+            seen[lain::rand::distributions::Distribution::sample(&dist, &mut mutator.rng)] = true;
+        }
+        assert_eq!(seen, [true; 3]);
+    }
+
+    #[test]
+    fn test_constrained_enum_generation() {
+        #[derive(Debug, Copy, Clone, PartialEq, NewFuzzed, BinarySerialize)]
+        #[repr(u8)]
+        enum Command {
+            Nop,
+            Read,
+            #[lain(weight(3))]
+            Write,
+            #[lain(ignore = true)]
+            Erase,
+            Reset,
+        }
+
+        #[derive(Debug, Clone, PartialEq, NewFuzzed)]
+        enum Frame {
+            Ping(u8),
+            Data(u32),
+            Close(u8),
+        }
+
+        #[derive(Debug, Clone, NewFuzzed)]
+        struct Request {
+            #[lain(min = 3, max = 5)]
+            command: Command,
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        // min/max bound the declaration index and keep the variants' relative weights
+        let constraints = Constraints {
+            min: Some(1),
+            max: Some(3),
+            ..Default::default()
+        };
+        let mut counts = [0; 5];
+        for _ in 0..1000 {
+            counts[Command::new_fuzzed(&mut mutator, Some(&constraints)) as usize] += 1;
+        }
+
+        assert_eq!(counts[Command::Nop as usize], 0);
+        assert_eq!(counts[Command::Reset as usize], 0);
+        assert_eq!(counts[Command::Erase as usize], 0);
+        assert!(counts[Command::Read as usize] > 150, "{:?}", counts);
+        assert!(counts[Command::Write as usize] > 650, "{:?}", counts);
+
+        // ignored variants are still counted, so only Reset lies in range
+        for _ in 0..100 {
+            let request = Request::new_fuzzed(&mut mutator, None);
+            assert_eq!(request.command, Command::Reset);
+        }
+
+        let constraints = Constraints {
+            min: Some(2),
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            match Frame::new_fuzzed(&mut mutator, Some(&constraints)) {
+                Frame::Close(_) => {}
+                other => panic!("{:?} is outside of the constraints", other),
+            }
+        }
+
+        // ranges with no variants fall back to picking from all of them
+        let constraints = Constraints {
+            min: Some(10),
+            ..Default::default()
+        };
+        Command::new_fuzzed(&mut mutator, Some(&constraints));
+    }
+
+    #[test]
+    fn test_lain_attribute_namespace() {
+        #[derive(Debug, Copy, Clone, PartialEq, FuzzerObject)]
+        #[repr(u8)]
+        enum Opcode {
+            #[lain(weight(100%))]
+            Data = 1,
+            Ack = 2,
+        }
+
+        impl Default for Opcode {
+            fn default() -> Self {
+                Opcode::Data
+            }
+        }
+
+        #[derive(Debug, Default, Clone, FuzzerObject)]
+        #[lain(serialized_size = 8)]
+        struct Packet {
+            #[lain(bitfield(backing_type = "u8", bits = 4))]
+            version: u8,
+            #[lain(bitfield(backing_type = "u8", bits = 4))]
+            flags: u8,
+            #[lain(byteorder(little), min = 1, max = 4)]
+            length: u16,
+            opcode: Opcode,
+            #[lain(ignore)]
+            reserved: u8,
+            #[lain(initializer = "0x4142")]
+            checksum: u16,
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        for _ in 0..100 {
+            let packet = Packet::new_fuzzed(&mut mutator, None);
+
+            assert!(packet.version < 16 && packet.flags < 16);
+            assert!(packet.length >= 1 && packet.length < 4, "{}", packet.length);
+            assert_eq!(packet.opcode, Opcode::Data);
+            assert_eq!(packet.reserved, 0);
+            assert_eq!(packet.checksum, 0x4142);
+        }
+
+        let packet = Packet {
+            version: 0x4,
+            flags: 0x5,
+            length: 0x0102,
+            opcode: Opcode::Ack,
+            reserved: 0,
+            checksum: 0x4142,
+        };
+
+        // FuzzerObject also provides BinarySerialize and SerializedSize
+        let mut serialized = vec![];
+        packet
+            .binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+        compare_slices(&[0x54, 0x02, 0x01, 0x02, 0x00, 0x41, 0x42], &serialized);
+        assert_eq!(packet.serialized_size(), 8);
+    }
+
+    #[test]
+    fn test_byte_permutation() {
+        use lain::byte_permutation::Direction;
+
+        // rotates the bytes left when serializing, so it needs the direction to undo itself
+        fn rotate(bytes: &mut [u8], direction: Direction) {
+            match direction {
+                Direction::Serialize => bytes.rotate_left(1),
+                Direction::Deserialize => bytes.rotate_right(1),
+            }
+        }
+
+        #[derive(Debug, PartialEq, BinarySerialize, BinaryDeserialize)]
+        struct Header {
+            #[lain(
+                byteorder(big),
+                byte_permutation = "lain::byte_permutation::pdp_endian"
+            )]
+            timestamp: u32,
+            #[lain(byte_permutation = "rotate")]
+            checksum: u16,
+            length: u16,
+        }
+
+        let header = Header {
+            timestamp: 0x0A0B_0C0D,
+            checksum: 0x1122,
+            length: 0x3344,
+        };
+
+        let mut serialized = Vec::new();
+        header
+            .binary_serialize::<_, LittleEndian>(&mut serialized)
+            .unwrap();
+        assert_eq!(header.serialized_size(), 8);
+        assert_eq!(serialized, [0x0B, 0x0A, 0x0D, 0x0C, 0x11, 0x22, 0x44, 0x33]);
+
+        let deserialized =
+            Header::binary_deserialize::<_, LittleEndian>(&mut serialized.as_slice()).unwrap();
+        assert_eq!(deserialized, header);
+
+        // missing permuted fields are filled in like any other
+        let truncated =
+            Header::binary_deserialize_lenient::<_, LittleEndian>(&mut &serialized[..4]).unwrap();
+        assert_eq!(truncated.timestamp, 0x0A0B_0C0D);
+        assert_eq!(truncated.checksum, 0);
+    }
+
+    #[test]
+    fn test_address_types() {
+        use lain::types::{Ipv4Address, Ipv6Address, MacAddress, Uuid};
+        use std::collections::HashSet;
+
+        #[derive(Debug, PartialEq, NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize)]
+        struct Packet {
+            source: Ipv4Address,
+            destination: Ipv6Address,
+            hardware: MacAddress,
+            session: Uuid,
+        }
+
+        let packet = Packet {
+            source: Ipv4Address::new([10, 0, 0, 1]),
+            destination: "fe80::1".parse::<std::net::Ipv6Addr>().unwrap().into(),
+            hardware: MacAddress::new([0x02, 0x00, 0x5E, 0x10, 0x20, 0x30]),
+            session: Uuid::new([
+                0x12, 0x3E, 0x45, 0x67, 0xE8, 0x9B, 0x42, 0xD3, 0xA4, 0x56, 0x42, 0x66, 0x14, 0x17,
+                0x40, 0x00,
+            ]),
+        };
+
+        assert_eq!(packet.source.to_string(), "10.0.0.1");
+        assert_eq!(packet.destination.to_string(), "fe80::1");
+        assert_eq!(packet.hardware.to_string(), "02:00:5e:10:20:30");
+        assert_eq!(
+            packet.session.to_string(),
+            "123e4567-e89b-42d3-a456-426614174000"
+        );
+        assert_eq!(packet.session.version(), 4);
+
+        // addresses are always written in network order
+        let mut serialized = Vec::new();
+        packet
+            .binary_serialize::<_, LittleEndian>(&mut serialized)
+            .unwrap();
+        assert_eq!(packet.serialized_size(), 4 + 16 + 6 + 16);
+        assert_eq!(serialized[..4], [10, 0, 0, 1]);
+        assert_eq!(serialized[20..26], packet.hardware.octets());
+
+        let deserialized =
+            Packet::binary_deserialize::<_, LittleEndian>(&mut serialized.as_slice()).unwrap();
+        assert_eq!(deserialized, packet);
+
+        let mut mutator = get_mutator();
+        let mut private = false;
+        let mut link_local = false;
+        let mut multicast_mac = false;
+        let mut v4_uuid = false;
+        let mut seen = HashSet::new();
+
+        for _ in 0..1000 {
+            let generated = Packet::new_fuzzed(&mut mutator, None);
+            let source = std::net::Ipv4Addr::from(generated.source);
+            let destination = generated.destination.octets();
+
+            private |= source.is_private();
+            link_local |= destination[0] == 0xFE && destination[1] & 0xC0 == 0x80;
+            multicast_mac |=
+                generated.hardware.is_multicast() && generated.hardware != MacAddress::BROADCAST;
+            v4_uuid |=
+                generated.session.version() == 4 && generated.session.as_bytes()[8] >> 6 == 2;
+            seen.insert(generated.source);
+        }
+
+        assert!(private);
+        assert!(link_local);
+        assert!(multicast_mac);
+        assert!(v4_uuid);
+        assert!(seen.len() > 100);
+
+        let mut mutated = Packet::new_fuzzed(&mut mutator, None);
+        let original = mutated.source;
+        let mut changed = false;
+        for _ in 0..100 {
+            mutated.mutate(&mut mutator, None);
+            changed |= mutated.source != original;
+        }
+
+        assert!(changed);
+    }
+
+    #[test]
+    fn test_time_types() {
+        use lain::types::{DosDateTime, FileTime, UnixTimestamp32, UnixTimestamp64};
+
+        #[derive(Debug, PartialEq, NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize)]
+        struct Entry {
+            modified: DosDateTime,
+            created: FileTime,
+            accessed: UnixTimestamp32,
+            expires: UnixTimestamp64,
+        }
+
+        let entry = Entry {
+            modified: DosDateTime::new(2024, 2, 29, 13, 37, 42),
+            created: FileTime::from_unix(946_684_800),
+            accessed: UnixTimestamp32::new(i32::max_value()),
+            expires: UnixTimestamp64::new(253_402_300_799),
+        };
+
+        assert!(entry.modified.is_valid());
+        assert_eq!(entry.modified.to_string(), "2024-02-29 13:37:42");
+        assert_eq!(entry.created.to_unix(), 946_684_800);
+        assert_eq!(entry.created.to_string(), "2000-01-01T00:00:00.0000000Z");
+        assert_eq!(entry.accessed.to_string(), "2038-01-19T03:14:07Z");
+        assert_eq!(entry.expires.to_string(), "9999-12-31T23:59:59Z");
+        assert!(!DosDateTime::new(2023, 2, 29, 0, 0, 0).is_valid());
+        assert!(!DosDateTime::default().is_valid());
+
+        let mut serialized = Vec::new();
+        entry
+            .binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+        assert_eq!(entry.serialized_size(), 4 + 8 + 4 + 8);
+        // DOS time comes before the date, and FILETIME's low half before its high half
+        assert_eq!(serialized[..2], entry.modified.time().to_be_bytes());
+        assert_eq!(
+            serialized[4..8],
+            (entry.created.intervals() as u32).to_be_bytes()
+        );
+        assert_eq!(serialized[12..16], [0x7F, 0xFF, 0xFF, 0xFF]);
+
+        let deserialized =
+            Entry::binary_deserialize::<_, BigEndian>(&mut serialized.as_slice()).unwrap();
+        assert_eq!(deserialized, entry);
+
+        let mut mutator = get_mutator();
+        let mut y2k38 = false;
+        let mut valid_dos = 0;
+        let mut invalid_dos = 0;
+        let mut unix_epoch = false;
+
+        for _ in 0..1000 {
+            let generated = Entry::new_fuzzed(&mut mutator, None);
+            let accessed = i64::from(generated.accessed.seconds());
+
+            y2k38 |= (accessed - i64::from(i32::max_value())).abs() <= 86_400;
+            unix_epoch |= generated.created.to_unix().abs() <= 86_400;
+            if generated.modified.is_valid() {
+                valid_dos += 1;
+            } else {
+                invalid_dos += 1;
+            }
+        }
+
+        assert!(y2k38);
+        assert!(unix_epoch);
+        assert!(valid_dos > invalid_dos);
+        assert!(invalid_dos > 0);
+
+        let mut mutated = Entry::new_fuzzed(&mut mutator, None);
+        let original = mutated.expires;
+        let mut changed = false;
+        for _ in 0..100 {
+            mutated.mutate(&mut mutator, None);
+            changed |= mutated.expires != original;
+        }
+
+        assert!(changed);
+    }
+
+    #[test]
+    fn test_no_alloc_serialize() {
+        use lain::types::Ipv4Address;
+
+        #[derive(BinarySerialize)]
+        #[lain(no_alloc_serialize, serialized_size = 4)]
+        enum Option4 {
+            Address(Ipv4Address),
+            Port(u16),
+            Extended(u32, u8),
+        }
+
+        #[derive(BinarySerialize)]
+        #[lain(no_alloc_serialize)]
+        struct Packet {
+            #[lain(bitfield(backing_type = "u8", bits = 4))]
+            version: u8,
+            #[lain(bitfield(backing_type = "u8", bits = 4))]
+            flags: u8,
+            option: Option4,
+            payload: [u16; 2],
+        }
+
+        let packet = Packet {
+            version: 4,
+            flags: 0xF,
+            option: Option4::Port(0x1234),
+            payload: [0xAABB, 0xCCDD],
+        };
+
+        // unused bytes are left untouched
+        let mut buffer = [0x55u8; 16];
+        let len = packet
+            .binary_serialize_into::<BigEndian>(&mut buffer)
+            .unwrap();
+        assert_eq!(len, packet.serialized_size());
+        assert_eq!(
+            buffer[..len],
+            [0xF4, 0x12, 0x34, 0x00, 0x00, 0xAA, 0xBB, 0xCC, 0xDD]
+        );
+        assert_eq!(buffer[len], 0x55);
+
+        let error = packet
+            .binary_serialize_into::<BigEndian>(&mut buffer[..len - 1])
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+
+        let address = Option4::Address(Ipv4Address::new([10, 0, 0, 1]));
+        assert_eq!(
+            address
+                .binary_serialize_into::<LittleEndian>(&mut buffer)
+                .unwrap(),
+            4
+        );
+        assert_eq!(buffer[..4], [10, 0, 0, 1]);
+
+        // variants larger than the enum's serialized size fail instead of panicking
+        let error = Option4::Extended(0, 0)
+            .binary_serialize_into::<BigEndian>(&mut buffer)
+            .unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_category_strategy_mix() {
+        use lain::mutator::{FieldCategory, StrategyMix, StringStrategy};
+
+        #[derive(Debug, Mutatable)]
+        struct Request {
+            path: AsciiString,
+            #[lain(category = "collection")]
+            label: AsciiString,
+        }
+
+        let mut config = MutatorConfig::default();
+        config.set_strategy_mix(
+            FieldCategory::Integer,
+            StrategyMix::default().with_numeric(NumericStrategy::ScaleByTwo, 1),
+        );
+        config.set_strategy_mix(
+            FieldCategory::String,
+            StrategyMix::default().with_string(StringStrategy::InsertToken, 1),
+        );
+        config.set_strategy_mix(
+            FieldCategory::Collection,
+            StrategyMix::default()
+                .with_numeric(NumericStrategy::BitFlip, 1)
+                .with_string(StringStrategy::Repeat, 1)
+                .with_string(StringStrategy::FlipCase, 0),
+        );
+
+        let mut mutator = get_mutator();
+        mutator.set_config(config);
+
+        for _ in 0..50 {
+            let mut request = Request {
+                path: AsciiString::new("index"),
+                label: AsciiString::new("ab"),
+            };
+            request.mutate(&mut mutator, None);
+
+            // every string token contains a '%' or '.', while repeating "ab" never adds one
+            let path = format!("{:?}", request.path);
+            let label = format!("{:?}", request.label);
+            assert!(path.contains("'%'") || path.contains("'.'"), "{}", path);
+            assert!(
+                !label.contains("'%'") && !label.contains("'.'"),
+                "{}",
+                label
+            );
+            assert!(label.matches("AsciiChar").count() > 2, "{}", label);
+        }
+
+        // integers outside of a categorized field use the integer mix
+        for _ in 0..50 {
+            let mut value = 1000u32;
+            mutator.mutate(&mut value);
+            assert!(value == 2000 || value == 500);
+
+            let previous = mutator.begin_category_scope(FieldCategory::Collection);
+            let mut value = 1000u32;
+            mutator.mutate(&mut value);
+            mutator.end_category_scope(previous);
+            assert_eq!((value ^ 1000).count_ones(), 1);
+        }
+
+        assert_eq!(mutator.category(), None);
+    }
+
+    #[test]
+    fn test_string_growth_is_bounded() {
+        use lain::mutator::{FieldCategory, StrategyMix, StringStrategy};
+
+        let mut config = MutatorConfig {
+            max_string_len: 100,
+            ..Default::default()
+        };
+        // only strategies which grow the string
+        config.set_strategy_mix(
+            FieldCategory::String,
+            StrategyMix::default()
+                .with_string(StringStrategy::PercentEncode, 1)
+                .with_string(StringStrategy::InsertToken, 1)
+                .with_string(StringStrategy::Repeat, 1),
+        );
+
+        let mut mutator = get_mutator();
+        mutator.set_config(config);
+
+        let mut ascii = AsciiString::new("GET");
+        let mut utf8 = Utf8String::new("caf\u{e9}");
+        let mut longest = 0;
+        for _ in 0..1000 {
+            mutator.begin_new_iteration();
+            ascii.mutate(&mut mutator, None);
+            utf8.mutate(&mut mutator, None);
+
+            let ascii_len = String::from(ascii.clone()).chars().count();
+            let utf8_len = String::from(utf8.clone()).chars().count();
+            assert!(ascii_len <= 100, "{}", ascii_len);
+            assert!(utf8_len <= 100, "{}", utf8_len);
+            longest = std::cmp::max(longest, ascii_len);
+        }
+
+        // the strings did grow up to the bound
+        assert!(longest > 90, "{}", longest);
+    }
+
+    #[test]
+    fn test_field_wire_order() {
+        #[derive(Debug, Clone, PartialEq, BinarySerialize, BinaryDeserialize)]
+        #[lain(sort_by_order)]
+        struct Record {
+            #[lain(order = 4)]
+            payload: [u8; 2],
+            #[lain(order = 0)]
+            kind: u8,
+            #[lain(order = 3, byteorder(little))]
+            length: u16,
+            // bitfields are packed in wire order, so these share a byte
+            #[lain(order = 1, bitfield(backing_type = "u8", bits = 4))]
+            version: u8,
+            #[lain(order = 2, bitfield(backing_type = "u8", bits = 4))]
+            flags: u8,
+        }
+
+        let record = Record {
+            payload: [0xAA, 0xBB],
+            kind: 0x01,
+            length: 0x0203,
+            version: 0x4,
+            flags: 0x5,
+        };
+
+        let mut serialized = vec![];
+        record
+            .binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+        compare_slices(&[0x01, 0x54, 0x03, 0x02, 0xAA, 0xBB], &serialized);
+        assert_eq!(record.serialized_size(), serialized.len());
+
+        let deserialized =
+            Record::binary_deserialize::<_, BigEndian>(&mut serialized.as_slice()).unwrap();
+        assert_eq!(deserialized, record);
+    }
+
+    #[test]
+    fn test_protocol_version_gates() {
+        use lain::mutator::ProtocolVersion;
+
+        #[derive(Debug, PartialEq, NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize)]
+        struct Hello {
+            #[lain(protocol_version)]
+            version: u8,
+            session_id: u32,
+            #[lain(version_gate = "self.version >= 2")]
+            extensions: u16,
+            #[lain(version_gate = "self.version >= 3 && self.extensions != 0")]
+            cookie: [u8; 4],
+        }
+
+        let v1 = Hello {
+            version: 1,
+            session_id: 0x0102_0304,
+            extensions: 0xAAAA,
+            cookie: [0xBB; 4],
+        };
+
+        let mut serialized = vec![];
+        v1.binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+        compare_slices(&[0x01, 0x01, 0x02, 0x03, 0x04], &serialized);
+        assert_eq!(v1.serialized_size(), 5);
+        assert_eq!(Hello::min_nonzero_elements_size(), 5);
+
+        let deserialized =
+            Hello::binary_deserialize::<_, BigEndian>(&mut serialized.as_slice()).unwrap();
+        assert_eq!(deserialized.extensions, 0);
+        assert_eq!(deserialized.cookie, [0; 4]);
+
+        let v3 = Hello { version: 3, ..v1 };
+
+        let mut serialized = vec![];
+        v3.binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+        compare_slices(
+            &[
+                0x03, 0x01, 0x02, 0x03, 0x04, 0xAA, 0xAA, 0xBB, 0xBB, 0xBB, 0xBB,
+            ],
+            &serialized,
+        );
+        assert_eq!(v3.serialized_size(), serialized.len());
+
+        let deserialized =
+            Hello::binary_deserialize::<_, BigEndian>(&mut serialized.as_slice()).unwrap();
+        assert_eq!(deserialized, v3);
+
+        let mut mutator = get_mutator();
+        mutator.set_protocol_version(Some(ProtocolVersion(1)));
+
+        for _ in 0..50 {
+            let mut hello = Hello::new_fuzzed(&mut mutator, None);
+            assert_eq!(hello.version, 1);
+            assert_eq!(hello.extensions, 0);
+            assert_eq!(hello.cookie, [0; 4]);
+
+            hello.mutate(&mut mutator, None);
+            assert_eq!(hello.version, 1);
+            assert_eq!(hello.extensions, 0);
+            assert_eq!(hello.serialized_size(), 5);
+        }
+
+        mutator.set_protocol_version(Some(ProtocolVersion(2)));
+        let hello = Hello::new_fuzzed(&mut mutator, None);
+        assert_eq!(hello.version, 2);
+        assert_eq!(hello.cookie, [0; 4]);
+        assert_eq!(hello.serialized_size(), 7);
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_field_metadata() {
+        use lain::types::FieldLayout;
+
+        #[derive(Debug, BinarySerialize)]
+        #[lain(field_metadata)]
+        struct Header {
+            #[lain(byteorder(big))]
+            length: u16,
+            #[lain(bitfield(backing_type = "u8", bits = 4))]
+            version: u8,
+            #[bitfield(backing_type = "u8", bits = 4)]
+            flags: u8,
+        }
+
+        #[derive(Debug, BinarySerialize)]
+        #[lain(field_metadata)]
+        struct Packet {
+            header: Header,
+            #[lain(min = 1, max = 10)]
+            kind: u8,
+            payload: Vec<u8>,
+        }
+
+        let fields = Packet::field_info();
+        let names: Vec<&str> = fields.iter().map(|f| f.name).collect();
+        let types: Vec<&str> = fields.iter().map(|f| f.type_name.as_str()).collect();
+        assert_eq!(names, ["header", "kind", "payload"]);
+        assert_eq!(types, ["Header", "u8", "Vec<u8>"]);
+        assert_eq!(fields[1].attributes, ["min = 1", "max = 10"]);
+        assert_eq!(fields[1].attribute("max"), Some("10"));
+        assert_eq!(fields[2].attribute("min"), None);
+
+        let fields = Header::field_info();
+        assert_eq!(fields[0].attribute("byteorder"), Some("big"));
+        // the standalone spelling is reported like the namespaced one
+        assert_eq!(fields[1].attributes, fields[2].attributes);
+        assert_eq!(
+            fields[2].attribute("bitfield"),
+            Some("backing_type = \"u8\", bits = 4")
+        );
+
+        assert!(u32::field_info().is_empty());
+        assert!(0u32.field_layout().is_empty());
+
+        let packet = Packet {
+            header: Header {
+                length: 3,
+                version: 1,
+                flags: 2,
+            },
+            kind: 4,
+            payload: vec![5, 6, 7],
+        };
+
+        let layout: Vec<(String, usize, usize, Option<std::ops::Range<usize>>)> = packet
+            .field_layout()
+            .into_iter()
+            .map(|f: FieldLayout| (f.path, f.offset, f.size, f.bits))
+            .collect();
+        assert_eq!(
+            layout,
+            [
+                ("header".to_string(), 0, 3, None),
+                ("header.length".to_string(), 0, 2, None),
+                ("header.version".to_string(), 2, 1, Some(0..4)),
+                ("header.flags".to_string(), 2, 1, Some(4..8)),
+                ("kind".to_string(), 3, 1, None),
+                ("payload".to_string(), 4, 3, None),
+            ]
+        );
+
+        assert_eq!(packet.field_at_offset(1).unwrap().path, "header.length");
+        assert_eq!(packet.field_at_offset(3).unwrap().path, "kind");
+        assert_eq!(packet.field_at_offset(6).unwrap().type_name, "Vec<u8>");
+        assert_eq!(packet.field_at_offset(7), None);
+    }
+
+    #[test]
+    fn test_tuples() {
+        #[derive(
+            Debug,
+            Clone,
+            PartialEq,
+            NewFuzzed,
+            Mutatable,
+            BinarySerialize,
+            BinaryDeserialize,
+            StructuredDiff,
+        )]
+        struct Record {
+            kind: u8,
+            #[lain(byteorder(little))]
+            entry: (u16, Vec<u8>),
+        }
+
+        let record = Record {
+            kind: 1,
+            entry: (0x0203, vec![4, 5]),
+        };
+
+        assert_eq!(record.serialized_size(), 5);
+        assert_eq!(Record::min_nonzero_elements_size(), 4);
+        assert_eq!(<(u8, u32)>::min_nonzero_elements_size(), 5);
+
+        let mut buffer = vec![];
+        record
+            .binary_serialize::<_, BigEndian>(&mut buffer)
+            .unwrap();
+        compare_slices(&[1, 0x03, 0x02, 4, 5], &buffer);
+
+        let pair = <(u16, u8)>::binary_deserialize::<_, BigEndian>(&mut &buffer[..3]).unwrap();
+        assert_eq!(pair, (0x0103, 2));
+
+        // missing trailing elements are defaulted when deserializing leniently
+        let triple =
+            <(u8, u8, u16)>::binary_deserialize_lenient::<_, BigEndian>(&mut &buffer[..1]).unwrap();
+        assert_eq!(triple, (1, 0, 0));
+
+        let mut changed = record.clone();
+        changed.entry.0 = 7;
+        let deltas = record.diff(&changed);
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].path, "entry.0");
+
+        let mut mutator = get_mutator();
+        for _i in 0..100 {
+            let mut record = Record::new_fuzzed(&mut mutator, None);
+            record.mutate(&mut mutator, None);
+
+            let mut buffer = vec![];
+            record
+                .binary_serialize::<_, BigEndian>(&mut buffer)
+                .unwrap();
+            assert_eq!(buffer.len(), record.serialized_size());
+        }
+    }
+
+    #[test]
+    fn test_generation_observers() {
+        use lain::observer::{self, GeneratedField, Verdict};
+        use std::any::Any;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug, Default, Clone, NewFuzzed, BinarySerialize)]
+        struct Header {
+            magic: u32,
+            length: u8,
+        }
+
+        #[derive(Debug, NewFuzzed, BinarySerialize)]
+        struct Packet {
+            header: Header,
+            payload: Vec<u8>,
+        }
+
+        // headers always get the right magic and an even length
+        let header_observer = observer::register_type_observer::<Header, _>(
+            |field: &GeneratedField, value: &mut dyn Any| {
+                if field.path.ends_with("magic") {
+                    *value.downcast_mut::<u32>().unwrap() = 0x1234;
+                } else if let Some(length) = value.downcast_mut::<u8>() {
+                    if *length % 2 != 0 {
+                        return Verdict::Reject;
+                    }
+                }
+
+                Verdict::Accept
+            },
+        );
+
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let recorded_paths = paths.clone();
+        let global_observer =
+            observer::register_observer(move |field: &GeneratedField, _value: &mut dyn Any| {
+                if field.container.ends_with("Packet") || field.container.ends_with("Header") {
+                    recorded_paths.lock().unwrap().push(field.path.to_string());
+                }
+
+                Verdict::Accept
+            });
+
+        let mut mutator = get_mutator();
+        for _i in 0..100 {
+            let packet = Packet::new_fuzzed(&mut mutator, None);
+            assert_eq!(packet.header.magic, 0x1234);
+            assert_eq!(packet.header.length % 2, 0);
+        }
+
+        let paths = paths.lock().unwrap();
+        for path in ["header.magic", "header.length", "header", "payload"].iter() {
+            assert!(paths.iter().any(|p| p == path), "{} wasn't observed", path);
+        }
+
+        assert!(observer::unregister_observer(header_observer));
+        assert!(observer::unregister_observer(global_observer));
+        assert!(!observer::unregister_observer(header_observer));
+
+        let mut odd_lengths = 0;
+        for _i in 0..100 {
+            let header = Header::new_fuzzed(&mut mutator, None);
+            odd_lengths += header.length % 2;
+        }
+
+        assert!(odd_lengths > 0);
+    }
+
+    #[test]
+    fn test_inline_vecs() {
+        use lain::arrayvec::ArrayVec;
+        use lain::smallvec::SmallVec;
+
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize)]
+        struct Record {
+            kind: u8,
+            #[lain(min = 1, max = 3)]
+            ids: ArrayVec<u16, 3>,
+            payload: SmallVec<[u8; 8]>,
+        }
+
+        let record = Record {
+            kind: 1,
+            ids: [0x0203, 0x0405].iter().cloned().collect(),
+            payload: SmallVec::from_slice(&[6, 7]),
+        };
+
+        let mut buffer = vec![];
+        record
+            .binary_serialize::<_, BigEndian>(&mut buffer)
+            .unwrap();
+        compare_slices(&[1, 2, 3, 4, 5, 6, 7], &buffer);
+        assert_eq!(record.serialized_size(), buffer.len());
+
+        // serialized exactly like a Vec of the same elements
+        let mut vec_buffer = vec![];
+        vec![0x0203u16, 0x0405]
+            .binary_serialize::<_, BigEndian>(&mut vec_buffer)
+            .unwrap();
+        compare_slices(&buffer[1..5], &vec_buffer);
+
+        // ArrayVecs stop reading once they're full
+        let ids = ArrayVec::<u8, 3>::binary_deserialize::<_, BigEndian>(&mut &buffer[..]).unwrap();
+        assert_eq!(ids.as_slice(), [1, 2, 3]);
+        let payload =
+            SmallVec::<[u8; 2]>::binary_deserialize::<_, BigEndian>(&mut &buffer[..]).unwrap();
+        assert_eq!(payload.as_slice(), &buffer[..]);
+
+        let mut mutator = get_mutator();
+        let mut spilled = 0;
+        for _i in 0..1000 {
+            let mut record = Record::new_fuzzed(&mut mutator, None);
+            assert!(record.ids.len() <= 3);
+            if record.payload.spilled() {
+                spilled += 1;
+            }
+
+            record.mutate(&mut mutator, None);
+
+            let mut buffer = vec![];
+            record
+                .binary_serialize::<_, BigEndian>(&mut buffer)
+                .unwrap();
+            assert_eq!(buffer.len(), record.serialized_size());
+        }
+
+        // the inline capacity is a soft max
+        assert!(spilled > 0 && spilled < 500, "{} spilled", spilled);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sandbox() {
+        use lain::libc;
+        use lain::sandbox::{CrashKind, Sandbox, SandboxConfig, SandboxStatus, SeccompFilter};
+        use std::time::Duration;
+
+        let sandbox = Sandbox::new(SandboxConfig::default());
+        assert_eq!(
+            sandbox.run(|| Ok::<(), ()>(())).unwrap(),
+            SandboxStatus::Passed
+        );
+        assert_eq!(
+            sandbox.run(|| Err::<(), ()>(())).unwrap(),
+            SandboxStatus::Failed
+        );
+        assert_eq!(
+            sandbox
+                .run(|| -> Result<(), ()> { std::process::exit(3) })
+                .unwrap(),
+            SandboxStatus::Exited(3)
+        );
+
+        let status = sandbox
+            .run(|| -> Result<(), ()> {
+                unsafe { std::ptr::null_mut::<u8>().write_volatile(1) };
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(status, SandboxStatus::Crashed(CrashKind::SegmentationFault));
+        assert!(status.is_crash());
+
+        // changes made by the child aren't seen by the parent
+        let mut value = 1;
+        sandbox
+            .run(|| {
+                value = 2;
+                Ok::<(), ()>(())
+            })
+            .unwrap();
+        assert_eq!(value, 1);
+
+        let mut config = SandboxConfig::default();
+        config.timeout = Some(Duration::from_millis(50));
+        config.seccomp = Some(SeccompFilter::deny(&[libc::SYS_getppid]));
+        let sandbox = Sandbox::new(config);
+
+        assert_eq!(
+            sandbox.run(|| -> Result<(), ()> { loop {} }).unwrap(),
+            SandboxStatus::TimedOut
+        );
+        assert_eq!(
+            sandbox
+                .run(|| {
+                    unsafe { libc::getppid() };
+                    Ok::<(), ()>(())
+                })
+                .unwrap(),
+            SandboxStatus::Crashed(CrashKind::SeccompViolation)
+        );
+        assert_eq!(
+            sandbox.run(|| Ok::<(), ()>(())).unwrap(),
+            SandboxStatus::Passed
+        );
+
+        // the x32 ABI's number for a denied syscall doesn't get around the filter
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(
+            sandbox
+                .run(|| {
+                    unsafe { libc::syscall(libc::SYS_getppid | 0x4000_0000) };
+                    Ok::<(), ()>(())
+                })
+                .unwrap(),
+            SandboxStatus::Crashed(CrashKind::SeccompViolation)
+        );
+    }
+
+    #[test]
+    fn test_fixup_order() {
+        use std::cell::RefCell;
+
+        thread_local! {
+            static FIXED_UP: RefCell<Vec<&'static str>> = RefCell::new(vec![]);
+        }
+
+        #[derive(Debug, Default, Clone, NewFuzzed, BinarySerialize)]
+        struct Trailer {
+            checksum: u8,
+        }
+
+        impl Fixup for Trailer {
+            fn fixup<R: Rng>(&mut self, _mutator: &mut Mutator<R>) {
+                FIXED_UP.with(|f| f.borrow_mut().push("trailer"));
+            }
+        }
+
+        #[derive(Debug, Default, Clone, NewFuzzed, BinarySerialize)]
+        struct Body {
+            length: u8,
+            #[lain(min = 0, max = 16)]
+            payload: Vec<u8>,
+        }
+
+        impl Fixup for Body {
+            fn fixup<R: Rng>(&mut self, _mutator: &mut Mutator<R>) {
+                FIXED_UP.with(|f| f.borrow_mut().push("body"));
+                self.length = self.payload.len() as u8;
+            }
+        }
+
+        // the checksum covers the body's length, which is only correct once the body has been
+        // fixed up
+        #[derive(Debug, Clone, NewFuzzed, BinarySerialize, FixupChildren)]
+        struct Frame {
+            #[lain(fixup_order = 1)]
+            trailer: Trailer,
+            body: Body,
+        }
+
+        impl Fixup for Frame {
+            fn fixup<R: Rng>(&mut self, mutator: &mut Mutator<R>) {
+                self.trailer.checksum = !self.body.length;
+                self.fixup_children(mutator);
+            }
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let mut frame = Frame::new_fuzzed(&mut mutator, None);
+        FIXED_UP.with(|f| f.borrow_mut().clear());
+        frame.fixup_children(&mut mutator);
+        FIXED_UP.with(|f| assert_eq!(*f.borrow(), ["body", "trailer"]));
+
+        for _i in 0..100 {
+            let frame = Frame::new_fuzzed(&mut mutator, None);
+            assert_eq!(frame.body.length as usize, frame.body.payload.len());
+            assert_eq!(frame.trailer.checksum, !frame.body.length);
+        }
+    }
+
+    #[test]
+    fn test_option_result_cow() {
+        use std::borrow::Cow;
+
+        #[derive(
+            Debug, Clone, NewFuzzed, Mutatable, BinarySerialize, FuzzedDebug, StructuredDiff,
+        )]
+        struct Reply {
+            status: Result<u8, u16>,
+            session: Option<Option<u32>>,
+            #[lain(max = 8)]
+            body: Cow<'static, [u8]>,
+        }
+
+        let reply = Reply {
+            status: Err(0x0102),
+            session: Some(None),
+            body: Cow::Borrowed(&[3, 4]),
+        };
+
+        let mut buffer = vec![];
+        reply.binary_serialize::<_, BigEndian>(&mut buffer).unwrap();
+        compare_slices(&[1, 2, 3, 4], &buffer);
+        assert_eq!(reply.serialized_size(), 4);
+        assert_eq!(Reply::min_nonzero_elements_size(), 2);
+        assert!(reply.to_fuzzed_debug_string().contains("Err"));
+
+        let body = Cow::<[u8]>::binary_deserialize::<_, BigEndian>(&mut &buffer[2..]).unwrap();
+        assert_eq!(body, reply.body);
+
+        let mut changed = reply.clone();
+        changed.status = Ok(1);
+        changed.session = Some(Some(5));
+        let deltas = reply.diff(&changed);
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0].path, "status");
+        assert_eq!(deltas[1].path, "session");
+
+        // the variants are picked with the configured bias
+        let mut mutator = get_mutator();
+        let mut config = MutatorConfig::default();
+        config.err_chance = 0.0;
+        config.none_chance = 0.0;
+        mutator.set_config(config);
+
+        for _i in 0..100 {
+            let reply = Reply::new_fuzzed(&mut mutator, None);
+            assert!(reply.status.is_ok());
+            assert!(reply.session.unwrap().is_some());
+        }
+
+        let mut config = MutatorConfig::default();
+        config.err_chance = 100.0;
+        config.none_chance = 100.0;
+        mutator.set_config(config);
+
+        for _i in 0..100 {
+            let reply = Reply::new_fuzzed(&mut mutator, None);
+            assert!(reply.status.is_err());
+            assert!(reply.session.is_none());
+        }
+
+        mutator.set_config(MutatorConfig::default());
+        for _i in 0..100 {
+            let mut reply = Reply::new_fuzzed(&mut mutator, None);
+            reply.mutate(&mut mutator, None);
+
+            let mut buffer = vec![];
+            reply.binary_serialize::<_, BigEndian>(&mut buffer).unwrap();
+            assert_eq!(buffer.len(), reply.serialized_size());
+        }
+    }
+
+    #[test]
+    fn test_text_encoded_fields() {
+        fn text<T: BinarySerialize + SerializedSize>(value: &T) -> String {
+            let mut buffer = vec![];
+            value.binary_serialize::<_, BigEndian>(&mut buffer).unwrap();
+            assert_eq!(buffer.len(), value.serialized_size());
+
+            String::from_utf8(buffer).unwrap()
+        }
+
+        assert_eq!(text(&HexEncoded::new(0x12abu16)), "12ab");
+        assert_eq!(text(&Base64Encoded::new(b"foob".to_vec())), "Zm9vYg==");
+        assert_eq!(text(&Base64Encoded::new(b"fooba".to_vec())), "Zm9vYmE=");
+        assert_eq!(text(&Base64Encoded::new(b"foobar".to_vec())), "Zm9vYmFy");
+
+        // flaws are applied to the text
+        let flawed = |flaw| text(&Base64Encoded::with_flaw(b"foob".to_vec(), flaw));
+        assert_eq!(flawed(EncodingFlaw::BadPadding), "Zm9vYg");
+        assert_eq!(flawed(EncodingFlaw::Truncated), "Zm9vYg=");
+        assert_eq!(
+            flawed(EncodingFlaw::InvalidChar { offset: 9, c: b'!' }),
+            "Z!9vYg=="
+        );
+        assert_eq!(
+            text(&Base64Encoded::with_flaw(
+                b"foo".to_vec(),
+                EncodingFlaw::BadPadding
+            )),
+            "Zm9v="
+        );
+        assert_eq!(
+            text(&HexEncoded::with_flaw(0x12abu16, EncodingFlaw::BadPadding)),
+            "12ab0"
+        );
+
+        let decoded =
+            Base64Encoded::<u16>::binary_deserialize::<_, BigEndian>(&mut &b"Eqs="[..]).unwrap();
+        assert_eq!(decoded, Base64Encoded::new(0x12ab));
+        assert!(HexEncoded::<u16>::binary_deserialize::<_, BigEndian>(&mut &b"12a"[..]).is_err());
+        assert!(
+            Base64Encoded::<u16>::binary_deserialize::<_, BigEndian>(&mut &b"E=qs"[..]).is_err()
+        );
+
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Upload {
+            #[lain(max = 16)]
+            blob: Base64Encoded<Vec<u8>>,
+            id: HexEncoded<u32>,
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+        for _i in 0..100 {
+            let upload = Upload::new_fuzzed(&mut mutator, None);
+            assert!(upload.blob.flaw.is_none() && upload.id.flaw.is_none());
+
+            let encoded = text(&upload.blob);
+            let decoded = Base64Encoded::<Vec<u8>>::binary_deserialize::<_, BigEndian>(
+                &mut encoded.as_bytes(),
+            )
+            .unwrap();
+            assert_eq!(decoded.value, upload.blob.value);
+        }
+
+        mutator.set_profile(GenerationProfile::Hostile);
+        let mut flawed = 0;
+        for _i in 0..100 {
+            let mut upload = Upload::new_fuzzed(&mut mutator, None);
+            upload.mutate(&mut mutator, None);
+
+            let mut buffer = vec![];
+            upload
+                .binary_serialize::<_, BigEndian>(&mut buffer)
+                .unwrap();
+            assert_eq!(buffer.len(), upload.serialized_size());
+
+            if upload.blob.flaw.is_some() {
+                flawed += 1;
+            }
+        }
+
+        assert!(flawed > 0 && flawed < 100);
+    }
+
+    #[test]
+    fn test_unsafe_enum_inspection() {
+        #[derive(Debug, Copy, Clone, ToPrimitiveU8)]
+        #[repr(u8)]
+        enum Command {
+            Read = 1,
+            Write = 2,
+        }
+
+        let valid: UnsafeEnum<Command, u8> = UnsafeEnum::Valid(Command::Write);
+        assert!(valid.is_valid());
+        assert!(matches!(valid.as_valid(), Some(Command::Write)));
+        assert_eq!(valid.raw_value(), 2);
+        assert_eq!(format!("{:?}", valid), "Valid(Write = 2)");
+
+        let invalid: UnsafeEnum<Command, u8> = UnsafeEnum::Invalid(7);
+        assert!(!invalid.is_valid());
+        assert!(invalid.as_valid().is_none());
+        assert_eq!(invalid.raw_value(), 7);
+        assert_eq!(format!("{:?}", invalid), "Invalid(7)");
+
+        let read: UnsafeEnum<Command, u8> = UnsafeEnum::Valid(Command::Read);
+        assert_eq!(read.raw_value(), 1);
+
+        // without ToPrimitive the discriminant isn't known
+        let opaque: UnsafeEnum<bool, u8> = UnsafeEnum::Valid(true);
+        assert_eq!(format!("{:?}", opaque), "Valid(true)");
+    }
+
+    #[test]
+    fn test_ioctl_generation() {
+        use lain::ioctl::{Ioctl, IoctlCode};
+
+        #[derive(Debug, Copy, Clone, PartialEq, NewFuzzed, BinarySerialize, ToPrimitiveU32)]
+        #[repr(u32)]
+        enum Code {
+            GetVersion = 0x22_2000,
+            SetConfig = 0x22_2004,
+        }
+
+        #[derive(Debug, Default, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Config {
+            flags: u32,
+            timeout: u16,
+        }
+
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        enum Input {
+            Empty(()),
+            SetConfig(Config),
+        }
+
+        impl IoctlCode<Input> for Code {
+            fn new_input<R: Rng>(&self, mutator: &mut Mutator<R>) -> Input {
+                match *self {
+                    Code::GetVersion => Input::Empty(()),
+                    Code::SetConfig => Input::SetConfig(Config::new_fuzzed(mutator, None)),
+                }
+            }
+
+            fn output_len(&self) -> Option<u32> {
+                match *self {
+                    Code::GetVersion => Some(4),
+                    Code::SetConfig => None,
+                }
+            }
+        }
+
+        fn correlated(ioctl: &Ioctl<Code, Input>) -> bool {
+            match (ioctl.code.as_valid(), &ioctl.input) {
+                (Some(Code::GetVersion), Input::Empty(_)) => ioctl.output_len == 4,
+                (Some(Code::SetConfig), Input::SetConfig(_)) => true,
+                _ => false,
+            }
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let mut ioctl = Ioctl::<Code, Input>::new_fuzzed(&mut mutator, None);
+        for _ in 0..100 {
+            assert!(correlated(&ioctl), "{:?}", ioctl);
+
+            let mut buffer = vec![];
+            ioctl
+                .binary_serialize::<_, LittleEndian>(&mut buffer)
+                .unwrap();
+            assert_eq!(buffer.len(), ioctl.serialized_size());
+            assert_eq!(&buffer[..4], &ioctl.raw_code().to_le_bytes()[..]);
+            assert_eq!(&buffer[4..8], &ioctl.output_len.to_le_bytes()[..]);
+
+            ioctl.mutate(&mut mutator, None);
+            mutator.begin_new_iteration();
+        }
+
+        // other profiles occasionally try unknown codes and unexpected output sizes
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Hostile);
+
+        let uncorrelated = (0..1000)
+            .map(|_| Ioctl::<Code, Input>::new_fuzzed(&mut mutator, None))
+            .filter(|ioctl| !correlated(ioctl))
+            .count();
+        assert!(uncorrelated > 0);
+    }
+
+    #[test]
+    fn test_generate_once_fields() {
+        use std::cell::Cell;
+
+        thread_local! {
+            static GENERATED: Cell<usize> = Cell::new(0);
+        }
+
+        fn build_chain() -> Vec<u8> {
+            GENERATED.with(|g| g.set(g.get() + 1));
+            (0..0x1000).map(|i| i as u8).collect()
+        }
+
+        #[derive(Debug, Default, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Hello {
+            random: u64,
+            #[lain(generate_once, initializer = "build_chain()")]
+            certificates: Vec<u8>,
+        }
+
+        let mut mutator = get_mutator();
+
+        for _ in 0..100 {
+            let mut hello = Hello::new_fuzzed(&mut mutator, None);
+            hello.mutate(&mut mutator, None);
+            assert_eq!(hello.certificates, build_chain());
+            mutator.begin_new_iteration();
+        }
+
+        // one call per iteration's comparison, plus the first generation
+        assert_eq!(GENERATED.with(Cell::get), 101);
+
+        mutator.clear_static_sections();
+        Hello::new_fuzzed(&mut mutator, None);
+        assert_eq!(GENERATED.with(Cell::get), 102);
+    }
+
+    #[test]
+    fn test_variant_transitions() {
+        use lain::mutator::VariantTransitions;
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, NewFuzzed, Mutatable)]
+        enum State {
+            #[lain(transitions(Write, Close))]
+            Open,
+            #[lain(transitions(Write, Close))]
+            Write,
+            #[lain(transitions(Open))]
+            Close,
+        }
+
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable)]
+        enum Message {
+            #[lain(transitions(Data))]
+            Hello(u8),
+            Data(u32),
+            #[lain(transitions(Hello))]
+            Goodbye(u8),
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let mut seen = std::collections::HashSet::new();
+        let mut state = State::Open;
+        for _ in 0..1000 {
+            let previous = state;
+            state.mutate(&mut mutator, None);
+            seen.insert((previous, state));
+        }
+        assert!(!seen.contains(&(State::Close, State::Write)));
+        assert!(!seen.contains(&(State::Write, State::Open)));
+        assert!(seen.contains(&(State::Write, State::Close)));
+        assert!(seen.contains(&(State::Close, State::Open)));
+
+        // variants with data occasionally switch to an allowed variant
+        let mut switched = false;
+        for _ in 0..1000 {
+            let mut message = Message::Hello(0);
+            message.mutate(&mut mutator, None);
+            assert!(!matches!(message, Message::Goodbye(_)));
+            switched |= matches!(message, Message::Data(_));
+
+            let mut message = Message::Goodbye(0);
+            message.mutate(&mut mutator, None);
+            assert!(!matches!(message, Message::Data(_)));
+        }
+        assert!(switched);
+
+        // runtime transitions replace the declared ones
+        mutator
+            .set_variant_transitions::<State>(VariantTransitions::new().allow("Close", &["Write"]));
+        let mut seen_write = false;
+        for _ in 0..1000 {
+            let mut state = State::Close;
+            state.mutate(&mut mutator, None);
+            assert_ne!(state, State::Open);
+            seen_write |= state == State::Write;
+        }
+        assert!(seen_write);
+    }
+
+    #[test]
+    fn test_autodict() {
+        use lain::autodict::{AutoDictConfig, AutoDictionary};
+        use std::sync::Arc;
+
+        let dictionary = AutoDictionary::new(AutoDictConfig {
+            min_token_len: 4,
+            max_token_len: 16,
+            max_tokens: 4,
+        });
+
+        // runs which are too short or too long are skipped, and known tokens aren't learned again
+        let learned = dictionary.learn_from_response(
+            b"ERR expected \"X-Session:\" at 0x1f\r\nok aaaaaaaaaaaaaaaaaaaaaaaa expected",
+        );
+        assert_eq!(learned, 3);
+        assert_eq!(
+            dictionary.tokens(),
+            vec![
+                b"expected".to_vec(),
+                b"\"X-Session:\"".to_vec(),
+                b"0x1f".to_vec()
+            ]
+        );
+
+        // operands which compared equal teach nothing, and a full dictionary drops new tokens
+        assert_eq!(dictionary.record_comparison(b"\x7fELF", b"\x7fELF"), 0);
+        assert_eq!(dictionary.record_comparison(b"\x7fELF", b"MZ\x90\x00"), 1);
+        assert_eq!(dictionary.len(), 4);
+        assert_eq!(dictionary.tokens()[3], b"\x7fELF".to_vec());
+
+        let dictionary = Arc::new(AutoDictionary::new(AutoDictConfig::default()));
+        dictionary.record_comparison(b"X-Token", b"qqqqqqq");
+
+        let mut config = MutatorConfig::default();
+        config.set_strategy_mix(
+            FieldCategory::String,
+            StrategyMix::default().with_string(StringStrategy::InsertToken, 1),
+        );
+
+        let mut mutator = get_mutator();
+        mutator.set_config(config);
+        mutator.set_dictionary(Some(dictionary));
+
+        let mut learned = 0;
+        for _ in 0..100 {
+            let mut header = AsciiString::new("");
+            header.mutate(&mut mutator, None);
+
+            let header = String::from(header);
+            if header == "X-Token" || header == "qqqqqqq" {
+                learned += 1;
+            }
+        }
+        assert!(learned > 30 && learned < 70, "{}", learned);
+    }
+
+    #[test]
+    fn test_compare_operand_substitution() {
+        use lain::cmplog::{CompareTable, TABLE_SIZE};
+        use std::sync::Arc;
+
+        let table = Arc::new(CompareTable::new());
+        table.record_integers(4, 0, 0x464c_457f);
+        table.record_integers(2, 0xfffe, 7);
+        table.record_bytes(b"AAAA", b"RIFF");
+
+        // equal operands aren't captured, and only the most recent comparisons are kept
+        table.record_integers(4, 5, 5);
+        for i in 0..TABLE_SIZE as u64 {
+            table.record_integers(8, i, i + 1);
+        }
+        table.record_integers(8, 100, 200);
+        assert_eq!(table.integer_operands(4), vec![(0, 0x464c_457f)]);
+        assert_eq!(table.integer_operands(8).len(), TABLE_SIZE);
+        assert!(table.integer_operands(8).contains(&(100, 200)));
+        assert!(!table.integer_operands(8).contains(&(0, 1)));
+
+        let mut config = MutatorConfig::default();
+        config.numeric_strategies = vec![NumericStrategy::CompareOperand];
+
+        let mut mutator = get_mutator();
+        mutator.set_config(config);
+        mutator.set_compare_table(Some(table));
+
+        // numbers become the other side of the comparison they were part of
+        for _ in 0..100 {
+            let mut magic = 0u32;
+            mutator.mutate(&mut magic);
+            assert_eq!(magic, 0x464c_457f);
+
+            let mut kind = -2i16;
+            mutator.mutate(&mut kind);
+            assert_eq!(kind, 7);
+        }
+
+        let mut substituted = 0;
+        for _ in 0..200 {
+            let mut tag = b"xAAAAx".to_vec();
+            tag.mutate(&mut mutator, None);
+
+            if tag.windows(4).any(|w| w == b"RIFF") {
+                substituted += 1;
+            }
+        }
+        assert!(substituted > 5, "{}", substituted);
+    }
+
+    #[test]
+    fn test_corpus_splicing() {
+        use std::sync::{Arc, RwLock};
+
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Extension {
+            kind: u16,
+            data: Vec<u8>,
+        }
+
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Hello {
+            version: u16,
+            extension: Extension,
+            session: u64,
+        }
+
+        let original = Hello {
+            version: 0x0303,
+            extension: Extension {
+                kind: 1,
+                data: vec![1, 2, 3],
+            },
+            session: 0,
+        };
+        let donor = Hello {
+            version: 0x0304,
+            extension: Extension {
+                kind: 2,
+                data: vec![4, 5],
+            },
+            session: 0xdead_beef,
+        };
+
+        let mut corpus = Corpus::new();
+        corpus.push(donor.clone());
+
+        let mut mutator = get_mutator();
+        mutator.set_config(MutatorConfig {
+            splice_chance: 100.0,
+            ..MutatorConfig::default()
+        });
+        mutator.set_splice_corpus(Arc::new(RwLock::new(corpus)));
+
+        let mut spliced = [false; 3];
+        for _ in 0..100 {
+            let mut hello = original.clone();
+            hello.mutate(&mut mutator, None);
+
+            // exactly one field is taken from the donor and the rest are left as they were
+            let from_donor = [
+                hello.version == donor.version,
+                hello.extension == donor.extension,
+                hello.session == donor.session,
+            ];
+            let unchanged = [
+                hello.version == original.version,
+                hello.extension == original.extension,
+                hello.session == original.session,
+            ];
+            assert_eq!(from_donor.iter().filter(|&&f| f).count(), 1, "{:?}", hello);
+            assert_eq!(unchanged.iter().filter(|&&f| f).count(), 2, "{:?}", hello);
+
+            for (spliced, &from_donor) in spliced.iter_mut().zip(from_donor.iter()) {
+                *spliced |= from_donor;
+            }
+        }
+        assert_eq!(spliced, [true; 3]);
+    }
+
+    #[test]
+    fn test_fixup_toggle() {
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Chunk {
+            #[lain(ignore)]
+            len: u32,
+            #[lain(min = 1, max = 16)]
+            data: Vec<u8>,
+        }
+
+        impl Fixup for Chunk {
+            fn fixup<R: Rng>(&mut self, _mutator: &mut Mutator<R>) {
+                self.len = self.data.len() as u32;
+            }
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+        assert!(mutator.fixup_enabled());
+
+        let chunk = Chunk::new_fuzzed(&mut mutator, None);
+        assert_eq!(chunk.len as usize, chunk.data.len());
+
+        // the setting persists across iterations until fixups are enabled again
+        mutator.set_fixup_enabled(false);
+        for _ in 0..100 {
+            mutator.begin_new_iteration();
+            assert!(!mutator.should_fixup());
+
+            let chunk = Chunk::new_fuzzed(&mut mutator, None);
+            assert_eq!(chunk.len, 0);
+        }
+
+        mutator.set_fixup_enabled(true);
+        let chunk = Chunk::new_fuzzed(&mut mutator, None);
+        assert_eq!(chunk.len as usize, chunk.data.len());
+    }
+
+    #[test]
+    fn test_field_groups() {
+        #[derive(Debug, Clone, NewFuzzed, BinarySerialize)]
+        struct Probe {
+            mutations: u32,
+        }
+
+        impl Mutatable for Probe {
+            fn mutate<R: lain::rand::Rng>(
+                &mut self,
+                _mutator: &mut Mutator<R>,
+                _constraints: Option<&Constraints<u8>>,
+            ) {
+                self.mutations += 1;
+            }
+        }
+
+        #[derive(Debug, Clone, NewFuzzed, Mutatable)]
+        struct Rect {
+            #[lain(group = "geometry")]
+            width: Probe,
+            tag: Probe,
+            #[lain(group = "geometry")]
+            height: Probe,
+        }
+
+        let mut mutator = get_mutator();
+        let mut rect = Rect {
+            width: Probe { mutations: 0 },
+            tag: Probe { mutations: 0 },
+            height: Probe { mutations: 0 },
+        };
+
+        let iterations = 200;
+        let mut geometry_alone = 0;
+        for _ in 0..iterations {
+            let previous = rect.clone();
+            rect.mutate(&mut mutator, None);
+            mutator.begin_new_iteration();
+
+            // the width and height are always mutated together, and something is always mutated
+            assert_eq!(rect.width.mutations, rect.height.mutations);
+            let geometry = rect.width.mutations != previous.width.mutations;
+            let tag = rect.tag.mutations != previous.tag.mutations;
+            assert!(geometry || tag);
+
+            if geometry && !tag {
+                geometry_alone += 1;
+            }
+        }
+
+        assert!(rect.width.mutations < iterations);
+        assert!(rect.tag.mutations < iterations);
+        assert!(geometry_alone > 0);
+    }
+
+    #[test]
+    fn test_counted_size() {
+        use lain::buffer::CountingWriter;
+        use std::io::Write;
+
+        // 0xFF bytes are escaped by doubling them, which the hand-written size forgets
+        #[derive(Debug, Clone)]
+        struct Escaped(Vec<u8>);
+
+        impl BinarySerialize for Escaped {
+            fn binary_serialize<W: std::io::Write, E: lain::byteorder::ByteOrder>(
+                &self,
+                buffer: &mut W,
+            ) -> std::io::Result<usize> {
+                let mut bytes_written = 0;
+                for &b in self.0.iter() {
+                    bytes_written += b.binary_serialize::<_, E>(buffer)?;
+                    if b == 0xFF {
+                        bytes_written += b.binary_serialize::<_, E>(buffer)?;
+                    }
+                }
+
+                Ok(bytes_written)
+            }
+        }
+
+        impl SerializedSize for Escaped {
+            fn serialized_size(&self) -> usize {
+                self.0.len()
+            }
+
+            fn min_nonzero_elements_size() -> usize {
+                1
+            }
+        }
+
+        #[derive(Debug, Clone, BinarySerialize)]
+        #[lain(counted_size)]
+        struct Record {
+            tag: u8,
+            name: Escaped,
+            entries: Vec<u16>,
+        }
+
+        let mut writer = CountingWriter::new();
+        writer.write_all(&[0u8; 5]).unwrap();
+        writer.write_all(&[]).unwrap();
+        assert_eq!(writer.count(), 5);
+
+        let record = Record {
+            tag: 1,
+            name: Escaped(vec![0x41, 0xFF, 0x42, 0xFF]),
+            entries: vec![1, 2, 3],
+        };
+
+        let mut serialized = vec![];
+        record
+            .binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+
+        assert_eq!(serialized.len(), 1 + 6 + 6);
+        assert_eq!(record.serialized_size(), serialized.len());
+    }
+
+    #[test]
+    fn test_session_record_replay() {
+        use lain::net::{replay, NetConfig, ResponseStatus, Sender, SessionRecorder, TcpSender};
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        // echoes every message of a single connection, prefixed with its position
+        fn echo_server() -> (std::net::SocketAddr, std::thread::JoinHandle<()>) {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            let server = std::thread::spawn(move || {
+                let mut stream = listener.incoming().next().unwrap().unwrap();
+                let mut buffer = [0u8; 16];
+                for i in 0u8.. {
+                    match stream.read(&mut buffer).unwrap() {
+                        0 => break,
+                        size => {
+                            let mut reply = vec![i];
+                            reply.extend_from_slice(&buffer[..size]);
+                            stream.write_all(&reply).unwrap();
+                        }
+                    }
+                }
+            });
+
+            (addr, server)
+        }
+
+        let (addr, server) = echo_server();
+        let mut recorder =
+            SessionRecorder::new(TcpSender::new(addr, NetConfig::default()).unwrap());
+
+        let mut response = b"stale".to_vec();
+        for message in [&b"HELLO"[..], &b"DATA 1"[..], &b"BYE"[..]].iter() {
+            recorder.send(message, &mut response).unwrap();
+        }
+
+        let session = recorder.take_session();
+        assert!(recorder.session().is_empty());
+        drop(recorder);
+        server.join().unwrap();
+
+        assert_eq!(session.len(), 3);
+        assert_eq!(session.exchanges[1].sent, b"DATA 1");
+        assert_eq!(session.exchanges[1].response, b"\x01DATA 1");
+        assert_eq!(session.exchanges[2].status, Some(ResponseStatus::Received));
+
+        let path = std::env::temp_dir().join(format!("lain_session_{}", std::process::id()));
+        session.save(&path).unwrap();
+
+        let (addr, server) = echo_server();
+        let mut sender = TcpSender::new(addr, NetConfig::default()).unwrap();
+        let replayed = replay(&path, &mut sender).unwrap();
+        drop(sender);
+        server.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(replayed, session);
+    }
+
+    #[test]
+    fn test_differential_fuzzing() {
+        use lain::corpus::ContentHash;
+        use lain::driver::{start_differential_fuzzer, FuzzerDriver};
+        use std::sync::Arc;
+
+        fn parse_signed(input: &[u8]) -> i32 {
+            i32::from(input[0] as i8)
+        }
+
+        fn parse_unsigned(input: &[u8]) -> i32 {
+            i32::from(input[0])
+        }
+
+        let dir = std::env::temp_dir().join(format!("lain_divergences_{}", std::process::id()));
+
+        let mut driver = FuzzerDriver::<()>::new(1);
+        driver.set_seed(0x1234);
+        driver.set_divergence_dir(&dir);
+
+        let driver = Arc::new(driver);
+        start_differential_fuzzer::<u8, BigEndian, _, _, _, _, _>(
+            driver.clone(),
+            parse_signed,
+            parse_unsigned,
+            |a, b| a == b,
+        );
+
+        let one_milli = std::time::Duration::from_millis(1);
+        while driver.num_iterations() < 200 {
+            std::thread::sleep(one_milli);
+        }
+
+        driver.signal_exit();
+        driver.join_threads();
+
+        let divergent = driver.divergent_inputs();
+        assert!(!divergent.is_empty());
+
+        for input in divergent.entries() {
+            assert!(input[0] >= 0x80);
+
+            let path = dir.join(format!("divergence-{}", ContentHash::of(input)));
+            assert_eq!(&std::fs::read(&path).unwrap(), input);
+        }
+
+        // inputs the implementations agreed on are neither kept nor written
+        assert!(std::fs::read_dir(&dir).unwrap().count() <= divergent.len());
+        assert!(driver.stats().snapshot().failed_iterations >= divergent.len());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fuzzed_iter() {
+        use lain::stream::fuzzed_iter;
+
+        let values: Vec<NestedStruct> = fuzzed_iter(0x1234).take(50).collect();
+        let again: Vec<NestedStruct> = fuzzed_iter(0x1234).take(50).collect();
+        assert_eq!(format!("{:?}", values), format!("{:?}", again));
+
+        let mut iter = fuzzed_iter::<u64>(0x1234);
+        let skipped = iter.nth(20).unwrap();
+        assert_eq!(iter.index(), 21);
+        assert_eq!(Some(skipped), fuzzed_iter::<u64>(0x1234).take(21).last());
+
+        let distinct: std::collections::HashSet<u64> = fuzzed_iter(0x1234).take(50).collect();
+        assert!(distinct.len() > 1);
+    }
+
+    #[test]
+    fn test_danger_score() {
+        #[derive(Debug, Copy, Clone, NewFuzzed)]
+        enum Kind {
+            First,
+            Second,
+        }
+
+        #[derive(Debug, NewFuzzed)]
+        struct Scored {
+            #[lain(min = 10, max = 20)]
+            bounded: u32,
+            unbounded: u64,
+            kind: UnsafeEnum<Kind, u8>,
+        }
+
+        let mut mutator = get_mutator();
+
+        mutator.set_profile(GenerationProfile::Valid);
+        for _ in 0..100 {
+            mutator.begin_new_iteration();
+            let _ = Scored::new_fuzzed(&mut mutator, None);
+            assert!(mutator.danger_score().is_clean());
+        }
+
+        mutator.set_profile(GenerationProfile::Hostile);
+        let mut totals = DangerScore::default();
+        for _ in 0..200 {
+            mutator.begin_new_iteration();
+            let value = Scored::new_fuzzed(&mut mutator, None);
+            let score = mutator.danger_score();
+
+            if score.constraint_violations == 0 && score.extreme_values == 0 {
+                assert!(value.bounded >= 10 && value.bounded <= 20);
+            }
+
+            totals.constraint_violations += score.constraint_violations;
+            totals.extreme_values += score.extreme_values;
+            totals.invalid_enums += score.invalid_enums;
+        }
+
+        assert!(totals.constraint_violations > 0);
+        assert!(totals.extreme_values > 0);
+        assert!(totals.invalid_enums > 0);
+
+        mutator.begin_new_iteration();
+        assert_eq!(mutator.danger_score(), DangerScore::default());
+    }
+
+    #[test]
+    fn test_packed_layout() {
+        #[derive(Debug, Clone, Copy, NewFuzzed, BinarySerialize)]
+        #[repr(C, packed)]
+        #[lain(packed_layout, field_metadata)]
+        struct Inner {
+            kind: u16,
+            delta: i8,
+        }
+
+        #[derive(Debug, Clone, Copy, NewFuzzed, BinarySerialize)]
+        #[repr(C, packed)]
+        #[lain(packed_layout, memcpy_serialize, field_metadata)]
+        struct Header {
+            magic: u32,
+            flags: u8,
+            length: u16,
+            tag: [u8; 3],
+            inner: Inner,
+            valid: bool,
+        }
+
+        fn expected<E: lain::byteorder::ByteOrder>(header: &Header) -> Vec<u8> {
+            let mut buffer = vec![];
+            { header.magic }.binary_serialize::<_, E>(&mut buffer).unwrap();
+            { header.flags }.binary_serialize::<_, E>(&mut buffer).unwrap();
+            { header.length }.binary_serialize::<_, E>(&mut buffer).unwrap();
+            { header.tag }.binary_serialize::<_, E>(&mut buffer).unwrap();
+            { header.inner.kind }.binary_serialize::<_, E>(&mut buffer).unwrap();
+            { header.inner.delta }.binary_serialize::<_, E>(&mut buffer).unwrap();
+            { header.valid }.binary_serialize::<_, E>(&mut buffer).unwrap();
+            buffer
+        }
+
+        let mut mutator = get_mutator();
+        for _ in 0..50 {
+            let header = Header::new_fuzzed(&mut mutator, None);
+            assert_eq!(header.serialized_size(), std::mem::size_of::<Header>());
+
+            for &big_endian in &[true, false] {
+                let mut buffer = vec![];
+                let (written, expected) = if big_endian {
+                    let written = header.binary_serialize::<_, BigEndian>(&mut buffer).unwrap();
+                    (written, expected::<BigEndian>(&header))
+                } else {
+                    let written = header.binary_serialize::<_, LittleEndian>(&mut buffer).unwrap();
+                    (written, expected::<LittleEndian>(&header))
+                };
+
+                assert_eq!(written, 14);
+                assert_eq!(buffer, expected);
+            }
+        }
+
+        let header = Header::new_fuzzed(&mut mutator, None);
+        let layout = header.field_layout();
+        let inner_kind = layout.iter().find(|f| f.path == "inner.kind").unwrap();
+        assert_eq!((inner_kind.offset, inner_kind.size), (10, 2));
+
+        fn is_plain_old_data<T: lain::traits::PlainOldData>() {}
+        is_plain_old_data::<Header>();
+    }
+
+    #[test]
+    fn test_fixed_length_string() {
+        let mut mutator = get_mutator();
+
+        let mut buffer = vec![];
+        FixedLengthString::<8>::new("lain")
+            .binary_serialize::<_, BigEndian>(&mut buffer)
+            .unwrap();
+        assert_eq!(buffer, b"lain\0\0\0\0");
+
+        let name = FixedLengthString::<4, b' '>::new("fuzzer");
+        assert!(name.is_truncated());
+        assert_eq!(name.serialized_size(), 4);
+        assert_eq!(name.serialized_contents(), b"fuzz");
+
+        buffer.clear();
+        FixedLengthString::<6, b' '>::new("ab")
+            .binary_serialize::<_, BigEndian>(&mut buffer)
+            .unwrap();
+        assert_eq!(buffer, b"ab    ");
+        let parsed = FixedLengthString::<6, b' '>::binary_deserialize::<_, BigEndian>(
+            &mut buffer.as_slice(),
+        )
+        .unwrap();
+        assert_eq!(parsed, FixedLengthString::new("ab"));
+
+        let lengths = Constraints {
+            min: Some(2),
+            max: Some(64),
+            bounds: Bounds::Closed,
+            ..Default::default()
+        };
+        let mut exact_fits = 0;
+        for _i in 0..200 {
+            let label = FixedLengthString::<12>::new_fuzzed(&mut mutator, Some(&lengths));
+            assert!(label.len() >= 2 && label.len() <= 12);
+            exact_fits += label.is_exact_fit() as usize;
+
+            let mut buffer = vec![];
+            assert_eq!(
+                label.binary_serialize::<_, BigEndian>(&mut buffer).unwrap(),
+                12
+            );
+            assert_eq!(buffer.len(), 12);
+        }
+        assert!(exact_fits > 0);
+
+        let mut label = FixedLengthString::<12>::default();
+        let mut overflowed = false;
+        for _i in 0..200 {
+            label.mutate(&mut mutator, None);
+            assert!(label.len() <= 13);
+            overflowed |= label.is_truncated();
+
+            let mut buffer = vec![];
+            label.binary_serialize::<_, BigEndian>(&mut buffer).unwrap();
+            assert_eq!(buffer.len(), 12);
+        }
+        assert!(overflowed);
+    }
+
+    #[test]
+    fn test_mutate_options() {
+        #[derive(Default, Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Record {
+            length: u32,
+            first: u32,
+            second: u32,
+            third: u32,
+        }
+
+        impl Fixup for Record {
+            fn fixup<R: Rng>(&mut self, _mutator: &mut Mutator<R>) {
+                self.length = 12;
+            }
+        }
+
+        fn changed_fields(a: &Record, b: &Record) -> usize {
+            (a.first != b.first) as usize
+                + (a.second != b.second) as usize
+                + (a.third != b.third) as usize
+        }
+
+        let mut mutator = get_mutator();
+        let original = Record::default();
+
+        for _i in 0..100 {
+            let mut record = original.clone();
+            let options = MutateOptions::default()
+                .with_max_fields(1)
+                .with_fixup(false);
+            mutator.mutate_with_options(&mut record, options);
+            assert!(changed_fields(&original, &record) <= 1);
+            mutator.begin_new_iteration();
+
+            let mut record = original.clone();
+            mutator.mutate_with_options(&mut record, MutateOptions::default().with_fixup(true));
+            assert_eq!(record.length, 12);
+            mutator.begin_new_iteration();
+
+            let mut record = original.clone();
+            let options = MutateOptions::default()
+                .with_early_bail(true)
+                .with_fixup(true);
+            mutator.mutate_with_options(&mut record, options);
+            assert!(changed_fields(&original, &record) <= 1);
+            assert_eq!(record.length, 12);
+            mutator.begin_new_iteration();
+        }
+
+        // the options only apply to the call they were passed to
+        assert_eq!(mutator.mutate_options(), MutateOptions::default());
+    }
+
+    #[test]
+    fn test_one_of() {
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Request {
+            session: OneOf<u32>,
+            file_id: OneOf<u16>,
+        }
+
+        let mut mutator = get_mutator();
+        mutator.set_candidates(vec![0x1000u32, 0x2000, 0x3000]);
+
+        let mut picked = [false; 3];
+        for _i in 0..100 {
+            let request = Request::new_fuzzed(&mut mutator, None);
+            assert!(request.session.is_candidate());
+            picked[*request.session.value() as usize / 0x1000 - 1] = true;
+
+            // without candidates the value is generated as usual
+            assert!(request.file_id.candidates().is_empty());
+
+            let mut buffer = vec![];
+            request
+                .binary_serialize::<_, BigEndian>(&mut buffer)
+                .unwrap();
+            assert_eq!(&buffer[..4], &request.session.value().to_be_bytes());
+            assert_eq!(buffer.len(), 6);
+        }
+        assert_eq!(picked, [true; 3]);
+
+        let session = OneOf::new(vec![0x1000u32, 0x2000]);
+        assert_eq!(*session.value(), 0x1000);
+
+        let mut perturbed = 0;
+        for _i in 0..500 {
+            let mut mutated = session.clone();
+            mutated.mutate(&mut mutator, None);
+            perturbed += !mutated.is_candidate() as usize;
+        }
+        assert!(perturbed > 0 && perturbed < 250);
+
+        let mut buffer = vec![];
+        session
+            .binary_serialize::<_, BigEndian>(&mut buffer)
+            .unwrap();
+        let parsed = OneOf::<u32>::binary_deserialize::<_, BigEndian>(&mut buffer.as_slice());
+        assert_eq!(parsed.unwrap(), session);
+    }
+
+    #[test]
+    fn test_max_bytes_changed() {
+        #[derive(Debug, Clone, NewFuzzed, Mutatable, BinarySerialize)]
+        struct Packet {
+            header: u32,
+            flags: u16,
+            payload: Vec<u8>,
+            trailer: Option<u64>,
+        }
+
+        fn changed_bytes(mutator: &mut Mutator<SmallRng>, packet: &Packet) -> usize {
+            let mut before = vec![];
+            packet
+                .binary_serialize::<_, BigEndian>(&mut before)
+                .unwrap();
+
+            let mut mutated = packet.clone();
+            mutator.begin_new_iteration();
+            mutated.mutate(mutator, None);
+
+            let mut after = vec![];
+            mutated
+                .binary_serialize::<_, BigEndian>(&mut after)
+                .unwrap();
+            assert_eq!(before.len(), after.len());
+
+            before
+                .iter()
+                .zip(after.iter())
+                .filter(|(a, b)| a != b)
+                .count()
+        }
+
+        let packet = Packet {
+            header: 0,
+            flags: 0,
+            payload: vec![0; 32],
+            trailer: Some(0),
+        };
+
+        let mut mutator = get_mutator();
+        let mut config = MutatorConfig::default();
+        config.max_bytes_changed = Some(2);
+        mutator.set_config(config);
+
+        let mut changed_any = false;
+        for _i in 0..200 {
+            let changed = changed_bytes(&mut mutator, &packet);
+            assert!(changed <= 2, "{} bytes changed", changed);
+            assert!(mutator.bytes_changed() <= 2);
+            changed_any |= changed > 0;
+        }
+        assert!(changed_any);
+
+        // without a budget every field is free to change
+        mutator.set_config(MutatorConfig::default());
+        let mut changed_many = false;
+        for _i in 0..200 {
+            let mut mutated = packet.clone();
+            mutator.begin_new_iteration();
+            mutated.mutate(&mut mutator, None);
+            changed_many |= mutator.bytes_changed() > 2;
+        }
+        assert!(changed_many);
+    }
+
+    #[test]
+    fn test_model_coverage() {
+        use lain::model_coverage::{ModelCoverage, ValueBucket};
+        use std::sync::Arc;
+
+        #[derive(Debug, Copy, Clone, NewFuzzed, BinarySerialize)]
+        #[repr(u8)]
+        enum Command {
+            Read,
+            Write,
+            #[lain(weight(0))]
+            Erase,
+        }
+
+        #[derive(Debug, Clone, NewFuzzed, BinarySerialize)]
+        struct Request {
+            command: Command,
+            length: Option<u32>,
+            #[lain(min = 1, max = 10)]
+            count: u16,
+        }
+
+        let coverage = Arc::new(ModelCoverage::new());
+        let mut mutator = get_mutator();
+        mutator.set_model_coverage(Some(coverage.clone()));
+
+        for _i in 0..1000 {
+            Request::new_fuzzed(&mut mutator, None);
+        }
+
+        let report = coverage.report();
+
+        assert_eq!(report.variants.len(), 1);
+        let variants = &report.variants[0];
+        assert!(variants.enum_name.ends_with("Command"));
+        assert_eq!(variants.missing(), vec!["Erase"]);
+        let generated: usize = variants.counts.iter().map(|(_, count)| count).sum();
+        assert_eq!(generated, 1000);
+
+        assert_eq!(report.options.len(), 1);
+        assert!(report.options[0].missing().is_empty());
+        assert_eq!(report.options[0].some + report.options[0].none, 1000);
+
+        // the count stays close to its bounds, so it never reaches the top of a u16's range
+        let count = report
+            .values
+            .iter()
+            .find(|coverage| coverage.path == "count")
+            .unwrap();
+        assert!(count.missing().contains(&ValueBucket::Max));
+        assert!(!count.missing().contains(&ValueBucket::Small));
+
+        let gaps = report.gaps();
+        assert!(gaps[0].ends_with("Command::Erase"));
+        assert!(gaps.iter().any(|gap| gap.ends_with("count (Max)")));
+        assert!(report.to_string().contains("never generated:"));
+
+        coverage.clear();
+        assert!(coverage.report().variants.is_empty());
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        use lain::corpus::CorpusStore;
+        use std::error::Error as _;
+        use std::io;
+
+        #[derive(Debug, Clone)]
+        struct Unserializable;
+
+        impl BinarySerialize for Unserializable {
+            fn binary_serialize<W: std::io::Write, E: lain::byteorder::ByteOrder>(
+                &self,
+                _buffer: &mut W,
+            ) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "no wire format"))
+            }
+        }
+
+        let constraints = Constraints {
+            min: Some(1u8),
+            max: Some(10),
+            ..Default::default()
+        };
+        assert!(constraints.check(&5).is_ok());
+        let error = constraints.check(&10).unwrap_err();
+        match error {
+            lain::Error::ConstraintViolation { ref value, .. } => assert_eq!(value, "10"),
+            ref other => panic!("unexpected error: {}", other),
+        }
+        assert!(error.to_string().starts_with("10 violates"));
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::InvalidData);
+
+        let mut corpus = Corpus::new();
+        corpus.push(Unserializable);
+        corpus.push(Unserializable);
+        let error = corpus.try_dedup::<BigEndian>().unwrap_err();
+        assert!(matches!(error, lain::Error::Serialize(_)));
+        assert_eq!(error.source().unwrap().to_string(), "no wire format");
+        assert_eq!(corpus.len(), 2);
+
+        let mut corpus = Corpus::new();
+        for value in &[1u32, 2, 1] {
+            corpus.push(*value);
+        }
+        assert_eq!(corpus.try_dedup::<BigEndian>().unwrap(), 1);
+
+        let dir = std::env::temp_dir().join(format!("lain_typed_errors_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut store = CorpusStore::open(&dir).unwrap();
+        store.insert(&[0, 0, 0, 1]).unwrap();
+        store.insert(&[0, 2]).unwrap();
+
+        // the lenient loader skips the truncated entry, the strict one reports it
+        assert_eq!(store.load::<u32, BigEndian>().unwrap().len(), 1);
+        match store.load_strict::<u32, BigEndian>() {
+            Err(lain::Error::Deserialize { type_name, .. }) => assert_eq!(type_name, "u32"),
+            other => panic!("unexpected result: {:?}", other.map(|corpus| corpus.len())),
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_mixed_unit_and_data_variants() {
+        #[derive(Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize)]
+        enum Command {
+            Nop,
+            Read(u32),
+            Reset,
+            Write(u8, u16),
+        }
+
+        let mut mutator = get_mutator();
+        let mut generated = [0; 4];
+        for _i in 0..200 {
+            let command = Command::new_fuzzed(&mut mutator, None);
+            let mut buffer = vec![];
+            command
+                .binary_serialize::<_, BigEndian>(&mut buffer)
+                .unwrap();
+
+            // unit variants carry no payload
+            let (index, len) = match command {
+                Command::Nop => (0, 0),
+                Command::Read(_) => (1, 4),
+                Command::Reset => (2, 0),
+                Command::Write(..) => (3, 3),
+            };
+            generated[index] += 1;
+            assert_eq!(buffer.len(), len);
+            assert_eq!(command.serialized_size(), len);
+        }
+        assert!(generated.iter().all(|&count| count > 0));
+
+        // variants with data keep their variant and mutate their fields, while unit variants
+        // have nothing to mutate and are replaced
+        let mut kept_variant = 0;
+        for _i in 0..100 {
+            let mut command = Command::Read(0x4141_4141);
+            command.mutate(&mut mutator, None);
+            kept_variant += matches!(command, Command::Read(_)) as usize;
+        }
+        assert_eq!(kept_variant, 100);
+
+        let mut switched = 0;
+        for _i in 0..100 {
+            let mut command = Command::Nop;
+            command.mutate(&mut mutator, None);
+            switched += (command != Command::Nop) as usize;
+        }
+        assert!(switched > 50);
+    }
+
+    #[test]
+    fn driver_follows_reuse_yield() {
+        use lain::driver::{FuzzerContext, FuzzerDriver};
+        use lain::feedback::{Feedback, Observation};
+        use lain::reuse::{InputSource, ReusePolicy, ReuseScheduler};
+        use std::sync::{Arc, RwLock};
+
+        // only mutated corpus entries ever make the target do something new
+        struct CorpusIsInteresting;
+
+        impl FeedbackProvider for CorpusIsInteresting {
+            fn evaluate(&self, _input: &[u8], observation: &Observation) -> Feedback {
+                if observation.response == [1] {
+                    Feedback::Interesting
+                } else {
+                    Feedback::Boring
+                }
+            }
+        }
+
+        fn fuzzer_routine<R: lain::rand::Rng>(
+            mutator: &mut Mutator<R>,
+            ctx: &mut FuzzerContext<()>,
+            global_ctx: Option<Arc<RwLock<Corpus<u32>>>>,
+        ) -> Result<(), ()> {
+            let corpus = global_ctx.unwrap();
+            let value = ctx.reuse_or_generate(mutator, &corpus.read().unwrap());
+            ctx.serialize::<_, BigEndian>(&value);
+            ctx.response
+                .push((ctx.input_source() == InputSource::Corpus) as u8);
+
+            Ok(())
+        }
+
+        let mut corpus = Corpus::new();
+        corpus.push(0x4141_4141u32);
+
+        let mut driver = FuzzerDriver::new(2);
+        driver.set_global_context(Arc::new(RwLock::new(corpus)));
+        driver.set_feedback_provider(CorpusIsInteresting);
+        driver.set_reuse_policy(ReusePolicy {
+            adjust_interval: 100,
+            ..Default::default()
+        });
+
+        let driver = Arc::new(driver);
+        lain::driver::start_fuzzer(driver.clone(), fuzzer_routine);
+
+        while driver.num_iterations() < 2000 {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+
+        driver.signal_exit();
+        driver.join_threads();
+
+        // the percentage hovers just under the max since the last window may have few fresh
+        // iterations to judge their yield by
+        let scheduler = driver.reuse_scheduler().unwrap();
+        assert!(scheduler.reuse_percentage() > 80.0);
+        assert!(scheduler.reuse_percentage() <= 90.0);
+
+        // a fixed policy never moves, whatever the yields
+        let scheduler = ReuseScheduler::new(ReusePolicy::fixed(100.0));
+        let mut mutator = get_mutator();
+        for _i in 0..10 {
+            assert_eq!(scheduler.pick(&mut mutator.rng), InputSource::Corpus);
+            scheduler.record(InputSource::Fresh, true);
+        }
+        assert_eq!(scheduler.reuse_percentage(), 100.0);
+    }
+
+    #[test]
+    fn test_offset_mutations() {
+        #[derive(
+            Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize,
+        )]
+        struct SectionHeader {
+            #[lain(min = 0x20, max = 0x200)]
+            data_offset: Offset<u32>,
+            string_table: Offset<u16>,
+        }
+
+        let header = SectionHeader {
+            data_offset: Offset::new(0x40),
+            string_table: Offset::new(0x1ff),
+        };
+
+        let mut serialized = vec![];
+        header
+            .binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+        compare_slices(&[0x00, 0x00, 0x00, 0x40, 0x01, 0xff], &serialized);
+
+        let deserialized =
+            SectionHeader::binary_deserialize::<_, BigEndian>(&mut serialized.as_slice()).unwrap();
+        assert_eq!(deserialized, header);
+
+        let mut mutator = get_mutator();
+        mutator.set_input_len(Some(0x300));
+
+        let mut nudged = false;
+        let mut aligned = false;
+        let mut past_max = false;
+        let mut past_input = false;
+
+        for _ in 0..1000 {
+            let mut header = SectionHeader {
+                data_offset: Offset::new(0x43),
+                string_table: Offset::new(0x123),
+            };
+            header.mutate(&mut mutator, None);
+
+            let data_offset = header.data_offset.value();
+            let string_table = header.string_table.value();
+            nudged |= data_offset != 0x43 && (0x33..=0x53).contains(&data_offset);
+            aligned |= [0x40, 0x44, 0x48, 0x50].contains(&data_offset);
+            past_max |= (0x200..0x204).contains(&data_offset);
+            past_input |= (0x300..0x304).contains(&string_table);
+
+            mutator.begin_new_iteration();
+        }
+
+        assert!(nudged);
+        assert!(aligned);
+        assert!(past_max);
+        assert!(past_input);
+    }
+
+    #[test]
+    fn test_target_config() {
+        use lain::target::{Endianness, PointerWidth, TargetConfig};
+
+        #[derive(
+            Debug, Clone, PartialEq, NewFuzzed, Mutatable, BinarySerialize, BinaryDeserialize,
+        )]
+        struct Request {
+            tag: u16,
+            address: TargetUsize,
+            delta: TargetIsize,
+            length: PointerAligned<U24>,
+        }
+
+        let request = Request {
+            tag: 0x0102,
+            address: TargetUsize::new(0x1000),
+            delta: TargetIsize::new(-2),
+            length: PointerAligned(U24::new(0x010203)),
+        };
+
+        let target_32 = TargetConfig {
+            endianness: Endianness::Big,
+            pointer_width: PointerWidth::Bits32,
+        };
+        let target_64 = TargetConfig {
+            endianness: Endianness::Little,
+            pointer_width: PointerWidth::Bits64,
+        };
+
+        // pointer-sized values follow the target's byte order rather than the input's
+        target_32.make_current();
+        let mut serialized = vec![];
+        request
+            .binary_serialize::<_, LittleEndian>(&mut serialized)
+            .unwrap();
+        compare_slices(
+            &[
+                0x02, 0x01, 0x00, 0x00, 0x10, 0x00, 0xFF, 0xFF, 0xFF, 0xFE, 0x03, 0x02, 0x01, 0x00,
+            ],
+            &serialized,
+        );
+        assert_eq!(request.serialized_size(), serialized.len());
+        assert_eq!(
+            Request::binary_deserialize::<_, LittleEndian>(&mut serialized.as_slice()).unwrap(),
+            request
+        );
+
+        target_64.make_current();
+        let mut serialized = vec![];
+        request
+            .binary_serialize::<_, BigEndian>(&mut serialized)
+            .unwrap();
+        compare_slices(
+            &[
+                0x01, 0x02, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFE, 0xFF, 0xFF, 0xFF,
+                0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x02, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            &serialized,
+        );
+        assert_eq!(request.serialized_size(), serialized.len());
+        assert_eq!(
+            Request::binary_deserialize::<_, BigEndian>(&mut serialized.as_slice()).unwrap(),
+            request
+        );
+
+        // values are generated and mutated within a 32-bit target's width
+        target_32.make_current();
+        let mut mutator = get_mutator();
+        for _ in 0..100 {
+            let mut request = Request::new_fuzzed(&mut mutator, None);
+            request.mutate(&mut mutator, None);
+            assert!(request.address.value() <= u64::from(u32::max_value()));
+            assert!(request.delta.value() >= i64::from(i32::min_value()));
+            assert!(request.delta.value() <= i64::from(i32::max_value()));
+        }
+
+        TargetConfig::host().make_current();
+    }
+
+    #[test]
+    fn test_zero_sized_types() {
+        use std::marker::PhantomData;
+
+        #[derive(
+            Debug,
+            Default,
+            Clone,
+            PartialEq,
+            NewFuzzed,
+            Mutatable,
+            BinarySerialize,
+            BinaryDeserialize,
+            FuzzedDebug,
+            StructuredDiff,
+        )]
+        struct Marker;
+
+        #[derive(Debug, Default, Clone, PartialEq)]
+        struct Open;
+
+        #[derive(
+            Debug,
+            Clone,
+            PartialEq,
+            NewFuzzed,
+            Mutatable,
+            BinarySerialize,
+            BinaryDeserialize,
+            FuzzedDebug,
+            StructuredDiff,
+        )]
+        struct Handle<S: 'static> {
+            id: u16,
+            marker: Marker,
+            state: PhantomData<S>,
+            reserved: (),
+            flags: u8,
+        }
+
+        assert_eq!(Marker.serialized_size(), 0);
+        assert_eq!(Marker::min_nonzero_elements_size(), 0);
+        assert_eq!(Handle::<Open>::min_nonzero_elements_size(), 3);
+
+        let handle = Handle::<Open> {
+            id: 0x0102,
+            marker: Marker,
+            state: PhantomData,
+            reserved: (),
+            flags: 3,
+        };
+
+        let mut buffer = vec![];
+        handle
+            .binary_serialize::<_, BigEndian>(&mut buffer)
+            .unwrap();
+        compare_slices(&[1, 2, 3], &buffer);
+        assert_eq!(handle.serialized_size(), 3);
+
+        let deserialized =
+            Handle::<Open>::binary_deserialize::<_, BigEndian>(&mut &buffer[..]).unwrap();
+        assert_eq!(deserialized, handle);
+        assert!(handle.to_fuzzed_debug_string().contains("state"));
+
+        let mut mutator = get_mutator();
+        for _i in 0..100 {
+            let mut handle = Handle::<Open>::new_fuzzed(&mut mutator, None);
+            handle.mutate(&mut mutator, None);
+
+            let mut buffer = vec![];
+            handle
+                .binary_serialize::<_, BigEndian>(&mut buffer)
+                .unwrap();
+            assert_eq!(buffer.len(), 3);
+            assert_eq!(handle.serialized_size(), 3);
+        }
+    }
+
+    #[test]
+    fn test_volatile_fields() {
+        #[derive(Debug, Default, Clone, PartialEq, NewFuzzed, BinarySerialize)]
+        struct Header {
+            #[lain(volatile)]
+            sequence: u32,
+            kind: u8,
+        }
+
+        #[derive(Debug, Default, Clone, PartialEq, NewFuzzed, BinarySerialize)]
+        struct Packet {
+            header: Header,
+            #[lain(volatile, min = 1, max = 4)]
+            nonce: u8,
+            payload: u16,
+            checksum: u8,
+        }
+
+        impl Fixup for Packet {
+            fn fixup<R: Rng>(&mut self, _mutator: &mut Mutator<R>) {
+                self.checksum = self.header.sequence as u8 ^ self.nonce;
+            }
+        }
+
+        #[derive(Debug, Default, Clone, PartialEq, NewFuzzed)]
+        struct Static {
+            kind: u8,
+        }
+
+        #[derive(Debug, Default, Clone, PartialEq, NewFuzzed)]
+        #[lain(refresh)]
+        struct Session {
+            id: u8,
+            header: Header,
+        }
+
+        assert!(Header::has_volatile_fields());
+        assert!(Packet::has_volatile_fields());
+        assert!(!Static::has_volatile_fields());
+        assert!(Session::has_volatile_fields());
+
+        let mut mutator = get_mutator();
+        mutator.set_profile(GenerationProfile::Valid);
+
+        let entry = Packet::new_fuzzed(&mut mutator, None);
+
+        let mut sequences = std::collections::HashSet::new();
+        for _i in 0..100 {
+            let mut replayed = entry.clone();
+            replayed.refresh(&mut mutator);
+
+            // only the volatile fields change, and the packet is fixed up to match them
+            assert_eq!(replayed.header.kind, entry.header.kind);
+            assert_eq!(replayed.payload, entry.payload);
+            assert!(replayed.nonce >= 1 && replayed.nonce < 4);
+            assert_eq!(
+                replayed.checksum,
+                replayed.header.sequence as u8 ^ replayed.nonce
+            );
+
+            sequences.insert(replayed.header.sequence);
+        }
+
+        assert!(sequences.len() > 50);
+
+        let mut unchanged = Static { kind: 7 };
+        unchanged.refresh(&mut mutator);
+        assert_eq!(unchanged.kind, 7);
+
+        // structs without volatile fields of their own refresh the ones nested in them
+        let session = Session::new_fuzzed(&mut mutator, None);
+        let mut refreshed = session.clone();
+        while refreshed.header.sequence == session.header.sequence {
+            refreshed.refresh(&mut mutator);
+        }
+        assert_eq!(refreshed.id, session.id);
+        assert_eq!(refreshed.header.kind, session.header.kind);
     }
 
     fn compare_slices(expected: &[u8], actual: &[u8]) {
@@ -0,0 +1,13 @@
+// Enums whose variants are all ignored
+
+use lain::prelude::*;
+
+#[derive(Clone, Copy, NewFuzzed)]
+enum Opcode {
+    #[fuzzer(ignore = true)]
+    Load,
+    #[fuzzer(ignore = true)]
+    Store,
+}
+
+fn main() {}
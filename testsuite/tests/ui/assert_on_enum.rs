@@ -0,0 +1,12 @@
+// Invariants on enums, which have no fields for `self` to refer to
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+#[lain(assert = "true")]
+enum Kind {
+    Data,
+    Ack,
+}
+
+fn main() {}
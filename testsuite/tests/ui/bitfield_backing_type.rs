@@ -0,0 +1,11 @@
+// Unsupported bitfield backing types
+
+use lain::prelude::*;
+
+#[derive(BinarySerialize)]
+struct Flags {
+    #[bitfield(backing_type = "u128", bits = 4)]
+    kind: u8,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// Byte permutations on bitfields
+
+use lain::prelude::*;
+
+#[derive(BinarySerialize)]
+struct Flags {
+    #[lain(bitfield(backing_type = "u16", bits = 16), byte_permutation = "lain::byte_permutation::reverse")]
+    kind: u16,
+}
+
+fn main() {}
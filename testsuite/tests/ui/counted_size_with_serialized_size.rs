@@ -0,0 +1,11 @@
+// Counted sizes on a type which also has a fixed size
+
+use lain::prelude::*;
+
+#[derive(BinarySerialize)]
+#[lain(counted_size, serialized_size = 16)]
+struct Record {
+    tag: u8,
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+// The standalone attributes still work, but warn in favor of their `#[lain(...)]` spelling
+
+#![deny(deprecated)]
+
+use lain::prelude::*;
+
+#[derive(Clone, Copy, NewFuzzed, BinarySerialize)]
+#[serialized_size(4)]
+struct Header {
+    #[fuzzer(min = 1, max = 10)]
+    length: u8,
+    #[bitfield(backing_type = "u8", bits = 4)]
+    version: u8,
+    #[bitfield(backing_type = "u8", bits = 4)]
+    flags: u8,
+    #[byteorder(little)]
+    checksum: u16,
+}
+
+#[derive(Clone, Copy, NewFuzzed)]
+enum Opcode {
+    #[weight(3)]
+    Load,
+    Store,
+}
+
+fn main() {}
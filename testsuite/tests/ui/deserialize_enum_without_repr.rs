@@ -0,0 +1,11 @@
+// Enums without an integer `#[repr]` can't be deserialized
+
+use lain::prelude::*;
+
+#[derive(BinaryDeserialize)]
+enum Kind {
+    Data,
+    Ack,
+}
+
+fn main() {}
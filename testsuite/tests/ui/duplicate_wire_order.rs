@@ -0,0 +1,14 @@
+// Fields sharing a wire position
+
+use lain::prelude::*;
+
+#[derive(BinarySerialize)]
+#[lain(sort_by_order)]
+struct Record {
+    #[lain(order = 0)]
+    kind: u8,
+    #[lain(order = 0)]
+    flags: u8,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+// Enum variants with named fields
+
+use lain::prelude::*;
+
+#[derive(Mutatable)]
+enum Message {
+    Ping { id: u8 },
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// Exhaustively swept floats
+
+use lain::prelude::*;
+
+#[derive(Enumerable)]
+struct Sample {
+    #[fuzzer(exhaustive, min = 0.0, max = 1.0)]
+    value: f32,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// Flattened fields which aren't structs
+
+use lain::prelude::*;
+
+#[derive(StructuredDiff)]
+struct Message {
+    #[lain(flatten)]
+    length: u32,
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+// Flattened fields with their own byte order
+
+use lain::prelude::*;
+
+#[derive(BinarySerialize)]
+struct Header {
+    length: u32,
+}
+
+#[derive(BinarySerialize)]
+struct Message {
+    #[lain(flatten, byteorder(big))]
+    header: Header,
+}
+
+fn main() {}
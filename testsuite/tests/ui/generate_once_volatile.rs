@@ -0,0 +1,11 @@
+// Generate-once sections which are also regenerated as volatile fields
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+struct Hello {
+    #[lain(generate_once, volatile)]
+    certificates: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// `#[lain]` field attributes which aren't valid meta items
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+struct Sample {
+    #[lain(min = -1, max = 1)]
+    value: i8,
+}
+
+fn main() {}
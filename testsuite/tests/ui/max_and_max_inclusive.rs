@@ -0,0 +1,11 @@
+// Both an exclusive and an inclusive max on the same field
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+struct Frame {
+    #[lain(min = 1, max = 4, max_inclusive = 3)]
+    channel: u8,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// Count fields which don't exist
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+struct Table {
+    #[lain(count_field = "num_entries")]
+    entries: Vec<u32>,
+}
+
+fn main() {}
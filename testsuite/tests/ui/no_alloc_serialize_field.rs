@@ -0,0 +1,16 @@
+// Allocation-free serialization of fields which don't guarantee it
+
+use lain::prelude::*;
+
+#[derive(BinarySerialize)]
+struct Header {
+    length: u16,
+}
+
+#[derive(BinarySerialize)]
+#[lain(no_alloc_serialize)]
+struct Packet {
+    header: Header,
+}
+
+fn main() {}
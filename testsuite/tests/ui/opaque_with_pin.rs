@@ -0,0 +1,11 @@
+// Opaque fields with pinned regions
+
+use lain::prelude::*;
+
+#[derive(Mutatable)]
+struct Message {
+    #[lain(opaque, pin(0..4))]
+    signature: Vec<u8>,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// Packed layouts with a field which isn't serialized as its in-memory bytes
+
+use lain::prelude::*;
+
+#[derive(Clone, Copy, BinarySerialize)]
+#[repr(C, packed)]
+#[lain(packed_layout)]
+struct Header {
+    magic: u32,
+    name: &'static str,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+// Packed layouts on a struct which isn't `#[repr(C, packed)]`
+
+use lain::prelude::*;
+
+#[derive(Clone, Copy, BinarySerialize)]
+#[lain(packed_layout)]
+struct Header {
+    magic: u32,
+    flags: u8,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+// Tuple structs
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+struct Header(u8, u32);
+
+fn main() {}
@@ -0,0 +1,11 @@
+// Unions
+
+use lain::prelude::*;
+
+#[derive(StructuredDiff)]
+union Value {
+    byte: u8,
+    word: u32,
+}
+
+fn main() {}
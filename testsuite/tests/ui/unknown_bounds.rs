@@ -0,0 +1,11 @@
+// Unknown `bounds` values
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+struct Sample {
+    #[fuzzer(min = 0.0, max = 1.0, bounds = "half_open")]
+    value: f32,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+// Unknown byte orders
+
+use lain::prelude::*;
+
+#[derive(BinarySerialize)]
+struct Header {
+    #[byteorder(middle)]
+    length: u32,
+}
+
+fn main() {}
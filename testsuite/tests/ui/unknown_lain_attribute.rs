@@ -0,0 +1,11 @@
+// Unknown `#[lain]` attributes
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+#[lain(seed = "session")]
+struct Session {
+    id: u64,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+// Transitions into a variant the enum doesn't have
+
+use lain::prelude::*;
+
+#[derive(Clone, Copy, NewFuzzed, Mutatable)]
+enum State {
+    #[lain(transitions(Closed))]
+    Open,
+    Close,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+// Version gates on bitfields, which share their backing value with other fields
+
+use lain::prelude::*;
+
+#[derive(BinarySerialize)]
+struct Flags {
+    version: u8,
+    #[lain(bitfield(backing_type = "u8", bits = 4), version_gate = "self.version >= 2")]
+    kind: u8,
+    #[lain(bitfield(backing_type = "u8", bits = 4))]
+    flags: u8,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// Percentages mixed with relative weights
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+enum Opcode {
+    #[weight(60%)]
+    Load,
+    #[weight(3)]
+    Store,
+}
+
+fn main() {}
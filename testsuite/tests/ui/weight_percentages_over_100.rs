@@ -0,0 +1,13 @@
+// Percentage weights adding up to more than 100%
+
+use lain::prelude::*;
+
+#[derive(NewFuzzed)]
+enum Opcode {
+    #[weight(60%)]
+    Load,
+    #[weight(50%)]
+    Store,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+// Percentage weights adding up to more than 100%, given as constant expressions
+
+use lain::prelude::*;
+
+const PERCENT: u64 = 60;
+
+#[derive(Clone, Copy, NewFuzzed)]
+enum Opcode {
+    #[lain(weight(PERCENT%))]
+    Load,
+    #[lain(weight(PERCENT%))]
+    Store,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+// Enums whose variants all have a weight of 0
+
+use lain::prelude::*;
+
+#[derive(Clone, Copy, NewFuzzed)]
+enum Opcode {
+    #[weight(0)]
+    Load,
+    #[weight(0)]
+    Store,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+// Enums whose variants all have a weight of 0, given as constant expressions
+
+use lain::prelude::*;
+
+const WEIGHT: u64 = 0;
+
+#[derive(Clone, Copy, NewFuzzed)]
+enum Opcode {
+    #[lain(weight(WEIGHT))]
+    Load,
+    #[lain(weight(WEIGHT * 2))]
+    Store,
+}
+
+fn main() {}